@@ -3,5 +3,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 参考: https://docs.rs/tonic-build/latest/tonic_build/
     tonic_prost_build::configure().compile_protos(&["proto/file_service.proto"], &["proto"])?;
 
+    // 构建信息（/api/version）：git 提交与构建时间
+    let git_commit = std::process::Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let build_timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=SILENT_NAS_GIT_COMMIT={}", git_commit);
+    println!(
+        "cargo:rustc-env=SILENT_NAS_BUILD_TIMESTAMP={}",
+        build_timestamp
+    );
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+
     Ok(())
 }
@@ -0,0 +1,101 @@
+//! 时钟抽象
+//!
+//! 锁定到期、回收站保留期、生命周期过期等与时间相关的逻辑通过 [`Clock`] 获取当前时间，
+//! 运行时使用 [`SystemClock`]，测试中使用 [`MockClock`] 手动推进时间，无需真实等待。
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime};
+use std::sync::{Arc, Mutex};
+
+/// 时钟
+pub trait Clock: Send + Sync {
+    /// 当前时间
+    fn now(&self) -> DateTime<Local>;
+
+    /// 当前时间（本地时间，不含时区）
+    fn now_naive(&self) -> NaiveDateTime {
+        self.now().naive_local()
+    }
+}
+
+/// 共享时钟
+pub type SharedClock = Arc<dyn Clock>;
+
+/// 系统时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// 共享的系统时钟实例
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// 可手动控制的时钟
+///
+/// 时间只在调用 [`MockClock::advance`] 或 [`MockClock::set`] 时变化。
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Local>>,
+}
+
+impl MockClock {
+    /// 从指定时间开始的时钟
+    pub fn new(start: DateTime<Local>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    /// 向前推进时间
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += duration;
+    }
+
+    /// 设置当前时间
+    pub fn set(&self, time: DateTime<Local>) {
+        *self.now.lock().unwrap() = time;
+    }
+}
+
+impl Default for MockClock {
+    /// 从当前系统时间开始
+    fn default() -> Self {
+        Self::new(Local::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Local> {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_advance_and_set() {
+        let start = Local::now();
+        let clock = MockClock::new(start);
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::minutes(30));
+        assert_eq!(clock.now(), start + Duration::minutes(30));
+        assert_eq!(
+            clock.now_naive(),
+            (start + Duration::minutes(30)).naive_local()
+        );
+
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+}
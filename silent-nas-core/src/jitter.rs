@@ -0,0 +1,66 @@
+//! 周期任务间隔抖动
+//!
+//! 多个节点以相同的固定间隔执行后台任务（GC、索引提交、巡检补拉等）时，
+//! 各节点的执行时刻会对齐并同时产生负载尖峰。每次等待前把间隔按一定比例随机伸缩，
+//! 使负载在时间上分散开。
+
+use std::time::Duration;
+
+/// 默认抖动比例（间隔的 ±10%）
+pub const DEFAULT_JITTER: f64 = 0.1;
+
+/// 在 `interval` 上叠加 ±`fraction` 比例的随机抖动
+///
+/// 结果均匀分布在 `[interval × (1 - fraction), interval × (1 + fraction)]` 内，
+/// `fraction` 截断到 `[0, 1]`，为 0 时原样返回。
+pub fn jittered(interval: Duration, fraction: f64) -> Duration {
+    let fraction = if fraction.is_finite() {
+        fraction.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    if fraction == 0.0 || interval.is_zero() {
+        return interval;
+    }
+    interval.mul_f64(1.0 - fraction + 2.0 * fraction * random_unit())
+}
+
+/// `[0, 1)` 内的随机数
+///
+/// 取自标准库随机种子的哈希器，每次调用的种子不同，无需额外依赖。
+fn random_unit() -> f64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jittered_interval_varies_within_band() {
+        let base = Duration::from_secs(30);
+        let samples: Vec<Duration> = (0..200).map(|_| jittered(base, 0.2)).collect();
+
+        let low = base.mul_f64(0.8);
+        let high = base.mul_f64(1.2);
+        assert!(samples.iter().all(|d| *d >= low && *d <= high));
+
+        // 各次等待时长不同，且覆盖抖动区间的两侧
+        let distinct: std::collections::HashSet<_> = samples.iter().collect();
+        assert!(distinct.len() > 150);
+        assert!(samples.iter().any(|d| *d < base.mul_f64(0.9)));
+        assert!(samples.iter().any(|d| *d > base.mul_f64(1.1)));
+
+        // 比例为 0 时不抖动，超出范围的比例被截断
+        assert_eq!(jittered(base, 0.0), base);
+        assert_eq!(jittered(base, f64::NAN), base);
+        assert!((0..50).all(|_| jittered(base, 5.0) <= base * 2));
+    }
+}
@@ -5,9 +5,15 @@
 //! - 文件事件模型
 //! - 文件版本模型
 //! - 存储管理器 trait
+//! - 时钟抽象
+//! - 周期任务间隔抖动
 
+mod clock;
+mod jitter;
 mod models;
 mod storage;
 
+pub use clock::*;
+pub use jitter::*;
 pub use models::*;
 pub use storage::*;
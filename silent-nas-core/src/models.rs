@@ -1,5 +1,6 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// 文件元数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +19,9 @@ pub struct FileMetadata {
     pub created_at: NaiveDateTime,
     /// 修改时间（本地时间）
     pub modified_at: NaiveDateTime,
+    /// 用户自定义元数据（如 S3 `x-amz-meta-*`，键为小写）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub user_metadata: HashMap<String, String>,
 }
 
 /// 文件事件类型
@@ -138,6 +142,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: chrono::Local::now().naive_local(),
             modified_at: chrono::Local::now().naive_local(),
+            user_metadata: Default::default(),
         }
     }
 
@@ -0,0 +1,70 @@
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use sha2::{Digest, Sha256};
+use silent_storage::bloom::ChunkBloomFilter;
+use silent_storage::services::WeakHashDedupIndex;
+
+/// 生成模拟块的 (弱哈希, 强哈希) 列表
+fn generate_chunks(count: usize, seed: u64) -> Vec<(u32, String)> {
+    (0..count as u64)
+        .map(|i| {
+            let x = (i + seed).wrapping_mul(6364136223846793005).wrapping_add(1);
+            let weak_hash = ((x >> 32) as u32).max(1);
+            let strong_hash = hex::encode(Sha256::digest(x.to_le_bytes()));
+            (weak_hash, strong_hash)
+        })
+        .collect()
+}
+
+/// 基准测试：弱哈希预过滤 vs 仅 Bloom Filter 的去重查询
+///
+/// 分别测量命中（块已存在）与未命中（新块）两类查询。
+fn bench_dedup_lookup(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("dedup_lookup");
+
+    for count in [1_000usize, 10_000, 100_000] {
+        let stored = generate_chunks(count, 0);
+        let hits: Vec<(u32, String)> = stored.iter().step_by(count / 1_000).cloned().collect();
+        let misses = generate_chunks(1_000, 1 << 40);
+
+        let index = WeakHashDedupIndex::new();
+        rt.block_on(index.rebuild(stored.clone()));
+        let bloom = ChunkBloomFilter::new(count, 0.01);
+        rt.block_on(bloom.rebuild(stored.iter().map(|(_, s)| s.clone()).collect()));
+
+        for (kind, probes) in [("hit", &hits), ("miss", &misses)] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("bloom_only/{}", kind), count),
+                probes,
+                |b, probes| {
+                    b.iter(|| {
+                        rt.block_on(async {
+                            for (_, strong) in probes {
+                                black_box(bloom.contains(strong).await);
+                            }
+                        })
+                    });
+                },
+            );
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("weak_prefilter/{}", kind), count),
+                probes,
+                |b, probes| {
+                    b.iter(|| {
+                        rt.block_on(async {
+                            for (weak, strong) in probes {
+                                black_box(index.contains(*weak, strong).await);
+                            }
+                        })
+                    });
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_dedup_lookup);
+criterion_main!(benches);
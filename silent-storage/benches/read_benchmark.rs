@@ -0,0 +1,70 @@
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use silent_storage::{IncrementalConfig, StorageManager};
+use tempfile::TempDir;
+
+/// 生成低重复度测试数据（伪随机）
+fn generate_test_data(size: usize) -> Vec<u8> {
+    (0..size)
+        .map(|i| {
+            let x = i.wrapping_mul(1103515245).wrapping_add(12345);
+            (x / 65536 % 256) as u8
+        })
+        .collect()
+}
+
+/// 基准测试：顺序读取大文件时启用/禁用块预取
+fn bench_sequential_read_prefetch(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let size = 32 * 1024 * 1024; // 32MB
+    let temp_dir = TempDir::new().unwrap();
+    let storage = StorageManager::new(
+        temp_dir.path().to_path_buf(),
+        256 * 1024,
+        IncrementalConfig::default(),
+    );
+    let data = generate_test_data(size);
+    let version_id = rt.block_on(async {
+        storage.init().await.unwrap();
+        let (_, version) = storage
+            .save_version("large_file", &data, None)
+            .await
+            .unwrap();
+        version.version_id
+    });
+
+    let mut group = c.benchmark_group("sequential_read");
+    group.throughput(Throughput::Bytes(size as u64));
+    group.sample_size(10);
+
+    for prefetch in [0usize, 2, 4, 8] {
+        group.bench_with_input(
+            BenchmarkId::new("prefetch", prefetch),
+            &prefetch,
+            |b, &prefetch| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        let mut reader = storage
+                            .open_version_reader(&version_id)
+                            .await
+                            .unwrap()
+                            .with_prefetch(prefetch);
+                        let mut total = 0;
+                        while let Some(chunk) = reader.next_chunk().await.unwrap() {
+                            total += black_box(chunk).len();
+                        }
+                        assert_eq!(total, size);
+                    })
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_sequential_read_prefetch);
+criterion_main!(benches);
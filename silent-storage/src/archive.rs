@@ -0,0 +1,1055 @@
+//! 归档导入
+//!
+//! 将上传的 tar / zip 归档展开为独立存储的文件：逐个条目流式读取，
+//! 每个文件条目通过 [`StorageManager::save_version_from_reader`] 保存到 `<前缀>/<条目名>`，
+//! 内存占用与归档大小无关。目录条目在数据目录中创建对应目录，空目录也能被列出。
+//!
+//! 支持的格式：
+//!
+//! - tar：ustar / GNU 长文件名（`L`）/ PAX 扩展头中的 `path`；
+//!   符号链接、硬链接、设备文件等条目跳过；
+//! - zip：存储（stored）与 deflate 压缩的条目、ZIP64 大小扩展、数据描述符，逐条校验 CRC32；
+//!   加密条目与其他压缩方式跳过（大小未知时无法跳过，返回错误）。
+//!
+//! 条目名包含 `..`、绝对路径或盘符时视为路径穿越（zip-slip），该条目被跳过并记入统计。
+//! 条目的长度与 CRC32 在数据读完、版本提交之前校验（见 [`CheckedEntry`]），
+//! 截断或损坏的条目不会写入存储。导入中途出错时返回错误，之前已导入的条目保留。
+
+use crate::error::{Result, StorageError};
+use crate::storage::StorageManager;
+use crc::{CRC_32_ISO_HDLC, Crc, Digest};
+use flate2::{Decompress, FlushDecompress, Status};
+use serde::{Deserialize, Serialize};
+use silent_nas_core::StorageManagerTrait;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::fs;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt, BufReader, ReadBuf};
+use tracing::{info, warn};
+
+/// tar 块大小
+const TAR_BLOCK: u64 = 512;
+
+/// GNU 长文件名 / PAX 扩展头的最大长度
+const MAX_TAR_META_LEN: u64 = 1024 * 1024;
+
+/// zip 本地文件头签名
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+/// zip 中央目录签名（本地文件条目到此结束）
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+/// zip 中央目录结束签名（空归档）
+const ZIP_END_OF_CENTRAL: u32 = 0x0605_4b50;
+/// zip 数据描述符签名（可选）
+const ZIP_DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+
+static CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// 归档格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+/// 归档导入统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveImportStats {
+    /// 导入的文件数量
+    pub files: usize,
+    /// 创建的目录数量
+    pub directories: usize,
+    /// 导入的文件数据总字节数（解压后）
+    pub bytes: u64,
+    /// 跳过的条目（不安全的路径、不支持的条目类型）
+    pub skipped: Vec<String>,
+}
+
+impl StorageManager {
+    /// 导入归档，将其中的条目展开为 `prefix` 下的独立文件
+    ///
+    /// 条目逐个流式保存，不会缓冲整个归档；目录条目在数据目录中创建对应目录。
+    pub async fn import_archive<R>(
+        &self,
+        reader: &mut R,
+        prefix: &str,
+        format: ArchiveFormat,
+    ) -> Result<ArchiveImportStats>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.ensure_writable("导入归档")?;
+
+        let mut reader = BufReader::new(reader);
+        let stats = match format {
+            ArchiveFormat::Tar => self.import_tar(&mut reader, prefix).await?,
+            ArchiveFormat::Zip => self.import_zip(&mut reader, prefix).await?,
+        };
+
+        info!(
+            "归档导入完成: prefix={}, {} 个文件, {} 个目录, {} 字节, 跳过 {} 个条目",
+            prefix,
+            stats.files,
+            stats.directories,
+            stats.bytes,
+            stats.skipped.len()
+        );
+        Ok(stats)
+    }
+
+    /// 保存一个文件条目，返回写入的字节数
+    async fn import_archive_file<R>(&self, file_id: &str, reader: &mut R) -> Result<u64>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let (_delta, version) = self.save_version_from_reader(file_id, reader, None).await?;
+        Ok(version.size)
+    }
+
+    /// 记录目录条目
+    async fn import_archive_dir(&self, dir_id: &str) -> Result<()> {
+        fs::create_dir_all(self.get_full_path(dir_id)).await?;
+        Ok(())
+    }
+
+    async fn import_tar<R>(&self, reader: &mut R, prefix: &str) -> Result<ArchiveImportStats>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut stats = ArchiveImportStats::default();
+        // GNU 长文件名或 PAX 头给出的下一条目名称
+        let mut pending_name: Option<String> = None;
+
+        loop {
+            let mut header = [0u8; TAR_BLOCK as usize];
+            match reader.read_exact(&mut header).await {
+                Ok(_) => {}
+                // 没有结束块的归档在条目边界处结束也视为完整
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            if header.iter().all(|b| *b == 0) {
+                break;
+            }
+            verify_tar_checksum(&header)?;
+
+            let size = parse_tar_number(&header[124..136])?;
+            let padding = (TAR_BLOCK - size % TAR_BLOCK) % TAR_BLOCK;
+            let entry_type = header[156];
+
+            match entry_type {
+                b'L' | b'x' => {
+                    let meta = read_tar_meta(reader, size).await?;
+                    skip_bytes(reader, padding).await?;
+                    let name = if entry_type == b'L' {
+                        Some(c_string(&meta))
+                    } else {
+                        pax_path(&meta)
+                    };
+                    if name.is_some() {
+                        pending_name = name;
+                    }
+                    continue;
+                }
+                // 全局 PAX 头不影响条目名称
+                b'g' => {
+                    skip_bytes(reader, size + padding).await?;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let raw_name = pending_name
+                .take()
+                .unwrap_or_else(|| tar_header_name(&header));
+            let is_dir = entry_type == b'5' || raw_name.ends_with('/');
+            let is_file = matches!(entry_type, b'0' | b'\0' | b'7') && !is_dir;
+
+            let Some(entry_id) = archive_entry_id(prefix, &raw_name) else {
+                warn!("跳过不安全的归档条目: {}", raw_name);
+                stats.skipped.push(raw_name);
+                skip_bytes(reader, size + padding).await?;
+                continue;
+            };
+
+            if is_dir {
+                self.import_archive_dir(&entry_id).await?;
+                stats.directories += 1;
+                skip_bytes(reader, size + padding).await?;
+            } else if is_file {
+                let entry = CheckedEntry::new((&mut *reader).take(size), Expected::Size(size));
+                let written = entry
+                    .import(self, &entry_id, || {
+                        StorageError::ChecksumMismatch(format!("tar 条目 {} 校验失败", raw_name))
+                    })
+                    .await?;
+                skip_bytes(reader, padding).await?;
+                stats.files += 1;
+                stats.bytes += written;
+            } else {
+                stats.skipped.push(raw_name);
+                skip_bytes(reader, size + padding).await?;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    async fn import_zip<R>(&self, reader: &mut R, prefix: &str) -> Result<ArchiveImportStats>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut stats = ArchiveImportStats::default();
+
+        loop {
+            let signature = reader.read_u32_le().await.map_err(truncated)?;
+            match signature {
+                ZIP_LOCAL_HEADER => {}
+                ZIP_CENTRAL_HEADER | ZIP_END_OF_CENTRAL => break,
+                _ => {
+                    return Err(StorageError::Storage(format!(
+                        "无效的 zip 条目签名: {:#010x}",
+                        signature
+                    )));
+                }
+            }
+
+            let header = read_zip_local_header(reader).await?;
+            let raw_name = header.name.clone();
+
+            let Some(entry_id) = archive_entry_id(prefix, &raw_name) else {
+                warn!("跳过不安全的归档条目: {}", raw_name);
+                stats.skipped.push(raw_name);
+                skip_zip_entry(reader, &header).await?;
+                continue;
+            };
+
+            if raw_name.ends_with('/') {
+                self.import_archive_dir(&entry_id).await?;
+                stats.directories += 1;
+                skip_zip_entry(reader, &header).await?;
+                continue;
+            }
+            if header.encrypted() || !matches!(header.method, 0 | 8) {
+                stats.skipped.push(raw_name);
+                skip_zip_entry(reader, &header).await?;
+                continue;
+            }
+
+            let mismatch =
+                || StorageError::ChecksumMismatch(format!("zip 条目 {} 校验失败", raw_name));
+            let from_header = Expected::Crc {
+                crc: header.crc32,
+                size: header.uncompressed_size,
+            };
+            let written = match (header.method, header.has_descriptor()) {
+                (0, true) => {
+                    return Err(StorageError::Storage(format!(
+                        "无法流式读取带数据描述符的未压缩条目: {}",
+                        raw_name
+                    )));
+                }
+                (0, false) => {
+                    let entry = (&mut *reader).take(header.compressed_size);
+                    CheckedEntry::new(entry, from_header)
+                        .import(self, &entry_id, mismatch)
+                        .await?
+                }
+                (_, true) => {
+                    // 压缩流结束后紧接着读取数据描述符，校验通过才提交
+                    let expected = Expected::Descriptor {
+                        zip64: header.zip64,
+                        buf: Vec::new(),
+                    };
+                    CheckedEntry::new(DeflateReader::new(&mut *reader), expected)
+                        .import(self, &entry_id, mismatch)
+                        .await?
+                }
+                (_, false) => {
+                    let mut limited = (&mut *reader).take(header.compressed_size);
+                    let written = CheckedEntry::new(DeflateReader::new(&mut limited), from_header)
+                        .import(self, &entry_id, mismatch)
+                        .await?;
+                    // 丢弃压缩流之后的剩余字节，对齐到下一个条目
+                    skip_bytes(&mut limited, u64::MAX).await?;
+                    written
+                }
+            };
+
+            stats.files += 1;
+            stats.bytes += written;
+        }
+
+        Ok(stats)
+    }
+}
+
+/// 由导入前缀与条目名得到文件 ID，条目名不安全时返回 `None`
+///
+/// 反斜杠按路径分隔符处理，空段与 `.` 段忽略；
+/// 以 `/` 开头、含 `..` 段或盘符（如 `C:`）的条目名视为路径穿越。
+fn archive_entry_id(prefix: &str, name: &str) -> Option<String> {
+    let normalized = name.replace('\\', "/");
+    if normalized.starts_with('/') {
+        return None;
+    }
+
+    let mut segments = Vec::new();
+    for segment in normalized.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            s if s.contains(':') || s.contains('\0') => return None,
+            s => segments.push(s),
+        }
+    }
+    if segments.is_empty() {
+        return None;
+    }
+
+    let prefix = prefix.trim_end_matches('/');
+    let relative = segments.join("/");
+    Some(if prefix.is_empty() {
+        relative
+    } else {
+        format!("{}/{}", prefix, relative)
+    })
+}
+
+/// 校验 tar 头部校验和（校验和字段按空格计算）
+fn verify_tar_checksum(header: &[u8; TAR_BLOCK as usize]) -> Result<()> {
+    let expected = parse_tar_number(&header[148..156])?;
+    let actual: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if (148..156).contains(&i) {
+                u64::from(b' ')
+            } else {
+                u64::from(*b)
+            }
+        })
+        .sum();
+    if actual != expected {
+        return Err(StorageError::ChecksumMismatch(
+            "tar 头部校验和不匹配".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 解析 tar 数值字段（八进制，或 GNU base-256 编码）
+fn parse_tar_number(field: &[u8]) -> Result<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        let mut value = u64::from(field[0] & 0x7f);
+        for b in &field[1..] {
+            value = value
+                .checked_mul(256)
+                .and_then(|v| v.checked_add(u64::from(*b)))
+                .ok_or_else(|| StorageError::Storage("tar 数值字段溢出".to_string()))?;
+        }
+        return Ok(value);
+    }
+
+    let text: String = field
+        .iter()
+        .skip_while(|b| **b == b' ')
+        .take_while(|b| **b != 0 && **b != b' ')
+        .map(|b| *b as char)
+        .collect();
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(&text, 8)
+        .map_err(|_| StorageError::Storage(format!("无效的 tar 数值字段: {}", text)))
+}
+
+/// tar 头部中的条目名称（ustar 格式拼接 prefix 字段）
+fn tar_header_name(header: &[u8; TAR_BLOCK as usize]) -> String {
+    let name = c_string(&header[0..100]);
+    let is_ustar = &header[257..263] == b"ustar\0";
+    let prefix = c_string(&header[345..500]);
+    if is_ustar && !prefix.is_empty() {
+        format!("{}/{}", prefix, name)
+    } else {
+        name
+    }
+}
+
+/// 以 NUL 结尾的字节串
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// 从 PAX 扩展头中取出 `path` 记录（格式：`<长度> <键>=<值>\n`）
+fn pax_path(meta: &[u8]) -> Option<String> {
+    let mut rest = meta;
+    while !rest.is_empty() {
+        let space = rest.iter().position(|b| *b == b' ')?;
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()?.parse().ok()?;
+        if len <= space || len > rest.len() {
+            return None;
+        }
+        let record = &rest[space + 1..len];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        if let Some(value) = record.strip_prefix(b"path=") {
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+        rest = &rest[len..];
+    }
+    None
+}
+
+async fn read_tar_meta<R>(reader: &mut R, size: u64) -> Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+{
+    if size > MAX_TAR_META_LEN {
+        return Err(StorageError::Storage(format!(
+            "tar 扩展头过大: {} 字节",
+            size
+        )));
+    }
+    let mut meta = vec![0u8; size as usize];
+    reader.read_exact(&mut meta).await.map_err(truncated)?;
+    Ok(meta)
+}
+
+/// zip 本地文件头
+struct ZipLocalHeader {
+    flags: u16,
+    method: u16,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    name: String,
+    zip64: bool,
+}
+
+impl ZipLocalHeader {
+    fn encrypted(&self) -> bool {
+        self.flags & 0x0001 != 0
+    }
+
+    /// 大小与 CRC 记录在数据之后的数据描述符中
+    fn has_descriptor(&self) -> bool {
+        self.flags & 0x0008 != 0
+    }
+}
+
+async fn read_zip_local_header<R>(reader: &mut R) -> Result<ZipLocalHeader>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut fixed = [0u8; 26];
+    reader.read_exact(&mut fixed).await.map_err(truncated)?;
+    let u16_at = |i: usize| u16::from_le_bytes([fixed[i], fixed[i + 1]]);
+    let u32_at =
+        |i: usize| u32::from_le_bytes([fixed[i], fixed[i + 1], fixed[i + 2], fixed[i + 3]]);
+
+    let flags = u16_at(2);
+    let method = u16_at(4);
+    let crc32 = u32_at(10);
+    let mut compressed_size = u64::from(u32_at(14));
+    let mut uncompressed_size = u64::from(u32_at(18));
+    let name_len = u16_at(22) as usize;
+    let extra_len = u16_at(24) as usize;
+
+    let mut name = vec![0u8; name_len];
+    reader.read_exact(&mut name).await.map_err(truncated)?;
+    let mut extra = vec![0u8; extra_len];
+    reader.read_exact(&mut extra).await.map_err(truncated)?;
+
+    // ZIP64 扩展字段：依次给出值为 0xFFFFFFFF 的原始大小与压缩大小
+    let mut zip64 = false;
+    let mut rest = &extra[..];
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let len = (u16::from_le_bytes([rest[2], rest[3]]) as usize).min(rest.len() - 4);
+        let mut data = &rest[4..4 + len];
+        if id == 0x0001 {
+            zip64 = true;
+            for size in [&mut uncompressed_size, &mut compressed_size] {
+                if *size == u64::from(u32::MAX) && data.len() >= 8 {
+                    *size = u64::from_le_bytes(data[..8].try_into().unwrap_or_default());
+                    data = &data[8..];
+                }
+            }
+        }
+        rest = &rest[4 + len..];
+    }
+
+    Ok(ZipLocalHeader {
+        flags,
+        method,
+        crc32,
+        compressed_size,
+        uncompressed_size,
+        name: String::from_utf8_lossy(&name).into_owned(),
+        zip64,
+    })
+}
+
+/// 跳过不导入的 zip 条目数据
+async fn skip_zip_entry<R>(reader: &mut R, header: &ZipLocalHeader) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+{
+    if !header.has_descriptor() {
+        return skip_bytes(reader, header.compressed_size).await;
+    }
+    if header.method == 8 && !header.encrypted() {
+        skip_bytes(&mut DeflateReader::new(&mut *reader), u64::MAX).await?;
+        read_zip_descriptor(reader, header.zip64).await?;
+        return Ok(());
+    }
+    Err(StorageError::Storage(format!(
+        "无法跳过大小未知的 zip 条目: {}",
+        header.name
+    )))
+}
+
+/// 读取数据描述符，返回 (CRC32, 原始大小)
+async fn read_zip_descriptor<R>(reader: &mut R, zip64: bool) -> Result<(u32, u64)>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut crc = reader.read_u32_le().await.map_err(truncated)?;
+    if crc == ZIP_DATA_DESCRIPTOR {
+        crc = reader.read_u32_le().await.map_err(truncated)?;
+    }
+    let size = if zip64 {
+        let _compressed = reader.read_u64_le().await.map_err(truncated)?;
+        reader.read_u64_le().await.map_err(truncated)?
+    } else {
+        let _compressed = reader.read_u32_le().await.map_err(truncated)?;
+        u64::from(reader.read_u32_le().await.map_err(truncated)?)
+    };
+    Ok((crc, size))
+}
+
+/// 丢弃最多 `len` 个字节（`u64::MAX` 表示读到末尾）
+async fn skip_bytes<R>(reader: &mut R, len: u64) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    let skipped = tokio::io::copy(&mut reader.take(len), &mut tokio::io::sink()).await?;
+    if len != u64::MAX && skipped != len {
+        return Err(truncated_archive());
+    }
+    Ok(())
+}
+
+fn truncated_archive() -> StorageError {
+    StorageError::Storage("归档数据不完整".to_string())
+}
+
+fn truncated(e: io::Error) -> StorageError {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        truncated_archive()
+    } else {
+        StorageError::Io(e)
+    }
+}
+
+/// 流式 deflate 解压
+///
+/// 只从底层缓冲读取器消费解压器实际用到的字节，压缩流结束后底层读取器
+/// 恰好停在下一段数据（数据描述符或下一个条目）的开头。
+struct DeflateReader<R> {
+    inner: R,
+    state: Decompress,
+    done: bool,
+}
+
+impl<R> DeflateReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: Decompress::new(false),
+            done: false,
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for DeflateReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.done || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let input = ready!(Pin::new(&mut this.inner).poll_fill_buf(cx))?;
+            let eof = input.is_empty();
+            let (in_before, out_before) = (this.state.total_in(), this.state.total_out());
+            let flush = if eof {
+                FlushDecompress::Finish
+            } else {
+                FlushDecompress::None
+            };
+            let status = this
+                .state
+                .decompress(input, buf.initialize_unfilled(), flush)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let consumed = (this.state.total_in() - in_before) as usize;
+            let produced = (this.state.total_out() - out_before) as usize;
+            Pin::new(&mut this.inner).consume(consumed);
+            buf.advance(produced);
+
+            if status == Status::StreamEnd {
+                this.done = true;
+            }
+            if produced > 0 || this.done {
+                return Poll::Ready(Ok(()));
+            }
+            if eof || consumed == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "deflate 数据不完整",
+                )));
+            }
+        }
+    }
+}
+
+/// 条目数据读取器，数据结束后可取回归档的底层读取器（读取紧随其后的数据描述符）
+trait EntryReader: AsyncRead + Unpin {
+    type Archive: AsyncBufRead + Unpin;
+
+    fn archive(&mut self) -> &mut Self::Archive;
+
+    /// 条目数据是否提前结束（归档不完整）
+    fn truncated(&self) -> bool {
+        false
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> EntryReader for tokio::io::Take<R> {
+    type Archive = R;
+
+    fn archive(&mut self) -> &mut R {
+        self.get_mut()
+    }
+
+    fn truncated(&self) -> bool {
+        self.limit() > 0
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> EntryReader for DeflateReader<R> {
+    type Archive = R;
+
+    fn archive(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+/// 条目的预期长度与 CRC32
+enum Expected {
+    /// 只校验长度（tar）
+    Size(u64),
+    /// 本地文件头给出的 CRC32 与原始大小
+    Crc { crc: u32, size: u64 },
+    /// 记录在压缩流之后的数据描述符中，`buf` 暂存已读到的描述符字节
+    Descriptor { zip64: bool, buf: Vec<u8> },
+}
+
+/// 条目校验失败的原因
+#[derive(Debug, Clone, Copy)]
+enum EntryFailure {
+    Truncated,
+    Mismatch,
+}
+
+/// 校验条目长度与 CRC32 的读取器
+///
+/// 条目数据结束时先完成校验，不符时返回错误而不是 EOF：
+/// 保存条目的 `save_version_from_reader` 因此在提交版本之前失败，损坏的条目不会留在存储中。
+struct CheckedEntry<R> {
+    inner: R,
+    digest: Digest<'static, u32>,
+    size: u64,
+    expected: Expected,
+    failure: Option<EntryFailure>,
+    verified: bool,
+}
+
+impl<R: EntryReader> CheckedEntry<R> {
+    fn new(inner: R, expected: Expected) -> Self {
+        Self {
+            inner,
+            digest: CRC32.digest(),
+            size: 0,
+            expected,
+            failure: None,
+            verified: false,
+        }
+    }
+
+    /// 保存条目，返回写入的字节数；校验失败时返回 `mismatch` 给出的错误或归档不完整错误
+    async fn import(
+        mut self,
+        storage: &StorageManager,
+        file_id: &str,
+        mismatch: impl FnOnce() -> StorageError,
+    ) -> Result<u64> {
+        match storage.import_archive_file(file_id, &mut self).await {
+            Ok(written) => Ok(written),
+            Err(e) => Err(match self.failure {
+                Some(EntryFailure::Truncated) => truncated_archive(),
+                Some(EntryFailure::Mismatch) => mismatch(),
+                None => e,
+            }),
+        }
+    }
+
+    /// 数据结束后校验，描述符尚未读完时返回 `Pending`
+    fn poll_verify(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), EntryFailure>> {
+        if self.inner.truncated() {
+            return Poll::Ready(Err(EntryFailure::Truncated));
+        }
+        let crc = self.digest.clone().finalize();
+        let (expected_crc, expected_size) = match &mut self.expected {
+            Expected::Size(size) => {
+                return Poll::Ready(if self.size == *size {
+                    Ok(())
+                } else {
+                    Err(EntryFailure::Truncated)
+                });
+            }
+            Expected::Crc { crc, size } => (*crc, *size),
+            Expected::Descriptor { zip64, buf } => {
+                let archive = self.inner.archive();
+                loop {
+                    let needed = zip_descriptor_len(buf, *zip64);
+                    if buf.len() >= needed {
+                        break;
+                    }
+                    let available = ready!(Pin::new(&mut *archive).poll_fill_buf(cx))
+                        .map_err(|_| EntryFailure::Truncated)?;
+                    if available.is_empty() {
+                        return Poll::Ready(Err(EntryFailure::Truncated));
+                    }
+                    let take = available.len().min(needed - buf.len());
+                    buf.extend_from_slice(&available[..take]);
+                    Pin::new(&mut *archive).consume(take);
+                }
+                parse_zip_descriptor(buf, *zip64)
+            }
+        };
+        Poll::Ready(if crc == expected_crc && self.size == expected_size {
+            Ok(())
+        } else {
+            Err(EntryFailure::Mismatch)
+        })
+    }
+}
+
+impl<R: EntryReader> AsyncRead for CheckedEntry<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.verified || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        let before = buf.filled().len();
+        if let Err(e) = ready!(Pin::new(&mut this.inner).poll_read(cx, buf)) {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                this.failure = Some(EntryFailure::Truncated);
+            }
+            return Poll::Ready(Err(e));
+        }
+        let read = &buf.filled()[before..];
+        if !read.is_empty() {
+            this.digest.update(read);
+            this.size += read.len() as u64;
+            return Poll::Ready(Ok(()));
+        }
+
+        // 条目数据结束：校验通过才报告 EOF
+        match ready!(this.poll_verify(cx)) {
+            Ok(()) => {
+                this.verified = true;
+                Poll::Ready(Ok(()))
+            }
+            Err(failure) => {
+                this.failure = Some(failure);
+                Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "归档条目校验失败",
+                )))
+            }
+        }
+    }
+}
+
+/// 数据描述符的总长度（已读到开头 4 字节时才能确定是否带签名）
+fn zip_descriptor_len(buf: &[u8], zip64: bool) -> usize {
+    if buf.len() < 4 {
+        return 4;
+    }
+    let signature = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let prefix = if signature == ZIP_DATA_DESCRIPTOR {
+        4
+    } else {
+        0
+    };
+    prefix + 4 + if zip64 { 16 } else { 8 }
+}
+
+/// 解析完整的数据描述符，返回 (CRC32, 原始大小)
+fn parse_zip_descriptor(buf: &[u8], zip64: bool) -> (u32, u64) {
+    let body = if buf.len() > 4 + if zip64 { 16 } else { 8 } {
+        &buf[4..]
+    } else {
+        buf
+    };
+    let crc = u32::from_le_bytes([body[0], body[1], body[2], body[3]]);
+    let size = if zip64 {
+        u64::from_le_bytes(body[12..20].try_into().unwrap_or_default())
+    } else {
+        u64::from(u32::from_le_bytes([body[8], body[9], body[10], body[11]]))
+    };
+    (crc, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_storage;
+    use flate2::Compression;
+    use flate2::write::DeflateEncoder;
+    use std::io::Write;
+
+    /// 构造 ustar 条目头部
+    fn tar_header(name: &str, size: usize, entry_type: u8) -> [u8; 512] {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        header[136..147].copy_from_slice(b"00000000000");
+        header[156] = entry_type;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].copy_from_slice(b"        ");
+        let sum: u32 = header.iter().map(|b| u32::from(*b)).sum();
+        header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        header
+    }
+
+    fn tar_archive(entries: &[(&str, &[u8], u8)]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        for (name, data, entry_type) in entries {
+            archive.extend_from_slice(&tar_header(name, data.len(), *entry_type));
+            archive.extend_from_slice(data);
+            archive.resize(archive.len().div_ceil(512) * 512, 0);
+        }
+        archive.extend_from_slice(&[0u8; 1024]);
+        archive
+    }
+
+    #[tokio::test]
+    async fn test_import_tar_stores_each_entry() {
+        let (storage, _dir) = test_storage().await;
+        let large: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let archive = tar_archive(&[
+            ("docs/", b"", b'5'),
+            ("docs/readme.txt", b"hello archive", b'0'),
+            ("docs/empty/", b"", b'5'),
+            ("data/large.bin", &large, b'0'),
+            ("link", b"", b'2'),
+        ]);
+
+        let stats = storage
+            .import_archive(&mut &archive[..], "imported", ArchiveFormat::Tar)
+            .await
+            .unwrap();
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.directories, 2);
+        assert_eq!(stats.bytes, 13 + large.len() as u64);
+        assert_eq!(stats.skipped, vec!["link".to_string()]);
+
+        assert_eq!(
+            storage.read_file("imported/docs/readme.txt").await.unwrap(),
+            b"hello archive"
+        );
+        assert_eq!(
+            storage.read_file("imported/data/large.bin").await.unwrap(),
+            large
+        );
+        let (files, mut dirs) = storage.list_directory("imported/docs").await.unwrap();
+        dirs.sort();
+        assert_eq!(files, vec!["imported/docs/readme.txt".to_string()]);
+        assert_eq!(dirs, vec!["empty".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_import_skips_path_traversal() {
+        let (storage, dir) = test_storage().await;
+        let archive = tar_archive(&[
+            ("../escape.txt", b"evil", b'0'),
+            ("/etc/evil", b"evil", b'0'),
+            ("a/../../evil", b"evil", b'0'),
+            ("ok.txt", b"fine", b'0'),
+        ]);
+
+        let stats = storage
+            .import_archive(&mut &archive[..], "safe", ArchiveFormat::Tar)
+            .await
+            .unwrap();
+        assert_eq!(stats.files, 1);
+        assert_eq!(stats.skipped.len(), 3);
+        assert_eq!(storage.read_file("safe/ok.txt").await.unwrap(), b"fine");
+        assert!(!dir.path().join("escape.txt").exists());
+        assert_eq!(
+            storage.list_files().await.unwrap(),
+            vec!["safe/ok.txt".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_zip_stored_and_deflated() {
+        let (storage, _dir) = test_storage().await;
+        let text = b"deflated zip entry ".repeat(200);
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&text).unwrap();
+        let deflated = encoder.finish().unwrap();
+
+        let archive = zip_archive(
+            &[
+                ("dir/", 0, b"", b"", false),
+                ("dir/plain.txt", 0, b"stored", b"stored", false),
+                ("dir/text.txt", 8, &text, &deflated, false),
+                ("streamed.txt", 8, &text, &deflated, true),
+            ],
+            &[],
+        );
+
+        let stats = storage
+            .import_archive(&mut &archive[..], "", ArchiveFormat::Zip)
+            .await
+            .unwrap();
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.directories, 1);
+        assert_eq!(storage.read_file("dir/plain.txt").await.unwrap(), b"stored");
+        assert_eq!(storage.read_file("dir/text.txt").await.unwrap(), text);
+        assert_eq!(storage.read_file("streamed.txt").await.unwrap(), text);
+    }
+
+    #[tokio::test]
+    async fn test_import_corrupt_zip_entry_is_not_stored() {
+        let text = b"corrupt zip entry ".repeat(200);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&text).unwrap();
+        let deflated = encoder.finish().unwrap();
+
+        for (name, method, stored, descriptor) in [
+            ("bad.txt", 0u16, &text[..], false),
+            ("bad.txt", 8, &deflated[..], false),
+            ("bad.txt", 8, &deflated[..], true),
+        ] {
+            let (storage, _dir) = test_storage().await;
+            let archive = zip_archive(
+                &[
+                    ("ok.txt", 0, b"fine", b"fine", false),
+                    (name, method, &text, stored, descriptor),
+                    ("after.txt", 0, b"after", b"after", false),
+                ],
+                &["bad.txt"],
+            );
+
+            let err = storage
+                .import_archive(&mut &archive[..], "", ArchiveFormat::Zip)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, StorageError::ChecksumMismatch(_)), "{err}");
+            // 校验失败的条目及其后的条目都没有写入
+            assert_eq!(
+                storage.list_files().await.unwrap(),
+                vec!["ok.txt".to_string()]
+            );
+            assert!(storage.read_file("bad.txt").await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_truncated_tar_entry_is_not_stored() {
+        let (storage, _dir) = test_storage().await;
+        let large: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let mut archive = tar_archive(&[("large.bin", &large, b'0')]);
+        archive.truncate(512 + 10_000);
+
+        let err = storage
+            .import_archive(&mut &archive[..], "", ArchiveFormat::Tar)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("不完整"), "{err}");
+        assert!(storage.list_files().await.unwrap().is_empty());
+    }
+
+    /// zip 测试条目：(名称, 压缩方式, 原始数据, 存储数据, 是否使用数据描述符)
+    type ZipEntry<'a> = (&'a str, u16, &'a [u8], &'a [u8], bool);
+
+    /// 构造 zip 归档，`bad_crc` 中的条目记录错误的 CRC32
+    fn zip_archive(entries: &[ZipEntry], bad_crc: &[&str]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        for &(name, method, data, stored, descriptor) in entries {
+            let mut crc = CRC32.checksum(data);
+            if bad_crc.contains(&name) {
+                crc ^= 0xffff_ffff;
+            }
+            let flags: u16 = if descriptor { 0x0008 } else { 0 };
+            let (crc_field, csize, usize_) = if descriptor {
+                (0, 0, 0)
+            } else {
+                (crc, stored.len() as u32, data.len() as u32)
+            };
+            archive.extend_from_slice(&ZIP_LOCAL_HEADER.to_le_bytes());
+            archive.extend_from_slice(&20u16.to_le_bytes());
+            archive.extend_from_slice(&flags.to_le_bytes());
+            archive.extend_from_slice(&method.to_le_bytes());
+            archive.extend_from_slice(&[0u8; 4]);
+            archive.extend_from_slice(&crc_field.to_le_bytes());
+            archive.extend_from_slice(&csize.to_le_bytes());
+            archive.extend_from_slice(&usize_.to_le_bytes());
+            archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            archive.extend_from_slice(&0u16.to_le_bytes());
+            archive.extend_from_slice(name.as_bytes());
+            archive.extend_from_slice(stored);
+            if descriptor {
+                archive.extend_from_slice(&ZIP_DATA_DESCRIPTOR.to_le_bytes());
+                archive.extend_from_slice(&crc.to_le_bytes());
+                archive.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+                archive.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            }
+        }
+        archive.extend_from_slice(&ZIP_END_OF_CENTRAL.to_le_bytes());
+        archive.extend_from_slice(&[0u8; 18]);
+        archive
+    }
+
+    #[test]
+    fn test_archive_entry_id() {
+        assert_eq!(
+            archive_entry_id("up/", "./a/b.txt").as_deref(),
+            Some("up/a/b.txt")
+        );
+        assert_eq!(archive_entry_id("", "a\\b.txt").as_deref(), Some("a/b.txt"));
+        assert_eq!(archive_entry_id("up", "a/../../b"), None);
+        assert_eq!(archive_entry_id("up", "C:/windows"), None);
+        assert_eq!(archive_entry_id("up", "/abs"), None);
+        assert_eq!(archive_entry_id("up", "./"), None);
+    }
+}
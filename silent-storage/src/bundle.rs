@@ -0,0 +1,443 @@
+//! 单文件导出包
+//!
+//! 将单个文件的当前版本导出为自带校验信息的字节流，用于在存储实例之间搬运单个文件。
+//!
+//! 包格式：
+//!
+//! ```text
+//! MAGIC(8) | FORMAT_VERSION(u32 LE) | 清单长度(u32 LE) | 清单(JSON) | 块数据...
+//! ```
+//!
+//! 清单按偏移顺序列出每个块的原始数据哈希、原始大小、存储大小和压缩算法，
+//! 以及整个文件的大小和哈希；块数据按清单顺序紧随其后，保持存储形式（可能已压缩）。
+//! 导入时逐块解压并与清单校验，全部通过后才写入存储，损坏的包不会留下部分导入的数据。
+
+use crate::core::compression::CompressionAlgorithm;
+use crate::error::{Result, StorageError};
+use crate::snapshot::{MAX_CHUNK_LEN, MAX_META_LEN, sha256_hex};
+use crate::storage::StorageManager;
+use crate::{ChunkInfo, VersionInfo};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use silent_nas_core::FileVersion;
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::info;
+
+/// 导出包魔数
+const BUNDLE_MAGIC: &[u8; 8] = b"SNASFILE";
+
+/// 导出包格式版本
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// 导出包清单
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileBundleManifest {
+    /// 文件 ID
+    pub file_id: String,
+    /// 源存储中的版本 ID（仅供参考）
+    pub version_id: String,
+    /// 版本创建时间
+    pub created_at: chrono::NaiveDateTime,
+    /// 文件大小
+    pub size: u64,
+    /// 文件哈希（SHA-256）
+    pub hash: String,
+    /// 按偏移顺序排列的块
+    pub chunks: Vec<BundleChunk>,
+}
+
+/// 导出包中的块
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleChunk {
+    /// 原始数据哈希（SHA-256）
+    pub hash: String,
+    /// 原始大小
+    pub size: u64,
+    /// 包内存储大小
+    pub stored_size: u64,
+    /// 压缩算法
+    pub compression: CompressionAlgorithm,
+}
+
+impl StorageManager {
+    /// 导出文件当前版本
+    ///
+    /// 分块存储的版本直接导出块的存储形式，导出前会校验每个块的原始数据哈希；
+    /// 旧版存储模式的数据按分块大小切分后以未压缩形式导出。
+    pub async fn export_file<W>(&self, file_id: &str, writer: &mut W) -> Result<FileBundleManifest>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let version_id = self.current_version_id(file_id).await?;
+        let version_info = self.get_version_info(&version_id).await?;
+
+        let mut manifest = FileBundleManifest {
+            file_id: file_id.to_string(),
+            version_id: version_id.clone(),
+            created_at: version_info.created_at,
+            size: 0,
+            hash: String::new(),
+            chunks: Vec::new(),
+        };
+        let mut file_hasher = Sha256::new();
+
+        // 第一遍：校验块并生成清单；第二遍再写出块数据，避免整个文件驻留内存
+        let stored_chunks = self.bundle_source_chunks(&version_info).await?;
+        let preloaded = match &stored_chunks {
+            Some(chunks) => {
+                for chunk in chunks {
+                    let stored = self.read_stored_chunk(&chunk.chunk_id).await?;
+                    let stored_size = stored.len() as u64;
+                    let data = self.decompress_chunk(stored, chunk.compression)?;
+                    let hash = sha256_hex(&data);
+                    if data.len() != chunk.size
+                        || (!chunk.strong_hash.is_empty() && hash != chunk.strong_hash)
+                    {
+                        return Err(StorageError::ChecksumMismatch(format!(
+                            "块校验失败: {} ({})",
+                            chunk.chunk_id, file_id
+                        )));
+                    }
+                    file_hasher.update(&data);
+                    manifest.size += data.len() as u64;
+                    manifest.chunks.push(BundleChunk {
+                        hash,
+                        size: data.len() as u64,
+                        stored_size,
+                        compression: chunk.compression,
+                    });
+                }
+                None
+            }
+            None => {
+                let data = self.read_version_data(&version_id).await?;
+                for piece in data.chunks(self.chunk_size().max(1)) {
+                    manifest.chunks.push(BundleChunk {
+                        hash: sha256_hex(piece),
+                        size: piece.len() as u64,
+                        stored_size: piece.len() as u64,
+                        compression: CompressionAlgorithm::None,
+                    });
+                }
+                file_hasher.update(&data);
+                manifest.size = data.len() as u64;
+                Some(data)
+            }
+        };
+        manifest.hash = hex::encode(file_hasher.finalize());
+
+        if manifest.size != version_info.file_size {
+            return Err(StorageError::ChecksumMismatch(format!(
+                "文件大小与版本记录不一致: {} (期望 {}, 实际 {})",
+                file_id, version_info.file_size, manifest.size
+            )));
+        }
+
+        let manifest_json = serde_json::to_vec(&manifest)?;
+        writer.write_all(BUNDLE_MAGIC).await?;
+        writer.write_u32_le(BUNDLE_FORMAT_VERSION).await?;
+        writer.write_u32_le(manifest_json.len() as u32).await?;
+        writer.write_all(&manifest_json).await?;
+
+        match (preloaded, stored_chunks) {
+            (Some(data), _) => writer.write_all(&data).await?,
+            (None, Some(chunks)) => {
+                for (chunk, entry) in chunks.iter().zip(&manifest.chunks) {
+                    let stored = self.read_stored_chunk(&chunk.chunk_id).await?;
+                    if stored.len() as u64 != entry.stored_size {
+                        return Err(StorageError::Chunk(format!(
+                            "导出过程中块发生变化: {}",
+                            chunk.chunk_id
+                        )));
+                    }
+                    writer.write_all(&stored).await?;
+                }
+            }
+            (None, None) => unreachable!("未分块的版本总是预先加载"),
+        }
+        writer.flush().await?;
+
+        info!(
+            "文件导出完成: {} ({} 字节, {} 个块)",
+            file_id,
+            manifest.size,
+            manifest.chunks.len()
+        );
+        Ok(manifest)
+    }
+
+    /// 从导出包导入文件
+    ///
+    /// 每个块都与清单中的哈希和大小比对，整个文件与清单中的文件哈希比对，
+    /// 包末尾不允许有多余数据。校验全部通过后才作为新版本保存
+    /// （目标存储中已有同名文件时追加在其当前版本之后），
+    /// 任何校验失败都会返回错误且不修改存储。
+    pub async fn import_file<R>(&self, reader: &mut R) -> Result<FileVersion>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.ensure_writable("导入文件")?;
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic).await.map_err(truncated)?;
+        if &magic != BUNDLE_MAGIC {
+            return Err(StorageError::Storage("不是有效的文件导出包".to_string()));
+        }
+        let format_version = reader.read_u32_le().await.map_err(truncated)?;
+        if format_version != BUNDLE_FORMAT_VERSION {
+            return Err(StorageError::Storage(format!(
+                "不支持的导出包格式版本: {}",
+                format_version
+            )));
+        }
+        let manifest_len = reader.read_u32_le().await.map_err(truncated)?;
+        if manifest_len > MAX_META_LEN {
+            return Err(StorageError::Storage(format!(
+                "导出包清单过大: {} 字节",
+                manifest_len
+            )));
+        }
+        let mut manifest_json = vec![0u8; manifest_len as usize];
+        reader
+            .read_exact(&mut manifest_json)
+            .await
+            .map_err(truncated)?;
+        let manifest: FileBundleManifest = serde_json::from_slice(&manifest_json)?;
+        validate_manifest(&manifest)?;
+
+        // 校验通过的数据先写入暂存文件，全部校验完成后才保存到存储
+        let scratch_dir = self.scratch_dir();
+        fs::create_dir_all(&scratch_dir).await?;
+        let staging = scratch_dir.join(format!("bundle-import-{}", scru128::new()));
+        let result = async {
+            self.stage_bundle_chunks(reader, &manifest, &staging)
+                .await?;
+            let parent = self.current_version_id(&manifest.file_id).await.ok();
+            self.save_version_from_path(&manifest.file_id, &staging, parent.as_deref())
+                .await
+        }
+        .await;
+        let _ = fs::remove_file(&staging).await;
+        let (_, version) = result?;
+
+        info!(
+            "文件导入完成: {} ({} 字节, {} 个块)",
+            manifest.file_id,
+            manifest.size,
+            manifest.chunks.len()
+        );
+        Ok(version)
+    }
+
+    /// 导出使用的块列表（按偏移排序）
+    ///
+    /// 非分块存储或旧版增量链未覆盖全部数据时返回 `None`，此时需完整读取版本数据。
+    async fn bundle_source_chunks(
+        &self,
+        version_info: &VersionInfo,
+    ) -> Result<Option<Vec<ChunkInfo>>> {
+        if !self.is_chunked_file(&version_info.file_id)? {
+            return Ok(None);
+        }
+        let mut delta = self
+            .read_delta(&version_info.file_id, &version_info.version_id)
+            .await?;
+        let covered: u64 = delta.chunks.iter().map(|c| c.size as u64).sum();
+        if covered != version_info.file_size {
+            return Ok(None);
+        }
+        delta.chunks.sort_by_key(|c| c.offset);
+        Ok(Some(delta.chunks))
+    }
+
+    /// 逐块读取、解压并校验导出包数据，写入暂存文件
+    async fn stage_bundle_chunks<R>(
+        &self,
+        reader: &mut R,
+        manifest: &FileBundleManifest,
+        staging: &std::path::Path,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut file = fs::File::create(staging).await?;
+        let mut file_hasher = Sha256::new();
+
+        for (index, chunk) in manifest.chunks.iter().enumerate() {
+            let mut stored = vec![0u8; chunk.stored_size as usize];
+            reader.read_exact(&mut stored).await.map_err(truncated)?;
+            let corrupted = || {
+                StorageError::ChecksumMismatch(format!(
+                    "导出包块校验失败: 第 {} 块 ({})",
+                    index, manifest.file_id
+                ))
+            };
+            let data = self
+                .decompress_chunk(stored, chunk.compression)
+                .map_err(|_| corrupted())?;
+            if data.len() as u64 != chunk.size || sha256_hex(&data) != chunk.hash {
+                return Err(corrupted());
+            }
+            file_hasher.update(&data);
+            file.write_all(&data).await?;
+        }
+        file.flush().await?;
+
+        if hex::encode(file_hasher.finalize()) != manifest.hash {
+            return Err(StorageError::ChecksumMismatch(format!(
+                "导出包文件校验失败: {}",
+                manifest.file_id
+            )));
+        }
+        let mut rest = [0u8; 1];
+        if reader.read(&mut rest).await? != 0 {
+            return Err(StorageError::Storage("导出包末尾存在多余数据".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// 导入前检查清单自身是否一致
+fn validate_manifest(manifest: &FileBundleManifest) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(StorageError::Storage(format!(
+            "无效的导出包清单: {} ({})",
+            reason, manifest.file_id
+        )))
+    };
+
+    if manifest.file_id.is_empty() {
+        return invalid("文件 ID 为空");
+    }
+    if !is_sha256_hex(&manifest.hash) {
+        return invalid("文件哈希格式错误");
+    }
+    let mut total = 0u64;
+    for chunk in &manifest.chunks {
+        if !is_sha256_hex(&chunk.hash) {
+            return invalid("块哈希格式错误");
+        }
+        if chunk.stored_size > MAX_CHUNK_LEN || chunk.size > MAX_CHUNK_LEN {
+            return invalid("块过大");
+        }
+        if chunk.compression == CompressionAlgorithm::Auto {
+            return invalid("块压缩算法无效");
+        }
+        total += chunk.size;
+    }
+    if total != manifest.size {
+        return invalid("块大小之和与文件大小不一致");
+    }
+    Ok(())
+}
+
+fn is_sha256_hex(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// 流提前结束时给出明确的错误
+fn truncated(e: std::io::Error) -> StorageError {
+    if e.kind() == std::io::ErrorKind::UnexpectedEof {
+        StorageError::Storage("导出包数据不完整".to_string())
+    } else {
+        StorageError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_data, test_storage};
+    use silent_nas_core::StorageManagerTrait;
+
+    /// 导出包中第一个块数据的偏移
+    fn first_chunk_offset(bundle: &[u8]) -> usize {
+        let header_len = BUNDLE_MAGIC.len() + 4;
+        let manifest_len =
+            u32::from_le_bytes(bundle[header_len..header_len + 4].try_into().unwrap()) as usize;
+        header_len + 4 + manifest_len
+    }
+
+    #[tokio::test]
+    async fn test_export_import_file_roundtrip() {
+        let (source, _source_dir) = test_storage().await;
+        let mut data = test_data(64 * 1024, 0);
+        data.extend(std::iter::repeat_n(0u8, 32 * 1024));
+        source
+            .save_version("docs/a.bin", &data, None)
+            .await
+            .unwrap();
+
+        let mut bundle = Vec::new();
+        let manifest = source.export_file("docs/a.bin", &mut bundle).await.unwrap();
+        assert_eq!(manifest.size, data.len() as u64);
+        assert_eq!(manifest.hash, sha256_hex(&data));
+        assert!(manifest.chunks.len() > 1);
+
+        let (target, _target_dir) = test_storage().await;
+        let version = target.import_file(&mut bundle.as_slice()).await.unwrap();
+        assert_eq!(version.file_id, "docs/a.bin");
+        assert_eq!(version.size, data.len() as u64);
+        assert_eq!(target.read_file("docs/a.bin").await.unwrap(), data);
+
+        // 再次导入追加为新版本
+        target.import_file(&mut bundle.as_slice()).await.unwrap();
+        assert_eq!(
+            target.list_file_versions("docs/a.bin").await.unwrap().len(),
+            2
+        );
+
+        // 空文件
+        source.save_version("empty", b"", None).await.unwrap();
+        let mut bundle = Vec::new();
+        source.export_file("empty", &mut bundle).await.unwrap();
+        target.import_file(&mut bundle.as_slice()).await.unwrap();
+        assert!(target.read_file("empty").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_tampered_bundle() {
+        let (source, _source_dir) = test_storage().await;
+        source
+            .save_version("file", &test_data(16 * 1024, 3), None)
+            .await
+            .unwrap();
+        let mut bundle = Vec::new();
+        source.export_file("file", &mut bundle).await.unwrap();
+
+        let (target, _target_dir) = test_storage().await;
+
+        // 第一个块的数据被篡改
+        let mut tampered = bundle.clone();
+        let offset = first_chunk_offset(&tampered);
+        tampered[offset] ^= 0xff;
+        assert!(matches!(
+            target.import_file(&mut tampered.as_slice()).await,
+            Err(StorageError::ChecksumMismatch(_))
+        ));
+
+        // 被截断或末尾有多余数据
+        let truncated = &bundle[..bundle.len() - 10];
+        assert!(target.import_file(&mut &truncated[..]).await.is_err());
+        let mut extended = bundle.clone();
+        extended.push(0);
+        assert!(target.import_file(&mut extended.as_slice()).await.is_err());
+
+        // 没有导入任何数据，暂存文件已清理
+        assert!(!target.file_exists("file").await);
+        let mut entries = fs::read_dir(target.scratch_dir()).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            assert!(
+                !entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("bundle-import-")
+            );
+        }
+
+        // 原始导出包仍可正常导入
+        target.import_file(&mut bundle.as_slice()).await.unwrap();
+        assert!(target.file_exists("file").await);
+    }
+}
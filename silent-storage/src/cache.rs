@@ -3,9 +3,22 @@
 //! 使用 moka 库实现高性能的 LRU 缓存，提升热数据访问性能
 
 use moka::future::Cache;
+use moka::notification::RemovalCause;
+use moka::policy::EvictionPolicy;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
+/// 热数据缓存淘汰策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HotDataEviction {
+    /// 按最近最少使用淘汰，适合重组后的大文件
+    #[default]
+    Lru,
+    /// TinyLFU：按访问频率准入与淘汰，适合大量小文件
+    TinyLfu,
+}
+
 /// 缓存配置
 #[derive(Debug, Clone)]
 pub struct CacheConfig {
@@ -13,8 +26,10 @@ pub struct CacheConfig {
     pub file_metadata_capacity: u64,
     /// Chunk 索引缓存容量（条目数）
     pub chunk_index_capacity: u64,
-    /// 热数据缓存容量（字节）
+    /// 热数据缓存容量（字节），超过该大小的单个条目不进入缓存
     pub hot_data_capacity: u64,
+    /// 热数据缓存淘汰策略
+    pub hot_data_eviction: HotDataEviction,
     /// 缓存过期时间（秒）
     pub ttl_seconds: u64,
     /// 空闲淘汰时间（秒）
@@ -27,8 +42,9 @@ impl Default for CacheConfig {
             file_metadata_capacity: 10_000,       // 10000 个文件
             chunk_index_capacity: 100_000,        // 100000 个 chunks
             hot_data_capacity: 100 * 1024 * 1024, // 100 MB
-            ttl_seconds: 3600,                    // 1 小时
-            idle_seconds: 300,                    // 5 分钟
+            hot_data_eviction: HotDataEviction::Lru,
+            ttl_seconds: 3600, // 1 小时
+            idle_seconds: 300, // 5 分钟
         }
     }
 }
@@ -80,6 +96,10 @@ pub struct CacheManager {
     chunk_index_cache: Cache<String, ChunkIndexEntry>,
     /// 热数据缓存（使用权重限制总大小）
     hot_data_cache: Cache<String, HotDataEntry>,
+    /// 热数据缓存因容量或过期被淘汰的条目数
+    hot_data_evictions: Arc<AtomicU64>,
+    /// 因超过整个缓存容量而未缓存的条目数
+    hot_data_bypassed: AtomicU64,
 }
 
 impl CacheManager {
@@ -100,9 +120,21 @@ impl CacheManager {
             .build();
 
         // 热数据缓存（按总字节数限制）
+        let hot_data_evictions = Arc::new(AtomicU64::new(0));
+        let evictions = hot_data_evictions.clone();
+        let eviction_policy = match config.hot_data_eviction {
+            HotDataEviction::Lru => EvictionPolicy::lru(),
+            HotDataEviction::TinyLfu => EvictionPolicy::tiny_lfu(),
+        };
         let hot_data_cache = Cache::builder()
             .max_capacity(config.hot_data_capacity)
-            .weigher(|_key: &String, value: &HotDataEntry| value.size as u32)
+            .eviction_policy(eviction_policy)
+            .weigher(|_key: &String, value: &HotDataEntry| value.size.min(u32::MAX as u64) as u32)
+            .eviction_listener(move |_key, _value, cause: RemovalCause| {
+                if cause.was_evicted() {
+                    evictions.fetch_add(1, Ordering::Relaxed);
+                }
+            })
             .time_to_live(Duration::from_secs(config.ttl_seconds))
             .time_to_idle(Duration::from_secs(config.idle_seconds))
             .build();
@@ -112,6 +144,8 @@ impl CacheManager {
             file_metadata_cache,
             chunk_index_cache,
             hot_data_cache,
+            hot_data_evictions,
+            hot_data_bypassed: AtomicU64::new(0),
         }
     }
 
@@ -176,8 +210,16 @@ impl CacheManager {
     }
 
     /// 设置热数据
+    ///
+    /// 大于整个缓存容量的数据直接跳过，避免为一个条目清空缓存；
+    /// 同时移除该键的旧数据，防止读到过期内容。
     pub async fn set_hot_data(&self, key: String, data: Vec<u8>) {
         let size = data.len() as u64;
+        if size > self.config.hot_data_capacity {
+            self.hot_data_bypassed.fetch_add(1, Ordering::Relaxed);
+            self.hot_data_cache.invalidate(&key).await;
+            return;
+        }
         let entry = HotDataEntry {
             data: Arc::new(data),
             size,
@@ -204,6 +246,8 @@ impl CacheManager {
             chunk_index_count: self.chunk_index_cache.entry_count(),
             hot_data_count: self.hot_data_cache.entry_count(),
             hot_data_size: self.hot_data_cache.weighted_size(),
+            hot_data_evictions: self.hot_data_evictions.load(Ordering::Relaxed),
+            hot_data_bypassed: self.hot_data_bypassed.load(Ordering::Relaxed),
             config: self.config.clone(),
         }
     }
@@ -232,6 +276,10 @@ pub struct CacheStats {
     pub hot_data_count: u64,
     /// 热数据缓存总大小（字节）
     pub hot_data_size: u64,
+    /// 热数据缓存累计淘汰条目数
+    pub hot_data_evictions: u64,
+    /// 超过缓存容量而未缓存的条目数
+    pub hot_data_bypassed: u64,
     /// 缓存配置
     pub config: CacheConfig,
 }
@@ -263,6 +311,32 @@ impl CacheStats {
             self.hot_data_size as f64 / self.config.hot_data_capacity as f64
         }
     }
+
+    /// 导出热数据缓存的 Prometheus 格式指标
+    pub fn to_prometheus(&self) -> String {
+        format!(
+            "# HELP storage_hot_cache_bytes Bytes held by the reconstructed file cache\n\
+             # TYPE storage_hot_cache_bytes gauge\n\
+             storage_hot_cache_bytes {}\n\
+             # HELP storage_hot_cache_capacity_bytes Byte budget of the reconstructed file cache\n\
+             # TYPE storage_hot_cache_capacity_bytes gauge\n\
+             storage_hot_cache_capacity_bytes {}\n\
+             # HELP storage_hot_cache_entries Number of entries in the reconstructed file cache\n\
+             # TYPE storage_hot_cache_entries gauge\n\
+             storage_hot_cache_entries {}\n\
+             # HELP storage_hot_cache_evictions_total Entries evicted from the reconstructed file cache\n\
+             # TYPE storage_hot_cache_evictions_total counter\n\
+             storage_hot_cache_evictions_total {}\n\
+             # HELP storage_hot_cache_bypassed_total Entries larger than the cache budget that were not cached\n\
+             # TYPE storage_hot_cache_bypassed_total counter\n\
+             storage_hot_cache_bypassed_total {}\n",
+            self.hot_data_size,
+            self.config.hot_data_capacity,
+            self.hot_data_count,
+            self.hot_data_evictions,
+            self.hot_data_bypassed
+        )
+    }
 }
 
 #[cfg(test)]
@@ -353,6 +427,44 @@ mod tests {
         assert!(manager.get_hot_data("data1").await.is_none());
     }
 
+    #[tokio::test]
+    async fn test_hot_data_evicts_lru_by_size() {
+        let manager = CacheManager::new(CacheConfig {
+            hot_data_capacity: 1000,
+            ..Default::default()
+        });
+
+        manager.set_hot_data("a".to_string(), vec![0; 400]).await;
+        manager.set_hot_data("b".to_string(), vec![1; 400]).await;
+        manager.hot_data_cache.run_pending_tasks().await;
+        // 访问 a，使 b 成为最近最少使用
+        assert!(manager.get_hot_data("a").await.is_some());
+        manager.hot_data_cache.run_pending_tasks().await;
+
+        manager.set_hot_data("c".to_string(), vec![2; 400]).await;
+        let stats = manager.get_stats().await;
+        assert!(stats.hot_data_size <= 1000);
+        assert_eq!(stats.hot_data_count, 2);
+        assert_eq!(stats.hot_data_evictions, 1);
+        assert!(manager.get_hot_data("b").await.is_none());
+        assert!(manager.get_hot_data("a").await.is_some());
+        assert!(manager.get_hot_data("c").await.is_some());
+
+        // 超过整个容量的条目不进入缓存，也不挤出已有条目
+        manager
+            .set_hot_data("huge".to_string(), vec![3; 2000])
+            .await;
+        let stats = manager.get_stats().await;
+        assert!(manager.get_hot_data("huge").await.is_none());
+        assert_eq!(stats.hot_data_bypassed, 1);
+        assert_eq!(stats.hot_data_count, 2);
+        assert!(
+            stats
+                .to_prometheus()
+                .contains("storage_hot_cache_evictions_total 1")
+        );
+    }
+
     #[tokio::test]
     async fn test_batch_operations() {
         let manager = CacheManager::with_default();
@@ -0,0 +1,365 @@
+//! 块存储后端
+//!
+//! [`ChunkStore`] 抽象了按块哈希存取块数据的操作，存储引擎只通过它读写块，
+//! 不直接访问文件系统。默认使用 [`LocalChunkStore`]（本地文件系统，按哈希前缀分层），
+//! 对象存储等其他后端实现该 trait 后即可通过 [`StorageManager::with_chunk_store`] 接入。
+//!
+//! 后端保存的是块的存储形式（可能已压缩），压缩与解压由存储引擎负责。
+//!
+//! [`StorageManager::with_chunk_store`]: crate::StorageManager::with_chunk_store
+
+use crate::error::{Result, StorageError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+/// 块存储后端
+#[async_trait]
+pub trait ChunkStore: Send + Sync {
+    /// 写入块，块已存在时不覆盖
+    ///
+    /// 写入必须是原子的：失败时不能留下部分写入的块。
+    /// 返回 `true` 表示新写入，`false` 表示块已存在（包括并发写入者先完成的情况）。
+    async fn put(&self, chunk_id: &str, data: &[u8]) -> Result<bool>;
+
+    /// 读取块数据
+    async fn get(&self, chunk_id: &str) -> Result<Vec<u8>>;
+
+    /// 块是否存在
+    async fn exists(&self, chunk_id: &str) -> Result<bool>;
+
+    /// 删除块，返回释放的字节数（块不存在时返回 `None`）
+    async fn delete(&self, chunk_id: &str) -> Result<Option<u64>>;
+
+    /// 列出所有块 ID
+    async fn list(&self) -> Result<Vec<String>>;
+
+    /// 块的存储大小（块不存在时返回 `None`）
+    async fn stored_size(&self, chunk_id: &str) -> Result<Option<u64>>;
+
+    /// 块的定位路径，记录在块引用计数中
+    ///
+    /// 本地后端为块文件路径，其他后端为后端内的逻辑键。
+    fn location(&self, chunk_id: &str) -> PathBuf;
+
+    /// 整理存储，回收已删除块仍占用的空间，返回实际释放的字节数
+    ///
+    /// 删除即释放空间的后端无需实现。GC 删除未引用的块后调用。
+    async fn compact(&self) -> Result<u64> {
+        Ok(0)
+    }
+
+    /// 将后端自身维护的状态（如块索引）落盘
+    ///
+    /// 每次写入都已落盘的后端无需实现。由 [`StorageManager::sync_all`] 调用。
+    ///
+    /// [`StorageManager::sync_all`]: crate::StorageManager::sync_all
+    async fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 本地文件系统块存储
+///
+/// 块保存在 `<root>/data/<哈希前两位>/<哈希>`，
+/// 写入先落到同目录的临时文件并落盘，再通过硬链接原子发布。
+pub struct LocalChunkStore {
+    /// 块存储根目录
+    root: PathBuf,
+    /// 测试用：写入一半数据后模拟磁盘已满
+    #[cfg(test)]
+    fail_writes: std::sync::atomic::AtomicBool,
+}
+
+impl LocalChunkStore {
+    /// 创建本地块存储
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            #[cfg(test)]
+            fail_writes: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    /// 测试用：之后的写入在临时文件写入一半数据后以磁盘已满失败
+    #[cfg(test)]
+    pub(crate) fn set_fail_writes(&self, fail: bool) {
+        self.fail_writes
+            .store(fail, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// 块存储根目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 获取块路径（按哈希前缀分层）
+    fn chunk_path(&self, chunk_id: &str) -> PathBuf {
+        let prefix = &chunk_id[..2.min(chunk_id.len())];
+        self.root.join("data").join(prefix).join(chunk_id)
+    }
+
+    /// 是否为写入过程中的临时文件
+    fn is_temp_file(name: &str) -> bool {
+        name.contains(".tmp.")
+    }
+}
+
+#[async_trait]
+impl ChunkStore for LocalChunkStore {
+    /// 数据先写入同目录下的临时文件并 fsync，成功后通过硬链接发布到最终路径
+    /// （崩溃后不会出现已发布但内容未落盘的块）：
+    /// 目标已存在时链接失败（与 create_new 相同的独占语义），
+    /// 任何错误都会删除临时文件，不留下部分写入的块。
+    /// 磁盘空间不足时返回 `StorageError::OutOfSpace`。
+    async fn put(&self, chunk_id: &str, data: &[u8]) -> Result<bool> {
+        let chunk_path = self.chunk_path(chunk_id);
+        if chunk_path.exists() {
+            return Ok(false);
+        }
+        if let Some(parent) = chunk_path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| StorageError::from_io(e, "创建块目录失败"))?;
+        }
+
+        let temp_path = chunk_path.with_extension(format!("tmp.{}", scru128::new()));
+        let write_result = async {
+            let mut file = fs::File::create(&temp_path).await?;
+            #[cfg(test)]
+            if self.fail_writes.load(std::sync::atomic::Ordering::Relaxed) {
+                file.write_all(&data[..data.len() / 2]).await?;
+                file.flush().await?;
+                return Err(std::io::Error::from(std::io::ErrorKind::StorageFull));
+            }
+            file.write_all(data).await?;
+            file.flush().await?;
+            file.sync_all().await
+        }
+        .await;
+
+        if let Err(e) = write_result {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(StorageError::from_io(e, "写入块文件失败"));
+        }
+
+        let publish_result = fs::hard_link(&temp_path, &chunk_path).await;
+        let _ = fs::remove_file(&temp_path).await;
+
+        match publish_result {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(StorageError::from_io(e, "发布块文件失败")),
+        }
+    }
+
+    async fn get(&self, chunk_id: &str) -> Result<Vec<u8>> {
+        fs::read(self.chunk_path(chunk_id))
+            .await
+            .map_err(StorageError::Io)
+    }
+
+    async fn exists(&self, chunk_id: &str) -> Result<bool> {
+        Ok(fs::try_exists(self.chunk_path(chunk_id)).await?)
+    }
+
+    async fn delete(&self, chunk_id: &str) -> Result<Option<u64>> {
+        let chunk_path = self.chunk_path(chunk_id);
+        let size = match fs::metadata(&chunk_path).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(StorageError::Io(e)),
+        };
+        match fs::remove_file(&chunk_path).await {
+            Ok(()) => Ok(Some(size)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let mut chunk_ids = Vec::new();
+        let data_dir = self.root.join("data");
+        if !data_dir.exists() {
+            return Ok(chunk_ids);
+        }
+
+        // 遍历所有前缀目录
+        let mut prefix_entries = fs::read_dir(&data_dir).await?;
+        while let Some(prefix_entry) = prefix_entries.next_entry().await? {
+            if !prefix_entry.file_type().await?.is_dir() {
+                continue;
+            }
+            let mut chunk_entries = fs::read_dir(prefix_entry.path()).await?;
+            while let Some(chunk_entry) = chunk_entries.next_entry().await? {
+                if let Some(name) = chunk_entry.file_name().to_str()
+                    && !Self::is_temp_file(name)
+                {
+                    chunk_ids.push(name.to_string());
+                }
+            }
+        }
+        Ok(chunk_ids)
+    }
+
+    async fn stored_size(&self, chunk_id: &str) -> Result<Option<u64>> {
+        match fs::metadata(self.chunk_path(chunk_id)).await {
+            Ok(metadata) => Ok(Some(metadata.len())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    fn location(&self, chunk_id: &str) -> PathBuf {
+        self.chunk_path(chunk_id)
+    }
+}
+
+/// 内存块存储
+///
+/// 进程退出后数据丢失，适用于测试和临时存储。
+#[derive(Default)]
+pub struct MemoryChunkStore {
+    chunks: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryChunkStore {
+    /// 创建空的内存块存储
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 块数量
+    pub async fn len(&self) -> usize {
+        self.chunks.read().await.len()
+    }
+
+    /// 是否没有任何块
+    pub async fn is_empty(&self) -> bool {
+        self.chunks.read().await.is_empty()
+    }
+}
+
+#[async_trait]
+impl ChunkStore for MemoryChunkStore {
+    async fn put(&self, chunk_id: &str, data: &[u8]) -> Result<bool> {
+        let mut chunks = self.chunks.write().await;
+        if chunks.contains_key(chunk_id) {
+            return Ok(false);
+        }
+        chunks.insert(chunk_id.to_string(), data.to_vec());
+        Ok(true)
+    }
+
+    async fn get(&self, chunk_id: &str) -> Result<Vec<u8>> {
+        self.chunks
+            .read()
+            .await
+            .get(chunk_id)
+            .cloned()
+            .ok_or_else(|| {
+                StorageError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("块不存在: {}", chunk_id),
+                ))
+            })
+    }
+
+    async fn exists(&self, chunk_id: &str) -> Result<bool> {
+        Ok(self.chunks.read().await.contains_key(chunk_id))
+    }
+
+    async fn delete(&self, chunk_id: &str) -> Result<Option<u64>> {
+        Ok(self
+            .chunks
+            .write()
+            .await
+            .remove(chunk_id)
+            .map(|data| data.len() as u64))
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        Ok(self.chunks.read().await.keys().cloned().collect())
+    }
+
+    async fn stored_size(&self, chunk_id: &str) -> Result<Option<u64>> {
+        Ok(self
+            .chunks
+            .read()
+            .await
+            .get(chunk_id)
+            .map(|data| data.len() as u64))
+    }
+
+    fn location(&self, chunk_id: &str) -> PathBuf {
+        PathBuf::from("memory").join(chunk_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn exercise(store: &dyn ChunkStore) {
+        assert!(!store.exists("abcdef").await.unwrap());
+        assert!(store.put("abcdef", b"chunk data").await.unwrap());
+        // 重复写入不覆盖
+        assert!(!store.put("abcdef", b"other").await.unwrap());
+
+        assert!(store.exists("abcdef").await.unwrap());
+        assert_eq!(store.get("abcdef").await.unwrap(), b"chunk data");
+        assert_eq!(store.stored_size("abcdef").await.unwrap(), Some(10));
+        assert_eq!(store.list().await.unwrap(), vec!["abcdef".to_string()]);
+
+        assert_eq!(store.delete("abcdef").await.unwrap(), Some(10));
+        assert_eq!(store.delete("abcdef").await.unwrap(), None);
+        assert!(store.get("abcdef").await.is_err());
+        assert!(store.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_local_chunk_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = LocalChunkStore::new(temp_dir.path().to_path_buf());
+        exercise(&store).await;
+        assert_eq!(
+            store.location("abcdef"),
+            temp_dir.path().join("data").join("ab").join("abcdef")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_local_chunk_store_write_failure_removes_temp_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = LocalChunkStore::new(temp_dir.path().to_path_buf());
+
+        store.set_fail_writes(true);
+        let err = store.put("abcdef", &[7u8; 4096]).await.unwrap_err();
+        assert!(matches!(err, StorageError::OutOfSpace(_)));
+
+        // 写了一半的临时文件已删除，块也没有发布
+        let mut entries = fs::read_dir(temp_dir.path().join("data").join("ab"))
+            .await
+            .unwrap();
+        let leftover = entries
+            .next_entry()
+            .await
+            .unwrap()
+            .map(|entry| entry.path());
+        assert!(leftover.is_none(), "残留部分写入的文件: {:?}", leftover);
+        assert!(!store.exists("abcdef").await.unwrap());
+
+        store.set_fail_writes(false);
+        assert!(store.put("abcdef", &[7u8; 4096]).await.unwrap());
+        assert_eq!(store.get("abcdef").await.unwrap(), vec![7u8; 4096]);
+    }
+
+    #[tokio::test]
+    async fn test_memory_chunk_store() {
+        exercise(&MemoryChunkStore::new()).await;
+    }
+}
@@ -4,6 +4,9 @@
 //! - 滚动哈希计算
 //! - 弱哈希 + 强哈希双校验
 //! - 边界检测
+//!
+//! 另提供 FastCDC（齿轮哈希 + 归一化分块）与固定大小分块器，
+//! 由 `IncrementalConfig::chunker_type` 选择。
 
 use crate::core::circular_buffer::CircularBuffer;
 use crate::error::Result;
@@ -201,6 +204,12 @@ pub trait Chunker {
     fn chunk(&mut self, data: &[u8]) -> Result<Vec<ChunkInfo>>;
 }
 
+impl Chunker for RabinKarpChunker {
+    fn chunk(&mut self, data: &[u8]) -> Result<Vec<ChunkInfo>> {
+        self.chunk_data(data)
+    }
+}
+
 /// 固定大小分块器
 pub struct FixedSizeChunker {
     chunk_size: usize,
@@ -298,6 +307,130 @@ impl Chunker for FastChunker {
     }
 }
 
+/// FastCDC 齿轮哈希表（固定种子生成，保证各节点分块边界一致）
+const GEAR: [u64; 256] = build_gear_table();
+
+const fn build_gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut i = 0;
+    while i < 256 {
+        // splitmix64
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// FastCDC 分块器（齿轮哈希 + 归一化分块）
+///
+/// 未达到平均大小前使用更严格的掩码，超过后使用更宽松的掩码，
+/// 使分块大小集中在平均值附近。
+pub struct FastCdcChunker {
+    /// 最小分块大小
+    min_chunk_size: usize,
+    /// 平均分块大小
+    avg_chunk_size: usize,
+    /// 最大分块大小
+    max_chunk_size: usize,
+    /// 严格掩码（平均大小之前）
+    mask_s: u64,
+    /// 宽松掩码（平均大小之后）
+    mask_l: u64,
+}
+
+impl FastCdcChunker {
+    /// 创建分块器
+    ///
+    /// chunk_size 为平均分块大小，边界与 RabinKarp 一致:
+    /// - min_chunk_size = chunk_size / 2
+    /// - max_chunk_size = chunk_size * 2
+    pub fn new(chunk_size: usize) -> Self {
+        let avg_chunk_size = chunk_size.max(64);
+        let bits = avg_chunk_size.ilog2();
+
+        Self {
+            min_chunk_size: avg_chunk_size / 2,
+            avg_chunk_size,
+            max_chunk_size: avg_chunk_size * 2,
+            mask_s: high_bits_mask(bits + 1),
+            mask_l: high_bits_mask(bits - 1),
+        }
+    }
+
+    /// 查找下一个分块边界，返回 (分块长度, 边界处的齿轮哈希)
+    fn cut_point(&self, data: &[u8]) -> (usize, u64) {
+        if data.len() <= self.min_chunk_size {
+            return (data.len(), 0);
+        }
+
+        let normal = data.len().min(self.avg_chunk_size);
+        let max = data.len().min(self.max_chunk_size);
+        let mut hash = 0u64;
+        let mut i = self.min_chunk_size;
+
+        while i < normal {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & self.mask_s == 0 {
+                return (i + 1, hash);
+            }
+            i += 1;
+        }
+        while i < max {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & self.mask_l == 0 {
+                return (i + 1, hash);
+            }
+            i += 1;
+        }
+
+        (max, hash)
+    }
+}
+
+impl Chunker for FastCdcChunker {
+    fn chunk(&mut self, data: &[u8]) -> Result<Vec<ChunkInfo>> {
+        let mut chunks = Vec::new();
+        let mut offset = 0usize;
+
+        while offset < data.len() {
+            let (size, hash) = self.cut_point(&data[offset..]);
+            let chunk = &data[offset..offset + size];
+
+            let mut hasher = Sha256::new();
+            hasher.update(chunk);
+            let strong_hash = hex::encode(hasher.finalize());
+
+            chunks.push(ChunkInfo {
+                chunk_id: strong_hash.clone(),
+                offset,
+                size,
+                // 边界判定只使用高位，低 32 位作为去重预过滤的弱哈希
+                weak_hash: hash as u32,
+                strong_hash,
+                compression: crate::core::compression::CompressionAlgorithm::None,
+            });
+
+            offset += size;
+        }
+
+        Ok(chunks)
+    }
+}
+
+/// 高 `bits` 位为 1 的掩码
+fn high_bits_mask(bits: u32) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        u64::MAX << (64 - bits.min(64))
+    }
+}
+
 /// 计算多项式的幂
 fn calculate_power(base: u64, exp: usize) -> u64 {
     let mut result = 1u64;
@@ -348,6 +481,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fastcdc_chunker() {
+        let chunk_size = 4096;
+        let data: Vec<u8> = (0..256 * 1024u64)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let mut chunker = FastCdcChunker::new(chunk_size);
+        let chunks = chunker.chunk(&data).unwrap();
+
+        // 分块覆盖全部数据且大小在边界内（最后一块除外）
+        assert_eq!(chunks.iter().map(|c| c.size).sum::<usize>(), data.len());
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.size >= chunk_size / 2);
+            assert!(chunk.size <= chunk_size * 2);
+        }
+
+        // 头部插入数据后，后续分块边界重新对齐
+        let mut shifted = b"prefix".to_vec();
+        shifted.extend_from_slice(&data);
+        let shifted_chunks = chunker.chunk(&shifted).unwrap();
+        let original: std::collections::HashSet<_> =
+            chunks.iter().map(|c| c.chunk_id.clone()).collect();
+        let shared = shifted_chunks
+            .iter()
+            .filter(|c| original.contains(&c.chunk_id))
+            .count();
+        assert!(shared >= chunks.len() - 2);
+    }
+
     #[test]
     fn test_calculate_power() {
         assert_eq!(calculate_power(2, 0), 1);
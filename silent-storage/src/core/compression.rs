@@ -1,7 +1,7 @@
 //! 数据压缩模块
 //!
 //! 支持LZ4和Zstd压缩算法，提供：
-//! - 多种压缩算法选择
+//! - 多种压缩算法选择（含按采样自动选择）
 //! - 压缩比监控
 //! - 性能优化
 //! - 冷数据自动压缩
@@ -20,6 +20,63 @@ pub enum CompressionAlgorithm {
     LZ4,
     /// Zstd压缩（高压缩比）
     Zstd,
+    /// 自动选择：按数据采样比较 LZ4 与 Zstd 的压缩效果，逐块选择
+    ///
+    /// 仅用于配置，块上记录的始终是实际使用的算法。
+    Auto,
+}
+
+/// 自动选择时的采样大小（字节）
+const AUTO_SAMPLE_SIZE: usize = 16 * 1024;
+
+/// 自动选择时 Zstd 相对 LZ4 的最小压缩比收益
+///
+/// Zstd 压缩耗时明显高于 LZ4，只有压缩比至少高出 10% 时才值得付出额外的 CPU。
+const AUTO_ZSTD_MIN_GAIN: f32 = 1.1;
+
+/// 估算熵时的采样窗口数与窗口大小（字节）
+///
+/// 数据不超过 `ENTROPY_SAMPLE_WINDOWS × ENTROPY_SAMPLE_WINDOW` 时统计全部字节，
+/// 否则均匀取若干窗口，估算开销与块大小无关。
+const ENTROPY_SAMPLE_WINDOWS: usize = 16;
+const ENTROPY_SAMPLE_WINDOW: usize = 256;
+
+/// 按采样字节直方图估算数据的香农熵（比特/字节，0~8）
+///
+/// 只反映字节分布：接近 8 的数据（加密、已压缩或随机数据）基本无法再压缩。
+/// 采样位置固定，相同数据总是得到相同的估算值。
+pub fn estimate_entropy(data: &[u8]) -> f32 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let mut histogram = [0u32; 256];
+    let mut count = |bytes: &[u8]| {
+        for &b in bytes {
+            histogram[b as usize] += 1;
+        }
+    };
+    let sample_size = ENTROPY_SAMPLE_WINDOWS * ENTROPY_SAMPLE_WINDOW;
+    if data.len() <= sample_size {
+        count(data);
+    } else {
+        let stride = (data.len() - ENTROPY_SAMPLE_WINDOW) / (ENTROPY_SAMPLE_WINDOWS - 1);
+        for i in 0..ENTROPY_SAMPLE_WINDOWS {
+            let start = i * stride;
+            count(&data[start..start + ENTROPY_SAMPLE_WINDOW]);
+        }
+    }
+
+    let total: u32 = histogram.iter().sum();
+    let total = total as f32;
+    histogram
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f32 / total;
+            -p * p.log2()
+        })
+        .sum()
 }
 
 /// 压缩配置
@@ -70,19 +127,45 @@ pub struct CompressionResult {
 /// 压缩器
 pub struct Compressor {
     config: CompressionConfig,
+    /// 熵上限（比特/字节），估算熵超过该值的数据直接不压缩
+    max_entropy: Option<f32>,
 }
 
 impl Compressor {
     pub fn new(config: CompressionConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            max_entropy: None,
+        }
+    }
+
+    /// 按熵跳过压缩：估算熵（见 [`estimate_entropy`]）超过 `max_entropy` 的数据
+    /// 不尝试压缩，直接记录为 [`CompressionAlgorithm::None`]
+    ///
+    /// 混合内容的文件中各块的可压缩性差别很大，先用字节直方图筛掉高熵块，
+    /// 避免把 CPU 浪费在压不动的数据上。`max_entropy` 不小于 8 时不跳过任何数据。
+    pub fn with_max_entropy(mut self, max_entropy: f32) -> Self {
+        self.max_entropy = (max_entropy < 8.0).then_some(max_entropy);
+        self
+    }
+
+    /// 数据的估算熵是否超过上限（不值得尝试压缩）
+    pub fn is_high_entropy(&self, data: &[u8]) -> bool {
+        self.max_entropy
+            .is_some_and(|max| estimate_entropy(data) > max)
+    }
+
+    /// 配置的压缩算法
+    pub fn algorithm(&self) -> CompressionAlgorithm {
+        self.config.algorithm
     }
 
     /// 压缩数据
     pub fn compress(&self, data: &[u8]) -> Result<CompressionResult> {
         let start = std::time::Instant::now();
 
-        // 检查是否需要压缩
-        if data.len() < self.config.min_size {
+        // 检查是否需要压缩：过小或高熵的数据不压缩
+        if data.len() < self.config.min_size || self.is_high_entropy(data) {
             return Ok(CompressionResult {
                 original_size: data.len() as u64,
                 compressed_size: data.len() as u64,
@@ -103,6 +186,29 @@ impl Compressor {
                 let compressed = compress_zstd(data, self.config.level)?;
                 (compressed, CompressionAlgorithm::Zstd)
             }
+            CompressionAlgorithm::Auto => {
+                // 选择只取决于采样结果，整块不再按压缩比回退，
+                // 保证相同数据总是得到相同的算法（见 `select_algorithm`）
+                let algorithm = self.select_algorithm(data)?;
+                let compressed = match algorithm {
+                    CompressionAlgorithm::LZ4 => compress_lz4(data, self.config.level)?,
+                    CompressionAlgorithm::Zstd => compress_zstd(data, self.config.level)?,
+                    _ => data.to_vec(),
+                };
+                let ratio = if compressed.is_empty() {
+                    1.0
+                } else {
+                    data.len() as f32 / compressed.len() as f32
+                };
+                return Ok(CompressionResult {
+                    original_size: data.len() as u64,
+                    compressed_size: compressed.len() as u64,
+                    ratio,
+                    duration_ms: start.elapsed().as_millis() as u64,
+                    algorithm,
+                    compressed_data: compressed,
+                });
+            }
         };
 
         let duration = start.elapsed();
@@ -135,12 +241,40 @@ impl Compressor {
         })
     }
 
+    /// 自动模式下为数据选择压缩算法
+    ///
+    /// 取数据开头的一段样本分别用 LZ4 与 Zstd 压缩：
+    /// 两者都达不到 `min_ratio` 时不压缩；Zstd 压缩比超出 LZ4 的
+    /// [`AUTO_ZSTD_MIN_GAIN`] 倍时选 Zstd，否则选更快的 LZ4。
+    /// 结果只取决于数据内容，块已存在时可据此还原写入时使用的算法。
+    pub fn select_algorithm(&self, data: &[u8]) -> Result<CompressionAlgorithm> {
+        if data.len() < self.config.min_size || data.is_empty() || self.is_high_entropy(data) {
+            return Ok(CompressionAlgorithm::None);
+        }
+
+        let sample = &data[..data.len().min(AUTO_SAMPLE_SIZE)];
+        let lz4_ratio = sample.len() as f32 / compress_lz4(sample, self.config.level)?.len() as f32;
+        let zstd_ratio =
+            sample.len() as f32 / compress_zstd(sample, self.config.level)?.len() as f32;
+
+        if lz4_ratio.max(zstd_ratio) < self.config.min_ratio {
+            Ok(CompressionAlgorithm::None)
+        } else if zstd_ratio >= lz4_ratio * AUTO_ZSTD_MIN_GAIN {
+            Ok(CompressionAlgorithm::Zstd)
+        } else {
+            Ok(CompressionAlgorithm::LZ4)
+        }
+    }
+
     /// 解压缩数据
     pub fn decompress(&self, data: &[u8], algorithm: CompressionAlgorithm) -> Result<Vec<u8>> {
         match algorithm {
             CompressionAlgorithm::None => Ok(data.to_vec()),
             CompressionAlgorithm::LZ4 => decompress_lz4(data),
             CompressionAlgorithm::Zstd => decompress_zstd(data),
+            CompressionAlgorithm::Auto => Err(StorageError::Storage(
+                "Auto 不是实际的压缩算法，无法解压".to_string(),
+            )),
         }
     }
 
@@ -508,4 +642,95 @@ mod tests {
             data
         );
     }
+
+    /// 确定性的伪随机序列（xorshift）
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    fn sample_random(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..len).map(|_| xorshift(&mut state) as u8).collect()
+    }
+
+    fn sample_text(len: usize) -> Vec<u8> {
+        const WORDS: [&str; 8] = [
+            "storage ", "chunk ", "version ", "delta ", "index ", "file ", "sync ", "node ",
+        ];
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut text = Vec::with_capacity(len + 16);
+        while text.len() < len {
+            text.extend_from_slice(WORDS[(xorshift(&mut state) % 8) as usize].as_bytes());
+        }
+        text.truncate(len);
+        text
+    }
+
+    #[test]
+    fn test_auto_selects_zstd_for_compressible_and_raw_for_random() {
+        let config = CompressionConfig {
+            algorithm: CompressionAlgorithm::Auto,
+            level: 3,
+            min_size: 0,
+            auto_compress_days: 0,
+            min_ratio: 1.1,
+        };
+        let compressor = Compressor::new(config);
+
+        // 小词表随机组成的文本：熵编码让 Zstd 压缩比远高于 LZ4
+        let text = sample_text(64 * 1024);
+        let result = compressor.compress(&text).unwrap();
+        assert_eq!(result.algorithm, CompressionAlgorithm::Zstd);
+        assert!(result.compressed_size < result.original_size);
+        assert_eq!(
+            compressor
+                .decompress(&result.compressed_data, result.algorithm)
+                .unwrap(),
+            text
+        );
+
+        // 伪随机数据：两种算法都无法压缩，原样存储
+        let random = sample_random(64 * 1024);
+        let result = compressor.compress(&random).unwrap();
+        assert_eq!(result.algorithm, CompressionAlgorithm::None);
+        assert_eq!(result.compressed_data, random);
+
+        // 选择结果只取决于数据内容
+        assert_eq!(
+            compressor.select_algorithm(&text).unwrap(),
+            CompressionAlgorithm::Zstd
+        );
+        assert!(
+            compressor
+                .decompress(&result.compressed_data, CompressionAlgorithm::Auto)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_high_entropy_data_skips_compression() {
+        let random = sample_random(64 * 1024);
+        let text = sample_text(64 * 1024);
+        assert!(estimate_entropy(&random) > 7.9);
+        assert!(estimate_entropy(&text) < 4.5);
+        assert_eq!(estimate_entropy(&[0u8; 4096]), 0.0);
+        assert_eq!(estimate_entropy(&[]), 0.0);
+
+        let compressor = Compressor::new(CompressionConfig::default()).with_max_entropy(7.5);
+        assert!(compressor.is_high_entropy(&random));
+        assert!(!compressor.is_high_entropy(&text));
+
+        let result = compressor.compress(&random).unwrap();
+        assert_eq!(result.algorithm, CompressionAlgorithm::None);
+        assert_eq!(result.compressed_data, random);
+        let result = compressor.compress(&text).unwrap();
+        assert_eq!(result.algorithm, CompressionAlgorithm::LZ4);
+
+        // 上限不小于 8 时不按熵跳过
+        let compressor = Compressor::new(CompressionConfig::default()).with_max_entropy(8.0);
+        assert!(!compressor.is_high_entropy(&random));
+    }
 }
@@ -3,7 +3,10 @@
 //! 该模块实现增量更新的差异生成和应用功能
 
 use crate::error::{Result, StorageError};
-use crate::{FileDelta, IncrementalConfig, RabinKarpChunker};
+use crate::{
+    ChunkInfo, Chunker, ChunkerType, FastCdcChunker, FileDelta, FixedSizeChunker,
+    IncrementalConfig, RabinKarpChunker,
+};
 use chrono::Local;
 use sha2::Digest;
 use std::collections::HashMap;
@@ -12,16 +15,20 @@ use std::collections::HashMap;
 pub struct DeltaGenerator {
     #[allow(dead_code)]
     config: IncrementalConfig,
-    chunker: RabinKarpChunker,
+    chunker: Box<dyn Chunker + Send>,
 }
 
 impl DeltaGenerator {
     /// 创建差异生成器
     ///
     /// chunk_size: 目标分块大小
-    /// config: 增量存储配置
+    /// config: 增量存储配置（按 `chunker_type` 选择分块算法）
     pub fn new(chunk_size: usize, config: IncrementalConfig) -> Self {
-        let chunker = RabinKarpChunker::new(chunk_size, &config);
+        let chunker: Box<dyn Chunker + Send> = match config.chunker_type {
+            ChunkerType::Fixed => Box::new(FixedSizeChunker::new(chunk_size)),
+            ChunkerType::RabinKarp => Box::new(RabinKarpChunker::new(chunk_size, &config)),
+            ChunkerType::FastCdc => Box::new(FastCdcChunker::new(chunk_size)),
+        };
         Self { config, chunker }
     }
 
@@ -51,7 +58,7 @@ impl DeltaGenerator {
         // 对新数据分块
         let chunks = self
             .chunker
-            .chunk_data(new_data)
+            .chunk(new_data)
             .map_err(|e| StorageError::Storage(format!("分块失败: {}", e)))?;
 
         Ok(FileDelta {
@@ -65,7 +72,12 @@ impl DeltaGenerator {
 
     /// 生成完整版本的差异（从空文件开始）
     pub fn generate_full_delta(&mut self, data: &[u8], file_id: &str) -> Result<FileDelta> {
-        self.generate_delta(&[], data, file_id, "")
+        let delta = self.generate_delta(&[], data, file_id, "")?;
+        debug_assert!(
+            delta.is_contiguous(data.len()),
+            "完整快照的块必须按偏移连续覆盖整个文件"
+        );
+        Ok(delta)
     }
 
     /// 比较两个版本的差异
@@ -120,11 +132,13 @@ impl DeltaApplier {
             base_data.unwrap_or(&[]).to_vec()
         };
 
-        // 重建文件：应用所有分块
+        // 重建文件：按偏移顺序应用所有分块，不依赖块列表的存储顺序
+        let mut chunks: Vec<&ChunkInfo> = delta.chunks.iter().collect();
+        chunks.sort_by_key(|c| c.offset);
         let mut result = Vec::new();
         let mut base_pos = 0usize;
 
-        for chunk in &delta.chunks {
+        for chunk in chunks {
             // 复制基础数据中到当前块偏移量的部分
             if chunk.offset > base_pos {
                 let copy_len = chunk.offset - base_pos;
@@ -255,6 +269,34 @@ impl FileDelta {
     pub fn is_empty(&self) -> bool {
         self.chunks.is_empty()
     }
+
+    /// 检查块是否按偏移排序且首尾相接地覆盖 `[0, file_size)`
+    ///
+    /// 完整快照（`generate_full_delta` 的结果及写入块后的列表）必须满足该不变量；
+    /// 读取路径始终按 `offset` 定位块，不依赖块列表的顺序。
+    pub fn is_contiguous(&self, file_size: usize) -> bool {
+        chunks_are_contiguous(&self.chunks, file_size)
+    }
+
+    /// 块是否覆盖整个文件（完整快照，重建时无需再叠加父版本数据）
+    ///
+    /// 与 [`is_contiguous`](Self::is_contiguous) 不同，只比较块的总大小，不要求块按偏移排序。
+    pub fn is_full_snapshot(&self, file_size: u64) -> bool {
+        let covered: u64 = self.chunks.iter().map(|c| c.size as u64).sum();
+        covered >= file_size
+    }
+}
+
+/// 检查块列表是否按偏移排序且首尾相接地覆盖 `[0, file_size)`
+pub(crate) fn chunks_are_contiguous(chunks: &[ChunkInfo], file_size: usize) -> bool {
+    let mut expected_offset = 0;
+    for chunk in chunks {
+        if chunk.offset != expected_offset {
+            return false;
+        }
+        expected_offset += chunk.size;
+    }
+    expected_offset == file_size
 }
 
 #[cfg(test)]
@@ -375,4 +417,35 @@ mod tests {
 
         assert!(empty_delta.is_empty());
     }
+
+    #[test]
+    fn test_full_delta_chunks_are_contiguous() {
+        let data = crate::test_util::test_data(64 * 1024, 0);
+        let mut generator = DeltaGenerator::new(4096, IncrementalConfig::default());
+        let mut delta = generator.generate_full_delta(&data, "test_file").unwrap();
+        assert!(delta.chunks.len() > 1);
+        assert!(delta.is_contiguous(data.len()));
+        assert!(!delta.is_contiguous(data.len() + 1));
+
+        let chunks: HashMap<String, Vec<u8>> = delta
+            .chunks
+            .iter()
+            .map(|c| {
+                (
+                    c.chunk_id.clone(),
+                    data[c.offset..c.offset + c.size].to_vec(),
+                )
+            })
+            .collect();
+
+        // 打乱块顺序后按偏移重建，结果不变
+        delta.chunks.reverse();
+        assert!(!delta.is_contiguous(data.len()));
+        assert!(delta.is_full_snapshot(data.len() as u64));
+        assert!(!delta.is_full_snapshot(data.len() as u64 + 1));
+        let result = create_test_applier()
+            .apply_delta(None, &delta, |id| Ok(chunks[id].clone()))
+            .unwrap();
+        assert_eq!(result, data);
+    }
 }
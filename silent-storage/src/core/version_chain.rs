@@ -7,6 +7,9 @@ use crate::error::{Result, StorageError};
 use crate::{ChunkInfo, FileDelta, VersionInfo};
 use std::collections::HashMap;
 
+/// 可回溯的最大版本链深度（超过视为循环引用）
+pub const MAX_VERSION_CHAIN_DEPTH: usize = 100;
+
 /// 版本链深度配置
 #[derive(Debug, Clone)]
 pub struct VersionChainConfig {
@@ -70,10 +73,11 @@ impl VersionChainManager {
                 depth += 1;
 
                 // 防止无限循环（检测环）
-                if depth > 100 {
-                    return Err(StorageError::Storage(
-                        "版本链深度超过100层，可能存在循环引用".to_string(),
-                    ));
+                if depth > MAX_VERSION_CHAIN_DEPTH {
+                    return Err(StorageError::Storage(format!(
+                        "版本链深度超过{}层，可能存在循环引用",
+                        MAX_VERSION_CHAIN_DEPTH
+                    )));
                 }
             } else {
                 break;
@@ -212,6 +216,9 @@ mod tests {
             storage_size: 500,
             created_at: Local::now().naive_local(),
             is_current: version_id == "v5",
+            author: None,
+            comment: None,
+            compression: None,
         }
     }
 
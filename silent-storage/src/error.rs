@@ -6,6 +6,18 @@ pub enum StorageError {
     #[error("文件未找到: {0}")]
     FileNotFound(String),
 
+    #[error("版本未找到: {0}")]
+    VersionNotFound(String),
+
+    #[error("超出存储配额: {0}")]
+    QuotaExceeded(String),
+
+    #[error("文件超过上传大小限制: {0}")]
+    FileTooLarge(String),
+
+    #[error("校验和不匹配: {0}")]
+    ChecksumMismatch(String),
+
     #[error("存储错误: {0}")]
     Storage(String),
 
@@ -39,6 +51,36 @@ pub enum StorageError {
     #[error("数据库错误: {0}")]
     Database(String),
 
+    #[error("磁盘空间不足: {0}")]
+    OutOfSpace(String),
+
+    #[error("只读模式，拒绝写操作: {0}")]
+    ReadOnly(String),
+
+    #[error("维护模式，拒绝写操作: {0}")]
+    Maintenance(String),
+
+    #[error("存储繁忙，请稍后重试: {0}")]
+    Busy(String),
+
+    #[error("文件处于保留期，拒绝修改: {0}")]
+    RetentionLocked(String),
+
+    #[error("前置条件不满足: {0}")]
+    PreconditionFailed(String),
+
+    #[error("当前节点不是 GC 主节点: {0}")]
+    NotLeader(String),
+
+    #[error("别名指向的文件不存在: {0}")]
+    DanglingAlias(String),
+
+    #[error("保留路径，不能通过全局接口访问: {0}")]
+    ReservedPath(String),
+
+    #[error("文件优化未完成，缺少块: {0}")]
+    IncompleteOptimization(String),
+
     #[error("IO错误: {0}")]
     Io(#[from] std::io::Error),
 
@@ -46,5 +88,50 @@ pub enum StorageError {
     Serialization(#[from] serde_json::Error),
 }
 
+impl StorageError {
+    /// 稳定的错误码（供 API 客户端区分错误类型，不随错误消息变化）
+    pub fn code(&self) -> &'static str {
+        match self {
+            StorageError::FileNotFound(_) => "FILE_NOT_FOUND",
+            StorageError::VersionNotFound(_) => "VERSION_NOT_FOUND",
+            StorageError::QuotaExceeded(_) => "QUOTA_EXCEEDED",
+            StorageError::FileTooLarge(_) => "FILE_TOO_LARGE",
+            StorageError::ChecksumMismatch(_) => "CHECKSUM_MISMATCH",
+            StorageError::Storage(_) => "STORAGE_ERROR",
+            StorageError::Metadata(_) => "METADATA_ERROR",
+            StorageError::Chunk(_) => "CHUNK_ERROR",
+            StorageError::Dedup(_) => "DEDUP_ERROR",
+            StorageError::Compression(_) => "COMPRESSION_ERROR",
+            StorageError::Index(_) => "INDEX_ERROR",
+            StorageError::Tiering(_) => "TIERING_ERROR",
+            StorageError::Lifecycle(_) => "LIFECYCLE_ERROR",
+            StorageError::Delta(_) => "DELTA_ERROR",
+            StorageError::Config(_) => "CONFIG_ERROR",
+            StorageError::Database(_) => "DATABASE_ERROR",
+            StorageError::OutOfSpace(_) => "OUT_OF_SPACE",
+            StorageError::ReadOnly(_) => "READ_ONLY",
+            StorageError::Maintenance(_) => "MAINTENANCE",
+            StorageError::Busy(_) => "BUSY",
+            StorageError::RetentionLocked(_) => "RETENTION_LOCKED",
+            StorageError::PreconditionFailed(_) => "PRECONDITION_FAILED",
+            StorageError::NotLeader(_) => "NOT_LEADER",
+            StorageError::DanglingAlias(_) => "DANGLING_ALIAS",
+            StorageError::ReservedPath(_) => "RESERVED_PATH",
+            StorageError::IncompleteOptimization(_) => "INCOMPLETE_OPTIMIZATION",
+            StorageError::Io(_) => "IO_ERROR",
+            StorageError::Serialization(_) => "SERIALIZATION_ERROR",
+        }
+    }
+
+    /// 转换 IO 错误，磁盘空间不足（ENOSPC）时返回 `OutOfSpace`
+    pub fn from_io(e: std::io::Error, context: &str) -> Self {
+        if e.kind() == std::io::ErrorKind::StorageFull {
+            StorageError::OutOfSpace(format!("{}: {}", context, e))
+        } else {
+            StorageError::Io(e)
+        }
+    }
+}
+
 /// Result 类型别名
 pub type Result<T> = std::result::Result<T, StorageError>;
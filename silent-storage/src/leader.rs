@@ -0,0 +1,326 @@
+//! GC 主节点选举
+//!
+//! 多个节点共享块存储时，只允许一个节点执行全局 GC 与孤儿块清理，
+//! 避免两个节点同时删除或写入同一个块。选举基于共享目录中的租约文件：
+//!
+//! - 租约文件按代数命名（`gc-lease.<代数>`），代数最大的文件为当前租约；
+//! - 持有者在租约有效期内原地续约（写临时文件后 rename 覆盖）；
+//! - 租约过期后，其他节点以硬链接创建下一代租约文件接管，
+//!   同一代只有一个节点能创建成功，因此同一时刻至多一个主节点。
+//!
+//! 过期判断依赖各节点的系统时钟，节点间时钟偏差应远小于租约有效期。
+//! 非主节点照常处理读写，只跳过破坏性的后台任务。
+//!
+//! 耗时可能超过租约有效期的任务（如 GC）以租约代数做隔离：开始时记下代数，
+//! 每批删除前调用 [`GcLease::acquire`] 续约并确认代数未变，否则立即中止。
+
+use crate::error::{Result, StorageError};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+/// 租约文件名前缀
+const LEASE_PREFIX: &str = "gc-lease.";
+
+/// GC 主节点选举配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GcElectionConfig {
+    /// 是否启用选举（多个节点共享块存储时启用，单节点无需启用）
+    pub enable: bool,
+    /// 本节点标识，为空时启动时随机生成
+    pub node_id: String,
+    /// 租约文件目录，必须位于所有节点共享的存储上；`None` 时使用块存储目录
+    pub lease_dir: Option<PathBuf>,
+    /// 租约有效期（秒），主节点失联超过该时长后由其他节点接管
+    pub lease_ttl_secs: u64,
+}
+
+impl Default for GcElectionConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            node_id: String::new(),
+            lease_dir: None,
+            lease_ttl_secs: 60,
+        }
+    }
+}
+
+impl GcElectionConfig {
+    /// 校验配置
+    pub fn validate(&self) -> Result<()> {
+        if self.enable && self.lease_ttl_secs == 0 {
+            return Err(StorageError::Config(
+                "启用 GC 选举时 lease_ttl_secs 必须大于 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 租约记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaseRecord {
+    /// 持有者节点标识
+    pub holder: String,
+    /// 租约代数
+    pub generation: u64,
+    /// 过期时间（Unix 毫秒）
+    pub expires_at: i64,
+}
+
+/// 基于共享目录租约文件的 GC 主节点选举
+#[derive(Debug)]
+pub struct GcLease {
+    dir: PathBuf,
+    node_id: String,
+    ttl_ms: i64,
+}
+
+impl GcLease {
+    pub fn new(dir: PathBuf, node_id: impl Into<String>, ttl_secs: u64) -> Self {
+        Self {
+            dir,
+            node_id: node_id.into(),
+            ttl_ms: (ttl_secs as i64).saturating_mul(1000),
+        }
+    }
+
+    /// 本节点标识
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// 租约有效期
+    pub fn ttl(&self) -> Duration {
+        Duration::from_millis(self.ttl_ms.max(0) as u64)
+    }
+
+    /// 尝试获取或续约租约，返回本节点当前是否为主节点
+    pub async fn try_acquire(&self) -> Result<bool> {
+        Ok(self.acquire().await?.is_some())
+    }
+
+    /// 尝试获取或续约租约，本节点为主节点时返回持有的租约代数
+    ///
+    /// 租约曾过期并被其他节点接管后，即使本节点再次获取，代数也已改变。
+    pub async fn acquire(&self) -> Result<Option<u64>> {
+        fs::create_dir_all(&self.dir).await?;
+        let now = Utc::now().timestamp_millis();
+
+        let next_generation = match self.current().await? {
+            Some(record) if record.expires_at > now => {
+                if record.holder != self.node_id {
+                    return Ok(None);
+                }
+                // 续约：只有持有者会在有效期内改写本代租约文件
+                let renewed = LeaseRecord {
+                    expires_at: now + self.ttl_ms,
+                    ..record
+                };
+                self.replace(&renewed).await?;
+                return Ok(Some(renewed.generation));
+            }
+            Some(record) => record.generation + 1,
+            None => 1,
+        };
+
+        // 接管：创建下一代租约文件，同一代只有一个节点能成功
+        let record = LeaseRecord {
+            holder: self.node_id.clone(),
+            generation: next_generation,
+            expires_at: now + self.ttl_ms,
+        };
+        if !self.create(&record).await? {
+            debug!("GC 租约第 {} 代已被其他节点获取", next_generation);
+            return Ok(None);
+        }
+
+        // 观察到的租约可能已过时（更新的代数已被创建并清理了旧文件），以最新一代为准
+        if self.current().await?.as_ref() != Some(&record) {
+            let _ = fs::remove_file(self.lease_path(next_generation)).await;
+            return Ok(None);
+        }
+        self.prune(next_generation).await;
+        info!(
+            "节点 {} 成为 GC 主节点（租约第 {} 代）",
+            self.node_id, next_generation
+        );
+        Ok(Some(next_generation))
+    }
+
+    /// 释放租约（仅在本节点持有时生效）
+    ///
+    /// 将租约标记为立即过期而不是删除文件，保证代数单调递增。
+    pub async fn release(&self) -> Result<()> {
+        if let Some(record) = self.current().await?
+            && record.holder == self.node_id
+        {
+            let released = LeaseRecord {
+                expires_at: Utc::now().timestamp_millis(),
+                ..record
+            };
+            self.replace(&released).await?;
+            info!("节点 {} 释放 GC 租约", self.node_id);
+        }
+        Ok(())
+    }
+
+    /// 读取当前（代数最大的）租约
+    pub async fn current(&self) -> Result<Option<LeaseRecord>> {
+        let Some(generation) = self.generations().await?.into_iter().max() else {
+            return Ok(None);
+        };
+        match fs::read(self.lease_path(generation)).await {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            // 读取前被接管者清理，视为该代已过期
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Some(LeaseRecord {
+                holder: String::new(),
+                generation,
+                expires_at: 0,
+            })),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 列出目录中所有租约文件的代数
+    async fn generations(&self) -> Result<Vec<u64>> {
+        let mut generations = Vec::new();
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(generations),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(generation) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(LEASE_PREFIX))
+                .and_then(|suffix| suffix.parse::<u64>().ok())
+            {
+                generations.push(generation);
+            }
+        }
+        Ok(generations)
+    }
+
+    fn lease_path(&self, generation: u64) -> PathBuf {
+        self.dir.join(format!("{}{:020}", LEASE_PREFIX, generation))
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        self.dir.join(format!(
+            ".gc-lease-{}-{}.tmp",
+            self.node_id,
+            scru128::new_string()
+        ))
+    }
+
+    /// 以硬链接原子创建租约文件，目标已存在时返回 `false`
+    async fn create(&self, record: &LeaseRecord) -> Result<bool> {
+        let temp = self.temp_path();
+        fs::write(&temp, serde_json::to_vec(record)?).await?;
+        let result = fs::hard_link(&temp, self.lease_path(record.generation)).await;
+        let _ = fs::remove_file(&temp).await;
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 原子覆盖租约文件
+    async fn replace(&self, record: &LeaseRecord) -> Result<()> {
+        let temp = self.temp_path();
+        fs::write(&temp, serde_json::to_vec(record)?).await?;
+        fs::rename(&temp, self.lease_path(record.generation)).await?;
+        Ok(())
+    }
+
+    /// 清理早于指定代数的租约文件
+    async fn prune(&self, current: u64) {
+        let Ok(generations) = self.generations().await else {
+            return;
+        };
+        for generation in generations.into_iter().filter(|g| *g < current) {
+            if let Err(e) = fs::remove_file(self.lease_path(generation)).await
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                warn!("清理过期 GC 租约失败: 第 {} 代: {}", generation, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_single_leader_and_takeover() {
+        let dir = TempDir::new().unwrap();
+        let a = GcLease::new(dir.path().to_path_buf(), "node-a", 60);
+        let b = GcLease::new(dir.path().to_path_buf(), "node-b", 60);
+
+        assert!(a.try_acquire().await.unwrap());
+        assert!(!b.try_acquire().await.unwrap());
+        // 持有者续约
+        assert!(a.try_acquire().await.unwrap());
+        assert_eq!(a.current().await.unwrap().unwrap().generation, 1);
+
+        // 释放后由其他节点接管，代数递增
+        a.release().await.unwrap();
+        assert!(b.try_acquire().await.unwrap());
+        assert!(!a.try_acquire().await.unwrap());
+        let current = b.current().await.unwrap().unwrap();
+        assert_eq!(current.holder, "node-b");
+        assert_eq!(current.generation, 2);
+        assert_eq!(b.generations().await.unwrap(), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_generation_changes_after_takeover() {
+        let dir = TempDir::new().unwrap();
+        let a = GcLease::new(dir.path().to_path_buf(), "node-a", 60);
+        let b = GcLease::new(dir.path().to_path_buf(), "node-b", 60);
+
+        // 续约不改变代数
+        let generation = a.acquire().await.unwrap().unwrap();
+        assert_eq!(a.acquire().await.unwrap(), Some(generation));
+
+        // 租约过期后被接管：原持有者再次获取时代数已变
+        a.release().await.unwrap();
+        assert_eq!(b.acquire().await.unwrap(), Some(generation + 1));
+        b.release().await.unwrap();
+        let regained = a.acquire().await.unwrap().unwrap();
+        assert_ne!(regained, generation);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_takeover_elects_one() {
+        let dir = TempDir::new().unwrap();
+        let leases: Vec<GcLease> = (0..8)
+            .map(|i| GcLease::new(dir.path().to_path_buf(), format!("node-{}", i), 60))
+            .collect();
+
+        let results = futures::future::join_all(leases.iter().map(|l| l.try_acquire())).await;
+        let leaders = results.into_iter().filter(|r| *r.as_ref().unwrap()).count();
+        assert_eq!(leaders, 1);
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_ttl() {
+        let config = GcElectionConfig {
+            enable: true,
+            lease_ttl_secs: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        assert!(GcElectionConfig::default().validate().is_ok());
+    }
+}
@@ -61,10 +61,17 @@
 //! │   ├── index       # 索引服务
 //! │   ├── lifecycle   # 生命周期管理
 //! │   └── tiering     # 分层存储
+//! ├── archive.rs      # tar/zip 归档导入
+//! ├── bundle.rs       # 单文件导出/导入
 //! ├── cache.rs        # 三级缓存系统
+//! ├── chunk_store.rs  # 块存储后端（本地文件系统/内存）
 //! ├── metadata.rs     # 元数据管理（Sled）
 //! ├── metrics.rs      # Prometheus 指标
+//! ├── namespace.rs    # 多租户命名空间
+//! ├── pack.rs         # 小块打包存储
+//! ├── reader.rs       # 顺序块读取（预取）
 //! ├── reliability.rs  # 可靠性保障
+//! ├── snapshot.rs     # 快照导出/导入
 //! └── storage.rs      # 顶层 API
 //! ```
 //!
@@ -77,20 +84,30 @@
 //! - [`StorageMetrics`] - Prometheus 指标
 
 mod error;
+#[cfg(test)]
+mod test_util;
 
 // ============================================================================
 // 公共模块
 // ============================================================================
 
+pub mod archive;
 pub mod bench;
 pub mod bloom;
+pub mod bundle;
 pub mod cache;
+pub mod chunk_store;
 pub mod core;
+pub mod leader;
 pub mod metadata;
 pub mod metrics;
+pub mod namespace;
 pub mod optimization;
+pub mod pack;
+pub mod reader;
 pub mod reliability;
 pub mod services;
+pub mod snapshot;
 pub mod storage;
 
 // ============================================================================
@@ -103,17 +120,47 @@ pub use storage::StorageManager;
 /// 错误处理
 pub use error::{Result, StorageError};
 
+/// 存储格式版本
+///
+/// 块、版本记录与元数据库的磁盘布局发生不兼容变化时递增，
+/// 迁移与导入导出据此判断两端的数据是否兼容。
+pub const STORAGE_FORMAT_VERSION: u32 = 1;
+
 // ============================================================================
 // 存储类型和统计
 // ============================================================================
 
-pub use storage::{ChunkRefCount, FileIndexEntry, GarbageCollectResult, StorageStats};
+pub use storage::{
+    ChangeOp, ChangeRecord, ChunkRefCount, ChunkingRecommendation, ContentRef, DedupRebuildResult,
+    EraseHook, FileDedupReport, FileIndexEntry, FileStat, GarbageCollectResult,
+    MAX_USER_METADATA_SIZE, MutationEvent, MutationOp, RetentionLock, RetentionMode, StorageStats,
+    UsageSummary,
+};
 
 // ============================================================================
 // 缓存系统
 // ============================================================================
 
-pub use cache::{CacheConfig, CacheManager, CacheStats};
+pub use cache::{CacheConfig, CacheManager, CacheStats, HotDataEviction};
+
+// ============================================================================
+// 块存储后端
+// ============================================================================
+
+pub use chunk_store::{ChunkStore, LocalChunkStore, MemoryChunkStore};
+pub use pack::{PackConfig, PackedChunkStore};
+
+// ============================================================================
+// 元数据数据库
+// ============================================================================
+
+pub use metadata::{MetadataDbConfig, MetadataDbMode};
+
+// ============================================================================
+// GC 主节点选举
+// ============================================================================
+
+pub use leader::{GcElectionConfig, GcLease, LeaseRecord};
 
 // ============================================================================
 // 监控和指标
@@ -129,13 +176,43 @@ pub use optimization::{
     OptimizationScheduler, OptimizationStats, OptimizationStrategy, OptimizationTask,
 };
 
+// ============================================================================
+// 多租户命名空间
+// ============================================================================
+
+pub use namespace::{Namespace, NamespacedStorage};
+
+// ============================================================================
+// 顺序读取
+// ============================================================================
+
+pub use reader::{ChunkAsyncReader, ChunkStreamReader};
+
+// ============================================================================
+// 快照导出/导入
+// ============================================================================
+
+pub use snapshot::{SNAPSHOT_FORMAT_VERSION, SnapshotStats};
+
+// ============================================================================
+// 归档导入
+// ============================================================================
+
+pub use archive::{ArchiveFormat, ArchiveImportStats};
+
+// ============================================================================
+// 单文件导出/导入
+// ============================================================================
+
+pub use bundle::{BUNDLE_FORMAT_VERSION, BundleChunk, FileBundleManifest};
+
 // ============================================================================
 // 可靠性组件
 // ============================================================================
 
 pub use reliability::{
-    ChunkVerifier, ChunkVerifyReport, CleanupReport, OrphanChunkCleaner, WalEntry, WalManager,
-    WalOperation,
+    ChunkVerifier, ChunkVerifyReport, CleanupReport, ExpectedChunk, OrphanChunkCleaner, ScanHandle,
+    ScanOptions, WalEntry, WalManager, WalOperation,
 };
 
 // ============================================================================
@@ -183,6 +260,7 @@ use serde::{Deserialize, Serialize};
 ///
 /// 去重功能已内置于存储策略中，无需单独配置。
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct IncrementalConfig {
     /// 分块算法类型
     pub chunker_type: ChunkerType,
@@ -192,12 +270,198 @@ pub struct IncrementalConfig {
     pub weak_hash_mod: usize,
     /// 启用压缩
     pub enable_compression: bool,
-    /// 压缩算法 (lz4, zstd)
+    /// 压缩算法 (lz4, zstd, auto, none)
+    ///
+    /// `auto` 按块采样比较 LZ4 与 Zstd，选择更合适的算法（或不压缩），
+    /// 整文件压缩存储模式下按 LZ4 处理。
     pub compression_algorithm: String,
+    /// 按文件大小分段选择压缩算法，按顺序匹配第一条 `max_size` 大于文件大小的规则
+    ///
+    /// 为空或没有匹配的规则时使用 `compression_algorithm`。只在后台优化（整文件压缩、
+    /// 分块压缩）时生效；使用的算法随块与文件记录，修改规则不影响已有数据的读取。
+    pub compression_bands: Vec<CompressionBand>,
+    /// 逐块压缩的熵上限（比特/字节，0~8）
+    ///
+    /// 按采样字节直方图估算块的熵，超过该值的块（加密、已压缩、随机数据）不尝试压缩，
+    /// 直接以不压缩形式存储。设为 8 时关闭该判断，每个块都尝试压缩。
+    pub compression_max_entropy: f32,
     /// 启用自动GC
     pub enable_auto_gc: bool,
     /// GC触发间隔（秒）
     pub gc_interval_secs: u64,
+    /// GC 间隔的随机抖动比例（0~1），每次等待 `gc_interval_secs × (1 ± gc_jitter)`，
+    /// 避免多个节点同时执行 GC
+    pub gc_jitter: f64,
+    /// GC 完成后重新计算各文件分摊的实际存储大小（见 `StorageManager::recompute_usage`）
+    pub recompute_usage_after_gc: bool,
+    /// 顺序读取时预取的块数量（0 表示按需读取）
+    #[serde(default = "IncrementalConfig::default_prefetch_chunks")]
+    pub prefetch_chunks: usize,
+    /// 只读副本模式：拒绝所有写操作，不启动 GC 与后台优化
+    #[serde(default)]
+    pub read_only: bool,
+    /// 存储配额（字节），按所有文件（含回收站）的当前大小计算，`None` 表示不限制
+    #[serde(default)]
+    pub quota_bytes: Option<u64>,
+    /// 单个文件的最大上传大小（字节），流式写入超过该大小时中止并返回 `StorageError::FileTooLarge`，
+    /// `None` 表示不限制
+    pub max_upload_size: Option<u64>,
+    /// 临时文件目录（上传会话、快照导入暂存等），`None` 时使用 `<root_path>/tmp`
+    ///
+    /// 启动时会清理其中的过期文件，应指向专用目录而不是 `/tmp` 这类共享目录。
+    pub scratch_dir: Option<std::path::PathBuf>,
+    /// 启动时清理临时目录中超过该时长未修改的残留文件（秒，0 表示不清理）
+    pub scratch_max_age_secs: u64,
+    /// 块数据目录，`None` 时使用 `<root_path>/incremental/chunks`
+    ///
+    /// 与元数据分开存放时，可把块放在大容量磁盘、元数据放在 SSD 上。
+    pub chunk_root: Option<std::path::PathBuf>,
+    /// Sled 元数据数据库目录，`None` 时使用 `<root_path>/incremental/metadata`
+    pub metadata_dir: Option<std::path::PathBuf>,
+    /// 热存储目录（尚未优化的文件），`None` 时使用 `<root_path>/hot`
+    pub hot_dir: Option<std::path::PathBuf>,
+    /// 最大版本链深度，保存后超过该深度时自动压缩版本链（0 表示不限制）
+    pub max_chain_depth: usize,
+    /// 版本链压缩的执行方式
+    pub chain_compaction: ChainCompactionMode,
+    /// 去重索引内存中最多保留的块数量，超出后按 LRU 溢出到 Sled（0 表示不限制）
+    pub max_memory_index: usize,
+    /// 命名空间块 ID 盐值（命名空间名称 -> 盐值）
+    ///
+    /// 配置了盐值的命名空间中，块 ID 由盐值与块数据共同派生，只与同一命名空间内的块去重，
+    /// 其他租户无法通过去重探测文件是否存在。未配置盐值的命名空间与全局文件共享去重。
+    /// 修改盐值只影响之后写入的块，已有版本仍按原块 ID 读取。
+    pub namespace_salts: std::collections::HashMap<String, String>,
+    /// 内容寻址版本 ID：由文件 ID、父版本 ID 与整文件哈希派生，
+    /// 不同节点以相同历史保存相同内容时得到相同的版本 ID
+    pub content_addressed_versions: bool,
+    /// 秒传范围：客户端只提交整文件 SHA-256 时，在哪些已有文件中查找相同内容
+    /// （默认禁用，风险见 [`InstantUploadScope`]）
+    pub instant_upload: InstantUploadScope,
+    /// 上传背压软阈值：优化队列长度超过该值时，每次保存先等待 `backpressure_delay_ms`（0 表示不启用）
+    pub optimization_queue_high_water: usize,
+    /// 上传背压硬上限：优化队列长度达到该值时拒绝保存，返回 `StorageError::Busy`（0 表示不启用）
+    pub optimization_queue_hard_cap: usize,
+    /// 软背压时每次保存的等待时间（毫秒）
+    pub backpressure_delay_ms: u64,
+    /// 达到硬上限时建议客户端重试的间隔（秒），用于 `Retry-After` 响应头
+    pub backpressure_retry_after_secs: u64,
+    /// 后台优化同时执行的最大任务数（分块、哈希与压缩都是 CPU 密集型）
+    pub optimization_concurrency: usize,
+    /// 每个 CPU 的 1 分钟平均负载超过该值时暂停领取新的优化任务（0 表示不限制，仅 Linux 生效）
+    pub optimization_max_load: f64,
+    /// 优化后先由新的存储形式还原文件并校验哈希，通过后才删除热存储文件
+    pub verify_optimization: bool,
+    /// 流式下载时边读取边计算 SHA-256，读完后与记录的整文件哈希比对（见 `StorageManager::open_read`）
+    pub verify_downloads: bool,
+    /// Sled 元数据数据库配置（缓存容量、刷盘间隔、压缩）
+    pub metadata: metadata::MetadataDbConfig,
+    /// GC 主节点选举（多节点共享块存储时只由主节点执行 GC 与孤儿块清理）
+    pub gc_election: leader::GcElectionConfig,
+    /// 小块打包：小块追加到包文件而不是各占一个块文件（仅默认的本地块存储生效）
+    pub packing: pack::PackConfig,
+}
+
+impl IncrementalConfig {
+    fn default_prefetch_chunks() -> usize {
+        4
+    }
+
+    /// 指定大小的文件适用的压缩算法（未启用压缩时为 `None`）
+    pub fn compression_for_size(&self, size: u64) -> core::compression::CompressionAlgorithm {
+        if !self.enable_compression {
+            return core::compression::CompressionAlgorithm::None;
+        }
+        let name = self
+            .compression_bands
+            .iter()
+            .find(|band| band.max_size.is_none_or(|max| size < max))
+            .map_or(self.compression_algorithm.as_str(), |band| {
+                band.algorithm.as_str()
+            });
+        parse_compression_algorithm(name)
+    }
+
+    /// 校验配置组合是否有效
+    ///
+    /// 启动时调用，尽早暴露配置错误，而不是在首次写入时才失败。
+    pub fn validate(&self) -> error::Result<()> {
+        if !matches!(
+            self.compression_algorithm.as_str(),
+            "lz4" | "zstd" | "auto" | "none"
+        ) {
+            return Err(error::StorageError::Config(format!(
+                "未知的压缩算法: {}（可选 lz4, zstd, auto, none）",
+                self.compression_algorithm
+            )));
+        }
+        let mut previous_max = None;
+        for (i, band) in self.compression_bands.iter().enumerate() {
+            if !matches!(band.algorithm.as_str(), "lz4" | "zstd" | "auto" | "none") {
+                return Err(error::StorageError::Config(format!(
+                    "compression_bands 中未知的压缩算法: {}（可选 lz4, zstd, auto, none）",
+                    band.algorithm
+                )));
+            }
+            match band.max_size {
+                Some(max) if previous_max.is_some_and(|prev| max <= prev) => {
+                    return Err(error::StorageError::Config(
+                        "compression_bands 的 max_size 必须递增".to_string(),
+                    ));
+                }
+                Some(max) => previous_max = Some(max),
+                None if i + 1 < self.compression_bands.len() => {
+                    return Err(error::StorageError::Config(
+                        "compression_bands 中只有最后一条规则可以不设 max_size".to_string(),
+                    ));
+                }
+                None => {}
+            }
+        }
+        if !(0.0..=8.0).contains(&self.compression_max_entropy) {
+            return Err(error::StorageError::Config(
+                "compression_max_entropy 必须在 0 到 8 之间".to_string(),
+            ));
+        }
+        if self.enable_auto_gc && self.gc_interval_secs == 0 {
+            return Err(error::StorageError::Config(
+                "启用自动GC时 gc_interval_secs 必须大于 0".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.gc_jitter) {
+            return Err(error::StorageError::Config(
+                "gc_jitter 必须在 0 到 1 之间".to_string(),
+            ));
+        }
+        if self.max_chain_depth > core::version_chain::MAX_VERSION_CHAIN_DEPTH {
+            return Err(error::StorageError::Config(format!(
+                "max_chain_depth 不能超过 {}",
+                core::version_chain::MAX_VERSION_CHAIN_DEPTH
+            )));
+        }
+        if self.optimization_queue_high_water > 0
+            && self.optimization_queue_hard_cap > 0
+            && self.optimization_queue_high_water >= self.optimization_queue_hard_cap
+        {
+            return Err(error::StorageError::Config(
+                "optimization_queue_high_water 必须小于 optimization_queue_hard_cap".to_string(),
+            ));
+        }
+        if self.optimization_concurrency == 0 {
+            return Err(error::StorageError::Config(
+                "optimization_concurrency 必须大于 0".to_string(),
+            ));
+        }
+        if self.weak_hash_mod == 0 {
+            return Err(error::StorageError::Config(
+                "weak_hash_mod 必须大于 0".to_string(),
+            ));
+        }
+        self.metadata.validate()?;
+        self.gc_election.validate()?;
+        self.packing.validate()?;
+        Ok(())
+    }
 }
 
 impl Default for IncrementalConfig {
@@ -208,12 +472,62 @@ impl Default for IncrementalConfig {
             weak_hash_mod: 2048,       // 2^11
             enable_compression: true,
             compression_algorithm: "lz4".to_string(),
+            compression_bands: Vec::new(),
+            compression_max_entropy: 7.5,
             enable_auto_gc: true,
             gc_interval_secs: 3600, // 默认每小时执行一次GC
+            gc_jitter: silent_nas_core::DEFAULT_JITTER,
+            recompute_usage_after_gc: false,
+            prefetch_chunks: Self::default_prefetch_chunks(),
+            read_only: false,
+            quota_bytes: None,
+            max_upload_size: None,
+            scratch_dir: None,
+            scratch_max_age_secs: 86400,
+            chunk_root: None,
+            metadata_dir: None,
+            hot_dir: None,
+            max_chain_depth: 0,
+            chain_compaction: ChainCompactionMode::Deferred,
+            max_memory_index: 100_000,
+            namespace_salts: Default::default(),
+            content_addressed_versions: false,
+            instant_upload: InstantUploadScope::default(),
+            optimization_queue_high_water: 0,
+            optimization_queue_hard_cap: 0,
+            backpressure_delay_ms: 200,
+            backpressure_retry_after_secs: 5,
+            optimization_concurrency: 2,
+            optimization_max_load: 0.0,
+            verify_optimization: true,
+            verify_downloads: false,
+            metadata: metadata::MetadataDbConfig::default(),
+            gc_election: leader::GcElectionConfig::default(),
+            packing: pack::PackConfig::default(),
         }
     }
 }
 
+/// 按名称解析压缩算法，未知名称视为不压缩（由 [`IncrementalConfig::validate`] 提前拒绝）
+pub(crate) fn parse_compression_algorithm(name: &str) -> core::compression::CompressionAlgorithm {
+    match name {
+        "lz4" => core::compression::CompressionAlgorithm::LZ4,
+        "zstd" => core::compression::CompressionAlgorithm::Zstd,
+        "auto" => core::compression::CompressionAlgorithm::Auto,
+        _ => core::compression::CompressionAlgorithm::None,
+    }
+}
+
+/// 按文件大小选择压缩算法的规则（见 [`IncrementalConfig::compression_bands`]）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionBand {
+    /// 适用的文件大小上限（字节，不含），`None` 表示不设上限
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    /// 压缩算法 (lz4, zstd, auto, none)
+    pub algorithm: String,
+}
+
 /// 分块算法类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChunkerType {
@@ -221,6 +535,36 @@ pub enum ChunkerType {
     Fixed,
     /// Rabin-Karp滚动哈希
     RabinKarp,
+    /// FastCDC 齿轮哈希 + 归一化分块
+    #[serde(alias = "FastCDC")]
+    FastCdc,
+}
+
+/// 版本链压缩执行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ChainCompactionMode {
+    /// 在触发压缩的保存操作中同步执行
+    Sync,
+    /// 由后台任务异步执行，不阻塞保存
+    #[default]
+    Deferred,
+}
+
+/// 秒传查找范围
+///
+/// 秒传只凭客户端声明的 SHA-256 与大小复制已有内容，不校验客户端是否真的持有数据：
+/// 知道某个文件的哈希和大小即可在查找范围内取得其内容，也可据此探测内容是否存在。
+/// 因此默认禁用，只应在可信用户之间开启，且优先使用 `Namespace` 限制在同一租户内。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InstantUploadScope {
+    /// 禁用秒传，总是要求上传完整内容
+    #[default]
+    Disabled,
+    /// 只匹配同一命名空间内的文件（全局文件只匹配全局文件）
+    Namespace,
+    /// 匹配所有块 ID 兼容的文件（配置了盐值的命名空间仍只匹配本命名空间）
+    Global,
 }
 
 /// 存储模式
@@ -309,6 +653,17 @@ pub struct VersionInfo {
     pub created_at: chrono::NaiveDateTime,
     /// 是否为当前版本
     pub is_current: bool,
+    /// 创建者（可选）
+    #[serde(default)]
+    pub author: Option<String>,
+    /// 版本说明（可选）
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// 整文件压缩版本实际使用的压缩算法（数据不可压缩时为 `None` 算法）
+    ///
+    /// 分块版本与旧数据为 `None`，旧数据读取时按当前配置推断。
+    #[serde(default)]
+    pub compression: Option<crate::core::compression::CompressionAlgorithm>,
 }
 
 /// 去重统计信息
@@ -331,11 +686,14 @@ pub struct DeduplicationStats {
 }
 
 impl DeduplicationStats {
-    /// 计算去重率
+    /// 计算去重率（原始大小为 0 时为 0）
     pub fn calculate_dedup_ratio(&mut self) {
         if self.original_size > 0 {
             self.space_saved = self.original_size.saturating_sub(self.stored_size);
             self.dedup_ratio = (self.space_saved as f64 / self.original_size as f64) * 100.0;
+        } else {
+            self.space_saved = 0;
+            self.dedup_ratio = 0.0;
         }
     }
 }
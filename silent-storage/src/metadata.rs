@@ -6,15 +6,78 @@ use crate::VersionInfo;
 use crate::error::{Result, StorageError};
 use crate::storage::{ChunkRefCount, FileIndexEntry};
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+/// Sled 元数据数据库配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetadataDbConfig {
+    /// 页缓存容量（MB）
+    pub cache_mb: u64,
+    /// 后台刷盘间隔（毫秒，0 表示只在显式 flush 时刷盘）
+    pub flush_every_ms: u64,
+    /// 存储模式
+    pub mode: MetadataDbMode,
+}
+
+/// Sled 存储模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum MetadataDbMode {
+    /// 优先写入吞吐
+    #[default]
+    HighThroughput,
+    /// 优先磁盘占用，更积极地合并日志
+    LowSpace,
+}
+
+impl Default for MetadataDbConfig {
+    fn default() -> Self {
+        Self {
+            cache_mb: 1024,
+            flush_every_ms: 500,
+            mode: MetadataDbMode::HighThroughput,
+        }
+    }
+}
+
+impl MetadataDbConfig {
+    /// 校验配置是否有效
+    pub fn validate(&self) -> Result<()> {
+        if self.cache_mb == 0 {
+            return Err(StorageError::Config(
+                "metadata.cache_mb 必须大于 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn to_sled_config(&self, db_path: &Path) -> sled::Config {
+        let mode = match self.mode {
+            MetadataDbMode::HighThroughput => sled::Mode::HighThroughput,
+            MetadataDbMode::LowSpace => sled::Mode::LowSpace,
+        };
+        sled::Config::new()
+            .path(db_path)
+            .cache_capacity(self.cache_mb * 1024 * 1024)
+            .flush_every_ms((self.flush_every_ms > 0).then_some(self.flush_every_ms))
+            .mode(mode)
+    }
+}
 
 /// Sled 数据库封装
 ///
-/// 用于存储三种类型的元数据：
+/// 用于存储八种类型的元数据：
 /// - 文件索引（file_index）
 /// - 版本索引（version_index）
 /// - 块引用计数（chunk_ref_count）
+/// - 块压缩算法（chunk_codecs，块写入时实际使用的算法）
+/// - 块反向索引（chunk_referrers，块 -> 引用它的版本）
+/// - 资源死属性（dead_props，WebDAV PROPPATCH 写入的自定义属性）
+/// - 内容哈希索引（content_hash_index，整文件哈希 -> 文件，用于秒传）
+/// - 块引用计数重建暂存（chunk_ref_rebuild，全量重建期间的累计结果与断点）
 pub struct SledMetadataDb {
     /// Sled 数据库实例
     db: sled::Db,
@@ -27,18 +90,92 @@ pub struct SledMetadataDb {
 
     /// 块引用计数树
     chunk_ref_tree: sled::Tree,
+
+    /// 块压缩算法树（块ID -> 写入块存储时实际使用的压缩算法）
+    ///
+    /// 每次写入块时覆盖，块删除后残留的记录会在该块重新写入时被覆盖。
+    chunk_codec_tree: sled::Tree,
+
+    /// 块反向索引树（键为 `块ID \0 文件ID \0 版本ID`，值为空）
+    chunk_referrer_tree: sled::Tree,
+
+    /// 死属性树（资源路径 -> 属性表）
+    dead_props_tree: sled::Tree,
+
+    /// 内容哈希索引树（键为 `整文件哈希 \0 文件ID`，值为空）
+    ///
+    /// 只在写入文件索引时追加，文件内容变化或删除后旧条目保留，查询时按文件索引核对。
+    content_hash_tree: sled::Tree,
+
+    /// 块引用计数重建暂存树（块ID -> 累计的引用计数，另含一条断点记录）
+    chunk_ref_rebuild_tree: sled::Tree,
+
+    /// 文件索引条目的大小汇总
+    ///
+    /// 首次查询时全表扫描一次，之后随文件索引的写入与删除增量维护；
+    /// 写入文件索引时持有该锁，保证扫描与增量更新不会交错。
+    index_totals: std::sync::Mutex<Option<IndexTotals>>,
+}
+
+/// 文件索引条目的大小汇总
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexTotals {
+    /// 所有条目的 `file_size` 之和（配额检查使用）
+    pub file_size: u64,
+    /// 热存储模式条目的 `file_size` 之和（尚未优化的数据量）
+    pub hot_size: u64,
+}
+
+impl IndexTotals {
+    /// 序列化的文件索引条目计入的大小（只解析需要的字段，无法解析时按 0 计）
+    fn of_entry(value: &[u8]) -> Self {
+        #[derive(Deserialize)]
+        struct SizeFields {
+            #[serde(default)]
+            file_size: u64,
+            #[serde(default)]
+            storage_mode: Option<crate::StorageMode>,
+        }
+        let Ok(fields) = serde_json::from_slice::<SizeFields>(value) else {
+            return Self::default();
+        };
+        #[allow(deprecated)]
+        let hot = fields.storage_mode == Some(crate::StorageMode::Hot);
+        Self {
+            file_size: fields.file_size,
+            hot_size: if hot { fields.file_size } else { 0 },
+        }
+    }
+
+    fn add(&mut self, other: Self) {
+        self.file_size += other.file_size;
+        self.hot_size += other.hot_size;
+    }
+
+    fn sub(&mut self, other: Self) {
+        self.file_size = self.file_size.saturating_sub(other.file_size);
+        self.hot_size = self.hot_size.saturating_sub(other.hot_size);
+    }
 }
 
 impl SledMetadataDb {
-    /// 打开或创建 Sled 数据库
+    /// 使用默认配置打开或创建 Sled 数据库
     ///
     /// # 参数
     /// * `db_path` - 数据库路径
     pub fn open<P: AsRef<Path>>(db_path: P) -> Result<Self> {
-        let db = sled::open(&db_path)
+        Self::open_with_config(db_path, &MetadataDbConfig::default())
+    }
+
+    /// 使用指定的缓存、刷盘与压缩配置打开或创建 Sled 数据库
+    pub fn open_with_config<P: AsRef<Path>>(db_path: P, config: &MetadataDbConfig) -> Result<Self> {
+        config.validate()?;
+        let db = config
+            .to_sled_config(db_path.as_ref())
+            .open()
             .map_err(|e| StorageError::Database(format!("打开 Sled 数据库失败: {}", e)))?;
 
-        // 打开三个独立的树
+        // 打开八个独立的树
         let file_index_tree = db
             .open_tree("file_index")
             .map_err(|e| StorageError::Database(format!("打开 file_index 树失败: {}", e)))?;
@@ -51,6 +188,26 @@ impl SledMetadataDb {
             .open_tree("chunk_ref_count")
             .map_err(|e| StorageError::Database(format!("打开 chunk_ref_count 树失败: {}", e)))?;
 
+        let chunk_codec_tree = db
+            .open_tree("chunk_codecs")
+            .map_err(|e| StorageError::Database(format!("打开 chunk_codecs 树失败: {}", e)))?;
+
+        let chunk_referrer_tree = db
+            .open_tree("chunk_referrers")
+            .map_err(|e| StorageError::Database(format!("打开 chunk_referrers 树失败: {}", e)))?;
+
+        let dead_props_tree = db
+            .open_tree("dead_props")
+            .map_err(|e| StorageError::Database(format!("打开 dead_props 树失败: {}", e)))?;
+
+        let content_hash_tree = db.open_tree("content_hash_index").map_err(|e| {
+            StorageError::Database(format!("打开 content_hash_index 树失败: {}", e))
+        })?;
+
+        let chunk_ref_rebuild_tree = db
+            .open_tree("chunk_ref_rebuild")
+            .map_err(|e| StorageError::Database(format!("打开 chunk_ref_rebuild 树失败: {}", e)))?;
+
         info!("Sled 数据库初始化完成: {:?}", db_path.as_ref());
 
         Ok(Self {
@@ -58,6 +215,12 @@ impl SledMetadataDb {
             file_index_tree,
             version_index_tree,
             chunk_ref_tree,
+            chunk_codec_tree,
+            chunk_referrer_tree,
+            dead_props_tree,
+            content_hash_tree,
+            chunk_ref_rebuild_tree,
+            index_totals: std::sync::Mutex::new(None),
         })
     }
 
@@ -74,11 +237,13 @@ impl SledMetadataDb {
 
     /// 保存文件索引条目
     pub fn put_file_index(&self, file_id: &str, entry: &FileIndexEntry) -> Result<()> {
-        let value = serde_json::to_vec(entry).map_err(StorageError::Serialization)?;
+        let value =
+            sled::IVec::from(serde_json::to_vec(entry).map_err(StorageError::Serialization)?);
 
-        self.file_index_tree
-            .insert(file_id.as_bytes(), value)
-            .map_err(|e| StorageError::Database(format!("插入文件索引失败: {}", e)))?;
+        self.insert_file_index_value("插入文件索引", file_id, value)?;
+        if !entry.file_hash.is_empty() {
+            self.add_content_hash(&entry.file_hash, file_id)?;
+        }
 
         debug!("保存文件索引: {}", file_id);
         Ok(())
@@ -86,19 +251,69 @@ impl SledMetadataDb {
 
     /// 获取文件索引条目
     pub fn get_file_index(&self, file_id: &str) -> Result<Option<FileIndexEntry>> {
+        #[cfg(test)]
+        access_counts::record(|counts| counts.file_lookups += 1);
+
         self.get_value(&self.file_index_tree, file_id)
     }
 
     /// 删除文件索引条目
     pub fn remove_file_index(&self, file_id: &str) -> Result<()> {
-        self.file_index_tree
-            .remove(file_id.as_bytes())
-            .map_err(|e| StorageError::Database(format!("删除文件索引失败: {}", e)))?;
+        let mut totals = self.lock_index_totals();
+        let old = with_retry("删除文件索引", || {
+            self.file_index_tree.remove(file_id.as_bytes())
+        })?;
+        if let (Some(totals), Some(old)) = (totals.as_mut(), old) {
+            totals.sub(IndexTotals::of_entry(&old));
+        }
 
         debug!("删除文件索引: {}", file_id);
         Ok(())
     }
 
+    /// 写入文件索引条目的序列化值，并增量更新大小汇总
+    fn insert_file_index_value(
+        &self,
+        context: &str,
+        file_id: &str,
+        value: sled::IVec,
+    ) -> Result<()> {
+        let mut totals = self.lock_index_totals();
+        let old = with_retry(context, || {
+            self.file_index_tree
+                .insert(file_id.as_bytes(), value.clone())
+        })?;
+        if let Some(totals) = totals.as_mut() {
+            if let Some(old) = old {
+                totals.sub(IndexTotals::of_entry(&old));
+            }
+            totals.add(IndexTotals::of_entry(&value));
+        }
+        Ok(())
+    }
+
+    /// 文件索引条目的大小汇总
+    ///
+    /// 首次调用时遍历文件索引，之后直接返回增量维护的结果。
+    pub fn index_totals(&self) -> Result<IndexTotals> {
+        let mut totals = self.lock_index_totals();
+        if let Some(totals) = *totals {
+            return Ok(totals);
+        }
+        let mut sum = IndexTotals::default();
+        for item in self.file_index_tree.iter() {
+            let (_, value) =
+                item.map_err(|e| StorageError::Database(format!("遍历文件索引失败: {}", e)))?;
+            sum.add(IndexTotals::of_entry(&value));
+        }
+        *totals = Some(sum);
+        Ok(sum)
+    }
+
+    fn lock_index_totals(&self) -> std::sync::MutexGuard<'_, Option<IndexTotals>> {
+        self.index_totals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     /// 列出所有文件 ID
     pub fn list_file_ids(&self) -> Result<Vec<String>> {
         let mut file_ids = Vec::new();
@@ -116,18 +331,23 @@ impl SledMetadataDb {
 
     /// 列出所有文件索引条目
     pub fn list_all_files(&self) -> Result<Vec<crate::storage::FileIndexEntry>> {
-        let mut files = Vec::new();
-
-        for item in self.file_index_tree.iter() {
-            let (_, value) =
-                item.map_err(|e| StorageError::Database(format!("遍历文件索引失败: {}", e)))?;
+        self.iter_files().collect()
+    }
 
-            let entry: crate::storage::FileIndexEntry =
-                serde_json::from_slice(&value).map_err(StorageError::Serialization)?;
-            files.push(entry);
-        }
+    /// 惰性遍历所有文件索引条目（按键顺序，每次迭代读取一条）
+    pub fn iter_files(
+        &self,
+    ) -> impl Iterator<Item = Result<crate::storage::FileIndexEntry>> + Send + 'static {
+        #[cfg(test)]
+        access_counts::record(|counts| counts.file_scans += 1);
 
-        Ok(files)
+        self.file_index_tree
+            .iter()
+            .map(|item| -> Result<crate::storage::FileIndexEntry> {
+                let (_, value) =
+                    item.map_err(|e| StorageError::Database(format!("遍历文件索引失败: {}", e)))?;
+                serde_json::from_slice(&value).map_err(StorageError::Serialization)
+            })
     }
 
     /// 获取文件索引数量
@@ -139,11 +359,13 @@ impl SledMetadataDb {
 
     /// 保存版本信息
     pub fn put_version_info(&self, version_id: &str, info: &VersionInfo) -> Result<()> {
-        let value = serde_json::to_vec(info).map_err(StorageError::Serialization)?;
+        let value =
+            sled::IVec::from(serde_json::to_vec(info).map_err(StorageError::Serialization)?);
 
-        self.version_index_tree
-            .insert(version_id.as_bytes(), value)
-            .map_err(|e| StorageError::Database(format!("插入版本信息失败: {}", e)))?;
+        with_retry("插入版本信息", || {
+            self.version_index_tree
+                .insert(version_id.as_bytes(), value.clone())
+        })?;
 
         debug!("保存版本信息: {}", version_id);
         Ok(())
@@ -156,9 +378,9 @@ impl SledMetadataDb {
 
     /// 删除版本信息
     pub fn remove_version_info(&self, version_id: &str) -> Result<()> {
-        self.version_index_tree
-            .remove(version_id.as_bytes())
-            .map_err(|e| StorageError::Database(format!("删除版本信息失败: {}", e)))?;
+        with_retry("删除版本信息", || {
+            self.version_index_tree.remove(version_id.as_bytes())
+        })?;
 
         debug!("删除版本信息: {}", version_id);
         Ok(())
@@ -166,6 +388,9 @@ impl SledMetadataDb {
 
     /// 列出指定文件的所有版本
     pub fn list_file_versions(&self, file_id: &str) -> Result<Vec<VersionInfo>> {
+        #[cfg(test)]
+        access_counts::record(|counts| counts.version_scans += 1);
+
         let mut versions = Vec::new();
 
         for item in self.version_index_tree.iter() {
@@ -186,20 +411,99 @@ impl SledMetadataDb {
         Ok(versions)
     }
 
+    /// 惰性遍历所有版本信息（按键顺序，每次迭代读取一条）
+    pub fn iter_versions(&self) -> impl Iterator<Item = Result<VersionInfo>> + Send + 'static {
+        self.version_index_tree
+            .iter()
+            .map(|item| -> Result<VersionInfo> {
+                let (_, value) =
+                    item.map_err(|e| StorageError::Database(format!("遍历版本索引失败: {}", e)))?;
+                serde_json::from_slice(&value).map_err(StorageError::Serialization)
+            })
+    }
+
+    /// 从指定版本之后（不含）按键顺序惰性遍历版本信息，`None` 表示从头开始
+    pub fn iter_versions_after(
+        &self,
+        after: Option<&str>,
+    ) -> impl Iterator<Item = Result<VersionInfo>> + Send + 'static {
+        let iter = match after {
+            Some(version_id) => self.version_index_tree.range((
+                std::ops::Bound::Excluded(version_id.as_bytes().to_vec()),
+                std::ops::Bound::<Vec<u8>>::Unbounded,
+            )),
+            None => self.version_index_tree.iter(),
+        };
+        iter.map(|item| -> Result<VersionInfo> {
+            let (_, value) =
+                item.map_err(|e| StorageError::Database(format!("遍历版本索引失败: {}", e)))?;
+            serde_json::from_slice(&value).map_err(StorageError::Serialization)
+        })
+    }
+
     /// 获取版本索引数量
     pub fn version_index_count(&self) -> usize {
         self.version_index_tree.len()
     }
 
+    // ========== 内容哈希索引操作 ==========
+
+    /// 记录文件的整文件哈希
+    pub fn add_content_hash(&self, file_hash: &str, file_id: &str) -> Result<()> {
+        let key = content_hash_key(file_hash, file_id);
+        with_retry("插入内容哈希索引", || {
+            self.content_hash_tree
+                .insert(key.as_slice(), sled::IVec::default())
+        })?;
+        Ok(())
+    }
+
+    /// 移除过期的内容哈希索引条目
+    pub fn remove_content_hash(&self, file_hash: &str, file_id: &str) -> Result<()> {
+        let key = content_hash_key(file_hash, file_id);
+        with_retry("删除内容哈希索引", || {
+            self.content_hash_tree.remove(key.as_slice())
+        })?;
+        Ok(())
+    }
+
+    /// 列出曾以该整文件哈希写入的文件 ID（可能包含已变化或已删除的文件，调用方须核对）
+    pub fn files_with_content_hash(&self, file_hash: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}\0", file_hash);
+        let mut file_ids = Vec::new();
+
+        for item in self.content_hash_tree.scan_prefix(prefix.as_bytes()) {
+            let (key, _) =
+                item.map_err(|e| StorageError::Database(format!("遍历内容哈希索引失败: {}", e)))?;
+            file_ids.push(String::from_utf8_lossy(&key[prefix.len()..]).to_string());
+        }
+
+        Ok(file_ids)
+    }
+
+    /// 获取内容哈希索引条目总数
+    pub fn content_hash_count(&self) -> usize {
+        self.content_hash_tree.len()
+    }
+
+    /// 清空内容哈希索引（重建前调用）
+    pub fn clear_content_hashes(&self) -> Result<()> {
+        with_retry("清空内容哈希索引", || {
+            self.content_hash_tree.clear()
+        })
+    }
+
     // ========== 块引用计数操作 ==========
 
     /// 保存块引用计数
     pub fn put_chunk_ref(&self, chunk_id: &str, ref_count: &ChunkRefCount) -> Result<()> {
-        let value = serde_json::to_vec(ref_count).map_err(StorageError::Serialization)?;
+        let value =
+            sled::IVec::from(serde_json::to_vec(ref_count).map_err(StorageError::Serialization)?);
 
-        self.chunk_ref_tree
-            .insert(chunk_id.as_bytes(), value)
-            .map_err(|e| StorageError::Database(format!("插入块引用计数失败: {}", e)))?;
+        with_retry("插入块引用计数", || {
+            self.chunk_ref_tree
+                .insert(chunk_id.as_bytes(), value.clone())
+        })?;
 
         debug!(
             "保存块引用计数: {} (ref_count={})",
@@ -215,14 +519,73 @@ impl SledMetadataDb {
 
     /// 删除块引用计数
     pub fn remove_chunk_ref(&self, chunk_id: &str) -> Result<()> {
-        self.chunk_ref_tree
-            .remove(chunk_id.as_bytes())
-            .map_err(|e| StorageError::Database(format!("删除块引用计数失败: {}", e)))?;
+        with_retry("删除块引用计数", || {
+            self.chunk_ref_tree.remove(chunk_id.as_bytes())
+        })?;
 
         debug!("删除块引用计数: {}", chunk_id);
         Ok(())
     }
 
+    /// 记录块写入块存储时实际使用的压缩算法
+    pub fn put_chunk_codec(
+        &self,
+        chunk_id: &str,
+        algorithm: crate::core::compression::CompressionAlgorithm,
+    ) -> Result<()> {
+        let value =
+            sled::IVec::from(serde_json::to_vec(&algorithm).map_err(StorageError::Serialization)?);
+        with_retry("插入块压缩算法", || {
+            self.chunk_codec_tree
+                .insert(chunk_id.as_bytes(), value.clone())
+        })?;
+        Ok(())
+    }
+
+    /// 获取块写入时记录的压缩算法（记录该信息之前写入的块返回 `None`）
+    pub fn get_chunk_codec(
+        &self,
+        chunk_id: &str,
+    ) -> Result<Option<crate::core::compression::CompressionAlgorithm>> {
+        self.get_value(&self.chunk_codec_tree, chunk_id)
+    }
+
+    /// 删除块的压缩算法记录
+    pub fn remove_chunk_codec(&self, chunk_id: &str) -> Result<()> {
+        with_retry("删除块压缩算法", || {
+            self.chunk_codec_tree.remove(chunk_id.as_bytes())
+        })?;
+        Ok(())
+    }
+
+    /// 原子性登记块引用：引用记录已存在时累加引用计数，否则插入该记录
+    ///
+    /// 并发保存相同内容时，新块的写入者与复用者登记引用的先后不确定，
+    /// 两者都通过该方法登记，结果与顺序无关。返回登记后的引用计数。
+    pub fn add_chunk_ref(&self, chunk_id: &str, chunk_ref: &ChunkRefCount) -> Result<usize> {
+        let result = with_retry("登记块引用", || {
+            self.chunk_ref_tree
+                .update_and_fetch(chunk_id.as_bytes(), |old_value| {
+                    let merged = match old_value
+                        .and_then(|bytes| serde_json::from_slice::<ChunkRefCount>(bytes).ok())
+                    {
+                        Some(mut existing) => {
+                            existing.ref_count += chunk_ref.ref_count;
+                            existing
+                        }
+                        None => chunk_ref.clone(),
+                    };
+                    serde_json::to_vec(&merged).ok()
+                })
+        })?;
+
+        let bytes =
+            result.ok_or_else(|| StorageError::Chunk(format!("登记块引用失败: {}", chunk_id)))?;
+        let ref_count: ChunkRefCount =
+            serde_json::from_slice(&bytes).map_err(StorageError::Serialization)?;
+        Ok(ref_count.ref_count)
+    }
+
     /// 原子性增加块引用计数
     pub fn increment_chunk_ref(&self, chunk_id: &str) -> Result<usize> {
         self.update_chunk_ref_atomic(chunk_id, |count| count + 1)
@@ -238,20 +601,20 @@ impl SledMetadataDb {
     where
         F: Fn(usize) -> usize,
     {
-        let result = self
-            .chunk_ref_tree
-            .update_and_fetch(chunk_id.as_bytes(), |old_value| {
-                let mut ref_count = if let Some(bytes) = old_value {
-                    serde_json::from_slice::<ChunkRefCount>(bytes).ok()?
-                } else {
-                    return None; // 块不存在
-                };
-
-                ref_count.ref_count = update_fn(ref_count.ref_count);
-
-                serde_json::to_vec(&ref_count).ok()
-            })
-            .map_err(|e| StorageError::Database(format!("原子更新块引用计数失败: {}", e)))?;
+        let result = with_retry("原子更新块引用计数", || {
+            self.chunk_ref_tree
+                .update_and_fetch(chunk_id.as_bytes(), |old_value| {
+                    let mut ref_count = if let Some(bytes) = old_value {
+                        serde_json::from_slice::<ChunkRefCount>(bytes).ok()?
+                    } else {
+                        return None; // 块不存在
+                    };
+
+                    ref_count.ref_count = update_fn(ref_count.ref_count);
+
+                    serde_json::to_vec(&ref_count).ok()
+                })
+        })?;
 
         match result {
             Some(bytes) => {
@@ -315,6 +678,69 @@ impl SledMetadataDb {
         }
     }
 
+    // ========== 块反向索引操作 ==========
+
+    /// 记录版本引用的块
+    pub fn add_chunk_referrers(
+        &self,
+        file_id: &str,
+        version_id: &str,
+        chunk_ids: &[String],
+    ) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for chunk_id in chunk_ids {
+            batch.insert(
+                chunk_referrer_key(chunk_id, file_id, version_id),
+                sled::IVec::default(),
+            );
+        }
+
+        with_retry("批量插入块反向索引", || {
+            self.chunk_referrer_tree.apply_batch(batch.clone())
+        })?;
+        Ok(())
+    }
+
+    /// 移除版本对块的引用记录
+    pub fn remove_chunk_referrers(
+        &self,
+        file_id: &str,
+        version_id: &str,
+        chunk_ids: &[String],
+    ) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for chunk_id in chunk_ids {
+            batch.remove(chunk_referrer_key(chunk_id, file_id, version_id));
+        }
+
+        with_retry("批量删除块反向索引", || {
+            self.chunk_referrer_tree.apply_batch(batch.clone())
+        })?;
+        Ok(())
+    }
+
+    /// 列出引用指定块的所有版本，返回 (文件ID, 版本ID)
+    pub fn list_chunk_referrers(&self, chunk_id: &str) -> Result<Vec<(String, String)>> {
+        let prefix = format!("{}\0", chunk_id);
+        let mut referrers = Vec::new();
+
+        for item in self.chunk_referrer_tree.scan_prefix(prefix.as_bytes()) {
+            let (key, _) =
+                item.map_err(|e| StorageError::Database(format!("遍历块反向索引失败: {}", e)))?;
+            let key = String::from_utf8_lossy(&key[prefix.len()..]).to_string();
+            if let Some((file_id, version_id)) = key.rsplit_once('\0') {
+                referrers.push((file_id.to_string(), version_id.to_string()));
+            }
+        }
+
+        Ok(referrers)
+    }
+
+    /// 获取块反向索引条目总数
+    pub fn chunk_referrer_count(&self) -> usize {
+        self.chunk_referrer_tree.len()
+    }
+
     // ========== 批量操作（性能优化）==========
 
     /// 批量保存块引用计数（使用 Batch 合并写入）
@@ -328,9 +754,9 @@ impl SledMetadataDb {
             batch.insert(chunk_id.as_bytes(), value);
         }
 
-        self.chunk_ref_tree
-            .apply_batch(batch)
-            .map_err(|e| StorageError::Database(format!("批量插入块引用计数失败: {}", e)))?;
+        with_retry("批量插入块引用计数", || {
+            self.chunk_ref_tree.apply_batch(batch.clone())
+        })?;
 
         debug!("批量保存 {} 个块引用计数", chunk_refs.len());
         Ok(())
@@ -346,14 +772,25 @@ impl SledMetadataDb {
             batch.remove(chunk_id.as_bytes());
         }
 
-        self.chunk_ref_tree
-            .apply_batch(batch)
-            .map_err(|e| StorageError::Database(format!("批量删除块引用计数失败: {}", e)))?;
+        with_retry("批量删除块引用计数", || {
+            self.chunk_ref_tree.apply_batch(batch.clone())
+        })?;
 
         debug!("批量删除 {} 个块引用计数", chunk_ids.len());
         Ok(())
     }
 
+    /// 批量原子性登记块引用（见 [`Self::add_chunk_ref`]）
+    ///
+    /// 适用场景：保存版本时登记新写入块与复用块的引用
+    pub fn add_chunk_refs_batch(&self, chunk_refs: &[(String, ChunkRefCount)]) -> Result<()> {
+        for (chunk_id, chunk_ref) in chunk_refs {
+            self.add_chunk_ref(chunk_id, chunk_ref)?;
+        }
+        debug!("批量登记 {} 个块引用", chunk_refs.len());
+        Ok(())
+    }
+
     /// 批量原子性增加块引用计数
     ///
     /// 适用场景：保存版本时批量增加多个块的引用计数
@@ -384,6 +821,124 @@ impl SledMetadataDb {
         Ok(results)
     }
 
+    // ========== 块引用计数重建 ==========
+
+    /// 读取块引用计数重建断点，没有进行中的重建时返回 `None`
+    pub fn get_ref_rebuild_checkpoint(&self) -> Result<Option<RefRebuildCheckpoint>> {
+        match with_retry("读取重建断点", || {
+            self.chunk_ref_rebuild_tree.get(REF_REBUILD_CHECKPOINT_KEY)
+        })? {
+            Some(bytes) => Ok(Some(
+                serde_json::from_slice(&bytes).map_err(StorageError::Serialization)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// 清空重建暂存树（丢弃未完成的重建）
+    pub fn clear_ref_rebuild(&self) -> Result<()> {
+        with_retry("清空重建暂存", || self.chunk_ref_rebuild_tree.clear())
+    }
+
+    /// 把一批统计结果累加到暂存树，并在同一批写入中更新断点
+    ///
+    /// 断点与累计结果原子生效，中断后从断点继续不会重复计数
+    pub fn stage_chunk_refs(
+        &self,
+        chunk_refs: &HashMap<String, ChunkRefCount>,
+        checkpoint: &RefRebuildCheckpoint,
+    ) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for (chunk_id, chunk_ref) in chunk_refs {
+            let mut staged = self
+                .get_value::<ChunkRefCount>(&self.chunk_ref_rebuild_tree, chunk_id)?
+                .unwrap_or_else(|| ChunkRefCount {
+                    ref_count: 0,
+                    ..chunk_ref.clone()
+                });
+            staged.ref_count += chunk_ref.ref_count;
+            let value = serde_json::to_vec(&staged).map_err(StorageError::Serialization)?;
+            batch.insert(chunk_id.as_bytes(), value);
+        }
+        let value = serde_json::to_vec(checkpoint).map_err(StorageError::Serialization)?;
+        batch.insert(REF_REBUILD_CHECKPOINT_KEY, value);
+
+        with_retry("写入重建暂存", || {
+            self.chunk_ref_rebuild_tree.apply_batch(batch.clone())
+        })?;
+        Ok(())
+    }
+
+    /// 用暂存树中的统计结果替换块引用计数，完成后清空暂存树
+    ///
+    /// 暂存树中没有的块引用计数置 0（保留记录，交由垃圾回收删除块文件）。
+    /// 中途失败时暂存树保持不变，重新执行结果相同。
+    /// 返回 `(重建的块数, 置 0 的块数)`。
+    pub fn apply_ref_rebuild(&self) -> Result<(usize, usize)> {
+        let mut reset = 0usize;
+        let mut batch = sled::Batch::default();
+        let mut pending = 0usize;
+        for item in self.chunk_ref_tree.iter() {
+            let (key, value) =
+                item.map_err(|e| StorageError::Database(format!("遍历块引用计数失败: {}", e)))?;
+            let staged = with_retry("读取重建暂存", || {
+                self.chunk_ref_rebuild_tree.contains_key(&key)
+            })?;
+            if staged {
+                continue;
+            }
+            let mut chunk_ref: ChunkRefCount =
+                serde_json::from_slice(&value).map_err(StorageError::Serialization)?;
+            if chunk_ref.ref_count == 0 {
+                continue;
+            }
+            chunk_ref.ref_count = 0;
+            batch.insert(
+                key,
+                serde_json::to_vec(&chunk_ref).map_err(StorageError::Serialization)?,
+            );
+            reset += 1;
+            pending += 1;
+            if pending >= REF_REBUILD_APPLY_BATCH {
+                with_retry("重置块引用计数", || {
+                    self.chunk_ref_tree.apply_batch(batch.clone())
+                })?;
+                batch = sled::Batch::default();
+                pending = 0;
+            }
+        }
+        with_retry("重置块引用计数", || {
+            self.chunk_ref_tree.apply_batch(batch.clone())
+        })?;
+
+        let mut rebuilt = 0usize;
+        let mut batch = sled::Batch::default();
+        let mut pending = 0usize;
+        for item in self.chunk_ref_rebuild_tree.iter() {
+            let (key, value) =
+                item.map_err(|e| StorageError::Database(format!("遍历重建暂存失败: {}", e)))?;
+            if key.as_ref() == REF_REBUILD_CHECKPOINT_KEY {
+                continue;
+            }
+            batch.insert(key, value);
+            rebuilt += 1;
+            pending += 1;
+            if pending >= REF_REBUILD_APPLY_BATCH {
+                with_retry("写入块引用计数", || {
+                    self.chunk_ref_tree.apply_batch(batch.clone())
+                })?;
+                batch = sled::Batch::default();
+                pending = 0;
+            }
+        }
+        with_retry("写入块引用计数", || {
+            self.chunk_ref_tree.apply_batch(batch.clone())
+        })?;
+
+        self.clear_ref_rebuild()?;
+        Ok((rebuilt, reset))
+    }
+
     /// 原子事务：保存版本相关的所有元数据
     ///
     /// 一次事务保存：文件索引 + 版本信息 + 块引用计数
@@ -395,22 +950,23 @@ impl SledMetadataDb {
         chunk_refs: &[(String, ChunkRefCount)],
     ) -> Result<()> {
         // 准备所有数据
-        let file_data = serde_json::to_vec(file_index).map_err(StorageError::Serialization)?;
-        let version_data =
-            serde_json::to_vec(version_info).map_err(StorageError::Serialization)?;
+        let file_data =
+            sled::IVec::from(serde_json::to_vec(file_index).map_err(StorageError::Serialization)?);
+        let version_data = sled::IVec::from(
+            serde_json::to_vec(version_info).map_err(StorageError::Serialization)?,
+        );
 
         // 使用多个 Batch 操作（Sled 不支持跨 Tree 的事务）
         // 但由于 LSM-tree 的特性，这些操作会在内存中批量合并
 
         // 1. 保存文件索引
-        self.file_index_tree
-            .insert(file_index.file_id.as_bytes(), file_data)
-            .map_err(|e| StorageError::Database(format!("保存文件索引失败: {}", e)))?;
+        self.insert_file_index_value("保存文件索引", &file_index.file_id, file_data)?;
 
         // 2. 保存版本信息
-        self.version_index_tree
-            .insert(version_info.version_id.as_bytes(), version_data)
-            .map_err(|e| StorageError::Database(format!("保存版本信息失败: {}", e)))?;
+        with_retry("保存版本信息", || {
+            self.version_index_tree
+                .insert(version_info.version_id.as_bytes(), version_data.clone())
+        })?;
 
         // 3. 批量保存块引用计数
         if !chunk_refs.is_empty() {
@@ -427,21 +983,234 @@ impl SledMetadataDb {
         Ok(())
     }
 
+    // ========== 死属性操作 ==========
+
+    /// 保存资源的死属性（属性表为空时删除条目）
+    pub fn put_dead_props(&self, path: &str, props: &HashMap<String, String>) -> Result<()> {
+        if props.is_empty() {
+            with_retry("删除死属性", || {
+                self.dead_props_tree.remove(path.as_bytes())
+            })?;
+            return Ok(());
+        }
+
+        let value =
+            sled::IVec::from(serde_json::to_vec(props).map_err(StorageError::Serialization)?);
+        with_retry("保存死属性", || {
+            self.dead_props_tree.insert(path.as_bytes(), value.clone())
+        })?;
+
+        debug!("保存死属性: {} ({} 项)", path, props.len());
+        Ok(())
+    }
+
+    /// 获取资源的死属性（不存在时返回空表）
+    pub fn get_dead_props(&self, path: &str) -> Result<HashMap<String, String>> {
+        Ok(self
+            .get_value(&self.dead_props_tree, path)?
+            .unwrap_or_default())
+    }
+
+    /// 删除资源及其所有子资源的死属性，返回删除的条目数
+    pub fn remove_dead_props(&self, path: &str) -> Result<usize> {
+        let keys = self.dead_props_subtree(path)?;
+        for (key, _) in &keys {
+            with_retry("删除死属性", || {
+                self.dead_props_tree.remove(key.as_bytes())
+            })?;
+        }
+        Ok(keys.len())
+    }
+
+    /// 将资源及其所有子资源的死属性复制到新路径，返回复制的条目数
+    ///
+    /// `remove_source` 为 true 时删除源条目（用于移动）
+    pub fn copy_dead_props(&self, from: &str, to: &str, remove_source: bool) -> Result<usize> {
+        let entries = self.dead_props_subtree(from)?;
+        let from_base = from.trim_end_matches('/');
+        let to_base = to.trim_end_matches('/');
+        for (key, value) in &entries {
+            let new_key = format!("{}{}", to_base, &key[from_base.len()..]);
+            with_retry("复制死属性", || {
+                self.dead_props_tree
+                    .insert(new_key.as_bytes(), value.clone())
+            })?;
+            if remove_source {
+                with_retry("删除死属性", || {
+                    self.dead_props_tree.remove(key.as_bytes())
+                })?;
+            }
+        }
+        Ok(entries.len())
+    }
+
+    /// 列出资源自身及其子资源（`path/` 前缀）的死属性条目
+    fn dead_props_subtree(&self, path: &str) -> Result<Vec<(String, sled::IVec)>> {
+        let mut entries = Vec::new();
+        if let Some(value) = with_retry("读取死属性", || {
+            self.dead_props_tree.get(path.as_bytes())
+        })? {
+            entries.push((path.to_string(), value));
+        }
+
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        for item in self.dead_props_tree.scan_prefix(prefix.as_bytes()) {
+            let (key, value) =
+                item.map_err(|e| StorageError::Database(format!("遍历死属性失败: {}", e)))?;
+            let key = String::from_utf8_lossy(&key).to_string();
+            if key != path {
+                entries.push((key, value));
+            }
+        }
+        Ok(entries)
+    }
+
     // ========== 通用辅助方法 ==========
 
     /// 从树中获取并反序列化值
     fn get_value<T: DeserializeOwned>(&self, tree: &sled::Tree, key: &str) -> Result<Option<T>> {
-        match tree.get(key.as_bytes()) {
-            Ok(Some(bytes)) => {
+        match with_retry("读取数据", || tree.get(key.as_bytes()))? {
+            Some(bytes) => {
                 let value = serde_json::from_slice(&bytes).map_err(StorageError::Serialization)?;
                 Ok(Some(value))
             }
-            Ok(None) => Ok(None),
-            Err(e) => Err(StorageError::Database(format!("读取数据失败: {}", e))),
+            None => Ok(None),
         }
     }
 }
 
+/// 重建断点在暂存树中的键（块ID不会以 `\0` 开头）
+const REF_REBUILD_CHECKPOINT_KEY: &[u8] = b"\0checkpoint";
+
+/// 替换块引用计数时每批写入的条目数
+const REF_REBUILD_APPLY_BATCH: usize = 1024;
+
+/// 块引用计数重建断点
+///
+/// 与暂存的统计结果在同一批写入中更新，两者总是一致
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RefRebuildCheckpoint {
+    /// 最后一个已统计的版本ID（版本索引按键顺序遍历）
+    pub last_version_id: Option<String>,
+    /// 已统计的版本数
+    pub versions_scanned: usize,
+}
+
+/// 块反向索引键：按块 ID 前缀扫描即可得到所有引用者
+fn chunk_referrer_key(chunk_id: &str, file_id: &str, version_id: &str) -> Vec<u8> {
+    format!("{}\0{}\0{}", chunk_id, file_id, version_id).into_bytes()
+}
+
+/// 内容哈希索引键：按哈希前缀扫描即可得到所有候选文件
+fn content_hash_key(file_hash: &str, file_id: &str) -> Vec<u8> {
+    format!("{}\0{}", file_hash, file_id).into_bytes()
+}
+
+// ========== 瞬时错误重试 ==========
+
+/// 数据库操作的最大尝试次数（含首次）
+const DB_MAX_ATTEMPTS: u32 = 4;
+
+/// 首次重试前的退避时间（毫秒），之后每次翻倍
+const DB_RETRY_BASE_DELAY_MS: u64 = 5;
+
+/// 是否为可重试的瞬时错误
+///
+/// 只有锁竞争、被中断、超时这类 IO 错误会在稍后自行恢复；
+/// 数据损坏、树不存在、不支持的操作等错误重试也不会成功，直接返回。
+fn is_transient(err: &sled::Error) -> bool {
+    match err {
+        sled::Error::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::WouldBlock
+                | std::io::ErrorKind::Interrupted
+                | std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::ResourceBusy
+        ),
+        _ => false,
+    }
+}
+
+/// 执行数据库操作，遇到瞬时错误时按指数退避重试
+///
+/// 超过 [`DB_MAX_ATTEMPTS`] 次或遇到非瞬时错误时返回 `StorageError::Database`。
+/// 元数据操作是同步调用，退避等待见 [`retry_backoff`]。
+fn with_retry<T>(context: &str, mut op: impl FnMut() -> sled::Result<T>) -> Result<T> {
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(e) if is_transient(&e) && attempt < DB_MAX_ATTEMPTS => {
+                let delay = DB_RETRY_BASE_DELAY_MS << (attempt - 1);
+                warn!(
+                    "{}遇到瞬时错误，{}ms 后重试（第 {} 次）: {}",
+                    context, delay, attempt, e
+                );
+                retry_backoff(std::time::Duration::from_millis(delay));
+                attempt += 1;
+            }
+            Err(e) => return Err(StorageError::Database(format!("{}失败: {}", context, e))),
+        }
+    }
+}
+
+/// 重试前的退避等待
+///
+/// 在多线程运行时中调用时经 `block_in_place` 把当前工作线程上的其他任务交给别的线程，
+/// 等待期间不会卡住异步任务；运行时之外（或单线程运行时）直接休眠当前线程。
+fn retry_backoff(delay: std::time::Duration) {
+    use tokio::runtime::{Handle, RuntimeFlavor};
+
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| std::thread::sleep(delay))
+        }
+        _ => std::thread::sleep(delay),
+    }
+}
+
+/// 元数据访问计数（仅测试使用）
+///
+/// 按线程统计：`#[tokio::test]` 默认的单线程运行时中，即为当前测试内的全部访问。
+#[cfg(test)]
+pub(crate) mod access_counts {
+    use std::cell::Cell;
+
+    /// 各类访问的次数
+    #[derive(Debug, Clone, Copy, Default)]
+    pub(crate) struct AccessCounts {
+        /// 版本索引全表扫描次数
+        pub(crate) version_scans: usize,
+        /// 文件索引全表扫描次数
+        pub(crate) file_scans: usize,
+        /// 文件索引单条查找次数
+        pub(crate) file_lookups: usize,
+    }
+
+    thread_local! {
+        static COUNTS: Cell<AccessCounts> = Cell::new(AccessCounts::default());
+    }
+
+    /// 记录一次访问
+    pub(super) fn record(update: impl FnOnce(&mut AccessCounts)) {
+        COUNTS.with(|cell| {
+            let mut counts = cell.get();
+            update(&mut counts);
+            cell.set(counts);
+        });
+    }
+
+    /// 清零计数
+    pub(crate) fn reset() {
+        COUNTS.with(|cell| cell.set(AccessCounts::default()));
+    }
+
+    /// 当前计数
+    pub(crate) fn get() -> AccessCounts {
+        COUNTS.with(Cell::get)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -472,6 +1241,15 @@ mod tests {
             optimization_status: crate::OptimizationStatus::Completed,
             file_size: 0,
             file_hash: String::new(),
+            user_metadata: HashMap::new(),
+            optimization_strategy: None,
+            optimized_at: None,
+            space_saved: 0,
+            stored_size: 0,
+            attributed_size: 0,
+            alias_target: None,
+            compression: None,
+            retention: None,
         };
 
         // 保存
@@ -492,6 +1270,111 @@ mod tests {
         assert!(db.get_file_index("test_file").unwrap().is_none());
     }
 
+    #[test]
+    fn test_index_totals_track_index_writes() {
+        let (db, _temp) = create_test_db();
+        let now = Local::now().naive_local();
+        let entry = |file_id: &str, file_size: u64| FileIndexEntry {
+            file_id: file_id.to_string(),
+            latest_version_id: "v1".to_string(),
+            version_count: 1,
+            created_at: now,
+            modified_at: now,
+            is_deleted: false,
+            deleted_at: None,
+            storage_mode: crate::StorageMode::Chunked,
+            optimization_status: crate::OptimizationStatus::Completed,
+            file_size,
+            file_hash: String::new(),
+            user_metadata: HashMap::new(),
+            optimization_strategy: None,
+            optimized_at: None,
+            space_saved: 0,
+            stored_size: 0,
+            attributed_size: 0,
+            alias_target: None,
+            compression: None,
+            retention: None,
+        };
+
+        let totals = |file_size, hot_size| IndexTotals {
+            file_size,
+            hot_size,
+        };
+
+        // 首次查询前写入的条目由扫描计入
+        db.put_file_index("a", &entry("a", 100)).unwrap();
+        assert_eq!(db.index_totals().unwrap(), totals(100, 0));
+
+        // 之后的新增、覆盖与删除增量更新
+        db.put_file_index("b", &entry("b", 50)).unwrap();
+        db.put_file_index("a", &entry("a", 30)).unwrap();
+        assert_eq!(db.index_totals().unwrap(), totals(80, 0));
+        db.remove_file_index("b").unwrap();
+        db.remove_file_index("missing").unwrap();
+        assert_eq!(db.index_totals().unwrap(), totals(30, 0));
+
+        // 热存储条目单独汇总，转为分块存储后扣除
+        let mut hot = entry("h", 20);
+        #[allow(deprecated)]
+        {
+            hot.storage_mode = crate::StorageMode::Hot;
+        }
+        db.put_file_index("h", &hot).unwrap();
+        assert_eq!(db.index_totals().unwrap(), totals(50, 20));
+        db.put_file_index("h", &entry("h", 20)).unwrap();
+        assert_eq!(db.index_totals().unwrap(), totals(50, 0));
+    }
+
+    #[test]
+    fn test_open_with_small_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MetadataDbConfig {
+            cache_mb: 1,
+            flush_every_ms: 0,
+            mode: MetadataDbMode::LowSpace,
+        };
+        let db =
+            SledMetadataDb::open_with_config(temp_dir.path().join("small.db"), &config).unwrap();
+
+        let now = Local::now().naive_local();
+        let entry = FileIndexEntry {
+            file_id: "small_cache".to_string(),
+            latest_version_id: "v1".to_string(),
+            version_count: 1,
+            created_at: now,
+            modified_at: now,
+            is_deleted: false,
+            deleted_at: None,
+            storage_mode: crate::StorageMode::Chunked,
+            optimization_status: crate::OptimizationStatus::Completed,
+            file_size: 42,
+            file_hash: "abc".to_string(),
+            user_metadata: HashMap::new(),
+            optimization_strategy: None,
+            optimized_at: None,
+            space_saved: 0,
+            stored_size: 0,
+            attributed_size: 0,
+            alias_target: None,
+            compression: None,
+            retention: None,
+        };
+        db.put_file_index("small_cache", &entry).unwrap();
+
+        let retrieved = db.get_file_index("small_cache").unwrap().unwrap();
+        assert_eq!(retrieved.file_size, 42);
+        assert_eq!(retrieved.file_hash, "abc");
+
+        let invalid = MetadataDbConfig {
+            cache_mb: 0,
+            ..MetadataDbConfig::default()
+        };
+        assert!(
+            SledMetadataDb::open_with_config(temp_dir.path().join("invalid.db"), &invalid).is_err()
+        );
+    }
+
     #[test]
     fn test_version_index_operations() {
         let (db, _temp) = create_test_db();
@@ -506,6 +1389,9 @@ mod tests {
             storage_size: 1024,
             created_at: now,
             is_current: true,
+            author: None,
+            comment: None,
+            compression: None,
         };
 
         // 保存
@@ -525,6 +1411,42 @@ mod tests {
         assert!(db.get_version_info("v1").unwrap().is_none());
     }
 
+    #[test]
+    fn test_iter_versions_after() {
+        let (db, _temp) = create_test_db();
+        let now = Local::now().naive_local();
+
+        for id in ["v1", "v2", "v3"] {
+            let version = VersionInfo {
+                version_id: id.to_string(),
+                file_id: "test_file".to_string(),
+                parent_version_id: None,
+                file_size: 1024,
+                chunk_count: 1,
+                storage_size: 1024,
+                created_at: now,
+                is_current: false,
+                author: None,
+                comment: None,
+                compression: None,
+            };
+            db.put_version_info(id, &version).unwrap();
+        }
+
+        let ids = |after: Option<&str>| -> Vec<String> {
+            db.iter_versions_after(after)
+                .map(|v| v.unwrap().version_id)
+                .collect()
+        };
+        assert_eq!(ids(None), ["v1", "v2", "v3"]);
+        // 起点本身不包含在结果中
+        assert_eq!(ids(Some("v1")), ["v2", "v3"]);
+        // 起点已被删除时从其后的键继续
+        db.remove_version_info("v2").unwrap();
+        assert_eq!(ids(Some("v2")), ["v3"]);
+        assert!(ids(Some("v3")).is_empty());
+    }
+
     #[test]
     fn test_chunk_ref_operations() {
         let (db, _temp) = create_test_db();
@@ -534,6 +1456,7 @@ mod tests {
             ref_count: 5,
             size: 1024,
             path: PathBuf::from("/tmp/chunk1"),
+            weak_hash: 0,
         };
 
         // 保存
@@ -574,9 +1497,120 @@ mod tests {
             optimization_status: crate::OptimizationStatus::Completed,
             file_size: 0,
             file_hash: String::new(),
+            user_metadata: HashMap::new(),
+            optimization_strategy: None,
+            optimized_at: None,
+            space_saved: 0,
+            stored_size: 0,
+            attributed_size: 0,
+            alias_target: None,
+            compression: None,
+            retention: None,
         };
 
         db.put_file_index("test", &entry).unwrap();
         db.flush().await.unwrap();
     }
+
+    #[test]
+    fn test_dead_props_operations() {
+        let (db, _temp) = create_test_db();
+
+        let mut props = HashMap::new();
+        props.insert(
+            "ns:urn:x-example#category".to_string(),
+            "interop".to_string(),
+        );
+        db.put_dead_props("/docs", &props).unwrap();
+        db.put_dead_props("/docs/a.txt", &props).unwrap();
+        db.put_dead_props("/docs2/b.txt", &props).unwrap();
+        assert_eq!(db.get_dead_props("/docs/a.txt").unwrap(), props);
+        assert!(db.get_dead_props("/missing").unwrap().is_empty());
+
+        // 移动目录：子资源随之移动，同前缀的兄弟目录不受影响
+        assert_eq!(db.copy_dead_props("/docs", "/archive", true).unwrap(), 2);
+        assert!(db.get_dead_props("/docs/a.txt").unwrap().is_empty());
+        assert_eq!(db.get_dead_props("/archive/a.txt").unwrap(), props);
+        assert_eq!(db.get_dead_props("/docs2/b.txt").unwrap(), props);
+
+        // 复制保留源条目
+        assert_eq!(
+            db.copy_dead_props("/docs2/b.txt", "/c.txt", false).unwrap(),
+            1
+        );
+        assert_eq!(db.get_dead_props("/docs2/b.txt").unwrap(), props);
+        assert_eq!(db.get_dead_props("/c.txt").unwrap(), props);
+
+        // 删除递归清理
+        assert_eq!(db.remove_dead_props("/archive").unwrap(), 2);
+        assert!(db.get_dead_props("/archive").unwrap().is_empty());
+
+        // 空属性表删除条目
+        db.put_dead_props("/c.txt", &HashMap::new()).unwrap();
+        assert!(db.get_dead_props("/c.txt").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_with_retry_transient_and_persistent_errors() {
+        let transient = || sled::Error::Io(std::io::Error::from(std::io::ErrorKind::WouldBlock));
+
+        // 首次瞬时失败，重试后成功
+        let mut calls = 0;
+        let value = with_retry("读取数据", || {
+            calls += 1;
+            if calls == 1 { Err(transient()) } else { Ok(42) }
+        })
+        .unwrap();
+        assert_eq!(value, 42);
+        assert_eq!(calls, 2);
+
+        // 持续的瞬时错误在用尽重试次数后失败
+        let mut calls = 0;
+        let result: Result<()> = with_retry("读取数据", || {
+            calls += 1;
+            Err(transient())
+        });
+        assert!(matches!(result, Err(StorageError::Database(_))));
+        assert_eq!(calls, DB_MAX_ATTEMPTS);
+
+        // 非瞬时错误不重试
+        let mut calls = 0;
+        let result: Result<()> = with_retry("读取数据", || {
+            calls += 1;
+            Err(sled::Error::Unsupported("corrupted".to_string()))
+        });
+        assert!(matches!(result, Err(StorageError::Database(_))));
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_with_retry_backoff_does_not_stall_runtime() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        // 唯一的工作线程在退避等待时，其他任务仍然得到调度
+        let fired = Arc::new(AtomicBool::new(false));
+        let ticker = tokio::spawn({
+            let fired = fired.clone();
+            async move {
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                fired.store(true, Ordering::SeqCst);
+            }
+        });
+        let observed = tokio::spawn(async move {
+            let transient =
+                || sled::Error::Io(std::io::Error::from(std::io::ErrorKind::WouldBlock));
+            let mut observed = false;
+            let result: Result<()> = with_retry("读取数据", || {
+                observed = fired.load(Ordering::SeqCst);
+                Err(transient())
+            });
+            assert!(result.is_err());
+            observed
+        })
+        .await
+        .unwrap();
+        assert!(observed);
+        ticker.await.unwrap();
+    }
 }
@@ -0,0 +1,376 @@
+//! 多租户命名空间
+//!
+//! 为 file_id 增加租户前缀，使不同租户的同名文件互不冲突，
+//! 并保证列表操作只返回本租户的文件。
+//!
+//! 命名空间内的文件以 `.ns/<租户>/<file_id>` 为键存储；
+//! 未指定命名空间的全局列表会排除所有命名空间下的文件。
+
+use crate::error::{Result, StorageError};
+use crate::storage::StorageManager;
+use silent_nas_core::{FileMetadata, StorageManagerTrait};
+
+/// 命名空间键的根前缀
+pub const NAMESPACE_ROOT: &str = ".ns";
+
+/// 租户命名空间
+///
+/// 名称只允许字母、数字、`-`、`_`、`.`，可直接由 S3 bucket 名或用户 ID 构造。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Namespace(String);
+
+impl Namespace {
+    /// 创建命名空间（校验名称）
+    pub fn new(name: impl Into<String>) -> Result<Self> {
+        let name = name.into();
+        let valid = !name.is_empty()
+            && name != "."
+            && name != ".."
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+        if !valid {
+            return Err(StorageError::Storage(format!("无效的命名空间: {}", name)));
+        }
+        Ok(Self(name))
+    }
+
+    /// 命名空间名称
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 将租户内的 file_id 转换为存储键（去除前导 `/`）
+    pub fn scope(&self, file_id: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            NAMESPACE_ROOT,
+            self.0,
+            file_id.trim_start_matches('/')
+        )
+    }
+
+    /// 将存储键还原为租户内的 file_id，不属于本命名空间时返回 `None`
+    pub fn unscope<'a>(&self, key: &'a str) -> Option<&'a str> {
+        key.strip_prefix(NAMESPACE_ROOT)?
+            .strip_prefix('/')?
+            .strip_prefix(self.0.as_str())?
+            .strip_prefix('/')
+    }
+}
+
+impl std::fmt::Display for Namespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// 判断存储键是否属于某个命名空间
+pub fn is_namespaced(key: &str) -> bool {
+    key.strip_prefix(NAMESPACE_ROOT)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// 存储键所属的命名空间名称，不属于任何命名空间时返回 `None`
+pub fn namespace_of(key: &str) -> Option<&str> {
+    let rest = key.strip_prefix(NAMESPACE_ROOT)?.strip_prefix('/')?;
+    rest.split_once('/').map(|(name, _)| name)
+}
+
+/// 限定在单个命名空间内的存储视图
+///
+/// 通过 [`StorageManager::namespace`] 获取，所有 file_id 均为租户内路径。
+/// 全局接口拒绝 `.ns/` 下的键，租户文件只能经由本视图访问。
+pub struct NamespacedStorage {
+    storage: StorageManager,
+    namespace: Namespace,
+}
+
+impl NamespacedStorage {
+    pub(crate) fn new(storage: StorageManager, namespace: Namespace) -> Self {
+        Self { storage, namespace }
+    }
+
+    /// 所属命名空间
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    /// 保存文件
+    pub async fn save_file(&self, file_id: &str, data: &[u8]) -> Result<FileMetadata> {
+        let key = self.namespace.scope(file_id);
+        let metadata = self.storage.save_file(&key, data).await?;
+        Ok(self.unscope_metadata(metadata))
+    }
+
+    /// 读取文件
+    pub async fn read_file(&self, file_id: &str) -> Result<Vec<u8>> {
+        self.storage.read_file(&self.namespace.scope(file_id)).await
+    }
+
+    /// 获取文件元数据
+    pub async fn get_metadata(&self, file_id: &str) -> Result<FileMetadata> {
+        let metadata = self
+            .storage
+            .get_metadata(&self.namespace.scope(file_id))
+            .await?;
+        Ok(self.unscope_metadata(metadata))
+    }
+
+    /// 检查文件是否存在
+    pub async fn file_exists(&self, file_id: &str) -> bool {
+        self.storage
+            .file_exists(&self.namespace.scope(file_id))
+            .await
+    }
+
+    /// 软删除文件
+    pub async fn delete_file(&self, file_id: &str) -> Result<()> {
+        StorageManager::delete_file(&self.storage, &self.namespace.scope(file_id)).await
+    }
+
+    /// 列出命名空间内的所有文件（不含已删除文件）
+    pub async fn list_files(&self) -> Result<Vec<String>> {
+        let mut files: Vec<String> = self
+            .storage
+            .list_all_file_keys()
+            .await?
+            .iter()
+            .filter_map(|key| self.namespace.unscope(key).map(str::to_string))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// 列出命名空间内指定目录下的文件和子目录
+    pub async fn list_directory(&self, dir_path: &str) -> Result<(Vec<String>, Vec<String>)> {
+        let files = self.list_files().await?;
+        let scoped_dir = self.namespace.scope(dir_path);
+        self.storage
+            .build_directory_listing(files, dir_path, &scoped_dir)
+            .await
+    }
+
+    fn unscope_metadata(&self, mut metadata: FileMetadata) -> FileMetadata {
+        if let Some(file_id) = self.namespace.unscope(&metadata.id) {
+            let file_id = file_id.to_string();
+            metadata.name = file_id.clone();
+            metadata.path = file_id.clone();
+            metadata.id = file_id;
+        }
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IncrementalConfig;
+    use crate::test_util::test_storage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_namespace_validation() {
+        assert!(Namespace::new("tenant-a").is_ok());
+        assert!(Namespace::new("user_42.bucket").is_ok());
+        assert!(Namespace::new("").is_err());
+        assert!(Namespace::new("..").is_err());
+        assert!(Namespace::new("a/b").is_err());
+    }
+
+    #[test]
+    fn test_scope_roundtrip() {
+        let ns = Namespace::new("acme").unwrap();
+        let key = ns.scope("/docs/report.txt");
+        assert_eq!(key, ".ns/acme/docs/report.txt");
+        assert_eq!(ns.unscope(&key), Some("docs/report.txt"));
+        assert!(is_namespaced(&key));
+
+        let other = Namespace::new("acme2").unwrap();
+        assert_eq!(other.unscope(&key), None);
+        assert!(!is_namespaced(".nsfile"));
+        assert_eq!(namespace_of(&key), Some("acme"));
+        assert_eq!(namespace_of("docs/report.txt"), None);
+    }
+
+    #[tokio::test]
+    async fn test_tenants_do_not_collide() {
+        let (storage, _temp) = test_storage().await;
+        let tenant_a = storage.namespace(Namespace::new("tenant_a").unwrap());
+        let tenant_b = storage.namespace(Namespace::new("tenant_b").unwrap());
+
+        let meta = tenant_a
+            .save_file("report.txt", b"report of A")
+            .await
+            .unwrap();
+        assert_eq!(meta.id, "report.txt");
+        tenant_b
+            .save_file("report.txt", b"report of B")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tenant_a.read_file("report.txt").await.unwrap(),
+            b"report of A"
+        );
+        assert_eq!(
+            tenant_b.read_file("report.txt").await.unwrap(),
+            b"report of B"
+        );
+
+        // 全局命名空间中不存在同名文件
+        assert!(!storage.file_exists("report.txt").await);
+    }
+
+    #[tokio::test]
+    async fn test_global_api_rejects_namespaced_keys() {
+        let (storage, _temp) = test_storage().await;
+        let tenant_a = storage.namespace(Namespace::new("tenant_a").unwrap());
+        let meta = tenant_a.save_file("secret.txt", b"secret").await.unwrap();
+        let key = tenant_a.namespace().scope("secret.txt");
+        let version_id = meta.hash;
+
+        // 读取：文件ID与版本ID都不可见
+        assert!(matches!(
+            storage.read_file(&key).await,
+            Err(StorageError::ReservedPath(_))
+        ));
+        assert!(!storage.file_exists(&key).await);
+        assert!(!storage.file_exists(&format!("/{}", key)).await);
+        assert!(storage.get_metadata(&key).await.is_err());
+        assert!(matches!(
+            storage.read_version_data(&version_id).await,
+            Err(StorageError::VersionNotFound(_))
+        ));
+
+        // 写入：不能覆盖或复制到租户文件
+        assert!(matches!(
+            storage.save_file(&key, b"overwritten").await,
+            Err(StorageError::ReservedPath(_))
+        ));
+        storage.save_file("public.txt", b"public").await.unwrap();
+        assert!(storage.copy_file("public.txt", &key).await.is_err());
+        assert!(storage.move_file("public.txt", &key).await.is_err());
+        assert!(storage.move_prefix(".ns/tenant_a", "stolen").await.is_err());
+
+        // 删除
+        assert!(matches!(
+            StorageManager::delete_file(&storage, &key).await,
+            Err(StorageError::ReservedPath(_))
+        ));
+        assert!(storage.hard_delete_file(&key).await.is_err());
+
+        // 租户视图不受影响
+        assert_eq!(tenant_a.read_file("secret.txt").await.unwrap(), b"secret");
+        assert_eq!(
+            tenant_a.list_files().await.unwrap(),
+            vec!["secret.txt".to_string()]
+        );
+
+        // 另一个租户同样无法通过全局接口访问
+        let tenant_b = storage.namespace(Namespace::new("tenant_b").unwrap());
+        assert!(tenant_b.read_file(&key).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_salted_namespaces_do_not_share_chunks() {
+        let data = b"identical tenant content ".repeat(512);
+
+        // 未配置盐值：不同租户的相同内容共享块
+        let (storage, _temp) = test_storage().await;
+        let tenant_a = storage.namespace(Namespace::new("tenant_a").unwrap());
+        let tenant_b = storage.namespace(Namespace::new("tenant_b").unwrap());
+        tenant_a.save_file("same.bin", &data).await.unwrap();
+        tenant_b.save_file("same.bin", &data).await.unwrap();
+        let report = storage
+            .with_namespace_access()
+            .file_dedup_report(&tenant_a.namespace().scope("same.bin"))
+            .await
+            .unwrap();
+        assert!(report.shared_chunks > 0);
+
+        // 配置盐值：只在命名空间内去重
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            namespace_salts: [
+                ("tenant_a".to_string(), "salt-a".to_string()),
+                ("tenant_b".to_string(), "salt-b".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 64 * 1024, config);
+        storage.init().await.unwrap();
+        let tenant_a = storage.namespace(Namespace::new("tenant_a").unwrap());
+        let tenant_b = storage.namespace(Namespace::new("tenant_b").unwrap());
+        tenant_a.save_file("same.bin", &data).await.unwrap();
+        tenant_b.save_file("same.bin", &data).await.unwrap();
+        storage.save_file("same.bin", &data).await.unwrap();
+
+        for key in [
+            tenant_a.namespace().scope("same.bin"),
+            tenant_b.namespace().scope("same.bin"),
+            "same.bin".to_string(),
+        ] {
+            let report = storage
+                .with_namespace_access()
+                .file_dedup_report(&key)
+                .await
+                .unwrap();
+            assert_eq!(report.shared_chunks, 0, "{} 不应与其他租户共享块", key);
+        }
+        assert_eq!(tenant_a.read_file("same.bin").await.unwrap(), data);
+        assert_eq!(tenant_b.read_file("same.bin").await.unwrap(), data);
+
+        // 同一命名空间内仍然去重
+        tenant_a.save_file("copy.bin", &data).await.unwrap();
+        let report = storage
+            .with_namespace_access()
+            .file_dedup_report(&tenant_a.namespace().scope("copy.bin"))
+            .await
+            .unwrap();
+        assert!(report.shared_chunks > 0);
+    }
+
+    #[tokio::test]
+    async fn test_listing_is_isolated() {
+        let (storage, _temp) = test_storage().await;
+        let tenant_a = storage.namespace(Namespace::new("tenant_a").unwrap());
+        let tenant_b = storage.namespace(Namespace::new("tenant_b").unwrap());
+
+        tenant_a.save_file("a.txt", b"a").await.unwrap();
+        tenant_a.save_file("docs/a2.txt", b"a2").await.unwrap();
+        tenant_b.save_file("b.txt", b"b").await.unwrap();
+        storage.save_file("global.txt", b"g").await.unwrap();
+
+        assert_eq!(
+            tenant_a.list_files().await.unwrap(),
+            vec!["a.txt".to_string(), "docs/a2.txt".to_string()]
+        );
+        assert_eq!(
+            tenant_b.list_files().await.unwrap(),
+            vec!["b.txt".to_string()]
+        );
+        assert_eq!(
+            StorageManager::list_files(&storage).await.unwrap(),
+            vec!["global.txt".to_string()]
+        );
+
+        let (files, subdirs) = tenant_a.list_directory("/").await.unwrap();
+        assert_eq!(files, vec!["a.txt".to_string()]);
+        assert_eq!(subdirs, vec!["docs".to_string()]);
+
+        let (files, subdirs) = storage.list_directory("/").await.unwrap();
+        assert_eq!(files, vec!["global.txt".to_string()]);
+        assert!(subdirs.is_empty());
+
+        // 软删除后不再出现在租户列表中
+        tenant_a.delete_file("a.txt").await.unwrap();
+        assert_eq!(
+            tenant_a.list_files().await.unwrap(),
+            vec!["docs/a2.txt".to_string()]
+        );
+    }
+}
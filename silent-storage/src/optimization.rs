@@ -4,10 +4,10 @@
 
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, OwnedSemaphorePermit, RwLock, Semaphore};
 use tracing::{debug, error, info, warn};
 
 /// 优化策略 - 决定如何优化文件
@@ -24,8 +24,8 @@ pub enum OptimizationStrategy {
 impl OptimizationStrategy {
     /// 根据文件类型和大小决定优化策略
     pub fn decide(file_type: &crate::core::FileType, file_size: u64) -> Self {
-        // 已压缩文件跳过优化
-        if file_type.is_compressed() {
+        // 空文件和已压缩文件跳过优化
+        if file_size == 0 || file_type.is_compressed() {
             return Self::Skip;
         }
 
@@ -181,6 +181,9 @@ pub struct OptimizationStats {
     pub pending_tasks: usize,
     /// 执行中任务数
     pub running_tasks: usize,
+    /// 同时执行任务数的峰值
+    #[serde(default)]
+    pub peak_running_tasks: usize,
     /// 已完成任务数
     pub completed_tasks: usize,
     /// 失败任务数
@@ -193,6 +196,16 @@ pub struct OptimizationStats {
     pub optimized_size: u64,
 }
 
+/// 每个 CPU 的 1 分钟平均负载
+///
+/// 读取 `/proc/loadavg`，不支持的平台返回 `None`（不做负载退避）。
+pub fn system_load_per_cpu() -> Option<f64> {
+    let loadavg = std::fs::read_to_string("/proc/loadavg").ok()?;
+    let load: f64 = loadavg.split_whitespace().next()?.parse().ok()?;
+    let cpus = std::thread::available_parallelism().ok()?.get();
+    Some(load / cpus as f64)
+}
+
 /// 任务优先级包装器（用于BinaryHeap）
 /// BinaryHeap是最大堆，我们需要优先级高的任务先执行
 #[derive(Debug, Clone)]
@@ -227,11 +240,16 @@ pub struct OptimizationScheduler {
     task_queue: Arc<RwLock<BinaryHeap<PrioritizedTask>>>,
     /// 任务映射（file_id -> task_id）- 用于快速查找
     task_map: Arc<RwLock<HashMap<String, String>>>,
+    /// 执行中任务的文件，同一文件同时最多一个任务在执行或排队
+    running_files: Arc<RwLock<HashSet<String>>>,
     /// 统计信息
     stats: Arc<RwLock<OptimizationStats>>,
-    /// 最大并发任务数（预留，用于将来的并发控制）
-    #[allow(dead_code)]
+    /// 最大并发任务数
     max_concurrent: usize,
+    /// 并发槽位，每个执行中的任务持有一个
+    slots: Arc<Semaphore>,
+    /// 提交新任务或停止时唤醒空闲的执行循环
+    wakeup: Arc<Notify>,
     /// 调度器是否运行
     running: Arc<RwLock<bool>>,
     /// 后台任务句柄
@@ -239,32 +257,39 @@ pub struct OptimizationScheduler {
 }
 
 impl OptimizationScheduler {
-    /// 创建新的调度器
+    /// 创建新的调度器，`max_concurrent` 为同时执行的最大任务数（至少为 1）
     pub fn new(max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
         Self {
             task_queue: Arc::new(RwLock::new(BinaryHeap::new())),
             task_map: Arc::new(RwLock::new(HashMap::new())),
+            running_files: Arc::new(RwLock::new(HashSet::new())),
             stats: Arc::new(RwLock::new(OptimizationStats::default())),
             max_concurrent,
+            slots: Arc::new(Semaphore::new(max_concurrent)),
+            wakeup: Arc::new(Notify::new()),
             running: Arc::new(RwLock::new(false)),
             scheduler_handle: Arc::new(RwLock::new(None)),
         }
     }
 
     /// 提交优化任务
-    pub async fn submit_task(&self, task: OptimizationTask) {
+    ///
+    /// 同一文件已有排队或执行中的任务时不再提交，返回 `false`；
+    /// 任务结束（完成、失败或跳过）后可再次提交。
+    pub async fn submit_task(&self, task: OptimizationTask) -> bool {
         let file_id = task.file_id.clone();
         let task_id = task.task_id.clone();
 
-        // 检查是否已存在任务
+        // 加锁顺序与其他方法一致：队列 → 映射 → 执行中集合
+        let mut queue = self.task_queue.write().await;
         let mut task_map = self.task_map.write().await;
-        if task_map.contains_key(&file_id) {
+        if task_map.contains_key(&file_id) || self.running_files.read().await.contains(&file_id) {
             warn!("文件 {} 已有优化任务，跳过", file_id);
-            return;
+            return false;
         }
 
         // 添加到队列
-        let mut queue = self.task_queue.write().await;
         queue.push(PrioritizedTask { task: task.clone() });
         task_map.insert(file_id, task_id);
 
@@ -272,11 +297,13 @@ impl OptimizationScheduler {
         let mut stats = self.stats.write().await;
         stats.total_tasks += 1;
         stats.pending_tasks += 1;
+        self.wakeup.notify_one();
 
         debug!(
             "优化任务已提交: file_id={}, priority={}, strategy={:?}",
             task.file_id, task.priority, task.strategy
         );
+        true
     }
 
     /// 获取下一个就绪的任务
@@ -290,8 +317,12 @@ impl OptimizationScheduler {
 
         while let Some(prioritized) = queue.pop() {
             if prioritized.task.is_ready() {
-                // 找到就绪任务
+                // 找到就绪任务，执行结束前同一文件不再接受新任务
                 task_map.remove(&prioritized.task.file_id);
+                self.running_files
+                    .write()
+                    .await
+                    .insert(prioritized.task.file_id.clone());
                 result = Some(prioritized.task);
                 break;
             } else {
@@ -310,6 +341,7 @@ impl OptimizationScheduler {
             let mut stats = self.stats.write().await;
             stats.pending_tasks = stats.pending_tasks.saturating_sub(1);
             stats.running_tasks += 1;
+            stats.peak_running_tasks = stats.peak_running_tasks.max(stats.running_tasks);
 
             info!(
                 "获取优化任务: file_id={}, priority={}",
@@ -322,6 +354,7 @@ impl OptimizationScheduler {
 
     /// 标记任务完成
     pub async fn mark_task_completed(&self, file_id: &str, space_saved: u64, optimized_size: u64) {
+        self.running_files.write().await.remove(file_id);
         let mut stats = self.stats.write().await;
         stats.running_tasks = stats.running_tasks.saturating_sub(1);
         stats.completed_tasks += 1;
@@ -333,6 +366,7 @@ impl OptimizationScheduler {
 
     /// 标记任务失败
     pub async fn mark_task_failed(&self, file_id: &str, error: &str) {
+        self.running_files.write().await.remove(file_id);
         let mut stats = self.stats.write().await;
         stats.running_tasks = stats.running_tasks.saturating_sub(1);
         stats.failed_tasks += 1;
@@ -342,6 +376,7 @@ impl OptimizationScheduler {
 
     /// 标记任务跳过
     pub async fn mark_task_skipped(&self, file_id: &str, reason: &str) {
+        self.running_files.write().await.remove(file_id);
         let mut stats = self.stats.write().await;
         stats.running_tasks = stats.running_tasks.saturating_sub(1);
         stats.skipped_tasks += 1;
@@ -363,6 +398,59 @@ impl OptimizationScheduler {
         self.submit_task(task).await;
     }
 
+    /// 推迟任务（不计入重试次数，用于磁盘空间不足等暂时性状况）
+    pub async fn defer_task(&self, mut task: OptimizationTask, delay_secs: u64) {
+        task.reset_for_retry(delay_secs);
+
+        let mut queue = self.task_queue.write().await;
+        let mut task_map = self.task_map.write().await;
+        self.running_files.write().await.remove(&task.file_id);
+        let mut stats = self.stats.write().await;
+        stats.running_tasks = stats.running_tasks.saturating_sub(1);
+
+        // 期间已提交了新任务，则以新任务为准
+        if task_map.contains_key(&task.file_id) {
+            return;
+        }
+
+        task_map.insert(task.file_id.clone(), task.task_id.clone());
+        queue.push(PrioritizedTask { task });
+        stats.pending_tasks += 1;
+    }
+
+    /// 最大并发任务数
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// 等待一个空闲的并发槽位，任务执行期间持有返回的许可
+    pub async fn acquire_slot(&self) -> OwnedSemaphorePermit {
+        self.slots
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("优化调度器的并发信号量不会关闭")
+    }
+
+    /// 等待所有执行中的任务结束
+    pub async fn wait_idle(&self) {
+        let _all = self
+            .slots
+            .acquire_many(self.max_concurrent as u32)
+            .await
+            .expect("优化调度器的并发信号量不会关闭");
+    }
+
+    /// 等待新任务提交或 [`Self::wake`]，最长等待 `timeout`
+    pub async fn wait_for_work(&self, timeout: std::time::Duration) {
+        let _ = tokio::time::timeout(timeout, self.wakeup.notified()).await;
+    }
+
+    /// 唤醒等待中的执行循环（用于停止）
+    pub fn wake(&self) {
+        self.wakeup.notify_one();
+    }
+
     /// 获取统计信息
     pub async fn get_stats(&self) -> OptimizationStats {
         self.stats.read().await.clone()
@@ -453,6 +541,12 @@ mod tests {
             OptimizationStrategy::decide(&text_type, 10_000_000),
             OptimizationStrategy::Full
         );
+
+        // 空文件跳过
+        assert_eq!(
+            OptimizationStrategy::decide(&FileType::detect(b""), 0),
+            OptimizationStrategy::Skip
+        );
     }
 
     #[test]
@@ -594,6 +688,28 @@ mod tests {
         assert!(pt1 > pt2);
     }
 
+    #[tokio::test]
+    async fn test_scheduler_slots_limit_concurrency() {
+        let scheduler = OptimizationScheduler::new(2);
+        assert_eq!(scheduler.max_concurrent(), 2);
+        assert_eq!(OptimizationScheduler::new(0).max_concurrent(), 1);
+
+        let first = scheduler.acquire_slot().await;
+        let _second = scheduler.acquire_slot().await;
+        // 槽位用尽时第三个任务需要等待
+        assert!(
+            tokio::time::timeout(
+                std::time::Duration::from_millis(50),
+                scheduler.acquire_slot()
+            )
+            .await
+            .is_err()
+        );
+
+        drop(first);
+        let _third = scheduler.acquire_slot().await;
+    }
+
     #[tokio::test]
     async fn test_scheduler_submit_and_queue_len() {
         let scheduler = OptimizationScheduler::new(2);
@@ -644,6 +760,39 @@ mod tests {
         assert_eq!(scheduler.queue_len().await, 1);
     }
 
+    #[tokio::test]
+    async fn test_scheduler_dedups_running_file() {
+        let scheduler = OptimizationScheduler::new(2);
+        let new_task = || {
+            OptimizationTask::new(
+                "file1".to_string(),
+                PathBuf::from("/tmp/file1"),
+                1_000_000,
+                "hash1".to_string(),
+                OptimizationStrategy::Full,
+                0,
+            )
+        };
+
+        // 并发提交同一文件，只有一个被接受
+        let (a, b) = tokio::join!(
+            scheduler.submit_task(new_task()),
+            scheduler.submit_task(new_task())
+        );
+        assert!(a ^ b);
+
+        // 执行期间再次提交被拒绝，不会出现第二个执行中的任务
+        let running = scheduler.get_next_ready_task().await.unwrap();
+        assert!(!scheduler.submit_task(new_task()).await);
+        assert!(scheduler.get_next_ready_task().await.is_none());
+        assert_eq!(scheduler.get_stats().await.total_tasks, 1);
+
+        // 完成后可以再次提交
+        scheduler.mark_task_completed(&running.file_id, 0, 0).await;
+        assert!(scheduler.submit_task(new_task()).await);
+        assert_eq!(scheduler.queue_len().await, 1);
+    }
+
     #[tokio::test]
     async fn test_scheduler_get_next_ready_task() {
         let scheduler = OptimizationScheduler::new(2);
@@ -747,6 +896,34 @@ mod tests {
         assert_eq!(scheduler.queue_len().await, 1);
     }
 
+    #[tokio::test]
+    async fn test_scheduler_defer_task() {
+        let scheduler = OptimizationScheduler::new(2);
+
+        let task = OptimizationTask::new(
+            "file1".to_string(),
+            PathBuf::from("/tmp/file1"),
+            1_000_000,
+            "hash1".to_string(),
+            OptimizationStrategy::Full,
+            0,
+        );
+        scheduler.submit_task(task).await;
+        let task = scheduler.get_next_ready_task().await.unwrap();
+
+        scheduler.defer_task(task, 3600).await;
+
+        // 推迟的任务回到队列，但尚未就绪，且不计入重试次数
+        assert_eq!(scheduler.queue_len().await, 1);
+        assert!(scheduler.get_next_ready_task().await.is_none());
+        let pending = scheduler.get_pending_tasks().await;
+        assert_eq!(pending[0].retry_count, 0);
+
+        let stats = scheduler.get_stats().await;
+        assert_eq!(stats.pending_tasks, 1);
+        assert_eq!(stats.running_tasks, 0);
+    }
+
     #[tokio::test]
     async fn test_scheduler_start_stop() {
         let scheduler = OptimizationScheduler::new(2);
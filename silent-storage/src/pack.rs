@@ -0,0 +1,606 @@
+//! 小块打包存储
+//!
+//! 大量小文件时每个块各占一个块文件，inode 与目录项的开销、每次写入的元数据更新
+//! 都远大于数据本身。[`PackedChunkStore`] 把存储大小不超过阈值的块顺序追加到
+//! 只追加的包文件（`<root>/packs/pack-<编号>.pack`）中，并在包索引（Sled）中记录
+//! `块 ID -> (包编号, 偏移, 长度)`；超过阈值的块仍交给内层块存储。
+//! 包对存储引擎透明：读写、去重、校验与 GC 都照常按块 ID 进行。
+//!
+//! 删除块只移除索引条目，包内空间在 [`ChunkStore::compact`] 时回收：
+//! 有效数据占比低于 `repack_threshold` 的包，其仍被引用的块被复制到当前包后整体删除。
+//! 每次打开时都新建当前包，不向可能在崩溃时写了一半的旧包追加。
+
+use crate::chunk_store::ChunkStore;
+use crate::error::{Result, StorageError};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::{Mutex as AsyncMutex, OnceCell, RwLock};
+use tracing::{debug, info, warn};
+
+/// 包文件名前缀与扩展名
+const PACK_PREFIX: &str = "pack-";
+const PACK_EXTENSION: &str = "pack";
+
+/// 小块打包配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackConfig {
+    /// 是否启用打包（只影响之后写入的块，已有块文件照常读取）
+    pub enable: bool,
+    /// 存储大小不超过该值（字节）的块写入包文件
+    pub max_chunk_size: u64,
+    /// 单个包文件的大小上限（字节），写满后切换到新的包
+    pub max_pack_size: u64,
+    /// 有效数据占比低于该值（0~1）的包在整理时重写
+    pub repack_threshold: f64,
+}
+
+impl Default for PackConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            max_chunk_size: 64 * 1024,
+            max_pack_size: 64 * 1024 * 1024,
+            repack_threshold: 0.5,
+        }
+    }
+}
+
+impl PackConfig {
+    /// 校验配置
+    pub fn validate(&self) -> Result<()> {
+        if !self.enable {
+            return Ok(());
+        }
+        if self.max_chunk_size == 0 || self.max_chunk_size > self.max_pack_size {
+            return Err(StorageError::Config(
+                "packing.max_chunk_size 必须大于 0 且不超过 max_pack_size".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.repack_threshold) {
+            return Err(StorageError::Config(
+                "packing.repack_threshold 必须在 0 到 1 之间".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 块在包中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PackLocation {
+    pack: u64,
+    offset: u64,
+    len: u64,
+}
+
+impl PackLocation {
+    fn encode(&self) -> [u8; 24] {
+        let mut buf = [0u8; 24];
+        buf[..8].copy_from_slice(&self.pack.to_le_bytes());
+        buf[8..16].copy_from_slice(&self.offset.to_le_bytes());
+        buf[16..].copy_from_slice(&self.len.to_le_bytes());
+        buf
+    }
+
+    fn decode(raw: &[u8]) -> Result<Self> {
+        if raw.len() != 24 {
+            return Err(StorageError::Database(format!(
+                "包索引条目长度无效: {}",
+                raw.len()
+            )));
+        }
+        let field = |i: usize| u64::from_le_bytes(raw[i * 8..(i + 1) * 8].try_into().unwrap());
+        Ok(Self {
+            pack: field(0),
+            offset: field(1),
+            len: field(2),
+        })
+    }
+}
+
+/// 包的空间占用
+#[derive(Debug, Clone, Copy, Default)]
+struct PackUsage {
+    /// 包文件大小
+    total: u64,
+    /// 仍被索引引用的字节数
+    live: u64,
+}
+
+/// 正在追加的包
+struct ActivePack {
+    id: u64,
+    file: fs::File,
+    len: u64,
+}
+
+/// 打开后的包状态
+struct PackState {
+    /// 包索引（块 ID -> 位置）
+    index: sled::Db,
+    /// 当前包，追加写入时持有
+    active: AsyncMutex<ActivePack>,
+    /// 各包的空间占用
+    usage: Mutex<HashMap<u64, PackUsage>>,
+}
+
+/// 小块打包的块存储
+///
+/// 包装一个内层块存储：小块写入包文件，其余块交给内层存储。
+/// 查找顺序为先包索引、后内层存储，因此启用打包前写入的块文件仍可读取。
+pub struct PackedChunkStore {
+    /// 包目录
+    dir: PathBuf,
+    /// 大块使用的内层存储
+    inner: Arc<dyn ChunkStore>,
+    config: PackConfig,
+    state: OnceCell<PackState>,
+    /// 读取包文件时持有读锁，整理删除包文件时持有写锁
+    files: RwLock<()>,
+    /// 同一时刻只允许一次整理
+    compacting: AsyncMutex<()>,
+}
+
+impl PackedChunkStore {
+    /// 创建打包块存储，包文件与包索引位于 `dir`，首次访问时打开
+    pub fn new(dir: PathBuf, inner: Arc<dyn ChunkStore>, config: PackConfig) -> Self {
+        Self {
+            dir,
+            inner,
+            config,
+            state: OnceCell::new(),
+            files: RwLock::new(()),
+            compacting: AsyncMutex::new(()),
+        }
+    }
+
+    /// 包目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn pack_path(&self, pack: u64) -> PathBuf {
+        self.dir
+            .join(format!("{}{:016x}.{}", PACK_PREFIX, pack, PACK_EXTENSION))
+    }
+
+    fn parse_pack_id(name: &str) -> Option<u64> {
+        let id = name
+            .strip_prefix(PACK_PREFIX)?
+            .strip_suffix(PACK_EXTENSION)?
+            .strip_suffix('.')?;
+        u64::from_str_radix(id, 16).ok()
+    }
+
+    async fn state(&self) -> Result<&PackState> {
+        self.state.get_or_try_init(|| self.open()).await
+    }
+
+    /// 打开包索引，统计各包的空间占用并新建当前包
+    async fn open(&self) -> Result<PackState> {
+        fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| StorageError::from_io(e, "创建包目录失败"))?;
+        let index = sled::open(self.dir.join("index"))
+            .map_err(|e| StorageError::Database(format!("打开包索引失败: {}", e)))?;
+
+        let mut usage: HashMap<u64, PackUsage> = HashMap::new();
+        let mut entries = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(pack) = entry.file_name().to_str().and_then(Self::parse_pack_id) {
+                usage.entry(pack).or_default().total = entry.metadata().await?.len();
+            }
+        }
+        for item in index.iter() {
+            let (_, value) =
+                item.map_err(|e| StorageError::Database(format!("读取包索引失败: {}", e)))?;
+            let location = PackLocation::decode(&value)?;
+            usage.entry(location.pack).or_default().live += location.len;
+        }
+
+        let id = usage.keys().max().map_or(0, |max| max + 1);
+        let active = self.create_pack(id).await?;
+        usage.insert(id, PackUsage::default());
+        debug!("打开包存储: {:?}，已有 {} 个包", self.dir, usage.len() - 1);
+
+        Ok(PackState {
+            index,
+            active: AsyncMutex::new(active),
+            usage: Mutex::new(usage),
+        })
+    }
+
+    async fn create_pack(&self, id: u64) -> Result<ActivePack> {
+        let file = fs::OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(self.pack_path(id))
+            .await
+            .map_err(|e| StorageError::from_io(e, "创建包文件失败"))?;
+        Ok(ActivePack { id, file, len: 0 })
+    }
+
+    fn lookup(state: &PackState, chunk_id: &str) -> Result<Option<PackLocation>> {
+        state
+            .index
+            .get(chunk_id)
+            .map_err(|e| StorageError::Database(format!("读取包索引失败: {}", e)))?
+            .map(|raw| PackLocation::decode(&raw))
+            .transpose()
+    }
+
+    /// 追加数据到当前包，写满时先切换到新包
+    ///
+    /// 写入失败时截断回写入前的长度，不留下部分写入的数据。
+    async fn append(
+        &self,
+        state: &PackState,
+        active: &mut ActivePack,
+        data: &[u8],
+    ) -> Result<PackLocation> {
+        if active.len > 0 && active.len + data.len() as u64 > self.config.max_pack_size {
+            let next = self.create_pack(active.id + 1).await?;
+            *active = next;
+            state
+                .usage
+                .lock()
+                .unwrap()
+                .insert(active.id, PackUsage::default());
+        }
+
+        let offset = active.len;
+        let write_result = async {
+            active.file.write_all(data).await?;
+            active.file.flush().await
+        }
+        .await;
+        if let Err(e) = write_result {
+            let _ = active.file.set_len(offset).await;
+            return Err(StorageError::from_io(e, "写入包文件失败"));
+        }
+        active.len += data.len() as u64;
+        state
+            .usage
+            .lock()
+            .unwrap()
+            .entry(active.id)
+            .or_default()
+            .total = active.len;
+
+        Ok(PackLocation {
+            pack: active.id,
+            offset,
+            len: data.len() as u64,
+        })
+    }
+
+    /// 将包文件的数据落盘
+    async fn sync_pack(&self, pack: u64) -> Result<()> {
+        fs::File::open(self.pack_path(pack))
+            .await?
+            .sync_all()
+            .await
+            .map_err(|e| StorageError::from_io(e, "同步包文件失败"))
+    }
+
+    async fn read_at(&self, location: PackLocation) -> Result<Vec<u8>> {
+        let mut file = fs::File::open(self.pack_path(location.pack)).await?;
+        file.seek(SeekFrom::Start(location.offset)).await?;
+        let mut data = vec![0u8; location.len as usize];
+        file.read_exact(&mut data).await?;
+        Ok(data)
+    }
+
+    fn add_live(state: &PackState, pack: u64, delta: i64) {
+        let mut usage = state.usage.lock().unwrap();
+        let entry = usage.entry(pack).or_default();
+        entry.live = entry.live.saturating_add_signed(delta);
+    }
+
+    /// 需要重写的包（不含当前包）
+    fn packs_to_rewrite(&self, state: &PackState, active: u64) -> Vec<(u64, PackUsage)> {
+        state
+            .usage
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, usage)| {
+                **id != active
+                    && (usage.live == 0
+                        || (usage.live as f64) < usage.total as f64 * self.config.repack_threshold)
+            })
+            .map(|(id, usage)| (*id, *usage))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ChunkStore for PackedChunkStore {
+    async fn put(&self, chunk_id: &str, data: &[u8]) -> Result<bool> {
+        if data.len() as u64 > self.config.max_chunk_size {
+            if self.exists(chunk_id).await? {
+                return Ok(false);
+            }
+            return self.inner.put(chunk_id, data).await;
+        }
+
+        let state = self.state().await?;
+        if Self::lookup(state, chunk_id)?.is_some() || self.inner.exists(chunk_id).await? {
+            return Ok(false);
+        }
+
+        let mut active = state.active.lock().await;
+        // 等待写锁期间可能已有并发写入者写入同一块
+        if Self::lookup(state, chunk_id)?.is_some() {
+            return Ok(false);
+        }
+        let location = self.append(state, &mut active, data).await?;
+        // 包数据落盘后才发布索引条目，崩溃后索引不会指向包文件末尾之外
+        active
+            .file
+            .sync_data()
+            .await
+            .map_err(|e| StorageError::from_io(e, "同步包文件失败"))?;
+        state
+            .index
+            .insert(chunk_id, &location.encode()[..])
+            .map_err(|e| StorageError::Database(format!("写入包索引失败: {}", e)))?;
+        Self::add_live(state, location.pack, location.len as i64);
+        Ok(true)
+    }
+
+    async fn get(&self, chunk_id: &str) -> Result<Vec<u8>> {
+        let state = self.state().await?;
+        let _files = self.files.read().await;
+        match Self::lookup(state, chunk_id)? {
+            Some(location) => self.read_at(location).await,
+            None => self.inner.get(chunk_id).await,
+        }
+    }
+
+    async fn exists(&self, chunk_id: &str) -> Result<bool> {
+        let state = self.state().await?;
+        Ok(Self::lookup(state, chunk_id)?.is_some() || self.inner.exists(chunk_id).await?)
+    }
+
+    /// 包中的块只移除索引条目，返回其占用的字节数，空间在整理时实际释放
+    async fn delete(&self, chunk_id: &str) -> Result<Option<u64>> {
+        let state = self.state().await?;
+        let removed = state
+            .index
+            .remove(chunk_id)
+            .map_err(|e| StorageError::Database(format!("删除包索引条目失败: {}", e)))?;
+        match removed {
+            Some(raw) => {
+                let location = PackLocation::decode(&raw)?;
+                Self::add_live(state, location.pack, -(location.len as i64));
+                Ok(Some(location.len))
+            }
+            None => self.inner.delete(chunk_id).await,
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let state = self.state().await?;
+        let mut chunk_ids = Vec::new();
+        for key in state.index.iter().keys() {
+            let key = key.map_err(|e| StorageError::Database(format!("读取包索引失败: {}", e)))?;
+            chunk_ids.push(String::from_utf8_lossy(&key).into_owned());
+        }
+        chunk_ids.extend(self.inner.list().await?);
+        Ok(chunk_ids)
+    }
+
+    async fn stored_size(&self, chunk_id: &str) -> Result<Option<u64>> {
+        let state = self.state().await?;
+        match Self::lookup(state, chunk_id)? {
+            Some(location) => Ok(Some(location.len)),
+            None => self.inner.stored_size(chunk_id).await,
+        }
+    }
+
+    /// 包中的块为 `<包目录>/<块 ID>` 形式的逻辑键（重写后包编号会变化）
+    fn location(&self, chunk_id: &str) -> PathBuf {
+        let packed = self
+            .state
+            .get()
+            .is_some_and(|state| state.index.contains_key(chunk_id).unwrap_or(false));
+        if packed {
+            self.dir.join(chunk_id)
+        } else {
+            self.inner.location(chunk_id)
+        }
+    }
+
+    /// 刷新包索引（包数据在写入时已落盘），再交给内层存储
+    async fn sync(&self) -> Result<()> {
+        if let Some(state) = self.state.get() {
+            state
+                .index
+                .flush_async()
+                .await
+                .map_err(|e| StorageError::Database(format!("刷新包索引失败: {}", e)))?;
+        }
+        self.inner.sync().await
+    }
+
+    /// 重写有效数据占比过低的包：仍被引用的块复制到当前包，旧包文件整体删除
+    async fn compact(&self) -> Result<u64> {
+        let _compacting = self.compacting.lock().await;
+        let state = self.state().await?;
+        let active_id = state.active.lock().await.id;
+        let candidates = self.packs_to_rewrite(state, active_id);
+        if candidates.is_empty() {
+            return self.inner.compact().await;
+        }
+
+        // 一次扫描索引，收集各待重写包中仍被引用的块
+        let mut live: HashMap<u64, Vec<(sled::IVec, PackLocation)>> =
+            candidates.iter().map(|(id, _)| (*id, Vec::new())).collect();
+        for item in state.index.iter() {
+            let (key, value) =
+                item.map_err(|e| StorageError::Database(format!("读取包索引失败: {}", e)))?;
+            let location = PackLocation::decode(&value)?;
+            if let Some(entries) = live.get_mut(&location.pack) {
+                entries.push((key, location));
+            }
+        }
+
+        // 先把仍被引用的块复制到当前包，复制的数据落盘后再更新索引
+        let mut written = HashSet::new();
+        for (pack, _) in &candidates {
+            let mut copied = Vec::new();
+            for (key, old) in live.remove(pack).unwrap_or_default() {
+                let data = self.read_at(old).await?;
+                let mut active = state.active.lock().await;
+                let new = self.append(state, &mut active, &data).await?;
+                written.insert(new.pack);
+                copied.push((key, old, new));
+            }
+            for pack in &written {
+                self.sync_pack(*pack).await?;
+            }
+            for (key, old, new) in copied {
+                // 复制期间块可能已被删除，只在索引仍指向旧位置时更新
+                let swapped = state
+                    .index
+                    .compare_and_swap(&key, Some(&old.encode()[..]), Some(&new.encode()[..]))
+                    .map_err(|e| StorageError::Database(format!("更新包索引失败: {}", e)))?;
+                if swapped.is_ok() {
+                    Self::add_live(state, new.pack, new.len as i64);
+                    Self::add_live(state, *pack, -(old.len as i64));
+                }
+            }
+        }
+
+        // 新索引落盘后才能删除旧包：否则中途崩溃会留下指向已删除包的索引条目
+        state
+            .index
+            .flush_async()
+            .await
+            .map_err(|e| StorageError::Database(format!("刷新包索引失败: {}", e)))?;
+
+        let mut freed = 0u64;
+        for (pack, usage) in candidates {
+            let _files = self.files.write().await;
+            match fs::remove_file(self.pack_path(pack)).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    warn!("删除包文件 {} 失败: {}", pack, e);
+                    continue;
+                }
+            }
+            state.usage.lock().unwrap().remove(&pack);
+            freed += usage.total.saturating_sub(usage.live);
+        }
+
+        info!("包整理完成，释放 {} 字节", freed);
+        Ok(freed + self.inner.compact().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_store::MemoryChunkStore;
+    use tempfile::TempDir;
+
+    fn packed_store(dir: &Path, inner: Arc<MemoryChunkStore>) -> PackedChunkStore {
+        PackedChunkStore::new(
+            dir.join("packs"),
+            inner,
+            PackConfig {
+                enable: true,
+                max_chunk_size: 16,
+                max_pack_size: 64,
+                repack_threshold: 0.5,
+            },
+        )
+    }
+
+    async fn pack_files(dir: &Path) -> Vec<PathBuf> {
+        let mut packs = Vec::new();
+        let mut entries = fs::read_dir(dir).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            if entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == PACK_EXTENSION)
+            {
+                packs.push(entry.path());
+            }
+        }
+        packs
+    }
+
+    #[tokio::test]
+    async fn test_small_chunks_packed_and_large_chunks_delegated() {
+        let temp_dir = TempDir::new().unwrap();
+        let inner = Arc::new(MemoryChunkStore::new());
+        let store = packed_store(temp_dir.path(), inner.clone());
+
+        assert!(store.put("small", b"tiny").await.unwrap());
+        assert!(!store.put("small", b"tiny").await.unwrap());
+        assert!(store.put("large", &[7u8; 32]).await.unwrap());
+        assert_eq!(inner.len().await, 1);
+
+        assert_eq!(store.get("small").await.unwrap(), b"tiny");
+        assert_eq!(store.get("large").await.unwrap(), vec![7u8; 32]);
+        assert_eq!(store.stored_size("small").await.unwrap(), Some(4));
+        let mut listed = store.list().await.unwrap();
+        listed.sort();
+        assert_eq!(listed, vec!["large".to_string(), "small".to_string()]);
+
+        assert_eq!(store.delete("small").await.unwrap(), Some(4));
+        assert!(!store.exists("small").await.unwrap());
+        assert_eq!(store.delete("large").await.unwrap(), Some(32));
+        assert!(inner.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_compact_rewrites_sparse_packs() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = packed_store(temp_dir.path(), Arc::new(MemoryChunkStore::new()));
+
+        // 每块 16 字节，每个包 4 块
+        for i in 0..12 {
+            let data = format!("chunk-data-{:05}", i);
+            store
+                .put(&format!("c{}", i), data.as_bytes())
+                .await
+                .unwrap();
+        }
+        assert_eq!(pack_files(store.dir()).await.len(), 3);
+
+        // 第一个包删除 3 块，第二个包全部删除
+        for i in [0, 1, 2, 4, 5, 6, 7] {
+            store.delete(&format!("c{}", i)).await.unwrap();
+        }
+        let freed = store.compact().await.unwrap();
+        assert_eq!(freed, 7 * 16);
+        // 两个旧包都被删除，第一个包剩余的块搬到新的当前包（原当前包已满）
+        assert_eq!(pack_files(store.dir()).await.len(), 2);
+
+        for i in [3, 8, 9, 10, 11] {
+            assert_eq!(
+                store.get(&format!("c{}", i)).await.unwrap(),
+                format!("chunk-data-{:05}", i).into_bytes()
+            );
+        }
+        assert!(store.get("c0").await.is_err());
+
+        // 重新打开后索引与包文件保持一致
+        drop(store);
+        let store = packed_store(temp_dir.path(), Arc::new(MemoryChunkStore::new()));
+        assert_eq!(store.get("c3").await.unwrap(), b"chunk-data-00003".to_vec());
+        assert_eq!(store.list().await.unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,377 @@
+//! 顺序块读取器
+//!
+//! 按偏移顺序逐块读取版本数据，并在调用方消费当前块的同时
+//! 并发预取后续 N 个块，使顺序下载时磁盘 IO 与数据处理重叠。
+//! 下载时可再包一层 [`VerifyingReader`]，边读取边校验整文件哈希。
+
+use crate::ChunkInfo;
+use crate::error::{Result, StorageError};
+use crate::storage::StorageManager;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::task::JoinHandle;
+
+/// 带预取的顺序块读取器
+///
+/// 通过 [`StorageManager::open_version_reader`] 创建。
+/// 预取窗口不会越过文件末尾，读取器被丢弃时未完成的预取会被取消。
+pub struct ChunkStreamReader {
+    /// 存储管理器
+    storage: Arc<StorageManager>,
+    /// 按偏移排序的块列表
+    chunks: Vec<ChunkInfo>,
+    /// 下一个待发起读取的块索引
+    next_fetch: usize,
+    /// 已发起的读取（按块顺序）
+    in_flight: VecDeque<JoinHandle<Result<Vec<u8>>>>,
+    /// 预取块数量（0 表示按需读取）
+    prefetch: usize,
+    /// 无法按块读取时（如旧版增量链）预先加载的完整数据
+    preloaded: Option<Vec<u8>>,
+}
+
+impl ChunkStreamReader {
+    /// 创建按块读取的读取器
+    pub(crate) fn new(
+        storage: Arc<StorageManager>,
+        mut chunks: Vec<ChunkInfo>,
+        prefetch: usize,
+    ) -> Self {
+        chunks.sort_by_key(|c| c.offset);
+
+        Self {
+            storage,
+            chunks,
+            next_fetch: 0,
+            in_flight: VecDeque::new(),
+            prefetch,
+            preloaded: None,
+        }
+    }
+
+    /// 创建基于已加载数据的读取器
+    pub(crate) fn preloaded(storage: Arc<StorageManager>, data: Vec<u8>) -> Self {
+        let mut reader = Self::new(storage, Vec::new(), 0);
+        reader.preloaded = (!data.is_empty()).then_some(data);
+        reader
+    }
+
+    /// 设置预取块数量（覆盖配置中的 `prefetch_chunks`）
+    pub fn with_prefetch(mut self, prefetch: usize) -> Self {
+        self.prefetch = prefetch;
+        self
+    }
+
+    /// 块总数
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// 读取下一个块，读到文件末尾时返回 `None`
+    pub async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        if let Some(data) = self.preloaded.take() {
+            return Ok(Some(data));
+        }
+
+        // 至少保证当前块已发起读取
+        self.fill_window(self.prefetch.max(1));
+
+        let Some(handle) = self.in_flight.pop_front() else {
+            return Ok(None);
+        };
+        let data = handle
+            .await
+            .map_err(|e| StorageError::Chunk(format!("块读取任务失败: {}", e)))??;
+
+        // 调用方处理当前块时，后续块已在读取
+        self.fill_window(self.prefetch);
+
+        Ok(Some(data))
+    }
+
+    /// 读取全部剩余数据
+    pub async fn read_to_end(&mut self) -> Result<Vec<u8>> {
+        let mut result = Vec::new();
+        while let Some(chunk) = self.next_chunk().await? {
+            result.extend_from_slice(&chunk);
+        }
+        Ok(result)
+    }
+
+    /// 转换为 [`AsyncRead`]，预取行为不变
+    pub fn into_async_read(self) -> ChunkAsyncReader {
+        ChunkAsyncReader {
+            state: ReadState::Idle(self),
+            buffer: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// 发起读取，直到在途请求达到窗口大小或没有剩余块
+    fn fill_window(&mut self, window: usize) {
+        while self.in_flight.len() < window && self.next_fetch < self.chunks.len() {
+            let chunk = self.chunks[self.next_fetch].clone();
+            let storage = self.storage.clone();
+            self.in_flight.push_back(tokio::spawn(async move {
+                storage.read_chunk(&chunk.chunk_id, chunk.compression).await
+            }));
+            self.next_fetch += 1;
+        }
+    }
+}
+
+impl Drop for ChunkStreamReader {
+    fn drop(&mut self) {
+        for handle in &self.in_flight {
+            handle.abort();
+        }
+    }
+}
+
+type NextChunk = Pin<Box<dyn Future<Output = (ChunkStreamReader, Result<Option<Vec<u8>>>)> + Send>>;
+
+enum ReadState {
+    /// 等待下一次读取
+    Idle(ChunkStreamReader),
+    /// 正在读取下一个块
+    Reading(NextChunk),
+    /// 已读到末尾或出错
+    Done,
+}
+
+/// 以 [`AsyncRead`] 形式读取块数据
+///
+/// 通过 [`ChunkStreamReader::into_async_read`] 创建，逐块读取并缓存当前块，
+/// 读取出错后返回 IO 错误并结束。
+pub struct ChunkAsyncReader {
+    state: ReadState,
+    /// 当前块中尚未读出的数据
+    buffer: Vec<u8>,
+    pos: usize,
+}
+
+impl AsyncRead for ChunkAsyncReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.pos < this.buffer.len() {
+                let n = buf.remaining().min(this.buffer.len() - this.pos);
+                buf.put_slice(&this.buffer[this.pos..this.pos + n]);
+                this.pos += n;
+                return Poll::Ready(Ok(()));
+            }
+
+            match std::mem::replace(&mut this.state, ReadState::Done) {
+                ReadState::Idle(mut reader) => {
+                    this.state = ReadState::Reading(Box::pin(async move {
+                        let result = reader.next_chunk().await;
+                        (reader, result)
+                    }));
+                }
+                ReadState::Reading(mut next) => match next.as_mut().poll(cx) {
+                    Poll::Pending => {
+                        this.state = ReadState::Reading(next);
+                        return Poll::Pending;
+                    }
+                    Poll::Ready((reader, Ok(Some(chunk)))) => {
+                        this.buffer = chunk;
+                        this.pos = 0;
+                        this.state = ReadState::Idle(reader);
+                    }
+                    Poll::Ready((_, Ok(None))) => return Poll::Ready(Ok(())),
+                    Poll::Ready((_, Err(e))) => {
+                        return Poll::Ready(Err(std::io::Error::other(e)));
+                    }
+                },
+                ReadState::Done => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+}
+
+/// 边读取边校验整文件 SHA-256 的读取句柄
+///
+/// 开启 `verify_downloads` 时由 [`StorageManager::open_read`] 创建。读到末尾时与记录的
+/// 整文件哈希比对，不一致说明重建出的内容已损坏：已读出的数据无法撤回，只通过
+/// [`StorageManager::report_corrupt_read`] 记录损坏事件。未读到末尾（如客户端中途断开）时不校验。
+pub struct VerifyingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    storage: Arc<StorageManager>,
+    version_id: String,
+    /// 记录的整文件哈希（十六进制）
+    expected: String,
+    /// 已读到末尾并完成校验
+    verified: bool,
+}
+
+impl<R> VerifyingReader<R> {
+    pub(crate) fn new(
+        inner: R,
+        storage: Arc<StorageManager>,
+        version_id: String,
+        expected: String,
+    ) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            storage,
+            version_id,
+            expected,
+            verified: false,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for VerifyingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+
+        let read = &buf.filled()[before..];
+        if !read.is_empty() {
+            this.hasher.update(read);
+        } else if buf.remaining() > 0 && !this.verified {
+            // 缓冲区有空间却没有读出数据：已到末尾
+            this.verified = true;
+            let actual = hex::encode(std::mem::take(&mut this.hasher).finalize());
+            if !actual.eq_ignore_ascii_case(&this.expected) {
+                this.storage
+                    .report_corrupt_read(&this.version_id, &this.expected, &actual);
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::IncrementalConfig;
+    use crate::chunk_store::{ChunkStore, MemoryChunkStore};
+    use crate::storage::StorageManager;
+    use crate::test_util::{test_data, test_storage};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_sequential_read_with_and_without_prefetch() {
+        let (storage, _temp) = test_storage().await;
+        let data = test_data(256 * 1024, 0);
+        let (_, version) = storage.save_version("big_file", &data, None).await.unwrap();
+
+        for prefetch in [0, 1, 4, 64] {
+            let mut reader = storage
+                .open_version_reader(&version.version_id)
+                .await
+                .unwrap()
+                .with_prefetch(prefetch);
+            assert!(reader.chunk_count() > 1);
+            assert_eq!(reader.read_to_end().await.unwrap(), data);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_stops_at_end_of_file() {
+        let (storage, _temp) = test_storage().await;
+        let data = test_data(32 * 1024, 0);
+        let (_, version) = storage
+            .save_version("small_file", &data, None)
+            .await
+            .unwrap();
+
+        let mut reader = storage
+            .open_version_reader(&version.version_id)
+            .await
+            .unwrap()
+            .with_prefetch(1000);
+        let total = reader.chunk_count();
+
+        let mut read = 0;
+        while reader.next_chunk().await.unwrap().is_some() {
+            read += 1;
+            assert!(reader.next_fetch <= total);
+            assert!(reader.in_flight.len() <= total - read);
+        }
+        assert_eq!(read, total);
+        assert!(reader.next_chunk().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_empty_file() {
+        let (storage, _temp) = test_storage().await;
+        let (_, version) = storage.save_version("empty", b"", None).await.unwrap();
+
+        let mut reader = storage
+            .open_version_reader(&version.version_id)
+            .await
+            .unwrap();
+        assert!(reader.next_chunk().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_streaming_corrupted_chunk_records_corruption() {
+        let temp_dir = TempDir::new().unwrap();
+        let chunk_store = Arc::new(MemoryChunkStore::new());
+        let config = IncrementalConfig {
+            enable_compression: false,
+            enable_auto_gc: false,
+            verify_downloads: true,
+            ..Default::default()
+        };
+        let storage = StorageManager::with_chunk_store(
+            temp_dir.path().to_path_buf(),
+            4096,
+            config,
+            chunk_store.clone(),
+        );
+        storage.init().await.unwrap();
+
+        let data = test_data(64 * 1024, 0);
+        let (delta, version) = storage.save_version("big_file", &data, None).await.unwrap();
+
+        // 完好的文件校验通过
+        let mut read = Vec::new();
+        let mut reader = storage.open_read(&version.version_id).await.unwrap();
+        reader.read_to_end(&mut read).await.unwrap();
+        assert_eq!(read, data);
+        assert_eq!(storage.corrupt_read_count(), 0);
+
+        // 篡改一个块的内容（长度不变）
+        let chunk_id = &delta.chunks[1].chunk_id;
+        let mut corrupted = chunk_store.get(chunk_id).await.unwrap();
+        corrupted[0] ^= 0xff;
+        chunk_store.delete(chunk_id).await.unwrap();
+        chunk_store.put(chunk_id, &corrupted).await.unwrap();
+
+        // 数据照常流出，读完后记录一次损坏事件，之后的读取改走强一致路径
+        let mut read = Vec::new();
+        let mut reader = storage.open_read(&version.version_id).await.unwrap();
+        reader.read_to_end(&mut read).await.unwrap();
+        assert_eq!(read.len(), data.len());
+        assert_ne!(read, data);
+        assert_eq!(storage.corrupt_read_count(), 1);
+        assert!(storage.is_suspect_version(&version.version_id));
+
+        // 中途放弃的读取不校验
+        let mut reader = storage.open_read(&version.version_id).await.unwrap();
+        let mut head = vec![0u8; 1024];
+        reader.read_exact(&mut head).await.unwrap();
+        drop(reader);
+        assert_eq!(storage.corrupt_read_count(), 1);
+    }
+}
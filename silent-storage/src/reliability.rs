@@ -2,11 +2,16 @@
 //!
 //! 提供 WAL、数据校验、自动修复和孤儿资源清理功能
 
+use crate::chunk_store::{ChunkStore, LocalChunkStore};
+use crate::core::compression::{CompressionAlgorithm, Compressor};
 use crate::error::Result;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 use tracing::{error, info, warn};
@@ -170,6 +175,19 @@ impl WalManager {
         Ok(entries)
     }
 
+    /// 将 WAL 文件落盘
+    pub async fn sync(&self) -> Result<()> {
+        if !self.wal_path.exists() {
+            return Ok(());
+        }
+        let file = fs::OpenOptions::new()
+            .append(true)
+            .open(&self.wal_path)
+            .await?;
+        file.sync_all().await?;
+        Ok(())
+    }
+
     /// 清空 WAL
     pub async fn clear(&mut self) -> Result<()> {
         fs::remove_file(&self.wal_path).await?;
@@ -180,117 +198,239 @@ impl WalManager {
     }
 }
 
+/// 块的预期内容（来自版本记录的块信息）
+///
+/// 加盐或压缩的块，其 ID 不等于存储字节的 SHA-256，需解压后与记录的强哈希比较。
+#[derive(Debug, Clone)]
+pub struct ExpectedChunk {
+    /// 块数据本身（解压后）的 SHA-256
+    pub strong_hash: String,
+    /// 写入时使用的压缩算法
+    pub compression: CompressionAlgorithm,
+}
+
 /// Chunk 校验器
 pub struct ChunkVerifier {
-    chunk_root: PathBuf,
+    store: Arc<dyn ChunkStore>,
+    /// 只用于解压，不使用压缩配置
+    decompressor: Compressor,
 }
 
 impl ChunkVerifier {
-    /// 创建新的校验器
+    /// 创建校验本地块目录的校验器
     pub fn new(chunk_root: PathBuf) -> Self {
-        Self { chunk_root }
+        Self::with_store(Arc::new(LocalChunkStore::new(chunk_root)))
     }
 
-    /// 获取 chunk 实际路径（处理分层存储）
-    fn get_chunk_path(&self, chunk_hash: &str) -> PathBuf {
-        let prefix = &chunk_hash[..2.min(chunk_hash.len())];
-        self.chunk_root.join("data").join(prefix).join(chunk_hash)
+    /// 创建校验指定块存储的校验器
+    pub fn with_store(store: Arc<dyn ChunkStore>) -> Self {
+        Self {
+            store,
+            decompressor: Compressor::new(Default::default()),
+        }
     }
 
-    /// 验证单个 chunk
+    /// 验证单个 chunk（存储字节的 SHA-256 应等于 chunk ID）
     pub async fn verify_chunk(&self, chunk_hash: &str) -> Result<bool> {
-        let chunk_path = self.get_chunk_path(chunk_hash);
+        self.verify_chunk_expected(chunk_hash, None).await
+    }
 
-        if !chunk_path.exists() {
+    /// 按预期内容验证单个 chunk
+    ///
+    /// 有预期内容时解压后与记录的强哈希比较，否则要求存储字节的 SHA-256 等于 chunk ID。
+    /// 解压失败视为无效。
+    pub async fn verify_chunk_expected(
+        &self,
+        chunk_hash: &str,
+        expected: Option<&ExpectedChunk>,
+    ) -> Result<bool> {
+        if !self.store.exists(chunk_hash).await? {
             return Ok(false);
         }
 
         // 读取 chunk 数据
-        let data = fs::read(&chunk_path).await?;
+        let data = self.store.get(chunk_hash).await?;
+
+        let (data, expected_hash) = match expected {
+            Some(expected) => match self.decompressor.decompress(&data, expected.compression) {
+                Ok(data) => (data, expected.strong_hash.as_str()),
+                Err(e) => {
+                    warn!("chunk 解压失败: {} - {}", chunk_hash, e);
+                    return Ok(false);
+                }
+            },
+            None => (data, chunk_hash),
+        };
 
         // 计算实际哈希
         let mut hasher = Sha256::new();
         hasher.update(&data);
         let actual_hash = hex::encode(hasher.finalize());
 
-        Ok(actual_hash == chunk_hash)
+        Ok(actual_hash == expected_hash)
     }
 
-    /// 批量验证 chunks
-    pub async fn verify_chunks(&self, chunk_hashes: &[String]) -> Result<ChunkVerifyReport> {
-        let mut valid = 0;
-        let mut invalid = 0;
-        let mut missing = 0;
-        let mut corrupted_chunks = Vec::new();
-
-        for chunk_hash in chunk_hashes {
-            let chunk_path = self.get_chunk_path(chunk_hash);
-
-            if !chunk_path.exists() {
-                missing += 1;
-                corrupted_chunks.push(chunk_hash.clone());
-                continue;
+    /// 检查单个 chunk 的状态（读取失败视为无效）
+    async fn check_chunk(&self, chunk_hash: &str, expected: Option<&ExpectedChunk>) -> ChunkStatus {
+        match self.store.exists(chunk_hash).await {
+            Ok(false) => return ChunkStatus::Missing,
+            Ok(true) => {}
+            Err(e) => {
+                error!("验证 chunk 失败: {} - {}", chunk_hash, e);
+                return ChunkStatus::Invalid;
             }
+        }
 
-            match self.verify_chunk(chunk_hash).await {
-                Ok(true) => valid += 1,
-                Ok(false) => {
-                    invalid += 1;
-                    corrupted_chunks.push(chunk_hash.clone());
-                }
-                Err(e) => {
-                    error!("验证 chunk 失败: {} - {}", chunk_hash, e);
-                    invalid += 1;
-                    corrupted_chunks.push(chunk_hash.clone());
-                }
+        match self.verify_chunk_expected(chunk_hash, expected).await {
+            Ok(true) => ChunkStatus::Valid,
+            Ok(false) => ChunkStatus::Invalid,
+            Err(e) => {
+                error!("验证 chunk 失败: {} - {}", chunk_hash, e);
+                ChunkStatus::Invalid
             }
         }
+    }
 
-        Ok(ChunkVerifyReport {
-            total: chunk_hashes.len(),
-            valid,
-            invalid,
-            missing,
-            corrupted_chunks,
-        })
+    /// 批量验证 chunks
+    pub async fn verify_chunks(&self, chunk_hashes: &[String]) -> Result<ChunkVerifyReport> {
+        self.verify_chunks_expected(chunk_hashes, &HashMap::new())
+            .await
+    }
+
+    /// 按预期内容批量验证 chunks（不在 `expected` 中的 chunk 按 ID 校验）
+    pub async fn verify_chunks_expected(
+        &self,
+        chunk_hashes: &[String],
+        expected: &HashMap<String, ExpectedChunk>,
+    ) -> Result<ChunkVerifyReport> {
+        let mut report = ChunkVerifyReport::default();
+        for chunk_hash in chunk_hashes {
+            let status = self.check_chunk(chunk_hash, expected.get(chunk_hash)).await;
+            report.record(chunk_hash.clone(), status);
+        }
+        Ok(report)
     }
 
     /// 扫描所有 chunks 并验证
     pub async fn scan_and_verify(&self) -> Result<ChunkVerifyReport> {
-        let mut chunk_hashes = Vec::new();
-
-        // 递归扫描 data 目录下的所有 chunk 文件
-        let data_dir = self.chunk_root.join("data");
-        if !data_dir.exists() {
-            return Ok(ChunkVerifyReport {
-                total: 0,
-                valid: 0,
-                invalid: 0,
-                missing: 0,
-                corrupted_chunks: Vec::new(),
-            });
-        }
+        self.scan_and_verify_with(&ScanOptions::default(), &ScanHandle::new())
+            .await
+    }
 
-        // 遍历所有前缀目录
-        let mut prefix_entries = fs::read_dir(&data_dir).await?;
-        while let Some(prefix_entry) = prefix_entries.next_entry().await? {
-            if prefix_entry.file_type().await?.is_dir() {
-                // 遍历前缀目录下的所有 chunk 文件
-                let mut chunk_entries = fs::read_dir(prefix_entry.path()).await?;
-                while let Some(chunk_entry) = chunk_entries.next_entry().await? {
-                    if let Some(file_name) = chunk_entry.file_name().to_str() {
-                        chunk_hashes.push(file_name.to_string());
-                    }
-                }
-            }
+    /// 按选项扫描所有 chunks 并验证
+    ///
+    /// 最多同时校验 `max_concurrency` 个 chunk，进度实时写入 `handle`。
+    /// 通过 [`ScanHandle::cancel`] 取消后不再发起新的校验，
+    /// 返回的报告只包含已校验的 chunk，并标记为已取消。
+    pub async fn scan_and_verify_with(
+        &self,
+        options: &ScanOptions,
+        handle: &ScanHandle,
+    ) -> Result<ChunkVerifyReport> {
+        self.scan_and_verify_expected(options, handle, &HashMap::new())
+            .await
+    }
+
+    /// 按选项扫描所有 chunks 并按预期内容验证（不在 `expected` 中的 chunk 按 ID 校验）
+    pub async fn scan_and_verify_expected(
+        &self,
+        options: &ScanOptions,
+        handle: &ScanHandle,
+        expected: &HashMap<String, ExpectedChunk>,
+    ) -> Result<ChunkVerifyReport> {
+        let chunk_hashes = self.store.list().await?;
+        handle.total.store(chunk_hashes.len(), Ordering::Relaxed);
+
+        let mut results = futures::stream::iter(chunk_hashes)
+            .take_while(|_| futures::future::ready(!handle.is_cancelled()))
+            .map(|chunk_hash| async move {
+                let status = self
+                    .check_chunk(&chunk_hash, expected.get(&chunk_hash))
+                    .await;
+                (chunk_hash, status)
+            })
+            .buffer_unordered(options.max_concurrency.max(1));
+
+        let mut report = ChunkVerifyReport::default();
+        while let Some((chunk_hash, status)) = results.next().await {
+            report.record(chunk_hash, status);
+            handle.scanned.fetch_add(1, Ordering::Relaxed);
         }
+        report.cancelled = handle.is_cancelled();
+
+        if report.cancelled {
+            warn!(
+                "chunk 校验已取消: 已校验 {}/{}",
+                report.total,
+                handle.total()
+            );
+        }
+        Ok(report)
+    }
+}
+
+/// 单个 chunk 的校验结果
+enum ChunkStatus {
+    Valid,
+    Invalid,
+    Missing,
+}
+
+/// 全量扫描选项
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// 最大并发校验数（0 视为 1）
+    pub max_concurrency: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self { max_concurrency: 1 }
+    }
+}
+
+/// 扫描句柄
+///
+/// 在扫描进行中由其他任务共享，用于查询进度和取消扫描。
+#[derive(Debug, Default)]
+pub struct ScanHandle {
+    /// 已校验数量
+    scanned: AtomicUsize,
+    /// 待校验总数（列出 chunk 后设置）
+    total: AtomicUsize,
+    /// 取消标志
+    cancelled: AtomicBool,
+}
+
+impl ScanHandle {
+    /// 创建扫描句柄
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 已校验的 chunk 数量
+    pub fn scanned(&self) -> usize {
+        self.scanned.load(Ordering::Relaxed)
+    }
+
+    /// 待校验的 chunk 总数
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// 取消扫描
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
 
-        self.verify_chunks(&chunk_hashes).await
+    /// 是否已取消
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
     }
 }
 
 /// Chunk 验证报告
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChunkVerifyReport {
     /// 总数
     pub total: usize,
@@ -302,52 +442,54 @@ pub struct ChunkVerifyReport {
     pub missing: usize,
     /// 损坏的 chunks
     pub corrupted_chunks: Vec<String>,
+    /// 扫描是否被取消（取消时报告只包含已校验的 chunk）
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+impl ChunkVerifyReport {
+    /// 记录一个 chunk 的校验结果
+    fn record(&mut self, chunk_hash: String, status: ChunkStatus) {
+        self.total += 1;
+        match status {
+            ChunkStatus::Valid => self.valid += 1,
+            ChunkStatus::Invalid => {
+                self.invalid += 1;
+                self.corrupted_chunks.push(chunk_hash);
+            }
+            ChunkStatus::Missing => {
+                self.missing += 1;
+                self.corrupted_chunks.push(chunk_hash);
+            }
+        }
+    }
 }
 
 /// 孤儿 Chunk 清理器
 pub struct OrphanChunkCleaner {
-    chunk_root: PathBuf,
+    store: Arc<dyn ChunkStore>,
 }
 
 impl OrphanChunkCleaner {
-    /// 创建新的清理器
+    /// 创建清理本地块目录的清理器
     pub fn new(chunk_root: PathBuf) -> Self {
-        Self { chunk_root }
+        Self::with_store(Arc::new(LocalChunkStore::new(chunk_root)))
     }
 
-    /// 获取 chunk 实际路径（处理分层存储）
-    fn get_chunk_path(&self, chunk_hash: &str) -> PathBuf {
-        let prefix = &chunk_hash[..2.min(chunk_hash.len())];
-        self.chunk_root.join("data").join(prefix).join(chunk_hash)
+    /// 创建清理指定块存储的清理器
+    pub fn with_store(store: Arc<dyn ChunkStore>) -> Self {
+        Self { store }
     }
 
     /// 检测孤儿 chunks
     pub async fn detect_orphans(&self, referenced_chunks: &HashSet<String>) -> Result<Vec<String>> {
-        let mut orphans = Vec::new();
-
-        // 递归扫描 data 目录
-        let data_dir = self.chunk_root.join("data");
-        if !data_dir.exists() {
-            return Ok(orphans);
-        }
-
-        // 遍历所有前缀目录
-        let mut prefix_entries = fs::read_dir(&data_dir).await?;
-        while let Some(prefix_entry) = prefix_entries.next_entry().await? {
-            if prefix_entry.file_type().await?.is_dir() {
-                // 遍历前缀目录下的所有 chunk 文件
-                let mut chunk_entries = fs::read_dir(prefix_entry.path()).await?;
-                while let Some(chunk_entry) = chunk_entries.next_entry().await? {
-                    if let Some(file_name) = chunk_entry.file_name().to_str()
-                        && !referenced_chunks.contains(file_name)
-                    {
-                        orphans.push(file_name.to_string());
-                    }
-                }
-            }
-        }
-
-        Ok(orphans)
+        Ok(self
+            .store
+            .list()
+            .await?
+            .into_iter()
+            .filter(|chunk_hash| !referenced_chunks.contains(chunk_hash))
+            .collect())
     }
 
     /// 清理孤儿 chunks
@@ -357,21 +499,17 @@ impl OrphanChunkCleaner {
         let mut freed_space = 0u64;
 
         for chunk_hash in orphan_hashes {
-            let chunk_path = self.get_chunk_path(chunk_hash);
-
-            match fs::metadata(&chunk_path).await {
-                Ok(metadata) => {
-                    freed_space += metadata.len();
-                    match fs::remove_file(&chunk_path).await {
-                        Ok(_) => deleted += 1,
-                        Err(e) => {
-                            error!("删除孤儿 chunk 失败: {} - {}", chunk_hash, e);
-                            failed.push(chunk_hash.clone());
-                        }
-                    }
+            match self.store.delete(chunk_hash).await {
+                Ok(Some(size)) => {
+                    freed_space += size;
+                    deleted += 1;
+                }
+                Ok(None) => {
+                    error!("孤儿 chunk 不存在: {}", chunk_hash);
+                    failed.push(chunk_hash.clone());
                 }
                 Err(e) => {
-                    error!("获取 chunk 元数据失败: {} - {}", chunk_hash, e);
+                    error!("删除孤儿 chunk 失败: {} - {}", chunk_hash, e);
                     failed.push(chunk_hash.clone());
                 }
             }
@@ -760,6 +898,102 @@ mod tests {
 
         assert_eq!(report.total, 0);
         assert_eq!(report.valid, 0);
+        assert!(!report.cancelled);
+    }
+
+    /// 读取指定数量的 chunk 后取消扫描的块存储
+    struct CancelAfterReads {
+        inner: crate::chunk_store::MemoryChunkStore,
+        handle: Arc<ScanHandle>,
+        reads: AtomicUsize,
+        limit: usize,
+    }
+
+    #[async_trait::async_trait]
+    impl ChunkStore for CancelAfterReads {
+        async fn put(&self, chunk_id: &str, data: &[u8]) -> Result<bool> {
+            self.inner.put(chunk_id, data).await
+        }
+
+        async fn get(&self, chunk_id: &str) -> Result<Vec<u8>> {
+            if self.reads.fetch_add(1, Ordering::Relaxed) + 1 == self.limit {
+                self.handle.cancel();
+            }
+            self.inner.get(chunk_id).await
+        }
+
+        async fn exists(&self, chunk_id: &str) -> Result<bool> {
+            self.inner.exists(chunk_id).await
+        }
+
+        async fn delete(&self, chunk_id: &str) -> Result<Option<u64>> {
+            self.inner.delete(chunk_id).await
+        }
+
+        async fn list(&self) -> Result<Vec<String>> {
+            self.inner.list().await
+        }
+
+        async fn stored_size(&self, chunk_id: &str) -> Result<Option<u64>> {
+            self.inner.stored_size(chunk_id).await
+        }
+
+        fn location(&self, chunk_id: &str) -> PathBuf {
+            self.inner.location(chunk_id)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chunk_verifier_scan_cancel_returns_partial_report() {
+        let handle = Arc::new(ScanHandle::new());
+        let store = Arc::new(CancelAfterReads {
+            inner: crate::chunk_store::MemoryChunkStore::new(),
+            handle: handle.clone(),
+            reads: AtomicUsize::new(0),
+            limit: 3,
+        });
+        // 内容与哈希不符，每个 chunk 都应被判定为损坏
+        for i in 0..10 {
+            store
+                .put(&format!("chunk{}", i), b"corrupted")
+                .await
+                .unwrap();
+        }
+
+        let verifier = ChunkVerifier::with_store(store);
+        let options = ScanOptions { max_concurrency: 1 };
+        let report = verifier
+            .scan_and_verify_with(&options, &handle)
+            .await
+            .unwrap();
+
+        assert!(report.cancelled);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.invalid, 3);
+        assert_eq!(report.corrupted_chunks.len(), 3);
+        assert_eq!(handle.scanned(), 3);
+        assert_eq!(handle.total(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_verifier_scan_cancelled_before_start() {
+        let store = Arc::new(crate::chunk_store::MemoryChunkStore::new());
+        for i in 0..5 {
+            store.put(&format!("chunk{}", i), b"data").await.unwrap();
+        }
+        let handle = ScanHandle::new();
+        handle.cancel();
+
+        let verifier = ChunkVerifier::with_store(store);
+        let report = verifier
+            .scan_and_verify_with(&ScanOptions::default(), &handle)
+            .await
+            .unwrap();
+
+        assert!(report.cancelled);
+        assert_eq!(report.total, 0);
+        assert_eq!(handle.scanned(), 0);
+        assert_eq!(handle.total(), 5);
     }
 
     #[tokio::test]
@@ -850,6 +1084,7 @@ mod tests {
             invalid: 1,
             missing: 1,
             corrupted_chunks: vec!["chunk1".to_string(), "chunk2".to_string()],
+            cancelled: false,
         };
 
         let json = serde_json::to_string(&report).unwrap();
@@ -858,6 +1093,7 @@ mod tests {
         assert_eq!(deserialized.total, 10);
         assert_eq!(deserialized.valid, 8);
         assert_eq!(deserialized.corrupted_chunks.len(), 2);
+        assert!(!deserialized.cancelled);
     }
 
     #[test]
@@ -0,0 +1,418 @@
+//! 去重索引模块
+//!
+//! 使用 CDC 分块时计算的弱哈希作为快速预过滤：
+//! 按弱哈希将块分桶，只在弱哈希命中的桶内比较强哈希（SHA-256）。
+//! 强哈希仍是唯一的判定依据，预过滤不影响去重正确性。
+//!
+//! 内存中的索引可以限制块数量：超出容量时按 LRU 淘汰最久未访问的桶，
+//! 被淘汰的弱哈希记为"已溢出"。块引用计数（含弱哈希）始终持久化在 Sled 中，
+//! 调用方在弱哈希已溢出时回查 Sled，并通过 [`WeakHashDedupIndex::load`] 将其加载回内存。
+//! 每个弱哈希记录仍在内存之外的块数量，块加载回内存或被删除后递减，归零时不再视为已溢出。
+//!
+//! 查询只持有读锁：访问序号以原子量记录在桶上，LRU 顺序在淘汰时再按最新序号校正。
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::RwLock;
+
+/// 弱哈希桶
+struct Bucket {
+    /// 强哈希列表
+    strong_hashes: Vec<String>,
+    /// 在 LRU 表中的序号
+    lru_key: u64,
+    /// 最近访问序号（查询时在读锁下更新，可能比 `lru_key` 新）
+    last_access: AtomicU64,
+}
+
+/// 索引内部状态
+#[derive(Default)]
+struct IndexState {
+    /// 弱哈希 -> 桶
+    buckets: HashMap<u32, Bucket>,
+    /// 访问序号 -> 弱哈希（序号最小者为最久未访问，淘汰时按桶的最新序号校正）
+    lru: BTreeMap<u64, u32>,
+    /// 已溢出的弱哈希 -> 仍在内存之外的块数量
+    spilled: HashMap<u32, usize>,
+    /// 内存中的块数量
+    chunk_count: usize,
+    /// 访问序号计数器
+    tick: AtomicU64,
+}
+
+impl IndexState {
+    /// 分配新的访问序号
+    fn next_tick(&self) -> u64 {
+        self.tick.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// 标记桶为最近访问
+    fn touch(&mut self, weak_hash: u32) {
+        let tick = self.next_tick();
+        if let Some(bucket) = self.buckets.get_mut(&weak_hash) {
+            self.lru.remove(&bucket.lru_key);
+            bucket.lru_key = tick;
+            *bucket.last_access.get_mut() = tick;
+            self.lru.insert(tick, weak_hash);
+        }
+    }
+
+    /// 添加块，返回是否为新增
+    fn insert(&mut self, weak_hash: u32, strong_hash: &str) -> bool {
+        let bucket = self.buckets.entry(weak_hash).or_insert_with(|| Bucket {
+            strong_hashes: Vec::new(),
+            lru_key: 0,
+            last_access: AtomicU64::new(0),
+        });
+        let added = !bucket.strong_hashes.iter().any(|s| s == strong_hash);
+        if added {
+            bucket.strong_hashes.push(strong_hash.to_string());
+            self.chunk_count += 1;
+        }
+        self.touch(weak_hash);
+        added
+    }
+
+    /// 弱哈希下一个已溢出的块回到内存或被删除
+    fn release_spilled(&mut self, weak_hash: u32) {
+        if let Some(count) = self.spilled.get_mut(&weak_hash) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                self.spilled.remove(&weak_hash);
+            }
+        }
+    }
+
+    /// 按 LRU 淘汰桶直到不超过容量，返回被淘汰的块数量
+    ///
+    /// 最近访问的桶始终保留，即使它本身超过容量。
+    fn evict(&mut self, capacity: usize) -> u64 {
+        let mut evicted = 0;
+        while capacity > 0 && self.chunk_count > capacity && self.lru.len() > 1 {
+            let Some((key, weak_hash)) = self.lru.pop_first() else {
+                break;
+            };
+            let Some(bucket) = self.buckets.get_mut(&weak_hash) else {
+                continue;
+            };
+            // 读锁下被访问过的桶按最新序号放回，不淘汰
+            let last_access = *bucket.last_access.get_mut();
+            if last_access != key {
+                bucket.lru_key = last_access;
+                self.lru.insert(last_access, weak_hash);
+                continue;
+            }
+            if let Some(bucket) = self.buckets.remove(&weak_hash) {
+                let count = bucket.strong_hashes.len();
+                self.chunk_count -= count;
+                evicted += count as u64;
+                *self.spilled.entry(weak_hash).or_default() += count;
+            }
+        }
+        evicted
+    }
+}
+
+/// 弱哈希预过滤去重索引
+///
+/// 弱哈希为 0 表示未计算（固定大小分块），此类块不进入索引，
+/// 由调用方回退到 Bloom Filter 判断。
+pub struct WeakHashDedupIndex {
+    /// 索引状态
+    state: RwLock<IndexState>,
+    /// 内存中最多保留的块数量（0 表示不限制）
+    capacity: usize,
+    /// 累计强哈希比较次数
+    strong_comparisons: AtomicU64,
+    /// 累计弱哈希未命中次数（无需比较强哈希）
+    weak_misses: AtomicU64,
+    /// 累计溢出的块数量
+    spilled_chunks: AtomicU64,
+}
+
+impl WeakHashDedupIndex {
+    /// 创建不限容量的空索引
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// 创建限制内存块数量的空索引（0 表示不限制）
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            state: RwLock::new(IndexState::default()),
+            capacity,
+            strong_comparisons: AtomicU64::new(0),
+            weak_misses: AtomicU64::new(0),
+            spilled_chunks: AtomicU64::new(0),
+        }
+    }
+
+    /// 添加块到索引
+    ///
+    /// 超出容量时淘汰最久未访问的桶。
+    pub async fn insert(&self, weak_hash: u32, strong_hash: &str) {
+        if weak_hash == 0 {
+            return;
+        }
+
+        let mut state = self.state.write().await;
+        if state.insert(weak_hash, strong_hash) {
+            let evicted = state.evict(self.capacity);
+            self.spilled_chunks.fetch_add(evicted, Ordering::Relaxed);
+        }
+    }
+
+    /// 将已溢出的块（从持久化索引中查到）加载回内存
+    pub async fn load(&self, weak_hash: u32, strong_hash: &str) {
+        if weak_hash == 0 {
+            return;
+        }
+
+        let mut state = self.state.write().await;
+        if state.insert(weak_hash, strong_hash) {
+            state.release_spilled(weak_hash);
+            let evicted = state.evict(self.capacity);
+            self.spilled_chunks.fetch_add(evicted, Ordering::Relaxed);
+        }
+    }
+
+    /// 从索引中移除块
+    ///
+    /// 块不在内存中而弱哈希已溢出时，视为移除了一个已溢出的块。
+    pub async fn remove(&self, weak_hash: u32, strong_hash: &str) {
+        let mut state = self.state.write().await;
+        let Some(bucket) = state.buckets.get_mut(&weak_hash) else {
+            state.release_spilled(weak_hash);
+            return;
+        };
+        let before = bucket.strong_hashes.len();
+        bucket.strong_hashes.retain(|s| s != strong_hash);
+        let removed = before - bucket.strong_hashes.len();
+        let emptied = bucket.strong_hashes.is_empty();
+        let lru_key = bucket.lru_key;
+
+        state.chunk_count -= removed;
+        if removed == 0 {
+            state.release_spilled(weak_hash);
+        }
+        if emptied {
+            state.buckets.remove(&weak_hash);
+            state.lru.remove(&lru_key);
+        }
+    }
+
+    /// 按强哈希移除块（弱哈希未知时使用，需要遍历所有桶）
+    pub async fn remove_by_strong_hash(&self, strong_hashes: &HashSet<&str>) {
+        if strong_hashes.is_empty() {
+            return;
+        }
+        let mut state = self.state.write().await;
+        let mut removed = 0;
+        let mut emptied = Vec::new();
+        for (weak_hash, bucket) in state.buckets.iter_mut() {
+            let before = bucket.strong_hashes.len();
+            bucket
+                .strong_hashes
+                .retain(|s| !strong_hashes.contains(s.as_str()));
+            removed += before - bucket.strong_hashes.len();
+            if bucket.strong_hashes.is_empty() {
+                emptied.push((*weak_hash, bucket.lru_key));
+            }
+        }
+
+        state.chunk_count -= removed;
+        for (weak_hash, lru_key) in emptied {
+            state.buckets.remove(&weak_hash);
+            state.lru.remove(&lru_key);
+        }
+    }
+
+    /// 检查块是否已存在于内存索引
+    ///
+    /// 只持有读锁；弱哈希未命中时直接返回 `false`，不进行任何强哈希比较。
+    /// 返回 `false` 且 [`is_spilled`](Self::is_spilled) 为真时，调用方应回查持久化索引。
+    pub async fn contains(&self, weak_hash: u32, strong_hash: &str) -> bool {
+        let state = self.state.read().await;
+        let Some(bucket) = state.buckets.get(&weak_hash) else {
+            self.weak_misses.fetch_add(1, Ordering::Relaxed);
+            return false;
+        };
+
+        let mut found = false;
+        for candidate in &bucket.strong_hashes {
+            self.strong_comparisons.fetch_add(1, Ordering::Relaxed);
+            if candidate == strong_hash {
+                found = true;
+                break;
+            }
+        }
+        bucket
+            .last_access
+            .store(state.next_tick(), Ordering::Relaxed);
+        found
+    }
+
+    /// 弱哈希是否有条目被溢出出内存
+    pub async fn is_spilled(&self, weak_hash: u32) -> bool {
+        self.state.read().await.spilled.contains_key(&weak_hash)
+    }
+
+    /// 重建索引（从 (弱哈希, 强哈希) 列表）
+    ///
+    /// 条目数超过容量时，列表中靠前的条目会被溢出。
+    pub async fn rebuild(&self, entries: Vec<(u32, String)>) {
+        let mut state = self.state.write().await;
+        *state = IndexState::default();
+        let mut evicted = 0;
+        for (weak_hash, strong_hash) in entries {
+            if weak_hash != 0 && state.insert(weak_hash, &strong_hash) {
+                evicted += state.evict(self.capacity);
+            }
+        }
+        self.spilled_chunks.fetch_add(evicted, Ordering::Relaxed);
+    }
+
+    /// 获取索引统计信息
+    pub async fn get_stats(&self) -> DedupIndexStats {
+        let state = self.state.read().await;
+        DedupIndexStats {
+            bucket_count: state.buckets.len(),
+            chunk_count: state.chunk_count,
+            strong_comparisons: self.strong_comparisons.load(Ordering::Relaxed),
+            weak_misses: self.weak_misses.load(Ordering::Relaxed),
+            capacity: self.capacity,
+            spilled_buckets: state.spilled.len(),
+            spilled_chunks: self.spilled_chunks.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for WeakHashDedupIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 去重索引统计信息
+#[derive(Debug, Clone)]
+pub struct DedupIndexStats {
+    /// 弱哈希桶数量
+    pub bucket_count: usize,
+    /// 索引中的块数量
+    pub chunk_count: usize,
+    /// 累计强哈希比较次数
+    pub strong_comparisons: u64,
+    /// 累计弱哈希未命中次数
+    pub weak_misses: u64,
+    /// 内存中最多保留的块数量（0 表示不限制）
+    pub capacity: usize,
+    /// 有条目被溢出的弱哈希数量
+    pub spilled_buckets: usize,
+    /// 累计溢出的块数量
+    pub spilled_chunks: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_weak_hash_prefilter() {
+        let index = WeakHashDedupIndex::new();
+        index.insert(1, "strong_a").await;
+        index.insert(2, "strong_b").await;
+
+        assert!(index.contains(1, "strong_a").await);
+        assert!(!index.contains(3, "strong_c").await);
+
+        // 弱哈希未命中时不比较强哈希
+        let stats = index.get_stats().await;
+        assert_eq!(stats.strong_comparisons, 1);
+        assert_eq!(stats.weak_misses, 1);
+    }
+
+    #[tokio::test]
+    async fn test_strong_hash_is_authoritative() {
+        let index = WeakHashDedupIndex::new();
+        index.insert(7, "strong_a").await;
+
+        // 弱哈希碰撞但强哈希不同，判定为不存在
+        assert!(!index.contains(7, "strong_b").await);
+
+        index.insert(7, "strong_b").await;
+        assert!(index.contains(7, "strong_b").await);
+        assert_eq!(index.get_stats().await.chunk_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_zero_weak_hash_not_indexed() {
+        let index = WeakHashDedupIndex::new();
+        index.insert(0, "fixed_chunk").await;
+
+        assert!(!index.contains(0, "fixed_chunk").await);
+        assert_eq!(index.get_stats().await.chunk_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_remove_and_rebuild() {
+        let index = WeakHashDedupIndex::new();
+        index.insert(1, "strong_a").await;
+        index.remove(1, "strong_a").await;
+        assert!(!index.contains(1, "strong_a").await);
+        assert_eq!(index.get_stats().await.bucket_count, 0);
+
+        index.insert(2, "strong_b").await;
+        index.insert(2, "strong_c").await;
+        index
+            .remove_by_strong_hash(&HashSet::from(["strong_b", "strong_c"]))
+            .await;
+        assert!(!index.contains(2, "strong_b").await);
+        assert_eq!(index.get_stats().await.chunk_count, 0);
+
+        index
+            .rebuild(vec![(1, "strong_a".to_string()), (0, "fixed".to_string())])
+            .await;
+        assert!(index.contains(1, "strong_a").await);
+        assert_eq!(index.get_stats().await.chunk_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_lru_spill_over_capacity() {
+        let index = WeakHashDedupIndex::with_capacity(2);
+        index.insert(1, "strong_a").await;
+        index.insert(2, "strong_b").await;
+        // 访问 1，使 2 成为最久未访问
+        assert!(index.contains(1, "strong_a").await);
+        index.insert(3, "strong_c").await;
+
+        let stats = index.get_stats().await;
+        assert_eq!(stats.chunk_count, 2);
+        assert_eq!(stats.spilled_chunks, 1);
+        assert!(index.is_spilled(2).await);
+        assert!(!index.is_spilled(1).await);
+        assert!(!index.contains(2, "strong_b").await);
+
+        // 加载回内存后再次命中
+        index.insert(2, "strong_b").await;
+        assert!(index.contains(2, "strong_b").await);
+        assert!(index.is_spilled(1).await);
+    }
+
+    #[tokio::test]
+    async fn test_spilled_pruned_after_load_and_remove() {
+        let index = WeakHashDedupIndex::with_capacity(1);
+        index.insert(1, "strong_a").await;
+        index.insert(2, "strong_b").await;
+        assert!(index.is_spilled(1).await);
+
+        // 加载回内存后不再视为已溢出，同时淘汰另一个桶
+        index.load(1, "strong_a").await;
+        assert!(!index.is_spilled(1).await);
+        assert!(index.is_spilled(2).await);
+
+        // 删除已溢出的块后清除溢出记录
+        index.remove(2, "strong_b").await;
+        assert!(!index.is_spilled(2).await);
+        assert_eq!(index.get_stats().await.spilled_buckets, 0);
+    }
+}
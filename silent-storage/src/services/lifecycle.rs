@@ -4,6 +4,7 @@
 
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
+use silent_nas_core::{SharedClock, SystemClock};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use tokio::fs;
@@ -109,6 +110,8 @@ pub struct LifecycleManager {
     entries: HashMap<String, LifecycleEntry>,
     /// 统计信息
     stats: LifecycleStats,
+    /// 时钟（过期判断与访问/修改时间均以此为准）
+    clock: SharedClock,
 }
 
 impl LifecycleManager {
@@ -117,9 +120,16 @@ impl LifecycleManager {
             config,
             entries: HashMap::new(),
             stats: LifecycleStats::new(),
+            clock: SystemClock::shared(),
         }
     }
 
+    /// 使用指定时钟
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// 初始化生命周期管理器
     pub fn init(&mut self) -> Result<()> {
         info!("生命周期管理器初始化完成");
@@ -142,7 +152,7 @@ impl LifecycleManager {
     /// 更新访问时间
     pub fn update_access_time(&mut self, file_id: &str) -> Result<()> {
         if let Some(entry) = self.entries.get_mut(file_id) {
-            entry.last_accessed = chrono::Local::now().naive_local();
+            entry.last_accessed = self.clock.now_naive();
             // 重新计算清理时间
             let cleanup_time = Self::calculate_cleanup_time(entry);
             entry.scheduled_cleanup_at = cleanup_time;
@@ -154,7 +164,7 @@ impl LifecycleManager {
     /// 更新修改时间
     pub fn update_modification_time(&mut self, file_id: &str) -> Result<()> {
         if let Some(entry) = self.entries.get_mut(file_id) {
-            entry.last_modified = chrono::Local::now().naive_local();
+            entry.last_modified = self.clock.now_naive();
             // 重新计算清理时间
             let cleanup_time = Self::calculate_cleanup_time(entry);
             entry.scheduled_cleanup_at = cleanup_time;
@@ -166,7 +176,7 @@ impl LifecycleManager {
     /// 执行生命周期检查
     pub fn check_lifecycle(&mut self) -> Result<LifecycleCheckResult> {
         let mut result = LifecycleCheckResult::default();
-        let now = chrono::Local::now().naive_local();
+        let now = self.clock.now_naive();
 
         // 预先计算所有状态变更
         let mut state_changes = Vec::new();
@@ -217,7 +227,6 @@ impl LifecycleManager {
         }
 
         let mut result = CleanupResult::default();
-        let _now = chrono::Local::now().naive_local();
 
         // 收集所有已过期的文件
         let mut to_cleanup: Vec<String> = Vec::new();
@@ -598,4 +607,32 @@ mod tests {
 
         assert!(result.success);
     }
+
+    #[tokio::test]
+    async fn test_lifecycle_ttl_expires_with_mock_clock() {
+        use silent_nas_core::{Clock, MockClock};
+
+        let clock = std::sync::Arc::new(MockClock::default());
+        let mut manager =
+            LifecycleManager::new(LifecycleConfig::default()).with_clock(clock.clone());
+        let now = clock.now_naive();
+
+        let entry = LifecycleEntry {
+            file_id: "test_ttl".to_string(),
+            policy: LifecyclePolicy::Ttl { ttl_seconds: 3600 },
+            created_at: now,
+            last_modified: now,
+            last_accessed: now,
+            state: LifecycleState::Active,
+            version_id: None,
+            storage_path: PathBuf::new(),
+            scheduled_cleanup_at: None,
+        };
+        manager.add_entry(entry).unwrap();
+        assert!(manager.check_lifecycle().unwrap().expired_files.is_empty());
+
+        clock.advance(chrono::Duration::hours(2));
+        let result = manager.check_lifecycle().unwrap();
+        assert_eq!(result.expired_files, vec!["test_ttl".to_string()]);
+    }
 }
@@ -3,9 +3,12 @@
 //! 该模块包含需要维护状态的服务：
 //! - 分层存储（热数据、冷数据）
 //! - 生命周期管理（数据清理、过期处理）
+//! - 去重索引（弱哈希预过滤）
 
+pub mod dedup;
 pub mod lifecycle;
 pub mod tiering;
 
+pub use dedup::*;
 pub use lifecycle::*;
 pub use tiering::*;
@@ -4,6 +4,7 @@
 
 use crate::error::{Result, StorageError};
 use serde::{Deserialize, Serialize};
+use silent_nas_core::{SharedClock, SystemClock};
 use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -123,6 +124,8 @@ pub struct TieredStorage {
     tier_usage: RwLock<HashMap<StorageTier, u64>>,
     /// 当前层级使用量
     tier_sizes: RwLock<HashMap<StorageTier, u64>>,
+    /// 时钟（访问时间以此为准）
+    clock: SharedClock,
 }
 
 impl TieredStorage {
@@ -141,9 +144,16 @@ impl TieredStorage {
             lru_queue: RwLock::new(VecDeque::new()),
             tier_usage: RwLock::new(HashMap::new()),
             tier_sizes: RwLock::new(HashMap::new()),
+            clock: SystemClock::shared(),
         }
     }
 
+    /// 使用指定时钟
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// 初始化分层存储
     pub async fn init(&self) -> Result<()> {
         // 创建各层级目录
@@ -168,7 +178,7 @@ impl TieredStorage {
 
         if let Some(item) = items.get_mut(file_id) {
             // 更新访问信息
-            item.last_accessed = chrono::Local::now().naive_local();
+            item.last_accessed = self.clock.now_naive();
             item.total_accesses += 1;
 
             // 更新LRU队列
@@ -223,8 +233,8 @@ impl TieredStorage {
             size,
             tier,
             storage_path: storage_path.clone(),
-            created_at: chrono::Local::now().naive_local(),
-            last_accessed: chrono::Local::now().naive_local(),
+            created_at: self.clock.now_naive(),
+            last_accessed: self.clock.now_naive(),
             total_accesses: 0,
             is_compressed: false,
         };
@@ -475,8 +485,8 @@ impl TieredStorage {
                         size: metadata.len(),
                         tier: *tier,
                         storage_path: path.clone(),
-                        created_at: chrono::Local::now().naive_local(),
-                        last_accessed: chrono::Local::now().naive_local(),
+                        created_at: self.clock.now_naive(),
+                        last_accessed: self.clock.now_naive(),
                         total_accesses: 0,
                         is_compressed: false,
                     };
@@ -0,0 +1,496 @@
+//! 存储快照导出与导入
+//!
+//! 将整个存储（所有未删除文件的全部版本）序列化为一个自包含的字节流，
+//! 用于备份或迁移到另一个存储实例。
+//!
+//! 流格式：
+//!
+//! ```text
+//! MAGIC(8) | FORMAT_VERSION(u32 LE) | 记录... | 结束记录
+//! 记录 = 类型(u8) | 元数据长度(u32 LE) | 元数据(JSON) | 数据长度(u64 LE) | 数据
+//! ```
+//!
+//! 块记录携带原始（未压缩）块数据，同一块在流中只出现一次；
+//! 版本记录按创建时间顺序出现，只引用之前已写出的块 ID。
+//! 导入时块先暂存到临时目录，再按版本重组数据并通过正常写入路径保存，
+//! 因此会与目标存储中已有的块去重。版本 ID 在导入后重新生成，版本顺序保持不变。
+
+use crate::error::{Result, StorageError};
+use crate::storage::StorageManager;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::info;
+
+/// 快照流魔数
+const SNAPSHOT_MAGIC: &[u8; 8] = b"SNASSNAP";
+
+/// 快照格式版本
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// 单条记录元数据的最大长度
+pub(crate) const MAX_META_LEN: u32 = 16 * 1024 * 1024;
+
+/// 单个块数据的最大长度
+pub(crate) const MAX_CHUNK_LEN: u64 = 256 * 1024 * 1024;
+
+/// 记录类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordKind {
+    /// 块数据
+    Chunk = 1,
+    /// 文件版本
+    Version = 2,
+    /// 流结束（携带统计信息，用于检测截断）
+    End = 3,
+}
+
+impl RecordKind {
+    fn from_u8(tag: u8) -> Result<Self> {
+        match tag {
+            1 => Ok(Self::Chunk),
+            2 => Ok(Self::Version),
+            3 => Ok(Self::End),
+            _ => Err(StorageError::Storage(format!(
+                "未知的快照记录类型: {}",
+                tag
+            ))),
+        }
+    }
+}
+
+/// 块记录元数据
+#[derive(Debug, Serialize, Deserialize)]
+struct ChunkRecord {
+    /// 块 ID（原始数据的 SHA-256）
+    chunk_id: String,
+}
+
+/// 版本记录元数据
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionRecord {
+    /// 文件 ID
+    file_id: String,
+    /// 源存储中的版本 ID（仅供参考）
+    version_id: String,
+    /// 创建时间
+    created_at: chrono::NaiveDateTime,
+    /// 文件大小
+    size: u64,
+    /// 文件哈希（SHA-256）
+    hash: String,
+    /// 按偏移顺序排列的块 ID
+    chunks: Vec<String>,
+}
+
+/// 快照统计信息
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotStats {
+    /// 文件数量
+    pub files: usize,
+    /// 版本数量
+    pub versions: usize,
+    /// 块数量（去重后）
+    pub chunks: usize,
+    /// 块数据总字节数（去重后、未压缩）
+    pub chunk_bytes: u64,
+}
+
+impl StorageManager {
+    /// 导出整个存储的快照
+    ///
+    /// 导出前会调用 [`sync_all`](Self::sync_all) 保证读取到一致的已落盘状态。
+    /// 仅导出未删除的文件（含命名空间内的文件）及其全部版本，回收站内容不导出。
+    pub async fn export_snapshot<W>(&self, writer: &mut W) -> Result<SnapshotStats>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.sync_all().await?;
+
+        writer.write_all(SNAPSHOT_MAGIC).await?;
+        writer.write_u32_le(SNAPSHOT_FORMAT_VERSION).await?;
+
+        let mut stats = SnapshotStats::default();
+        let mut written_chunks = HashSet::new();
+
+        let mut file_ids = self.list_all_file_keys().await?;
+        file_ids.sort();
+
+        // 快照包含各命名空间内的文件
+        let storage = self.with_namespace_access();
+        for file_id in file_ids {
+            let mut versions = storage.list_file_versions(&file_id).await?;
+            versions.sort_by(|a, b| {
+                a.created_at
+                    .cmp(&b.created_at)
+                    .then_with(|| a.version_id.cmp(&b.version_id))
+            });
+
+            for version in versions {
+                let data = storage.read_version_data(&version.version_id).await?;
+                let mut generator = crate::core::delta::DeltaGenerator::new(
+                    self.chunk_size(),
+                    self.config().clone(),
+                );
+                let delta = generator
+                    .generate_full_delta(&data, &file_id)
+                    .map_err(|e| StorageError::Storage(format!("生成分块失败: {}", e)))?;
+
+                let mut chunk_ids = Vec::with_capacity(delta.chunks.len());
+                for chunk in &delta.chunks {
+                    if written_chunks.insert(chunk.chunk_id.clone()) {
+                        let chunk_data = &data[chunk.offset..chunk.offset + chunk.size];
+                        let record = ChunkRecord {
+                            chunk_id: chunk.chunk_id.clone(),
+                        };
+                        write_record(writer, RecordKind::Chunk, &record, chunk_data).await?;
+                        stats.chunks += 1;
+                        stats.chunk_bytes += chunk.size as u64;
+                    }
+                    chunk_ids.push(chunk.chunk_id.clone());
+                }
+
+                let record = VersionRecord {
+                    file_id: file_id.clone(),
+                    version_id: version.version_id.clone(),
+                    created_at: version.created_at,
+                    size: data.len() as u64,
+                    hash: sha256_hex(&data),
+                    chunks: chunk_ids,
+                };
+                write_record(writer, RecordKind::Version, &record, &[]).await?;
+                stats.versions += 1;
+            }
+            stats.files += 1;
+        }
+
+        write_record(writer, RecordKind::End, &stats, &[]).await?;
+        writer.flush().await?;
+
+        info!(
+            "快照导出完成: {} 个文件, {} 个版本, {} 个块",
+            stats.files, stats.versions, stats.chunks
+        );
+        Ok(stats)
+    }
+
+    /// 从快照导入文件
+    ///
+    /// 快照中的每个版本按原顺序保存为新版本，块与目标存储中已有的块去重。
+    /// 目标存储中已存在同名文件时，导入的版本追加在其当前版本之后。
+    /// 快照损坏（校验失败、缺块、被截断）时返回错误，已导入的版本保留。
+    pub async fn import_snapshot<R>(&self, reader: &mut R) -> Result<SnapshotStats>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.ensure_writable("导入快照")?;
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic).await.map_err(truncated)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(StorageError::Storage("不是有效的存储快照".to_string()));
+        }
+        let format_version = reader.read_u32_le().await.map_err(truncated)?;
+        if format_version != SNAPSHOT_FORMAT_VERSION {
+            return Err(StorageError::Storage(format!(
+                "不支持的快照格式版本: {}",
+                format_version
+            )));
+        }
+
+        // 块先暂存到磁盘，避免大快照占满内存
+        let staging = self
+            .scratch_dir()
+            .join(format!("snapshot-import-{}", scru128::new()));
+        fs::create_dir_all(&staging).await?;
+        let result = self.import_records(reader, &staging).await;
+        let _ = fs::remove_dir_all(&staging).await;
+        let stats = result?;
+
+        self.sync_all().await?;
+        info!(
+            "快照导入完成: {} 个文件, {} 个版本, {} 个块",
+            stats.files, stats.versions, stats.chunks
+        );
+        Ok(stats)
+    }
+
+    /// 逐条处理快照记录，直到结束记录
+    async fn import_records<R>(&self, reader: &mut R, staging: &Path) -> Result<SnapshotStats>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut stats = SnapshotStats::default();
+        // 文件 ID -> 本次导入的最新版本 ID
+        let mut parents: HashMap<String, String> = HashMap::new();
+        // 快照包含各命名空间内的文件
+        let storage = self.with_namespace_access();
+
+        loop {
+            let (kind, meta, data) = read_record(reader).await?;
+            match kind {
+                RecordKind::Chunk => {
+                    let record: ChunkRecord = serde_json::from_slice(&meta)?;
+                    validate_chunk_id(&record.chunk_id)?;
+                    if sha256_hex(&data) != record.chunk_id {
+                        return Err(StorageError::ChecksumMismatch(format!(
+                            "快照块校验失败: {}",
+                            record.chunk_id
+                        )));
+                    }
+                    fs::write(staging.join(&record.chunk_id), &data).await?;
+                    stats.chunks += 1;
+                    stats.chunk_bytes += data.len() as u64;
+                }
+                RecordKind::Version => {
+                    let record: VersionRecord = serde_json::from_slice(&meta)?;
+                    let data = assemble_version(&record, staging).await?;
+
+                    let parent = match parents.get(&record.file_id) {
+                        Some(parent) => Some(parent.clone()),
+                        None => storage.current_version_id(&record.file_id).await.ok(),
+                    };
+                    // 内容相同的相邻版本也要逐一保留
+                    let _guard = self.file_lock(&record.file_id).lock().await;
+                    let (_, version) = self
+                        .create_version(&record.file_id, &data, parent.as_deref())
+                        .await?;
+                    parents.insert(record.file_id, version.version_id);
+                    stats.versions += 1;
+                }
+                RecordKind::End => {
+                    let expected: SnapshotStats = serde_json::from_slice(&meta)?;
+                    stats.files = parents.len();
+                    if expected != stats {
+                        return Err(StorageError::Storage(format!(
+                            "快照内容与结束记录不一致: 期望 {:?}, 实际 {:?}",
+                            expected, stats
+                        )));
+                    }
+                    return Ok(stats);
+                }
+            }
+        }
+    }
+}
+
+/// 写入一条记录
+async fn write_record<W, T>(writer: &mut W, kind: RecordKind, meta: &T, data: &[u8]) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let meta = serde_json::to_vec(meta)?;
+    writer.write_u8(kind as u8).await?;
+    writer.write_u32_le(meta.len() as u32).await?;
+    writer.write_all(&meta).await?;
+    writer.write_u64_le(data.len() as u64).await?;
+    writer.write_all(data).await?;
+    Ok(())
+}
+
+/// 读取一条记录，返回 (类型, 元数据, 数据)
+async fn read_record<R>(reader: &mut R) -> Result<(RecordKind, Vec<u8>, Vec<u8>)>
+where
+    R: AsyncRead + Unpin,
+{
+    let kind = RecordKind::from_u8(reader.read_u8().await.map_err(truncated)?)?;
+
+    let meta_len = reader.read_u32_le().await.map_err(truncated)?;
+    if meta_len > MAX_META_LEN {
+        return Err(StorageError::Storage(format!(
+            "快照记录元数据过大: {} 字节",
+            meta_len
+        )));
+    }
+    let mut meta = vec![0u8; meta_len as usize];
+    reader.read_exact(&mut meta).await.map_err(truncated)?;
+
+    let data_len = reader.read_u64_le().await.map_err(truncated)?;
+    if data_len > MAX_CHUNK_LEN {
+        return Err(StorageError::Storage(format!(
+            "快照块数据过大: {} 字节",
+            data_len
+        )));
+    }
+    let mut data = vec![0u8; data_len as usize];
+    reader.read_exact(&mut data).await.map_err(truncated)?;
+
+    Ok((kind, meta, data))
+}
+
+/// 从暂存的块重组版本数据并校验
+async fn assemble_version(record: &VersionRecord, staging: &Path) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(record.size as usize);
+    for chunk_id in &record.chunks {
+        validate_chunk_id(chunk_id)?;
+        let chunk = fs::read(staging.join(chunk_id)).await.map_err(|_| {
+            StorageError::Chunk(format!("快照缺少块: {} ({})", chunk_id, record.file_id))
+        })?;
+        data.extend_from_slice(&chunk);
+    }
+
+    if data.len() as u64 != record.size || sha256_hex(&data) != record.hash {
+        return Err(StorageError::ChecksumMismatch(format!(
+            "快照版本校验失败: {} ({})",
+            record.file_id, record.version_id
+        )));
+    }
+    Ok(data)
+}
+
+/// 块 ID 必须是 SHA-256 十六进制串（同时防止暂存路径穿越）
+fn validate_chunk_id(chunk_id: &str) -> Result<()> {
+    if chunk_id.len() == 64 && chunk_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(StorageError::Storage(format!(
+            "无效的快照块 ID: {}",
+            chunk_id
+        )))
+    }
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// 流提前结束时给出明确的错误
+fn truncated(e: std::io::Error) -> StorageError {
+    if e.kind() == std::io::ErrorKind::UnexpectedEof {
+        StorageError::Storage("快照数据不完整".to_string())
+    } else {
+        StorageError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::namespace::Namespace;
+    use crate::test_util::{test_data, test_storage};
+    use silent_nas_core::StorageManagerTrait;
+
+    #[tokio::test]
+    async fn test_export_import_roundtrip() {
+        let (source, _source_dir) = test_storage().await;
+        let shared = test_data(64 * 1024, 0);
+        source.save_version("a.bin", &shared, None).await.unwrap();
+        source
+            .save_version("copy/a.bin", &shared, None)
+            .await
+            .unwrap();
+        source.save_version("empty", b"", None).await.unwrap();
+
+        let parent = source.current_version_id("a.bin").await.unwrap();
+        let mut edited = shared.clone();
+        edited.extend_from_slice(&test_data(8 * 1024, 7));
+        source
+            .save_version("a.bin", &edited, Some(&parent))
+            .await
+            .unwrap();
+
+        source
+            .namespace(Namespace::new("tenant").unwrap())
+            .save_file("doc.txt", b"tenant doc")
+            .await
+            .unwrap();
+        source.save_version("deleted", b"gone", None).await.unwrap();
+        StorageManager::delete_file(&source, "deleted")
+            .await
+            .unwrap();
+
+        let mut snapshot = Vec::new();
+        let exported = source.export_snapshot(&mut snapshot).await.unwrap();
+        assert_eq!(exported.files, 4);
+        assert_eq!(exported.versions, 5);
+
+        let (target, _target_dir) = test_storage().await;
+        let imported = target
+            .import_snapshot(&mut snapshot.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(imported, exported);
+
+        let mut source_files = source.list_all_file_keys().await.unwrap();
+        let mut target_files = target.list_all_file_keys().await.unwrap();
+        source_files.sort();
+        target_files.sort();
+        assert_eq!(source_files, target_files);
+
+        // 逐一比对内容（包括命名空间内的文件）
+        let source = source.with_namespace_access();
+        let target = target.with_namespace_access();
+        for file_id in &source_files {
+            assert_eq!(
+                target.read_file(file_id).await.unwrap(),
+                source.read_file(file_id).await.unwrap()
+            );
+
+            // 历史版本按相同顺序保留
+            let source_versions = source.list_file_versions(file_id).await.unwrap();
+            let target_versions = target.list_file_versions(file_id).await.unwrap();
+            assert_eq!(source_versions.len(), target_versions.len());
+            for (s, t) in source_versions.iter().zip(&target_versions) {
+                assert_eq!(
+                    source.read_version_data(&s.version_id).await.unwrap(),
+                    target.read_version_data(&t.version_id).await.unwrap()
+                );
+            }
+        }
+        assert!(!target.file_exists("deleted").await);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_corrupted_snapshot() {
+        let (source, _source_dir) = test_storage().await;
+        source
+            .save_version("file", &test_data(16 * 1024, 3), None)
+            .await
+            .unwrap();
+        let mut snapshot = Vec::new();
+        source.export_snapshot(&mut snapshot).await.unwrap();
+
+        let (target, _target_dir) = test_storage().await;
+
+        // 魔数错误
+        let mut bad_magic = snapshot.clone();
+        bad_magic[0] ^= 0xff;
+        assert!(
+            target
+                .import_snapshot(&mut bad_magic.as_slice())
+                .await
+                .is_err()
+        );
+
+        // 第一个块记录的数据被篡改
+        let mut tampered = snapshot.clone();
+        let header_len = SNAPSHOT_MAGIC.len() + 4;
+        let first_data = header_len + 1 + 4;
+        let meta_len =
+            u32::from_le_bytes(tampered[header_len + 1..first_data].try_into().unwrap()) as usize;
+        tampered[first_data + meta_len + 8] ^= 0xff;
+        assert!(matches!(
+            target.import_snapshot(&mut tampered.as_slice()).await,
+            Err(StorageError::ChecksumMismatch(_))
+        ));
+
+        // 被截断
+        let truncated = &snapshot[..snapshot.len() - 10];
+        assert!(target.import_snapshot(&mut &truncated[..]).await.is_err());
+
+        // 暂存目录已清理
+        let mut entries = fs::read_dir(target.version_root()).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            assert!(
+                !entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("snapshot-import-")
+            );
+        }
+    }
+}
@@ -9,7 +9,7 @@
 //! ## 核心存储 (Lines 121-1298)
 //! - 初始化和配置管理
 //! - 版本保存和读取 (`save_version`, `read_version_data`)
-//! - 版本管理 (`list_file_versions`, `delete_file_version`)
+//! - 版本管理 (`current_version_id`, `list_file_versions`, `delete_file_version`)
 //! - 存储统计 (`get_storage_stats`, `get_deduplication_stats`)
 //! - 块操作 (`save_chunk`, `read_chunk`)
 //! - 路径管理和辅助方法
@@ -17,8 +17,9 @@
 //! ## 索引和文件管理 (Lines 1300-1733)
 //! - 引用计数管理 (`load_chunk_ref_count`, `save_chunk_ref_count`)
 //! - 文件索引 (`load_file_index`, `save_file_index`, `rebuild_file_index`)
-//! - 文件列表和删除 (`list_files`, `delete_file`, `permanently_delete_file`)
-//! - 回收站管理 (`list_deleted_files`, `restore_file`, `empty_recycle_bin`)
+//! - 文件列表和删除 (`list_files`, `delete_file`, `permanently_delete_file`, `hard_delete_file`)
+//! - 回收站管理 (`list_deleted_files`, `restore_file`, `empty_recycle_bin`, `purge_expired_recycle_bin`)
+//! - 保留锁（WORM） (`set_retention`, `remove_retention`, `get_retention`)
 //!
 //! ## 垃圾回收 (Lines 1736-1901)
 //! - 块级垃圾回收 (`garbage_collect_blocks`)
@@ -30,7 +31,7 @@
 //! - 文件信息查询 (`get_file_info`)
 //!
 //! ## 可靠性 (Lines 2119-2163)
-//! - 块校验 (`verify_all_chunks`, `verify_chunks`)
+//! - 块校验 (`verify_all_chunks`, `verify_all_chunks_with`, `verify_chunks`)
 //! - 孤儿块检测和清理 (`detect_orphan_chunks`, `cleanup_orphan_chunks`)
 //!
 //! ## 后台优化 (Lines 2165-2663)
@@ -38,30 +39,71 @@
 //! - 优化策略 (`optimize_compress_only`, `optimize_full`)
 //! - 后台优化任务 (`start_optimization_task`, `stop_optimization_task`)
 //! - 优化调度器控制 (`pause/resume_optimization_scheduler`)
+//! - 维护模式 (`enter_maintenance`, `exit_maintenance`)
 //!
 //! ## Trait 实现 (Lines 2708-2931)
 //! - `StorageManagerTrait` 实现
 //! - `S3CompatibleStorageTrait` 实现
 
 use crate::cache::CacheManager;
+use crate::chunk_store::{ChunkStore, LocalChunkStore};
 use crate::error::{Result, StorageError};
 use crate::metadata::SledMetadataDb;
 use crate::reliability::{ChunkVerifier, OrphanChunkCleaner, WalManager};
 use crate::{ChunkInfo, FileDelta, IncrementalConfig, VersionInfo};
 use async_trait::async_trait;
 use chrono::Local;
+use futures::Stream;
 use moka::future::Cache;
 use serde::{Deserialize, Serialize};
-use silent_nas_core::{FileMetadata, FileVersion, S3CompatibleStorageTrait, StorageManagerTrait};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use silent_nas_core::{
+    FileMetadata, FileVersion, S3CompatibleStorageTrait, SharedClock, StorageManagerTrait,
+    SystemClock,
+};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::fs;
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
-use tokio::sync::{OnceCell, RwLock};
-use tracing::{info, warn};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::{OnceCell, RwLock, broadcast};
+use tracing::{error, info, warn};
+
+/// 磁盘空间不足时优化任务的暂停时长（秒）
+const OUT_OF_SPACE_PAUSE_SECS: u64 = 60;
+
+/// 系统负载超过上限时暂停领取优化任务的时长（秒）
+const OPTIMIZATION_LOAD_BACKOFF_SECS: u64 = 5;
+
+/// 用户自定义元数据的大小上限（所有键与值的 UTF-8 字节数之和，与 S3 的 2KB 限制一致）
+pub const MAX_USER_METADATA_SIZE: usize = 2 * 1024;
+
+/// 变更通知通道容量（订阅者落后超过此数量时丢弃最旧的事件）
+const MUTATION_CHANNEL_CAPACITY: usize = 1024;
+
+/// 文件保存的分段锁数量（按文件ID哈希选择）
+const FILE_LOCK_STRIPES: usize = 64;
+
+/// 块写入的分段锁数量（按块ID哈希选择）
+const CHUNK_WRITE_LOCK_STRIPES: usize = 256;
+
+/// 重建去重索引时每批写入暂存结果的版本数
+const DEDUP_REBUILD_BATCH_VERSIONS: usize = 256;
+
+/// GC 每删除多少个块检查一次租约（见 [`GcFence`]）
+const GC_FENCE_BATCH: usize = 256;
+
+/// 分块建议最多抽样的块数量
+const CHUNKING_SAMPLE_LIMIT: usize = 100_000;
+
+/// 分块建议所需的最少样本块数量
+const CHUNKING_MIN_SAMPLES: usize = 64;
+
+/// 建议的平均分块大小下限与上限
+const MIN_RECOMMENDED_CHUNK_SIZE: usize = 4 * 1024;
+const MAX_RECOMMENDED_CHUNK_SIZE: usize = 16 * 1024 * 1024;
 
 /// 块引用计数信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,6 +116,9 @@ pub struct ChunkRefCount {
     pub size: u64,
     /// 存储路径
     pub path: PathBuf,
+    /// 弱哈希（0 表示未计算，如固定大小分块或旧数据）
+    #[serde(default)]
+    pub weak_hash: u32,
 }
 
 /// 文件索引信息
@@ -107,6 +152,186 @@ pub struct FileIndexEntry {
     /// 文件哈希（SHA-256）
     #[serde(default)]
     pub file_hash: String,
+    /// 用户自定义元数据（跨版本保留）
+    #[serde(default)]
+    pub user_metadata: HashMap<String, String>,
+    /// 后台优化采用的策略（未经后台优化时为 `None`）
+    #[serde(default)]
+    pub optimization_strategy: Option<crate::OptimizationStrategy>,
+    /// 后台优化完成时间
+    #[serde(default)]
+    pub optimized_at: Option<chrono::NaiveDateTime>,
+    /// 后台优化节省的空间（字节）
+    #[serde(default)]
+    pub space_saved: u64,
+    /// 后台优化后新增的实际存储大小（字节，去重命中的块不计入）
+    #[serde(default)]
+    pub stored_size: u64,
+    /// 按当前块共享情况分摊到该文件所有版本的实际存储大小（字节）
+    ///
+    /// 由 [`StorageManager::recompute_usage`] 计算，未计算过时为 0。
+    #[serde(default)]
+    pub attributed_size: u64,
+    /// 别名指向的目标文件ID（普通文件为 `None`）
+    ///
+    /// 别名自身没有版本，读取时解析到目标文件的当前版本，见 [`StorageManager::create_alias`]。
+    #[serde(default)]
+    pub alias_target: Option<String>,
+    /// 压缩存储模式下整文件使用的压缩算法
+    ///
+    /// 由后台整文件压缩记录，`None` 表示按当前配置推断（旧数据）。
+    #[serde(default)]
+    pub compression: Option<crate::core::compression::CompressionAlgorithm>,
+    /// 保留锁（WORM），到期前文件及其所有版本不能删除或移动
+    ///
+    /// 见 [`StorageManager::set_retention`]。
+    #[serde(default)]
+    pub retention: Option<RetentionLock>,
+}
+
+impl FileIndexEntry {
+    /// 清除后台优化记录（新版本的存储形式与之前的优化结果无关）
+    fn clear_optimization_stats(&mut self) {
+        self.optimization_strategy = None;
+        self.optimized_at = None;
+        self.space_saved = 0;
+        self.stored_size = 0;
+    }
+
+    /// 在 `now` 时仍然有效的保留锁
+    fn active_retention(&self, now: chrono::NaiveDateTime) -> Option<&RetentionLock> {
+        self.retention.as_ref().filter(|lock| lock.is_active(now))
+    }
+}
+
+/// 保留模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionMode {
+    /// 治理模式：到期前可由管理员缩短或解除
+    Governance,
+    /// 合规模式：到期前任何人（包括管理员）都不能缩短、降级或解除
+    Compliance,
+}
+
+/// 文件保留锁（一次写入多次读取）
+///
+/// 到期前文件不能删除（含永久删除）、移动，已有版本不能删除；
+/// 仍可写入新版本，旧版本随文件一起保留。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionLock {
+    /// 保留截止时间（本地时间）
+    pub until: chrono::NaiveDateTime,
+    /// 保留模式
+    pub mode: RetentionMode,
+}
+
+impl RetentionLock {
+    /// 在 `now` 时是否仍在保留期内
+    pub fn is_active(&self, now: chrono::NaiveDateTime) -> bool {
+        now < self.until
+    }
+}
+
+/// 检查有效的保留锁能否被缩短或解除
+fn ensure_retention_relaxable(
+    file_id: &str,
+    current: &RetentionLock,
+    bypass_governance: bool,
+) -> Result<()> {
+    match current.mode {
+        RetentionMode::Compliance => Err(StorageError::RetentionLocked(format!(
+            "{} 处于合规模式保留期（至 {}），不能缩短或解除",
+            file_id, current.until
+        ))),
+        RetentionMode::Governance if !bypass_governance => {
+            Err(StorageError::RetentionLocked(format!(
+                "{} 处于治理模式保留期（至 {}），缩短或解除需要管理员权限",
+                file_id, current.until
+            )))
+        }
+        RetentionMode::Governance => Ok(()),
+    }
+}
+
+/// 文件状态
+///
+/// 只来自文件索引的一次查找，不读取版本记录和内容，
+/// 供 HEAD、PROPFIND 等只需要元数据的请求使用。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileStat {
+    /// 文件ID
+    pub file_id: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 文件内容哈希（SHA-256）
+    pub hash: String,
+    /// 当前版本ID
+    pub version_id: String,
+    /// 创建时间
+    pub created_at: chrono::NaiveDateTime,
+    /// 最后修改时间
+    pub modified_at: chrono::NaiveDateTime,
+    /// 存储模式
+    pub storage_mode: crate::StorageMode,
+    /// 版本数量
+    #[serde(default)]
+    pub version_count: usize,
+    /// 用户自定义元数据
+    pub user_metadata: HashMap<String, String>,
+    /// 别名指向的目标文件ID（普通文件为 `None`，其余字段取自目标文件）
+    #[serde(default)]
+    pub alias_target: Option<String>,
+}
+
+impl FileStat {
+    /// RFC 1123 格式的最后修改时间，用于 `Last-Modified` 头和 `getlastmodified` 属性
+    pub fn last_modified_http(&self) -> String {
+        Self::http_date(&self.modified_at)
+    }
+
+    /// 将时间格式化为 RFC 1123 格式（如 `Wed, 01 May 2024 12:00:00 GMT`）
+    pub fn http_date(time: &chrono::NaiveDateTime) -> String {
+        time.and_utc()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
+    }
+
+    /// 条件 GET 是否应返回 `304 Not Modified`
+    ///
+    /// 带 `If-None-Match` 时只按 ETag 弱比较（忽略 `W/` 前缀），不再看 `If-Modified-Since`；
+    /// 否则修改时间按秒截断后不晚于 `If-Modified-Since` 即视为未修改（HTTP 日期只精确到秒）。
+    /// 无法解析的日期视为条件不成立。
+    pub fn not_modified(
+        &self,
+        etag: &str,
+        if_none_match: Option<&str>,
+        if_modified_since: Option<&str>,
+    ) -> bool {
+        if let Some(value) = if_none_match {
+            let strip = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+            return value.trim() == "*" || value.split(',').any(|tag| strip(tag) == strip(etag));
+        }
+        if_modified_since
+            .and_then(|value| chrono::DateTime::parse_from_rfc2822(value.trim()).ok())
+            .is_some_and(|since| self.modified_at.and_utc().timestamp() <= since.timestamp())
+    }
+
+    /// 转换为 `FileMetadata`
+    ///
+    /// 与 `get_metadata` 保持一致，`hash` 取当前版本ID（用作 ETag）。
+    pub fn into_metadata(self) -> FileMetadata {
+        FileMetadata {
+            id: self.file_id.clone(),
+            name: self.file_id.clone(),
+            path: self.file_id,
+            size: self.size,
+            hash: self.version_id,
+            created_at: self.created_at,
+            modified_at: self.modified_at,
+            user_metadata: self.user_metadata,
+        }
+    }
 }
 
 /// 存储管理器
@@ -124,8 +349,10 @@ pub struct StorageManager {
     config: IncrementalConfig,
     /// 版本根目录 (root_path/incremental)
     version_root: PathBuf,
-    /// 块存储根目录
+    /// 块目录 (root_path/incremental/chunks，默认本地块存储的根目录)
     chunk_root: PathBuf,
+    /// 块存储后端
+    chunk_store: Arc<dyn ChunkStore>,
     /// 块大小（预留字段，当前使用 IncrementalConfig 中的分块配置）
     #[allow(dead_code)]
     chunk_size: usize,
@@ -147,16 +374,49 @@ pub struct StorageManager {
     compressor: Arc<crate::core::compression::Compressor>,
     /// Bloom Filter（快速块存在性检测，减少文件系统调用）
     chunk_bloom_filter: Arc<crate::bloom::ChunkBloomFilter>,
+    /// 弱哈希去重索引（CDC 块的快速预过滤）
+    dedup_index: Arc<crate::services::WeakHashDedupIndex>,
     /// GC任务句柄
     gc_task_handle: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
     /// GC任务停止标志（无锁原子操作）
     gc_stop_flag: Arc<AtomicBool>,
+    /// 唤醒等待下一轮的GC任务（停止时无需等满一个间隔）
+    gc_wake: Arc<tokio::sync::Notify>,
     /// 优化调度器
     optimization_scheduler: Arc<crate::OptimizationScheduler>,
     /// 优化任务句柄
     optimization_task_handle: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
     /// 优化任务停止标志（无锁原子操作）
     optimization_stop_flag: Arc<AtomicBool>,
+    /// 时钟（删除时间与回收站保留期以此为准）
+    clock: SharedClock,
+    /// 变更通知发送端
+    mutation_tx: broadcast::Sender<MutationEvent>,
+    /// 硬删除时同步清理上层状态的钩子
+    erase_hooks: Arc<std::sync::RwLock<Vec<Arc<dyn EraseHook>>>>,
+    /// 是否允许访问命名空间内的键（租户视图与后台任务持有的副本为 `true`）
+    namespace_access: bool,
+    /// 文件保存的分段锁（同一文件的所有保存路径互斥）
+    file_locks: Arc<Vec<tokio::sync::Mutex<()>>>,
+    /// 块写入的分段锁（同一新块只由一个写入者压缩并写入）
+    chunk_write_locks: Arc<Vec<tokio::sync::Mutex<()>>>,
+    /// GC 主节点租约（未启用选举时为 `None`，本节点总是执行 GC）
+    gc_lease: Option<Arc<crate::leader::GcLease>>,
+    /// 后台执行中的版本链压缩任务（关闭与进入维护模式时等待其完成）
+    chain_compactions: Arc<std::sync::Mutex<tokio::task::JoinSet<()>>>,
+    /// 块引用计数重建锁（同一时间只允许一个重建任务）
+    dedup_rebuild_lock: Arc<tokio::sync::Mutex<()>>,
+    /// 维护模式标志（无锁原子操作，写操作据此拒绝）
+    maintenance_flag: Arc<AtomicBool>,
+    /// 进入维护模式前后台任务的运行情况，退出时据此恢复（不在维护模式时为 `None`）
+    maintenance_resume: Arc<tokio::sync::Mutex<Option<MaintenanceResume>>>,
+    /// 下载校验发现内容损坏的次数
+    corrupt_reads: Arc<AtomicU64>,
+    /// 下载校验发现内容损坏的版本，之后的读取改走强一致路径
+    suspect_versions: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// 版本记录查询次数（仅测试使用）
+    #[cfg(test)]
+    version_lookups: Arc<std::sync::atomic::AtomicUsize>,
 }
 
 // ============================================================================
@@ -166,39 +426,67 @@ pub struct StorageManager {
 // ============================================================================
 
 impl StorageManager {
+    /// 创建存储管理器
+    ///
+    /// 块数据、元数据数据库与热存储默认位于 `root_path` 下，可通过
+    /// [`IncrementalConfig::chunk_root`]、[`IncrementalConfig::metadata_dir`]、
+    /// [`IncrementalConfig::hot_dir`] 分别放到其他目录，缺失的目录在 `init` 时创建。
     pub fn new(root_path: PathBuf, chunk_size: usize, config: IncrementalConfig) -> Self {
+        let chunk_root = config
+            .chunk_root
+            .clone()
+            .unwrap_or_else(|| root_path.join("incremental").join("chunks"));
+        let mut chunk_store: Arc<dyn ChunkStore> =
+            Arc::new(LocalChunkStore::new(chunk_root.clone()));
+        if config.packing.enable {
+            chunk_store = Arc::new(crate::pack::PackedChunkStore::new(
+                chunk_root.join("packs"),
+                chunk_store,
+                config.packing.clone(),
+            ));
+        }
+        Self::with_chunk_store(root_path, chunk_size, config, chunk_store)
+    }
+
+    /// 使用指定的块存储后端创建存储管理器
+    ///
+    /// 版本和差异数据仍保存在 `root_path` 下，只有块数据交给 `chunk_store`。
+    pub fn with_chunk_store(
+        root_path: PathBuf,
+        chunk_size: usize,
+        config: IncrementalConfig,
+        chunk_store: Arc<dyn ChunkStore>,
+    ) -> Self {
         let data_root = root_path.join("data");
-        let hot_storage_root = root_path.join("hot");
+        let hot_storage_root = config
+            .hot_dir
+            .clone()
+            .unwrap_or_else(|| root_path.join("hot"));
         let version_root = root_path.join("incremental");
-        let chunk_root = version_root.join("chunks");
+        let chunk_root = config
+            .chunk_root
+            .clone()
+            .unwrap_or_else(|| version_root.join("chunks"));
         let wal_path = version_root.join("wal.log");
 
         // 从 IncrementalConfig 创建压缩配置
-        let compression_algorithm = match config.compression_algorithm.as_str() {
-            "lz4" => crate::core::compression::CompressionAlgorithm::LZ4,
-            "zstd" => crate::core::compression::CompressionAlgorithm::Zstd,
-            _ => crate::core::compression::CompressionAlgorithm::None,
-        };
-
-        let compression_config = crate::core::compression::CompressionConfig {
-            algorithm: if config.enable_compression {
-                compression_algorithm
-            } else {
-                crate::core::compression::CompressionAlgorithm::None
-            },
-            level: 1,       // 快速压缩
-            min_size: 1024, // 1KB 以上才压缩
-            auto_compress_days: 7,
-            min_ratio: 1.1, // 压缩比至少 10%
+        let compression_algorithm = if config.enable_compression {
+            crate::parse_compression_algorithm(&config.compression_algorithm)
+        } else {
+            crate::core::compression::CompressionAlgorithm::None
         };
+        let compressor = Arc::new(
+            crate::core::compression::Compressor::new(chunk_compression_config(
+                compression_algorithm,
+            ))
+            .with_max_entropy(config.compression_max_entropy),
+        );
 
-        let compressor = Arc::new(crate::core::compression::Compressor::new(
-            compression_config,
+        // 初始化优化调度器（并发数由配置决定）
+        let optimization_scheduler = Arc::new(crate::OptimizationScheduler::new(
+            config.optimization_concurrency,
         ));
 
-        // 初始化优化调度器（最多2个并发任务）
-        let optimization_scheduler = Arc::new(crate::OptimizationScheduler::new(2));
-
         // 初始化 LRU 缓存（有界，防止 OOM）
         // version_cache: 10,000 个版本，TTL 1小时，空闲5分钟淘汰
         let version_cache = Cache::builder()
@@ -217,43 +505,113 @@ impl StorageManager {
         // 初始化 Bloom Filter（1000万块，0.1% 假阳性率，~12 MB 内存）
         let chunk_bloom_filter = Arc::new(crate::bloom::ChunkBloomFilter::with_defaults());
 
+        // 弱哈希去重索引：超出容量时按 LRU 溢出，条目可从 Sled 块引用中加载回来
+        let dedup_index = Arc::new(crate::services::WeakHashDedupIndex::with_capacity(
+            config.max_memory_index,
+        ));
+
+        // GC 主节点租约：默认放在块存储目录，与共享的块数据位于同一存储
+        let gc_lease = config.gc_election.enable.then(|| {
+            let election = &config.gc_election;
+            let node_id = if election.node_id.is_empty() {
+                scru128::new_string()
+            } else {
+                election.node_id.clone()
+            };
+            let lease_dir = election
+                .lease_dir
+                .clone()
+                .unwrap_or_else(|| chunk_root.clone());
+            Arc::new(crate::leader::GcLease::new(
+                lease_dir,
+                node_id,
+                election.lease_ttl_secs,
+            ))
+        });
+
         Self {
             root_path,
             data_root,
             hot_storage_root,
             config,
             version_root,
-            chunk_root: chunk_root.clone(),
+            chunk_root,
+            chunk_store: chunk_store.clone(),
             chunk_size,
             metadata_db: Arc::new(OnceCell::new()),
             version_cache,
             block_cache,
             cache_manager: Arc::new(CacheManager::with_default()),
             wal_manager: Arc::new(RwLock::new(WalManager::new(wal_path))),
-            chunk_verifier: Arc::new(ChunkVerifier::new(chunk_root.clone())),
-            orphan_cleaner: Arc::new(OrphanChunkCleaner::new(chunk_root)),
+            chunk_verifier: Arc::new(ChunkVerifier::with_store(chunk_store.clone())),
+            orphan_cleaner: Arc::new(OrphanChunkCleaner::with_store(chunk_store)),
             compressor,
             chunk_bloom_filter,
+            dedup_index,
             gc_task_handle: Arc::new(RwLock::new(None)),
             gc_stop_flag: Arc::new(AtomicBool::new(false)),
+            gc_wake: Arc::new(tokio::sync::Notify::new()),
             optimization_scheduler,
             optimization_task_handle: Arc::new(RwLock::new(None)),
             optimization_stop_flag: Arc::new(AtomicBool::new(false)),
+            clock: SystemClock::shared(),
+            mutation_tx: broadcast::channel(MUTATION_CHANNEL_CAPACITY).0,
+            erase_hooks: Arc::new(std::sync::RwLock::new(Vec::new())),
+            namespace_access: false,
+            file_locks: Arc::new(
+                (0..FILE_LOCK_STRIPES)
+                    .map(|_| tokio::sync::Mutex::new(()))
+                    .collect(),
+            ),
+            chunk_write_locks: Arc::new(
+                (0..CHUNK_WRITE_LOCK_STRIPES)
+                    .map(|_| tokio::sync::Mutex::new(()))
+                    .collect(),
+            ),
+            gc_lease,
+            chain_compactions: Arc::new(std::sync::Mutex::new(tokio::task::JoinSet::new())),
+            dedup_rebuild_lock: Arc::new(tokio::sync::Mutex::new(())),
+            maintenance_flag: Arc::new(AtomicBool::new(false)),
+            maintenance_resume: Arc::new(tokio::sync::Mutex::new(None)),
+            corrupt_reads: Arc::new(AtomicU64::new(0)),
+            suspect_versions: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            #[cfg(test)]
+            version_lookups: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
         }
     }
 
+    /// 使用指定时钟
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// 初始化增量存储
     pub async fn init(&self) -> Result<()> {
+        self.config.validate()?;
+
         // 创建必要的目录
         fs::create_dir_all(&self.root_path).await?;
         fs::create_dir_all(&self.data_root).await?;
         fs::create_dir_all(&self.hot_storage_root).await?;
         fs::create_dir_all(&self.version_root).await?;
         fs::create_dir_all(&self.chunk_root).await?;
+        fs::create_dir_all(self.metadata_dir()).await?;
+        fs::create_dir_all(self.scratch_dir()).await?;
+
+        // 清理上次运行（如崩溃）残留的临时文件
+        if !self.config.read_only && self.config.scratch_max_age_secs > 0 {
+            let removed = self
+                .sweep_scratch_dir(Duration::from_secs(self.config.scratch_max_age_secs))
+                .await?;
+            if removed > 0 {
+                info!("已清理 {} 个过期临时文件", removed);
+            }
+        }
 
         // 初始化 Sled 元数据数据库
-        let db_path = self.version_root.join("metadata");
-        let metadata_db = SledMetadataDb::open(&db_path)
+        let db_path = self.metadata_dir();
+        let metadata_db = SledMetadataDb::open_with_config(&db_path, &self.config.metadata)
             .map_err(|e| StorageError::Storage(format!("初始化 Sled 数据库失败: {}", e)))?;
 
         self.metadata_db
@@ -272,20 +630,27 @@ impl StorageManager {
         self.load_block_index().await?;
         self.load_chunk_ref_count().await?;
         self.load_file_index().await?;
+        self.load_chunk_referrers().await?;
+        self.load_content_hash_index().await?;
 
         // 重建 Bloom Filter（从现有块）
         self.rebuild_bloom_filter().await?;
         info!("Bloom Filter 重建完成");
 
-        // 启动自动GC任务（如果启用）
-        if self.config.enable_auto_gc {
-            self.start_gc_task().await;
-            info!("自动GC任务已启动，间隔: {}秒", self.config.gc_interval_secs);
-        }
+        // 只读副本不启动任何会修改数据的后台任务
+        if self.config.read_only {
+            info!("只读模式：跳过自动GC与后台优化任务");
+        } else {
+            // 启动自动GC任务（如果启用）
+            if self.config.enable_auto_gc {
+                self.start_gc_task().await;
+                info!("自动GC任务已启动，间隔: {}秒", self.config.gc_interval_secs);
+            }
 
-        // 启动后台优化任务（统一流程，始终启用）
-        self.start_optimization_task().await;
-        info!("后台优化任务已启动");
+            // 启动后台优化任务（统一流程，始终启用）
+            self.start_optimization_task().await;
+            info!("后台优化任务已启动");
+        }
 
         info!(
             "增量存储初始化完成: root={:?}, data={:?}, version_root={:?}",
@@ -301,6 +666,136 @@ impl StorageManager {
             .ok_or_else(|| StorageError::Storage("元数据数据库未初始化".to_string()))
     }
 
+    /// 是否为只读副本模式
+    pub fn is_read_only(&self) -> bool {
+        self.config.read_only
+    }
+
+    /// 当前使用的增量存储配置
+    pub fn config(&self) -> &IncrementalConfig {
+        &self.config
+    }
+
+    /// 目标分块大小
+    pub(crate) fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// 只读模式下拒绝写操作
+    pub(crate) fn ensure_writable(&self, operation: &str) -> Result<()> {
+        if self.config.read_only {
+            return Err(StorageError::ReadOnly(operation.to_string()));
+        }
+        if self.maintenance_flag.load(Ordering::Relaxed) {
+            return Err(StorageError::Maintenance(operation.to_string()));
+        }
+        Ok(())
+    }
+
+    /// 本节点当前是否为 GC 主节点
+    ///
+    /// 未启用选举时总是返回 `true`；启用时获取或续约共享租约，读写租约失败视为非主节点。
+    pub async fn is_gc_leader(&self) -> bool {
+        let Some(lease) = &self.gc_lease else {
+            return true;
+        };
+        match lease.try_acquire().await {
+            Ok(leader) => leader,
+            Err(e) => {
+                warn!("获取 GC 租约失败: {}", e);
+                false
+            }
+        }
+    }
+
+    /// 全局 GC、孤儿块清理等破坏性操作只允许主节点执行
+    async fn ensure_gc_leader(&self, operation: &'static str) -> Result<()> {
+        self.acquire_gc_fence(operation).await.map(|_| ())
+    }
+
+    /// 获取 GC 租约凭证，本节点不是主节点时返回 `NotLeader`
+    async fn acquire_gc_fence(&self, operation: &'static str) -> Result<GcFence> {
+        let generation = match &self.gc_lease {
+            None => None,
+            Some(lease) => match lease.acquire().await {
+                Ok(Some(generation)) => Some(generation),
+                Ok(None) => return Err(StorageError::NotLeader(operation.to_string())),
+                Err(e) => {
+                    warn!("获取 GC 租约失败: {}", e);
+                    return Err(StorageError::NotLeader(operation.to_string()));
+                }
+            },
+        };
+        Ok(GcFence {
+            operation,
+            generation,
+            pending: 0,
+            renewed_at: std::time::Instant::now(),
+        })
+    }
+
+    /// 删除下一个块前检查租约
+    ///
+    /// 每 [`GC_FENCE_BATCH`] 个块或距上次续约超过租约有效期的三分之一时续约一次
+    /// （见 [`Self::renew_gc_fence`]）。
+    async fn check_gc_fence(&self, fence: &mut GcFence) -> Result<()> {
+        let Some(lease) = &self.gc_lease else {
+            return Ok(());
+        };
+        fence.pending += 1;
+        if fence.pending < GC_FENCE_BATCH && fence.renewed_at.elapsed() < lease.ttl() / 3 {
+            return Ok(());
+        }
+        self.renew_gc_fence(fence).await
+    }
+
+    /// 续约 GC 租约并确认代数未变
+    ///
+    /// 续约失败或代数已变（租约曾过期并被接管）时返回 `NotLeader`，调用方应立即停止删除。
+    async fn renew_gc_fence(&self, fence: &mut GcFence) -> Result<()> {
+        let (Some(lease), Some(generation)) = (&self.gc_lease, fence.generation) else {
+            return Ok(());
+        };
+        match lease.acquire().await {
+            Ok(Some(current)) if current == generation => {
+                fence.pending = 0;
+                fence.renewed_at = std::time::Instant::now();
+                Ok(())
+            }
+            Ok(current) => {
+                warn!(
+                    "{}期间失去 GC 租约（持有第 {} 代，当前 {:?}），中止",
+                    fence.operation, generation, current
+                );
+                Err(StorageError::NotLeader(fence.operation.to_string()))
+            }
+            Err(e) => {
+                warn!("{}期间续约 GC 租约失败，中止: {}", fence.operation, e);
+                Err(StorageError::NotLeader(fence.operation.to_string()))
+            }
+        }
+    }
+
+    /// 配额剩余空间（字节），未配置配额时返回 `None`
+    ///
+    /// `file_id` 自身的当前大小不计入已用空间（新版本将取代它）。
+    /// 已用空间取自元数据库增量维护的文件大小总和，不逐次遍历文件索引。
+    fn quota_remaining(&self, file_id: &str) -> Result<Option<u64>> {
+        let Some(quota) = self.config.quota_bytes else {
+            return Ok(None);
+        };
+
+        let metadata_db = self.get_metadata_db()?;
+        let own_size = metadata_db
+            .get_file_index(file_id)?
+            .map_or(0, |entry| entry.file_size);
+        let used = metadata_db
+            .index_totals()?
+            .file_size
+            .saturating_sub(own_size);
+        Ok(Some(quota.saturating_sub(used)))
+    }
+
     /// 获取缓存管理器引用
     pub fn get_cache_manager(&self) -> Arc<CacheManager> {
         self.cache_manager.clone()
@@ -324,9 +819,60 @@ impl StorageManager {
             hash: file_version.version_id.clone(),
             created_at: file_version.created_at,
             modified_at: file_version.created_at,
+            user_metadata: Default::default(),
+        })
+    }
+
+    /// 保存文件并记录版本的创建者与说明
+    pub async fn save_file_with_meta(
+        &self,
+        file_id: &str,
+        data: &[u8],
+        author: Option<&str>,
+        comment: Option<&str>,
+    ) -> Result<FileMetadata> {
+        let (_delta, file_version) = self
+            .save_version_with_meta(file_id, data, None, author, comment)
+            .await?;
+
+        Ok(FileMetadata {
+            id: file_id.to_string(),
+            name: file_id.to_string(),
+            path: file_id.to_string(),
+            size: file_version.size,
+            hash: file_version.version_id.clone(),
+            created_at: file_version.created_at,
+            modified_at: file_version.created_at,
+            user_metadata: Default::default(),
         })
     }
 
+    /// 秒传保存文件，未命中时返回 `Ok(None)`（见 [`Self::save_version_by_hash`]）
+    pub async fn save_file_by_hash(
+        &self,
+        file_id: &str,
+        file_hash: &str,
+        file_size: u64,
+    ) -> Result<Option<FileMetadata>> {
+        let Some((_delta, file_version)) = self
+            .save_version_by_hash(file_id, file_hash, file_size, None)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(FileMetadata {
+            id: file_id.to_string(),
+            name: file_id.to_string(),
+            path: file_id.to_string(),
+            size: file_version.size,
+            hash: file_version.version_id.clone(),
+            created_at: file_version.created_at,
+            modified_at: file_version.created_at,
+            user_metadata: Default::default(),
+        }))
+    }
+
     /// 从异步读取器流式保存文件（供上层传入 HTTP body 等场景使用）
     pub async fn save_file_from_reader<R>(
         &self,
@@ -346,6 +892,7 @@ impl StorageManager {
             hash: file_version.version_id.clone(),
             created_at: file_version.created_at,
             modified_at: file_version.created_at,
+            user_metadata: Default::default(),
         })
     }
 
@@ -365,7 +912,9 @@ impl StorageManager {
 
     /// 从异步读取器流式保存文件版本（用于 WebDAV 等场景）
     ///
-    /// 流式读取数据后进行即时分块+去重存储
+    /// 流式读取数据后进行即时分块+去重存储。读取与写入块期间不持有文件锁
+    /// （文件锁按哈希分条共享，慢速客户端不应阻塞其他文件的保存），
+    /// 只在登记引用与提交版本时持有。
     pub async fn save_version_from_reader<R>(
         &self,
         file_id: &str,
@@ -375,16 +924,24 @@ impl StorageManager {
     where
         R: AsyncRead + Unpin,
     {
+        self.ensure_writable("保存版本")?;
+        let target = self.resolve_write_target(file_id)?;
+        let file_id = target.as_str();
+        self.apply_backpressure().await?;
+        // 读取期间按当前用量提前中止超限的上传，提交前持锁再次检查
+        let quota_remaining = self.quota_remaining(file_id)?;
+
         // 流式分块存储：读取 → 分块 → 保存（内存占用恒定）
-        let version_id = format!("v_{}", scru128::new());
         let now = Local::now().naive_local();
 
-        info!("文件 {} 开始流式分块存储，版本 {}", file_id, version_id);
+        info!("文件 {} 开始流式分块存储", file_id);
 
+        let chunk_salt = self.chunk_salt(file_id);
         let mut chunks = Vec::new();
         let mut offset = 0usize;
         let mut file_size = 0u64;
         let mut buffer = vec![0u8; self.chunk_size];
+        let mut file_hasher = Sha256::new();
         let mut dedup_stats = crate::DeduplicationStats {
             total_chunks: 0,
             original_size: 0,
@@ -393,7 +950,7 @@ impl StorageManager {
 
         // 批量写入优化：分两阶段处理
         let mut new_chunk_refs = Vec::new();
-        let mut existing_chunk_ids = Vec::new();
+        let mut existing_chunk_refs = Vec::new();
 
         // 流式读取并分块（固定大小分块，保证内存恒定）
         loop {
@@ -403,6 +960,7 @@ impl StorageManager {
                 match reader.read(&mut buffer[total_read..]).await {
                     Ok(0) => break, // EOF
                     Ok(n) => total_read += n,
+                    // 读取失败（如归档条目校验不通过）时中止写入，不登记任何引用
                     Err(e) => return Err(StorageError::Io(e)),
                 }
             }
@@ -413,17 +971,28 @@ impl StorageManager {
 
             let chunk_data = &buffer[..total_read];
             file_size += total_read as u64;
-
-            // 计算块哈希
-            let chunk_id = self.calculate_hash(chunk_data);
+            // 超限时中止写入，不创建文件也不登记引用。本次新写入的块不在此删除：
+            // 并发写入可能已复用同一块而尚未登记引用，残留的块由孤儿块清理回收
+            check_upload_size(file_id, file_size, self.config.max_upload_size)?;
+            file_hasher.update(chunk_data);
+            check_quota(file_id, file_size, quota_remaining)?;
+
+            // 计算块哈希（命名空间配置了盐值时块 ID 加盐）
+            let strong_hash = self.calculate_hash(chunk_data);
+            let chunk_id = match chunk_salt {
+                Some(salt) => salted_chunk_id(salt, chunk_data),
+                None => strong_hash.clone(),
+            };
             let weak_hash = 0u32; // 固定大小分块不需要弱哈希
 
             // 去重检查 + 写入
-            let (written, compression_algo) = self.save_chunk_data(&chunk_id, chunk_data).await?;
+            let (written, compression_algo) = self
+                .save_chunk_data(&self.compressor, &chunk_id, weak_hash, chunk_data)
+                .await?;
 
             if written {
                 // 块是新写入的
-                let chunk_path = self.get_chunk_path(&chunk_id);
+                let chunk_path = self.chunk_store.location(&chunk_id);
                 new_chunk_refs.push((
                     chunk_id.clone(),
                     ChunkRefCount {
@@ -431,14 +1000,24 @@ impl StorageManager {
                         ref_count: 1,
                         size: total_read as u64,
                         path: chunk_path,
+                        weak_hash,
                     },
                 ));
 
                 dedup_stats.new_chunks += 1;
                 dedup_stats.stored_size += total_read as u64;
             } else {
-                // 块已存在
-                existing_chunk_ids.push(chunk_id.clone());
+                // 块已存在（可能刚由并发写入者写入、尚未登记引用）
+                existing_chunk_refs.push((
+                    chunk_id.clone(),
+                    ChunkRefCount {
+                        chunk_id: chunk_id.clone(),
+                        ref_count: 1,
+                        size: total_read as u64,
+                        path: self.chunk_store.location(&chunk_id),
+                        weak_hash,
+                    },
+                ));
                 dedup_stats.duplicate_chunks += 1;
             }
 
@@ -448,7 +1027,7 @@ impl StorageManager {
                 offset,
                 size: total_read,
                 weak_hash,
-                strong_hash: chunk_id,
+                strong_hash,
                 compression: compression_algo,
             });
 
@@ -459,18 +1038,23 @@ impl StorageManager {
         dedup_stats.original_size = file_size;
         dedup_stats.calculate_dedup_ratio();
 
+        // 块已全部写入，登记引用与提交版本期间持有文件锁；
+        // 在此之前失败时新写入的块尚无引用，由孤儿块清理回收
+        let _guard = self.file_lock(file_id).lock().await;
+        check_quota(file_id, file_size, self.quota_remaining(file_id)?)?;
+
         // 批量写入元数据到 Sled
         let metadata_db = self.get_metadata_db()?;
 
         if !new_chunk_refs.is_empty() {
             metadata_db
-                .put_chunk_refs_batch(&new_chunk_refs)
+                .add_chunk_refs_batch(&new_chunk_refs)
                 .map_err(|e| StorageError::Storage(format!("批量保存块引用计数失败: {}", e)))?;
         }
 
-        if !existing_chunk_ids.is_empty() {
+        if !existing_chunk_refs.is_empty() {
             metadata_db
-                .increment_chunk_refs_batch(&existing_chunk_ids)
+                .add_chunk_refs_batch(&existing_chunk_refs)
                 .map_err(|e| StorageError::Storage(format!("批量增加块引用计数失败: {}", e)))?;
         }
 
@@ -483,8 +1067,9 @@ impl StorageManager {
             dedup_stats.dedup_ratio
         );
 
-        // 计算文件哈希（使用SHA256）
-        let file_hash = format!("{:x}", md5::compute(&file_size.to_le_bytes())); // 简化哈希，因为没有完整数据
+        // 计算文件哈希（使用SHA256，读取过程中增量计算）
+        let file_hash = hex::encode(file_hasher.finalize());
+        let version_id = self.new_version_id(file_id, parent_version_id, &file_hash)?;
 
         // 创建 Delta
         let delta = FileDelta {
@@ -509,22 +1094,39 @@ impl StorageManager {
         };
 
         // 更新文件索引
-        let mut file_entry = metadata_db
+        // 回收站中的别名被新文件取代
+        let existing_entry = metadata_db
             .get_file_index(file_id)
             .map_err(|e| StorageError::Storage(format!("读取文件索引失败: {}", e)))?
-            .unwrap_or_else(|| FileIndexEntry {
-                file_id: file_id.to_string(),
-                latest_version_id: version_id.clone(),
-                version_count: 0,
-                created_at: now,
-                modified_at: now,
-                is_deleted: false,
-                deleted_at: None,
-                storage_mode: crate::StorageMode::Chunked,
-                optimization_status: crate::OptimizationStatus::Completed,
-                file_size,
-                file_hash: file_hash.clone(),
-            });
+            .filter(|entry| entry.alias_target.is_none());
+        let previous_version_id = existing_entry.as_ref().map(|e| e.latest_version_id.clone());
+        let op = if existing_entry.is_some() {
+            MutationOp::Updated
+        } else {
+            MutationOp::Created
+        };
+        let mut file_entry = existing_entry.unwrap_or_else(|| FileIndexEntry {
+            file_id: file_id.to_string(),
+            latest_version_id: version_id.clone(),
+            version_count: 0,
+            created_at: now,
+            modified_at: now,
+            is_deleted: false,
+            deleted_at: None,
+            storage_mode: crate::StorageMode::Chunked,
+            optimization_status: crate::OptimizationStatus::Completed,
+            file_size,
+            file_hash: file_hash.clone(),
+            user_metadata: HashMap::new(),
+            optimization_strategy: None,
+            optimized_at: None,
+            space_saved: 0,
+            stored_size: 0,
+            attributed_size: 0,
+            alias_target: None,
+            compression: None,
+            retention: None,
+        });
 
         file_entry.latest_version_id = version_id.clone();
         file_entry.version_count += 1;
@@ -533,6 +1135,7 @@ impl StorageManager {
         file_entry.optimization_status = crate::OptimizationStatus::Completed;
         file_entry.file_size = file_size;
         file_entry.file_hash = file_hash.clone();
+        file_entry.clear_optimization_stats();
 
         metadata_db
             .put_file_index(file_id, &file_entry)
@@ -543,36 +1146,575 @@ impl StorageManager {
         let _version_info = self
             .save_version_info(file_id, &delta, parent_version_id)
             .await?;
+        if let Some(previous) = previous_version_id {
+            self.clear_current_flag(&previous).await?;
+        }
+        if parent_version_id.is_some()
+            && let Err(e) = self.maybe_compact_chain(file_id).await
+        {
+            warn!("版本链压缩失败: {} - {}", file_id, e);
+        }
+        self.notify_mutation(file_id, op).await;
 
         Ok((delta, file_version))
     }
 
     /// 保存文件版本（使用增量存储）
+    ///
+    /// 内容与当前版本完全相同（整文件 SHA-256 一致）时不创建新版本，
+    /// 只更新修改时间并返回当前版本。
     pub async fn save_version(
         &self,
         file_id: &str,
         data: &[u8],
         parent_version_id: Option<&str>,
     ) -> Result<(FileDelta, FileVersion)> {
-        let version_id = format!("v_{}", scru128::new());
-        let now = Local::now().naive_local();
-
-        // 1. 计算文件哈希
-        let file_hash = self.calculate_hash(data);
+        self.save_version_with_meta(file_id, data, parent_version_id, None, None)
+            .await
+    }
 
-        // 2. CDC 分块
-        let mut generator =
-            crate::core::delta::DeltaGenerator::new(self.chunk_size, self.config.clone());
-        let delta_result = generator
-            .generate_full_delta(data, file_id)
-            .map_err(|e| StorageError::Storage(format!("生成分块失败: {}", e)))?;
+    /// 保存文件版本并记录创建者与版本说明
+    ///
+    /// 与 [`save_version`](Self::save_version) 相同；内容与当前版本相同而未创建新版本时，
+    /// 保留当前版本原有的创建者与说明。
+    pub async fn save_version_with_meta(
+        &self,
+        file_id: &str,
+        data: &[u8],
+        parent_version_id: Option<&str>,
+        author: Option<&str>,
+        comment: Option<&str>,
+    ) -> Result<(FileDelta, FileVersion)> {
+        self.ensure_writable("保存版本")?;
+        let target = self.resolve_write_target(file_id)?;
+        let file_id = target.as_str();
+        let _guard = self.file_lock(file_id).lock().await;
+        self.save_version_locked(file_id, data, parent_version_id, author, comment)
+            .await
+    }
 
-        // 3. 对每个块执行去重检查 + 写入（去重功能始终启用）
-        let mut dedup_stats = crate::DeduplicationStats {
-            total_chunks: delta_result.chunks.len(),
-            original_size: data.len() as u64,
-            ..Default::default()
-        };
+    /// [`save_version_with_meta`](Self::save_version_with_meta) 的实现，调用方已持有文件锁
+    async fn save_version_locked(
+        &self,
+        file_id: &str,
+        data: &[u8],
+        parent_version_id: Option<&str>,
+        author: Option<&str>,
+        comment: Option<&str>,
+    ) -> Result<(FileDelta, FileVersion)> {
+        check_upload_size(file_id, data.len() as u64, self.config.max_upload_size)?;
+        self.apply_backpressure().await?;
+        if let Some(current) = self.reuse_identical_version(file_id, data).await? {
+            return Ok(current);
+        }
+        let (delta, mut file_version) = self
+            .create_version(file_id, data, parent_version_id)
+            .await?;
+        if author.is_some() || comment.is_some() {
+            self.set_version_meta(&file_version.version_id, author, comment)
+                .await?;
+            file_version.author = author.map(str::to_string);
+            file_version.comment = comment.map(str::to_string);
+        }
+        Ok((delta, file_version))
+    }
+
+    /// 记录版本的创建者与说明
+    async fn set_version_meta(
+        &self,
+        version_id: &str,
+        author: Option<&str>,
+        comment: Option<&str>,
+    ) -> Result<()> {
+        let metadata_db = self.get_metadata_db()?;
+        let mut version_info = self.load_version_info(version_id)?;
+        version_info.author = author.map(str::to_string);
+        version_info.comment = comment.map(str::to_string);
+        metadata_db
+            .put_version_info(version_id, &version_info)
+            .map_err(|e| StorageError::Storage(format!("保存版本信息到 Sled 失败: {}", e)))?;
+        self.version_cache
+            .insert(version_id.to_string(), version_info)
+            .await;
+        Ok(())
+    }
+
+    /// 以指定存储形式保存文件版本
+    ///
+    /// 跳过 [`OptimizationStrategy::decide`](crate::OptimizationStrategy::decide)，
+    /// 由调用方强制选择：`Chunked` 立即 CDC 分块去重，`Compressed` 整文件压缩后
+    /// 单独存放（适合不会去重的归档类文件）。已弃用的 `Hot` / `Cold` 模式返回错误。
+    pub async fn save_version_with_mode(
+        &self,
+        file_id: &str,
+        data: &[u8],
+        parent_version_id: Option<&str>,
+        mode: crate::StorageMode,
+    ) -> Result<(FileDelta, FileVersion)> {
+        self.ensure_writable("保存版本")?;
+        #[allow(deprecated)]
+        if matches!(mode, crate::StorageMode::Hot | crate::StorageMode::Cold) {
+            return Err(StorageError::Config(format!(
+                "不支持以 {:?} 模式保存版本，仅支持 Chunked 或 Compressed",
+                mode
+            )));
+        }
+        let target = self.resolve_write_target(file_id)?;
+        let file_id = target.as_str();
+        check_upload_size(file_id, data.len() as u64, self.config.max_upload_size)?;
+        self.apply_backpressure().await?;
+        let _guard = self.file_lock(file_id).lock().await;
+
+        // 内容相同但存储形式不同时仍创建新版本，按要求的形式重新存放
+        let current_mode = self
+            .get_metadata_db()?
+            .get_file_index(file_id)?
+            .filter(|entry| !entry.is_deleted)
+            .map(|entry| entry.storage_mode);
+        if current_mode == Some(mode)
+            && let Some(current) = self.reuse_identical_version(file_id, data).await?
+        {
+            return Ok(current);
+        }
+
+        match mode {
+            crate::StorageMode::Compressed => {
+                self.create_compressed_version(file_id, data, parent_version_id)
+                    .await
+            }
+            _ => self.create_version(file_id, data, parent_version_id).await,
+        }
+    }
+
+    /// 条件保存文件版本（比较并交换）
+    ///
+    /// 仅当文件当前版本等于 `expected_current_version` 时提交，`None` 表示要求文件不存在
+    /// （回收站中的文件视为不存在）；条件不满足时返回 `StorageError::PreconditionFailed`。
+    /// 检查与提交期间持有文件锁，与同一文件的其他保存（包括不带条件的
+    /// [`save_version`](Self::save_version)）相互串行，基于同一版本的并发条件保存只有一个成功。
+    pub async fn save_version_if(
+        &self,
+        file_id: &str,
+        data: &[u8],
+        expected_current_version: Option<&str>,
+    ) -> Result<(FileDelta, FileVersion)> {
+        self.ensure_writable("保存版本")?;
+        let target = self.resolve_write_target(file_id)?;
+        let file_id = target.as_str();
+        let _guard = self.file_lock(file_id).lock().await;
+
+        let current = self
+            .get_metadata_db()?
+            .get_file_index(file_id)?
+            .filter(|entry| !entry.is_deleted)
+            .map(|entry| entry.latest_version_id);
+        if current.as_deref() != expected_current_version {
+            return Err(StorageError::PreconditionFailed(format!(
+                "文件 {} 当前版本为 {}，期望 {}",
+                file_id,
+                current.as_deref().unwrap_or("<不存在>"),
+                expected_current_version.unwrap_or("<不存在>")
+            )));
+        }
+
+        self.save_version_locked(file_id, data, current.as_deref(), None, None)
+            .await
+    }
+
+    /// 秒传：只凭整文件 SHA-256 与大小保存文件版本，不传输文件内容
+    ///
+    /// 在 [`IncrementalConfig::instant_upload`] 允许的范围内查找内容相同的分块文件，
+    /// 找到时新版本直接引用其块并登记引用计数，返回 `Ok(Some(..))`；内容与当前版本相同时
+    /// 与 [`save_version`](Self::save_version) 一样不创建新版本。秒传已禁用、没有匹配的文件
+    /// 或匹配的文件不是完整分块存储时返回 `Ok(None)`，调用方应改为正常上传。
+    pub async fn save_version_by_hash(
+        &self,
+        file_id: &str,
+        file_hash: &str,
+        file_size: u64,
+        parent_version_id: Option<&str>,
+    ) -> Result<Option<(FileDelta, FileVersion)>> {
+        self.ensure_writable("保存版本")?;
+        if self.config.instant_upload == crate::InstantUploadScope::Disabled || file_size == 0 {
+            return Ok(None);
+        }
+        let target = self.resolve_write_target(file_id)?;
+        let file_id = target.as_str();
+        let file_hash = file_hash.to_ascii_lowercase();
+        check_upload_size(file_id, file_size, self.config.max_upload_size)?;
+        self.apply_backpressure().await?;
+        let _guard = self.file_lock(file_id).lock().await;
+        if let Some(current) = self
+            .reuse_identical_content(file_id, file_size, || file_hash.clone())
+            .await?
+        {
+            return Ok(Some(current));
+        }
+
+        let Some(chunks) = self
+            .find_content_chunks(file_id, &file_hash, file_size)
+            .await?
+        else {
+            return Ok(None);
+        };
+        info!(
+            "文件 {} 秒传命中，引用已有的 {} 个块",
+            file_id,
+            chunks.len()
+        );
+        self.commit_shared_chunks(file_id, chunks, file_size, file_hash, parent_version_id)
+            .await
+    }
+
+    /// 以已有的块提交新版本，不读写块数据（秒传与服务端复制共用）
+    ///
+    /// 登记块引用后确认块仍然存在，块已被回收时撤销登记并返回 `Ok(None)`。
+    async fn commit_shared_chunks(
+        &self,
+        file_id: &str,
+        chunks: Vec<ChunkInfo>,
+        file_size: u64,
+        file_hash: String,
+        parent_version_id: Option<&str>,
+    ) -> Result<Option<(FileDelta, FileVersion)>> {
+        check_quota(file_id, file_size, self.quota_remaining(file_id)?)?;
+
+        // 与正常保存一样，块在文件中每出现一次登记一次引用
+        let chunk_refs: Vec<(String, ChunkRefCount)> = chunks
+            .iter()
+            .map(|chunk| {
+                (
+                    chunk.chunk_id.clone(),
+                    ChunkRefCount {
+                        chunk_id: chunk.chunk_id.clone(),
+                        ref_count: 1,
+                        size: chunk.size as u64,
+                        path: self.chunk_store.location(&chunk.chunk_id),
+                        weak_hash: chunk.weak_hash,
+                    },
+                )
+            })
+            .collect();
+        let metadata_db = self.get_metadata_db()?;
+        metadata_db
+            .add_chunk_refs_batch(&chunk_refs)
+            .map_err(|e| StorageError::Storage(format!("批量增加块引用计数失败: {}", e)))?;
+
+        // 登记引用后确认块仍然存在：来源文件可能在查找之后被删除，其块已被回收
+        for chunk in &chunks {
+            if !self.chunk_store.exists(&chunk.chunk_id).await? {
+                let chunk_ids: Vec<String> = chunk_refs.into_iter().map(|(id, _)| id).collect();
+                metadata_db.decrement_chunk_refs_batch(&chunk_ids)?;
+                return Ok(None);
+            }
+        }
+
+        let version_id = self.new_version_id(file_id, parent_version_id, &file_hash)?;
+        self.commit_chunked_version(
+            file_id,
+            version_id,
+            chunks,
+            file_size,
+            file_hash,
+            parent_version_id,
+        )
+        .await
+        .map(Some)
+    }
+
+    /// 按 [`IncrementalConfig::instant_upload`] 的查找范围，`candidate` 的内容能否供 `file_id` 秒传
+    ///
+    /// 秒传禁用时总是 `false`；块 ID 的加盐方式不同时无法共享块，同样不可见。
+    fn instant_upload_visible(&self, file_id: &str, candidate: &str) -> bool {
+        match self.config.instant_upload {
+            crate::InstantUploadScope::Disabled => return false,
+            crate::InstantUploadScope::Namespace
+                if crate::namespace::namespace_of(candidate)
+                    != crate::namespace::namespace_of(file_id) =>
+            {
+                return false;
+            }
+            _ => {}
+        }
+        self.chunk_salt(candidate) == self.chunk_salt(file_id)
+    }
+
+    /// 在秒传范围内查找当前内容为 `file_hash` 的完整分块版本，返回其块列表
+    ///
+    /// 内容哈希索引中已失效的条目（文件已移除或内容已变化）顺带清理。
+    async fn find_content_chunks(
+        &self,
+        file_id: &str,
+        file_hash: &str,
+        file_size: u64,
+    ) -> Result<Option<Vec<ChunkInfo>>> {
+        let metadata_db = self.get_metadata_db()?;
+
+        for candidate in metadata_db.files_with_content_hash(file_hash)? {
+            let Some(entry) = metadata_db
+                .get_file_index(&candidate)?
+                .filter(|entry| entry.file_hash == file_hash)
+            else {
+                metadata_db.remove_content_hash(file_hash, &candidate)?;
+                continue;
+            };
+            if !self.instant_upload_visible(file_id, &candidate) {
+                continue;
+            }
+            #[allow(deprecated)]
+            if entry.is_deleted
+                || entry.file_size != file_size
+                || !matches!(
+                    entry.storage_mode,
+                    crate::StorageMode::Chunked | crate::StorageMode::Cold
+                )
+            {
+                continue;
+            }
+            let Ok(delta) = self.read_delta(&candidate, &entry.latest_version_id).await else {
+                continue;
+            };
+            if !delta.chunks.is_empty() && delta.is_contiguous(file_size as usize) {
+                return Ok(Some(delta.chunks));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 服务端复制文件
+    ///
+    /// 源文件为分块存储时，目标文件的新版本直接引用源文件当前版本的块并登记引用计数，
+    /// 不读写块数据，跨命名空间同样适用；源文件为整文件压缩存储或两者的块加盐方式不同时
+    /// 无法共享块，退化为读取后重新保存。目标已存在时作为其新版本，内容相同时
+    /// 与 [`save_version`](Self::save_version) 一样不创建新版本。用户元数据不随复制。
+    pub async fn copy_file(
+        &self,
+        source_file_id: &str,
+        dest_file_id: &str,
+    ) -> Result<FileMetadata> {
+        self.ensure_writable("复制文件")?;
+        self.ensure_global_id(source_file_id)?;
+        let source = self
+            .get_metadata_db()?
+            .get_file_index(source_file_id)?
+            .filter(|entry| !entry.is_deleted)
+            .ok_or_else(|| StorageError::FileNotFound(source_file_id.to_string()))?;
+        let source = self.resolve_alias(source)?;
+        let target = self.resolve_write_target(dest_file_id)?;
+        let dest_file_id = target.as_str();
+
+        let file_size = source.file_size;
+        let file_hash = if source.file_hash.is_empty() {
+            self.content_hash(&source.file_id).await?
+        } else {
+            source.file_hash.clone()
+        };
+        check_upload_size(dest_file_id, file_size, self.config.max_upload_size)?;
+        self.apply_backpressure().await?;
+        let _guard = self.file_lock(dest_file_id).lock().await;
+
+        #[allow(deprecated)]
+        let shareable = matches!(
+            source.storage_mode,
+            crate::StorageMode::Chunked | crate::StorageMode::Cold
+        ) && self.chunk_salt(&source.file_id) == self.chunk_salt(dest_file_id);
+        let (_delta, file_version) = match self
+            .reuse_identical_content(dest_file_id, file_size, || file_hash.clone())
+            .await?
+        {
+            Some(current) => current,
+            None => {
+                let shared = match self
+                    .read_delta(&source.file_id, &source.latest_version_id)
+                    .await
+                {
+                    Ok(delta)
+                        if shareable
+                            && file_size > 0
+                            && !delta.chunks.is_empty()
+                            && delta.is_contiguous(file_size as usize) =>
+                    {
+                        self.commit_shared_chunks(
+                            dest_file_id,
+                            delta.chunks,
+                            file_size,
+                            file_hash,
+                            None,
+                        )
+                        .await?
+                    }
+                    _ => None,
+                };
+                match shared {
+                    Some(shared) => {
+                        info!(
+                            "复制文件 {} -> {}：共享源文件的块",
+                            source.file_id, dest_file_id
+                        );
+                        shared
+                    }
+                    None => {
+                        let data = self.read_version_data(&source.latest_version_id).await?;
+                        self.save_version_locked(dest_file_id, &data, None, None, None)
+                            .await?
+                    }
+                }
+            }
+        };
+
+        Ok(FileMetadata {
+            id: dest_file_id.to_string(),
+            name: dest_file_id.to_string(),
+            path: dest_file_id.to_string(),
+            size: file_version.size,
+            hash: file_version.version_id.clone(),
+            created_at: file_version.created_at,
+            modified_at: file_version.created_at,
+            user_metadata: Default::default(),
+        })
+    }
+
+    /// 文件对应的保存锁
+    ///
+    /// 所有提交新版本的路径都先取得该锁，条件保存的检查与提交因此不会与
+    /// 同一文件的其他保存交错。锁不可重入，持有者应调用不再加锁的内部实现。
+    pub(crate) fn file_lock(&self, file_id: &str) -> &tokio::sync::Mutex<()> {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        file_id.hash(&mut hasher);
+        &self.file_locks[hasher.finish() as usize % self.file_locks.len()]
+    }
+
+    /// 块对应的写入锁
+    fn chunk_write_lock(&self, chunk_id: &str) -> &tokio::sync::Mutex<()> {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        chunk_id.hash(&mut hasher);
+        &self.chunk_write_locks[hasher.finish() as usize % self.chunk_write_locks.len()]
+    }
+
+    /// 上传背压：优化队列积压时延迟或拒绝保存
+    ///
+    /// 队列长度达到 `optimization_queue_hard_cap` 时返回 `StorageError::Busy`，
+    /// 超过 `optimization_queue_high_water` 时等待 `backpressure_delay_ms` 后继续。
+    async fn apply_backpressure(&self) -> Result<()> {
+        let high_water = self.config.optimization_queue_high_water;
+        let hard_cap = self.config.optimization_queue_hard_cap;
+        if high_water == 0 && hard_cap == 0 {
+            return Ok(());
+        }
+
+        let queue_len = self.get_optimization_queue_length().await;
+        if hard_cap > 0 && queue_len >= hard_cap {
+            return Err(StorageError::Busy(format!(
+                "优化队列积压 {} 个任务，已达上限 {}",
+                queue_len, hard_cap
+            )));
+        }
+        if high_water > 0 && queue_len > high_water {
+            warn!(
+                "优化队列积压 {} 个任务，超过阈值 {}，延迟 {}ms 接受保存",
+                queue_len, high_water, self.config.backpressure_delay_ms
+            );
+            tokio::time::sleep(Duration::from_millis(self.config.backpressure_delay_ms)).await;
+        }
+        Ok(())
+    }
+
+    /// 内容与当前版本完全相同时更新修改时间并返回当前版本
+    ///
+    /// 只比较整文件哈希，大小不同时不计算哈希。已删除的文件总是创建新版本。
+    async fn reuse_identical_version(
+        &self,
+        file_id: &str,
+        data: &[u8],
+    ) -> Result<Option<(FileDelta, FileVersion)>> {
+        self.reuse_identical_content(file_id, data.len() as u64, || self.calculate_hash(data))
+            .await
+    }
+
+    /// 按大小与整文件哈希判断内容是否与当前版本相同，相同时更新修改时间并返回当前版本
+    ///
+    /// `file_hash` 仅在大小一致时才调用。
+    async fn reuse_identical_content(
+        &self,
+        file_id: &str,
+        file_size: u64,
+        file_hash: impl FnOnce() -> String,
+    ) -> Result<Option<(FileDelta, FileVersion)>> {
+        let metadata_db = self.get_metadata_db()?;
+        let Some(mut file_entry) = metadata_db
+            .get_file_index(file_id)
+            .map_err(|e| StorageError::Storage(format!("读取文件索引失败: {}", e)))?
+        else {
+            return Ok(None);
+        };
+        if file_entry.is_deleted || file_entry.file_size != file_size {
+            return Ok(None);
+        }
+        let file_hash = file_hash();
+        if file_entry.file_hash != file_hash {
+            return Ok(None);
+        }
+
+        let version_info = self.get_version_info(&file_entry.latest_version_id).await?;
+        let delta = self.read_delta(file_id, &version_info.version_id).await?;
+
+        file_entry.modified_at = Local::now().naive_local();
+        metadata_db
+            .put_file_index(file_id, &file_entry)
+            .map_err(|e| StorageError::Storage(format!("保存文件索引失败: {}", e)))?;
+        self.notify_mutation(file_id, MutationOp::Updated).await;
+
+        info!(
+            "文件 {} 内容与当前版本 {} 相同，跳过创建新版本",
+            file_id, version_info.version_id
+        );
+
+        let file_version = FileVersion {
+            version_id: version_info.version_id.clone(),
+            file_id: file_id.to_string(),
+            name: file_id.to_string(),
+            size: version_info.file_size,
+            hash: file_hash,
+            created_at: version_info.created_at,
+            author: version_info.author,
+            comment: version_info.comment,
+            is_current: true,
+        };
+        Ok(Some((delta, file_version)))
+    }
+
+    /// 创建新版本（不检查内容是否与当前版本相同）
+    ///
+    /// 快照导入等需要逐一保留历史版本的场景使用。
+    pub(crate) async fn create_version(
+        &self,
+        file_id: &str,
+        data: &[u8],
+        parent_version_id: Option<&str>,
+    ) -> Result<(FileDelta, FileVersion)> {
+        self.ensure_writable("保存版本")?;
+        check_quota(file_id, data.len() as u64, self.quota_remaining(file_id)?)?;
+
+        // 1. 计算文件哈希
+        let file_hash = self.calculate_hash(data);
+        let version_id = self.new_version_id(file_id, parent_version_id, &file_hash)?;
+
+        // 2. CDC 分块
+        let mut generator =
+            crate::core::delta::DeltaGenerator::new(self.chunk_size, self.config.clone());
+        let mut delta_result = generator
+            .generate_full_delta(data, file_id)
+            .map_err(|e| StorageError::Storage(format!("生成分块失败: {}", e)))?;
+        self.apply_chunk_salt(file_id, data, &mut delta_result.chunks);
+
+        // 3. 对每个块执行去重检查 + 写入（去重功能始终启用）
+        let mut dedup_stats = crate::DeduplicationStats {
+            total_chunks: delta_result.chunks.len(),
+            original_size: data.len() as u64,
+            ..Default::default()
+        };
 
         let mut updated_chunks = Vec::with_capacity(delta_result.chunks.len());
         let metadata_db = self.get_metadata_db()?;
@@ -580,7 +1722,7 @@ impl StorageManager {
         // 批量写入优化：分两阶段处理
         // 阶段1：收集新块和已存在块的信息
         let mut new_chunk_refs = Vec::new();
-        let mut existing_chunk_ids = Vec::new();
+        let mut existing_chunk_refs = Vec::new();
 
         for chunk in &delta_result.chunks {
             let start = chunk.offset;
@@ -592,12 +1734,17 @@ impl StorageManager {
 
             // 统一策略：尝试写入块（基于文件系统去重）
             let (written, compression_algo) = self
-                .save_chunk_data(&chunk.chunk_id, chunk_data)
+                .save_chunk_data(
+                    &self.compressor,
+                    &chunk.chunk_id,
+                    chunk.weak_hash,
+                    chunk_data,
+                )
                 .await?;
 
             if written {
                 // 块是新写入的，收集引用计数信息
-                let chunk_path = self.get_chunk_path(&chunk.chunk_id);
+                let chunk_path = self.chunk_store.location(&chunk.chunk_id);
                 new_chunk_refs.push((
                     chunk.chunk_id.clone(),
                     ChunkRefCount {
@@ -605,14 +1752,24 @@ impl StorageManager {
                         ref_count: 1,
                         size: chunk.size as u64,
                         path: chunk_path,
+                        weak_hash: chunk.weak_hash,
                     },
                 ));
 
                 dedup_stats.new_chunks += 1;
                 dedup_stats.stored_size += chunk.size as u64;
             } else {
-                // 块已存在，收集块ID用于批量增加引用计数
-                existing_chunk_ids.push(chunk.chunk_id.clone());
+                // 块已存在（可能刚由并发写入者写入、尚未登记引用），收集后批量登记引用
+                existing_chunk_refs.push((
+                    chunk.chunk_id.clone(),
+                    ChunkRefCount {
+                        chunk_id: chunk.chunk_id.clone(),
+                        ref_count: 1,
+                        size: chunk.size as u64,
+                        path: self.chunk_store.location(&chunk.chunk_id),
+                        weak_hash: chunk.weak_hash,
+                    },
+                ));
                 dedup_stats.duplicate_chunks += 1;
             }
 
@@ -625,13 +1782,13 @@ impl StorageManager {
         // 阶段2：批量写入元数据到 Sled（减少 I/O 和事务开销）
         if !new_chunk_refs.is_empty() {
             metadata_db
-                .put_chunk_refs_batch(&new_chunk_refs)
+                .add_chunk_refs_batch(&new_chunk_refs)
                 .map_err(|e| StorageError::Storage(format!("批量保存块引用计数失败: {}", e)))?;
         }
 
-        if !existing_chunk_ids.is_empty() {
+        if !existing_chunk_refs.is_empty() {
             metadata_db
-                .increment_chunk_refs_batch(&existing_chunk_ids)
+                .add_chunk_refs_batch(&existing_chunk_refs)
                 .map_err(|e| StorageError::Storage(format!("批量增加块引用计数失败: {}", e)))?;
         }
 
@@ -646,21 +1803,51 @@ impl StorageManager {
             dedup_stats.dedup_ratio
         );
 
-        // 4. 创建 Delta（包含块列表）
+        self.commit_chunked_version(
+            file_id,
+            version_id,
+            updated_chunks,
+            data.len() as u64,
+            file_hash,
+            parent_version_id,
+        )
+        .await
+    }
+
+    /// 提交分块版本：更新文件索引，保存 delta 与版本信息
+    ///
+    /// 块数据须已写入且引用计数已登记，`chunks` 按偏移连续覆盖整个文件。
+    async fn commit_chunked_version(
+        &self,
+        file_id: &str,
+        version_id: String,
+        chunks: Vec<ChunkInfo>,
+        file_size: u64,
+        file_hash: String,
+        parent_version_id: Option<&str>,
+    ) -> Result<(FileDelta, FileVersion)> {
+        let now = Local::now().naive_local();
+
+        // 创建 Delta（包含块列表）
         let delta = FileDelta {
             file_id: file_id.to_string(),
             base_version_id: parent_version_id.unwrap_or("").to_string(),
             new_version_id: version_id.clone(),
-            chunks: updated_chunks,
+            chunks,
             created_at: now,
         };
+        // 写入块后仍须保持按偏移连续的块顺序
+        debug_assert!(
+            delta.is_contiguous(file_size as usize),
+            "块列表必须按偏移连续覆盖整个文件"
+        );
 
-        // 5. 创建文件版本信息
+        // 创建文件版本信息
         let file_version = FileVersion {
             version_id: version_id.clone(),
             file_id: file_id.to_string(),
             name: file_id.to_string(),
-            size: data.len() as u64,
+            size: file_size,
             hash: file_hash.clone(),
             created_at: now,
             author: None,
@@ -668,53 +1855,269 @@ impl StorageManager {
             is_current: true,
         };
 
-        // 6. 更新文件索引（Chunked模式，已完成优化）
+        // 更新文件索引（Chunked模式，已完成优化）
         let metadata_db = self.get_metadata_db()?;
-        let mut file_entry = metadata_db
+        // 回收站中的别名被新文件取代
+        let existing_entry = metadata_db
             .get_file_index(file_id)
             .map_err(|e| StorageError::Storage(format!("读取文件索引失败: {}", e)))?
-            .unwrap_or_else(|| FileIndexEntry {
-                file_id: file_id.to_string(),
-                latest_version_id: version_id.clone(),
-                version_count: 0,
-                created_at: now,
-                modified_at: now,
-                is_deleted: false,
-                deleted_at: None,
-                storage_mode: crate::StorageMode::Chunked,
-                optimization_status: crate::OptimizationStatus::Completed,
-                file_size: data.len() as u64,
-                file_hash: file_hash.clone(),
-            });
+            .filter(|entry| entry.alias_target.is_none());
+        let previous_version_id = existing_entry.as_ref().map(|e| e.latest_version_id.clone());
+        let op = if existing_entry.is_some() {
+            MutationOp::Updated
+        } else {
+            MutationOp::Created
+        };
+        let mut file_entry = existing_entry.unwrap_or_else(|| FileIndexEntry {
+            file_id: file_id.to_string(),
+            latest_version_id: version_id.clone(),
+            version_count: 0,
+            created_at: now,
+            modified_at: now,
+            is_deleted: false,
+            deleted_at: None,
+            storage_mode: crate::StorageMode::Chunked,
+            optimization_status: crate::OptimizationStatus::Completed,
+            file_size,
+            file_hash: file_hash.clone(),
+            user_metadata: HashMap::new(),
+            optimization_strategy: None,
+            optimized_at: None,
+            space_saved: 0,
+            stored_size: 0,
+            attributed_size: 0,
+            alias_target: None,
+            compression: None,
+            retention: None,
+        });
 
         file_entry.latest_version_id = version_id.clone();
         file_entry.version_count += 1;
         file_entry.modified_at = now;
         file_entry.storage_mode = crate::StorageMode::Chunked;
         file_entry.optimization_status = crate::OptimizationStatus::Completed;
-        file_entry.file_size = data.len() as u64;
+        file_entry.file_size = file_size;
         file_entry.file_hash = file_hash.clone();
+        file_entry.clear_optimization_stats();
 
         metadata_db
             .put_file_index(file_id, &file_entry)
             .map_err(|e| StorageError::Storage(format!("保存文件索引失败: {}", e)))?;
 
-        // 7. 保存 Delta 和版本信息
+        // 保存 Delta 和版本信息
         self.save_delta(file_id, &delta).await?;
         let _version_info = self
             .save_version_info(file_id, &delta, parent_version_id)
             .await?;
+        if let Some(previous) = previous_version_id {
+            self.clear_current_flag(&previous).await?;
+        }
+        if parent_version_id.is_some()
+            && let Err(e) = self.maybe_compact_chain(file_id).await
+        {
+            warn!("版本链压缩失败: {} - {}", file_id, e);
+        }
+        self.notify_mutation(file_id, op).await;
 
         Ok((delta, file_version))
     }
 
-    /// 读取版本数据
-    pub async fn read_version_data(&self, version_id: &str) -> Result<Vec<u8>> {
-        // 获取版本信息
-        let version_info = self.get_version_info(version_id).await?;
+    /// 以整文件压缩的形式创建新版本
+    ///
+    /// 压缩数据按版本单独存放（见 [`Self::get_compressed_version_path`]），
+    /// delta 不含任何块，读取时整体解压。
+    async fn create_compressed_version(
+        &self,
+        file_id: &str,
+        data: &[u8],
+        parent_version_id: Option<&str>,
+    ) -> Result<(FileDelta, FileVersion)> {
+        self.ensure_writable("保存版本")?;
+        check_quota(file_id, data.len() as u64, self.quota_remaining(file_id)?)?;
 
-        // 检查文件的存储模式
-        let metadata_db = self.get_metadata_db()?;
+        let now = Local::now().naive_local();
+        let file_hash = self.calculate_hash(data);
+        let version_id = self.new_version_id(file_id, parent_version_id, &file_hash)?;
+
+        // 1. 整文件压缩，先写临时文件再重命名
+        let (compressed, algorithm) = match self.whole_file_compression() {
+            Some((compressor, _)) => {
+                let result = compressor.compress(data)?;
+                (result.compressed_data, result.algorithm)
+            }
+            None => (data.to_vec(), crate::core::CompressionAlgorithm::None),
+        };
+        let compressed_path = self.get_compressed_version_path(&version_id);
+        if let Some(parent) = compressed_path.parent() {
+            fs::create_dir_all(parent).await.map_err(StorageError::Io)?;
+        }
+        let temp_path = compressed_path.with_extension("compressed.tmp");
+        fs::write(&temp_path, &compressed)
+            .await
+            .map_err(StorageError::Io)?;
+        fs::rename(&temp_path, &compressed_path)
+            .await
+            .map_err(StorageError::Io)?;
+
+        if self.config.verify_optimization {
+            let restored = self.decompress_chunk(compressed.clone(), algorithm)?;
+            if self.calculate_hash(&restored) != file_hash {
+                let _ = fs::remove_file(&compressed_path).await;
+                return Err(StorageError::ChecksumMismatch(format!(
+                    "文件 {} 压缩后无法还原原始内容",
+                    file_id
+                )));
+            }
+        }
+
+        let compressed_size = compressed.len() as u64;
+        let space_saved = (data.len() as u64).saturating_sub(compressed_size);
+
+        // 2. 更新文件索引（须先于版本信息写入，空块列表的版本大小取自索引）
+        let metadata_db = self.get_metadata_db()?;
+        // 回收站中的别名被新文件取代
+        let existing_entry = metadata_db
+            .get_file_index(file_id)
+            .map_err(|e| StorageError::Storage(format!("读取文件索引失败: {}", e)))?
+            .filter(|entry| entry.alias_target.is_none());
+        let previous_version_id = existing_entry.as_ref().map(|e| e.latest_version_id.clone());
+        let op = if existing_entry.is_some() {
+            MutationOp::Updated
+        } else {
+            MutationOp::Created
+        };
+        let mut file_entry = existing_entry.unwrap_or_else(|| FileIndexEntry {
+            file_id: file_id.to_string(),
+            latest_version_id: version_id.clone(),
+            version_count: 0,
+            created_at: now,
+            modified_at: now,
+            is_deleted: false,
+            deleted_at: None,
+            storage_mode: crate::StorageMode::Compressed,
+            optimization_status: crate::OptimizationStatus::Completed,
+            file_size: data.len() as u64,
+            file_hash: file_hash.clone(),
+            user_metadata: HashMap::new(),
+            optimization_strategy: None,
+            optimized_at: None,
+            space_saved: 0,
+            stored_size: 0,
+            attributed_size: 0,
+            alias_target: None,
+            compression: None,
+            retention: None,
+        });
+
+        file_entry.latest_version_id = version_id.clone();
+        file_entry.version_count += 1;
+        file_entry.modified_at = now;
+        file_entry.storage_mode = crate::StorageMode::Compressed;
+        file_entry.optimization_status = crate::OptimizationStatus::Completed;
+        file_entry.file_size = data.len() as u64;
+        file_entry.file_hash = file_hash.clone();
+        file_entry.clear_optimization_stats();
+        file_entry.optimization_strategy = Some(crate::OptimizationStrategy::CompressOnly);
+        file_entry.optimized_at = Some(now);
+        file_entry.stored_size = compressed_size;
+        file_entry.space_saved = space_saved;
+
+        metadata_db
+            .put_file_index(file_id, &file_entry)
+            .map_err(|e| StorageError::Storage(format!("保存文件索引失败: {}", e)))?;
+
+        // 3. 保存 Delta 和版本信息
+        let delta = FileDelta {
+            file_id: file_id.to_string(),
+            base_version_id: parent_version_id.unwrap_or("").to_string(),
+            new_version_id: version_id.clone(),
+            chunks: Vec::new(),
+            created_at: now,
+        };
+        self.save_delta(file_id, &delta).await?;
+        let mut version_info = self
+            .save_version_info(file_id, &delta, parent_version_id)
+            .await?;
+        version_info.storage_size = compressed_size;
+        // 记录实际使用的算法：不可压缩的数据原样保存，之后修改压缩配置也能正确读取
+        version_info.compression = Some(algorithm);
+        metadata_db
+            .put_version_info(&version_id, &version_info)
+            .map_err(|e| StorageError::Storage(format!("保存版本信息到 Sled 失败: {}", e)))?;
+        self.version_cache
+            .insert(version_id.clone(), version_info)
+            .await;
+        if let Some(previous) = previous_version_id {
+            self.clear_current_flag(&previous).await?;
+        }
+        self.notify_mutation(file_id, op).await;
+
+        info!(
+            "文件 {} 以压缩形式保存: 原始={}B, 压缩后={}B",
+            file_id,
+            data.len(),
+            compressed_size
+        );
+
+        let file_version = FileVersion {
+            version_id,
+            file_id: file_id.to_string(),
+            name: file_id.to_string(),
+            size: data.len() as u64,
+            hash: file_hash,
+            created_at: now,
+            author: None,
+            comment: None,
+            is_current: true,
+        };
+        Ok((delta, file_version))
+    }
+
+    /// 读取版本数据
+    ///
+    /// 下载校验发现过内容损坏的版本改走强一致路径（见 [`Self::report_corrupt_read`]）。
+    pub async fn read_version_data(&self, version_id: &str) -> Result<Vec<u8>> {
+        self.read_version_data_with(version_id, self.is_suspect_version(version_id))
+            .await
+    }
+
+    /// 强一致读取版本数据
+    ///
+    /// 绕过版本信息缓存，直接以 Sled 与磁盘上的权威状态重建数据，
+    /// 避免与并发修改（如移动、删除版本）交错时读到缓存中的过期条目。
+    /// 供校验、同步比对等对正确性敏感的调用方使用，普通读取仍走缓存。
+    pub async fn read_version_data_consistent(&self, version_id: &str) -> Result<Vec<u8>> {
+        self.read_version_data_with(version_id, true).await
+    }
+
+    /// 强一致读取文件当前版本的数据（见 [`Self::read_version_data_consistent`]）
+    pub async fn read_file_consistent(&self, file_id: &str) -> Result<Vec<u8>> {
+        let version_id = self.current_version_id(file_id).await?;
+        self.read_version_data_consistent(&version_id).await
+    }
+
+    async fn read_version_data_with(&self, version_id: &str, consistent: bool) -> Result<Vec<u8>> {
+        // 获取版本信息
+        let version_info = self.lookup_version_info(version_id, consistent).await?;
+
+        // 以整文件压缩形式保存的版本（见 `save_version_with_mode`）
+        let compressed_path = self.get_compressed_version_path(version_id);
+        if compressed_path.exists() {
+            let compressed_data = fs::read(&compressed_path).await.map_err(StorageError::Io)?;
+            // 按版本记录的算法解压，未记录算法的旧数据按当前配置推断
+            if let Some(algorithm) = version_info.compression {
+                return self.decompress_chunk(compressed_data, algorithm);
+            }
+            return match self.whole_file_compression() {
+                Some((compressor, algorithm)) => {
+                    Ok(compressor.decompress(&compressed_data, algorithm)?)
+                }
+                None => Ok(compressed_data),
+            };
+        }
+
+        // 检查文件的存储模式
+        let metadata_db = self.get_metadata_db()?;
         if let Some(file_entry) = metadata_db
             .get_file_index(&version_info.file_id)
             .map_err(|e| StorageError::Storage(format!("读取文件索引失败: {}", e)))?
@@ -745,27 +2148,18 @@ impl StorageManager {
                         let compressed_data =
                             fs::read(&compressed_path).await.map_err(StorageError::Io)?;
 
-                        // 解压数据
-                        if self.config.enable_compression {
-                            let algorithm = match self.config.compression_algorithm.as_str() {
-                                "lz4" => crate::core::CompressionAlgorithm::LZ4,
-                                "zstd" => crate::core::CompressionAlgorithm::Zstd,
-                                _ => crate::core::CompressionAlgorithm::LZ4,
-                            };
-                            let compression_config = crate::core::compression::CompressionConfig {
-                                algorithm,
-                                level: 1,
-                                min_size: 0,
-                                ..Default::default()
-                            };
-                            let compressor =
-                                crate::core::compression::Compressor::new(compression_config);
-                            let data = compressor.decompress(&compressed_data, algorithm)?;
-                            return Ok(data);
-                        } else {
-                            // 未启用压缩，直接返回
-                            return Ok(compressed_data);
+                        // 按记录的算法解压，旧数据按当前配置推断（未启用压缩时直接返回）
+                        if let Some(algorithm) = file_entry.compression {
+                            return self.decompress_chunk(compressed_data, algorithm);
                         }
+                        return match self.whole_file_compression() {
+                            Some((compressor, algorithm)) => {
+                                Ok(compressor.decompress(&compressed_data, algorithm)?)
+                            }
+                            None => Ok(compressed_data),
+                        };
+                    } else if version_info.chunk_count > 0 {
+                        // 文件改为压缩形式前保存的分块版本
                     } else {
                         return Err(StorageError::Storage(format!(
                             "压缩存储文件不存在: {}",
@@ -782,14 +2176,31 @@ impl StorageManager {
         let mut current_version_id = version_id.to_string();
 
         loop {
-            let version = self.get_version_info(&current_version_id).await?;
+            let version = self
+                .lookup_version_info(&current_version_id, consistent)
+                .await?;
             let delta = self
                 .read_delta(&version.file_id, &current_version_id)
                 .await?;
 
             // 读取并应用分块
             for chunk in &delta.chunks {
-                let chunk_data = self.read_chunk(&chunk.chunk_id, chunk.compression).await?;
+                let chunk_data = match self.read_chunk(&chunk.chunk_id, chunk.compression).await {
+                    Ok(data) => data,
+                    Err(e) => {
+                        if self
+                            .chunk_store
+                            .exists(&chunk.chunk_id)
+                            .await
+                            .unwrap_or(true)
+                        {
+                            return Err(e);
+                        }
+                        return self
+                            .read_hot_fallback(&version_info.file_id, version_id, &chunk.chunk_id)
+                            .await;
+                    }
+                };
 
                 // 确保result有足够的空间
                 let required_len = chunk.offset + chunk_data.len();
@@ -801,6 +2212,11 @@ impl StorageManager {
                 result[chunk.offset..chunk.offset + chunk_data.len()].copy_from_slice(&chunk_data);
             }
 
+            // 父版本的块写在新版本之后，完整快照若继续回溯，父版本会覆盖新内容
+            if delta.is_full_snapshot(version.file_size) {
+                break;
+            }
+
             // 如果有父版本，继续向上遍历
             if let Some(parent_id) = version.parent_version_id {
                 current_version_id = parent_id;
@@ -812,6 +2228,64 @@ impl StorageManager {
         Ok(result)
     }
 
+    /// 块缺失时的回退读取
+    ///
+    /// 优化中途崩溃可能留下文件索引已切换为分块存储、部分块却未写入的状态。
+    /// 读取的是当前版本且热存储文件仍在、内容与文件索引一致时直接返回热存储内容，
+    /// 并重新提交优化任务补写缺失的块；否则返回 `IncompleteOptimization`。
+    async fn read_hot_fallback(
+        &self,
+        file_id: &str,
+        version_id: &str,
+        missing_chunk: &str,
+    ) -> Result<Vec<u8>> {
+        let incomplete = || {
+            StorageError::IncompleteOptimization(format!(
+                "{}（文件 {}，版本 {}）",
+                missing_chunk, file_id, version_id
+            ))
+        };
+        let Some(entry) = self
+            .get_metadata_db()?
+            .get_file_index(file_id)?
+            .filter(|entry| entry.latest_version_id == version_id)
+        else {
+            return Err(incomplete());
+        };
+        let hot_path = self.get_hot_storage_path(file_id);
+        let Ok(data) = fs::read(&hot_path).await else {
+            return Err(incomplete());
+        };
+        if data.len() as u64 != entry.file_size
+            || (!entry.file_hash.is_empty() && self.calculate_hash(&data) != entry.file_hash)
+        {
+            warn!(
+                "文件 {} 的热存储副本与文件索引不一致，无法回退读取",
+                file_id
+            );
+            return Err(incomplete());
+        }
+
+        warn!(
+            "文件 {} 缺少块 {}，改为读取热存储副本",
+            file_id, missing_chunk
+        );
+        if !self.config.read_only {
+            let task = crate::OptimizationTask::new(
+                file_id.to_string(),
+                hot_path,
+                entry.file_size,
+                entry.file_hash,
+                crate::OptimizationStrategy::Full,
+                0,
+            );
+            if self.optimization_scheduler.submit_task(task).await {
+                info!("已重新提交文件 {} 的优化任务以补写缺失的块", file_id);
+            }
+        }
+        Ok(data)
+    }
+
     /// 流式读取版本数据（用于大文件，避免将整个文件加载到内存）
     ///
     /// 返回一个实现了 `AsyncRead` 的文件句柄，适用于流式传输场景。
@@ -864,11 +2338,182 @@ impl StorageManager {
         Ok(None)
     }
 
+    /// 读取版本数据的指定字节范围 `[offset, offset + len)`
+    ///
+    /// 完整快照只读取与范围重叠的块；其他存储形式回退到读取完整数据后截取。
+    /// 范围超出文件末尾时截断到文件大小。
+    pub async fn read_version_range(
+        &self,
+        version_id: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>> {
+        let version_info = self.get_version_info(version_id).await?;
+        let start = offset.min(version_info.file_size) as usize;
+        let end = offset.saturating_add(len).min(version_info.file_size) as usize;
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        if self.is_chunked_file(&version_info.file_id)? {
+            let delta = self.read_delta(&version_info.file_id, version_id).await?;
+            let covered: u64 = delta.chunks.iter().map(|c| c.size as u64).sum();
+            if covered >= version_info.file_size {
+                let mut result = vec![0u8; end - start];
+                for chunk in delta
+                    .chunks
+                    .iter()
+                    .filter(|c| c.offset < end && c.offset + c.size > start)
+                {
+                    let data = self.read_chunk(&chunk.chunk_id, chunk.compression).await?;
+                    let from = start.max(chunk.offset);
+                    let to = end.min(chunk.offset + chunk.size);
+                    if data.len() < to - chunk.offset {
+                        return Err(StorageError::Chunk(format!(
+                            "块 {} 数据长度不足: {} < {}",
+                            chunk.chunk_id,
+                            data.len(),
+                            to - chunk.offset
+                        )));
+                    }
+                    result[from - start..to - start]
+                        .copy_from_slice(&data[from - chunk.offset..to - chunk.offset]);
+                }
+                return Ok(result);
+            }
+        }
+
+        let data = self.read_version_data(version_id).await?;
+        let end = end.min(data.len());
+        Ok(data[start.min(end)..end].to_vec())
+    }
+
+    /// 文件是否以分块形式存储（文件索引不存在时视为分块）
+    #[allow(deprecated)]
+    pub(crate) fn is_chunked_file(&self, file_id: &str) -> Result<bool> {
+        let metadata_db = self.get_metadata_db()?;
+        Ok(metadata_db
+            .get_file_index(file_id)
+            .map_err(|e| StorageError::Storage(format!("读取文件索引失败: {}", e)))?
+            .map(|entry| {
+                matches!(
+                    entry.storage_mode,
+                    crate::StorageMode::Chunked | crate::StorageMode::Cold
+                )
+            })
+            .unwrap_or(true))
+    }
+
+    /// 打开版本的顺序块读取器
+    ///
+    /// 读取器按偏移顺序返回块数据，并根据配置 `prefetch_chunks`
+    /// 并发预取后续块。非分块存储或旧版增量链版本会先完整加载。
+    #[allow(deprecated)]
+    pub async fn open_version_reader(
+        &self,
+        version_id: &str,
+    ) -> Result<crate::reader::ChunkStreamReader> {
+        let version_info = self
+            .lookup_version_info(version_id, self.is_suspect_version(version_id))
+            .await?;
+        let storage = Arc::new(self.clone_for_gc());
+
+        if self.is_chunked_file(&version_info.file_id)? {
+            let delta = self.read_delta(&version_info.file_id, version_id).await?;
+            let covered: u64 = delta.chunks.iter().map(|c| c.size as u64).sum();
+            if covered >= version_info.file_size {
+                return Ok(crate::reader::ChunkStreamReader::new(
+                    storage,
+                    delta.chunks,
+                    self.config.prefetch_chunks,
+                ));
+            }
+        }
+
+        let data = self.read_version_data(version_id).await?;
+        Ok(crate::reader::ChunkStreamReader::preloaded(storage, data))
+    }
+
+    /// 打开版本的流式读取句柄
+    ///
+    /// 统一各存储形式，调用方无需再区分：旧热存储直接读取热文件，
+    /// 分块（含冷存储）版本按块顺序读取并预取，整文件压缩等其他形式
+    /// 解压后从内存读取（见 [`Self::open_version_reader`]）。
+    ///
+    /// 开启 `verify_downloads` 且版本记录了整文件哈希时，返回边读取边校验的读取句柄
+    /// （见 [`crate::reader::VerifyingReader`]）。
+    pub async fn open_read(&self, version_id: &str) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        let reader: Box<dyn AsyncRead + Send + Unpin> =
+            match self.read_version_stream(version_id).await? {
+                Some(file) => Box::new(file),
+                None => Box::new(
+                    self.open_version_reader(version_id)
+                        .await?
+                        .into_async_read(),
+                ),
+            };
+        if !self.config.verify_downloads {
+            return Ok(reader);
+        }
+
+        match self.recorded_version_hash(version_id).await? {
+            Some(expected) => Ok(Box::new(crate::reader::VerifyingReader::new(
+                reader,
+                Arc::new(self.clone_for_gc()),
+                version_id.to_string(),
+                expected,
+            ))),
+            None => Ok(reader),
+        }
+    }
+
+    /// 版本记录的整文件哈希
+    ///
+    /// 文件索引只记录当前版本的哈希，历史版本与未记录哈希的旧数据返回 `None`。
+    async fn recorded_version_hash(&self, version_id: &str) -> Result<Option<String>> {
+        let version_info = self.get_version_info(version_id).await?;
+        Ok(self
+            .get_metadata_db()?
+            .get_file_index(&version_info.file_id)?
+            .filter(|entry| entry.latest_version_id == version_id && !entry.file_hash.is_empty())
+            .map(|entry| entry.file_hash))
+    }
+
+    /// 记录下载校验发现的内容损坏
+    ///
+    /// 已发送的数据无法撤回，这里只记录错误日志并计数（见 [`Self::corrupt_read_count`]）；
+    /// 该版本之后的读取绕过版本信息缓存，改走强一致路径（见 [`Self::read_version_data_consistent`]）。
+    pub fn report_corrupt_read(&self, version_id: &str, expected: &str, actual: &str) {
+        error!(
+            "版本 {} 读出的内容与记录的哈希不一致，数据可能已损坏: 期望 {}，实际 {}",
+            version_id, expected, actual
+        );
+        self.corrupt_reads.fetch_add(1, Ordering::Relaxed);
+        self.suspect_versions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(version_id.to_string());
+    }
+
+    /// 下载校验发现内容损坏的累计次数
+    pub fn corrupt_read_count(&self) -> u64 {
+        self.corrupt_reads.load(Ordering::Relaxed)
+    }
+
+    /// 版本是否在下载校验中发现过内容损坏
+    pub fn is_suspect_version(&self, version_id: &str) -> bool {
+        self.suspect_versions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(version_id)
+    }
+
     /// 获取文件的流式读取路径（如果可用）
     ///
     /// 对于旧的热存储模式数据，返回文件的实际路径，可用于零拷贝发送（如 sendfile）。
     /// 对于 Chunked 模式（默认），返回 None。
     pub async fn get_file_path(&self, file_id: &str) -> Result<Option<PathBuf>> {
+        self.ensure_global_id(file_id)?;
         let metadata_db = self.get_metadata_db()?;
         if let Some(file_entry) = metadata_db
             .get_file_index(file_id)
@@ -887,27 +2532,83 @@ impl StorageManager {
 
     /// 获取版本信息
     pub async fn get_version_info(&self, version_id: &str) -> Result<VersionInfo> {
+        #[cfg(test)]
+        self.version_lookups.fetch_add(1, Ordering::Relaxed);
         // 首先尝试从 LRU 缓存读取（无锁并发安全）
         if let Some(info) = self.version_cache.get(version_id).await {
-            return Ok(info);
+            return self.ensure_global_version(info);
         }
 
         // 缓存未命中，从 Sled 读取
+        let version_info = self.load_version_info(version_id)?;
+
+        // 更新 LRU 缓存（无锁并发安全，自动淘汰）
+        self.version_cache
+            .insert(version_id.to_string(), version_info.clone())
+            .await;
+        self.ensure_global_version(version_info)
+    }
+
+    /// 从 Sled 读取版本信息（不经过也不更新缓存）
+    fn load_version_info(&self, version_id: &str) -> Result<VersionInfo> {
         let metadata_db = self.get_metadata_db()?;
-        let version_info = metadata_db
+        metadata_db
             .get_version_info(version_id)
             .map_err(|e| StorageError::Storage(format!("从 Sled 读取版本信息失败: {}", e)))?
-            .ok_or_else(|| StorageError::Storage(format!("版本信息不存在: {}", version_id)))?;
+            .ok_or_else(|| StorageError::VersionNotFound(version_id.to_string()))
+    }
 
-        // 更新 LRU 缓存（无锁并发安全，自动淘汰）
+    /// 按一致性要求获取版本信息：强一致时直接读 Sled，否则走缓存
+    async fn lookup_version_info(&self, version_id: &str, consistent: bool) -> Result<VersionInfo> {
+        if consistent {
+            self.ensure_global_version(self.load_version_info(version_id)?)
+        } else {
+            self.get_version_info(version_id).await
+        }
+    }
+
+    /// 获取文件当前版本ID
+    ///
+    /// 直接读取文件索引中的 `latest_version_id`，无需枚举全部版本
+    pub async fn current_version_id(&self, file_id: &str) -> Result<String> {
+        self.ensure_global_id(file_id)?;
+        let metadata_db = self.get_metadata_db()?;
+        let entry = metadata_db
+            .get_file_index(file_id)
+            .map_err(|e| StorageError::Storage(format!("读取文件索引失败: {}", e)))?
+            .ok_or_else(|| StorageError::FileNotFound(format!("文件不存在: {}", file_id)))?;
+        Ok(self.resolve_alias(entry)?.latest_version_id)
+    }
+
+    /// 将被新版本取代的旧版本标记为非当前版本
+    async fn clear_current_flag(&self, version_id: &str) -> Result<()> {
+        let metadata_db = self.get_metadata_db()?;
+        let Some(mut version_info) = metadata_db
+            .get_version_info(version_id)
+            .map_err(|e| StorageError::Storage(format!("从 Sled 读取版本信息失败: {}", e)))?
+        else {
+            return Ok(());
+        };
+
+        if !version_info.is_current {
+            return Ok(());
+        }
+
+        version_info.is_current = false;
+        metadata_db
+            .put_version_info(version_id, &version_info)
+            .map_err(|e| StorageError::Storage(format!("保存版本信息到 Sled 失败: {}", e)))?;
         self.version_cache
-            .insert(version_id.to_string(), version_info.clone())
+            .insert(version_id.to_string(), version_info)
             .await;
-        Ok(version_info)
+        Ok(())
     }
 
     /// 列出文件的所有版本
     pub async fn list_file_versions(&self, file_id: &str) -> Result<Vec<VersionInfo>> {
+        #[cfg(test)]
+        self.version_lookups.fetch_add(1, Ordering::Relaxed);
+        self.ensure_global_id(file_id)?;
         let metadata_db = self.get_metadata_db()?;
 
         // 从 Sled 获取文件的所有版本
@@ -923,12 +2624,21 @@ impl StorageManager {
 
     /// 删除特定文件版本
     pub async fn delete_file_version(&self, version_id: &str) -> Result<()> {
+        self.ensure_writable("删除版本")?;
+
         let version_info = self.get_version_info(version_id).await?;
 
         // 不允许删除当前版本
         if version_info.is_current {
             return Err(StorageError::Storage("无法删除当前版本".to_string()));
         }
+        // 保留期内文件的所有版本都不能删除
+        if let Some(entry) = self
+            .get_metadata_db()?
+            .get_file_index(&version_info.file_id)?
+        {
+            self.ensure_not_retained(&entry, "删除版本")?;
+        }
 
         // 读取delta以获取块信息
         let delta = self.read_delta(&version_info.file_id, version_id).await?;
@@ -942,12 +2652,17 @@ impl StorageManager {
                 .decrement_chunk_refs_batch(&chunk_ids)
                 .map_err(|e| StorageError::Storage(format!("批量减少块引用计数失败: {}", e)))?;
         }
+        metadata_db.remove_chunk_referrers(&version_info.file_id, version_id, &chunk_ids)?;
 
         // 删除delta文件
         let delta_path = self.get_delta_path(&version_info.file_id, version_id);
         if delta_path.exists() {
             fs::remove_file(&delta_path).await?;
         }
+        let compressed_path = self.get_compressed_version_path(version_id);
+        if compressed_path.exists() {
+            fs::remove_file(&compressed_path).await?;
+        }
 
         // 从数据库中删除版本信息
         metadata_db
@@ -963,6 +2678,8 @@ impl StorageManager {
 
     /// 恢复文件到指定版本
     pub async fn restore_file_version(&self, file_id: &str, version_id: &str) -> Result<()> {
+        self.ensure_writable("恢复版本")?;
+
         // 获取版本信息
         let version_info = self.get_version_info(version_id).await?;
 
@@ -973,89 +2690,225 @@ impl StorageManager {
         // 读取版本数据
         let version_data = self.read_version_data(version_id).await?;
 
-        // 获取当前版本（如果有）作为父版本
-        let current_versions = self.list_file_versions(file_id).await?;
-        let parent_version_id = current_versions.first().map(|v| v.version_id.as_str());
+        // 获取当前版本作为父版本
+        let parent_version_id = self.current_version_id(file_id).await?;
 
         // 保存为新版本（基于恢复的内容）
-        self.save_version(file_id, &version_data, parent_version_id)
+        self.save_version(file_id, &version_data, Some(&parent_version_id))
             .await?;
 
         info!("恢复文件到版本: {} -> {}", file_id, version_id);
         Ok(())
     }
 
-    /// 获取存储统计信息
-    pub async fn get_storage_stats(&self) -> Result<StorageStats> {
-        let mut total_versions = 0;
-        let mut total_chunks = 0;
-        let mut total_size = 0u64;
-        let mut unique_chunks = 0;
+    /// 获取文件当前版本的版本链深度（当前版本计为 1）
+    pub async fn version_chain_depth(&self, file_id: &str) -> Result<usize> {
+        Ok(self.current_version_chain(file_id).await?.depth)
+    }
 
-        // 从 Sled 读取所有文件和版本信息
-        let metadata_db = self.get_metadata_db()?;
-        let all_files = metadata_db
-            .list_all_files()
-            .map_err(|e| StorageError::Storage(format!("读取文件列表失败: {}", e)))?;
+    /// 压缩文件的版本链
+    ///
+    /// 将当前版本物化为完整快照并断开与父版本的链接，读取和恢复不再回溯历史版本。
+    /// 历史版本保持不变，仍可单独读取和恢复。返回断开的历史版本数。
+    /// 压缩期间持有文件锁，不与同一文件的保存交错。
+    pub async fn compact_version_chain(&self, file_id: &str) -> Result<usize> {
+        let _guard = self.file_lock(file_id).lock().await;
+        self.compact_version_chain_locked(file_id).await
+    }
 
-        // 遍历所有文件的所有版本
-        for file_entry in all_files {
-            let versions = metadata_db
-                .list_file_versions(&file_entry.file_id)
-                .map_err(|e| StorageError::Storage(format!("读取版本列表失败: {}", e)))?;
+    /// [`compact_version_chain`](Self::compact_version_chain) 的实现，调用方已持有文件锁
+    async fn compact_version_chain_locked(&self, file_id: &str) -> Result<usize> {
+        self.ensure_writable("压缩版本链")?;
 
-            for version in versions {
-                total_versions += 1;
-                total_size += version.storage_size;
-                total_chunks += version.chunk_count;
-            }
+        let chain = self.current_version_chain(file_id).await?;
+        let mut current = chain.versions[0].clone();
+        if current.parent_version_id.is_none() {
+            return Ok(0);
         }
 
-        // 统计唯一块数量（扫描chunks目录）
-        let chunks_dir = self.chunk_root.join("data");
-        if chunks_dir.exists() {
-            let mut entries = fs::read_dir(&chunks_dir).await.map_err(StorageError::Io)?;
-            let mut total_chunk_size = 0u64;
+        let metadata_db = self.get_metadata_db()?;
 
-            while let Some(entry) = entries.next_entry().await? {
-                if entry.path().is_file() {
-                    unique_chunks += 1;
-                    if let Ok(metadata) = entry.metadata().await {
-                        total_chunk_size += metadata.len();
-                    }
-                }
-            }
+        // 增量版本需先物化为完整快照，否则断链后无法重建数据
+        let delta = self.read_delta(file_id, &current.version_id).await?;
+        if !delta.is_full_snapshot(current.file_size) {
+            let data = self.read_version_data(&current.version_id).await?;
+            let chunks = self.store_snapshot_chunks(file_id, &data).await?;
+            let old_chunk_ids: Vec<String> =
+                delta.chunks.iter().map(|c| c.chunk_id.clone()).collect();
+            let snapshot = FileDelta {
+                base_version_id: String::new(),
+                chunks,
+                ..delta
+            };
+            self.save_delta(file_id, &snapshot).await?;
+            metadata_db
+                .decrement_chunk_refs_batch(&old_chunk_ids)
+                .map_err(|e| StorageError::Storage(format!("批量减少块引用计数失败: {}", e)))?;
 
-            return Ok(StorageStats {
-                total_versions,
-                total_chunks,
-                unique_chunks,
-                total_size,
-                total_chunk_size,
-                compression_ratio: if total_size > 0 {
-                    total_chunk_size as f64 / total_size as f64
-                } else {
-                    0.0
-                },
-                avg_chunk_size: if unique_chunks > 0 {
-                    total_chunk_size as f64 / unique_chunks as f64
-                } else {
-                    0.0
-                },
-            });
+            current.chunk_count = snapshot.chunks.len();
+            current.storage_size = snapshot.chunks.iter().map(|c| c.size as u64).sum();
         }
 
-        // 如果chunks目录不存在，返回基础统计
-        let total_chunk_size = 0;
+        current.parent_version_id = None;
+        metadata_db
+            .put_version_info(&current.version_id, &current)
+            .map_err(|e| StorageError::Storage(format!("保存版本信息失败: {}", e)))?;
+        self.version_cache
+            .insert(current.version_id.clone(), current)
+            .await;
 
-        Ok(StorageStats {
-            total_versions,
-            total_chunks,
-            unique_chunks,
-            total_size,
-            total_chunk_size,
-            compression_ratio: if total_size > 0 {
-                total_chunk_size as f64 / total_size as f64
+        info!("版本链已压缩: {} 深度 {} -> 1", file_id, chain.depth);
+        Ok(chain.depth - 1)
+    }
+
+    /// 构建文件当前版本的版本链
+    async fn current_version_chain(&self, file_id: &str) -> Result<crate::core::VersionChain> {
+        let version_id = self.current_version_id(file_id).await?;
+        let current = self.get_version_info(&version_id).await?;
+        let metadata_db = self.get_metadata_db()?;
+
+        crate::core::VersionChainManager::new(crate::core::VersionChainConfig {
+            max_depth: self.config.max_chain_depth,
+            keep_recent: 1,
+        })
+        .build_chain(&current, |id| metadata_db.get_version_info(id))
+    }
+
+    /// 版本链超过 `max_chain_depth` 时触发压缩（同步执行或交给后台任务）
+    ///
+    /// 由保存路径在持有文件锁时调用；后台任务在保存释放文件锁后自行加锁执行。
+    async fn maybe_compact_chain(&self, file_id: &str) -> Result<()> {
+        if self.config.max_chain_depth == 0 {
+            return Ok(());
+        }
+
+        let chain = self.current_version_chain(file_id).await?;
+        if !chain.needs_merge {
+            return Ok(());
+        }
+
+        match self.config.chain_compaction {
+            crate::ChainCompactionMode::Sync => {
+                self.compact_version_chain_locked(file_id).await?;
+            }
+            crate::ChainCompactionMode::Deferred => {
+                let storage = self.clone_for_gc();
+                let file_id = file_id.to_string();
+                let mut tasks = self
+                    .chain_compactions
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                // 顺带回收已完成的任务
+                while tasks.try_join_next().is_some() {}
+                tasks.spawn(async move {
+                    if let Err(e) = storage.compact_version_chain(&file_id).await {
+                        warn!("后台压缩版本链失败: {} - {}", file_id, e);
+                    }
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 等待所有后台版本链压缩任务完成
+    async fn drain_chain_compactions(&self) {
+        let mut tasks = std::mem::take(
+            &mut *self
+                .chain_compactions
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()),
+        );
+        while let Some(result) = tasks.join_next().await {
+            if let Err(e) = result {
+                warn!("后台压缩版本链任务异常退出: {}", e);
+            }
+        }
+    }
+
+    /// 对数据分块并写入块存储（含引用计数），返回带压缩信息的块列表
+    async fn store_snapshot_chunks(&self, file_id: &str, data: &[u8]) -> Result<Vec<ChunkInfo>> {
+        let mut generator =
+            crate::core::delta::DeltaGenerator::new(self.chunk_size, self.config.clone());
+        let mut delta = generator
+            .generate_full_delta(data, file_id)
+            .map_err(|e| StorageError::Storage(format!("生成分块失败: {}", e)))?;
+        self.apply_chunk_salt(file_id, data, &mut delta.chunks);
+
+        let metadata_db = self.get_metadata_db()?;
+        let mut chunks = Vec::with_capacity(delta.chunks.len());
+        for mut chunk in delta.chunks {
+            let chunk_data = &data[chunk.offset..chunk.offset + chunk.size];
+            let (_, compression) = self
+                .save_chunk_data(
+                    &self.compressor,
+                    &chunk.chunk_id,
+                    chunk.weak_hash,
+                    chunk_data,
+                )
+                .await?;
+
+            // 新块与复用块都按登记语义处理，与并发写入者的登记顺序无关
+            let ref_count = ChunkRefCount {
+                chunk_id: chunk.chunk_id.clone(),
+                ref_count: 1,
+                size: chunk.size as u64,
+                path: self.chunk_store.location(&chunk.chunk_id),
+                weak_hash: chunk.weak_hash,
+            };
+            metadata_db.add_chunk_ref(&chunk.chunk_id, &ref_count)?;
+
+            chunk.compression = compression;
+            chunks.push(chunk);
+        }
+        debug_assert!(
+            crate::core::delta::chunks_are_contiguous(&chunks, data.len()),
+            "块列表必须按偏移连续覆盖整个文件"
+        );
+        Ok(chunks)
+    }
+
+    /// 获取存储统计信息
+    pub async fn get_storage_stats(&self) -> Result<StorageStats> {
+        let mut total_versions = 0;
+        let mut total_chunks = 0;
+        let mut total_size = 0u64;
+        let mut unique_chunks = 0;
+
+        // 从 Sled 读取所有文件和版本信息
+        let metadata_db = self.get_metadata_db()?;
+        let all_files = metadata_db
+            .list_all_files()
+            .map_err(|e| StorageError::Storage(format!("读取文件列表失败: {}", e)))?;
+
+        // 遍历所有文件的所有版本
+        for file_entry in all_files {
+            let versions = metadata_db
+                .list_file_versions(&file_entry.file_id)
+                .map_err(|e| StorageError::Storage(format!("读取版本列表失败: {}", e)))?;
+
+            for version in versions {
+                total_versions += 1;
+                // 按逻辑大小统计：storage_size 可能已由 recompute_usage 改为分摊后的实际占用
+                total_size += version.file_size;
+                total_chunks += version.chunk_count;
+            }
+        }
+
+        // 统计唯一块数量和块存储占用
+        let mut total_chunk_size = 0u64;
+        for chunk_id in self.chunk_store.list().await? {
+            unique_chunks += 1;
+            total_chunk_size += self.chunk_store.stored_size(&chunk_id).await?.unwrap_or(0);
+        }
+
+        Ok(StorageStats {
+            total_versions,
+            total_chunks,
+            unique_chunks,
+            total_size,
+            total_chunk_size,
+            compression_ratio: if total_size > 0 {
+                total_chunk_size as f64 / total_size as f64
             } else {
                 0.0
             },
@@ -1067,6 +2920,144 @@ impl StorageManager {
         })
     }
 
+    /// 重新计算存储占用
+    ///
+    /// 写入时记录的 `VersionInfo.storage_size` 只反映写入当时的状态，GC 与版本清理后
+    /// 会偏离实际占用。该方法按当前的块共享情况重新分摊：每个块的实际存储大小
+    /// （压缩后）平均分摊给引用它的所有版本（同一版本多次引用按次数计，按块向下取整），
+    /// 热存储的文件按其当前文件大小计入当前版本。结果写回各版本的 `storage_size`
+    /// 与文件索引的 `attributed_size`，并返回全局的逻辑/物理占用汇总。
+    ///
+    /// 计算期间的并发写入不会被阻塞，其结果在下一次计算时修正。
+    pub async fn recompute_usage(&self) -> Result<UsageSummary> {
+        self.ensure_writable("重新计算存储占用")?;
+
+        let metadata_db = self.get_metadata_db()?;
+        let files = metadata_db
+            .list_all_files()
+            .map_err(|e| StorageError::Storage(format!("读取文件列表失败: {}", e)))?;
+
+        let mut summary = UsageSummary {
+            files: files.len(),
+            ..Default::default()
+        };
+
+        // 阶段 1：收集所有版本引用的块，统计每个块的引用次数
+        let mut versions = Vec::new();
+        let mut references: HashMap<String, u64> = HashMap::new();
+        for entry in &files {
+            for version in metadata_db
+                .list_file_versions(&entry.file_id)
+                .map_err(|e| StorageError::Storage(format!("读取版本列表失败: {}", e)))?
+            {
+                let chunk_ids = match self.read_delta(&entry.file_id, &version.version_id).await {
+                    Ok(delta) => delta_chunk_ids(&delta),
+                    Err(e) => {
+                        warn!(
+                            "读取版本 {} 的块列表失败，跳过重新计算: {}",
+                            version.version_id, e
+                        );
+                        continue;
+                    }
+                };
+                for chunk_id in &chunk_ids {
+                    *references.entry(chunk_id.clone()).or_default() += 1;
+                }
+                summary.versions += 1;
+                summary.logical_size += version.file_size;
+                versions.push((version, chunk_ids));
+            }
+        }
+
+        // 阶段 2：读取被引用块的实际存储大小，统计等待 GC 的块
+        let mut stored_sizes: HashMap<String, u64> = HashMap::with_capacity(references.len());
+        for chunk_id in self.chunk_store.list().await? {
+            let size = self.chunk_store.stored_size(&chunk_id).await?.unwrap_or(0);
+            if references.contains_key(&chunk_id) {
+                summary.referenced_chunks += 1;
+                summary.physical_size += size;
+                stored_sizes.insert(chunk_id, size);
+            } else {
+                summary.unreferenced_size += size;
+            }
+        }
+
+        // 阶段 3：分摊到版本与文件，只写回发生变化的记录
+        let mut attributed: HashMap<String, u64> = HashMap::with_capacity(files.len());
+        for (mut version, chunk_ids) in versions {
+            let storage_size = if chunk_ids.is_empty() {
+                // 整文件压缩的版本计压缩文件大小，热存储只归当前版本
+                let standalone =
+                    match fs::metadata(self.get_compressed_version_path(&version.version_id)).await
+                    {
+                        Ok(m) => m.len(),
+                        Err(_) if version.is_current => {
+                            fs::metadata(self.get_hot_storage_path(&version.file_id))
+                                .await
+                                .map(|m| m.len())
+                                .unwrap_or(0)
+                        }
+                        Err(_) => 0,
+                    };
+                summary.physical_size += standalone;
+                standalone
+            } else {
+                chunk_ids
+                    .iter()
+                    .map(|id| stored_sizes.get(id).copied().unwrap_or(0) / references[id])
+                    .sum()
+            };
+            *attributed.entry(version.file_id.clone()).or_default() += storage_size;
+
+            if version.storage_size != storage_size {
+                version.storage_size = storage_size;
+                metadata_db
+                    .put_version_info(&version.version_id, &version)
+                    .map_err(|e| StorageError::Storage(format!("更新版本信息失败: {}", e)))?;
+                self.version_cache
+                    .insert(version.version_id.clone(), version)
+                    .await;
+            }
+        }
+
+        for entry in files {
+            let size = attributed.get(&entry.file_id).copied().unwrap_or(0);
+            // 重新读取最新的索引，避免覆盖计算期间的并发修改
+            if let Some(mut current) = metadata_db.get_file_index(&entry.file_id)?
+                && current.attributed_size != size
+            {
+                current.attributed_size = size;
+                metadata_db.put_file_index(&entry.file_id, &current)?;
+            }
+        }
+
+        info!(
+            "存储占用重新计算完成: {} 个文件，{} 个版本，逻辑 {} 字节，物理 {} 字节，待回收 {} 字节",
+            summary.files,
+            summary.versions,
+            summary.logical_size,
+            summary.physical_size,
+            summary.unreferenced_size
+        );
+        Ok(summary)
+    }
+
+    /// GC 完成后按配置重新计算存储占用
+    ///
+    /// 未启用 `recompute_usage_after_gc` 时返回 `None`；计算失败只记录日志。
+    pub async fn recompute_usage_after_gc(&self) -> Option<UsageSummary> {
+        if !self.config.recompute_usage_after_gc {
+            return None;
+        }
+        match self.recompute_usage().await {
+            Ok(summary) => Some(summary),
+            Err(e) => {
+                warn!("GC 后重新计算存储占用失败: {}", e);
+                None
+            }
+        }
+    }
+
     /// 获取全局去重统计（去重功能始终启用）
     pub async fn get_deduplication_stats(&self) -> Result<crate::DeduplicationStats> {
         // 从 Sled 获取所有块引用计数信息
@@ -1104,6 +3095,94 @@ impl StorageManager {
         Ok(stats)
     }
 
+    /// 列出引用指定块的所有版本
+    ///
+    /// 查询维护的块反向索引，返回按文件 ID、版本 ID 排序的 (文件ID, 版本ID)，
+    /// 用于排查去重问题
+    pub async fn chunk_referrers(&self, chunk_id: &str) -> Result<Vec<(String, String)>> {
+        let mut referrers = self.get_metadata_db()?.list_chunk_referrers(chunk_id)?;
+        referrers.sort();
+        Ok(referrers)
+    }
+
+    /// 统计单个文件版本历史的跨版本去重情况
+    ///
+    /// 遍历文件所有版本引用的块：同一块无论被多少个版本引用只计一次；
+    /// 同时被其他文件引用的块按本文件所占引用比例分摊物理大小。
+    pub async fn file_dedup_report(&self, file_id: &str) -> Result<FileDedupReport> {
+        let versions = self.list_file_versions(file_id).await?;
+        if versions.is_empty() {
+            return Err(StorageError::FileNotFound(file_id.to_string()));
+        }
+
+        // 块ID -> 本文件内的引用次数
+        let mut own_refs: HashMap<String, usize> = HashMap::new();
+        let mut logical_size = 0u64;
+        let mut total_chunk_refs = 0usize;
+        for version in &versions {
+            let delta = self.read_delta(file_id, &version.version_id).await?;
+            logical_size += version.file_size;
+            total_chunk_refs += delta.chunks.len();
+            for chunk in delta.chunks {
+                *own_refs.entry(chunk.chunk_id).or_default() += 1;
+            }
+        }
+
+        let metadata_db = self.get_metadata_db()?;
+        let mut report = FileDedupReport {
+            file_id: file_id.to_string(),
+            version_count: versions.len(),
+            logical_size,
+            total_chunk_refs,
+            unique_chunks: own_refs.len(),
+            ..Default::default()
+        };
+
+        for (chunk_id, own) in &own_refs {
+            let Some(chunk_ref) = metadata_db
+                .get_chunk_ref(chunk_id)
+                .map_err(|e| StorageError::Storage(format!("获取块引用计数失败: {}", e)))?
+            else {
+                warn!("块引用计数缺失: {} ({})", chunk_id, file_id);
+                continue;
+            };
+
+            if chunk_ref.ref_count > *own {
+                // 与其他文件共享：按引用比例分摊
+                report.shared_chunks += 1;
+                report.physical_size += chunk_ref.size * *own as u64 / chunk_ref.ref_count as u64;
+            } else {
+                report.exclusive_size += chunk_ref.size;
+                report.physical_size += chunk_ref.size;
+            }
+        }
+
+        report.saved_bytes = report.logical_size.saturating_sub(report.physical_size);
+        if report.logical_size > 0 {
+            report.dedup_ratio = report.saved_bytes as f64 / report.logical_size as f64 * 100.0;
+        }
+        Ok(report)
+    }
+
+    /// 根据已有块的大小分布与去重情况给出平均分块大小建议（仅供参考）
+    ///
+    /// 块数量超过上限时等间隔抽样，结果见 [`recommend_chunking`]。
+    pub async fn analyze_chunking(&self) -> Result<ChunkingRecommendation> {
+        let all_chunks = self
+            .get_metadata_db()?
+            .list_all_chunks()
+            .map_err(|e| StorageError::Storage(format!("获取块引用计数失败: {}", e)))?;
+
+        let step = all_chunks.len().div_ceil(CHUNKING_SAMPLE_LIMIT).max(1);
+        let samples: Vec<(u64, usize)> = all_chunks
+            .iter()
+            .step_by(step)
+            .filter(|(_, chunk_ref)| chunk_ref.ref_count > 0)
+            .map(|(_, chunk_ref)| (chunk_ref.size, chunk_ref.ref_count))
+            .collect();
+        Ok(recommend_chunking(self.chunk_size, &samples))
+    }
+
     /// 保存块数据，返回使用的压缩算法
     #[allow(dead_code)]
     async fn save_chunk(
@@ -1112,12 +3191,6 @@ impl StorageManager {
         file_data: &[u8],
     ) -> Result<crate::core::compression::CompressionAlgorithm> {
         let chunk_data = &file_data[chunk.offset..chunk.offset + chunk.size];
-        let chunk_path = self.get_chunk_path(&chunk.chunk_id);
-
-        // 创建父目录
-        if let Some(parent) = chunk_path.parent() {
-            fs::create_dir_all(parent).await?;
-        }
 
         // 应用压缩（如果启用）
         let compression_result = self.compressor.compress(chunk_data)?;
@@ -1125,116 +3198,248 @@ impl StorageManager {
         let algorithm = compression_result.algorithm;
 
         // 写入块数据（可能已压缩）
-        let mut file = fs::File::create(&chunk_path).await?;
-        file.write_all(data_to_write).await?;
-        file.flush().await?;
+        self.chunk_store.put(&chunk.chunk_id, data_to_write).await?;
 
         // 更新块索引 LRU 缓存
         self.block_cache
-            .insert(chunk.chunk_id.clone(), chunk_path)
+            .insert(
+                chunk.chunk_id.clone(),
+                self.chunk_store.location(&chunk.chunk_id),
+            )
             .await;
 
         Ok(algorithm)
     }
 
+    /// 检查块是否可能已存在（内存预过滤，不访问文件系统）
+    ///
+    /// CDC 块（弱哈希非 0）先按弱哈希分桶，仅在命中的桶内比较强哈希；
+    /// 弱哈希已溢出出内存索引时回查 Sled 中的块引用，命中则加载回内存。
+    /// 内存索引由 Sled 中的全部块引用重建、随写入与回收同步维护，弱哈希未命中即判定为不存在，
+    /// 不再查询 Bloom Filter；弱哈希为 0 的旧数据和固定大小分块仍由 Bloom Filter 判断。
+    /// 误判为不存在时由写入前的块存储检查兜底（见 `save_chunk_data`），不会重复写入。
+    pub async fn chunk_exists(&self, weak_hash: u32, chunk_id: &str) -> bool {
+        if weak_hash != 0 {
+            if self.dedup_index.contains(weak_hash, chunk_id).await {
+                return true;
+            }
+            return self.dedup_index.is_spilled(weak_hash).await
+                && self.load_spilled_chunk(weak_hash, chunk_id).await;
+        }
+        self.chunk_bloom_filter.contains(chunk_id).await
+    }
+
+    /// 从 Sled 块引用中加载已溢出的去重索引条目
+    async fn load_spilled_chunk(&self, weak_hash: u32, chunk_id: &str) -> bool {
+        let Ok(metadata_db) = self.get_metadata_db() else {
+            return false;
+        };
+        match metadata_db.get_chunk_ref(chunk_id) {
+            Ok(Some(ref_count)) if ref_count.weak_hash == weak_hash => {
+                self.dedup_index.load(weak_hash, chunk_id).await;
+                true
+            }
+            Ok(_) => false,
+            Err(e) => {
+                tracing::warn!("回查块引用失败: {}: {}", chunk_id, e);
+                false
+            }
+        }
+    }
+
+    /// 获取弱哈希去重索引统计信息
+    pub async fn get_dedup_index_stats(&self) -> crate::services::DedupIndexStats {
+        self.dedup_index.get_stats().await
+    }
+
     /// 保存块数据（仅当块不存在时写入）
     ///
     /// 三级去重检测策略：
-    /// 1. **Bloom Filter 快速检测**：O(1) 时间复杂度，内存中判断
-    /// 2. **文件系统检测**：Path::exists()，确认块是否真实存在
-    /// 3. **原子文件创建**：create_new(true)，防止并发重复写入
+    /// 1. **弱哈希 + Bloom Filter 快速检测**：内存中判断（见 `chunk_exists`）
+    /// 2. **块存储检测**：`ChunkStore::exists`，确认块是否真实存在
+    /// 3. **原子发布**：由块存储后端保证（见 `ChunkStore::put`），防止部分写入和并发重复写入
+    ///
+    /// 块不存在时先获取该块的写入锁并再次确认：并发保存相同内容时只有一个写入者
+    /// 压缩并写入新块，其余写入者等待后直接引用已写入的块。
+    /// 写入者在释放写入锁之前记录实际使用的压缩算法，复用者据此解压（见 `existing_chunk_algorithm`）。
     ///
     /// # 返回值
     /// - `Ok((true, algorithm))`: 块是新写入的
     /// - `Ok((false, algorithm))`: 块已存在，跳过写入
     async fn save_chunk_data(
         &self,
+        compressor: &crate::core::compression::Compressor,
         chunk_id: &str,
+        weak_hash: u32,
         chunk_data: &[u8],
     ) -> Result<(bool, crate::core::compression::CompressionAlgorithm)> {
-        let chunk_path = self.get_chunk_path(chunk_id);
-
-        // 步骤 1: Bloom Filter 快速检测（避免不必要的文件系统调用）
-        let bloom_says_exists = self.chunk_bloom_filter.contains(chunk_id).await;
-
-        // 步骤 2: 如果 Bloom Filter 说可能存在，进一步检查文件系统
-        if bloom_says_exists && chunk_path.exists() {
-            // 文件确实存在，直接返回（跳过压缩和写入）
-            let algo = if self.config.enable_compression {
-                crate::core::compression::CompressionAlgorithm::LZ4
-            } else {
-                crate::core::compression::CompressionAlgorithm::None
-            };
-
-            tracing::debug!("块 {} 已存在（Bloom Filter + 文件系统确认），跳过写入", chunk_id);
-            return Ok((false, algo));
+        // 步骤 1: 内存预过滤（避免不必要的块存储访问）
+        let maybe_exists = self.chunk_exists(weak_hash, chunk_id).await;
+
+        // 步骤 2: 如果预过滤说可能存在，进一步检查块存储
+        if maybe_exists && self.chunk_store.exists(chunk_id).await? {
+            // 块确实存在，直接返回（跳过压缩和写入）
+            tracing::debug!("块 {} 已存在（预过滤 + 块存储确认），跳过写入", chunk_id);
+            return Ok((
+                false,
+                self.existing_chunk_algorithm(compressor, chunk_id, chunk_data)
+                    .await?,
+            ));
         }
 
-        // 步骤 2: 文件不存在，创建父目录
-        if let Some(parent) = chunk_path.parent() {
-            fs::create_dir_all(parent).await?;
+        // 同一块的写入者串行化；等待期间块可能已由其他写入者写入
+        let _guard = self.chunk_write_lock(chunk_id).lock().await;
+        if self.chunk_store.exists(chunk_id).await? {
+            tracing::debug!("块 {} 已由并发写入者写入，直接引用", chunk_id);
+            return Ok((
+                false,
+                self.recorded_or_guessed_algorithm(compressor, chunk_id, chunk_data)
+                    .await?,
+            ));
         }
 
         // 步骤 3: 应用压缩（只在需要写入时才压缩）
-        let compression_result = self.compressor.compress(chunk_data)?;
+        let compression_result = compressor.compress(chunk_data)?;
         let data_to_write = &compression_result.compressed_data;
         let algorithm = compression_result.algorithm;
 
-        // 步骤 4: 使用 create_new 独占创建文件（原子操作，防止并发重复写入）
-        let file_result = fs::OpenOptions::new()
-            .write(true)
-            .create_new(true) // 如果文件已存在则返回错误
-            .open(&chunk_path)
-            .await;
+        // 步骤 4: 原子写入块存储（防止部分写入和并发重复写入）
+        if self.chunk_store.put(chunk_id, data_to_write).await? {
+            // 记录实际使用的压缩算法（可能因块过小或压缩率不足而未压缩）
+            self.get_metadata_db()?
+                .put_chunk_codec(chunk_id, algorithm)
+                .map_err(|e| StorageError::Storage(format!("记录块压缩算法失败: {}", e)))?;
 
-        match file_result {
-            Ok(mut file) => {
-                // 文件创建成功，写入数据
-                file.write_all(data_to_write).await?;
-                file.flush().await?;
+            // 更新块索引 LRU 缓存
+            self.block_cache
+                .insert(chunk_id.to_string(), self.chunk_store.location(chunk_id))
+                .await;
 
-                // 更新块索引 LRU 缓存
-                self.block_cache
-                    .insert(chunk_id.to_string(), chunk_path)
-                    .await;
+            // 更新 Bloom Filter 和弱哈希索引
+            self.chunk_bloom_filter.insert(chunk_id).await;
+            self.dedup_index.insert(weak_hash, chunk_id).await;
 
-                // 更新 Bloom Filter（异步操作，不影响主流程）
-                self.chunk_bloom_filter.insert(chunk_id).await;
+            tracing::debug!(
+                "块 {} 写入成功，大小: {} 字节",
+                chunk_id,
+                data_to_write.len()
+            );
+            Ok((true, algorithm))
+        } else {
+            // 并发场景：块已由其他写入者写入，按其记录的算法引用
+            tracing::debug!("块 {} 已被其他线程写入", chunk_id);
+            Ok((
+                false,
+                self.recorded_or_guessed_algorithm(compressor, chunk_id, chunk_data)
+                    .await?,
+            ))
+        }
+    }
 
-                tracing::debug!(
-                    "块 {} 写入成功，大小: {} 字节",
-                    chunk_id,
-                    data_to_write.len()
-                );
-                Ok((true, algorithm))
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
-                // 并发场景：另一个线程已经写入了这个块
-                let algo = if self.config.enable_compression {
-                    crate::core::compression::CompressionAlgorithm::LZ4
-                } else {
-                    crate::core::compression::CompressionAlgorithm::None
-                };
+    /// 已存在块写入时使用的压缩算法
+    ///
+    /// 块写入者在持有写入锁期间记录算法，未找到记录时等待可能仍在进行的写入完成后再查一次。
+    async fn existing_chunk_algorithm(
+        &self,
+        compressor: &crate::core::compression::Compressor,
+        chunk_id: &str,
+        chunk_data: &[u8],
+    ) -> Result<crate::core::compression::CompressionAlgorithm> {
+        if let Some(algorithm) = self.get_metadata_db()?.get_chunk_codec(chunk_id)? {
+            return Ok(algorithm);
+        }
+        let _guard = self.chunk_write_lock(chunk_id).lock().await;
+        self.recorded_or_guessed_algorithm(compressor, chunk_id, chunk_data)
+            .await
+    }
 
-                tracing::debug!("块 {} 已被其他线程写入", chunk_id);
-                Ok((false, algo))
-            }
-            Err(e) => {
-                // 其他 I/O 错误
-                Err(StorageError::Io(e))
+    /// 按记录查找已存在块的压缩算法（调用方持有该块的写入锁）
+    ///
+    /// 记录压缩算法之前写入的块从引用它的已有版本中查找；
+    /// 仍找不到时按当前配置推测，只适用于配置未变化的旧数据。
+    async fn recorded_or_guessed_algorithm(
+        &self,
+        compressor: &crate::core::compression::Compressor,
+        chunk_id: &str,
+        chunk_data: &[u8],
+    ) -> Result<crate::core::compression::CompressionAlgorithm> {
+        if let Some(algorithm) = self.get_metadata_db()?.get_chunk_codec(chunk_id)? {
+            return Ok(algorithm);
+        }
+        if let Some(algorithm) = self.recorded_chunk_algorithm(chunk_id).await? {
+            return Ok(algorithm);
+        }
+        // 与写入时的判断一致：高熵、过小或压缩率不足的块按原样存储
+        Ok(compressor.compress(chunk_data)?.algorithm)
+    }
+
+    /// 引用该块的已有版本中记录的压缩算法（按块反向索引查找）
+    async fn recorded_chunk_algorithm(
+        &self,
+        chunk_id: &str,
+    ) -> Result<Option<crate::core::compression::CompressionAlgorithm>> {
+        let referrers = self.get_metadata_db()?.list_chunk_referrers(chunk_id)?;
+        for (file_id, version_id) in referrers {
+            if let Ok(delta) = self.read_delta(&file_id, &version_id).await
+                && let Some(chunk) = delta.chunks.iter().find(|c| c.chunk_id == chunk_id)
+            {
+                return Ok(Some(chunk.compression));
             }
         }
+        Ok(None)
+    }
+
+    /// 后台优化按文件大小选择的块压缩器（未配置分段规则时为 `None`，使用默认压缩器）
+    fn banded_chunk_compressor(&self, size: u64) -> Option<crate::core::compression::Compressor> {
+        if self.config.compression_bands.is_empty() {
+            return None;
+        }
+        Some(
+            crate::core::compression::Compressor::new(chunk_compression_config(
+                self.config.compression_for_size(size),
+            ))
+            .with_max_entropy(self.config.compression_max_entropy),
+        )
+    }
+
+    /// 块已从块存储删除后，清除其在块缓存、块索引缓存、弱哈希去重索引与压缩算法记录中的记录
+    ///
+    /// 压缩算法记录在该块的写入锁下确认块仍不存在后才删除，不会删掉并发重新写入的记录。
+    async fn forget_chunk(&self, chunk_id: &str, weak_hash: u32) {
+        self.block_cache.invalidate(chunk_id).await;
+        self.cache_manager.remove_chunk_index(chunk_id).await;
+        self.dedup_index.remove(weak_hash, chunk_id).await;
+
+        let _guard = self.chunk_write_lock(chunk_id).lock().await;
+        if matches!(self.chunk_store.exists(chunk_id).await, Ok(false))
+            && let Ok(metadata_db) = self.get_metadata_db()
+            && let Err(e) = metadata_db.remove_chunk_codec(chunk_id)
+        {
+            warn!("删除块压缩算法记录失败: {} - {}", chunk_id, e);
+        }
     }
 
     /// 读取块数据
-    async fn read_chunk(
+    pub(crate) async fn read_chunk(
         &self,
         chunk_id: &str,
         compression: crate::core::compression::CompressionAlgorithm,
     ) -> Result<Vec<u8>> {
-        let chunk_path = self.get_chunk_path(chunk_id);
-        let data = fs::read(&chunk_path).await.map_err(StorageError::Io)?;
+        let data = self.read_stored_chunk(chunk_id).await?;
+        self.decompress_chunk(data, compression)
+    }
 
+    /// 读取块的存储形式（可能已压缩）
+    pub(crate) async fn read_stored_chunk(&self, chunk_id: &str) -> Result<Vec<u8>> {
+        self.chunk_store.get(chunk_id).await
+    }
+
+    /// 将块的存储形式还原为原始数据
+    pub(crate) fn decompress_chunk(
+        &self,
+        data: Vec<u8>,
+        compression: crate::core::compression::CompressionAlgorithm,
+    ) -> Result<Vec<u8>> {
         // 如果数据被压缩，解压缩
         if compression != crate::core::compression::CompressionAlgorithm::None {
             self.compressor.decompress(&data, compression)
@@ -1271,6 +3476,9 @@ impl StorageManager {
             storage_size: delta.chunks.iter().map(|c| c.size as u64).sum(),
             created_at: Local::now().naive_local(),
             is_current: true,
+            author: None,
+            comment: None,
+            compression: None,
         };
 
         // 保存到 Sled 数据库
@@ -1288,7 +3496,7 @@ impl StorageManager {
     }
 
     /// 读取差异数据
-    async fn read_delta(&self, file_id: &str, version_id: &str) -> Result<FileDelta> {
+    pub(crate) async fn read_delta(&self, file_id: &str, version_id: &str) -> Result<FileDelta> {
         let delta_path = self.get_delta_path(file_id, version_id);
         let data = fs::read(&delta_path).await.map_err(StorageError::Io)?;
         let delta: FileDelta = serde_json::from_slice(&data)
@@ -1357,24 +3565,79 @@ impl StorageManager {
     /// 加载块索引（已改为按需加载模式）
     async fn load_block_index(&self) -> Result<()> {
         // 块索引已改为按需加载 + LRU 缓存模式，不再在启动时全量加载
-        // 只统计块数量用于日志
-        let chunks_dir = self.chunk_root.join("data");
+        // 只统计块数量用于日志（不加载到内存）
+        let count = self.chunk_store.list().await?.len();
+
+        info!("发现 {} 个数据块，采用按需加载 + LRU 缓存模式", count);
+        Ok(())
+    }
 
-        if !chunks_dir.exists() {
-            info!("块索引目录不存在，采用按需加载模式");
+    /// 加载内容哈希索引
+    ///
+    /// 索引为空但已有文件时（旧版本创建的存储），从文件索引重建一次
+    async fn load_content_hash_index(&self) -> Result<()> {
+        let metadata_db = self.get_metadata_db()?;
+        if self.config.read_only
+            || metadata_db.content_hash_count() > 0
+            || metadata_db.file_index_count() == 0
+        {
             return Ok(());
         }
 
-        // 可选：统计块数量（不加载到内存）
-        let mut count = 0;
-        let mut entries = fs::read_dir(&chunks_dir).await.map_err(StorageError::Io)?;
-        while let Some(entry) = entries.next_entry().await? {
-            if entry.path().is_file() {
-                count += 1;
+        info!("内容哈希索引为空，开始从文件索引重建");
+        self.rebuild_content_hash_index().await?;
+        Ok(())
+    }
+
+    /// 重建内容哈希索引
+    ///
+    /// 清空索引后按文件索引记录的整文件哈希重新登记，用于索引与文件索引不一致时修复。
+    /// 未记录哈希的旧数据不登记，首次计算哈希（见 [`Self::content_hash`]）时补登。
+    /// 返回登记的文件数量。
+    pub async fn rebuild_content_hash_index(&self) -> Result<usize> {
+        self.ensure_writable("重建内容哈希索引")?;
+        let metadata_db = self.get_metadata_db()?;
+        metadata_db.clear_content_hashes()?;
+
+        let mut indexed = 0usize;
+        for entry in metadata_db.iter_files() {
+            let entry = entry?;
+            if !entry.file_hash.is_empty() {
+                metadata_db.add_content_hash(&entry.file_hash, &entry.file_id)?;
+                indexed += 1;
             }
         }
+        info!("内容哈希索引重建完成，共 {} 个文件", indexed);
+        Ok(indexed)
+    }
 
-        info!("发现 {} 个数据块，采用按需加载 + LRU 缓存模式", count);
+    /// 加载块反向索引
+    ///
+    /// 反向索引为空但已有块引用时（旧版本创建的存储），从所有版本的差异数据重建一次
+    async fn load_chunk_referrers(&self) -> Result<()> {
+        let metadata_db = self.get_metadata_db()?;
+        if self.config.read_only
+            || metadata_db.chunk_referrer_count() > 0
+            || metadata_db.chunk_ref_count() == 0
+        {
+            return Ok(());
+        }
+
+        info!("块反向索引为空，开始从差异数据重建");
+        let mut indexed = 0usize;
+        for version in metadata_db.iter_versions() {
+            let version = version?;
+            let Ok(delta) = self.read_delta(&version.file_id, &version.version_id).await else {
+                continue;
+            };
+            metadata_db.add_chunk_referrers(
+                &version.file_id,
+                &version.version_id,
+                &delta_chunk_ids(&delta),
+            )?;
+            indexed += 1;
+        }
+        info!("块反向索引重建完成，共 {} 个版本", indexed);
         Ok(())
     }
 
@@ -1395,9 +3658,78 @@ impl StorageManager {
             .join(format!("{}.json", version_id))
     }
 
-    /// 保存差异数据
+    /// 整文件压缩版本的数据路径（按版本 ID 存放，文件移动时无需迁移）
+    fn get_compressed_version_path(&self, version_id: &str) -> PathBuf {
+        self.data_root
+            .join("compressed")
+            .join(format!("{}.compressed", version_id))
+    }
+
+    /// 整文件压缩使用的压缩器与算法，未启用压缩时返回 `None`
+    fn whole_file_compression(
+        &self,
+    ) -> Option<(
+        crate::core::compression::Compressor,
+        crate::core::CompressionAlgorithm,
+    )> {
+        if !self.config.enable_compression {
+            return None;
+        }
+        let algorithm = match self.config.compression_algorithm.as_str() {
+            "zstd" => crate::core::CompressionAlgorithm::Zstd,
+            _ => crate::core::CompressionAlgorithm::LZ4,
+        };
+        let compression_config = crate::core::compression::CompressionConfig {
+            algorithm,
+            level: 1,
+            min_size: 0,
+            ..Default::default()
+        };
+        Some((
+            crate::core::compression::Compressor::new(compression_config),
+            algorithm,
+        ))
+    }
+
+    /// 后台整文件压缩使用的压缩器，配置了分段压缩规则时按文件大小选择算法
+    ///
+    /// 返回 `None` 表示不压缩。使用的算法记录在文件索引中（见 [`FileIndexEntry::compression`]）。
+    fn whole_file_compressor(&self, size: u64) -> Option<crate::core::compression::Compressor> {
+        if self.config.compression_bands.is_empty() {
+            return self
+                .whole_file_compression()
+                .map(|(compressor, _)| compressor);
+        }
+        let algorithm = self.config.compression_for_size(size);
+        if algorithm == crate::core::CompressionAlgorithm::None {
+            return None;
+        }
+        Some(crate::core::compression::Compressor::new(
+            crate::core::compression::CompressionConfig {
+                algorithm,
+                level: 1,
+                min_size: 0,
+                ..Default::default()
+            },
+        ))
+    }
+
+    /// 保存差异数据
+    ///
+    /// 同时维护块反向索引：覆盖已有差异（后台优化、压缩版本链）时先移除旧块列表的引用记录
     async fn save_delta(&self, file_id: &str, delta: &FileDelta) -> Result<()> {
         let delta_path = self.get_delta_path(file_id, &delta.new_version_id);
+        let metadata_db = self.get_metadata_db()?;
+
+        if delta_path.exists()
+            && let Ok(old_delta) = self.read_delta(file_id, &delta.new_version_id).await
+        {
+            metadata_db.remove_chunk_referrers(
+                file_id,
+                &delta.new_version_id,
+                &delta_chunk_ids(&old_delta),
+            )?;
+        }
 
         // 创建父目录
         if let Some(parent) = delta_path.parent() {
@@ -1412,14 +3744,9 @@ impl StorageManager {
             .await
             .map_err(StorageError::Io)?;
 
-        Ok(())
-    }
+        metadata_db.add_chunk_referrers(file_id, &delta.new_version_id, &delta_chunk_ids(delta))?;
 
-    /// 获取块路径
-    fn get_chunk_path(&self, chunk_id: &str) -> PathBuf {
-        // 使用哈希前缀分层存储
-        let prefix = &chunk_id[..2.min(chunk_id.len())];
-        self.chunk_root.join("data").join(prefix).join(chunk_id)
+        Ok(())
     }
 
     /// 获取热存储路径
@@ -1439,17 +3766,135 @@ impl StorageManager {
 
     /// 计算哈希值
     fn calculate_hash(&self, data: &[u8]) -> String {
-        use sha2::{Digest, Sha256};
         let mut hasher = Sha256::new();
         hasher.update(data);
         hex::encode(hasher.finalize())
     }
 
+    /// 文件所属命名空间配置的块 ID 盐值
+    fn chunk_salt(&self, file_id: &str) -> Option<&str> {
+        let namespace = crate::namespace::namespace_of(file_id)?;
+        self.config
+            .namespace_salts
+            .get(namespace)
+            .map(String::as_str)
+    }
+
+    /// 命名空间配置了盐值时，将分块结果的块 ID 替换为加盐 ID
+    ///
+    /// 强哈希保持为块数据本身的 SHA-256，用于读取时校验。
+    fn apply_chunk_salt(&self, file_id: &str, data: &[u8], chunks: &mut [ChunkInfo]) {
+        if let Some(salt) = self.chunk_salt(file_id) {
+            for chunk in chunks {
+                let chunk_data = &data[chunk.offset..chunk.offset + chunk.size];
+                chunk.chunk_id = salted_chunk_id(salt, chunk_data);
+            }
+        }
+    }
+
+    /// 为新版本分配版本 ID
+    ///
+    /// 默认使用随机的 scru128。启用 `content_addressed_versions` 时由文件 ID、
+    /// 文件当前最新版本、父版本 ID 与整文件哈希派生，不同节点以相同历史保存相同内容时
+    /// 得到相同的版本 ID。派生输入包含当前最新版本，同一文件的版本 ID 不会重复
+    /// （内容回退到旧值也会得到新 ID）；包含文件 ID，不同文件保存相同内容也不会共用版本 ID。
+    fn new_version_id(
+        &self,
+        file_id: &str,
+        parent_version_id: Option<&str>,
+        file_hash: &str,
+    ) -> Result<String> {
+        if !self.config.content_addressed_versions {
+            return Ok(format!("v_{}", scru128::new()));
+        }
+
+        let latest_version_id = self
+            .get_metadata_db()?
+            .get_file_index(file_id)
+            .map_err(|e| StorageError::Storage(format!("读取文件索引失败: {}", e)))?
+            .map(|entry| entry.latest_version_id)
+            .unwrap_or_default();
+
+        // 各字段带长度前缀，避免拼接歧义
+        let mut hasher = Sha256::new();
+        for field in [
+            file_id,
+            latest_version_id.as_str(),
+            parent_version_id.unwrap_or(""),
+            file_hash,
+        ] {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
+        Ok(format!("v_{}", hex::encode(hasher.finalize())))
+    }
+
     /// 获取版本根目录（公开方法，供适配器使用）
     pub fn version_root(&self) -> &Path {
         &self.version_root
     }
 
+    /// Sled 元数据数据库目录
+    pub fn metadata_dir(&self) -> PathBuf {
+        self.config
+            .metadata_dir
+            .clone()
+            .unwrap_or_else(|| self.version_root.join("metadata"))
+    }
+
+    /// 临时文件目录
+    ///
+    /// 上传会话、快照导入暂存等临时文件统一写在这里，启动时清理过期残留。
+    pub fn scratch_dir(&self) -> PathBuf {
+        self.config
+            .scratch_dir
+            .clone()
+            .unwrap_or_else(|| self.root_path.join("tmp"))
+    }
+
+    /// 删除临时目录中超过 `max_age` 未修改的文件，返回删除的文件数
+    ///
+    /// 子目录递归处理；清理后变为空的过期子目录一并删除，仍有新文件的目录保留。
+    async fn sweep_scratch_dir(&self, max_age: Duration) -> Result<usize> {
+        let now = std::time::SystemTime::now();
+        let is_stale = |metadata: &std::fs::Metadata| {
+            metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > max_age)
+        };
+
+        let mut removed = 0;
+        // 先序收集子目录（记录清理前是否过期），文件就地清理
+        let mut pending = vec![self.scratch_dir()];
+        let mut stale_dirs = Vec::new();
+        while let Some(dir) = pending.pop() {
+            let mut entries = fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    if is_stale(&metadata) {
+                        stale_dirs.push(path.clone());
+                    }
+                    pending.push(path);
+                } else if is_stale(&metadata) {
+                    match fs::remove_file(&path).await {
+                        Ok(()) => removed += 1,
+                        Err(e) => warn!("清理临时文件失败: {:?} - {}", path, e),
+                    }
+                }
+            }
+        }
+
+        // 从最深的目录开始删除已清空的过期目录（非空时删除失败，直接跳过）
+        for dir in stale_dirs.iter().rev() {
+            let _ = fs::remove_dir(dir).await;
+        }
+        Ok(removed)
+    }
+
     /// 确保文件在 data_root 中存在（用于 WebDAV 等需要文件系统访问的场景）
     /// 如果文件不存在，从块存储中重建
     pub async fn ensure_file_in_data_root(&self, file_id: &str) -> Result<()> {
@@ -1485,7 +3930,17 @@ impl StorageManager {
     /// 返回 (文件列表, 子目录列表)
     pub async fn list_directory(&self, dir_path: &str) -> Result<(Vec<String>, Vec<String>)> {
         let all_files = self.list_files().await?;
+        self.build_directory_listing(all_files, dir_path, dir_path)
+            .await
+    }
 
+    /// 从文件列表推断目录结构，并用 `storage_dir` 对应的文件系统目录补充空目录
+    pub(crate) async fn build_directory_listing(
+        &self,
+        all_files: Vec<String>,
+        dir_path: &str,
+        storage_dir: &str,
+    ) -> Result<(Vec<String>, Vec<String>)> {
         // 标准化目录路径
         let normalized_dir = if dir_path.is_empty() || dir_path == "/" {
             ""
@@ -1524,13 +3979,14 @@ impl StorageManager {
             }
         }
 
-        // 2. 从文件系统补充空目录（MKCOL 创建的目录）
-        let storage_path = self.get_full_path(dir_path);
+        // 2. 从文件系统补充空目录（MKCOL 创建的目录，跳过命名空间根目录）
+        let storage_path = self.get_full_path(storage_dir);
         if let Ok(mut entries) = fs::read_dir(&storage_path).await {
             while let Ok(Some(entry)) = entries.next_entry().await {
                 if let Ok(file_type) = entry.file_type().await
                     && file_type.is_dir()
                     && let Some(name) = entry.file_name().to_str()
+                    && name != crate::namespace::NAMESPACE_ROOT
                 {
                     // 添加文件系统中存在的目录（去重）
                     subdirs.insert(name.to_string());
@@ -1600,6 +4056,13 @@ impl StorageManager {
             .list_all_chunks()
             .map_err(|e| StorageError::Storage(format!("获取块列表失败: {}", e)))?;
 
+        // 重建弱哈希索引（旧数据 weak_hash 为 0，不进入索引）
+        let weak_entries: Vec<(u32, String)> = all_chunks
+            .iter()
+            .map(|(id, ref_count)| (ref_count.weak_hash, id.clone()))
+            .collect();
+        self.dedup_index.rebuild(weak_entries).await;
+
         // 提取块 ID（all_chunks 是 Vec<(String, ChunkRefCount)>）
         let chunk_ids: Vec<String> = all_chunks.into_iter().map(|(id, _)| id).collect();
 
@@ -1634,63 +4097,91 @@ impl StorageManager {
         Ok(())
     }
 
-    /// 重建块引用计数
-    /// 重建块引用计数（保留以备恢复使用）
-    #[allow(dead_code)]
-    async fn rebuild_chunk_ref_count(&self) -> Result<()> {
-        info!("开始重建块引用计数...");
-        let mut ref_counts: HashMap<String, ChunkRefCount> = HashMap::new();
-        let metadata_db = self.get_metadata_db()?;
-
-        // 从 Sled 遍历所有文件和版本，统计块引用
-        let all_files = metadata_db
-            .list_all_files()
-            .map_err(|e| StorageError::Storage(format!("列出所有文件失败: {}", e)))?;
-
-        for file_entry in all_files {
-            // 获取该文件的所有版本
-            let versions = metadata_db
-                .list_file_versions(&file_entry.file_id)
-                .map_err(|e| StorageError::Storage(format!("列出文件版本失败: {}", e)))?;
+    /// 全量重建块引用计数（去重索引）
+    ///
+    /// 按版本索引的键顺序流式读取每个版本的差异数据，每统计
+    /// [`DEDUP_REBUILD_BATCH_VERSIONS`] 个版本就把累计结果连同断点原子写入元数据库的
+    /// 暂存树，内存占用只与批大小有关。进程中断后再次调用从断点继续。
+    /// 全部统计完成后用暂存结果替换块引用计数：没有任何版本引用的块置 0，
+    /// 由下一次垃圾回收清理。
+    ///
+    /// 替换阶段会覆盖扫描期间其他写入、删除或 GC 对引用计数的修改，因此只能在
+    /// 维护模式下执行（见 [`Self::enter_maintenance`]），否则返回 `PreconditionFailed`。
+    pub async fn rebuild_dedup_index(&self) -> Result<DedupRebuildResult> {
+        if self.config.read_only {
+            return Err(StorageError::ReadOnly("重建去重索引".to_string()));
+        }
+        if !self.is_in_maintenance() {
+            return Err(StorageError::PreconditionFailed(
+                "重建去重索引需要先进入维护模式".to_string(),
+            ));
+        }
+        self.ensure_gc_leader("重建去重索引").await?;
+        let _guard = self
+            .dedup_rebuild_lock
+            .try_lock()
+            .map_err(|_| StorageError::Storage("去重索引重建已在进行中".to_string()))?;
 
-            for version_info in versions {
-                // 读取该版本的 delta
-                if let Ok(delta) = self
-                    .read_delta(&version_info.file_id, &version_info.version_id)
-                    .await
-                {
-                    for chunk in &delta.chunks {
-                        let entry =
-                            ref_counts
-                                .entry(chunk.chunk_id.clone())
-                                .or_insert_with(|| ChunkRefCount {
-                                    chunk_id: chunk.chunk_id.clone(),
-                                    ref_count: 0,
-                                    size: chunk.size as u64,
-                                    path: self.get_chunk_path(&chunk.chunk_id),
-                                });
-                        entry.ref_count += 1;
-                    }
-                }
+        let metadata_db = self.get_metadata_db()?;
+        let checkpoint = metadata_db.get_ref_rebuild_checkpoint()?;
+        let resumed = checkpoint.is_some();
+        match &checkpoint {
+            Some(c) => info!(
+                "从断点继续重建去重索引，已统计 {} 个版本",
+                c.versions_scanned
+            ),
+            None => {
+                info!("开始重建去重索引");
+                metadata_db.clear_ref_rebuild()?;
             }
         }
-
-        // 直接保存到 Sled
-        for (chunk_id, ref_count) in ref_counts.iter() {
-            metadata_db
-                .put_chunk_ref(chunk_id, ref_count)
-                .map_err(|e| StorageError::Storage(format!("保存块引用计数失败: {}", e)))?;
+        let mut checkpoint = checkpoint.unwrap_or_default();
+
+        let mut pending: HashMap<String, ChunkRefCount> = HashMap::new();
+        let mut pending_versions = 0usize;
+        for version in metadata_db.iter_versions_after(checkpoint.last_version_id.as_deref()) {
+            let version = version?;
+            // 差异数据缺失的版本不引用任何块
+            if let Ok(delta) = self.read_delta(&version.file_id, &version.version_id).await {
+                for chunk in &delta.chunks {
+                    pending
+                        .entry(chunk.chunk_id.clone())
+                        .or_insert_with(|| ChunkRefCount {
+                            chunk_id: chunk.chunk_id.clone(),
+                            ref_count: 0,
+                            size: chunk.size as u64,
+                            path: self.chunk_store.location(&chunk.chunk_id),
+                            weak_hash: chunk.weak_hash,
+                        })
+                        .ref_count += 1;
+                }
+            }
+            checkpoint.last_version_id = Some(version.version_id);
+            checkpoint.versions_scanned += 1;
+            pending_versions += 1;
+
+            if pending_versions >= DEDUP_REBUILD_BATCH_VERSIONS {
+                metadata_db.stage_chunk_refs(&pending, &checkpoint)?;
+                pending.clear();
+                pending_versions = 0;
+            }
         }
+        // 即使没有剩余版本也写入断点，替换阶段中断后重试时不会误判为新的重建
+        metadata_db.stage_chunk_refs(&pending, &checkpoint)?;
 
-        // 刷新到磁盘
-        metadata_db
-            .flush()
-            .await
-            .map_err(|e| StorageError::Storage(format!("刷新数据库失败: {}", e)))?;
+        let (chunks, reset_chunks) = metadata_db.apply_ref_rebuild()?;
+        metadata_db.flush().await?;
 
-        let count = ref_counts.len();
-        info!("重建完成，共 {} 个块", count);
-        Ok(())
+        info!(
+            "去重索引重建完成: {} 个版本，{} 个块，{} 个块引用计数置 0",
+            checkpoint.versions_scanned, chunks, reset_chunks
+        );
+        Ok(DedupRebuildResult {
+            versions_scanned: checkpoint.versions_scanned,
+            chunks,
+            reset_chunks,
+            resumed,
+        })
     }
 
     /// 加载文件索引
@@ -1780,6 +4271,15 @@ impl StorageManager {
                         optimization_status: crate::OptimizationStatus::Completed,
                         file_size: version_info.file_size,
                         file_hash: String::new(),
+                        user_metadata: HashMap::new(),
+                        optimization_strategy: None,
+                        optimized_at: None,
+                        space_saved: 0,
+                        stored_size: 0,
+                        attributed_size: 0,
+                        alias_target: None,
+                        compression: None,
+                        retention: None,
                     });
 
                 entry.version_count += 1;
@@ -1811,25 +4311,315 @@ impl StorageManager {
 
     /// 列出所有文件
     pub async fn list_files(&self) -> Result<Vec<String>> {
+        // 全局列表不包含任何命名空间下的文件
+        let mut files: Vec<String> = self
+            .list_all_file_keys()
+            .await?
+            .into_iter()
+            .filter(|key| !crate::namespace::is_namespaced(key))
+            .collect();
+
+        files.sort();
+        Ok(files)
+    }
+
+    /// 列出所有文件及其元数据
+    ///
+    /// 一次按键序遍历文件索引，直接由索引中的大小和时间构造元数据，不逐个查找文件和版本记录。
+    /// 与 `list_files` 一样不包含已删除文件和命名空间内的文件，结果按文件ID排序；
+    /// 只有未记录大小和哈希的旧索引条目回退到读取当前版本记录。
+    ///
+    /// 别名的大小和版本取自目标文件，`path` 为目标文件ID（普通文件与 `id` 相同），
+    /// 目标已删除的悬空别名不列出。
+    pub async fn list_files_with_metadata(&self) -> Result<Vec<FileMetadata>> {
+        let metadata_db = self.get_metadata_db()?;
+        let mut files = Vec::new();
+        for entry in metadata_db.iter_files() {
+            let entry = entry?;
+            if entry.is_deleted || crate::namespace::is_namespaced(&entry.file_id) {
+                continue;
+            }
+            let file_id = entry.file_id.clone();
+            let entry = match self.resolve_alias(entry) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    tracing::debug!("跳过悬空别名: {}", e);
+                    continue;
+                }
+            };
+
+            let size = if entry.file_hash.is_empty() {
+                match self.get_version_info(&entry.latest_version_id).await {
+                    Ok(version) => version.file_size,
+                    Err(e) => {
+                        warn!("读取文件 {} 的当前版本失败，跳过: {}", file_id, e);
+                        continue;
+                    }
+                }
+            } else {
+                entry.file_size
+            };
+
+            files.push(FileMetadata {
+                id: file_id.clone(),
+                name: file_id,
+                path: entry.file_id,
+                size,
+                hash: entry.latest_version_id,
+                created_at: entry.created_at,
+                modified_at: entry.modified_at,
+                user_metadata: entry.user_metadata,
+            });
+        }
+        Ok(files)
+    }
+
+    /// 以流的形式惰性遍历所有文件索引条目（包含已删除文件和所有命名空间）
+    ///
+    /// 基于 Sled 的惰性迭代，每次只反序列化一条记录，遍历大型存储时内存占用保持平稳。
+    /// 元数据数据库未初始化时流只产生一个错误。
+    pub fn iter_files(&self) -> impl Stream<Item = Result<FileIndexEntry>> + Send + 'static {
+        let (entries, error) = match self.get_metadata_db() {
+            Ok(db) => (Some(db.iter_files()), None),
+            Err(e) => (None, Some(e)),
+        };
+        futures::stream::iter(
+            error
+                .map(Err)
+                .into_iter()
+                .chain(entries.into_iter().flatten()),
+        )
+    }
+
+    /// 列出所有未删除文件的存储键（包含所有命名空间）
+    pub(crate) async fn list_all_file_keys(&self) -> Result<Vec<String>> {
         let metadata_db = self.get_metadata_db()?;
         let all_files = metadata_db
             .list_all_files()
             .map_err(|e| StorageError::Storage(format!("列出文件失败: {}", e)))?;
 
         // 过滤掉已删除的文件
-        let mut files: Vec<String> = all_files
+        Ok(all_files
             .into_iter()
             .filter(|entry| !entry.is_deleted)
             .map(|entry| entry.file_id)
-            .collect();
+            .collect())
+    }
 
-        files.sort();
-        Ok(files)
+    /// 列出指定时间之后发生变更的文件，按变更时间升序排列
+    ///
+    /// 每个文件只返回最近一次变更：删除时间晚于 `since` 的已删除文件记为删除，
+    /// 其余修改时间晚于 `since` 的文件按创建时间区分新建与修改。
+    /// 永久删除的文件不再出现在文件索引中，不会被报告；命名空间内的文件不计入。
+    pub async fn changes_since(&self, since: chrono::NaiveDateTime) -> Result<Vec<ChangeRecord>> {
+        let metadata_db = self.get_metadata_db()?;
+        let mut changes = Vec::new();
+        for entry in metadata_db.iter_files() {
+            let entry = entry?;
+            if crate::namespace::is_namespaced(&entry.file_id) {
+                continue;
+            }
+
+            let (op, timestamp) = match entry.deleted_at {
+                Some(deleted_at) if entry.is_deleted => {
+                    if deleted_at <= since {
+                        continue;
+                    }
+                    (ChangeOp::Deleted, deleted_at)
+                }
+                _ if entry.modified_at > since => {
+                    if entry.created_at > since {
+                        (ChangeOp::Created, entry.modified_at)
+                    } else {
+                        (ChangeOp::Modified, entry.modified_at)
+                    }
+                }
+                _ => continue,
+            };
+
+            changes.push(ChangeRecord {
+                file_id: entry.file_id,
+                version_id: entry.latest_version_id,
+                op,
+                timestamp,
+            });
+        }
+
+        changes.sort_by(|a, b| {
+            a.timestamp
+                .cmp(&b.timestamp)
+                .then_with(|| a.file_id.cmp(&b.file_id))
+        });
+        Ok(changes)
+    }
+
+    /// 订阅存储变更通知
+    ///
+    /// 新建、更新、删除、移动文件成功后发送 [`MutationEvent`]，供搜索索引和上层缓存精确失效。
+    /// 订阅者处理过慢时最旧的事件会被丢弃（`RecvError::Lagged`），此时应整体失效。
+    pub fn subscribe_mutations(&self) -> broadcast::Receiver<MutationEvent> {
+        self.mutation_tx.subscribe()
+    }
+
+    /// 注册硬删除钩子
+    ///
+    /// [`hard_delete_file`](Self::hard_delete_file) 在返回前依次调用，
+    /// 用于同步擦除搜索索引等上层保存的文件痕迹（变更通知是异步的，不保证及时清理）。
+    pub fn add_erase_hook(&self, hook: Arc<dyn EraseHook>) {
+        self.erase_hooks
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(hook);
+    }
+
+    /// 失效文件的内部缓存并发送变更通知
+    async fn notify_mutation(&self, file_id: &str, op: MutationOp) {
+        self.cache_manager.remove_file_metadata(file_id).await;
+        self.cache_manager.remove_hot_data(file_id).await;
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.mutation_tx.send(MutationEvent {
+            file_id: file_id.to_string(),
+            op,
+        });
+    }
+
+    /// 获取限定在指定命名空间内的存储视图
+    pub fn namespace(
+        &self,
+        namespace: crate::namespace::Namespace,
+    ) -> crate::namespace::NamespacedStorage {
+        crate::namespace::NamespacedStorage::new(self.with_namespace_access(), namespace)
+    }
+
+    /// 允许访问命名空间内键的副本（供租户视图与遍历全部文件的维护操作使用）
+    pub(crate) fn with_namespace_access(&self) -> Self {
+        let mut storage = self.clone();
+        storage.namespace_access = true;
+        storage
+    }
+
+    /// 全局接口拒绝命名空间内的键，租户文件只能经由 [`Self::namespace`] 访问
+    fn ensure_global_id(&self, file_id: &str) -> Result<()> {
+        if !self.namespace_access
+            && crate::namespace::is_namespaced(file_id.trim_start_matches('/'))
+        {
+            return Err(StorageError::ReservedPath(file_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// 全局接口按版本ID访问时，隐藏属于命名空间内文件的版本
+    fn ensure_global_version(&self, version_info: VersionInfo) -> Result<VersionInfo> {
+        if !self.namespace_access && crate::namespace::is_namespaced(&version_info.file_id) {
+            return Err(StorageError::VersionNotFound(version_info.version_id));
+        }
+        Ok(version_info)
+    }
+
+    /// 获取文件的保留锁（已过期的锁同样返回）
+    pub async fn get_retention(&self, file_id: &str) -> Result<Option<RetentionLock>> {
+        self.ensure_global_id(file_id)?;
+        let entry = self
+            .get_metadata_db()?
+            .get_file_index(file_id)?
+            .filter(|entry| !entry.is_deleted)
+            .ok_or_else(|| StorageError::FileNotFound(file_id.to_string()))?;
+        Ok(entry.retention)
+    }
+
+    /// 设置文件的保留锁
+    ///
+    /// 延长保留期、治理模式升级为合规模式总是允许；缩短有效的治理模式锁需要
+    /// `bypass_governance`（管理员），有效的合规模式锁不能缩短或降级。
+    /// 截止时间必须晚于当前时间。
+    pub async fn set_retention(
+        &self,
+        file_id: &str,
+        lock: RetentionLock,
+        bypass_governance: bool,
+    ) -> Result<()> {
+        self.ensure_writable("设置保留锁")?;
+        self.ensure_global_id(file_id)?;
+
+        let now = self.clock.now_naive();
+        if !lock.is_active(now) {
+            return Err(StorageError::Storage(format!(
+                "保留截止时间必须晚于当前时间: {}",
+                lock.until
+            )));
+        }
+
+        let metadata_db = self.get_metadata_db()?;
+        let mut entry = metadata_db
+            .get_file_index(file_id)?
+            .filter(|entry| !entry.is_deleted)
+            .ok_or_else(|| StorageError::FileNotFound(file_id.to_string()))?;
+
+        if let Some(current) = entry.active_retention(now) {
+            let weakened = lock.until < current.until
+                || (current.mode == RetentionMode::Compliance
+                    && lock.mode == RetentionMode::Governance);
+            if weakened {
+                ensure_retention_relaxable(file_id, current, bypass_governance)?;
+            }
+        }
+
+        entry.retention = Some(lock);
+        metadata_db.put_file_index(file_id, &entry)?;
+        metadata_db.flush().await?;
+
+        info!(
+            "文件 {} 设置保留锁: {:?} 至 {}",
+            file_id, lock.mode, lock.until
+        );
+        Ok(())
+    }
+
+    /// 解除文件的保留锁
+    ///
+    /// 有效的治理模式锁需要 `bypass_governance`（管理员），有效的合规模式锁不能解除；
+    /// 已过期的锁总是可以清除。
+    pub async fn remove_retention(&self, file_id: &str, bypass_governance: bool) -> Result<()> {
+        self.ensure_writable("解除保留锁")?;
+        self.ensure_global_id(file_id)?;
+
+        let metadata_db = self.get_metadata_db()?;
+        let mut entry = metadata_db
+            .get_file_index(file_id)?
+            .filter(|entry| !entry.is_deleted)
+            .ok_or_else(|| StorageError::FileNotFound(file_id.to_string()))?;
+        if entry.retention.is_none() {
+            return Ok(());
+        }
+        if let Some(current) = entry.active_retention(self.clock.now_naive()) {
+            ensure_retention_relaxable(file_id, current, bypass_governance)?;
+        }
+
+        entry.retention = None;
+        metadata_db.put_file_index(file_id, &entry)?;
+        metadata_db.flush().await?;
+
+        info!("文件 {} 已解除保留锁", file_id);
+        Ok(())
+    }
+
+    /// 文件处于保留期时拒绝删除、移动等操作
+    fn ensure_not_retained(&self, entry: &FileIndexEntry, operation: &str) -> Result<()> {
+        match entry.active_retention(self.clock.now_naive()) {
+            Some(lock) => Err(StorageError::RetentionLocked(format!(
+                "{} {}：{:?} 模式保留至 {}",
+                operation, entry.file_id, lock.mode, lock.until
+            ))),
+            None => Ok(()),
+        }
     }
 
     /// 软删除文件（移到回收站）
     /// 只标记文件为已删除，不实际删除数据
     pub async fn delete_file(&self, file_id: &str) -> Result<()> {
+        self.ensure_writable("删除文件")?;
+        self.ensure_global_id(file_id)?;
+
         info!("软删除文件: {}", file_id);
 
         let metadata_db = self.get_metadata_db()?;
@@ -1846,10 +4636,11 @@ impl StorageManager {
                 file_id
             )));
         }
+        self.ensure_not_retained(&file_entry, "删除文件")?;
 
         // 3. 标记为已删除
         file_entry.is_deleted = true;
-        file_entry.deleted_at = Some(chrono::Local::now().naive_local());
+        file_entry.deleted_at = Some(self.clock.now_naive());
 
         // 4. 更新文件索引
         metadata_db.put_file_index(file_id, &file_entry)?;
@@ -1857,6 +4648,7 @@ impl StorageManager {
         // 5. 持久化
         metadata_db.flush().await?;
 
+        self.notify_mutation(file_id, MutationOp::Deleted).await;
         info!("文件已移到回收站: {}", file_id);
         Ok(())
     }
@@ -1864,8 +4656,25 @@ impl StorageManager {
     /// 永久删除文件（物理删除）
     /// 删除文件的所有版本和块数据
     pub async fn permanently_delete_file(&self, file_id: &str) -> Result<()> {
+        self.ensure_writable("永久删除文件")?;
+        self.ensure_global_id(file_id)?;
+
         info!("开始永久删除文件: {}", file_id);
 
+        let metadata_db = self.get_metadata_db()?;
+        let file_entry = metadata_db.get_file_index(file_id)?;
+        if let Some(entry) = &file_entry {
+            self.ensure_not_retained(entry, "永久删除文件")?;
+        }
+
+        // 别名没有版本和块，只移除索引条目
+        if file_entry.is_some_and(|entry| entry.alias_target.is_some()) {
+            metadata_db.remove_file_index(file_id)?;
+            metadata_db.flush().await?;
+            self.notify_mutation(file_id, MutationOp::Deleted).await;
+            return Ok(());
+        }
+
         // 1. 获取该文件的所有版本
         let versions = self.list_file_versions(file_id).await?;
 
@@ -1879,9 +4688,13 @@ impl StorageManager {
         for version in &versions {
             // 读取 delta 获取块列表
             if let Ok(delta) = self.read_delta(file_id, &version.version_id).await {
-                for chunk in delta.chunks {
-                    chunks_to_decrement.push(chunk.chunk_id);
-                }
+                let chunk_ids = delta_chunk_ids(&delta);
+                self.get_metadata_db()?.remove_chunk_referrers(
+                    file_id,
+                    &version.version_id,
+                    &chunk_ids,
+                )?;
+                chunks_to_decrement.extend(chunk_ids);
             }
 
             // 删除版本信息文件
@@ -1899,6 +4712,12 @@ impl StorageManager {
                     .await
                     .map_err(StorageError::Io)?;
             }
+            let compressed_path = self.get_compressed_version_path(&version.version_id);
+            if compressed_path.exists() {
+                fs::remove_file(&compressed_path)
+                    .await
+                    .map_err(StorageError::Io)?;
+            }
 
             // 从 Sled 和缓存中移除版本信息
             let metadata_db = self.get_metadata_db()?;
@@ -1937,16 +4756,137 @@ impl StorageManager {
         self.save_file_index().await?;
         metadata_db.flush().await?;
 
+        self.notify_mutation(file_id, MutationOp::Deleted).await;
         info!("文件永久删除完成: {}", file_id);
         Ok(())
     }
 
-    /// 列出回收站中的文件
-    pub async fn list_deleted_files(&self) -> Result<Vec<FileIndexEntry>> {
-        let metadata_db = self.get_metadata_db()?;
-        let all_files = metadata_db.list_all_files()?;
+    /// 硬删除文件（跳过回收站，立即不可恢复）
+    ///
+    /// 用于合规擦除（如 GDPR "被遗忘权"）：永久删除所有版本后，立即回收
+    /// 引用计数归零的块，并清理缓存、重建副本和旧存储模式的残留文件；
+    /// 搜索索引等上层状态由 [`add_erase_hook`](Self::add_erase_hook) 注册的钩子同步清理。
+    /// 块只由持有 GC 租约的节点擦除，其他节点留给主节点的垃圾回收。
+    ///
+    /// # 返回
+    /// 返回被物理擦除的块数量。文件删除后任一块、残留文件或钩子清理失败时，
+    /// 其余步骤照常执行，最后返回汇总的错误。
+    pub async fn hard_delete_file(&self, file_id: &str) -> Result<usize> {
+        self.ensure_writable("硬删除文件")?;
+        self.ensure_global_id(file_id)?;
 
-        let deleted_files: Vec<FileIndexEntry> = all_files
+        info!("开始硬删除文件: {}", file_id);
+
+        // 1. 收集该文件引用的所有块（永久删除后 delta 将不可读）
+        let mut chunk_ids = std::collections::HashSet::new();
+        for version in self.list_file_versions(file_id).await? {
+            if let Ok(delta) = self.read_delta(file_id, &version.version_id).await {
+                chunk_ids.extend(delta_chunk_ids(&delta));
+            }
+        }
+
+        // 2. 永久删除所有版本和文件索引
+        self.permanently_delete_file(file_id).await?;
+
+        // 此后文件已不可恢复，各步骤的失败都记录下来，全部执行完后统一返回
+        let metadata_db = self.get_metadata_db()?;
+        let mut errors = Vec::new();
+
+        // 3. 立即擦除不再被任何文件引用的块（不等待定时 GC）
+        //
+        // 与垃圾回收一样只由持有 GC 租约的节点删除块；其他节点保留引用计数为 0 的记录，
+        // 由主节点的垃圾回收擦除。
+        let mut erased_chunks = Vec::new();
+        match self.acquire_gc_fence("硬删除文件").await {
+            Ok(mut fence) => {
+                for chunk_id in chunk_ids {
+                    if let Err(e) = self.check_gc_fence(&mut fence).await {
+                        warn!("硬删除期间失去 GC 租约，剩余块交由垃圾回收: {}", e);
+                        break;
+                    }
+                    // 持有块写入锁重新确认引用计数，并发保存可能刚复用了该块
+                    let guard = self.chunk_write_lock(&chunk_id).lock().await;
+                    let weak_hash = match metadata_db.get_chunk_ref(&chunk_id) {
+                        Ok(Some(chunk_ref)) if chunk_ref.ref_count > 0 => continue,
+                        Ok(Some(chunk_ref)) => chunk_ref.weak_hash,
+                        Ok(None) => 0,
+                        Err(e) => {
+                            errors.push(format!("读取块引用 {} 失败: {}", chunk_id, e));
+                            continue;
+                        }
+                    };
+                    if let Err(e) = self.chunk_store.delete(&chunk_id).await {
+                        errors.push(format!("删除块 {} 失败: {}", chunk_id, e));
+                        continue;
+                    }
+                    if let Err(e) = metadata_db.remove_chunk_ref(&chunk_id) {
+                        errors.push(format!("移除块引用 {} 失败: {}", chunk_id, e));
+                    }
+                    drop(guard);
+                    self.forget_chunk(&chunk_id, weak_hash).await;
+                    erased_chunks.push(chunk_id);
+                }
+            }
+            Err(e) => info!("{}，文件 {} 的块交由主节点的垃圾回收擦除", e, file_id),
+        }
+
+        // 4. 清理缓存
+        self.cache_manager.remove_file_metadata(file_id).await;
+        self.cache_manager.remove_hot_data(file_id).await;
+
+        // 5. 清理重建副本和旧存储模式的残留文件
+        let residual_paths = [
+            self.get_full_path(file_id),
+            self.get_hot_storage_path(file_id),
+            self.data_root.join(format!("{}.compressed", file_id)),
+        ];
+        for path in residual_paths {
+            if path.is_file()
+                && let Err(e) = fs::remove_file(&path).await
+            {
+                errors.push(format!("删除残留文件 {} 失败: {}", path.display(), e));
+            }
+        }
+
+        if let Err(e) = metadata_db.flush().await {
+            errors.push(format!("刷新数据库失败: {}", e));
+        }
+
+        // 6. 清理上层状态（搜索索引等）
+        let hooks = self
+            .erase_hooks
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        for hook in hooks {
+            if let Err(e) = hook.erase(file_id).await {
+                errors.push(format!("硬删除钩子执行失败: {}", e));
+            }
+        }
+
+        if !errors.is_empty() {
+            warn!("文件硬删除未完成: {} - {}", file_id, errors.join("; "));
+            return Err(StorageError::Storage(format!(
+                "硬删除文件 {} 未完成: {}",
+                file_id,
+                errors.join("; ")
+            )));
+        }
+
+        info!(
+            "文件硬删除完成: {}，擦除了 {} 个块",
+            file_id,
+            erased_chunks.len()
+        );
+        Ok(erased_chunks.len())
+    }
+
+    /// 列出回收站中的文件
+    pub async fn list_deleted_files(&self) -> Result<Vec<FileIndexEntry>> {
+        let metadata_db = self.get_metadata_db()?;
+        let all_files = metadata_db.list_all_files()?;
+
+        let deleted_files: Vec<FileIndexEntry> = all_files
             .into_iter()
             .filter(|entry| entry.is_deleted)
             .collect();
@@ -1957,6 +4897,9 @@ impl StorageManager {
 
     /// 恢复文件（从回收站恢复）
     pub async fn restore_file(&self, file_id: &str) -> Result<()> {
+        self.ensure_writable("恢复文件")?;
+        self.ensure_global_id(file_id)?;
+
         info!("恢复文件: {}", file_id);
 
         let metadata_db = self.get_metadata_db()?;
@@ -1984,19 +4927,24 @@ impl StorageManager {
         // 5. 持久化
         metadata_db.flush().await?;
 
+        self.notify_mutation(file_id, MutationOp::Created).await;
         info!("文件已恢复: {}", file_id);
         Ok(())
     }
 
     /// 清空回收站（永久删除所有已删除的文件）
     pub async fn empty_recycle_bin(&self) -> Result<usize> {
+        self.ensure_writable("清空回收站")?;
+
         info!("开始清空回收站");
 
         let deleted_files = self.list_deleted_files().await?;
         let count = deleted_files.len();
 
+        // 回收站包含各命名空间内的文件
+        let storage = self.with_namespace_access();
         for file_entry in deleted_files {
-            if let Err(e) = self.permanently_delete_file(&file_entry.file_id).await {
+            if let Err(e) = storage.permanently_delete_file(&file_entry.file_id).await {
                 info!("永久删除文件 {} 失败: {}", file_entry.file_id, e);
             }
         }
@@ -2005,9 +4953,36 @@ impl StorageManager {
         Ok(count)
     }
 
+    /// 永久删除在回收站中超过保留期的文件，返回删除的文件数
+    pub async fn purge_expired_recycle_bin(&self, retention: chrono::Duration) -> Result<usize> {
+        self.ensure_writable("清理回收站")?;
+
+        let cutoff = self.clock.now_naive() - retention;
+        let storage = self.with_namespace_access();
+        let mut count = 0;
+        for file_entry in self.list_deleted_files().await? {
+            if file_entry
+                .deleted_at
+                .is_none_or(|deleted_at| deleted_at > cutoff)
+            {
+                continue;
+            }
+            match storage.permanently_delete_file(&file_entry.file_id).await {
+                Ok(()) => count += 1,
+                Err(e) => info!("永久删除文件 {} 失败: {}", file_entry.file_id, e),
+            }
+        }
+
+        info!("回收站过期清理完成，删除了 {} 个文件", count);
+        Ok(count)
+    }
+
     /// 垃圾回收（清理引用计数为 0 的块）
     /// 删除没有任何文件引用的块，释放存储空间（去重功能始终启用）
     pub async fn garbage_collect_blocks(&self) -> Result<usize> {
+        self.ensure_writable("垃圾回收")?;
+        let mut fence = self.acquire_gc_fence("垃圾回收").await?;
+
         info!("开始垃圾回收");
 
         // 从 Sled 获取所有块引用计数信息
@@ -2022,18 +4997,24 @@ impl StorageManager {
         let mut chunks_to_delete = Vec::new();
 
         // 阶段 1：收集需要删除的块并删除物理文件
+        let mut fenced = Ok(());
         for (chunk_id, chunk_ref) in all_chunks {
             if chunk_ref.ref_count == 0 {
-                // 删除物理块文件
-                let chunk_path = self.get_chunk_path(&chunk_id);
-                if chunk_path.exists() {
-                    if let Err(e) = fs::remove_file(&chunk_path).await {
-                        info!("删除块文件 {} 失败: {}", chunk_id, e);
-                    } else {
+                // 失去租约时停止删除，已删除的块仍需移除引用记录
+                if let Err(e) = self.check_gc_fence(&mut fence).await {
+                    fenced = Err(e);
+                    break;
+                }
+                // 删除物理块
+                match self.chunk_store.delete(&chunk_id).await {
+                    Ok(Some(_)) => {
                         info!("删除未引用的块文件: {}", chunk_id);
+                        self.forget_chunk(&chunk_id, chunk_ref.weak_hash).await;
                         deleted_count += 1;
                         chunks_to_delete.push(chunk_id);
                     }
+                    Ok(None) => {}
+                    Err(e) => info!("删除块文件 {} 失败: {}", chunk_id, e),
                 }
             }
         }
@@ -2044,6 +5025,13 @@ impl StorageManager {
                 info!("批量从 Sled 移除块引用记录失败: {}", e);
             }
         }
+        fenced?;
+
+        // 阶段 3：整理块存储，回收已删除块在包文件中占用的空间（会删除包文件，先续约）
+        self.renew_gc_fence(&mut fence).await?;
+        if let Err(e) = self.chunk_store.compact().await {
+            info!("整理块存储失败: {}", e);
+        }
 
         info!("垃圾回收完成，清理了 {} 个未引用的块", deleted_count);
         Ok(deleted_count)
@@ -2054,6 +5042,11 @@ impl StorageManager {
     /// 该方法会启动一个后台任务，定期执行垃圾回收
     /// 任务间隔由配置中的gc_interval_secs决定
     pub async fn start_gc_task(&self) {
+        if self.config.read_only {
+            warn!("只读模式下不启动GC后台任务");
+            return;
+        }
+
         // 先停止已有的任务
         self.stop_gc_task().await;
 
@@ -2062,14 +5055,20 @@ impl StorageManager {
 
         let storage = self.clone_for_gc();
         let interval_secs = self.config.gc_interval_secs;
+        let jitter = self.config.gc_jitter;
         let stop_flag = self.gc_stop_flag.clone();
+        let wake = self.gc_wake.clone();
 
         let handle = tokio::spawn(async move {
             info!("GC后台任务启动，间隔: {}秒", interval_secs);
 
             loop {
-                // 等待指定间隔
-                tokio::time::sleep(tokio::time::Duration::from_secs(interval_secs)).await;
+                // 等待指定间隔（叠加随机抖动，停止时被提前唤醒）
+                let delay = silent_nas_core::jittered(Duration::from_secs(interval_secs), jitter);
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = wake.notified() => {}
+                }
 
                 // 检查停止标志
                 if stop_flag.load(Ordering::Relaxed) {
@@ -2082,6 +5081,10 @@ impl StorageManager {
                 match storage.garbage_collect_blocks().await {
                     Ok(count) => {
                         info!("定时GC完成，清理了 {} 个未引用的块", count);
+                        storage.recompute_usage_after_gc().await;
+                    }
+                    Err(StorageError::NotLeader(_)) => {
+                        info!("当前节点不是 GC 主节点，跳过定时GC");
                     }
                     Err(e) => {
                         info!("定时GC执行失败: {}", e);
@@ -2099,11 +5102,12 @@ impl StorageManager {
     ///
     /// 该方法会停止正在运行的GC后台任务
     pub async fn stop_gc_task(&self) {
-        // 设置停止标志
+        // 设置停止标志，唤醒等待中的任务
         self.gc_stop_flag.store(true, Ordering::Relaxed);
 
         // 等待任务结束
         if let Some(handle) = self.gc_task_handle.write().await.take() {
+            self.gc_wake.notify_one();
             let _ = handle.await;
             info!("GC后台任务已停止");
         }
@@ -2134,6 +5138,7 @@ impl StorageManager {
             config: self.config.clone(),
             version_root: self.version_root.clone(),
             chunk_root: self.chunk_root.clone(),
+            chunk_store: self.chunk_store.clone(),
             chunk_size: self.chunk_size,
             metadata_db: self.metadata_db.clone(),
             version_cache: self.version_cache.clone(),
@@ -2144,11 +5149,29 @@ impl StorageManager {
             orphan_cleaner: self.orphan_cleaner.clone(),
             compressor: self.compressor.clone(),
             chunk_bloom_filter: self.chunk_bloom_filter.clone(),
+            dedup_index: self.dedup_index.clone(),
             gc_task_handle: Arc::new(RwLock::new(None)),
             gc_stop_flag: self.gc_stop_flag.clone(),
+            gc_wake: self.gc_wake.clone(),
             optimization_scheduler: self.optimization_scheduler.clone(),
             optimization_task_handle: Arc::new(RwLock::new(None)),
             optimization_stop_flag: self.optimization_stop_flag.clone(),
+            clock: self.clock.clone(),
+            mutation_tx: self.mutation_tx.clone(),
+            erase_hooks: self.erase_hooks.clone(),
+            // 后台任务遍历全部文件，包括各命名空间内的文件
+            namespace_access: true,
+            file_locks: self.file_locks.clone(),
+            chunk_write_locks: self.chunk_write_locks.clone(),
+            gc_lease: self.gc_lease.clone(),
+            chain_compactions: self.chain_compactions.clone(),
+            dedup_rebuild_lock: self.dedup_rebuild_lock.clone(),
+            maintenance_flag: self.maintenance_flag.clone(),
+            maintenance_resume: self.maintenance_resume.clone(),
+            corrupt_reads: self.corrupt_reads.clone(),
+            suspect_versions: self.suspect_versions.clone(),
+            #[cfg(test)]
+            version_lookups: self.version_lookups.clone(),
         }
     }
 
@@ -2161,6 +5184,10 @@ impl StorageManager {
     /// # 返回
     /// 返回新文件的元数据
     pub async fn move_file(&self, old_file_id: &str, new_file_id: &str) -> Result<FileMetadata> {
+        self.ensure_writable("移动文件")?;
+        self.ensure_global_id(old_file_id)?;
+        self.ensure_global_id(new_file_id)?;
+
         info!("开始移动文件: {} -> {}", old_file_id, new_file_id);
 
         // 1. 检查目标文件是否已存在
@@ -2173,6 +5200,9 @@ impl StorageManager {
 
         // 2. 获取源文件的元数据
         let old_metadata = self.get_metadata(old_file_id).await?;
+        if let Some(entry) = self.get_metadata_db()?.get_file_index(old_file_id)? {
+            self.ensure_not_retained(&entry, "移动文件")?;
+        }
 
         // 3. 获取源文件的所有版本
         let versions = self.list_file_versions(old_file_id).await?;
@@ -2215,6 +5245,10 @@ impl StorageManager {
 
                 delta.file_id = new_file_id.to_string();
 
+                let chunk_ids = delta_chunk_ids(&delta);
+                metadata_db.remove_chunk_referrers(old_file_id, &version.version_id, &chunk_ids)?;
+                metadata_db.add_chunk_referrers(new_file_id, &version.version_id, &chunk_ids)?;
+
                 let updated_delta_data = serde_json::to_vec_pretty(&delta)
                     .map_err(|e| StorageError::Storage(format!("序列化 delta 失败: {}", e)))?;
 
@@ -2284,14 +5318,132 @@ impl StorageManager {
             hash: old_metadata.hash,
             created_at: old_metadata.created_at,
             modified_at: Local::now().naive_local(),
+            user_metadata: old_metadata.user_metadata,
         };
 
+        self.notify_mutation(
+            old_file_id,
+            MutationOp::Moved {
+                to: new_file_id.to_string(),
+            },
+        )
+        .await;
         info!("文件移动完成: {} -> {}", old_file_id, new_file_id);
         Ok(new_metadata)
     }
 
+    /// 移动目录前缀下的所有文件（如 WebDAV 对集合的 MOVE）
+    ///
+    /// `old_prefix` / `new_prefix` 按目录处理（`docs` 与 `docs/` 等价），
+    /// 嵌套子目录中的文件一并移动。目标前缀下已有文件、或两个前缀互相包含时拒绝移动；
+    /// 回收站中的文件不占用目标路径，与移动后的文件同名的会在移动前永久删除。
+    /// 逐个调用 [`Self::move_file`]，任一文件失败时按相反顺序移回已移动的文件，
+    /// 整体要么全部完成要么保持原状。回收站中的文件不参与移动。
+    ///
+    /// # 返回
+    /// 返回移动后各文件的元数据
+    pub async fn move_prefix(
+        &self,
+        old_prefix: &str,
+        new_prefix: &str,
+    ) -> Result<Vec<FileMetadata>> {
+        self.ensure_writable("移动目录")?;
+
+        let old_dir = format!("{}/", old_prefix.trim_end_matches('/'));
+        let new_dir = format!("{}/", new_prefix.trim_end_matches('/'));
+        if old_dir == "/" || new_dir == "/" {
+            return Err(StorageError::Storage("不能移动根目录".to_string()));
+        }
+        self.ensure_global_id(&old_dir)?;
+        self.ensure_global_id(&new_dir)?;
+        if new_dir.starts_with(&old_dir) || old_dir.starts_with(&new_dir) {
+            return Err(StorageError::Storage(format!(
+                "源目录与目标目录重叠: {} -> {}",
+                old_prefix, new_prefix
+            )));
+        }
+
+        let entries = self.get_metadata_db()?.list_all_files()?;
+        if let Some(existing) = entries.iter().find(|entry| {
+            !entry.is_deleted
+                && (entry.file_id.starts_with(&new_dir)
+                    || entry.file_id == new_dir.trim_end_matches('/'))
+        }) {
+            return Err(StorageError::Storage(format!(
+                "目标目录已存在文件: {}",
+                existing.file_id
+            )));
+        }
+        let mut sources: Vec<String> = entries
+            .iter()
+            .filter(|entry| !entry.is_deleted && entry.file_id.starts_with(&old_dir))
+            .map(|entry| entry.file_id.clone())
+            .collect();
+        if sources.is_empty() {
+            return Err(StorageError::FileNotFound(old_prefix.to_string()));
+        }
+        sources.sort();
+
+        // 回收站中与目标同名的文件让出路径，否则其版本会混入移动后的文件
+        let targets: HashSet<String> = sources
+            .iter()
+            .map(|file_id| format!("{}{}", new_dir, &file_id[old_dir.len()..]))
+            .collect();
+        for entry in entries
+            .iter()
+            .filter(|entry| entry.is_deleted && targets.contains(&entry.file_id))
+        {
+            self.permanently_delete_file(&entry.file_id).await?;
+        }
+
+        info!(
+            "开始移动目录: {} -> {}（{} 个文件）",
+            old_prefix,
+            new_prefix,
+            sources.len()
+        );
+
+        let mut moved: Vec<(String, String)> = Vec::with_capacity(sources.len());
+        let mut results = Vec::with_capacity(sources.len());
+        for old_file_id in sources {
+            let new_file_id = format!("{}{}", new_dir, &old_file_id[old_dir.len()..]);
+            match self.move_file(&old_file_id, &new_file_id).await {
+                Ok(metadata) => {
+                    moved.push((old_file_id, new_file_id));
+                    results.push(metadata);
+                }
+                Err(e) => {
+                    warn!(
+                        "移动目录失败，回滚已移动的 {} 个文件: {} -> {}: {}",
+                        moved.len(),
+                        old_file_id,
+                        new_file_id,
+                        e
+                    );
+                    for (from, to) in moved.iter().rev() {
+                        if let Err(rollback_err) = self.move_file(to, from).await {
+                            warn!("回滚文件移动失败: {} -> {}: {}", to, from, rollback_err);
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        info!(
+            "目录移动完成: {} -> {}（{} 个文件）",
+            old_prefix,
+            new_prefix,
+            results.len()
+        );
+        Ok(results)
+    }
+
     /// 垃圾回收 - 清理引用计数为0的块
     pub async fn garbage_collect(&self) -> Result<GarbageCollectResult> {
+        self.ensure_writable("垃圾回收")?;
+        let mut fence = self.acquire_gc_fence("垃圾回收").await?;
+
         info!("开始垃圾回收...");
 
         let mut orphaned_chunks = 0;
@@ -2305,45 +5457,36 @@ impl StorageManager {
             .list_orphaned_chunks()
             .map_err(|e| StorageError::Storage(format!("列出孤立块失败: {}", e)))?;
 
-        // 删除这些块
+        // 删除这些块（失去租约时停止，已删除的部分照常刷新）
+        let mut fenced = Ok(());
         for chunk_id in orphaned_chunk_ids {
-            // 从 Sled 获取块信息
-            if let Ok(Some(entry)) = metadata_db.get_chunk_ref(&chunk_id) {
-                if entry.path.exists() {
-                    match fs::metadata(&entry.path).await {
-                        Ok(metadata) => {
-                            reclaimed_space += metadata.len();
-                            match fs::remove_file(&entry.path).await {
-                                Ok(_) => {
-                                    orphaned_chunks += 1;
-                                    // 从 Sled 移除
-                                    if let Err(e) = metadata_db.remove_chunk_ref(&chunk_id) {
-                                        errors.push(format!(
-                                            "从 Sled 移除块 {} 失败: {}",
-                                            chunk_id, e
-                                        ));
-                                    }
-                                    // 从缓存中移除
-                                    self.block_cache.invalidate(&chunk_id).await;
-                                }
-                                Err(e) => {
-                                    errors.push(format!("删除块 {} 失败: {}", chunk_id, e));
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            errors.push(format!("获取块 {} 元数据失败: {}", chunk_id, e));
-                        }
-                    }
-                } else {
-                    // 块文件不存在，直接从索引中移除
-                    if let Err(e) = metadata_db.remove_chunk_ref(&chunk_id) {
-                        errors.push(format!("从 Sled 移除块 {} 失败: {}", chunk_id, e));
-                    }
-                    // 从缓存中移除
-                    self.block_cache.invalidate(&chunk_id).await;
+            if let Err(e) = self.check_gc_fence(&mut fence).await {
+                fenced = Err(e);
+                break;
+            }
+            // 从块存储删除，块不存在时直接从索引中移除
+            let deleted = match self.chunk_store.delete(&chunk_id).await {
+                Ok(freed) => freed,
+                Err(e) => {
+                    errors.push(format!("删除块 {} 失败: {}", chunk_id, e));
+                    continue;
                 }
+            };
+            if let Some(freed) = deleted {
+                reclaimed_space += freed;
+                orphaned_chunks += 1;
+            }
+            // 从 Sled 移除
+            let weak_hash = metadata_db
+                .get_chunk_ref(&chunk_id)
+                .ok()
+                .flatten()
+                .map_or(0, |chunk_ref| chunk_ref.weak_hash);
+            if let Err(e) = metadata_db.remove_chunk_ref(&chunk_id) {
+                errors.push(format!("从 Sled 移除块 {} 失败: {}", chunk_id, e));
             }
+            // 从缓存与去重索引中移除
+            self.forget_chunk(&chunk_id, weak_hash).await;
         }
 
         // 刷新数据库
@@ -2352,6 +5495,13 @@ impl StorageManager {
         {
             errors.push(format!("刷新数据库失败: {}", e));
         }
+        fenced?;
+
+        // 整理块存储（包文件中已删除块的空间在此实际释放，删除时已计入 reclaimed_space）
+        self.renew_gc_fence(&mut fence).await?;
+        if let Err(e) = self.chunk_store.compact().await {
+            errors.push(format!("整理块存储失败: {}", e));
+        }
 
         info!(
             "垃圾回收完成: 清理了 {} 个孤立块，回收了 {} 字节空间",
@@ -2367,6 +5517,7 @@ impl StorageManager {
 
     /// 获取文件信息（不读取内容）
     pub async fn get_file_info(&self, file_id: &str) -> Result<FileIndexEntry> {
+        self.ensure_global_id(file_id)?;
         let metadata_db = self.get_metadata_db()?;
         metadata_db
             .get_file_index(file_id)
@@ -2374,20 +5525,359 @@ impl StorageManager {
             .ok_or_else(|| StorageError::FileNotFound(file_id.to_string()))
     }
 
+    /// 获取文件状态（只查一次文件索引，不读取版本记录）
+    ///
+    /// 已删除的文件返回 `FileNotFound`。文件索引未记录大小和哈希的旧数据
+    /// （如由版本记录重建的索引）回退到读取当前版本记录。
+    pub async fn stat(&self, file_id: &str) -> Result<FileStat> {
+        let entry = self.get_file_info(file_id).await?;
+        if entry.is_deleted {
+            return Err(StorageError::FileNotFound(file_id.to_string()));
+        }
+        let alias_target = entry.alias_target.clone();
+        let entry = self.resolve_alias(entry)?;
+
+        let size = if entry.file_hash.is_empty() {
+            self.get_version_info(&entry.latest_version_id)
+                .await?
+                .file_size
+        } else {
+            entry.file_size
+        };
+
+        Ok(FileStat {
+            file_id: file_id.to_string(),
+            size,
+            hash: entry.file_hash,
+            version_id: entry.latest_version_id,
+            created_at: entry.created_at,
+            modified_at: entry.modified_at,
+            storage_mode: entry.storage_mode,
+            version_count: entry.version_count,
+            user_metadata: entry.user_metadata,
+            alias_target,
+        })
+    }
+
+    /// 创建别名：`alias_id` 指向 `target_id`，不复制数据
+    ///
+    /// 与复制不同，读取别名时总是解析到目标文件的当前版本，目标更新后通过别名可见；
+    /// 写入别名等同于写入目标文件。指向别名的别名直接指向其最终目标，不形成链。
+    /// 目标被删除后别名保留，读取返回 `StorageError::DanglingAlias`；
+    /// 删除别名只删除别名本身。`alias_id` 已是未删除的文件时返回 `PreconditionFailed`。
+    pub async fn create_alias(&self, alias_id: &str, target_id: &str) -> Result<FileStat> {
+        self.ensure_writable("创建别名")?;
+
+        let metadata_db = self.get_metadata_db()?;
+        let target = metadata_db
+            .get_file_index(target_id)?
+            .filter(|entry| !entry.is_deleted)
+            .ok_or_else(|| StorageError::FileNotFound(target_id.to_string()))?;
+        let target_id = target.alias_target.unwrap_or(target.file_id);
+        if target_id == alias_id {
+            return Err(StorageError::Config(format!(
+                "别名不能指向自身: {}",
+                alias_id
+            )));
+        }
+        if metadata_db
+            .get_file_index(alias_id)?
+            .is_some_and(|entry| !entry.is_deleted)
+        {
+            return Err(StorageError::PreconditionFailed(format!(
+                "文件已存在: {}",
+                alias_id
+            )));
+        }
+
+        let now = self.clock.now_naive();
+        let entry = FileIndexEntry {
+            file_id: alias_id.to_string(),
+            latest_version_id: String::new(),
+            version_count: 0,
+            created_at: now,
+            modified_at: now,
+            is_deleted: false,
+            deleted_at: None,
+            storage_mode: crate::StorageMode::Chunked,
+            optimization_status: crate::OptimizationStatus::Completed,
+            file_size: 0,
+            file_hash: String::new(),
+            user_metadata: HashMap::new(),
+            optimization_strategy: None,
+            optimized_at: None,
+            space_saved: 0,
+            stored_size: 0,
+            attributed_size: 0,
+            alias_target: Some(target_id.clone()),
+            compression: None,
+            retention: None,
+        };
+        metadata_db.put_file_index(alias_id, &entry)?;
+        metadata_db.flush().await?;
+
+        self.notify_mutation(alias_id, MutationOp::Created).await;
+        info!("创建别名: {} -> {}", alias_id, target_id);
+        self.stat(alias_id).await
+    }
+
+    /// 将别名的索引条目解析为目标文件的索引条目，普通文件原样返回
+    ///
+    /// 目标已不存在或在回收站中时返回 `StorageError::DanglingAlias`。
+    fn resolve_alias(&self, entry: FileIndexEntry) -> Result<FileIndexEntry> {
+        let Some(target_id) = entry.alias_target.as_deref() else {
+            return Ok(entry);
+        };
+        self.get_metadata_db()?
+            .get_file_index(target_id)?
+            .filter(|target| !target.is_deleted)
+            .ok_or_else(|| {
+                StorageError::DanglingAlias(format!("{} -> {}", entry.file_id, target_id))
+            })
+    }
+
+    /// 写入的实际文件ID：未删除的别名解析为其目标，其余原样返回
+    fn resolve_write_target(&self, file_id: &str) -> Result<String> {
+        self.ensure_global_id(file_id)?;
+        match self.get_metadata_db()?.get_file_index(file_id)? {
+            Some(entry) if !entry.is_deleted && entry.alias_target.is_some() => {
+                Ok(self.resolve_alias(entry)?.file_id)
+            }
+            _ => Ok(file_id.to_string()),
+        }
+    }
+
+    /// 文件当前内容的 SHA-256
+    ///
+    /// 文件索引未记录哈希的旧数据读取一次当前版本内容计算哈希，
+    /// 并回写到文件索引（只读模式下只计算不回写）。
+    pub async fn content_hash(&self, file_id: &str) -> Result<String> {
+        let stat = self.stat(file_id).await?;
+        if !stat.hash.is_empty() {
+            return Ok(stat.hash);
+        }
+
+        let data = self.read_version_data_consistent(&stat.version_id).await?;
+        let hash = self.calculate_hash(&data);
+        if !self.config.read_only {
+            let metadata_db = self.get_metadata_db()?;
+            if let Some(mut entry) = metadata_db.get_file_index(file_id)?
+                && entry.latest_version_id == stat.version_id
+            {
+                entry.file_hash = hash.clone();
+                entry.file_size = data.len() as u64;
+                metadata_db.put_file_index(file_id, &entry)?;
+            }
+        }
+        Ok(hash)
+    }
+
+    /// 按内容 SHA-256 查找已存在的文件，供客户端秒传前询问
+    ///
+    /// 与秒传一样只凭哈希即可探测内容是否存在，因此受 [`IncrementalConfig::instant_upload`]
+    /// 约束：秒传禁用时总是返回 `None`，启用时按全局文件的查找范围返回
+    /// [`Self::lookup_by_content_hash`] 命中的第一个文件，命名空间内的文件不计入。
+    pub async fn content_exists(&self, sha256: &str) -> Result<Option<ContentRef>> {
+        if self.config.instant_upload == crate::InstantUploadScope::Disabled {
+            return Ok(None);
+        }
+        Ok(self
+            .lookup_by_content_hash(sha256)
+            .await?
+            .into_iter()
+            .find(|found| {
+                !crate::namespace::is_namespaced(&found.file_id)
+                    && self.instant_upload_visible("", &found.file_id)
+            }))
+    }
+
+    /// 按整文件 SHA-256 查找当前内容与之相同的所有文件
+    ///
+    /// 基于保存与优化时随文件索引维护的内容哈希索引，按文件 ID 排序返回（索引键即按此排序），
+    /// 版本为文件当前内容所在的最新版本。已删除或内容已变化的条目跳过并顺带清理。
+    pub async fn lookup_by_content_hash(&self, sha256: &str) -> Result<Vec<ContentRef>> {
+        let file_hash = sha256.to_ascii_lowercase();
+        let metadata_db = self.get_metadata_db()?;
+
+        let mut found = Vec::new();
+        for candidate in metadata_db.files_with_content_hash(&file_hash)? {
+            let Some(entry) = metadata_db
+                .get_file_index(&candidate)?
+                .filter(|entry| entry.file_hash == file_hash)
+            else {
+                if !self.config.read_only {
+                    metadata_db.remove_content_hash(&file_hash, &candidate)?;
+                }
+                continue;
+            };
+            if entry.is_deleted {
+                continue;
+            }
+            found.push(ContentRef {
+                file_id: entry.file_id,
+                version_id: entry.latest_version_id,
+                size: entry.file_size,
+            });
+        }
+        Ok(found)
+    }
+
+    /// 文件的实体标签（ETag，含双引号）
+    ///
+    /// 取自当前内容的 SHA-256，HTTP、S3、WebDAV 的下载接口共用，
+    /// 同一文件在各协议下得到相同的 ETag，内容不变时 ETag 不随版本变化。
+    pub async fn entity_tag(&self, file_id: &str) -> Result<String> {
+        Ok(format!("\"{}\"", self.content_hash(file_id).await?))
+    }
+
+    /// 仅更新文件的修改时间，不创建新版本和块
+    ///
+    /// 用于同步客户端在内容未变化时同步时间戳（如 WebDAV 设置 getlastmodified）
+    pub async fn touch(
+        &self,
+        file_id: &str,
+        modified_at: chrono::NaiveDateTime,
+    ) -> Result<FileIndexEntry> {
+        self.ensure_writable("更新修改时间")?;
+        self.ensure_global_id(file_id)?;
+
+        let metadata_db = self.get_metadata_db()?;
+        let mut file_entry = metadata_db
+            .get_file_index(file_id)?
+            .filter(|entry| !entry.is_deleted)
+            .ok_or_else(|| StorageError::FileNotFound(file_id.to_string()))?;
+
+        file_entry.modified_at = modified_at;
+        metadata_db.put_file_index(file_id, &file_entry)?;
+        metadata_db.flush().await?;
+        self.notify_mutation(file_id, MutationOp::Updated).await;
+
+        Ok(file_entry)
+    }
+
+    /// 覆盖保存文件的用户自定义元数据
+    ///
+    /// 元数据记录在文件索引中，新版本与移动都会保留；
+    /// 总大小超过 [`MAX_USER_METADATA_SIZE`] 时返回错误。
+    pub async fn set_user_metadata(
+        &self,
+        file_id: &str,
+        user_metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        self.ensure_writable("更新用户元数据")?;
+        self.ensure_global_id(file_id)?;
+
+        let size: usize = user_metadata.iter().map(|(k, v)| k.len() + v.len()).sum();
+        if size > MAX_USER_METADATA_SIZE {
+            return Err(StorageError::Storage(format!(
+                "用户元数据过大: {} 字节（上限 {} 字节）",
+                size, MAX_USER_METADATA_SIZE
+            )));
+        }
+
+        let metadata_db = self.get_metadata_db()?;
+        let mut file_entry = metadata_db
+            .get_file_index(file_id)?
+            .filter(|entry| !entry.is_deleted)
+            .ok_or_else(|| StorageError::FileNotFound(file_id.to_string()))?;
+
+        file_entry.user_metadata = user_metadata;
+        metadata_db.put_file_index(file_id, &file_entry)?;
+        metadata_db.flush().await?;
+        self.notify_mutation(file_id, MutationOp::Updated).await;
+        Ok(())
+    }
+
+    // ============ 死属性（WebDAV 自定义属性）============
+
+    /// 获取资源的死属性，以资源路径为键（文件与目录均可）
+    pub async fn get_dead_properties(&self, path: &str) -> Result<HashMap<String, String>> {
+        self.get_metadata_db()?.get_dead_props(path)
+    }
+
+    /// 覆盖保存资源的死属性（属性表为空时删除）
+    pub async fn set_dead_properties(
+        &self,
+        path: &str,
+        props: &HashMap<String, String>,
+    ) -> Result<()> {
+        self.ensure_writable("保存死属性")?;
+        self.get_metadata_db()?.put_dead_props(path, props)
+    }
+
+    /// 删除资源及其子资源的死属性
+    pub async fn remove_dead_properties(&self, path: &str) -> Result<usize> {
+        self.ensure_writable("删除死属性")?;
+        self.get_metadata_db()?.remove_dead_props(path)
+    }
+
+    /// 随资源移动死属性（包括子资源）
+    pub async fn move_dead_properties(&self, from: &str, to: &str) -> Result<usize> {
+        self.ensure_writable("移动死属性")?;
+        self.get_metadata_db()?.copy_dead_props(from, to, true)
+    }
+
+    /// 随资源复制死属性（包括子资源）
+    pub async fn copy_dead_properties(&self, from: &str, to: &str) -> Result<usize> {
+        self.ensure_writable("复制死属性")?;
+        self.get_metadata_db()?.copy_dead_props(from, to, false)
+    }
+
     // ============ Phase 5 Step 4: 可靠性增强 API ============
 
     /// 验证所有 chunks 的完整性
     pub async fn verify_all_chunks(&self) -> Result<crate::ChunkVerifyReport> {
+        self.verify_all_chunks_with(
+            &crate::reliability::ScanOptions::default(),
+            &crate::reliability::ScanHandle::new(),
+        )
+        .await
+    }
+
+    /// 按选项验证所有 chunks 的完整性（可限制并发、查询进度和取消）
+    pub async fn verify_all_chunks_with(
+        &self,
+        options: &crate::reliability::ScanOptions,
+        handle: &crate::reliability::ScanHandle,
+    ) -> Result<crate::ChunkVerifyReport> {
+        let expected = self.expected_chunks().await?;
         self.chunk_verifier
-            .scan_and_verify()
+            .scan_and_verify_expected(options, handle, &expected)
             .await
             .map_err(|e| StorageError::Storage(format!("验证 chunks 失败: {}", e)))
     }
 
+    /// 从版本记录收集每个块的强哈希与压缩算法
+    ///
+    /// 加盐块的 ID 不是块数据的 SHA-256，压缩块存储的是压缩后的字节，
+    /// 校验时都需按记录的强哈希比较。未被任何版本引用或没有记录强哈希的块按 ID 校验。
+    async fn expected_chunks(&self) -> Result<HashMap<String, crate::ExpectedChunk>> {
+        let metadata_db = self.get_metadata_db()?;
+        let mut expected = HashMap::new();
+        for version in metadata_db.iter_versions_after(None) {
+            let version = version?;
+            // 差异数据缺失的版本不引用任何块
+            if let Ok(delta) = self.read_delta(&version.file_id, &version.version_id).await {
+                for chunk in delta.chunks {
+                    if !chunk.strong_hash.is_empty() {
+                        expected
+                            .entry(chunk.chunk_id)
+                            .or_insert_with(|| crate::ExpectedChunk {
+                                strong_hash: chunk.strong_hash,
+                                compression: chunk.compression,
+                            });
+                    }
+                }
+            }
+        }
+        Ok(expected)
+    }
+
     /// 验证指定 chunks 的完整性
     pub async fn verify_chunks(&self, chunk_hashes: &[String]) -> Result<crate::ChunkVerifyReport> {
+        let expected = self.expected_chunks().await?;
         self.chunk_verifier
-            .verify_chunks(chunk_hashes)
+            .verify_chunks_expected(chunk_hashes, &expected)
             .await
             .map_err(|e| StorageError::Storage(format!("验证 chunks 失败: {}", e)))
     }
@@ -2416,10 +5906,37 @@ impl StorageManager {
         &self,
         orphan_hashes: &[String],
     ) -> Result<crate::CleanupReport> {
-        self.orphan_cleaner
-            .clean_orphans(orphan_hashes)
-            .await
-            .map_err(|e| StorageError::Storage(format!("清理孤儿 chunks 失败: {}", e)))
+        self.ensure_writable("清理孤儿块")?;
+        let mut fence = self.acquire_gc_fence("清理孤儿块").await?;
+
+        // 分批删除，每批前续约并确认仍持有租约
+        let mut report = crate::CleanupReport {
+            total: orphan_hashes.len(),
+            deleted: 0,
+            failed: 0,
+            freed_space: 0,
+            failed_chunks: Vec::new(),
+        };
+        for batch in orphan_hashes.chunks(GC_FENCE_BATCH) {
+            self.renew_gc_fence(&mut fence).await?;
+            let batch_report = self
+                .orphan_cleaner
+                .clean_orphans(batch)
+                .await
+                .map_err(|e| StorageError::Storage(format!("清理孤儿 chunks 失败: {}", e)))?;
+            // 孤儿块没有引用记录、弱哈希未知，按强哈希从去重索引中移除
+            let deleted: HashSet<&str> = batch
+                .iter()
+                .map(String::as_str)
+                .filter(|id| !batch_report.failed_chunks.iter().any(|f| f == id))
+                .collect();
+            self.dedup_index.remove_by_strong_hash(&deleted).await;
+            report.deleted += batch_report.deleted;
+            report.failed += batch_report.failed;
+            report.freed_space += batch_report.freed_space;
+            report.failed_chunks.extend(batch_report.failed_chunks);
+        }
+        Ok(report)
     }
 
     /// 执行优化任务 - 将热存储文件优化为冷存储
@@ -2427,6 +5944,8 @@ impl StorageManager {
         &self,
         task: &mut crate::OptimizationTask,
     ) -> Result<(u64, u64)> {
+        self.ensure_writable("存储优化")?;
+
         info!(
             "开始执行优化任务: file_id={}, strategy={:?}",
             task.file_id, task.strategy
@@ -2468,24 +5987,13 @@ impl StorageManager {
             return Ok((0, 0));
         }
 
-        // 压缩数据
-        let (compressed, _compression_algo) = if self.config.enable_compression {
-            let algorithm = match self.config.compression_algorithm.as_str() {
-                "lz4" => crate::core::CompressionAlgorithm::LZ4,
-                "zstd" => crate::core::CompressionAlgorithm::Zstd,
-                _ => crate::core::CompressionAlgorithm::LZ4,
-            };
-            let compression_config = crate::core::compression::CompressionConfig {
-                algorithm,
-                level: 1,
-                min_size: 0, // 已经检查过是否需要压缩
-                ..Default::default()
-            };
-            let compressor = crate::core::compression::Compressor::new(compression_config);
-            let result = compressor.compress(&data)?;
-            (result.compressed_data, result.algorithm)
-        } else {
-            (data.clone(), crate::core::CompressionAlgorithm::None)
+        // 压缩数据（配置了分段压缩规则时按文件大小选择算法）
+        let (compressed, compression_algo) = match self.whole_file_compressor(original_size) {
+            Some(compressor) => {
+                let result = compressor.compress(&data)?;
+                (result.compressed_data, result.algorithm)
+            }
+            None => (data.clone(), crate::core::CompressionAlgorithm::None),
         };
 
         let compressed_size = compressed.len() as u64;
@@ -2500,11 +6008,29 @@ impl StorageManager {
             .await
             .map_err(StorageError::Io)?;
 
+        // 校验通过之前不切换存储形式，保留热存储文件
+        if self.config.verify_optimization {
+            let verified = match fs::read(&compressed_path).await {
+                Ok(stored) => self
+                    .decompress_chunk(stored, compression_algo)
+                    .and_then(|restored| self.check_optimized_hash(task, &data, &restored)),
+                Err(e) => Err(StorageError::Io(e)),
+            };
+            if let Err(e) = verified {
+                let _ = fs::remove_file(&compressed_path).await;
+                task.mark_failed(e.to_string());
+                return Err(e);
+            }
+        }
+
         // 更新文件索引
         self.update_file_index_after_optimization(
             &task.file_id,
             crate::StorageMode::Compressed,
+            crate::OptimizationStrategy::CompressOnly,
+            Some(compression_algo),
             compressed_size,
+            space_saved,
         )
         .await?;
 
@@ -2545,9 +6071,10 @@ impl StorageManager {
         // 2. 使用Delta生成器进行CDC分块
         let mut generator =
             crate::core::delta::DeltaGenerator::new(self.chunk_size, adjusted_config);
-        let delta = generator
+        let mut delta = generator
             .generate_full_delta(&data, &task.file_id)
             .map_err(|e| StorageError::Storage(format!("生成分块失败: {}", e)))?;
+        self.apply_chunk_salt(&task.file_id, &data, &mut delta.chunks);
 
         // 3. 保存所有chunks并进行去重，同时更新compression字段
         let mut dedup_stats = crate::DeduplicationStats {
@@ -2556,8 +6083,13 @@ impl StorageManager {
             ..Default::default()
         };
 
+        // 配置了分段压缩规则时按文件大小选择算法
+        let banded_compressor = self.banded_chunk_compressor(original_size);
+        let compressor = banded_compressor.as_ref().unwrap_or(&self.compressor);
+
         // 创建新的chunks向量，更新compression字段
         let mut updated_chunks = Vec::with_capacity(delta.chunks.len());
+        let mut written_chunk_ids = Vec::new();
         let metadata_db = self.get_metadata_db()?;
 
         for chunk in &delta.chunks {
@@ -2570,31 +6102,33 @@ impl StorageManager {
 
             // 统一策略：尝试写入块（基于文件系统去重）
             let (written, compression_algo) = self
-                .save_chunk_data(&chunk.chunk_id, chunk_data)
+                .save_chunk_data(compressor, &chunk.chunk_id, chunk.weak_hash, chunk_data)
                 .await?;
 
-            if written {
-                // 块是新写入的，初始化引用计数到 Sled
-                let chunk_path = self.get_chunk_path(&chunk.chunk_id);
-                metadata_db
-                    .put_chunk_ref(
-                        &chunk.chunk_id,
-                        &ChunkRefCount {
-                            chunk_id: chunk.chunk_id.clone(),
-                            ref_count: 1,
-                            size: chunk.size as u64,
-                            path: chunk_path,
-                        },
-                    )
-                    .map_err(|e| StorageError::Storage(format!("保存块引用计数失败: {}", e)))?;
+            // 登记引用计数：块已存在时累加，否则初始化（与并发写入者的登记顺序无关）
+            let chunk_path = self.chunk_store.location(&chunk.chunk_id);
+            metadata_db
+                .add_chunk_ref(
+                    &chunk.chunk_id,
+                    &ChunkRefCount {
+                        chunk_id: chunk.chunk_id.clone(),
+                        ref_count: 1,
+                        size: chunk.size as u64,
+                        path: chunk_path,
+                        weak_hash: chunk.weak_hash,
+                    },
+                )
+                .map_err(|e| StorageError::Storage(format!("登记块引用计数失败: {}", e)))?;
 
+            if written {
+                written_chunk_ids.push(chunk.chunk_id.clone());
                 dedup_stats.new_chunks += 1;
-                dedup_stats.stored_size += chunk.size as u64;
+                dedup_stats.stored_size += self
+                    .chunk_store
+                    .stored_size(&chunk.chunk_id)
+                    .await?
+                    .unwrap_or(chunk.size as u64);
             } else {
-                // 块已存在，增加引用计数
-                metadata_db
-                    .increment_chunk_ref(&chunk.chunk_id)
-                    .map_err(|e| StorageError::Storage(format!("增加块引用计数失败: {}", e)))?;
                 dedup_stats.duplicate_chunks += 1;
             }
 
@@ -2606,6 +6140,18 @@ impl StorageManager {
 
         dedup_stats.calculate_dedup_ratio();
 
+        // 校验：由刚写入的块重建文件，确认无误后才发布新的存储形式并删除热存储文件
+        if self.config.verify_optimization
+            && let Err(e) = self
+                .verify_optimized_chunks(task, &data, &updated_chunks)
+                .await
+        {
+            self.rollback_optimized_chunks(&updated_chunks, &written_chunk_ids)
+                .await;
+            task.mark_failed(e.to_string());
+            return Err(e);
+        }
+
         // 4. 获取现有的版本ID（从文件索引中）
         let metadata_db = self.get_metadata_db()?;
         let version_id = if let Some(file_entry) = metadata_db
@@ -2630,27 +6176,39 @@ impl StorageManager {
             chunks: updated_chunks,
             created_at: now,
         };
+        debug_assert!(
+            file_delta.is_contiguous(data.len()),
+            "块列表必须按偏移连续覆盖整个文件"
+        );
+
+        // 重新优化已分块的版本（如补写缺失的块）时，旧块列表的引用已登记过，需要释放
+        let old_chunk_ids = self
+            .read_delta(&task.file_id, &version_id)
+            .await
+            .map(|delta| delta_chunk_ids(&delta))
+            .unwrap_or_default();
 
         self.save_delta(&task.file_id, &file_delta).await?;
         self.save_version_info(&task.file_id, &file_delta, None)
             .await?;
-
-        // 6. 更新文件索引（重用已获取的metadata_db）
-        if let Some(mut file_entry) = metadata_db
-            .get_file_index(&task.file_id)
-            .map_err(|e| StorageError::Storage(format!("读取文件索引失败: {}", e)))?
-        {
-            file_entry.storage_mode = crate::StorageMode::Chunked;
-            file_entry.optimization_status = crate::OptimizationStatus::Completed;
+        if !old_chunk_ids.is_empty() {
             metadata_db
-                .put_file_index(&task.file_id, &file_entry)
-                .map_err(|e| StorageError::Storage(format!("保存文件索引失败: {}", e)))?;
+                .decrement_chunk_refs_batch(&old_chunk_ids)
+                .map_err(|e| StorageError::Storage(format!("批量减少块引用计数失败: {}", e)))?;
         }
 
-        // 计算节省的空间（原始大小 - 实际存储大小）
-        let stats = self.get_dedup_stats().await;
-        let stored_size = stats.stored_size;
+        // 6. 更新文件索引，节省的空间 = 原始大小 - 新写入块的实际存储大小
+        let stored_size = dedup_stats.stored_size;
         let space_saved = original_size.saturating_sub(stored_size);
+        self.update_file_index_after_optimization(
+            &task.file_id,
+            crate::StorageMode::Chunked,
+            crate::OptimizationStrategy::Full,
+            None,
+            stored_size,
+            space_saved,
+        )
+        .await?;
 
         // 清理热存储（优化完成后自动清理）
         let _ = fs::remove_file(&task.hot_path).await;
@@ -2658,18 +6216,95 @@ impl StorageManager {
         task.mark_completed();
         info!(
             "完整优化完成: file_id={}, 原始={}B, 存储={}B, 节省={}B, 去重率={:.2}%",
-            task.file_id, original_size, stored_size, space_saved, stats.dedup_ratio
+            task.file_id, original_size, stored_size, space_saved, dedup_stats.dedup_ratio
         );
 
         Ok((space_saved, stored_size))
     }
 
-    /// 更新文件索引（优化后）
+    /// 校验优化结果能还原出原始内容
+    ///
+    /// 按块列表读取并解压每个块，拼接结果必须与原始内容的哈希一致。
+    async fn verify_optimized_chunks(
+        &self,
+        task: &crate::OptimizationTask,
+        original: &[u8],
+        chunks: &[ChunkInfo],
+    ) -> Result<()> {
+        let mut restored = Vec::with_capacity(original.len());
+        for chunk in chunks {
+            let data = self.read_chunk(&chunk.chunk_id, chunk.compression).await?;
+            if data.len() != chunk.size {
+                return Err(StorageError::ChecksumMismatch(format!(
+                    "优化结果校验失败: 块 {} 大小不一致（期望 {}，实际 {}）",
+                    chunk.chunk_id,
+                    chunk.size,
+                    data.len()
+                )));
+            }
+            restored.extend_from_slice(&data);
+        }
+        self.check_optimized_hash(task, original, &restored)
+    }
+
+    /// 比对还原内容与任务记录的文件哈希（任务未记录哈希时与原始内容比对）
+    fn check_optimized_hash(
+        &self,
+        task: &crate::OptimizationTask,
+        original: &[u8],
+        restored: &[u8],
+    ) -> Result<()> {
+        let expected = if task.file_hash.is_empty() {
+            self.calculate_hash(original)
+        } else {
+            task.file_hash.clone()
+        };
+        let actual = self.calculate_hash(restored);
+        if actual != expected {
+            return Err(StorageError::ChecksumMismatch(format!(
+                "优化结果校验失败: {} 的还原内容哈希不一致（期望 {}，实际 {}）",
+                task.file_id, expected, actual
+            )));
+        }
+        Ok(())
+    }
+
+    /// 撤销校验失败的优化：释放登记的块引用，删除本次新写入且不再被引用的块
+    ///
+    /// 损坏的新块必须立即删除，否则之后相同内容的写入会去重到损坏的块上。
+    async fn rollback_optimized_chunks(&self, chunks: &[ChunkInfo], written_chunk_ids: &[String]) {
+        let Ok(metadata_db) = self.get_metadata_db() else {
+            return;
+        };
+        for chunk in chunks {
+            if let Err(e) = metadata_db.decrement_chunk_ref(&chunk.chunk_id) {
+                warn!("撤销块引用失败: {} - {}", chunk.chunk_id, e);
+            }
+        }
+        for chunk_id in written_chunk_ids {
+            let Ok(Some(chunk_ref)) = metadata_db.get_chunk_ref(chunk_id) else {
+                continue;
+            };
+            if chunk_ref.ref_count == 0 {
+                if let Err(e) = self.chunk_store.delete(chunk_id).await {
+                    warn!("删除校验失败的块失败: {} - {}", chunk_id, e);
+                    continue;
+                }
+                let _ = metadata_db.remove_chunk_ref(chunk_id);
+                self.forget_chunk(chunk_id, chunk_ref.weak_hash).await;
+            }
+        }
+    }
+
+    /// 更新文件索引（优化后），记录优化策略、完成时间与节省的空间
     async fn update_file_index_after_optimization(
         &self,
         file_id: &str,
         storage_mode: crate::StorageMode,
-        _storage_size: u64,
+        strategy: crate::OptimizationStrategy,
+        compression: Option<crate::core::compression::CompressionAlgorithm>,
+        stored_size: u64,
+        space_saved: u64,
     ) -> Result<()> {
         let metadata_db = self.get_metadata_db()?;
         if let Some(mut file_entry) = metadata_db
@@ -2678,7 +6313,12 @@ impl StorageManager {
         {
             file_entry.storage_mode = storage_mode;
             file_entry.optimization_status = crate::OptimizationStatus::Completed;
-            // 可以选择更新file_size为压缩后的大小
+            file_entry.optimization_strategy = Some(strategy);
+            file_entry.optimized_at = Some(Local::now().naive_local());
+            file_entry.compression = compression;
+            file_entry.stored_size = stored_size;
+            file_entry.space_saved = space_saved;
+            // file_size 始终是原始字节数（下载时用作 Content-Length），不随存储形式变化
             metadata_db
                 .put_file_index(file_id, &file_entry)
                 .map_err(|e| StorageError::Storage(format!("保存文件索引失败: {}", e)))?;
@@ -2686,15 +6326,13 @@ impl StorageManager {
         Ok(())
     }
 
-    /// 获取去重统计（临时方法，用于优化执行器）
-    async fn get_dedup_stats(&self) -> crate::DeduplicationStats {
-        // 简化实现，返回默认值
-        // 实际应该从dedup_manager获取统计
-        crate::DeduplicationStats::default()
-    }
-
     /// 启动后台优化任务
     pub async fn start_optimization_task(&self) {
+        if self.config.read_only {
+            warn!("只读模式下不启动后台优化任务");
+            return;
+        }
+
         if self.optimization_stop_flag.load(Ordering::Relaxed) {
             return; // 已停止，不启动
         }
@@ -2712,7 +6350,10 @@ impl StorageManager {
         let stop_flag = self.optimization_stop_flag.clone();
 
         let handle = tokio::spawn(async move {
-            info!("后台优化任务已启动");
+            info!(
+                "后台优化任务已启动，并发数: {}",
+                storage.optimization_scheduler.max_concurrent()
+            );
 
             loop {
                 // 检查停止标志（无锁原子操作）
@@ -2721,40 +6362,44 @@ impl StorageManager {
                     break;
                 }
 
+                // 等待空闲的并发槽位，任务执行期间一直持有
+                let slot = storage.optimization_scheduler.acquire_slot().await;
+                if stop_flag.load(Ordering::Relaxed) {
+                    continue;
+                }
+
+                // 系统负载过高时暂不领取新任务，把 CPU 让给前台请求
+                if let Some(load) = storage.optimization_overload() {
+                    drop(slot);
+                    tracing::debug!(
+                        "系统负载 {:.2}/CPU 超过上限，推迟优化 {} 秒",
+                        load,
+                        OPTIMIZATION_LOAD_BACKOFF_SECS
+                    );
+                    tokio::time::sleep(Duration::from_secs(OPTIMIZATION_LOAD_BACKOFF_SECS)).await;
+                    continue;
+                }
+
                 // 获取下一个就绪的任务
-                if let Some(mut task) = storage.optimization_scheduler.get_next_ready_task().await {
-                    info!("开始执行优化任务: file_id={}", task.file_id);
-
-                    // 执行优化
-                    match storage.execute_optimization_task(&mut task).await {
-                        Ok((space_saved, optimized_size)) => {
-                            storage
-                                .optimization_scheduler
-                                .mark_task_completed(&task.file_id, space_saved, optimized_size)
-                                .await;
-                        }
-                        Err(e) => {
-                            let error_msg = format!("优化失败: {}", e);
-                            storage
-                                .optimization_scheduler
-                                .mark_task_failed(&task.file_id, &error_msg)
-                                .await;
-
-                            // 如果可以重试，重新提交
-                            if task.can_retry() {
-                                storage
-                                    .optimization_scheduler
-                                    .resubmit_failed_task(task)
-                                    .await;
-                            }
-                        }
-                    }
+                if let Some(task) = storage.optimization_scheduler.get_next_ready_task().await {
+                    let storage = storage.clone();
+                    let stop_flag = stop_flag.clone();
+                    tokio::spawn(async move {
+                        let _slot = slot;
+                        storage.run_optimization_task(task, &stop_flag).await;
+                    });
                 } else {
-                    // 没有就绪的任务，等待一段时间
-                    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                    // 没有就绪的任务，等待新任务提交（或超时后重新检查延迟任务）
+                    drop(slot);
+                    storage
+                        .optimization_scheduler
+                        .wait_for_work(Duration::from_secs(10))
+                        .await;
                 }
             }
 
+            // 等待执行中的任务结束
+            storage.optimization_scheduler.wait_idle().await;
             info!("后台优化任务已停止");
         });
 
@@ -2765,8 +6410,9 @@ impl StorageManager {
     pub async fn stop_optimization_task(&self) {
         info!("停止后台优化任务");
 
-        // 设置停止标志（无锁原子操作）
+        // 设置停止标志（无锁原子操作），唤醒空闲等待中的执行循环
         self.optimization_stop_flag.store(true, Ordering::Relaxed);
+        self.optimization_scheduler.wake();
 
         // 等待任务完成
         if let Some(handle) = self.optimization_task_handle.write().await.take() {
@@ -2776,6 +6422,60 @@ impl StorageManager {
         info!("后台优化任务已停止");
     }
 
+    /// 执行一个优化任务并记录结果
+    async fn run_optimization_task(
+        &self,
+        mut task: crate::OptimizationTask,
+        stop_flag: &AtomicBool,
+    ) {
+        info!("开始执行优化任务: file_id={}", task.file_id);
+
+        match self.execute_optimization_task(&mut task).await {
+            Ok((space_saved, optimized_size)) => {
+                self.optimization_scheduler
+                    .mark_task_completed(&task.file_id, space_saved, optimized_size)
+                    .await;
+            }
+            Err(StorageError::OutOfSpace(msg)) => {
+                // 磁盘空间不足：推迟任务（不计入失败次数）并暂停，避免空转
+                warn!(
+                    "磁盘空间不足，暂停优化任务 {} 秒: {}",
+                    OUT_OF_SPACE_PAUSE_SECS, msg
+                );
+                self.optimization_scheduler
+                    .defer_task(task, OUT_OF_SPACE_PAUSE_SECS)
+                    .await;
+
+                for _ in 0..OUT_OF_SPACE_PAUSE_SECS {
+                    if stop_flag.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+            Err(e) => {
+                let error_msg = format!("优化失败: {}", e);
+                self.optimization_scheduler
+                    .mark_task_failed(&task.file_id, &error_msg)
+                    .await;
+
+                // 如果可以重试，重新提交
+                if task.can_retry() {
+                    self.optimization_scheduler.resubmit_failed_task(task).await;
+                }
+            }
+        }
+    }
+
+    /// 系统负载超过 `optimization_max_load` 时返回当前每 CPU 负载
+    fn optimization_overload(&self) -> Option<f64> {
+        let max_load = self.config.optimization_max_load;
+        if max_load <= 0.0 {
+            return None;
+        }
+        crate::optimization::system_load_per_cpu().filter(|load| *load > max_load)
+    }
+
     /// 获取优化统计信息
     pub async fn get_optimization_stats(&self) -> crate::OptimizationStats {
         self.optimization_scheduler.get_stats().await
@@ -2790,6 +6490,9 @@ impl StorageManager {
     /// 为指定的热存储文件立即创建优化任务，将其转换为分块存储。
     /// 注意：新版本中文件直接使用分块存储，此函数仅用于迁移旧数据。
     pub async fn trigger_file_optimization(&self, file_id: &str) -> Result<()> {
+        self.ensure_writable("存储优化")?;
+        self.ensure_global_id(file_id)?;
+
         // 获取文件索引信息
         let metadata_db = self.get_metadata_db()?;
         let file_entry = metadata_db
@@ -2830,8 +6533,11 @@ impl StorageManager {
             0, // 立即执行
         );
 
-        // 提交任务
-        self.optimization_scheduler.submit_task(task).await;
+        // 提交任务（同一文件已有排队或执行中的任务时不重复提交）
+        if !self.optimization_scheduler.submit_task(task).await {
+            info!("文件 {} 已有进行中的优化任务，不重复提交", file_id);
+            return Ok(());
+        }
 
         info!("手动触发文件 {} 的优化任务，策略: {:?}", file_id, strategy);
 
@@ -2861,6 +6567,65 @@ impl StorageManager {
         self.optimization_stop_flag.load(Ordering::Relaxed)
     }
 
+    /// 进入维护模式
+    ///
+    /// 拒绝所有写操作（返回 `StorageError::Maintenance`，读取不受影响），停止 GC 与
+    /// 后台优化任务并等待执行中的优化任务结束，最后刷新所有持久化状态。排队中的优化任务保留，
+    /// 退出维护模式后继续执行。进入前已通过写检查的写入不会被中断。
+    ///
+    /// 已在维护模式时只重新刷新数据，可用于刷新失败后重试。
+    pub async fn enter_maintenance(&self) -> Result<()> {
+        let mut resume = self.maintenance_resume.lock().await;
+        if resume.is_none() {
+            self.maintenance_flag.store(true, Ordering::Relaxed);
+            info!("进入维护模式");
+
+            let gc_running = self.is_gc_task_running().await;
+            self.stop_gc_task().await;
+            let optimization_running = self
+                .optimization_task_handle
+                .read()
+                .await
+                .as_ref()
+                .is_some_and(|handle| !handle.is_finished());
+            self.stop_optimization_task().await;
+            self.drain_chain_compactions().await;
+
+            *resume = Some(MaintenanceResume {
+                gc_running,
+                optimization_running,
+            });
+        }
+
+        self.sync_all().await?;
+        info!("维护模式：后台任务已停止，数据已刷新");
+        Ok(())
+    }
+
+    /// 退出维护模式，恢复写操作并重新启动进入前在运行的后台任务
+    pub async fn exit_maintenance(&self) -> Result<()> {
+        let mut resume = self.maintenance_resume.lock().await;
+        let Some(state) = resume.take() else {
+            return Ok(());
+        };
+        self.maintenance_flag.store(false, Ordering::Relaxed);
+
+        if state.optimization_running {
+            self.optimization_stop_flag.store(false, Ordering::Relaxed);
+            self.start_optimization_task().await;
+        }
+        if state.gc_running {
+            self.start_gc_task().await;
+        }
+        info!("退出维护模式");
+        Ok(())
+    }
+
+    /// 是否处于维护模式
+    pub fn is_in_maintenance(&self) -> bool {
+        self.maintenance_flag.load(Ordering::Relaxed)
+    }
+
     /// 获取待处理的优化任务列表
     pub async fn get_pending_optimization_tasks(&self) -> Vec<crate::OptimizationTask> {
         self.optimization_scheduler.get_pending_tasks().await
@@ -2871,6 +6636,15 @@ impl StorageManager {
         self.optimization_scheduler.queue_len().await
     }
 
+    /// 热存储中等待优化的数据量（字节）
+    ///
+    /// 汇总所有热存储模式文件的大小（热存储文件未分块、未去重、未压缩，与原始大小一致）。
+    /// 持续增长说明优化器跟不上写入。取自元数据库随文件索引写入增量维护的汇总，
+    /// 每次抓取指标时不遍历文件索引、不访问文件系统。
+    pub async fn hot_storage_bytes(&self) -> Result<u64> {
+        Ok(self.get_metadata_db()?.index_totals()?.hot_size)
+    }
+
     /// 清空优化队列
     ///
     /// 移除所有待处理的优化任务
@@ -2888,18 +6662,277 @@ impl StorageManager {
         info!("停止后台优化任务...");
         self.stop_optimization_task().await;
 
-        // 刷新元数据数据库
+        // 等待后台版本链压缩完成，关闭后不再有写入
+        self.drain_chain_compactions().await;
+
+        // 释放 GC 租约，其他节点无需等待租约过期即可接管
+        if let Some(lease) = &self.gc_lease
+            && let Err(e) = lease.release().await
+        {
+            warn!("释放 GC 租约失败: {}", e);
+        }
+
+        // 刷新元数据数据库与 WAL
+        self.sync_all().await?;
+
+        info!("StorageManager 优雅关闭完成");
+        Ok(())
+    }
+
+    /// 将所有已提交的状态持久化到磁盘
+    ///
+    /// 依次刷新 Sled 元数据数据库（文件索引、版本索引、块引用计数及其弱哈希）、
+    /// WAL 文件、块存储后端自身的索引（如小块打包的包索引，见 [`ChunkStore::sync`]），
+    /// 再将数据、热存储、版本与块目录树下的每个文件及其所在目录落盘，
+    /// 返回时之前保存的文件在断电后重新打开存储仍然可见。
+    /// 去重索引与 Bloom Filter 仅是内存缓存，重新打开时由已落盘的块引用计数重建，
+    /// 因此无需单独持久化。
+    ///
+    /// 需要遍历整个目录树，耗时与文件数量成正比，只在关闭、进入维护模式和快照前调用。
+    pub async fn sync_all(&self) -> Result<()> {
         let metadata_db = self.get_metadata_db()?;
         metadata_db
             .flush()
             .await
             .map_err(|e| StorageError::Storage(format!("刷新数据库失败: {}", e)))?;
 
-        info!("StorageManager 优雅关闭完成");
+        self.wal_manager.read().await.sync().await?;
+        self.chunk_store.sync().await?;
+
+        let roots = [
+            self.data_root.clone(),
+            self.hot_storage_root.clone(),
+            self.version_root.clone(),
+            self.chunk_root.clone(),
+        ];
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            for root in &roots {
+                sync_tree(root)?;
+            }
+            Ok(())
+        })
+        .await
+        .map_err(|e| StorageError::Storage(format!("落盘任务异常退出: {}", e)))??;
+
         Ok(())
     }
 }
 
+/// 破坏性后台操作持有的 GC 租约凭证
+///
+/// 记录开始时的租约代数；每批删除前续约并确认代数未变，
+/// 租约曾过期并被其他节点接管时立即中止，避免两个节点同时删除块。
+struct GcFence {
+    operation: &'static str,
+    /// 持有的租约代数，未启用选举时为 `None`
+    generation: Option<u64>,
+    /// 距上次续约已删除的块数
+    pending: usize,
+    /// 上次续约的时间
+    renewed_at: std::time::Instant,
+}
+
+/// 进入维护模式前后台任务的运行情况
+#[derive(Debug, Clone, Copy)]
+struct MaintenanceResume {
+    gc_running: bool,
+    optimization_running: bool,
+}
+
+/// 将目录树下的所有文件与目录落盘（每个目录在其中的文件之后落盘）
+///
+/// 遍历期间被删除的文件直接跳过；非 Unix 平台无法打开目录句柄，只落盘文件。
+fn sync_tree(root: &Path) -> Result<()> {
+    if !root.exists() {
+        return Ok(());
+    }
+    let ignore_missing = |result: std::io::Result<()>| match result {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    };
+
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(StorageError::Io(e)),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                ignore_missing(std::fs::File::open(entry.path()).and_then(|f| f.sync_all()))?;
+            }
+        }
+        if cfg!(unix) {
+            ignore_missing(std::fs::File::open(&dir).and_then(|f| f.sync_all()))?;
+        }
+    }
+    Ok(())
+}
+
+/// 写入 `size` 字节超出配额剩余空间时返回 `QuotaExceeded`
+fn check_quota(file_id: &str, size: u64, remaining: Option<u64>) -> Result<()> {
+    match remaining {
+        Some(remaining) if size > remaining => Err(StorageError::QuotaExceeded(format!(
+            "{}: 需要 {} 字节，剩余 {} 字节",
+            file_id, size, remaining
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// 加盐的块 ID：SHA-256(盐值长度 | 盐值 | 块数据)
+fn salted_chunk_id(salt: &str, data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((salt.len() as u64).to_le_bytes());
+    hasher.update(salt.as_bytes());
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
+
+/// 文件大小超过上传大小限制时返回 `FileTooLarge`
+fn check_upload_size(file_id: &str, size: u64, max_upload_size: Option<u64>) -> Result<()> {
+    match max_upload_size {
+        Some(max) if size > max => Err(StorageError::FileTooLarge(format!(
+            "{}: 已超过 {} 字节的上限",
+            file_id, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// 块压缩配置（逐块压缩，过小或压缩效果不佳的块不压缩）
+fn chunk_compression_config(
+    algorithm: crate::core::compression::CompressionAlgorithm,
+) -> crate::core::compression::CompressionConfig {
+    crate::core::compression::CompressionConfig {
+        algorithm,
+        level: 1,       // 快速压缩
+        min_size: 1024, // 1KB 以上才压缩
+        auto_compress_days: 7,
+        min_ratio: 1.1, // 压缩比至少 10%
+    }
+}
+
+/// 差异引用的块 ID 列表
+fn delta_chunk_ids(delta: &FileDelta) -> Vec<String> {
+    delta.chunks.iter().map(|c| c.chunk_id.clone()).collect()
+}
+
+/// 按字节加权的去重率（百分比）：`(逻辑大小 - 存储大小) / 逻辑大小`
+fn weighted_dedup_ratio(samples: &[(u64, usize)]) -> f64 {
+    let logical: u64 = samples.iter().map(|(size, refs)| size * *refs as u64).sum();
+    let stored: u64 = samples.iter().map(|(size, _)| size).sum();
+    if logical == 0 {
+        return 0.0;
+    }
+    logical.saturating_sub(stored) as f64 / logical as f64 * 100.0
+}
+
+/// 由块样本 `(块大小, 引用次数)` 推算平均分块大小建议
+///
+/// 以样本的中位块大小把块分为小块与大块两组，分别计算去重率：
+/// - 重复内容集中在小块中（小块去重率明显更高）时，更小的分块能发现更多共享内容，
+///   建议减半，差距悬殊时减为四分之一，预计去重率向小块组靠拢
+/// - 几乎没有去重，或重复内容集中在大块中（整文件副本）时，更大的分块能减少块数量与
+///   元数据开销而基本不损失去重，建议加倍
+/// - 其余情况保持当前大小
+pub(crate) fn recommend_chunking(
+    chunk_size: usize,
+    samples: &[(u64, usize)],
+) -> ChunkingRecommendation {
+    let current_dedup_ratio = weighted_dedup_ratio(samples);
+    let mut recommendation = ChunkingRecommendation {
+        current_chunk_size: chunk_size,
+        sampled_chunks: samples.len(),
+        avg_sampled_chunk_size: if samples.is_empty() {
+            0
+        } else {
+            samples.iter().map(|(size, _)| size).sum::<u64>() / samples.len() as u64
+        },
+        current_dedup_ratio,
+        recommended_chunk_size: chunk_size,
+        estimated_dedup_ratio: current_dedup_ratio,
+        reason: String::new(),
+    };
+    if samples.len() < CHUNKING_MIN_SAMPLES {
+        recommendation.reason = format!(
+            "样本不足（{} 个块，至少需要 {} 个），保持当前分块大小",
+            samples.len(),
+            CHUNKING_MIN_SAMPLES
+        );
+        return recommendation;
+    }
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable_by_key(|(size, _)| *size);
+    let (small, large) = sorted.split_at(sorted.len() / 2);
+    let small_ratio = weighted_dedup_ratio(small);
+    let large_ratio = weighted_dedup_ratio(large);
+
+    let smaller = chunk_size / 2;
+    let larger = chunk_size.saturating_mul(2);
+    if small_ratio >= large_ratio + 10.0 && smaller >= MIN_RECOMMENDED_CHUNK_SIZE {
+        let (size, weight) =
+            if small_ratio >= large_ratio + 20.0 && chunk_size / 4 >= MIN_RECOMMENDED_CHUNK_SIZE {
+                (chunk_size / 4, 1.0)
+            } else {
+                (smaller, 0.5)
+            };
+        recommendation.recommended_chunk_size = size;
+        recommendation.estimated_dedup_ratio =
+            current_dedup_ratio + (small_ratio - current_dedup_ratio).max(0.0) * weight;
+        recommendation.reason = format!(
+            "重复内容集中在小块中（小块去重率 {:.1}%，大块 {:.1}%），更小的分块可发现更多共享内容",
+            small_ratio, large_ratio
+        );
+    } else if (current_dedup_ratio < 2.0 || large_ratio >= small_ratio + 10.0)
+        && larger <= MAX_RECOMMENDED_CHUNK_SIZE
+    {
+        recommendation.recommended_chunk_size = larger;
+        recommendation.reason = if current_dedup_ratio < 2.0 {
+            format!(
+                "去重率仅 {:.1}%，更大的分块可减少块数量与元数据开销",
+                current_dedup_ratio
+            )
+        } else {
+            format!(
+                "重复内容集中在大块中（大块去重率 {:.1}%，小块 {:.1}%），更大的分块基本不损失去重",
+                large_ratio, small_ratio
+            )
+        };
+    } else {
+        recommendation.reason = format!(
+            "当前分块大小合适（去重率 {:.1}%，小块 {:.1}%，大块 {:.1}%）",
+            current_dedup_ratio, small_ratio, large_ratio
+        );
+    }
+    recommendation
+}
+
+/// 分块大小建议（见 [`StorageManager::analyze_chunking`]）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkingRecommendation {
+    /// 当前配置的平均分块大小（字节）
+    pub current_chunk_size: usize,
+    /// 参与分析的块数量
+    pub sampled_chunks: usize,
+    /// 样本块的平均大小（字节）
+    pub avg_sampled_chunk_size: u64,
+    /// 当前去重率（百分比）
+    pub current_dedup_ratio: f64,
+    /// 建议的平均分块大小（字节），与当前相同表示无需调整
+    pub recommended_chunk_size: usize,
+    /// 采用建议后的预计去重率（百分比，粗略估计）
+    pub estimated_dedup_ratio: f64,
+    /// 建议原因
+    pub reason: String,
+}
+
 /// 垃圾回收结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GarbageCollectResult {
@@ -2911,6 +6944,133 @@ pub struct GarbageCollectResult {
     pub errors: Vec<String>,
 }
 
+/// 去重索引重建结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupRebuildResult {
+    /// 统计的版本数（含中断前已统计的部分）
+    pub versions_scanned: usize,
+    /// 重建后有引用的块数
+    pub chunks: usize,
+    /// 引用计数被置 0 的块数（等待垃圾回收）
+    pub reset_chunks: usize,
+    /// 是否从上次中断的断点继续
+    pub resumed: bool,
+}
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeOp {
+    /// 新建
+    Created,
+    /// 修改（产生新版本或更新修改时间）
+    Modified,
+    /// 删除（移入回收站）
+    Deleted,
+}
+
+/// 文件变更记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeRecord {
+    /// 文件ID
+    pub file_id: String,
+    /// 变更后的最新版本ID
+    pub version_id: String,
+    /// 变更类型
+    pub op: ChangeOp,
+    /// 变更时间
+    pub timestamp: chrono::NaiveDateTime,
+}
+
+/// 内容哈希命中的文件引用
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentRef {
+    /// 文件ID
+    pub file_id: String,
+    /// 内容所在的版本ID
+    pub version_id: String,
+    /// 内容大小（字节）
+    pub size: u64,
+}
+
+/// 变更通知类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MutationOp {
+    /// 新建（含从回收站恢复）
+    Created,
+    /// 内容或元数据更新
+    Updated,
+    /// 删除（移入回收站或永久删除）
+    Deleted,
+    /// 移动/重命名
+    Moved {
+        /// 新文件ID
+        to: String,
+    },
+}
+
+/// 硬删除钩子
+///
+/// 通过 [`StorageManager::add_erase_hook`] 注册，文件被硬删除后同步调用。
+#[async_trait]
+pub trait EraseHook: Send + Sync {
+    /// 擦除文件在上层状态中的痕迹
+    async fn erase(&self, file_id: &str) -> Result<()>;
+}
+
+/// 变更通知
+///
+/// 通过 [`StorageManager::subscribe_mutations`] 订阅；文件ID为存储内部ID，命名空间内的文件带命名空间前缀。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MutationEvent {
+    /// 受影响的文件ID（移动时为原ID）
+    pub file_id: String,
+    /// 变更类型
+    pub op: MutationOp,
+}
+
+/// 单个文件的跨版本去重报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileDedupReport {
+    /// 文件ID
+    pub file_id: String,
+    /// 版本数量
+    pub version_count: usize,
+    /// 逻辑大小（所有版本大小之和）
+    pub logical_size: u64,
+    /// 所有版本的块引用总数
+    pub total_chunk_refs: usize,
+    /// 去重后的块数量
+    pub unique_chunks: usize,
+    /// 与其他文件共享的块数量
+    pub shared_chunks: usize,
+    /// 归属于本文件的物理大小（共享块按引用比例分摊）
+    pub physical_size: u64,
+    /// 仅被本文件引用的块的物理大小
+    pub exclusive_size: u64,
+    /// 节省的空间（字节）
+    pub saved_bytes: u64,
+    /// 去重率（百分比）
+    pub dedup_ratio: f64,
+}
+
+/// 存储占用汇总（见 [`StorageManager::recompute_usage`]）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageSummary {
+    /// 文件数（含回收站中的文件）
+    pub files: usize,
+    /// 版本数
+    pub versions: usize,
+    /// 逻辑大小：所有保留版本的文件大小之和（字节）
+    pub logical_size: u64,
+    /// 物理大小：被引用块的实际存储大小与热存储文件大小之和（字节）
+    pub physical_size: u64,
+    /// 被引用的唯一块数
+    pub referenced_chunks: usize,
+    /// 未被任何版本引用、等待 GC 回收的块占用（字节）
+    pub unreferenced_size: u64,
+}
+
 /// 存储统计信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageStats {
@@ -2943,20 +7103,8 @@ impl StorageManagerTrait for StorageManager {
         file_id: &str,
         data: &[u8],
     ) -> std::result::Result<FileMetadata, Self::Error> {
-        // 使用增量存储，这里我们保存第一个版本
-        // parent_version_id 为 None 表示创建新文件
-        let (_delta, file_version) = self.save_version(file_id, data, None).await?;
-
-        // 转换为 FileMetadata
-        Ok(FileMetadata {
-            id: file_id.to_string(),
-            name: file_id.to_string(),
-            path: file_id.to_string(),
-            size: data.len() as u64,
-            hash: file_version.version_id.clone(),
-            created_at: file_version.created_at,
-            modified_at: file_version.created_at,
-        })
+        // 使用增量存储，parent_version_id 为 None 表示创建新文件
+        self.save_file_with_meta(file_id, data, None, None).await
     }
 
     async fn save_at_path(
@@ -2970,22 +7118,9 @@ impl StorageManagerTrait for StorageManager {
     }
 
     async fn read_file(&self, file_id: &str) -> std::result::Result<Vec<u8>, Self::Error> {
-        // 读取文件的最新版本
-        // 首先获取文件的版本列表
-        let versions = self.list_file_versions(file_id).await?;
-
-        if versions.is_empty() {
-            return Err(StorageError::FileNotFound(format!(
-                "文件不存在: {}",
-                file_id
-            )));
-        }
-
-        // 获取最新版本（list_file_versions 已按时间降序排列）
-        let latest_version = &versions[0];
-
-        // 读取版本数据
-        self.read_version_data(&latest_version.version_id).await
+        // 读取文件的当前版本（由文件索引直接给出，无需枚举版本）
+        let version_id = self.current_version_id(file_id).await?;
+        self.read_version_data(&version_id).await
     }
 
     async fn delete_file(&self, file_id: &str) -> std::result::Result<(), Self::Error> {
@@ -2994,24 +7129,13 @@ impl StorageManagerTrait for StorageManager {
     }
 
     async fn file_exists(&self, file_id: &str) -> bool {
-        // 检查文件是否有版本
-        match self.list_file_versions(file_id).await {
-            Ok(versions) => !versions.is_empty(),
-            Err(_) => false,
-        }
+        // 检查文件索引中是否存在当前版本
+        self.current_version_id(file_id).await.is_ok()
     }
 
     async fn get_metadata(&self, file_id: &str) -> std::result::Result<FileMetadata, Self::Error> {
-        let versions = self.list_file_versions(file_id).await?;
-
-        if versions.is_empty() {
-            return Err(StorageError::FileNotFound(format!(
-                "文件不存在: {}",
-                file_id
-            )));
-        }
-
-        let latest_version = &versions[0];
+        let file_info = self.resolve_alias(self.get_file_info(file_id).await?)?;
+        let latest_version = self.get_version_info(&file_info.latest_version_id).await?;
 
         Ok(FileMetadata {
             id: file_id.to_string(),
@@ -3020,35 +7144,14 @@ impl StorageManagerTrait for StorageManager {
             size: latest_version.file_size,
             hash: latest_version.version_id.clone(),
             created_at: latest_version.created_at,
-            modified_at: latest_version.created_at,
+            // 修改时间以文件索引为准（touch 只更新索引）
+            modified_at: file_info.modified_at,
+            user_metadata: file_info.user_metadata,
         })
     }
 
     async fn list_files(&self) -> std::result::Result<Vec<FileMetadata>, Self::Error> {
-        // 从文件索引获取所有文件列表
-        let file_ids = StorageManager::list_files(self).await?;
-
-        let mut files = Vec::new();
-        for file_id in file_ids {
-            // 获取文件信息
-            if let Ok(file_info) = self.get_file_info(&file_id).await {
-                // 获取最新版本的详细信息
-                if let Ok(version_info) = self.get_version_info(&file_info.latest_version_id).await
-                {
-                    files.push(FileMetadata {
-                        id: file_id.clone(),
-                        name: file_id,
-                        path: file_info.latest_version_id.clone(),
-                        size: version_info.file_size,
-                        hash: version_info.version_id,
-                        created_at: file_info.created_at,
-                        modified_at: file_info.modified_at,
-                    });
-                }
-            }
-        }
-
-        Ok(files)
+        self.list_files_with_metadata().await
     }
 
     async fn verify_hash(
@@ -3081,6 +7184,8 @@ impl S3CompatibleStorageTrait for StorageManager {
     type Error = StorageError;
 
     async fn create_bucket(&self, bucket_name: &str) -> std::result::Result<(), Self::Error> {
+        self.ensure_writable("创建 bucket")?;
+
         // bucket 可以映射为目录
         let bucket_path = self.root_dir().join(bucket_name);
         tokio::fs::create_dir_all(&bucket_path).await?;
@@ -3088,6 +7193,8 @@ impl S3CompatibleStorageTrait for StorageManager {
     }
 
     async fn delete_bucket(&self, bucket_name: &str) -> std::result::Result<(), Self::Error> {
+        self.ensure_writable("删除 bucket")?;
+
         let bucket_path = self.root_dir().join(bucket_name);
         tokio::fs::remove_dir_all(&bucket_path).await?;
         Ok(())
@@ -3250,442 +7357,1011 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_storage_stats() {
+    async fn test_version_author_and_comment_listed() {
         let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        storage
-            .save_version("file1", b"Data 1", None)
+        let (_, v1) = storage
+            .save_version_with_meta("doc.txt", b"draft", None, Some("alice"), Some("初稿"))
             .await
             .unwrap();
-        storage
-            .save_version("file2", b"Data 2", None)
+        assert_eq!(v1.author.as_deref(), Some("alice"));
+        let (_, v2) = storage
+            .save_version("doc.txt", b"final", Some(&v1.version_id))
             .await
             .unwrap();
 
-        // 等待优化完成
-        wait_for_optimization(&storage, "file1", 10).await.unwrap();
-        wait_for_optimization(&storage, "file2", 10).await.unwrap();
+        let versions = storage.list_file_versions("doc.txt").await.unwrap();
+        let listed = versions
+            .iter()
+            .find(|v| v.version_id == v1.version_id)
+            .unwrap();
+        assert_eq!(listed.author.as_deref(), Some("alice"));
+        assert_eq!(listed.comment.as_deref(), Some("初稿"));
+        let info = storage.get_version_info(&v2.version_id).await.unwrap();
+        assert!(info.author.is_none() && info.comment.is_none());
+
+        // 内容未变化时不创建新版本，保留原有说明
+        let (_, same) = storage
+            .save_version_with_meta("doc.txt", b"final", None, Some("bob"), Some("重复上传"))
+            .await
+            .unwrap();
+        assert_eq!(same.version_id, v2.version_id);
+        assert!(same.author.is_none());
+    }
 
-        let stats = storage.get_storage_stats().await.unwrap();
-        // 优化过程可能创建额外版本，所以至少应该有2个版本
+    #[tokio::test]
+    async fn test_instant_upload_disabled_by_default() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        let data = b"content known only by hash".to_vec();
+        let hash = hex::encode(Sha256::digest(&data));
+        storage.save_file("a.bin", &data).await.unwrap();
+
+        // 默认不开启秒传，知道哈希也不能取得已有内容
         assert!(
-            stats.total_versions >= 2,
-            "至少应该有2个版本，实际: {}",
-            stats.total_versions
+            storage
+                .save_file_by_hash("b.bin", &hash, data.len() as u64)
+                .await
+                .unwrap()
+                .is_none()
         );
-        assert!(stats.total_chunks > 0, "优化完成后应该有chunks");
-
-        storage.shutdown().await.unwrap();
+        assert!(!storage.file_exists("b.bin").await);
     }
 
     #[tokio::test]
-    async fn test_list_files() {
-        let (storage, _temp) = create_test_storage().await;
+    async fn test_instant_upload_by_hash() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            instant_upload: crate::InstantUploadScope::Namespace,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
         storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+        let metadata_db = storage.get_metadata_db().unwrap();
 
-        // 保存多个文件
-        storage
-            .save_version("file1", b"Data 1", None)
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i * 13 % 251) as u8).collect();
+        let hash = hex::encode(Sha256::digest(&data));
+        let (_, original) = storage.save_version("a.bin", &data, None).await.unwrap();
+        let chunk_count = storage.chunk_store.list().await.unwrap().len();
+        let first_chunk = storage
+            .read_delta("a.bin", &original.version_id)
             .await
-            .unwrap();
-        storage
-            .save_version("file2", b"Data 2", None)
+            .unwrap()
+            .chunks[0]
+            .chunk_id
+            .clone();
+        assert_eq!(metadata_db.get_chunk_ref_count(&first_chunk).unwrap(), 1);
+
+        // 只提交哈希与大小，不传输任何内容
+        let metadata = storage
+            .save_file_by_hash("b.bin", &hash.to_uppercase(), data.len() as u64)
             .await
+            .unwrap()
             .unwrap();
-        storage
-            .save_version("file3", b"Data 3", None)
+        assert_eq!(metadata.size, data.len() as u64);
+        assert_eq!(storage.read_file("b.bin").await.unwrap(), data);
+        assert_eq!(storage.chunk_store.list().await.unwrap().len(), chunk_count);
+        assert_eq!(metadata_db.get_chunk_ref_count(&first_chunk).unwrap(), 2);
+
+        // 删除原文件后秒传出的文件仍可读取
+        storage.delete_file("a.bin").await.unwrap();
+        assert_eq!(storage.read_file("b.bin").await.unwrap(), data);
+
+        // 未知哈希或大小不符时回退普通上传
+        let unknown = hex::encode(Sha256::digest(b"never uploaded"));
+        assert!(
+            storage
+                .save_file_by_hash("c.bin", &unknown, 14)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            storage
+                .save_file_by_hash("c.bin", &hash, data.len() as u64 + 1)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(storage.current_version_id("c.bin").await.is_err());
+
+        // Namespace 范围仅在同一命名空间内秒传
+        let t1 = crate::Namespace::new("t1").unwrap();
+        let t2 = crate::Namespace::new("t2").unwrap();
+        let scoped = storage.with_namespace_access();
+        scoped
+            .save_version(&t1.scope("x.bin"), &data, None)
             .await
             .unwrap();
+        storage.delete_file("b.bin").await.unwrap();
+        assert!(
+            scoped
+                .save_file_by_hash(&t2.scope("x.bin"), &hash, data.len() as u64)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            scoped
+                .save_file_by_hash(&t1.scope("y.bin"), &hash, data.len() as u64)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
 
-        // 列出所有文件
-        let files = storage.list_files().await.unwrap();
-        assert_eq!(files.len(), 3);
-        assert!(files.contains(&"file1".to_string()));
-        assert!(files.contains(&"file2".to_string()));
-        assert!(files.contains(&"file3".to_string()));
+    #[test]
+    fn test_recommend_chunking_direction() {
+        let chunk_size = 64 * 1024;
+
+        // 小块被多次引用、大块各自独立：建议更小的分块
+        let mut samples = vec![(8 * 1024, 4usize); 100];
+        samples.extend(vec![(96 * 1024, 1usize); 100]);
+        let rec = recommend_chunking(chunk_size, &samples);
+        assert_eq!(rec.sampled_chunks, 200);
+        assert!(rec.recommended_chunk_size < chunk_size);
+        assert!(rec.estimated_dedup_ratio > rec.current_dedup_ratio);
+
+        // 没有任何重复：建议更大的分块
+        let samples: Vec<(u64, usize)> = (0..200).map(|i| (40 * 1024 + i * 256, 1)).collect();
+        let rec = recommend_chunking(chunk_size, &samples);
+        assert_eq!(rec.current_dedup_ratio, 0.0);
+        assert_eq!(rec.recommended_chunk_size, chunk_size * 2);
+
+        // 大小块去重率相近：保持不变
+        let samples: Vec<(u64, usize)> = (0..200).map(|i| (32 * 1024 + i * 256, 2)).collect();
+        let rec = recommend_chunking(chunk_size, &samples);
+        assert_eq!(rec.recommended_chunk_size, chunk_size);
+
+        // 样本不足时不给出调整
+        let rec = recommend_chunking(chunk_size, &[(8 * 1024, 10); 10]);
+        assert_eq!(rec.recommended_chunk_size, chunk_size);
+        assert!(rec.reason.contains("样本不足"));
     }
 
     #[tokio::test]
-    async fn test_delete_file() {
+    async fn test_save_version_if_rejects_stale_expectation() {
         let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        // 保存文件和版本
-        let (_delta1, version1) = storage
-            .save_version("test_file", b"Version 1", None)
+        // 不存在时创建，第二个“仅在不存在时创建”的写入者失败
+        let (_, v1) = storage
+            .save_version_if("cas_file", b"first", None)
             .await
             .unwrap();
-        let (_delta2, _version2) = storage
-            .save_version("test_file", b"Version 2", Some(&version1.version_id))
+        let err = storage
+            .save_version_if("cas_file", b"second", None)
             .await
-            .unwrap();
-
-        // 确认文件存在
-        let files = storage.list_files().await.unwrap();
-        assert!(files.contains(&"test_file".to_string()));
-
-        // 软删除文件
-        storage.delete_file("test_file").await.unwrap();
-
-        // 确认文件不在普通列表中
-        let files = storage.list_files().await.unwrap();
-        assert!(!files.contains(&"test_file".to_string()));
+            .unwrap_err();
+        assert!(matches!(err, StorageError::PreconditionFailed(_)));
 
-        // 软删除不会删除版本信息，版本仍然存在
-        let versions = storage.list_file_versions("test_file").await.unwrap();
-        assert_eq!(versions.len(), 2);
+        // 两个写入者都基于 v1，只有先提交的成功
+        let (_, v2) = storage
+            .save_version_if("cas_file", b"writer a", Some(&v1.version_id))
+            .await
+            .unwrap();
+        let err = storage
+            .save_version_if("cas_file", b"writer b", Some(&v1.version_id))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::PreconditionFailed(_)));
+        assert_eq!(
+            storage.current_version_id("cas_file").await.unwrap(),
+            v2.version_id
+        );
 
-        // 文件应该在已删除列表中
-        let deleted_files = storage.list_deleted_files().await.unwrap();
-        assert_eq!(deleted_files.len(), 1);
-        assert_eq!(deleted_files[0].file_id, "test_file");
+        // 并发的条件保存同样只有一个成功
+        let (a, b) = tokio::join!(
+            storage.save_version_if("cas_file", b"racer a", Some(&v2.version_id)),
+            storage.save_version_if("cas_file", b"racer b", Some(&v2.version_id)),
+        );
+        assert_eq!(a.is_ok() as u8 + b.is_ok() as u8, 1);
+        let winner: &[u8] = if a.is_ok() { b"racer a" } else { b"racer b" };
+        let current = storage.current_version_id("cas_file").await.unwrap();
+        assert_eq!(storage.read_version_data(&current).await.unwrap(), winner);
     }
 
     #[tokio::test]
-    async fn test_garbage_collect() {
+    async fn test_every_save_path_takes_file_lock() {
         let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
+        let blocked = Duration::from_millis(50);
 
-        // 保存文件
-        storage
-            .save_version("file1", b"Some data", None)
+        // 持有文件锁期间，各保存路径都等待，不会夹在条件保存的检查与提交之间
+        let guard = storage.file_lock("locked").lock().await;
+        assert!(
+            tokio::time::timeout(blocked, storage.save_version("locked", b"a", None))
+                .await
+                .is_err()
+        );
+        assert!(
+            tokio::time::timeout(
+                blocked,
+                storage.save_version_from_reader("locked", &mut &b"b"[..], None)
+            )
             .await
-            .unwrap();
+            .is_err()
+        );
+        assert!(
+            tokio::time::timeout(
+                blocked,
+                storage.save_version_with_mode(
+                    "locked",
+                    b"c",
+                    None,
+                    crate::StorageMode::Compressed
+                )
+            )
+            .await
+            .is_err()
+        );
+        assert!(storage.current_version_id("locked").await.is_err());
+        drop(guard);
+
+        storage.save_version("locked", b"a", None).await.unwrap();
+        let current = storage.current_version_id("locked").await.unwrap();
         storage
-            .save_version("file2", b"More data", None)
+            .save_version_if("locked", b"b", Some(&current))
             .await
             .unwrap();
+        assert_eq!(storage.read_file("locked").await.unwrap(), b"b");
+    }
 
-        // 删除一个文件
-        storage.delete_file("file1").await.unwrap();
+    #[tokio::test]
+    async fn test_stream_save_reads_without_file_lock() {
+        use tokio::io::AsyncWriteExt;
 
-        // 运行垃圾回收
-        let result = storage.garbage_collect().await.unwrap();
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+        let blocked = Duration::from_millis(50);
+
+        // 客户端发送部分数据后停顿，读取期间文件锁保持空闲
+        let (mut client, mut body) = tokio::io::duplex(64);
+        client.write_all(b"slow ").await.unwrap();
+        let save = storage.save_version_from_reader("slow", &mut body, None);
+        tokio::pin!(save);
+        assert!(tokio::time::timeout(blocked, &mut save).await.is_err());
+        assert!(storage.file_lock("slow").try_lock().is_ok());
+        storage.save_version("slow", b"other", None).await.unwrap();
+
+        client.write_all(b"body").await.unwrap();
+        drop(client);
+        save.await.unwrap();
+        assert_eq!(storage.read_file("slow").await.unwrap(), b"slow body");
+    }
 
-        // 应该有一些孤立块被清理
-        assert!(
-            result.orphaned_chunks > 0 || result.reclaimed_space > 0 || result.errors.is_empty()
-        );
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_optimization_concurrency_one_serializes_tasks() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            optimization_concurrency: 1,
+            enable_auto_gc: false,
+            ..Default::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+        assert_eq!(storage.optimization_scheduler.max_concurrent(), 1);
+
+        for i in 0..6 {
+            let hot_path = temp_dir.path().join(format!("hot_{}", i));
+            fs::write(&hot_path, b"already optimal").await.unwrap();
+            storage
+                .optimization_scheduler
+                .submit_task(crate::OptimizationTask::new(
+                    format!("opt_{}", i),
+                    hot_path,
+                    15,
+                    String::new(),
+                    crate::OptimizationStrategy::Skip,
+                    0,
+                ))
+                .await;
+        }
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while storage.get_optimization_stats().await.completed_tasks < 6 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("优化任务未按时完成");
+
+        // 同一时刻最多只有一个任务在执行
+        let stats = storage.get_optimization_stats().await;
+        assert_eq!(stats.peak_running_tasks, 1);
+        assert_eq!(stats.running_tasks, 0);
+
+        storage.stop_optimization_task().await;
     }
 
     #[tokio::test]
-    async fn test_get_file_info() {
-        let (storage, _temp) = create_test_storage().await;
+    async fn test_upload_backpressure_when_optimization_queue_saturated() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            optimization_queue_high_water: 1,
+            optimization_queue_hard_cap: 3,
+            backpressure_delay_ms: 100,
+            ..Default::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
         storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+
+        // 模拟停滞的优化器：每次上传后积压一个迟迟不执行的优化任务
+        let mut throttled_at = None;
+        for i in 0..5 {
+            let file_id = format!("upload_{}", i);
+            let started = std::time::Instant::now();
+            match storage.save_version(&file_id, b"payload", None).await {
+                Ok(_) => {
+                    if i >= 2 {
+                        // 队列超过软阈值后延迟接受
+                        assert!(started.elapsed() >= Duration::from_millis(100));
+                    }
+                    storage
+                        .optimization_scheduler
+                        .submit_task(crate::OptimizationTask::new(
+                            file_id,
+                            temp_dir.path().join("stalled"),
+                            7,
+                            String::new(),
+                            crate::OptimizationStrategy::Full,
+                            3600,
+                        ))
+                        .await;
+                }
+                Err(StorageError::Busy(_)) => {
+                    throttled_at = Some(i);
+                    break;
+                }
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        }
+        assert_eq!(throttled_at, Some(3));
+        assert!(storage.get_file_info("upload_3").await.is_err());
 
-        // 保存文件
+        // 优化器追上后恢复接受上传
+        storage.clear_optimization_queue().await.unwrap();
         storage
-            .save_version("test_file", b"Data", None)
+            .save_version("upload_3", b"payload", None)
             .await
             .unwrap();
-
-        // 获取文件信息
-        let file_info = storage.get_file_info("test_file").await.unwrap();
-        assert_eq!(file_info.file_id, "test_file");
-        assert_eq!(file_info.version_count, 1);
-        assert!(!file_info.latest_version_id.is_empty());
     }
 
     #[tokio::test]
-    async fn test_deduplication() {
+    async fn test_identical_reupload_reuses_version() {
         let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        // 创建包含重复内容的数据
-        let data1 = b"Hello World! ".repeat(100); // 1300 bytes
-        let data2 = b"Hello World! ".repeat(100); // 相同内容
+        let data = b"Same bytes uploaded twice".repeat(50);
+        let (_, first) = storage.save_version("dup_file", &data, None).await.unwrap();
+        let before = storage.get_file_info("dup_file").await.unwrap();
 
-        // 保存第一个文件
-        let (_delta1, _version1) = storage.save_version("file1", &data1, None).await.unwrap();
+        let (delta, second) = storage
+            .save_version("dup_file", &data, Some(&first.version_id))
+            .await
+            .unwrap();
+        assert_eq!(second.version_id, first.version_id);
+        assert!(!delta.chunks.is_empty());
 
-        // 保存第二个文件（相同内容，应触发去重）
-        let (_delta2, _version2) = storage.save_version("file2", &data2, None).await.unwrap();
+        let after = storage.get_file_info("dup_file").await.unwrap();
+        assert_eq!(after.version_count, 1);
+        assert_eq!(after.latest_version_id, first.version_id);
+        assert!(after.modified_at >= before.modified_at);
+        assert_eq!(
+            storage.list_file_versions("dup_file").await.unwrap().len(),
+            1
+        );
 
-        // 等待优化完成才能看到去重效果
-        wait_for_optimization(&storage, "file1", 10).await.unwrap();
-        wait_for_optimization(&storage, "file2", 10).await.unwrap();
+        // 同样大小但内容不同仍创建新版本
+        let mut changed = data.clone();
+        changed[0] ^= 1;
+        storage
+            .save_version("dup_file", &changed, Some(&first.version_id))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage
+                .get_file_info("dup_file")
+                .await
+                .unwrap()
+                .version_count,
+            2
+        );
+    }
 
-        // 获取去重统计
-        let dedup_stats = storage.get_deduplication_stats().await.unwrap();
+    #[tokio::test]
+    async fn test_high_entropy_chunks_stored_raw() {
+        use crate::core::CompressionAlgorithm;
 
-        // 验证去重效果
-        assert!(dedup_stats.total_chunks > 0, "应该有块数据");
+        let config = IncrementalConfig::default();
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+
+        // 文本段与伪随机段交替组成的混合文件
+        let words = [
+            "storage ", "chunk ", "version ", "delta ", "index ", "file ",
+        ];
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut data = Vec::new();
+        let mut random_ranges = Vec::new();
+        for _ in 0..3 {
+            let end = data.len() + 32 * 1024;
+            while data.len() < end {
+                data.extend_from_slice(words[(next() % words.len() as u64) as usize].as_bytes());
+            }
+            let start = data.len();
+            data.extend((0..32 * 1024).map(|_| next() as u8));
+            random_ranges.push(start..data.len());
+        }
+        let in_random = |offset: usize, size: usize| {
+            random_ranges
+                .iter()
+                .any(|r| r.start <= offset && offset + size <= r.end)
+        };
+        let overlaps_random = |offset: usize, size: usize| {
+            random_ranges
+                .iter()
+                .any(|r| offset < r.end && r.start < offset + size)
+        };
+
+        let (_, version) = storage.save_version("mixed", &data, None).await.unwrap();
+        let delta = storage
+            .read_delta("mixed", &version.version_id)
+            .await
+            .unwrap();
+        let sized: Vec<_> = delta.chunks.iter().filter(|c| c.size >= 1024).collect();
+
+        let random_chunks: Vec<_> = sized
+            .iter()
+            .filter(|c| in_random(c.offset, c.size))
+            .collect();
+        let text_chunks: Vec<_> = sized
+            .iter()
+            .filter(|c| !overlaps_random(c.offset, c.size))
+            .collect();
+        assert!(!random_chunks.is_empty());
+        assert!(!text_chunks.is_empty());
         assert!(
-            dedup_stats.duplicate_chunks > 0,
-            "应该有重复块（file2 与 file1 内容相同）"
+            random_chunks
+                .iter()
+                .all(|c| c.compression == CompressionAlgorithm::None)
         );
         assert!(
-            dedup_stats.dedup_ratio > 0.0,
-            "去重率应该大于 0: {}%",
-            dedup_stats.dedup_ratio
+            text_chunks
+                .iter()
+                .all(|c| c.compression == CompressionAlgorithm::LZ4)
         );
-        assert!(
-            dedup_stats.space_saved > 0,
-            "应该节省了存储空间: {} bytes",
-            dedup_stats.space_saved
+        assert_eq!(
+            storage
+                .read_version_data(&version.version_id)
+                .await
+                .unwrap(),
+            data
         );
 
-        // 验证原始大小应该是两份数据
-        let expected_original_size = (data1.len() + data2.len()) as u64;
-        assert!(
-            dedup_stats.original_size >= expected_original_size,
-            "原始大小应该至少等于两份数据大小"
+        // 去重引用已有的高熵块时记录的算法一致
+        let (_, copy) = storage.save_version("copy", &data, None).await.unwrap();
+        assert_eq!(
+            storage.read_version_data(&copy.version_id).await.unwrap(),
+            data
         );
+    }
 
-        // 验证存储大小应该小于原始大小
-        assert!(
-            dedup_stats.stored_size < dedup_stats.original_size,
-            "存储大小应该小于原始大小（由于去重）"
-        );
+    #[tokio::test]
+    async fn test_dedup_reuses_recorded_raw_chunk_codec() {
+        use crate::core::CompressionAlgorithm;
 
-        println!("去重统计: {:?}", dedup_stats);
+        // 关闭按熵跳过，不可压缩的块也尝试压缩后因压缩率不足按原样存储
+        let config = IncrementalConfig {
+            compression_max_entropy: 8.0,
+            ..Default::default()
+        };
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+
+        let tiny = b"small chunk below the compression threshold".repeat(10);
+        let random = crate::test_util::test_data(64 * 1024, 7);
+        for (name, data) in [("tiny", &tiny), ("random", &random)] {
+            let (_, first) = storage
+                .save_version(&format!("{}_a", name), data, None)
+                .await
+                .unwrap();
+            let (_, second) = storage
+                .save_version(&format!("{}_b", name), data, None)
+                .await
+                .unwrap();
+            let delta = storage
+                .read_delta(&format!("{}_b", name), &second.version_id)
+                .await
+                .unwrap();
+            let metadata_db = storage.get_metadata_db().unwrap();
+            for chunk in &delta.chunks {
+                assert_eq!(chunk.compression, CompressionAlgorithm::None);
+                assert_eq!(
+                    metadata_db.get_chunk_codec(&chunk.chunk_id).unwrap(),
+                    Some(CompressionAlgorithm::None)
+                );
+            }
+            for version in [&first, &second] {
+                assert_eq!(
+                    &storage
+                        .read_version_data(&version.version_id)
+                        .await
+                        .unwrap(),
+                    data
+                );
+            }
+        }
     }
 
     #[tokio::test]
-    async fn test_cross_file_deduplication() {
-        let (storage, _temp) = create_test_storage().await;
-        storage.init().await.unwrap();
+    async fn test_dedup_uses_recorded_codec_after_enabling_compression() {
+        use crate::core::CompressionAlgorithm;
 
-        // 策略：创建两个完全相同的文件，应该100%去重
-        let data = b"This is test data for deduplication. ".repeat(300); // ~11KB
-
-        println!("数据大小: {}B", data.len());
-
-        // 保存第一个文件
-        storage.save_version("file1", &data, None).await.unwrap();
-
-        // 保存第二个文件（完全相同的数据）
-        storage.save_version("file2", &data, None).await.unwrap();
-
-        // 等待优化完成才能看到去重效果
-        wait_for_optimization(&storage, "file1", 10).await.unwrap();
-        wait_for_optimization(&storage, "file2", 10).await.unwrap();
-
-        // 获取去重统计
-        let dedup_stats = storage.get_deduplication_stats().await.unwrap();
-
-        println!("跨文件去重统计: {:?}", dedup_stats);
-        println!(
-            "总块数: {}, 唯一块: {}, 重复块: {}",
-            dedup_stats.total_chunks, dedup_stats.new_chunks, dedup_stats.duplicate_chunks
-        );
-        println!(
-            "原始大小: {}B, 存储大小: {}B, 节省: {}B ({:.2}%)",
-            dedup_stats.original_size,
-            dedup_stats.stored_size,
-            dedup_stats.space_saved,
-            dedup_stats.dedup_ratio
-        );
+        // 关闭压缩时写入的可压缩块按原样存储；开启压缩后按当前配置推测会得到错误的算法
+        let temp_dir = TempDir::new().unwrap();
+        let text = b"recorded chunk codec survives config changes ".repeat(1024);
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            ..Default::default()
+        };
+        {
+            let raw_config = IncrementalConfig {
+                enable_compression: false,
+                ..config.clone()
+            };
+            let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, raw_config);
+            storage.init().await.unwrap();
+            storage.save_version("raw", &text, None).await.unwrap();
+            storage.sync_all().await.unwrap();
+            // 停止后台任务以释放数据库句柄
+            storage.stop_optimization_task().await;
+        }
 
-        // 验证跨文件去重效果
-        // 两个完全相同的文件，总块数应该是唯一块数的2倍
-        assert_eq!(
-            dedup_stats.total_chunks,
-            dedup_stats.new_chunks * 2,
-            "两个相同文件，总块数应该是唯一块数的2倍"
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+        let (_, copy) = storage.save_version("copy", &text, None).await.unwrap();
+        let delta = storage.read_delta("copy", &copy.version_id).await.unwrap();
+        assert!(!delta.chunks.is_empty());
+        assert!(
+            delta
+                .chunks
+                .iter()
+                .all(|c| c.compression == CompressionAlgorithm::None)
         );
         assert_eq!(
-            dedup_stats.duplicate_chunks, dedup_stats.new_chunks,
-            "重复块数应该等于唯一块数（因为文件完全相同）"
-        );
-        assert!(
-            dedup_stats.dedup_ratio >= 45.0,
-            "去重率应该接近 50%（两个相同文件）: {:.2}%",
-            dedup_stats.dedup_ratio
+            storage.read_version_data(&copy.version_id).await.unwrap(),
+            text
         );
     }
 
     #[tokio::test]
-    async fn test_verify_chunks() {
-        let (storage, _temp) = create_test_storage().await;
+    async fn test_auto_compression_selects_per_chunk() {
+        use crate::core::CompressionAlgorithm;
+
+        let config = IncrementalConfig {
+            compression_algorithm: "auto".to_string(),
+            ..Default::default()
+        };
+        config.validate().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
         storage.init().await.unwrap();
 
-        // 创建测试数据
-        let data = b"Test chunk verification data";
-        storage.save_version("test_file", data, None).await.unwrap();
+        // 小词表随机组成的文本高度可压缩，伪随机字节不可压缩
+        let words = [
+            "storage ", "chunk ", "version ", "delta ", "index ", "file ",
+        ];
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut text = Vec::new();
+        while text.len() < 64 * 1024 {
+            text.extend_from_slice(words[(next() % words.len() as u64) as usize].as_bytes());
+        }
+        let random: Vec<u8> = (0..64 * 1024).map(|_| next() as u8).collect();
+
+        for (file_id, data, expected) in [
+            ("text", &text, CompressionAlgorithm::Zstd),
+            ("random", &random, CompressionAlgorithm::None),
+        ] {
+            let (_, version) = storage.save_version(file_id, data, None).await.unwrap();
+            let delta = storage
+                .read_delta(file_id, &version.version_id)
+                .await
+                .unwrap();
+            // 末尾不足 min_size 的块不压缩，其余块都使用预期的算法
+            let sized: Vec<_> = delta.chunks.iter().filter(|c| c.size >= 1024).collect();
+            assert!(!sized.is_empty());
+            assert!(sized.iter().all(|c| c.compression == expected));
+            assert!(
+                delta
+                    .chunks
+                    .iter()
+                    .all(|c| c.compression != CompressionAlgorithm::Auto)
+            );
+            assert_eq!(
+                &storage
+                    .read_version_data(&version.version_id)
+                    .await
+                    .unwrap(),
+                data
+            );
+        }
+    }
 
-        // 等待优化完成才会有chunks
-        wait_for_optimization(&storage, "test_file", 10)
+    #[tokio::test]
+    async fn test_content_addressed_version_ids_match_across_managers() {
+        let config = IncrementalConfig {
+            content_addressed_versions: true,
+            ..Default::default()
+        };
+        let dir_a = TempDir::new().unwrap();
+        let dir_b = TempDir::new().unwrap();
+        let node_a = StorageManager::new(dir_a.path().to_path_buf(), 4096, config.clone());
+        let node_b = StorageManager::new(dir_b.path().to_path_buf(), 4096, config);
+        node_a.init().await.unwrap();
+        node_b.init().await.unwrap();
+
+        let data = b"identical content on two nodes".repeat(300);
+        let (_, a1) = node_a.save_version("doc", &data, None).await.unwrap();
+        let (_, b1) = node_b.save_version("doc", &data, None).await.unwrap();
+        assert_eq!(a1.version_id, b1.version_id);
+
+        // 流式保存同样得到相同的版本 ID
+        let (_, a2) = node_a
+            .save_version_from_reader("stream", &mut &data[..], None)
             .await
             .unwrap();
-
-        // 验证所有 chunks
-        let report = storage.verify_all_chunks().await.unwrap();
-        assert_eq!(report.valid + report.invalid + report.missing, report.total);
-        // 正常情况下应该所有 chunks 都是有效的
-        assert!(report.valid > 0, "应该有有效的 chunks");
-        assert_eq!(report.invalid, 0, "不应该有损坏的 chunk");
+        let (_, b2) = node_b
+            .save_version_from_reader("stream", &mut &data[..], None)
+            .await
+            .unwrap();
+        assert_eq!(a2.version_id, b2.version_id);
+
+        // 不同内容、不同文件、内容回退到旧值都不会产生重复的版本 ID
+        let mut changed = data.clone();
+        changed[0] ^= 1;
+        let (_, a3) = node_a.save_version("doc", &changed, None).await.unwrap();
+        let (_, a4) = node_a.save_version("doc", &data, None).await.unwrap();
+        let (_, other) = node_a.save_version("other", &data, None).await.unwrap();
+        let ids: std::collections::HashSet<_> = [&a1, &a2, &a3, &a4, &other]
+            .map(|v| v.version_id.clone())
+            .into();
+        assert_eq!(ids.len(), 5);
+        assert_eq!(node_a.get_file_info("doc").await.unwrap().version_count, 3);
     }
 
     #[tokio::test]
-    async fn test_soft_delete() {
-        let (storage, _temp) = create_test_storage().await;
+    async fn test_empty_file_lifecycle() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(
+            temp_dir.path().to_path_buf(),
+            4096,
+            IncrementalConfig::default(),
+        );
         storage.init().await.unwrap();
 
-        // 创建测试文件
-        let data = b"Test data for soft delete";
-        storage.save_version("test_file", data, None).await.unwrap();
+        // 保存：没有块，大小为 0
+        let (delta, version) = storage.save_version("empty.txt", b"", None).await.unwrap();
+        assert!(delta.chunks.is_empty());
+        assert_eq!(version.size, 0);
+        assert_eq!(delta.get_stats().avg_chunk_size, 0.0);
+
+        // 相同的空内容复用当前版本；流式保存空输入同样没有块
+        let (_, again) = storage.save_version("empty.txt", b"", None).await.unwrap();
+        assert_eq!(again.version_id, version.version_id);
+        let (stream_delta, stream_version) = storage
+            .save_version_from_reader("stream.txt", &mut &b""[..], None)
+            .await
+            .unwrap();
+        assert!(stream_delta.chunks.is_empty());
+        assert_eq!(stream_version.size, 0);
 
-        // 软删除文件
-        storage.delete_file("test_file").await.unwrap();
+        // 读取
+        assert!(
+            storage
+                .read_version_data(&version.version_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let mut reader = storage
+            .open_version_reader(&version.version_id)
+            .await
+            .unwrap();
+        assert!(reader.read_to_end().await.unwrap().is_empty());
+        assert!(
+            storage
+                .read_version_range(&version.version_id, 0, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            StorageManagerTrait::read_file(&storage, "empty.txt")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(storage.stat("empty.txt").await.unwrap().size, 0);
 
-        // 文件应该不在普通列表中
-        let files = storage.list_files().await.unwrap();
-        assert!(!files.contains(&"test_file".to_string()));
+        // 列表
+        let files = StorageManagerTrait::list_files(&storage).await.unwrap();
+        let listed = files.iter().find(|f| f.id == "empty.txt").unwrap();
+        assert_eq!(listed.size, 0);
 
-        // 但应该在已删除列表中
-        let deleted_files = storage.list_deleted_files().await.unwrap();
-        assert_eq!(deleted_files.len(), 1);
-        assert_eq!(deleted_files[0].file_id, "test_file");
-        assert!(deleted_files[0].is_deleted);
-        assert!(deleted_files[0].deleted_at.is_some());
+        // 统计：没有块时不出现除零
+        let stats = storage.get_storage_stats().await.unwrap();
+        assert_eq!(stats.total_chunks, 0);
+        assert_eq!(stats.avg_chunk_size, 0.0);
+        assert_eq!(stats.compression_ratio, 0.0);
+        assert_eq!(
+            storage.get_deduplication_stats().await.unwrap().dedup_ratio,
+            0.0
+        );
+        let report = storage.file_dedup_report("empty.txt").await.unwrap();
+        assert_eq!(report.logical_size, 0);
+        assert_eq!(report.dedup_ratio, 0.0);
+
+        // 删除：先移入回收站，再永久删除
+        storage.delete_file("empty.txt").await.unwrap();
+        assert!(matches!(
+            storage.stat("empty.txt").await,
+            Err(StorageError::FileNotFound(_))
+        ));
+        storage.permanently_delete_file("empty.txt").await.unwrap();
+        assert!(storage.get_file_info("empty.txt").await.is_err());
     }
 
     #[tokio::test]
-    async fn test_restore_file() {
+    async fn test_stat_reads_only_file_index() {
         let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        // 创建并删除文件
         storage
-            .save_version("test_file", b"Test data", None)
+            .save_version("stat_file", b"first", None)
+            .await
+            .unwrap();
+        let data = b"second version content".to_vec();
+        let (_, latest) = storage
+            .save_version("stat_file", &data, None)
             .await
             .unwrap();
-        storage.delete_file("test_file").await.unwrap();
-
-        // 恢复文件
-        storage.restore_file("test_file").await.unwrap();
 
-        // 文件应该回到普通列表
-        let files = storage.list_files().await.unwrap();
-        assert!(files.contains(&"test_file".to_string()));
+        storage.version_lookups.store(0, Ordering::Relaxed);
+        let stat = storage.stat("stat_file").await.unwrap();
+        assert_eq!(stat.size, data.len() as u64);
+        assert_eq!(stat.hash, storage.calculate_hash(&data));
+        assert_eq!(stat.version_id, latest.version_id);
+        assert_eq!(stat.storage_mode, crate::StorageMode::Chunked);
+        assert_eq!(storage.version_lookups.load(Ordering::Relaxed), 0);
 
-        // 不应该在已删除列表中
-        let deleted_files = storage.list_deleted_files().await.unwrap();
-        assert_eq!(deleted_files.len(), 0);
+        // 转换后的元数据与 get_metadata 一致
+        let metadata = StorageManagerTrait::get_metadata(&storage, "stat_file")
+            .await
+            .unwrap();
+        let converted = stat.into_metadata();
+        assert_eq!(converted.size, metadata.size);
+        assert_eq!(converted.hash, metadata.hash);
+
+        storage.delete_file("stat_file").await.unwrap();
+        assert!(matches!(
+            storage.stat("stat_file").await,
+            Err(StorageError::FileNotFound(_))
+        ));
     }
 
     #[tokio::test]
-    async fn test_empty_recycle_bin() {
+    async fn test_entity_tag_follows_content() {
         let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        // 创建并删除多个文件
+        let data = b"entity tag content".to_vec();
         storage
-            .save_version("file1", b"Data 1", None)
+            .save_version("etag_file", &data, None)
             .await
             .unwrap();
+        let etag = storage.entity_tag("etag_file").await.unwrap();
+        assert_eq!(etag, format!("\"{}\"", storage.calculate_hash(&data)));
+
+        // 内容相同的新版本 ETag 不变，内容变化后 ETag 随之变化
+        let parent = storage.current_version_id("etag_file").await.unwrap();
         storage
-            .save_version("file2", b"Data 2", None)
+            .save_version("etag_file", &data, Some(&parent))
             .await
             .unwrap();
-        storage.delete_file("file1").await.unwrap();
-        storage.delete_file("file2").await.unwrap();
+        assert_eq!(storage.entity_tag("etag_file").await.unwrap(), etag);
+        storage
+            .save_version("etag_file", b"changed", None)
+            .await
+            .unwrap();
+        assert_ne!(storage.entity_tag("etag_file").await.unwrap(), etag);
 
-        // 确认有已删除的文件
-        let deleted_files = storage.list_deleted_files().await.unwrap();
-        assert_eq!(deleted_files.len(), 2);
+        // 文件索引未记录哈希的旧数据按内容计算并回写
+        let metadata_db = storage.get_metadata_db().unwrap();
+        let mut entry = metadata_db.get_file_index("etag_file").unwrap().unwrap();
+        entry.file_hash.clear();
+        metadata_db.put_file_index("etag_file", &entry).unwrap();
+        let expected = storage.calculate_hash(b"changed");
+        assert_eq!(
+            storage.entity_tag("etag_file").await.unwrap(),
+            format!("\"{}\"", expected)
+        );
+        assert_eq!(storage.stat("etag_file").await.unwrap().hash, expected);
+    }
 
-        // 清空回收站
-        let count = storage.empty_recycle_bin().await.unwrap();
-        assert_eq!(count, 2);
+    #[tokio::test]
+    async fn test_file_stat_not_modified() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
 
-        // 已删除列表应该为空
-        let deleted_files = storage.list_deleted_files().await.unwrap();
-        assert_eq!(deleted_files.len(), 0);
+        storage.save_file("cond.txt", b"conditional").await.unwrap();
+        let modified_at = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_milli_opt(12, 0, 0, 750)
+            .unwrap();
+        storage.touch("cond.txt", modified_at).await.unwrap();
+        let stat = storage.stat("cond.txt").await.unwrap();
+        let etag = storage.entity_tag("cond.txt").await.unwrap();
+
+        // 修改时间按秒比较，毫秒部分不影响结果
+        let last_modified = stat.last_modified_http();
+        assert_eq!(last_modified, "Wed, 01 May 2024 12:00:00 GMT");
+        assert!(stat.not_modified(&etag, None, Some(&last_modified)));
+        assert!(!stat.not_modified(&etag, None, Some("Wed, 01 May 2024 11:59:59 GMT")));
+        assert!(!stat.not_modified(&etag, None, Some("not a date")));
+        assert!(!stat.not_modified(&etag, None, None));
+
+        // If-None-Match 优先于 If-Modified-Since
+        assert!(stat.not_modified(&etag, Some(&format!("W/{}", etag)), None));
+        assert!(!stat.not_modified(&etag, Some("\"other\""), Some(&last_modified)));
+        assert!(stat.not_modified(&etag, Some("*"), None));
     }
 
     #[tokio::test]
-    async fn test_permanently_delete_file() {
+    async fn test_user_metadata_survives_versioning_and_move() {
         let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        // 创建测试文件
-        let data = b"Test data for permanent delete";
-        storage.save_version("test_file", data, None).await.unwrap();
+        storage.save_file("meta_file", b"v1").await.unwrap();
+        let user_metadata = HashMap::from([("author".to_string(), "alice".to_string())]);
+        storage
+            .set_user_metadata("meta_file", user_metadata.clone())
+            .await
+            .unwrap();
 
-        // 永久删除文件
-        storage.permanently_delete_file("test_file").await.unwrap();
+        storage.save_file("meta_file", b"v2").await.unwrap();
+        let metadata = storage.get_metadata("meta_file").await.unwrap();
+        assert_eq!(metadata.user_metadata, user_metadata);
 
-        // 文件不应该存在
-        assert!(!storage.file_exists("test_file").await);
+        let moved = storage.move_file("meta_file", "meta_moved").await.unwrap();
+        assert_eq!(moved.user_metadata, user_metadata);
+        let metadata = storage.get_metadata("meta_moved").await.unwrap();
+        assert_eq!(metadata.user_metadata, user_metadata);
+
+        let oversized = HashMap::from([("k".to_string(), "v".repeat(MAX_USER_METADATA_SIZE))]);
+        assert!(
+            storage
+                .set_user_metadata("meta_moved", oversized)
+                .await
+                .is_err()
+        );
+        assert!(
+            storage
+                .set_user_metadata("missing", HashMap::new())
+                .await
+                .is_err()
+        );
     }
 
     #[tokio::test]
-    async fn test_garbage_collect_blocks_with_dedup() {
-        let temp_dir = TempDir::new().unwrap();
-        let config = IncrementalConfig {
-            enable_compression: false,
-            ..IncrementalConfig::default()
-        };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+    async fn test_read_version_does_not_overlay_parent_snapshot() {
+        let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        // 创建测试文件
-        let data1 = b"Test data 1 for garbage collection";
-        let data2 = b"Test data 2 for garbage collection";
-        storage.save_version("file1", data1, None).await.unwrap();
-        storage.save_version("file2", data2, None).await.unwrap();
-
-        // 永久删除文件1
-        storage.permanently_delete_file("file1").await.unwrap();
-
-        // 运行GC
-        let _deleted_count = storage.garbage_collect_blocks().await.unwrap();
+        // 新版本比父版本短且内容不同，重建时不能混入父版本的数据
+        let (_, v1) = storage
+            .save_version("shrink.txt", b"a much longer first version", None)
+            .await
+            .unwrap();
+        let (_, v2) = storage
+            .save_version("shrink.txt", b"short", Some(&v1.version_id))
+            .await
+            .unwrap();
 
-        // 应该清理了一些块
-        // 注意：具体数量取决于分块策略
-        // GC应该成功完成，不需要检查具体数量
+        assert_eq!(
+            storage.read_version_data(&v2.version_id).await.unwrap(),
+            b"short"
+        );
+        assert_eq!(
+            storage.read_version_data(&v1.version_id).await.unwrap(),
+            b"a much longer first version"
+        );
     }
 
     #[tokio::test]
-    async fn test_garbage_collect_blocks_without_dedup() {
-        let temp_dir = TempDir::new().unwrap();
-        let config = IncrementalConfig {
-            enable_compression: false,
-            ..IncrementalConfig::default()
-        };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+    async fn test_current_version_id_tracks_latest() {
+        let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        // 创建测试文件
-        let data = b"Test data for garbage collection without dedup";
-        storage.save_version("file1", data, None).await.unwrap();
+        assert!(matches!(
+            storage.current_version_id("missing").await,
+            Err(StorageError::FileNotFound(_))
+        ));
 
-        // 永久删除文件
-        storage.permanently_delete_file("file1").await.unwrap();
+        let (_, v1) = storage
+            .save_version("test_file", b"Version 1", None)
+            .await
+            .unwrap();
+        let (_, v2) = storage
+            .save_version("test_file", b"Version 2", Some(&v1.version_id))
+            .await
+            .unwrap();
 
-        // 运行GC
-        let _deleted_count = storage.garbage_collect_blocks().await.unwrap();
+        assert_eq!(
+            storage.current_version_id("test_file").await.unwrap(),
+            v2.version_id
+        );
 
-        // 应该清理了一些块
-        // GC应该成功完成，不需要检查具体数量
+        // 仅最新版本标记为当前版本，旧版本可被删除
+        let info1 = storage.get_version_info(&v1.version_id).await.unwrap();
+        let info2 = storage.get_version_info(&v2.version_id).await.unwrap();
+        assert!(!info1.is_current);
+        assert!(info2.is_current);
+        storage.delete_file_version(&v1.version_id).await.unwrap();
+        assert!(storage.delete_file_version(&v2.version_id).await.is_err());
+
+        assert_eq!(storage.read_file("test_file").await.unwrap(), b"Version 2");
     }
 
     #[tokio::test]
-    async fn test_delete_already_deleted_file() {
+    async fn test_read_file_does_not_enumerate_versions() {
         let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        // 创建并删除文件
-        storage
-            .save_version("test_file", b"Test data", None)
-            .await
-            .unwrap();
-        storage.delete_file("test_file").await.unwrap();
+        let mut parent: Option<String> = None;
+        for i in 0..100 {
+            let data = format!("content of version {}", i);
+            let (_, version) = storage
+                .save_version("test_file", data.as_bytes(), parent.as_deref())
+                .await
+                .unwrap();
+            parent = Some(version.version_id);
+        }
 
-        // 尝试再次删除应该失败
-        let result = storage.delete_file("test_file").await;
-        assert!(result.is_err());
+        crate::metadata::access_counts::reset();
+
+        let data = storage.read_file("test_file").await.unwrap();
+        assert_eq!(data, b"content of version 99");
+        let metadata = storage.get_metadata("test_file").await.unwrap();
+        assert_eq!(metadata.hash, parent.unwrap());
+        assert!(storage.file_exists("test_file").await);
+
+        assert_eq!(crate::metadata::access_counts::get().version_scans, 0);
     }
 
     #[tokio::test]
-    async fn test_list_files_excludes_deleted() {
+    async fn test_storage_stats() {
         let (storage, _temp) = create_test_storage().await;
         storage.init().await.unwrap();
 
-        // 创建多个文件
         storage
             .save_version("file1", b"Data 1", None)
             .await
@@ -3694,327 +8370,3562 @@ mod tests {
             .save_version("file2", b"Data 2", None)
             .await
             .unwrap();
+
+        // 等待优化完成
+        wait_for_optimization(&storage, "file1", 10).await.unwrap();
+        wait_for_optimization(&storage, "file2", 10).await.unwrap();
+
+        let stats = storage.get_storage_stats().await.unwrap();
+        // 优化过程可能创建额外版本，所以至少应该有2个版本
+        assert!(
+            stats.total_versions >= 2,
+            "至少应该有2个版本，实际: {}",
+            stats.total_versions
+        );
+        assert!(stats.total_chunks > 0, "优化完成后应该有chunks");
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_files() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 保存多个文件
+        storage
+            .save_version("file1", b"Data 1", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("file2", b"Data 2", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("file3", b"Data 3", None)
+            .await
+            .unwrap();
+
+        // 列出所有文件
+        let files = storage.list_files().await.unwrap();
+        assert_eq!(files.len(), 3);
+        assert!(files.contains(&"file1".to_string()));
+        assert!(files.contains(&"file2".to_string()));
+        assert!(files.contains(&"file3".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_list_files_with_metadata_single_scan() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        for i in 0..20 {
+            let data = format!("data {}", i);
+            storage
+                .save_version(&format!("file{:02}", i), data.as_bytes(), None)
+                .await
+                .unwrap();
+        }
+        storage.delete_file("file05").await.unwrap();
+
+        use crate::metadata::access_counts;
+        access_counts::reset();
+
+        let files = storage.list_files_with_metadata().await.unwrap();
+        assert_eq!(files.len(), 19);
+        assert_eq!(files[0].id, "file00");
+        assert_eq!(files[0].path, "file00");
+        assert_eq!(files[0].size, 6);
+        assert!(files.iter().all(|f| f.id != "file05"));
+
+        // 一次遍历，没有逐文件查找
+        let counts = access_counts::get();
+        assert_eq!(counts.file_scans, 1);
+        assert_eq!(counts.file_lookups, 0);
+        assert_eq!(counts.version_scans, 0);
+
+        // 结果与逐个读取元数据一致，后者每个文件都要查找一次索引
+        for file in &files {
+            let metadata = storage.get_metadata(&file.id).await.unwrap();
+            assert_eq!(metadata.size, file.size);
+            assert_eq!(metadata.hash, file.hash);
+        }
+        assert!(access_counts::get().file_lookups >= files.len());
+    }
+
+    #[tokio::test]
+    async fn test_alias_follows_target_updates() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        storage.save_version("doc.txt", b"v1", None).await.unwrap();
+        let alias = storage.create_alias("link.txt", "doc.txt").await.unwrap();
+        assert_eq!(alias.alias_target.as_deref(), Some("doc.txt"));
+        assert_eq!(alias.size, 2);
+
+        // 复制是保存时的快照
+        let data = StorageManagerTrait::read_file(&storage, "doc.txt")
+            .await
+            .unwrap();
+        storage.save_version("copy.txt", &data, None).await.unwrap();
+
+        storage
+            .save_version("doc.txt", b"version 2", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            StorageManagerTrait::read_file(&storage, "link.txt")
+                .await
+                .unwrap(),
+            b"version 2"
+        );
+        assert_eq!(
+            StorageManagerTrait::read_file(&storage, "copy.txt")
+                .await
+                .unwrap(),
+            b"v1"
+        );
+        let stat = storage.stat("link.txt").await.unwrap();
+        assert_eq!(stat.file_id, "link.txt");
+        assert_eq!(stat.size, 9);
+        assert_eq!(stat.version_count, 2);
+
+        // 写入别名即写入目标
+        storage.save_version("link.txt", b"v3", None).await.unwrap();
+        assert_eq!(
+            StorageManagerTrait::read_file(&storage, "doc.txt")
+                .await
+                .unwrap(),
+            b"v3"
+        );
+
+        // 列表中别名的 path 为目标文件
+        let files = storage.list_files_with_metadata().await.unwrap();
+        let link = files.iter().find(|f| f.id == "link.txt").unwrap();
+        assert_eq!(link.path, "doc.txt");
+        assert_eq!(link.size, 2);
+
+        // 目标删除后别名悬空
+        storage.delete_file("doc.txt").await.unwrap();
+        assert!(matches!(
+            StorageManagerTrait::read_file(&storage, "link.txt").await,
+            Err(StorageError::DanglingAlias(_))
+        ));
+        assert!(matches!(
+            storage.stat("link.txt").await,
+            Err(StorageError::DanglingAlias(_))
+        ));
+        assert!(
+            storage
+                .list_files_with_metadata()
+                .await
+                .unwrap()
+                .iter()
+                .all(|f| f.id != "link.txt")
+        );
+
+        // 删除别名不影响目标
+        storage.delete_file("link.txt").await.unwrap();
+        storage.permanently_delete_file("link.txt").await.unwrap();
+        storage.restore_file("doc.txt").await.unwrap();
+        assert_eq!(
+            StorageManagerTrait::read_file(&storage, "doc.txt")
+                .await
+                .unwrap(),
+            b"v3"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_iter_files_stream() {
+        use futures::StreamExt;
+
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        for file_id in ["a.txt", "b.txt", "dir/c.txt"] {
+            storage
+                .save_version(file_id, file_id.as_bytes(), None)
+                .await
+                .unwrap();
+        }
+        storage.delete_file("b.txt").await.unwrap();
+
+        let entries: Vec<FileIndexEntry> = storage
+            .iter_files()
+            .map(|entry| entry.unwrap())
+            .collect()
+            .await;
+        let mut ids: Vec<&str> = entries.iter().map(|e| e.file_id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["a.txt", "b.txt", "dir/c.txt"]);
+        assert!(
+            entries
+                .iter()
+                .all(|e| e.is_deleted == (e.file_id == "b.txt"))
+        );
+
+        // 逐条消费，提前结束时不会读取剩余条目
+        let mut stream = std::pin::pin!(storage.iter_files());
+        let first = stream.next().await.unwrap().unwrap();
+        assert!(ids.contains(&first.file_id.as_str()));
+        assert_eq!(stream.take(1).count().await, 1);
+
+        // 未初始化时流只产生一个错误
+        let (uninit, _temp2) = create_test_storage().await;
+        let results: Vec<_> = uninit.iter_files().collect().await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_file() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 保存文件和版本
+        let (_delta1, version1) = storage
+            .save_version("test_file", b"Version 1", None)
+            .await
+            .unwrap();
+        let (_delta2, _version2) = storage
+            .save_version("test_file", b"Version 2", Some(&version1.version_id))
+            .await
+            .unwrap();
+
+        // 确认文件存在
+        let files = storage.list_files().await.unwrap();
+        assert!(files.contains(&"test_file".to_string()));
+
+        // 软删除文件
+        storage.delete_file("test_file").await.unwrap();
+
+        // 确认文件不在普通列表中
+        let files = storage.list_files().await.unwrap();
+        assert!(!files.contains(&"test_file".to_string()));
+
+        // 软删除不会删除版本信息，版本仍然存在
+        let versions = storage.list_file_versions("test_file").await.unwrap();
+        assert_eq!(versions.len(), 2);
+
+        // 文件应该在已删除列表中
+        let deleted_files = storage.list_deleted_files().await.unwrap();
+        assert_eq!(deleted_files.len(), 1);
+        assert_eq!(deleted_files[0].file_id, "test_file");
+    }
+
+    #[tokio::test]
+    async fn test_mutation_events() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+        let mut events = storage.subscribe_mutations();
+
+        let (_, version1) = storage
+            .save_version("mut_file", b"Version 1", None)
+            .await
+            .unwrap();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.file_id, "mut_file");
+        assert_eq!(event.op, MutationOp::Created);
+
+        storage
+            .save_version("mut_file", b"Version 2", Some(&version1.version_id))
+            .await
+            .unwrap();
+        assert_eq!(events.try_recv().unwrap().op, MutationOp::Updated);
+
+        storage.move_file("mut_file", "mut_moved").await.unwrap();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.file_id, "mut_file");
+        assert_eq!(
+            event.op,
+            MutationOp::Moved {
+                to: "mut_moved".to_string()
+            }
+        );
+
+        storage.delete_file("mut_moved").await.unwrap();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.file_id, "mut_moved");
+        assert_eq!(event.op, MutationOp::Deleted);
+
+        storage.restore_file("mut_moved").await.unwrap();
+        assert_eq!(events.try_recv().unwrap().op, MutationOp::Created);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_garbage_collect() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 保存文件
+        storage
+            .save_version("file1", b"Some data", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("file2", b"More data", None)
+            .await
+            .unwrap();
+
+        // 删除一个文件
+        storage.delete_file("file1").await.unwrap();
+
+        // 运行垃圾回收
+        let result = storage.garbage_collect().await.unwrap();
+
+        // 应该有一些孤立块被清理
+        assert!(
+            result.orphaned_chunks > 0 || result.reclaimed_space > 0 || result.errors.is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_file_info() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 保存文件
+        storage
+            .save_version("test_file", b"Data", None)
+            .await
+            .unwrap();
+
+        // 获取文件信息
+        let file_info = storage.get_file_info("test_file").await.unwrap();
+        assert_eq!(file_info.file_id, "test_file");
+        assert_eq!(file_info.version_count, 1);
+        assert!(!file_info.latest_version_id.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_touch_updates_modified_at_only() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        storage
+            .save_version("touch_file", b"Data", None)
+            .await
+            .unwrap();
+        let before = storage.get_file_info("touch_file").await.unwrap();
+
+        let modified_at = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        storage.touch("touch_file", modified_at).await.unwrap();
+
+        let after = storage.get_file_info("touch_file").await.unwrap();
+        assert_eq!(after.modified_at, modified_at);
+        assert_eq!(after.version_count, before.version_count);
+        assert_eq!(after.latest_version_id, before.latest_version_id);
+        assert_eq!(
+            storage
+                .get_metadata("touch_file")
+                .await
+                .unwrap()
+                .modified_at,
+            modified_at
+        );
+        assert_eq!(storage.read_file("touch_file").await.unwrap(), b"Data");
+
+        assert!(matches!(
+            storage.touch("missing_file", modified_at).await,
+            Err(StorageError::FileNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_deduplication() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 创建包含重复内容的数据
+        let data1 = b"Hello World! ".repeat(100); // 1300 bytes
+        let data2 = b"Hello World! ".repeat(100); // 相同内容
+
+        // 保存第一个文件
+        let (_delta1, _version1) = storage.save_version("file1", &data1, None).await.unwrap();
+
+        // 保存第二个文件（相同内容，应触发去重）
+        let (_delta2, _version2) = storage.save_version("file2", &data2, None).await.unwrap();
+
+        // 等待优化完成才能看到去重效果
+        wait_for_optimization(&storage, "file1", 10).await.unwrap();
+        wait_for_optimization(&storage, "file2", 10).await.unwrap();
+
+        // 获取去重统计
+        let dedup_stats = storage.get_deduplication_stats().await.unwrap();
+
+        // 验证去重效果
+        assert!(dedup_stats.total_chunks > 0, "应该有块数据");
+        assert!(
+            dedup_stats.duplicate_chunks > 0,
+            "应该有重复块（file2 与 file1 内容相同）"
+        );
+        assert!(
+            dedup_stats.dedup_ratio > 0.0,
+            "去重率应该大于 0: {}%",
+            dedup_stats.dedup_ratio
+        );
+        assert!(
+            dedup_stats.space_saved > 0,
+            "应该节省了存储空间: {} bytes",
+            dedup_stats.space_saved
+        );
+
+        // 验证原始大小应该是两份数据
+        let expected_original_size = (data1.len() + data2.len()) as u64;
+        assert!(
+            dedup_stats.original_size >= expected_original_size,
+            "原始大小应该至少等于两份数据大小"
+        );
+
+        // 验证存储大小应该小于原始大小
+        assert!(
+            dedup_stats.stored_size < dedup_stats.original_size,
+            "存储大小应该小于原始大小（由于去重）"
+        );
+
+        println!("去重统计: {:?}", dedup_stats);
+    }
+
+    #[tokio::test]
+    async fn test_cross_file_deduplication() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 策略：创建两个完全相同的文件，应该100%去重
+        let data = b"This is test data for deduplication. ".repeat(300); // ~11KB
+
+        println!("数据大小: {}B", data.len());
+
+        // 保存第一个文件
+        storage.save_version("file1", &data, None).await.unwrap();
+
+        // 保存第二个文件（完全相同的数据）
+        storage.save_version("file2", &data, None).await.unwrap();
+
+        // 等待优化完成才能看到去重效果
+        wait_for_optimization(&storage, "file1", 10).await.unwrap();
+        wait_for_optimization(&storage, "file2", 10).await.unwrap();
+
+        // 获取去重统计
+        let dedup_stats = storage.get_deduplication_stats().await.unwrap();
+
+        println!("跨文件去重统计: {:?}", dedup_stats);
+        println!(
+            "总块数: {}, 唯一块: {}, 重复块: {}",
+            dedup_stats.total_chunks, dedup_stats.new_chunks, dedup_stats.duplicate_chunks
+        );
+        println!(
+            "原始大小: {}B, 存储大小: {}B, 节省: {}B ({:.2}%)",
+            dedup_stats.original_size,
+            dedup_stats.stored_size,
+            dedup_stats.space_saved,
+            dedup_stats.dedup_ratio
+        );
+
+        // 验证跨文件去重效果
+        // 两个完全相同的文件，总块数应该是唯一块数的2倍
+        assert_eq!(
+            dedup_stats.total_chunks,
+            dedup_stats.new_chunks * 2,
+            "两个相同文件，总块数应该是唯一块数的2倍"
+        );
+        assert_eq!(
+            dedup_stats.duplicate_chunks, dedup_stats.new_chunks,
+            "重复块数应该等于唯一块数（因为文件完全相同）"
+        );
+        assert!(
+            dedup_stats.dedup_ratio >= 45.0,
+            "去重率应该接近 50%（两个相同文件）: {:.2}%",
+            dedup_stats.dedup_ratio
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_dedup_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(
+            temp_dir.path().to_path_buf(),
+            4096,
+            IncrementalConfig::default(),
+        );
+        storage.init().await.unwrap();
+
+        let base = crate::test_util::test_data(64 * 1024, 0);
+        let mut appended = base.clone();
+        appended.extend((0..8 * 1024usize).map(|i| (i * 7 % 251) as u8));
+        let mut edited = appended.clone();
+        edited[..64].fill(0xAB);
+
+        let mut parent: Option<String> = None;
+        for data in [&base, &appended, &edited] {
+            let (_, version) = storage
+                .save_version("doc", data, parent.as_deref())
+                .await
+                .unwrap();
+            parent = Some(version.version_id);
+        }
+
+        // 期望值：按块ID去重后的块大小之和
+        let mut distinct = HashMap::new();
+        let mut total_refs = 0;
+        for version in storage.list_file_versions("doc").await.unwrap() {
+            let delta = storage
+                .read_delta("doc", &version.version_id)
+                .await
+                .unwrap();
+            total_refs += delta.chunks.len();
+            for chunk in delta.chunks {
+                distinct.insert(chunk.chunk_id, chunk.size as u64);
+            }
+        }
+        let expected_physical: u64 = distinct.values().sum();
+
+        let report = storage.file_dedup_report("doc").await.unwrap();
+        assert_eq!(report.version_count, 3);
+        assert_eq!(
+            report.logical_size,
+            (base.len() + appended.len() + edited.len()) as u64
+        );
+        assert_eq!(report.total_chunk_refs, total_refs);
+        // 版本间共享的块只计一次
+        assert_eq!(report.unique_chunks, distinct.len());
+        assert!(report.unique_chunks < report.total_chunk_refs);
+        assert_eq!(report.shared_chunks, 0);
+        assert_eq!(report.physical_size, expected_physical);
+        assert_eq!(report.exclusive_size, expected_physical);
+        assert_eq!(
+            report.saved_bytes,
+            report.logical_size - report.physical_size
+        );
+        assert!(report.dedup_ratio > 50.0);
+
+        // 其他文件引用相同内容后，共享块按引用比例分摊
+        storage.save_version("copy", &base, None).await.unwrap();
+        let shared = storage.file_dedup_report("doc").await.unwrap();
+        assert!(shared.shared_chunks > 0);
+        assert!(shared.physical_size < report.physical_size);
+        assert!(shared.exclusive_size < report.exclusive_size);
+
+        assert!(matches!(
+            storage.file_dedup_report("missing").await,
+            Err(StorageError::FileNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_chunk_referrers() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        let data = b"chunk referrers test data. ".repeat(100);
+        let (delta_a, version_a) = storage.save_version("a.txt", &data, None).await.unwrap();
+        let (_, version_b) = storage.save_version("b.txt", &data, None).await.unwrap();
+        let chunk_id = &delta_a.chunks[0].chunk_id;
+
+        let mut expected = vec![
+            ("a.txt".to_string(), version_a.version_id.clone()),
+            ("b.txt".to_string(), version_b.version_id.clone()),
+        ];
+        expected.sort();
+        assert_eq!(storage.chunk_referrers(chunk_id).await.unwrap(), expected);
+
+        storage.permanently_delete_file("a.txt").await.unwrap();
+        assert_eq!(
+            storage.chunk_referrers(chunk_id).await.unwrap(),
+            vec![("b.txt".to_string(), version_b.version_id.clone())]
+        );
+
+        // 移动文件后引用者随之更新
+        storage.move_file("b.txt", "c.txt").await.unwrap();
+        assert_eq!(
+            storage.chunk_referrers(chunk_id).await.unwrap(),
+            vec![("c.txt".to_string(), version_b.version_id)]
+        );
+        assert!(storage.chunk_referrers("missing").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_weak_hash_dedup_index() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        let data = b"Weak hash prefilter test data. ".repeat(200);
+        let (delta, _) = storage.save_version("file_a", &data, None).await.unwrap();
+        assert!(delta.chunks.iter().all(|c| c.weak_hash != 0));
+
+        let stats = storage.get_dedup_index_stats().await;
+        assert_eq!(stats.chunk_count, delta.chunks.len());
+
+        // 相同内容再次写入：弱哈希命中后由强哈希确认
+        for chunk in &delta.chunks {
+            assert!(storage.chunk_exists(chunk.weak_hash, &chunk.chunk_id).await);
+        }
+        let before = storage.get_dedup_index_stats().await.strong_comparisons;
+        let (delta_b, _) = storage.save_version("file_b", &data, None).await.unwrap();
+        let after = storage.get_dedup_index_stats().await.strong_comparisons;
+        assert!(after > before);
+
+        // 引用计数中持久化了弱哈希
+        let metadata_db = storage.get_metadata_db().unwrap();
+        let ref_count = metadata_db
+            .get_chunk_ref(&delta_b.chunks[0].chunk_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ref_count.weak_hash, delta_b.chunks[0].weak_hash);
+        assert_eq!(ref_count.ref_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_gc_removes_chunks_from_dedup_index() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        let data = b"Dedup index GC test data. ".repeat(200);
+        let (delta, _) = storage.save_version("gc_file", &data, None).await.unwrap();
+        let chunk = delta.chunks[0].clone();
+        assert!(chunk.weak_hash != 0);
+
+        storage.permanently_delete_file("gc_file").await.unwrap();
+        storage.garbage_collect().await.unwrap();
+
+        // 回收的块从去重索引中移除；弱哈希未命中即判定不存在，不会被 Bloom Filter 的残留记录误判
+        assert_eq!(storage.get_dedup_index_stats().await.chunk_count, 0);
+        assert!(storage.chunk_bloom_filter.contains(&chunk.chunk_id).await);
+        assert!(!storage.chunk_exists(chunk.weak_hash, &chunk.chunk_id).await);
+
+        // 再次写入相同内容时重新写入块
+        let (delta, _) = storage.save_version("gc_file", &data, None).await.unwrap();
+        assert_eq!(storage.read_file("gc_file").await.unwrap(), data);
+        assert!(
+            storage
+                .chunk_exists(chunk.weak_hash, &delta.chunks[0].chunk_id)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconstruct_from_shuffled_chunk_list() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(
+            temp_dir.path().to_path_buf(),
+            4096,
+            IncrementalConfig::default(),
+        );
+        storage.init().await.unwrap();
+
+        let data = crate::test_util::test_data(64 * 1024, 0);
+        let (delta, version) = storage.save_version("shuffled", &data, None).await.unwrap();
+        assert!(delta.chunks.len() > 2);
+        assert!(delta.is_contiguous(data.len()));
+
+        // 打乱持久化的块列表顺序，读取路径必须按 offset 定位块
+        let mut stored = storage
+            .read_delta("shuffled", &version.version_id)
+            .await
+            .unwrap();
+        let half = stored.chunks.len() / 2;
+        stored.chunks.reverse();
+        stored.chunks.rotate_left(half);
+        assert!(!stored.is_contiguous(data.len()));
+        storage.save_delta("shuffled", &stored).await.unwrap();
+
+        assert_eq!(
+            storage
+                .read_version_data(&version.version_id)
+                .await
+                .unwrap(),
+            data
+        );
+        let mut reader = storage
+            .open_version_reader(&version.version_id)
+            .await
+            .unwrap();
+        assert_eq!(reader.read_to_end().await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_read_version_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(
+            temp_dir.path().to_path_buf(),
+            4096,
+            IncrementalConfig::default(),
+        );
+        storage.init().await.unwrap();
+
+        let data = crate::test_util::test_data(64 * 1024, 0);
+        let (delta, version) = storage.save_version("ranged", &data, None).await.unwrap();
+        assert!(delta.chunks.len() > 2);
+
+        // 跨越多个块的范围
+        let range = storage
+            .read_version_range(&version.version_id, 1000, 20_000)
+            .await
+            .unwrap();
+        assert_eq!(range, &data[1000..21_000]);
+
+        // 超出文件末尾时截断
+        let tail = storage
+            .read_version_range(&version.version_id, 60_000, 10_000)
+            .await
+            .unwrap();
+        assert_eq!(tail, &data[60_000..]);
+        assert!(
+            storage
+                .read_version_range(&version.version_id, 70_000, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dedup_index_spill_over_capacity() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            max_memory_index: 4,
+            ..Default::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+
+        let data = crate::test_util::test_data(64 * 1024, 0);
+        let (delta, _) = storage.save_version("file_a", &data, None).await.unwrap();
+        assert!(delta.chunks.len() > 4);
+
+        // 超出容量：最早写入的块已溢出出内存索引
+        let stats = storage.get_dedup_index_stats().await;
+        assert!(stats.chunk_count <= 4);
+        assert!(stats.spilled_chunks > 0);
+        let first = &delta.chunks[0];
+        assert!(storage.dedup_index.is_spilled(first.weak_hash).await);
+        assert!(
+            !storage
+                .dedup_index
+                .contains(first.weak_hash, &first.chunk_id)
+                .await
+        );
+
+        // 写入与首块内容相同的文件：回查 Sled 后仍正确去重
+        let first_data = &data[first.offset..first.offset + first.size];
+        let (delta_b, version_b) = storage
+            .save_version("file_b", first_data, None)
+            .await
+            .unwrap();
+        assert_eq!(delta_b.chunks.len(), 1);
+        assert_eq!(delta_b.chunks[0].chunk_id, first.chunk_id);
+
+        let ref_count = storage
+            .get_metadata_db()
+            .unwrap()
+            .get_chunk_ref(&first.chunk_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ref_count.ref_count, 2);
+        assert!(
+            storage
+                .dedup_index
+                .contains(first.weak_hash, &first.chunk_id)
+                .await
+        );
+        assert_eq!(
+            storage
+                .read_version_data(&version_b.version_id)
+                .await
+                .unwrap(),
+            first_data
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chunk_write_failure_leaves_no_partial_chunk() {
+        let temp_dir = TempDir::new().unwrap();
+        let chunk_store = Arc::new(LocalChunkStore::new(
+            temp_dir.path().join("incremental").join("chunks"),
+        ));
+        let storage = StorageManager::with_chunk_store(
+            temp_dir.path().to_path_buf(),
+            4 * 1024 * 1024,
+            IncrementalConfig::default(),
+            chunk_store.clone(),
+        );
+        storage.init().await.unwrap();
+
+        // 块写入到一半时磁盘写满
+        chunk_store.set_fail_writes(true);
+        let data = b"Data written while the disk is full".repeat(100);
+        let result = storage.save_version("full_disk_file", &data, None).await;
+        assert!(matches!(result, Err(StorageError::OutOfSpace(_))));
+
+        // 块目录中不应残留任何块文件或临时文件
+        let mut pending: Vec<PathBuf> = vec![storage.chunk_root.join("data")];
+        while let Some(dir) = pending.pop() {
+            if !dir.exists() {
+                continue;
+            }
+            let mut entries = fs::read_dir(&dir).await.unwrap();
+            while let Some(entry) = entries.next_entry().await.unwrap() {
+                let path = entry.path();
+                assert!(path.is_dir(), "残留部分写入的文件: {:?}", path);
+                pending.push(path);
+            }
+        }
+        assert!(!storage.file_exists("full_disk_file").await);
+
+        // 恢复后可以正常写入
+        chunk_store.set_fail_writes(false);
+        storage
+            .save_version("full_disk_file", &data, None)
+            .await
+            .unwrap();
+        assert_eq!(storage.read_file("full_disk_file").await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_small_file_packing_reduces_on_disk_files() {
+        async fn count_files(root: &Path) -> usize {
+            let mut count = 0;
+            let mut pending = vec![root.to_path_buf()];
+            while let Some(dir) = pending.pop() {
+                let mut entries = fs::read_dir(&dir).await.unwrap();
+                while let Some(entry) = entries.next_entry().await.unwrap() {
+                    if entry.file_type().await.unwrap().is_dir() {
+                        pending.push(entry.path());
+                    } else {
+                        count += 1;
+                    }
+                }
+            }
+            count
+        }
+
+        let mut file_counts = Vec::new();
+        for enable in [false, true] {
+            let temp_dir = TempDir::new().unwrap();
+            let config = IncrementalConfig {
+                packing: crate::PackConfig {
+                    enable,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+            storage.init().await.unwrap();
+
+            for i in 0..300 {
+                let data = format!("small file #{} with a little unique content", i);
+                storage
+                    .save_version(&format!("small_{}", i), data.as_bytes(), None)
+                    .await
+                    .unwrap();
+            }
+            for i in 0..300 {
+                assert_eq!(
+                    storage.read_file(&format!("small_{}", i)).await.unwrap(),
+                    format!("small file #{} with a little unique content", i).into_bytes()
+                );
+            }
+            file_counts.push(count_files(&storage.chunk_root).await);
+        }
+
+        // 未打包时每个块一个文件；打包后只有包文件与包索引
+        assert!(file_counts[0] >= 300);
+        assert!(
+            file_counts[1] * 10 < file_counts[0],
+            "打包后的文件数应远少于未打包: {:?}",
+            file_counts
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_and_read_with_memory_chunk_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let chunk_store = Arc::new(crate::chunk_store::MemoryChunkStore::new());
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            ..Default::default()
+        };
+        let storage = StorageManager::with_chunk_store(
+            temp_dir.path().to_path_buf(),
+            4 * 1024 * 1024,
+            config,
+            chunk_store.clone(),
+        );
+        storage.init().await.unwrap();
+
+        // 保存并读取多个版本
+        let data1 = b"Hello, World! This is a test.".repeat(200);
+        let (_, version1) = storage
+            .save_version("mem_file", &data1, None)
+            .await
+            .unwrap();
+        let mut data2 = data1.clone();
+        data2.extend_from_slice(b" appended tail");
+        let (_, version2) = storage
+            .save_version("mem_file", &data2, Some(&version1.version_id))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage
+                .read_version_data(&version1.version_id)
+                .await
+                .unwrap(),
+            data1
+        );
+        assert_eq!(
+            storage
+                .read_version_data(&version2.version_id)
+                .await
+                .unwrap(),
+            data2
+        );
+        assert_eq!(storage.read_file("mem_file").await.unwrap(), data2);
+        assert!(!chunk_store.is_empty().await);
+
+        // 相同内容的文件复用已有块
+        let chunk_count = chunk_store.len().await;
+        storage
+            .save_version("mem_copy", &data2, None)
+            .await
+            .unwrap();
+        assert_eq!(chunk_store.len().await, chunk_count);
+        assert_eq!(storage.read_file("mem_copy").await.unwrap(), data2);
+
+        // 块数据不落到本地块目录
+        assert!(!temp_dir.path().join("incremental/chunks/data").exists());
+
+        // 硬删除后不再被引用的块从块存储中擦除
+        storage.hard_delete_file("mem_copy").await.unwrap();
+        storage.hard_delete_file("mem_file").await.unwrap();
+        assert!(chunk_store.is_empty().await);
+
+        storage.stop_optimization_task().await;
+    }
+
+    /// 统计每个块实际写入次数的块存储
+    struct CountingChunkStore {
+        inner: crate::chunk_store::MemoryChunkStore,
+        writes: tokio::sync::Mutex<HashMap<String, usize>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ChunkStore for CountingChunkStore {
+        async fn put(&self, chunk_id: &str, data: &[u8]) -> Result<bool> {
+            // 放大写入窗口，让并发写入者更容易在此交错
+            tokio::task::yield_now().await;
+            let written = self.inner.put(chunk_id, data).await?;
+            if written {
+                *self
+                    .writes
+                    .lock()
+                    .await
+                    .entry(chunk_id.to_string())
+                    .or_default() += 1;
+            }
+            Ok(written)
+        }
+
+        async fn get(&self, chunk_id: &str) -> Result<Vec<u8>> {
+            self.inner.get(chunk_id).await
+        }
+
+        async fn exists(&self, chunk_id: &str) -> Result<bool> {
+            self.inner.exists(chunk_id).await
+        }
+
+        async fn delete(&self, chunk_id: &str) -> Result<Option<u64>> {
+            self.inner.delete(chunk_id).await
+        }
+
+        async fn list(&self) -> Result<Vec<String>> {
+            self.inner.list().await
+        }
+
+        async fn stored_size(&self, chunk_id: &str) -> Result<Option<u64>> {
+            self.inner.stored_size(chunk_id).await
+        }
+
+        fn location(&self, chunk_id: &str) -> PathBuf {
+            self.inner.location(chunk_id)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_identical_saves_write_each_chunk_once() {
+        const WRITERS: usize = 8;
+
+        let temp_dir = TempDir::new().unwrap();
+        let chunk_store = Arc::new(CountingChunkStore {
+            inner: crate::chunk_store::MemoryChunkStore::new(),
+            writes: tokio::sync::Mutex::new(HashMap::new()),
+        });
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            ..Default::default()
+        };
+        let storage = StorageManager::with_chunk_store(
+            temp_dir.path().to_path_buf(),
+            4 * 1024 * 1024,
+            config,
+            chunk_store.clone(),
+        );
+        storage.init().await.unwrap();
+
+        let data: Vec<u8> = (0..256 * 1024u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+        let handles: Vec<_> = (0..WRITERS)
+            .map(|i| {
+                let storage = storage.clone();
+                let data = data.clone();
+                tokio::spawn(async move {
+                    storage
+                        .save_version(&format!("same_{}", i), &data, None)
+                        .await
+                        .map(|(delta, _)| delta)
+                })
+            })
+            .collect();
+        let mut deltas = Vec::new();
+        for handle in handles {
+            deltas.push(handle.await.unwrap().unwrap());
+        }
+
+        // 每个块只写入一次
+        let writes = chunk_store.writes.lock().await;
+        assert!(!writes.is_empty());
+        assert!(writes.values().all(|count| *count == 1), "{:?}", writes);
+
+        // 所有副本都可读，且每个块的引用计数等于引用它的保存次数
+        for i in 0..WRITERS {
+            assert_eq!(
+                storage.read_file(&format!("same_{}", i)).await.unwrap(),
+                data
+            );
+        }
+        let metadata_db = storage.get_metadata_db().unwrap();
+        let mut expected_refs: HashMap<&str, usize> = HashMap::new();
+        for delta in &deltas {
+            for chunk in &delta.chunks {
+                *expected_refs.entry(chunk.chunk_id.as_str()).or_default() += 1;
+            }
+        }
+        assert_eq!(expected_refs.len(), writes.len());
+        for (chunk_id, expected) in expected_refs {
+            let chunk_ref = metadata_db.get_chunk_ref(chunk_id).unwrap().unwrap();
+            assert_eq!(chunk_ref.ref_count, expected);
+        }
+
+        storage.stop_optimization_task().await;
+    }
+
+    #[tokio::test]
+    async fn test_recompute_usage_after_shared_file_deleted() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 两个文件内容相同，共享全部块
+        let data = b"Shared content for usage accounting. ".repeat(4000);
+        storage.save_version("usage_a", &data, None).await.unwrap();
+        storage.save_version("usage_b", &data, None).await.unwrap();
+
+        let summary = storage.recompute_usage().await.unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.logical_size, 2 * data.len() as u64);
+        assert!(summary.physical_size > 0);
+        assert!(summary.physical_size < data.len() as u64);
+        assert_eq!(summary.unreferenced_size, 0);
+
+        // 共享块由两个文件平分（按块向下取整）
+        let a = storage.get_file_info("usage_a").await.unwrap();
+        let b = storage.get_file_info("usage_b").await.unwrap();
+        assert_eq!(a.attributed_size, b.attributed_size);
+        assert!(a.attributed_size + b.attributed_size <= summary.physical_size);
+        assert!(
+            a.attributed_size + b.attributed_size + 2 * summary.referenced_chunks as u64
+                > summary.physical_size
+        );
+
+        // 删除其中一个文件后，剩余文件独占全部块
+        storage.hard_delete_file("usage_b").await.unwrap();
+        let summary = storage.recompute_usage().await.unwrap();
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.logical_size, data.len() as u64);
+
+        let a = storage.get_file_info("usage_a").await.unwrap();
+        assert_eq!(a.attributed_size, summary.physical_size);
+        let version = storage
+            .get_version_info(&a.latest_version_id)
+            .await
+            .unwrap();
+        assert_eq!(version.storage_size, summary.physical_size);
+    }
+
+    #[tokio::test]
+    async fn test_verify_chunks() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 创建测试数据
+        let data = b"Test chunk verification data";
+        storage.save_version("test_file", data, None).await.unwrap();
+
+        // 等待优化完成才会有chunks
+        wait_for_optimization(&storage, "test_file", 10)
+            .await
+            .unwrap();
+
+        // 验证所有 chunks
+        let report = storage.verify_all_chunks().await.unwrap();
+        assert_eq!(report.valid + report.invalid + report.missing, report.total);
+        // 正常情况下应该所有 chunks 都是有效的
+        assert!(report.valid > 0, "应该有有效的 chunks");
+        assert_eq!(report.invalid, 0, "不应该有损坏的 chunk");
+    }
+
+    #[tokio::test]
+    async fn test_verify_chunks_with_salted_namespace() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            namespace_salts: [("tenant_a".to_string(), "salt-a".to_string())]
+                .into_iter()
+                .collect(),
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 64 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 加盐块的 ID 不是块数据的 SHA-256，且可压缩数据以压缩形式存储
+        let tenant = storage.namespace(crate::namespace::Namespace::new("tenant_a").unwrap());
+        let data = b"salted chunk verification ".repeat(4096);
+        let meta = tenant.save_file("salted.bin", &data).await.unwrap();
+        let file_id = tenant.namespace().scope(&meta.id);
+        wait_for_optimization(&storage, &file_id, 10).await.unwrap();
+        let entry = storage
+            .with_namespace_access()
+            .get_file_info(&file_id)
+            .await
+            .unwrap();
+        let delta = storage
+            .read_delta(&file_id, &entry.latest_version_id)
+            .await
+            .unwrap();
+        assert!(!delta.chunks.is_empty());
+        assert!(delta.chunks.iter().all(|c| c.chunk_id != c.strong_hash));
+
+        let report = storage.verify_all_chunks().await.unwrap();
+        assert!(report.valid > 0);
+        assert_eq!(report.invalid, 0, "加盐块不应被判定为损坏");
+
+        let ids: Vec<String> = delta.chunks.iter().map(|c| c.chunk_id.clone()).collect();
+        let report = storage.verify_chunks(&ids).await.unwrap();
+        assert_eq!(report.valid, ids.len());
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 创建测试文件
+        let data = b"Test data for soft delete";
+        storage.save_version("test_file", data, None).await.unwrap();
+
+        // 软删除文件
+        storage.delete_file("test_file").await.unwrap();
+
+        // 文件应该不在普通列表中
+        let files = storage.list_files().await.unwrap();
+        assert!(!files.contains(&"test_file".to_string()));
+
+        // 但应该在已删除列表中
+        let deleted_files = storage.list_deleted_files().await.unwrap();
+        assert_eq!(deleted_files.len(), 1);
+        assert_eq!(deleted_files[0].file_id, "test_file");
+        assert!(deleted_files[0].is_deleted);
+        assert!(deleted_files[0].deleted_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_restore_file() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 创建并删除文件
+        storage
+            .save_version("test_file", b"Test data", None)
+            .await
+            .unwrap();
+        storage.delete_file("test_file").await.unwrap();
+
+        // 恢复文件
+        storage.restore_file("test_file").await.unwrap();
+
+        // 文件应该回到普通列表
+        let files = storage.list_files().await.unwrap();
+        assert!(files.contains(&"test_file".to_string()));
+
+        // 不应该在已删除列表中
+        let deleted_files = storage.list_deleted_files().await.unwrap();
+        assert_eq!(deleted_files.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_empty_recycle_bin() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 创建并删除多个文件
+        storage
+            .save_version("file1", b"Data 1", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("file2", b"Data 2", None)
+            .await
+            .unwrap();
+        storage.delete_file("file1").await.unwrap();
+        storage.delete_file("file2").await.unwrap();
+
+        // 确认有已删除的文件
+        let deleted_files = storage.list_deleted_files().await.unwrap();
+        assert_eq!(deleted_files.len(), 2);
+
+        // 清空回收站
+        let count = storage.empty_recycle_bin().await.unwrap();
+        assert_eq!(count, 2);
+
+        // 已删除列表应该为空
+        let deleted_files = storage.list_deleted_files().await.unwrap();
+        assert_eq!(deleted_files.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired_recycle_bin_with_mock_clock() {
+        let (storage, _temp) = create_test_storage().await;
+        let clock = Arc::new(silent_nas_core::MockClock::default());
+        let storage = storage.with_clock(clock.clone());
+        storage.init().await.unwrap();
+
+        storage.save_file("old_file", b"Data 1").await.unwrap();
+        storage.save_file("new_file", b"Data 2").await.unwrap();
+        storage.delete_file("old_file").await.unwrap();
+        clock.advance(chrono::Duration::days(5));
+        storage.delete_file("new_file").await.unwrap();
+        clock.advance(chrono::Duration::days(3));
+
+        // 只有超过 7 天保留期的文件被永久删除
+        let count = storage
+            .purge_expired_recycle_bin(chrono::Duration::days(7))
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        let deleted_files = storage.list_deleted_files().await.unwrap();
+        assert_eq!(deleted_files.len(), 1);
+        assert_eq!(deleted_files[0].file_id, "new_file");
+    }
+
+    #[tokio::test]
+    async fn test_retention_lock_blocks_deletion_until_expiry() {
+        use silent_nas_core::Clock;
+
+        let (storage, _temp) = create_test_storage().await;
+        let clock = Arc::new(silent_nas_core::MockClock::default());
+        let storage = storage.with_clock(clock.clone());
+        storage.init().await.unwrap();
+
+        let (_, v1) = storage.save_version("worm.txt", b"v1", None).await.unwrap();
+        storage
+            .save_version("worm.txt", b"v2", Some(&v1.version_id))
+            .await
+            .unwrap();
+
+        let lock = RetentionLock {
+            until: clock.now_naive() + chrono::Duration::days(30),
+            mode: RetentionMode::Compliance,
+        };
+        storage
+            .set_retention("worm.txt", lock, false)
+            .await
+            .unwrap();
+        assert_eq!(storage.get_retention("worm.txt").await.unwrap(), Some(lock));
+
+        // 保留期内不能删除、永久删除、移动，也不能删除旧版本
+        let locked = |r: Result<()>| matches!(r, Err(StorageError::RetentionLocked(_)));
+        assert!(locked(storage.delete_file("worm.txt").await));
+        assert!(locked(storage.permanently_delete_file("worm.txt").await));
+        assert!(locked(
+            storage.move_file("worm.txt", "moved.txt").await.map(|_| ())
+        ));
+        assert!(locked(storage.delete_file_version(&v1.version_id).await));
+        assert!(matches!(
+            storage.hard_delete_file("worm.txt").await,
+            Err(StorageError::RetentionLocked(_))
+        ));
+        assert_eq!(
+            storage.list_file_versions("worm.txt").await.unwrap().len(),
+            2
+        );
+
+        // 可以写入新版本，保留锁随文件保留
+        storage.save_version("worm.txt", b"v3", None).await.unwrap();
+        assert_eq!(storage.get_retention("worm.txt").await.unwrap(), Some(lock));
+
+        // 合规模式即使管理员也不能缩短、降级或解除，只能延长
+        let shorter = RetentionLock {
+            until: clock.now_naive() + chrono::Duration::days(1),
+            ..lock
+        };
+        assert!(locked(
+            storage.set_retention("worm.txt", shorter, true).await
+        ));
+        let downgraded = RetentionLock {
+            mode: RetentionMode::Governance,
+            ..lock
+        };
+        assert!(locked(
+            storage.set_retention("worm.txt", downgraded, true).await
+        ));
+        assert!(locked(storage.remove_retention("worm.txt", true).await));
+        let extended = RetentionLock {
+            until: lock.until + chrono::Duration::days(10),
+            ..lock
+        };
+        storage
+            .set_retention("worm.txt", extended, false)
+            .await
+            .unwrap();
+
+        // 到期后可以删除
+        clock.advance(chrono::Duration::days(39));
+        assert!(locked(storage.delete_file("worm.txt").await));
+        clock.advance(chrono::Duration::days(2));
+        storage.delete_file_version(&v1.version_id).await.unwrap();
+        storage.delete_file("worm.txt").await.unwrap();
+
+        // 治理模式：缩短或解除需要管理员
+        storage
+            .save_version("gov.txt", b"data", None)
+            .await
+            .unwrap();
+        let lock = RetentionLock {
+            until: clock.now_naive() + chrono::Duration::days(30),
+            mode: RetentionMode::Governance,
+        };
+        storage.set_retention("gov.txt", lock, false).await.unwrap();
+        let shorter = RetentionLock {
+            until: clock.now_naive() + chrono::Duration::days(1),
+            ..lock
+        };
+        assert!(locked(
+            storage.set_retention("gov.txt", shorter, false).await
+        ));
+        assert!(locked(storage.remove_retention("gov.txt", false).await));
+        assert!(locked(storage.delete_file("gov.txt").await));
+        storage
+            .set_retention("gov.txt", shorter, true)
+            .await
+            .unwrap();
+        storage.remove_retention("gov.txt", true).await.unwrap();
+        storage.delete_file("gov.txt").await.unwrap();
+
+        // 截止时间必须晚于当前时间
+        storage
+            .save_version("past.txt", b"data", None)
+            .await
+            .unwrap();
+        let expired = RetentionLock {
+            until: clock.now_naive() - chrono::Duration::days(1),
+            mode: RetentionMode::Compliance,
+        };
+        assert!(
+            storage
+                .set_retention("past.txt", expired, false)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_permanently_delete_file() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 创建测试文件
+        let data = b"Test data for permanent delete";
+        storage.save_version("test_file", data, None).await.unwrap();
+
+        // 永久删除文件
+        storage.permanently_delete_file("test_file").await.unwrap();
+
+        // 文件不应该存在
+        assert!(!storage.file_exists("test_file").await);
+    }
+
+    #[tokio::test]
+    async fn test_hard_delete_file() {
+        /// 记录被擦除文件的钩子
+        #[derive(Default)]
+        struct RecordingHook(std::sync::Mutex<Vec<String>>);
+
+        #[async_trait]
+        impl EraseHook for RecordingHook {
+            async fn erase(&self, file_id: &str) -> Result<()> {
+                self.0.lock().unwrap().push(file_id.to_string());
+                Ok(())
+            }
+        }
+
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+        let hook = Arc::new(RecordingHook::default());
+        storage.add_erase_hook(hook.clone());
+
+        // 两个版本的文件 + 一个共享内容的文件
+        let (_delta1, version1) = storage
+            .save_version("secret_file", b"Personal data v1", None)
+            .await
+            .unwrap();
+        let (delta2, _version2) = storage
+            .save_version(
+                "secret_file",
+                b"Personal data v2",
+                Some(&version1.version_id),
+            )
+            .await
+            .unwrap();
+        storage
+            .save_version("other_file", b"Personal data v2", None)
+            .await
+            .unwrap();
+
+        let erased = storage.hard_delete_file("secret_file").await.unwrap();
+        assert_eq!(*hook.0.lock().unwrap(), vec!["secret_file".to_string()]);
+
+        // 文件不在列表中，也不在回收站中，版本全部消失
+        assert!(
+            !storage
+                .list_files()
+                .await
+                .unwrap()
+                .contains(&"secret_file".to_string())
+        );
+        assert!(storage.list_deleted_files().await.unwrap().is_empty());
+        assert!(
+            storage
+                .list_file_versions("secret_file")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(storage.restore_file("secret_file").await.is_err());
+
+        // 仅被该文件引用的块被立即擦除，共享块保留
+        assert_eq!(erased, 1);
+        let shared_chunk = &delta2.chunks[0].chunk_id;
+        assert!(storage.chunk_store.exists(shared_chunk).await.unwrap());
+        assert_eq!(
+            storage.read_file("other_file").await.unwrap(),
+            b"Personal data v2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hard_delete_reports_hook_failure() {
+        /// 总是失败的钩子
+        struct FailingHook;
+
+        #[async_trait]
+        impl EraseHook for FailingHook {
+            async fn erase(&self, file_id: &str) -> Result<()> {
+                Err(StorageError::Storage(format!("索引不可用: {}", file_id)))
+            }
+        }
+
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+        storage.add_erase_hook(Arc::new(FailingHook));
+        let (delta, _) = storage
+            .save_version("secret_file", b"Personal data", None)
+            .await
+            .unwrap();
+
+        // 钩子失败时返回错误，但文件与块仍已擦除
+        assert!(storage.hard_delete_file("secret_file").await.is_err());
+        assert!(!storage.file_exists("secret_file").await);
+        for chunk in &delta.chunks {
+            assert!(!storage.chunk_store.exists(&chunk.chunk_id).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_garbage_collect_blocks_with_dedup() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_compression: false,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 创建测试文件
+        let data1 = b"Test data 1 for garbage collection";
+        let data2 = b"Test data 2 for garbage collection";
+        storage.save_version("file1", data1, None).await.unwrap();
+        storage.save_version("file2", data2, None).await.unwrap();
+
+        // 永久删除文件1
+        storage.permanently_delete_file("file1").await.unwrap();
+
+        // 运行GC
+        let _deleted_count = storage.garbage_collect_blocks().await.unwrap();
+
+        // 应该清理了一些块
+        // 注意：具体数量取决于分块策略
+        // GC应该成功完成，不需要检查具体数量
+    }
+
+    #[tokio::test]
+    async fn test_garbage_collect_blocks_without_dedup() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_compression: false,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 创建测试文件
+        let data = b"Test data for garbage collection without dedup";
+        storage.save_version("file1", data, None).await.unwrap();
+
+        // 永久删除文件
+        storage.permanently_delete_file("file1").await.unwrap();
+
+        // 运行GC
+        let _deleted_count = storage.garbage_collect_blocks().await.unwrap();
+
+        // 应该清理了一些块
+        // GC应该成功完成，不需要检查具体数量
+    }
+
+    #[tokio::test]
+    async fn test_delete_already_deleted_file() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 创建并删除文件
+        storage
+            .save_version("test_file", b"Test data", None)
+            .await
+            .unwrap();
+        storage.delete_file("test_file").await.unwrap();
+
+        // 尝试再次删除应该失败
+        let result = storage.delete_file("test_file").await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_files_excludes_deleted() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 创建多个文件
+        storage
+            .save_version("file1", b"Data 1", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("file2", b"Data 2", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("file3", b"Data 3", None)
+            .await
+            .unwrap();
+
+        // 删除file2
+        storage.delete_file("file2").await.unwrap();
+
+        // list_files应该只返回file1和file3
+        let files = storage.list_files().await.unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files.contains(&"file1".to_string()));
+        assert!(!files.contains(&"file2".to_string()));
+        assert!(files.contains(&"file3".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_gc_task_start_stop() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_auto_gc: false, // 手动控制GC任务
+            gc_interval_secs: 1,   // 1秒间隔用于快速测试
+            enable_compression: false,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 启动GC任务
+        storage.start_gc_task().await;
+
+        // 等待一小段时间
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        // 停止GC任务
+        storage.stop_gc_task().await;
+
+        // 验证任务已停止
+        assert!(storage.gc_task_handle.read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_auto_gc_on_init() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_auto_gc: true, // 自动启动GC
+            gc_interval_secs: 1,  // 1秒间隔用于快速测试
+            enable_compression: false,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 验证GC任务已启动
+        assert!(storage.gc_task_handle.read().await.is_some());
+
+        // 停止GC任务
+        storage.stop_gc_task().await;
+    }
+
+    #[tokio::test]
+    async fn test_gc_runs_only_on_elected_leader() {
+        let lease_dir = TempDir::new().unwrap();
+        let mut nodes = Vec::new();
+        for node_id in ["node-a", "node-b"] {
+            let temp_dir = TempDir::new().unwrap();
+            let config = IncrementalConfig {
+                enable_auto_gc: false,
+                gc_election: crate::GcElectionConfig {
+                    enable: true,
+                    node_id: node_id.to_string(),
+                    lease_dir: Some(lease_dir.path().to_path_buf()),
+                    lease_ttl_secs: 60,
+                },
+                ..IncrementalConfig::default()
+            };
+            let storage =
+                StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+            storage.init().await.unwrap();
+            storage
+                .save_version("file1", node_id.as_bytes(), None)
+                .await
+                .unwrap();
+            storage.permanently_delete_file("file1").await.unwrap();
+            nodes.push((storage, temp_dir));
+        }
+        let (a, b) = (&nodes[0].0, &nodes[1].0);
+        let orphans = |storage: &StorageManager| {
+            storage
+                .get_metadata_db()
+                .unwrap()
+                .list_orphaned_chunks()
+                .unwrap()
+                .len()
+        };
+        assert!(orphans(b) > 0);
+
+        // 先获取租约的节点成为主节点并执行 GC
+        assert!(a.is_gc_leader().await);
+        assert!(a.garbage_collect_blocks().await.unwrap() > 0);
+        assert_eq!(orphans(a), 0);
+
+        // 非主节点拒绝全局 GC，未引用的块保持不动，读写不受影响
+        let before = orphans(b);
+        assert!(!b.is_gc_leader().await);
+        assert!(matches!(
+            b.garbage_collect_blocks().await,
+            Err(StorageError::NotLeader(_))
+        ));
+        assert!(matches!(
+            b.garbage_collect().await,
+            Err(StorageError::NotLeader(_))
+        ));
+        assert_eq!(orphans(b), before);
+        b.save_version("file2", b"still writable", None)
+            .await
+            .unwrap();
+
+        // 主节点关闭时释放租约，另一节点接管
+        a.shutdown().await.unwrap();
+        assert!(b.is_gc_leader().await);
+        assert!(b.garbage_collect_blocks().await.unwrap() > 0);
+        assert_eq!(orphans(b), 0);
+        assert!(!a.is_gc_leader().await);
+
+        b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_manual_gc_trigger() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            enable_compression: false,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 创建测试文件
+        storage
+            .save_version("file1", b"Test data", None)
+            .await
+            .unwrap();
+
+        // 永久删除文件
+        storage.permanently_delete_file("file1").await.unwrap();
+
+        // 手动触发GC
+        let _deleted_count = storage.garbage_collect_blocks().await.unwrap();
+
+        // GC应该成功完成
+        // 不需要检查具体数量
+    }
+
+    #[tokio::test]
+    async fn test_gc_task_periodic_execution() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            gc_interval_secs: 1, // 1秒间隔用于快速测试
+            enable_compression: false,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 创建并删除文件
+        storage
+            .save_version("file1", b"Test data", None)
+            .await
+            .unwrap();
+        storage.permanently_delete_file("file1").await.unwrap();
+
+        // 启动GC任务
+        storage.start_gc_task().await;
+
+        // 等待至少一次GC执行
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+
+        // 停止GC任务
+        storage.stop_gc_task().await;
+
+        // GC任务应该已经执行过，清理了块
+        // 这里只验证任务能正常启动和停止
+    }
+
+    #[tokio::test]
+    async fn test_chunked_storage_upload_and_read() {
+        // 测试分块存储模式的上传和读取（新架构，直接分块+去重）
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_compression: false,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 上传测试数据（直接分块存储）
+        let test_data = b"Hello from chunked storage! This is a test file.";
+        let (delta, version) = storage
+            .save_version("test_chunked_file", test_data, None)
+            .await
+            .unwrap();
+
+        // 验证有分块（即时分块存储）
+        assert!(!delta.chunks.is_empty(), "分块存储应该有chunks");
+        assert_eq!(version.size, test_data.len() as u64);
+
+        // 验证文件索引的存储模式
+        let metadata_db = storage.get_metadata_db().unwrap();
+        let file_entry = metadata_db
+            .get_file_index("test_chunked_file")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            file_entry.storage_mode,
+            crate::StorageMode::Chunked,
+            "应该是分块存储模式"
+        );
+        assert_eq!(
+            file_entry.optimization_status,
+            crate::OptimizationStatus::Completed,
+            "应该是已完成状态"
+        );
+
+        // 读取数据（从分块存储读取）
+        let read_data = storage
+            .read_version_data(&version.version_id)
+            .await
+            .unwrap();
+        assert_eq!(read_data, test_data, "读取的数据应该与原始数据一致");
+    }
+
+    #[tokio::test]
+    async fn test_chunked_storage_with_deduplication() {
+        // 测试启用去重的分块存储（新架构）
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_compression: false,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 上传测试数据（直接分块+去重存储）
+        let test_data = b"Hello from chunked storage! This is a test file for chunking with dedup.";
+        let (delta, version) = storage
+            .save_version("test_dedup_file", test_data, None)
+            .await
+            .unwrap();
+
+        // 验证有分块
+        assert!(!delta.chunks.is_empty(), "应该有分块");
+
+        // 验证文件索引的存储模式
+        let metadata_db = storage.get_metadata_db().unwrap();
+        let file_entry = metadata_db
+            .get_file_index("test_dedup_file")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            file_entry.storage_mode,
+            crate::StorageMode::Chunked,
+            "应该是分块存储模式"
+        );
+
+        // 读取数据（从分块读取）
+        let read_data = storage
+            .read_version_data(&version.version_id)
+            .await
+            .unwrap();
+        assert_eq!(read_data, test_data, "读取的数据应该与原始数据一致");
+    }
+
+    #[tokio::test]
+    async fn test_stream_upload() {
+        // 测试流式上传到分块存储
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 创建测试数据流
+        let test_data = b"Streaming data to chunked storage! This is a larger test file.".repeat(100);
+        let mut cursor = std::io::Cursor::new(test_data.clone());
+
+        // 流式上传
+        let (delta, version) = storage
+            .save_version_from_reader("test_stream_file", &mut cursor, None)
+            .await
+            .unwrap();
+
+        // 验证分块存储
+        assert!(!delta.chunks.is_empty(), "分块存储应该有chunks");
+
+        // 读取并验证数据
+        let read_data = storage
+            .read_version_data(&version.version_id)
+            .await
+            .unwrap();
+        assert_eq!(read_data, test_data, "流式上传的数据应该正确");
+    }
+
+    #[tokio::test]
+    async fn test_immediate_chunked_storage() {
+        // 测试即时分块存储功能（新架构：直接分块+去重，无需后台优化）
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_compression: true,
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 上传一个较大的文件（直接分块存储）
+        let test_data = b"This is test data for immediate chunked storage. ".repeat(1000); // ~50KB
+        let (delta, version) = storage
+            .save_version("test_chunked_file", &test_data, None)
+            .await
+            .unwrap();
+
+        // 验证直接使用分块存储（有chunks）
+        assert!(!delta.chunks.is_empty(), "分块存储应该有chunks");
+
+        // 验证文件索引的状态
+        let metadata_db = storage.get_metadata_db().unwrap();
+        let file_entry = metadata_db
+            .get_file_index("test_chunked_file")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            file_entry.storage_mode,
+            crate::StorageMode::Chunked,
+            "应该是分块存储模式"
+        );
+        assert_eq!(
+            file_entry.optimization_status,
+            crate::OptimizationStatus::Completed,
+            "优化状态应该是已完成（即时分块不需要后台优化）"
+        );
+
+        // 验证可以正确读取数据
+        let read_data = storage
+            .read_version_data(&version.version_id)
+            .await
+            .unwrap();
+        assert_eq!(read_data, test_data, "应该能正确读取数据");
+
+        // 关闭存储
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_optimization_api_error_cases() {
+        // 测试优化API的错误情况
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            ..IncrementalConfig::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        storage.init().await.unwrap();
+
+        // 上传文件（先到热存储，然后等待优化完成）
+        let test_data = b"Test data for error cases";
+        storage
+            .save_version("test_error", test_data, None)
+            .await
+            .unwrap();
+
+        // 等待优化完成（变成冷存储）
+        wait_for_optimization(&storage, "test_error", 10)
+            .await
+            .unwrap();
+
+        // 尝试触发已在冷存储的文件的优化（应该失败或者已经是Completed状态）
+        let result = storage.trigger_file_optimization("test_error").await;
+        // 如果文件已经是Completed状态，可能会返回错误或者忽略
+        if result.is_err() {
+            println!("触发已完成优化的文件返回错误（符合预期）");
+        }
+
+        // 尝试触发不存在的文件（应该失败）
+        let result_not_found = storage.trigger_file_optimization("non_existent").await;
+        assert!(result_not_found.is_err(), "触发不存在的文件应该失败");
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hot_storage_bytes_tracks_unoptimized_files() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+
+        // 模拟尚未优化的旧热存储文件
+        let files: Vec<(&str, Vec<u8>)> = vec![
+            ("hot/a.txt", b"hot storage text ".repeat(2048)),
+            ("hb.log", b"pending optimization ".repeat(512)),
+        ];
+        let now = Local::now().naive_local();
+        let metadata_db = storage.get_metadata_db().unwrap();
+        for (file_id, data) in &files {
+            let hot_path = storage.get_hot_storage_path(file_id);
+            fs::create_dir_all(hot_path.parent().unwrap())
+                .await
+                .unwrap();
+            fs::write(&hot_path, data).await.unwrap();
+            #[allow(deprecated)]
+            let entry = FileIndexEntry {
+                file_id: file_id.to_string(),
+                latest_version_id: format!("{}-v1", file_id),
+                version_count: 1,
+                created_at: now,
+                modified_at: now,
+                is_deleted: false,
+                deleted_at: None,
+                storage_mode: crate::StorageMode::Hot,
+                optimization_status: crate::OptimizationStatus::Pending,
+                file_size: data.len() as u64,
+                file_hash: storage.calculate_hash(data),
+                user_metadata: HashMap::new(),
+                optimization_strategy: None,
+                optimized_at: None,
+                space_saved: 0,
+                stored_size: 0,
+                attributed_size: 0,
+                alias_target: None,
+                compression: None,
+                retention: None,
+            };
+            metadata_db.put_file_index(file_id, &entry).unwrap();
+        }
+
+        // 分块存储的文件不计入
+        storage
+            .save_version("chunked.txt", b"already chunked", None)
+            .await
+            .unwrap();
+
+        let total: u64 = files.iter().map(|(_, data)| data.len() as u64).sum();
+        assert_eq!(storage.hot_storage_bytes().await.unwrap(), total);
+
+        for (file_id, data) in &files {
+            let file_type = crate::core::FileType::detect(data);
+            let mut task = crate::OptimizationTask::new(
+                file_id.to_string(),
+                storage.get_hot_storage_path(file_id),
+                data.len() as u64,
+                storage.calculate_hash(data),
+                crate::OptimizationStrategy::decide(&file_type, data.len() as u64),
+                0,
+            );
+            storage.execute_optimization_task(&mut task).await.unwrap();
+        }
+        assert_eq!(storage.hot_storage_bytes().await.unwrap(), 0);
+
+        storage.shutdown().await.unwrap();
+    }
+
+    /// 写入时损坏块内容的块存储（模拟分块或压缩实现的缺陷）
+    struct CorruptingChunkStore {
+        inner: crate::chunk_store::MemoryChunkStore,
+    }
+
+    #[async_trait::async_trait]
+    impl ChunkStore for CorruptingChunkStore {
+        async fn put(&self, chunk_id: &str, data: &[u8]) -> Result<bool> {
+            let mut corrupted = data.to_vec();
+            if let Some(byte) = corrupted.last_mut() {
+                *byte ^= 0xff;
+            }
+            self.inner.put(chunk_id, &corrupted).await
+        }
+
+        async fn get(&self, chunk_id: &str) -> Result<Vec<u8>> {
+            self.inner.get(chunk_id).await
+        }
+
+        async fn exists(&self, chunk_id: &str) -> Result<bool> {
+            self.inner.exists(chunk_id).await
+        }
+
+        async fn delete(&self, chunk_id: &str) -> Result<Option<u64>> {
+            self.inner.delete(chunk_id).await
+        }
+
+        async fn list(&self) -> Result<Vec<String>> {
+            self.inner.list().await
+        }
+
+        async fn stored_size(&self, chunk_id: &str) -> Result<Option<u64>> {
+            self.inner.stored_size(chunk_id).await
+        }
+
+        fn location(&self, chunk_id: &str) -> PathBuf {
+            self.inner.location(chunk_id)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_broken_chunk_write_keeps_hot_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let chunk_store = Arc::new(CorruptingChunkStore {
+            inner: crate::chunk_store::MemoryChunkStore::new(),
+        });
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            ..Default::default()
+        };
+        let storage = StorageManager::with_chunk_store(
+            temp_dir.path().to_path_buf(),
+            4096,
+            config,
+            chunk_store.clone(),
+        );
+        storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+
+        let file_id = "broken.txt";
+        let data = b"payload that must survive a broken optimizer ".repeat(1024);
+        let hot_path = storage.get_hot_storage_path(file_id);
+        fs::create_dir_all(hot_path.parent().unwrap())
+            .await
+            .unwrap();
+        fs::write(&hot_path, &data).await.unwrap();
+        let now = Local::now().naive_local();
+        #[allow(deprecated)]
+        let entry = FileIndexEntry {
+            file_id: file_id.to_string(),
+            latest_version_id: format!("{}-v1", file_id),
+            version_count: 1,
+            created_at: now,
+            modified_at: now,
+            is_deleted: false,
+            deleted_at: None,
+            storage_mode: crate::StorageMode::Hot,
+            optimization_status: crate::OptimizationStatus::Pending,
+            file_size: data.len() as u64,
+            file_hash: storage.calculate_hash(&data),
+            user_metadata: HashMap::new(),
+            optimization_strategy: None,
+            optimized_at: None,
+            space_saved: 0,
+            stored_size: 0,
+            attributed_size: 0,
+            alias_target: None,
+            compression: None,
+            retention: None,
+        };
+        storage
+            .get_metadata_db()
+            .unwrap()
+            .put_file_index(file_id, &entry)
+            .unwrap();
+
+        let mut task = crate::OptimizationTask::new(
+            file_id.to_string(),
+            hot_path.clone(),
+            data.len() as u64,
+            storage.calculate_hash(&data),
+            crate::OptimizationStrategy::Full,
+            0,
+        );
+        assert!(storage.execute_optimization_task(&mut task).await.is_err());
+        assert_eq!(task.status, crate::OptimizationStatus::Failed);
+
+        // 热存储文件与文件索引保持不变，损坏的块已被删除
+        assert_eq!(fs::read(&hot_path).await.unwrap(), data);
+        #[allow(deprecated)]
+        let expected_mode = crate::StorageMode::Hot;
+        assert_eq!(
+            storage.get_file_info(file_id).await.unwrap().storage_mode,
+            expected_mode
+        );
+        assert!(chunk_store.inner.is_empty().await);
+        assert!(
+            storage
+                .get_metadata_db()
+                .unwrap()
+                .list_all_chunks()
+                .unwrap()
+                .is_empty()
+        );
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_version_with_forced_mode() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+        let data = b"forced storage mode payload ".repeat(2048);
+
+        // 强制整文件压缩
+        let (delta, compressed_version) = storage
+            .save_version_with_mode("archive.log", &data, None, crate::StorageMode::Compressed)
+            .await
+            .unwrap();
+        assert!(delta.chunks.is_empty());
+        let compressed_path = storage.get_compressed_version_path(&compressed_version.version_id);
+        assert!(compressed_path.exists());
+        assert!(fs::metadata(&compressed_path).await.unwrap().len() < data.len() as u64);
+        let info = storage.get_file_info("archive.log").await.unwrap();
+        assert_eq!(info.storage_mode, crate::StorageMode::Compressed);
+        assert_eq!(storage.read_file("archive.log").await.unwrap(), data);
+
+        // 强制分块：同一文件改为分块后，压缩版本仍可读取
+        let (delta, _) = storage
+            .save_version_with_mode("archive.log", &data, None, crate::StorageMode::Chunked)
+            .await
+            .unwrap();
+        assert!(!delta.chunks.is_empty());
+        let info = storage.get_file_info("archive.log").await.unwrap();
+        assert_eq!(info.storage_mode, crate::StorageMode::Chunked);
+        assert_eq!(storage.read_file("archive.log").await.unwrap(), data);
+        assert_eq!(
+            storage
+                .read_version_data(&compressed_version.version_id)
+                .await
+                .unwrap(),
+            data
+        );
+
+        // 删除压缩版本时一并删除压缩文件
+        storage
+            .delete_file_version(&compressed_version.version_id)
+            .await
+            .unwrap();
+        assert!(!compressed_path.exists());
+
+        // 已弃用的模式被拒绝
+        #[allow(deprecated)]
+        let hot = crate::StorageMode::Hot;
+        let err = storage
+            .save_version_with_mode("hot.txt", &data, None, hot)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::Config(_)));
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compressed_version_records_algorithm() {
+        use crate::core::CompressionAlgorithm;
+
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            compression_algorithm: "zstd".to_string(),
+            ..Default::default()
+        };
+        let text = b"whole file compression algorithm ".repeat(2048);
+        // 随机数据不可压缩，按原样保存
+        let random = crate::test_util::test_data(64 * 1024, 3);
+
+        let (text_version, random_version) = {
+            let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config.clone());
+            storage.init().await.unwrap();
+            storage.pause_optimization_scheduler().await.unwrap();
+            let (_, text_version) = storage
+                .save_version_with_mode("text.log", &text, None, crate::StorageMode::Compressed)
+                .await
+                .unwrap();
+            let (_, random_version) = storage
+                .save_version_with_mode("random.bin", &random, None, crate::StorageMode::Compressed)
+                .await
+                .unwrap();
+
+            let info = storage
+                .get_version_info(&text_version.version_id)
+                .await
+                .unwrap();
+            assert_eq!(info.compression, Some(CompressionAlgorithm::Zstd));
+            let info = storage
+                .get_version_info(&random_version.version_id)
+                .await
+                .unwrap();
+            assert_eq!(info.compression, Some(CompressionAlgorithm::None));
+            assert_eq!(storage.read_file("random.bin").await.unwrap(), random);
+
+            storage.sync_all().await.unwrap();
+            storage.stop_optimization_task().await;
+            (text_version, random_version)
+        };
+
+        // 写入后修改压缩算法，已有版本仍按记录的算法解压
+        let config = IncrementalConfig {
+            compression_algorithm: "lz4".to_string(),
+            ..config
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+        assert_eq!(
+            storage
+                .read_version_data(&text_version.version_id)
+                .await
+                .unwrap(),
+            text
+        );
+        assert_eq!(
+            storage
+                .read_version_data(&random_version.version_id)
+                .await
+                .unwrap(),
+            random
+        );
+    }
+
+    #[tokio::test]
+    async fn test_open_read_streams_all_modes() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+        let metadata_db = storage.get_metadata_db().unwrap();
+
+        async fn read_all(storage: &StorageManager, version_id: &str) -> Vec<u8> {
+            let mut reader = storage.open_read(version_id).await.unwrap();
+            let mut out = Vec::new();
+            let mut buf = [0u8; 1000];
+            loop {
+                let n = reader.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                out.extend_from_slice(&buf[..n]);
+            }
+            out
+        }
+
+        // 分块存储（多个块）
+        let chunked: Vec<u8> = (0..300_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let (_, version) = storage
+            .save_version("chunked.bin", &chunked, None)
+            .await
+            .unwrap();
+        assert_eq!(read_all(&storage, &version.version_id).await, chunked);
+
+        // 冷存储
+        let cold = b"cold storage payload ".repeat(4096);
+        let (_, version) = storage.save_version("cold.bin", &cold, None).await.unwrap();
+        let mut entry = metadata_db.get_file_index("cold.bin").unwrap().unwrap();
+        #[allow(deprecated)]
+        {
+            entry.storage_mode = crate::StorageMode::Cold;
+        }
+        metadata_db.put_file_index("cold.bin", &entry).unwrap();
+        assert_eq!(read_all(&storage, &version.version_id).await, cold);
+
+        // 整文件压缩
+        let compressed = b"compressed payload ".repeat(4096);
+        let (_, version) = storage
+            .save_version_with_mode(
+                "archive.log",
+                &compressed,
+                None,
+                crate::StorageMode::Compressed,
+            )
+            .await
+            .unwrap();
+        assert_eq!(read_all(&storage, &version.version_id).await, compressed);
+
+        // 旧热存储：直接读取热文件
+        let (_, version) = storage
+            .save_version("hot.txt", b"chunked copy", None)
+            .await
+            .unwrap();
+        let hot_path = storage.get_hot_storage_path("hot.txt");
+        fs::create_dir_all(hot_path.parent().unwrap())
+            .await
+            .unwrap();
+        fs::write(&hot_path, b"hot file copy").await.unwrap();
+        let mut entry = metadata_db.get_file_index("hot.txt").unwrap().unwrap();
+        #[allow(deprecated)]
+        {
+            entry.storage_mode = crate::StorageMode::Hot;
+        }
+        metadata_db.put_file_index("hot.txt", &entry).unwrap();
+        assert_eq!(
+            read_all(&storage, &version.version_id).await,
+            b"hot file copy"
+        );
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_move_prefix_moves_nested_files() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        let files = [
+            ("docs/a.txt", b"file a".as_slice()),
+            ("docs/sub/b.txt", b"file b".as_slice()),
+            ("docs/sub/deep/c.txt", b"file c".as_slice()),
+        ];
+        for (file_id, data) in files {
+            storage.save_version(file_id, data, None).await.unwrap();
+        }
+        // 仅字符串前缀相同的兄弟目录不受影响
+        storage
+            .save_version("docs2/other.txt", b"other", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("taken/z.txt", b"z", None)
+            .await
+            .unwrap();
+
+        // 目标已有文件或前缀互相包含时拒绝，且不移动任何文件
+        assert!(storage.move_prefix("docs", "taken").await.is_err());
+        assert!(storage.move_prefix("docs", "docs/sub/inner").await.is_err());
+        assert!(storage.file_exists("docs/a.txt").await);
+
+        let moved = storage.move_prefix("docs/", "archive/docs").await.unwrap();
+        assert_eq!(moved.len(), 3);
+        for (file_id, data) in files {
+            let new_id = format!("archive/{}", file_id);
+            assert_eq!(storage.read_file(&new_id).await.unwrap(), data);
+            assert!(!storage.file_exists(file_id).await);
+        }
+        let remaining = storage.get_metadata_db().unwrap().list_file_ids().unwrap();
+        assert!(!remaining.iter().any(|id| id.starts_with("docs/")));
+        assert_eq!(
+            storage.read_file("docs2/other.txt").await.unwrap(),
+            b"other"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_move_prefix_into_recycled_destination() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        storage
+            .save_version("src/a.txt", b"new a", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("src/b.txt", b"new b", None)
+            .await
+            .unwrap();
+        // 目标目录下只有回收站中的文件，其中一个与移动后的文件同名
+        for file_id in ["dst/a.txt", "dst/gone.txt"] {
+            storage.save_version(file_id, b"old", None).await.unwrap();
+            storage.delete_file(file_id).await.unwrap();
+        }
+
+        storage.move_prefix("src", "dst").await.unwrap();
+        assert_eq!(storage.read_file("dst/a.txt").await.unwrap(), b"new a");
+        assert_eq!(storage.read_file("dst/b.txt").await.unwrap(), b"new b");
+        // 同名的旧文件被取代，版本不混入移动后的文件
+        let versions = storage.list_file_versions("dst/a.txt").await.unwrap();
+        assert_eq!(versions.len(), 1);
+        let deleted: Vec<String> = storage
+            .list_deleted_files()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.file_id)
+            .collect();
+        assert_eq!(deleted, vec!["dst/gone.txt".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_move_prefix_rolls_back_on_failure() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        let files = [
+            ("docs/a.txt", b"file a".as_slice()),
+            ("docs/sub/b.txt", b"file b".as_slice()),
+            ("docs/sub/deep/c.txt", b"file c".as_slice()),
+        ];
+        for (file_id, data) in files {
+            storage.save_version(file_id, data, None).await.unwrap();
+        }
+        // 最后移动的文件受保留锁保护，前两个文件移动后失败
+        let lock = RetentionLock {
+            until: Local::now().naive_local() + chrono::Duration::days(1),
+            mode: RetentionMode::Governance,
+        };
         storage
-            .save_version("file3", b"Data 3", None)
+            .set_retention("docs/sub/deep/c.txt", lock, false)
             .await
             .unwrap();
 
-        // 删除file2
-        storage.delete_file("file2").await.unwrap();
+        let err = storage.move_prefix("docs", "archive").await.unwrap_err();
+        assert!(matches!(err, StorageError::RetentionLocked(_)));
 
-        // list_files应该只返回file1和file3
-        let files = storage.list_files().await.unwrap();
-        assert_eq!(files.len(), 2);
-        assert!(files.contains(&"file1".to_string()));
-        assert!(!files.contains(&"file2".to_string()));
-        assert!(files.contains(&"file3".to_string()));
+        for (file_id, data) in files {
+            assert_eq!(storage.read_file(file_id).await.unwrap(), data);
+            assert_eq!(storage.list_file_versions(file_id).await.unwrap().len(), 1);
+        }
+        let remaining = storage.get_metadata_db().unwrap().list_file_ids().unwrap();
+        assert!(!remaining.iter().any(|id| id.starts_with("archive/")));
     }
 
     #[tokio::test]
-    async fn test_gc_task_start_stop() {
+    async fn test_file_info_records_optimization_stats() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+
+        let files = [
+            ("full.txt", crate::OptimizationStrategy::Full),
+            ("compress.txt", crate::OptimizationStrategy::CompressOnly),
+        ];
+        let data = b"optimization stats payload ".repeat(4096);
+        let now = Local::now().naive_local();
+        let metadata_db = storage.get_metadata_db().unwrap();
+        for (file_id, strategy) in files {
+            let hot_path = storage.get_hot_storage_path(file_id);
+            fs::create_dir_all(hot_path.parent().unwrap())
+                .await
+                .unwrap();
+            fs::write(&hot_path, &data).await.unwrap();
+            #[allow(deprecated)]
+            let entry = FileIndexEntry {
+                file_id: file_id.to_string(),
+                latest_version_id: format!("{}-v1", file_id),
+                version_count: 1,
+                created_at: now,
+                modified_at: now,
+                is_deleted: false,
+                deleted_at: None,
+                storage_mode: crate::StorageMode::Hot,
+                optimization_status: crate::OptimizationStatus::Pending,
+                file_size: data.len() as u64,
+                file_hash: storage.calculate_hash(&data),
+                user_metadata: HashMap::new(),
+                optimization_strategy: None,
+                optimized_at: None,
+                space_saved: 0,
+                stored_size: 0,
+                attributed_size: 0,
+                alias_target: None,
+                compression: None,
+                retention: None,
+            };
+            metadata_db.put_file_index(file_id, &entry).unwrap();
+
+            let info = storage.get_file_info(file_id).await.unwrap();
+            assert!(info.optimization_strategy.is_none());
+            assert!(info.optimized_at.is_none());
+
+            let mut task = crate::OptimizationTask::new(
+                file_id.to_string(),
+                hot_path,
+                data.len() as u64,
+                storage.calculate_hash(&data),
+                strategy,
+                0,
+            );
+            storage.execute_optimization_task(&mut task).await.unwrap();
+
+            let info = storage.get_file_info(file_id).await.unwrap();
+            assert_eq!(info.optimization_strategy, Some(strategy));
+            assert!(info.optimized_at.is_some_and(|t| t >= now));
+            assert!(info.stored_size > 0);
+            assert!(info.space_saved > 0, "重复文本应当节省空间: {}", file_id);
+            assert_eq!(info.stored_size + info.space_saved, data.len() as u64);
+        }
+
+        // 写入新版本后清除优化记录
+        storage
+            .save_version("full.txt", b"new content", None)
+            .await
+            .unwrap();
+        let info = storage.get_file_info("full.txt").await.unwrap();
+        assert!(info.optimization_strategy.is_none());
+        assert!(info.optimized_at.is_none());
+        assert_eq!(info.space_saved, 0);
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compression_bands_by_file_size() {
+        use crate::core::CompressionAlgorithm;
+
         let temp_dir = TempDir::new().unwrap();
+        let band = |max_size: Option<u64>, algorithm: &str| crate::CompressionBand {
+            max_size,
+            algorithm: algorithm.to_string(),
+        };
         let config = IncrementalConfig {
-            enable_auto_gc: false, // 手动控制GC任务
-            gc_interval_secs: 1,   // 1秒间隔用于快速测试
-            enable_compression: false,
+            enable_auto_gc: false,
+            compression_bands: vec![
+                band(Some(4 * 1024), "none"),
+                band(Some(256 * 1024), "lz4"),
+                band(None, "zstd"),
+            ],
+            ..Default::default()
+        };
+        config.validate().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+        let metadata_db = storage.get_metadata_db().unwrap();
+
+        let files = [
+            ("small.txt", 2 * 1024, CompressionAlgorithm::None),
+            ("medium.txt", 64 * 1024, CompressionAlgorithm::LZ4),
+            ("large.txt", 512 * 1024, CompressionAlgorithm::Zstd),
+        ];
+        let now = Local::now().naive_local();
+        for (file_id, size, expected) in files {
+            let mut data = Vec::with_capacity(size);
+            let mut line = 0;
+            while data.len() < size {
+                data.extend_from_slice(format!("{} line {:06}\n", file_id, line).as_bytes());
+                line += 1;
+            }
+            data.truncate(size);
+
+            let hot_path = storage.get_hot_storage_path(file_id);
+            fs::create_dir_all(hot_path.parent().unwrap())
+                .await
+                .unwrap();
+            fs::write(&hot_path, &data).await.unwrap();
+            #[allow(deprecated)]
+            let entry = FileIndexEntry {
+                file_id: file_id.to_string(),
+                latest_version_id: format!("{}-v1", file_id),
+                version_count: 1,
+                created_at: now,
+                modified_at: now,
+                is_deleted: false,
+                deleted_at: None,
+                storage_mode: crate::StorageMode::Hot,
+                optimization_status: crate::OptimizationStatus::Pending,
+                file_size: data.len() as u64,
+                file_hash: storage.calculate_hash(&data),
+                user_metadata: HashMap::new(),
+                optimization_strategy: None,
+                optimized_at: None,
+                space_saved: 0,
+                stored_size: 0,
+                attributed_size: 0,
+                alias_target: None,
+                compression: None,
+                retention: None,
+            };
+            metadata_db.put_file_index(file_id, &entry).unwrap();
+
+            let mut task = crate::OptimizationTask::new(
+                file_id.to_string(),
+                hot_path,
+                data.len() as u64,
+                storage.calculate_hash(&data),
+                crate::OptimizationStrategy::Full,
+                0,
+            );
+            storage.execute_optimization_task(&mut task).await.unwrap();
+
+            // 小于压缩下限的块不压缩，其余块使用文件所在分段的算法
+            let delta = storage
+                .read_delta(file_id, &entry.latest_version_id)
+                .await
+                .unwrap();
+            for chunk in delta.chunks.iter().filter(|c| c.size >= 1024) {
+                assert_eq!(chunk.compression, expected, "{}", file_id);
+            }
+            assert!(
+                delta
+                    .chunks
+                    .iter()
+                    .all(|c| c.compression == expected
+                        || c.compression == CompressionAlgorithm::None)
+            );
+            assert_eq!(storage.read_file(file_id).await.unwrap(), data);
+        }
+
+        // 整文件压缩同样按分段选择算法，并记录在文件索引中
+        let data = b"compress only band payload ".repeat(16 * 1024);
+        storage
+            .save_version("archive.log", &data, None)
+            .await
+            .unwrap();
+        let hot_path = storage.get_hot_storage_path("archive.log");
+        fs::create_dir_all(hot_path.parent().unwrap())
+            .await
+            .unwrap();
+        fs::write(&hot_path, &data).await.unwrap();
+        let mut task = crate::OptimizationTask::new(
+            "archive.log".to_string(),
+            hot_path,
+            data.len() as u64,
+            storage.calculate_hash(&data),
+            crate::OptimizationStrategy::CompressOnly,
+            0,
+        );
+        storage.execute_optimization_task(&mut task).await.unwrap();
+        let entry = metadata_db.get_file_index("archive.log").unwrap().unwrap();
+        assert_eq!(entry.storage_mode, crate::StorageMode::Compressed);
+        assert_eq!(entry.compression, Some(CompressionAlgorithm::Zstd));
+        assert_eq!(storage.read_file("archive.log").await.unwrap(), data);
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_falls_back_to_hot_copy_when_chunk_missing() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            ..Default::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+        let metadata_db = storage.get_metadata_db().unwrap();
+
+        let mut seed = 0x2545_f491_u32;
+        let data: Vec<u8> = (0..64 * 1024)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 24) as u8
+            })
+            .collect();
+        let file_id = "partial.bin";
+        let (_, version) = storage.save_version(file_id, &data, None).await.unwrap();
+        let mut entry = metadata_db.get_file_index(file_id).unwrap().unwrap();
+        #[allow(deprecated)]
+        {
+            entry.storage_mode = crate::StorageMode::Cold;
+        }
+        metadata_db.put_file_index(file_id, &entry).unwrap();
+
+        // 模拟优化中途崩溃：热存储副本仍在，其中一个块未写入
+        let delta = storage
+            .read_delta(file_id, &version.version_id)
+            .await
+            .unwrap();
+        assert!(delta.chunks.len() > 1);
+        let missing = delta.chunks[1].chunk_id.clone();
+        storage.chunk_store.delete(&missing).await.unwrap();
+        let hot_path = storage.get_hot_storage_path(file_id);
+        fs::create_dir_all(hot_path.parent().unwrap())
+            .await
+            .unwrap();
+        fs::write(&hot_path, &data).await.unwrap();
+
+        assert_eq!(storage.read_file(file_id).await.unwrap(), data);
+        assert_eq!(storage.get_optimization_queue_length().await, 1);
+
+        // 重新优化补写缺失的块，之后不再依赖热存储副本
+        let mut task = crate::OptimizationTask::new(
+            file_id.to_string(),
+            hot_path.clone(),
+            data.len() as u64,
+            storage.calculate_hash(&data),
+            crate::OptimizationStrategy::Full,
+            0,
+        );
+        storage.execute_optimization_task(&mut task).await.unwrap();
+        assert!(!hot_path.exists());
+        assert_eq!(storage.read_file(file_id).await.unwrap(), data);
+        let delta = storage
+            .read_delta(file_id, &version.version_id)
+            .await
+            .unwrap();
+        for chunk in &delta.chunks {
+            assert_eq!(metadata_db.get_chunk_ref_count(&chunk.chunk_id).unwrap(), 1);
+        }
+
+        // 没有热存储副本时返回明确的错误
+        let missing = delta.chunks[0].chunk_id.clone();
+        storage.chunk_store.delete(&missing).await.unwrap();
+        let err = storage.read_file(file_id).await.unwrap_err();
+        assert!(
+            matches!(err, StorageError::IncompleteOptimization(ref msg) if msg.contains(&missing))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_dedup_index_restores_ref_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            ..Default::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+        storage.pause_optimization_scheduler().await.unwrap();
+        let metadata_db = storage.get_metadata_db().unwrap();
+
+        let mut seed = 0x9e37_79b9_u32;
+        let mut random = |len: usize| -> Vec<u8> {
+            (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    (seed >> 24) as u8
+                })
+                .collect()
+        };
+        let shared = random(32 * 1024);
+        let unique = random(32 * 1024);
+        let (shared_delta, _) = storage.save_version("a.bin", &shared, None).await.unwrap();
+        storage.save_version("b.bin", &shared, None).await.unwrap();
+        let (unique_delta, _) = storage.save_version("c.bin", &unique, None).await.unwrap();
+        let shared_chunk = shared_delta.chunks[0].chunk_id.clone();
+        let unique_chunk = unique_delta.chunks[0].chunk_id.clone();
+        assert_eq!(metadata_db.get_chunk_ref_count(&shared_chunk).unwrap(), 2);
+
+        // 破坏引用计数：多计、丢失记录、残留无人引用的块
+        let mut chunk_ref = metadata_db.get_chunk_ref(&shared_chunk).unwrap().unwrap();
+        chunk_ref.ref_count = 7;
+        metadata_db
+            .put_chunk_ref(&shared_chunk, &chunk_ref)
+            .unwrap();
+        metadata_db.remove_chunk_ref(&unique_chunk).unwrap();
+        let stray = ChunkRefCount {
+            chunk_id: "stray".to_string(),
+            ref_count: 3,
+            ..chunk_ref.clone()
+        };
+        metadata_db.put_chunk_ref("stray", &stray).unwrap();
+
+        // 模拟上次重建统计完第一个版本后中断
+        let first = metadata_db.iter_versions().next().unwrap().unwrap();
+        let first_delta = storage
+            .read_delta(&first.file_id, &first.version_id)
+            .await
+            .unwrap();
+        let mut staged: HashMap<String, ChunkRefCount> = HashMap::new();
+        for chunk in &first_delta.chunks {
+            staged
+                .entry(chunk.chunk_id.clone())
+                .or_insert_with(|| ChunkRefCount {
+                    chunk_id: chunk.chunk_id.clone(),
+                    ref_count: 0,
+                    size: chunk.size as u64,
+                    path: storage.chunk_store.location(&chunk.chunk_id),
+                    weak_hash: chunk.weak_hash,
+                })
+                .ref_count += 1;
+        }
+        let checkpoint = crate::metadata::RefRebuildCheckpoint {
+            last_version_id: Some(first.version_id.clone()),
+            versions_scanned: 1,
+        };
+        metadata_db.stage_chunk_refs(&staged, &checkpoint).unwrap();
+
+        // 重建会覆盖并发修改的引用计数，必须在维护模式下执行
+        assert!(matches!(
+            storage.rebuild_dedup_index().await,
+            Err(StorageError::PreconditionFailed(_))
+        ));
+        storage.enter_maintenance().await.unwrap();
+
+        let result = storage.rebuild_dedup_index().await.unwrap();
+        assert!(result.resumed);
+        assert_eq!(result.versions_scanned, 3);
+        assert_eq!(result.reset_chunks, 1);
+        assert!(metadata_db.get_ref_rebuild_checkpoint().unwrap().is_none());
+        for chunk in &shared_delta.chunks {
+            assert_eq!(metadata_db.get_chunk_ref_count(&chunk.chunk_id).unwrap(), 2);
+        }
+        for chunk in &unique_delta.chunks {
+            assert_eq!(metadata_db.get_chunk_ref_count(&chunk.chunk_id).unwrap(), 1);
+        }
+        assert_eq!(metadata_db.get_chunk_ref_count("stray").unwrap(), 0);
+
+        // 再次重建结果不变
+        let again = storage.rebuild_dedup_index().await.unwrap();
+        assert!(!again.resumed);
+        assert_eq!(again.chunks, result.chunks);
+        assert_eq!(metadata_db.get_chunk_ref_count(&shared_chunk).unwrap(), 2);
+        storage.exit_maintenance().await.unwrap();
+
+        // 重建后的引用计数驱动 GC：删除一个共享者后块仍保留，全部删除后被回收
+        storage.permanently_delete_file("a.bin").await.unwrap();
+        storage.garbage_collect().await.unwrap();
+        assert!(storage.chunk_store.exists(&shared_chunk).await.unwrap());
+        assert!(storage.chunk_store.exists(&unique_chunk).await.unwrap());
+        assert!(metadata_db.get_chunk_ref("stray").unwrap().is_none());
+        assert_eq!(storage.read_file("b.bin").await.unwrap(), shared);
+
+        storage.permanently_delete_file("b.bin").await.unwrap();
+        storage.garbage_collect().await.unwrap();
+        assert!(!storage.chunk_store.exists(&shared_chunk).await.unwrap());
+        assert_eq!(storage.read_file("c.bin").await.unwrap(), unique);
+    }
+
+    #[tokio::test]
+    async fn test_consistent_read_bypasses_version_cache() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        let (_, version) = storage
+            .save_version("consistent.txt", b"cached content", None)
+            .await
+            .unwrap();
+        // 预热版本缓存
+        storage
+            .read_version_data(&version.version_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            storage
+                .read_version_data_consistent(&version.version_id)
+                .await
+                .unwrap(),
+            b"cached content"
+        );
+
+        // 绕过缓存直接使版本失效，缓存中仍保留旧条目
+        storage
+            .get_metadata_db()
+            .unwrap()
+            .remove_version_info(&version.version_id)
+            .unwrap();
+        assert!(
+            storage
+                .version_cache
+                .get(&version.version_id)
+                .await
+                .is_some()
+        );
+
+        // 普通读取命中缓存，强一致读取反映 Sled 中的新状态
+        assert!(storage.read_version_data(&version.version_id).await.is_ok());
+        assert!(matches!(
+            storage
+                .read_version_data_consistent(&version.version_id)
+                .await,
+            Err(StorageError::VersionNotFound(_))
+        ));
+        assert!(matches!(
+            storage.read_file_consistent("consistent.txt").await,
+            Err(StorageError::VersionNotFound(_))
+        ));
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_changes_since() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        storage
+            .save_version("old.txt", b"before baseline", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("untouched.txt", b"before baseline", None)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let baseline = Local::now().naive_local();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let (_, new_version) = storage
+            .save_version("new.txt", b"after baseline", None)
+            .await
+            .unwrap();
+        let (_, old_version) = storage
+            .save_version("old.txt", b"modified after baseline", None)
+            .await
+            .unwrap();
+        storage
+            .save_version("removed.txt", b"short lived", None)
+            .await
+            .unwrap();
+        StorageManager::delete_file(&storage, "removed.txt")
+            .await
+            .unwrap();
+
+        let changes = storage.changes_since(baseline).await.unwrap();
+        let summary: Vec<(&str, ChangeOp)> =
+            changes.iter().map(|c| (c.file_id.as_str(), c.op)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("new.txt", ChangeOp::Created),
+                ("old.txt", ChangeOp::Modified),
+                ("removed.txt", ChangeOp::Deleted),
+            ]
+        );
+        assert_eq!(changes[0].version_id, new_version.version_id);
+        assert_eq!(changes[1].version_id, old_version.version_id);
+        assert!(changes.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+        assert!(changes.iter().all(|c| c.timestamp > baseline));
+
+        // 基准时间之后没有变更
+        let latest = changes.last().unwrap().timestamp;
+        assert!(storage.changes_since(latest).await.unwrap().is_empty());
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_content_exists_by_hash() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            instant_upload: crate::InstantUploadScope::Namespace,
             ..IncrementalConfig::default()
         };
         let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
         storage.init().await.unwrap();
 
-        // 启动GC任务
-        storage.start_gc_task().await;
+        let data = b"backup client content";
+        let (_, version) = storage
+            .save_version("backup.bin", data, None)
+            .await
+            .unwrap();
+        let hash = storage.calculate_hash(data);
 
-        // 等待一小段时间
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let found = storage.content_exists(&hash).await.unwrap().unwrap();
+        assert_eq!(found.file_id, "backup.bin");
+        assert_eq!(found.version_id, version.version_id);
+        assert_eq!(found.size, data.len() as u64);
+        // 大写十六进制同样命中
+        assert!(
+            storage
+                .content_exists(&hash.to_ascii_uppercase())
+                .await
+                .unwrap()
+                .is_some()
+        );
 
-        // 停止GC任务
-        storage.stop_gc_task().await;
+        // 未知哈希
+        let unknown = storage.calculate_hash(b"never uploaded");
+        assert!(storage.content_exists(&unknown).await.unwrap().is_none());
 
-        // 验证任务已停止
-        assert!(storage.gc_task_handle.read().await.is_none());
+        // 内容变化后旧哈希不再命中
+        storage
+            .save_version("backup.bin", b"changed content", None)
+            .await
+            .unwrap();
+        assert!(storage.content_exists(&hash).await.unwrap().is_none());
+
+        storage.shutdown().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_auto_gc_on_init() {
+    async fn test_content_exists_disabled_with_instant_upload() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        // 秒传默认禁用，只凭哈希不能探测内容是否存在
+        let data = b"private content";
+        storage
+            .save_version("private.bin", data, None)
+            .await
+            .unwrap();
+        let hash = storage.calculate_hash(data);
+        assert!(storage.content_exists(&hash).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_lookup_by_content_hash_returns_all_identical_files() {
+        let (storage, _temp) = create_test_storage().await;
+        storage.init().await.unwrap();
+
+        let data = b"identical content in two places";
+        let (_, first) = storage
+            .save_version("b/copy.txt", data, None)
+            .await
+            .unwrap();
+        let (_, second) = storage
+            .save_version("a/original.txt", data, None)
+            .await
+            .unwrap();
+        storage
+            .save_version("c/other.txt", b"something else", None)
+            .await
+            .unwrap();
+        let hash = storage.calculate_hash(data);
+
+        // 两个文件都能查到，按文件 ID 排序
+        let expected = vec![
+            ContentRef {
+                file_id: "a/original.txt".to_string(),
+                version_id: second.version_id.clone(),
+                size: data.len() as u64,
+            },
+            ContentRef {
+                file_id: "b/copy.txt".to_string(),
+                version_id: first.version_id.clone(),
+                size: data.len() as u64,
+            },
+        ];
+        assert_eq!(
+            storage.lookup_by_content_hash(&hash).await.unwrap(),
+            expected
+        );
+
+        // 清空后可从文件索引重建
+        storage
+            .get_metadata_db()
+            .unwrap()
+            .clear_content_hashes()
+            .unwrap();
+        assert!(
+            storage
+                .lookup_by_content_hash(&hash)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(storage.rebuild_content_hash_index().await.unwrap(), 3);
+        assert_eq!(
+            storage.lookup_by_content_hash(&hash).await.unwrap(),
+            expected
+        );
+
+        // 删除与内容变化后不再命中
+        storage.delete_file("a/original.txt").await.unwrap();
+        storage
+            .save_version("b/copy.txt", b"changed", None)
+            .await
+            .unwrap();
+        assert!(
+            storage
+                .lookup_by_content_hash(&hash)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        storage.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_init_sweeps_stale_scratch_files() {
         let temp_dir = TempDir::new().unwrap();
+        let scratch = temp_dir.path().join("scratch");
+        std::fs::create_dir_all(scratch.join("crashed-import")).unwrap();
+        std::fs::create_dir_all(scratch.join("uploads")).unwrap();
+
+        let stale = scratch.join("stale.tmp");
+        let stale_staged = scratch.join("crashed-import").join("chunk");
+        let fresh = scratch.join("uploads").join("fresh.tmp");
+        std::fs::write(&stale, b"left over by a crash").unwrap();
+        std::fs::write(&stale_staged, b"staged chunk").unwrap();
+        std::fs::write(&fresh, b"upload in progress").unwrap();
+
+        let two_hours_ago = std::time::SystemTime::now() - Duration::from_secs(2 * 3600);
+        for path in [&stale, &stale_staged] {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(two_hours_ago)
+                .unwrap();
+        }
+        for dir in ["crashed-import", "uploads"] {
+            std::fs::File::open(scratch.join(dir))
+                .unwrap()
+                .set_modified(two_hours_ago)
+                .unwrap();
+        }
+
         let config = IncrementalConfig {
-            enable_auto_gc: true, // 自动启动GC
-            gc_interval_secs: 1,  // 1秒间隔用于快速测试
-            enable_compression: false,
+            scratch_dir: Some(scratch.clone()),
+            scratch_max_age_secs: 3600,
             ..IncrementalConfig::default()
         };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        let storage = StorageManager::new(temp_dir.path().join("store"), 64 * 1024, config);
+        assert_eq!(storage.scratch_dir(), scratch);
         storage.init().await.unwrap();
 
-        // 验证GC任务已启动
-        assert!(storage.gc_task_handle.read().await.is_some());
+        // 过期文件与清空的过期目录被删除，新文件及其所在目录保留
+        assert!(!stale.exists());
+        assert!(!scratch.join("crashed-import").exists());
+        assert!(fresh.exists());
 
-        // 停止GC任务
-        storage.stop_gc_task().await;
+        storage.shutdown().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_manual_gc_trigger() {
+    async fn test_quota_exceeded() {
         let temp_dir = TempDir::new().unwrap();
         let config = IncrementalConfig {
-            enable_auto_gc: false,
-            enable_compression: false,
+            quota_bytes: Some(1024),
             ..IncrementalConfig::default()
         };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 64 * 1024, config);
         storage.init().await.unwrap();
 
-        // 创建测试文件
         storage
-            .save_version("file1", b"Test data", None)
+            .save_version("a.bin", &[1u8; 600], None)
             .await
             .unwrap();
+        let err = storage
+            .save_version("b.bin", &[2u8; 600], None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::QuotaExceeded(_)));
+        assert_eq!(err.code(), "QUOTA_EXCEEDED");
 
-        // 永久删除文件
-        storage.permanently_delete_file("file1").await.unwrap();
+        // 覆盖同一文件时旧版本大小不计入
+        storage
+            .save_version("a.bin", &[3u8; 1000], None)
+            .await
+            .unwrap();
 
-        // 手动触发GC
-        let _deleted_count = storage.garbage_collect_blocks().await.unwrap();
+        // 流式写入同样受配额限制
+        let mut reader: &[u8] = &[4u8; 100];
+        let result = storage
+            .save_version_from_reader("c.bin", &mut reader, None)
+            .await;
+        assert!(matches!(result, Err(StorageError::QuotaExceeded(_))));
 
-        // GC应该成功完成
-        // 不需要检查具体数量
+        storage.shutdown().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_gc_task_periodic_execution() {
+    async fn test_max_upload_size_aborts_stream() {
         let temp_dir = TempDir::new().unwrap();
         let config = IncrementalConfig {
+            max_upload_size: Some(10 * 1024),
             enable_auto_gc: false,
-            gc_interval_secs: 1, // 1秒间隔用于快速测试
-            enable_compression: false,
             ..IncrementalConfig::default()
         };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024, config);
         storage.init().await.unwrap();
 
-        // 创建并删除文件
+        // 前两个块写入后第三个块超限，流式写入中止
+        let data: Vec<u8> = (0..16 * 1024).map(|i| (i % 251) as u8).collect();
+        let err = storage
+            .save_version_from_reader("big.bin", &mut &data[..], None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::FileTooLarge(_)));
+        assert_eq!(err.code(), "FILE_TOO_LARGE");
+
+        // 不留下文件和已写入的块
+        assert!(matches!(
+            storage.stat("big.bin").await,
+            Err(StorageError::FileNotFound(_))
+        ));
+        // 已写入的块没有引用记录（并发写入可能复用，不立即删除），由孤儿块清理回收
+        let written = storage.chunk_store.list().await.unwrap();
+        assert!(
+            storage
+                .get_metadata_db()
+                .unwrap()
+                .list_all_chunks()
+                .unwrap()
+                .is_empty()
+        );
+        let orphans = storage.detect_orphan_chunks().await.unwrap();
+        assert_eq!(orphans.len(), written.len());
+        storage.cleanup_orphan_chunks(&orphans).await.unwrap();
+        assert!(storage.chunk_store.list().await.unwrap().is_empty());
+
+        // 一次性写入同样受限，未超限的文件正常保存
+        let err = storage
+            .save_version("big.bin", &data, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::FileTooLarge(_)));
         storage
-            .save_version("file1", b"Test data", None)
+            .save_version_from_reader("small.bin", &mut &data[..10 * 1024], None)
             .await
             .unwrap();
-        storage.permanently_delete_file("file1").await.unwrap();
-
-        // 启动GC任务
-        storage.start_gc_task().await;
-
-        // 等待至少一次GC执行
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-
-        // 停止GC任务
-        storage.stop_gc_task().await;
+        assert_eq!(storage.stat("small.bin").await.unwrap().size, 10 * 1024);
 
-        // GC任务应该已经执行过，清理了块
-        // 这里只验证任务能正常启动和停止
+        storage.shutdown().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_chunked_storage_upload_and_read() {
-        // 测试分块存储模式的上传和读取（新架构，直接分块+去重）
+    async fn test_maintenance_mode_quiesces_background_tasks() {
         let temp_dir = TempDir::new().unwrap();
-        let config = IncrementalConfig {
-            enable_compression: false,
-            ..IncrementalConfig::default()
-        };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        let storage = StorageManager::new(
+            temp_dir.path().to_path_buf(),
+            4096,
+            IncrementalConfig::default(),
+        );
         storage.init().await.unwrap();
-
-        // 上传测试数据（直接分块存储）
-        let test_data = b"Hello from chunked storage! This is a test file.";
-        let (delta, version) = storage
-            .save_version("test_chunked_file", test_data, None)
+        storage
+            .save_version("doc.txt", b"before maintenance", None)
             .await
             .unwrap();
 
-        // 验证有分块（即时分块存储）
-        assert!(!delta.chunks.is_empty(), "分块存储应该有chunks");
-        assert_eq!(version.size, test_data.len() as u64);
+        storage.enter_maintenance().await.unwrap();
+        assert!(storage.is_in_maintenance());
+        assert!(!storage.is_gc_task_running().await);
 
-        // 验证文件索引的存储模式
-        let metadata_db = storage.get_metadata_db().unwrap();
-        let file_entry = metadata_db
-            .get_file_index("test_chunked_file")
-            .unwrap()
-            .unwrap();
-        assert_eq!(
-            file_entry.storage_mode,
-            crate::StorageMode::Chunked,
-            "应该是分块存储模式"
-        );
+        // 拒绝写入，读取不受影响
+        let err = storage
+            .save_version("doc.txt", b"during maintenance", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::Maintenance(_)));
+        assert_eq!(err.code(), "MAINTENANCE");
+        assert!(matches!(
+            storage.delete_file("doc.txt").await,
+            Err(StorageError::Maintenance(_))
+        ));
+        assert!(matches!(
+            storage.garbage_collect().await,
+            Err(StorageError::Maintenance(_))
+        ));
         assert_eq!(
-            file_entry.optimization_status,
-            crate::OptimizationStatus::Completed,
-            "应该是已完成状态"
+            storage.read_file("doc.txt").await.unwrap(),
+            b"before maintenance"
         );
 
-        // 读取数据（从分块存储读取）
-        let read_data = storage
-            .read_version_data(&version.version_id)
+        // 维护期间排队的优化任务不执行
+        let hot_path = temp_dir.path().join("queued");
+        fs::write(&hot_path, b"already optimal").await.unwrap();
+        storage
+            .optimization_scheduler
+            .submit_task(crate::OptimizationTask::new(
+                "queued".to_string(),
+                hot_path,
+                15,
+                String::new(),
+                crate::OptimizationStrategy::Skip,
+                0,
+            ))
+            .await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(storage.get_optimization_stats().await.completed_tasks, 0);
+        assert_eq!(storage.get_optimization_queue_length().await, 1);
+
+        // 退出后恢复写入与后台任务，排队的优化任务继续执行
+        storage.exit_maintenance().await.unwrap();
+        assert!(!storage.is_in_maintenance());
+        assert!(storage.is_gc_task_running().await);
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while storage.get_optimization_stats().await.completed_tasks < 1 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("退出维护模式后优化任务未恢复");
+        storage
+            .save_version("doc.txt", b"after maintenance", None)
             .await
             .unwrap();
-        assert_eq!(read_data, test_data, "读取的数据应该与原始数据一致");
+
+        storage.shutdown().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_chunked_storage_with_deduplication() {
-        // 测试启用去重的分块存储（新架构）
+    async fn test_read_only_replica() {
         let temp_dir = TempDir::new().unwrap();
+
+        // 先以可写模式写入数据
+        {
+            let config = IncrementalConfig {
+                enable_auto_gc: false,
+                ..IncrementalConfig::default()
+            };
+            let storage = StorageManager::new(temp_dir.path().to_path_buf(), 64 * 1024, config);
+            storage.init().await.unwrap();
+            storage
+                .save_version("existing.txt", b"replicated data", None)
+                .await
+                .unwrap();
+            storage.shutdown().await.unwrap();
+        }
+
         let config = IncrementalConfig {
-            enable_compression: false,
+            read_only: true,
             ..IncrementalConfig::default()
         };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 64 * 1024, config);
         storage.init().await.unwrap();
+        assert!(storage.is_read_only());
+
+        // 后台任务不启动
+        assert!(!storage.is_gc_task_running().await);
+        assert!(storage.optimization_task_handle.read().await.is_none());
+
+        // 写操作被拒绝
+        let result = storage.save_version("new.txt", b"rejected", None).await;
+        assert!(matches!(result, Err(StorageError::ReadOnly(_))));
+        assert!(matches!(
+            StorageManager::delete_file(&storage, "existing.txt").await,
+            Err(StorageError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            storage.move_file("existing.txt", "moved.txt").await,
+            Err(StorageError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            storage.garbage_collect_blocks().await,
+            Err(StorageError::ReadOnly(_))
+        ));
 
-        // 上传测试数据（直接分块+去重存储）
-        let test_data = b"Hello from chunked storage! This is a test file for chunking with dedup.";
-        let (delta, version) = storage
-            .save_version("test_dedup_file", test_data, None)
-            .await
-            .unwrap();
-
-        // 验证有分块
-        assert!(!delta.chunks.is_empty(), "应该有分块");
-
-        // 验证文件索引的存储模式
-        let metadata_db = storage.get_metadata_db().unwrap();
-        let file_entry = metadata_db
-            .get_file_index("test_dedup_file")
-            .unwrap()
-            .unwrap();
+        // 读操作正常
         assert_eq!(
-            file_entry.storage_mode,
-            crate::StorageMode::Chunked,
-            "应该是分块存储模式"
+            storage.read_file("existing.txt").await.unwrap(),
+            b"replicated data"
+        );
+        assert!(!storage.file_exists("new.txt").await);
+        assert_eq!(
+            StorageManager::list_files(&storage).await.unwrap(),
+            vec!["existing.txt".to_string()]
         );
-
-        // 读取数据（从分块读取）
-        let read_data = storage
-            .read_version_data(&version.version_id)
-            .await
-            .unwrap();
-        assert_eq!(read_data, test_data, "读取的数据应该与原始数据一致");
     }
 
     #[tokio::test]
-    async fn test_stream_upload() {
-        // 测试流式上传到分块存储
+    async fn test_chain_depth_triggers_compaction() {
         let temp_dir = TempDir::new().unwrap();
         let config = IncrementalConfig {
-            ..IncrementalConfig::default()
+            max_chain_depth: 3,
+            chain_compaction: crate::ChainCompactionMode::Sync,
+            ..Default::default()
         };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
         storage.init().await.unwrap();
 
-        // 创建测试数据流
-        let test_data = b"Streaming data to chunked storage! This is a larger test file.".repeat(100);
-        let mut cursor = std::io::Cursor::new(test_data.clone());
-
-        // 流式上传
-        let (delta, version) = storage
-            .save_version_from_reader("test_stream_file", &mut cursor, None)
+        let (_, first) = storage
+            .save_version("chain.txt", b"version 0", None)
             .await
             .unwrap();
+        let mut versions = vec![(first.version_id, b"version 0".to_vec())];
+        for i in 1..8 {
+            let data = format!("version {}", i).into_bytes();
+            let parent = storage.current_version_id("chain.txt").await.unwrap();
+            let (_, version) = storage
+                .save_version("chain.txt", &data, Some(&parent))
+                .await
+                .unwrap();
+            versions.push((version.version_id, data));
+            assert!(storage.version_chain_depth("chain.txt").await.unwrap() <= 3);
+        }
 
-        // 验证分块存储
-        assert!(!delta.chunks.is_empty(), "分块存储应该有chunks");
+        // 第 4 个版本使深度超过 3，压缩后成为新的基础版本
+        let compacted = storage.get_version_info(&versions[3].0).await.unwrap();
+        assert!(compacted.parent_version_id.is_none());
 
-        // 读取并验证数据
-        let read_data = storage
-            .read_version_data(&version.version_id)
-            .await
-            .unwrap();
-        assert_eq!(read_data, test_data, "流式上传的数据应该正确");
+        // 所有版本仍可读取，历史版本未被删除
+        for (version_id, data) in &versions {
+            assert_eq!(storage.read_version_data(version_id).await.unwrap(), *data);
+        }
+        assert_eq!(storage.read_file("chain.txt").await.unwrap(), b"version 7");
+        assert_eq!(
+            storage.list_file_versions("chain.txt").await.unwrap().len(),
+            versions.len()
+        );
     }
 
     #[tokio::test]
-    async fn test_immediate_chunked_storage() {
-        // 测试即时分块存储功能（新架构：直接分块+去重，无需后台优化）
+    async fn test_deferred_chain_compaction() {
         let temp_dir = TempDir::new().unwrap();
         let config = IncrementalConfig {
-            enable_compression: true,
-            ..IncrementalConfig::default()
+            max_chain_depth: 2,
+            chain_compaction: crate::ChainCompactionMode::Deferred,
+            ..Default::default()
         };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
         storage.init().await.unwrap();
 
-        // 上传一个较大的文件（直接分块存储）
-        let test_data = b"This is test data for immediate chunked storage. ".repeat(1000); // ~50KB
-        let (delta, version) = storage
-            .save_version("test_chunked_file", &test_data, None)
-            .await
-            .unwrap();
+        storage.save_version("lazy.txt", b"a", None).await.unwrap();
+        for data in [b"b", b"c"] {
+            let parent = storage.current_version_id("lazy.txt").await.unwrap();
+            storage
+                .save_version("lazy.txt", data, Some(&parent))
+                .await
+                .unwrap();
+        }
 
-        // 验证直接使用分块存储（有chunks）
-        assert!(!delta.chunks.is_empty(), "分块存储应该有chunks");
+        // 压缩由后台任务完成
+        let mut depth = storage.version_chain_depth("lazy.txt").await.unwrap();
+        for _ in 0..100 {
+            if depth == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            depth = storage.version_chain_depth("lazy.txt").await.unwrap();
+        }
+        assert_eq!(depth, 1);
+        assert_eq!(storage.read_file("lazy.txt").await.unwrap(), b"c");
+    }
 
-        // 验证文件索引的状态
-        let metadata_db = storage.get_metadata_db().unwrap();
-        let file_entry = metadata_db
-            .get_file_index("test_chunked_file")
-            .unwrap()
-            .unwrap();
-        assert_eq!(
-            file_entry.storage_mode,
-            crate::StorageMode::Chunked,
-            "应该是分块存储模式"
-        );
-        assert_eq!(
-            file_entry.optimization_status,
-            crate::OptimizationStatus::Completed,
-            "优化状态应该是已完成（即时分块不需要后台优化）"
-        );
+    #[tokio::test]
+    async fn test_shutdown_drains_deferred_chain_compaction() {
+        // 默认不限制版本链深度，保持原有行为
+        assert_eq!(IncrementalConfig::default().max_chain_depth, 0);
 
-        // 验证可以正确读取数据
-        let read_data = storage
-            .read_version_data(&version.version_id)
-            .await
-            .unwrap();
-        assert_eq!(read_data, test_data, "应该能正确读取数据");
+        let temp_dir = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            max_chain_depth: 2,
+            chain_compaction: crate::ChainCompactionMode::Deferred,
+            ..Default::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
 
-        // 关闭存储
+        storage.save_version("drain.txt", b"a", None).await.unwrap();
+        for data in [b"b", b"c"] {
+            let parent = storage.current_version_id("drain.txt").await.unwrap();
+            storage
+                .save_version("drain.txt", data, Some(&parent))
+                .await
+                .unwrap();
+        }
+
+        // 关闭时等待后台压缩任务完成
         storage.shutdown().await.unwrap();
+        assert!(storage.chain_compactions.lock().unwrap().is_empty());
+        assert_eq!(storage.version_chain_depth("drain.txt").await.unwrap(), 1);
+        assert_eq!(storage.read_file("drain.txt").await.unwrap(), b"c");
     }
 
     #[tokio::test]
-    async fn test_optimization_api_error_cases() {
-        // 测试优化API的错误情况
+    async fn test_sync_all_survives_reopen() {
         let temp_dir = TempDir::new().unwrap();
         let config = IncrementalConfig {
-            ..IncrementalConfig::default()
+            enable_auto_gc: false,
+            ..Default::default()
         };
-        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4 * 1024 * 1024, config);
-        storage.init().await.unwrap();
+        let files: Vec<(String, Vec<u8>)> = (0..5)
+            .map(|i| {
+                (
+                    format!("dir/file_{}.bin", i),
+                    vec![i as u8; 10_000 + i * 1000],
+                )
+            })
+            .collect();
 
-        // 上传文件（先到热存储，然后等待优化完成）
-        let test_data = b"Test data for error cases";
-        storage
-            .save_version("test_error", test_data, None)
-            .await
-            .unwrap();
+        {
+            let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config.clone());
+            storage.init().await.unwrap();
+            for (file_id, data) in &files {
+                storage.save_version(file_id, data, None).await.unwrap();
+            }
+            storage.sync_all().await.unwrap();
+            // 停止后台任务以释放数据库句柄
+            storage.stop_optimization_task().await;
+        }
 
-        // 等待优化完成（变成冷存储）
-        wait_for_optimization(&storage, "test_error", 10)
-            .await
-            .unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+        for (file_id, data) in &files {
+            assert_eq!(&storage.read_file(file_id).await.unwrap(), data);
+            assert_eq!(storage.list_file_versions(file_id).await.unwrap().len(), 1);
+        }
+    }
 
-        // 尝试触发已在冷存储的文件的优化（应该失败或者已经是Completed状态）
-        let result = storage.trigger_file_optimization("test_error").await;
-        // 如果文件已经是Completed状态，可能会返回错误或者忽略
-        if result.is_err() {
-            println!("触发已完成优化的文件返回错误（符合预期）");
+    #[tokio::test]
+    async fn test_separate_chunk_and_metadata_dirs() {
+        let root = TempDir::new().unwrap();
+        let bulk = TempDir::new().unwrap();
+        let fast = TempDir::new().unwrap();
+        let config = IncrementalConfig {
+            enable_auto_gc: false,
+            chunk_root: Some(bulk.path().join("chunks")),
+            metadata_dir: Some(fast.path().join("meta")),
+            hot_dir: Some(fast.path().join("hot")),
+            ..Default::default()
+        };
+        let data = b"separated storage layout ".repeat(2048);
+
+        {
+            let storage = StorageManager::new(root.path().to_path_buf(), 4096, config.clone());
+            storage.init().await.unwrap();
+            storage.save_version("a.txt", &data, None).await.unwrap();
+            assert_eq!(storage.read_file("a.txt").await.unwrap(), data);
+            storage.sync_all().await.unwrap();
+            storage.stop_optimization_task().await;
         }
 
-        // 尝试触发不存在的文件（应该失败）
-        let result_not_found = storage.trigger_file_optimization("non_existent").await;
-        assert!(result_not_found.is_err(), "触发不存在的文件应该失败");
+        // 块与元数据只写入各自配置的目录
+        assert!(bulk.path().join("chunks").join("data").exists());
+        assert!(fast.path().join("meta").exists());
+        assert!(fast.path().join("hot").exists());
+        let default_root = root.path().join("incremental");
+        assert!(!default_root.join("metadata").exists());
+        assert!(!default_root.join("chunks").exists());
 
-        storage.shutdown().await.unwrap();
+        let storage = StorageManager::new(root.path().to_path_buf(), 4096, config);
+        storage.init().await.unwrap();
+        assert_eq!(storage.read_file("a.txt").await.unwrap(), data);
+        storage.save_version("b.txt", b"more", None).await.unwrap();
+        assert_eq!(storage.read_file("b.txt").await.unwrap(), b"more");
     }
 
 }
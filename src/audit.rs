@@ -1,13 +1,22 @@
 //! 审计日志模块
 //!
 //! 记录关键操作的审计日志，用于安全审查和合规性
+//!
+//! 事件按写入顺序组成哈希链：每个事件记录前一事件的哈希，自身哈希覆盖
+//! 前一哈希与事件内容。删除、插入或修改任一历史事件都会使 `verify_chain` 失败。
 
 #![allow(dead_code)] // 这些方法将在后续集成时使用
 
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, RwLock};
+
+/// 哈希链起点（第一个事件的前一哈希）
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
 
 /// 审计事件类型
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -18,6 +27,8 @@ pub enum AuditAction {
     FileDownload,
     /// 文件删除
     FileDelete,
+    /// 文件硬删除（跳过回收站的合规擦除）
+    FilePurge,
     /// 版本创建
     VersionCreate,
     /// 版本恢复
@@ -55,6 +66,12 @@ pub struct AuditEvent {
     pub error_message: Option<String>,
     /// 附加元数据
     pub metadata: serde_json::Value,
+    /// 前一事件的哈希（由 `AuditLogger` 记录时填写）
+    #[serde(default)]
+    pub prev_hash: String,
+    /// 本事件的哈希：SHA-256(前一哈希 + 事件内容)
+    #[serde(default)]
+    pub hash: String,
 }
 
 impl AuditEvent {
@@ -70,6 +87,8 @@ impl AuditEvent {
             success: true,
             error_message: None,
             metadata: serde_json::json!({}),
+            prev_hash: String::new(),
+            hash: String::new(),
         }
     }
 
@@ -98,6 +117,16 @@ impl AuditEvent {
         self
     }
 
+    /// 计算事件哈希（覆盖 `prev_hash` 与除 `hash` 外的所有字段）
+    pub fn compute_hash(&self) -> String {
+        let mut content = self.clone();
+        content.hash.clear();
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(serde_json::to_vec(&content).unwrap_or_default());
+        hex::encode(hasher.finalize())
+    }
+
     /// 记录到日志
     pub fn log(&self) {
         let json = serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string());
@@ -109,12 +138,25 @@ impl AuditEvent {
     }
 }
 
+/// 哈希链状态
+#[derive(Debug, Clone)]
+struct ChainState {
+    /// 内存中最早事件的前一哈希（更早的事件已移出缓存）
+    anchor: String,
+    /// 最新事件的哈希
+    head: String,
+}
+
 /// 审计日志管理器
 pub struct AuditLogger {
     /// 内存缓存的审计事件（可选，用于查询最近事件）
     events: Arc<RwLock<Vec<AuditEvent>>>,
     /// 最大缓存事件数
     max_events: usize,
+    /// 哈希链状态（同时串行化事件的写入顺序）
+    chain: Arc<Mutex<ChainState>>,
+    /// 持久化文件（每行一个 JSON 事件，只追加），`None` 时只保存在内存
+    persist_path: Option<PathBuf>,
 }
 
 impl AuditLogger {
@@ -123,22 +165,116 @@ impl AuditLogger {
         Self {
             events: Arc::new(RwLock::new(Vec::with_capacity(max_events))),
             max_events,
+            chain: Arc::new(Mutex::new(ChainState {
+                anchor: GENESIS_HASH.to_string(),
+                head: GENESIS_HASH.to_string(),
+            })),
+            persist_path: None,
+        }
+    }
+
+    /// 创建持久化到文件的审计日志管理器
+    ///
+    /// 文件已存在时加载最近的事件并从最后一个事件的哈希继续链接，
+    /// 重启后哈希链不中断。
+    pub async fn with_persistence(max_events: usize, path: PathBuf) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let events = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => parse_events(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        let head = events
+            .last()
+            .map(|e| e.hash.clone())
+            .unwrap_or_else(|| GENESIS_HASH.to_string());
+        let start = events.len().saturating_sub(max_events);
+        let recent = events[start..].to_vec();
+        let anchor = recent
+            .first()
+            .map(|e| e.prev_hash.clone())
+            .unwrap_or_else(|| head.clone());
+
+        Ok(Self {
+            events: Arc::new(RwLock::new(recent)),
+            max_events,
+            chain: Arc::new(Mutex::new(ChainState { anchor, head })),
+            persist_path: Some(path),
+        })
+    }
+
+    /// 按环境变量 `ENABLE_AUDIT` 创建审计日志管理器，未启用时返回 `None`
+    ///
+    /// 持久化到存储目录下的 `audit/audit.jsonl` 以保持哈希链，加载失败时只保存在内存中。
+    /// HTTP、WebDAV、S3 共用同一个实例。
+    pub async fn from_env(root_path: &std::path::Path) -> Option<Arc<Self>> {
+        std::env::var("ENABLE_AUDIT").ok()?;
+        let path = root_path.join("audit").join("audit.jsonl");
+        match Self::with_persistence(1000, path).await {
+            Ok(logger) => Some(Arc::new(logger)),
+            Err(e) => {
+                tracing::warn!("加载审计日志失败，仅保存在内存中: {}", e);
+                Some(Arc::new(Self::new(1000)))
+            }
         }
     }
 
     /// 记录审计事件
-    pub async fn log(&self, event: AuditEvent) {
+    ///
+    /// 事件链接到前一事件后写入；持久化文件写入失败只记录日志，
+    /// 不影响调用方的业务操作。
+    pub async fn log(&self, mut event: AuditEvent) {
+        let mut chain = self.chain.lock().await;
+        event.prev_hash = chain.head.clone();
+        event.hash = event.compute_hash();
+
         // 写入日志
         event.log();
 
+        if let Some(path) = &self.persist_path
+            && let Err(e) = append_event(path, &event).await
+        {
+            tracing::error!("审计事件写入持久化文件失败: {}", e);
+        }
+        chain.head = event.hash.clone();
+
         // 缓存到内存
         let mut events = self.events.write().await;
         events.push(event);
 
-        // 保持缓存大小限制
+        // 保持缓存大小限制，移出的事件由锚点哈希衔接
         if events.len() > self.max_events {
             let drain_count = events.len() - self.max_events;
             events.drain(0..drain_count);
+            chain.anchor = events[0].prev_hash.clone();
+        }
+    }
+
+    /// 校验哈希链
+    ///
+    /// 启用持久化时从头校验整个文件，并确认文件末尾与当前链头一致（检测截断）；
+    /// 否则校验内存中缓存的事件。任一事件被修改、删除或插入时返回 `false`。
+    pub async fn verify_chain(&self) -> bool {
+        let chain = self.chain.lock().await;
+        match &self.persist_path {
+            Some(path) => {
+                let events = match tokio::fs::read_to_string(path).await {
+                    Ok(content) => match parse_events(&content) {
+                        Ok(events) => events,
+                        Err(_) => return false,
+                    },
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                    Err(_) => return false,
+                };
+                verify_events(GENESIS_HASH, &events) == Some(chain.head.clone())
+            }
+            None => {
+                let events = self.events.read().await;
+                verify_events(&chain.anchor, &events) == Some(chain.head.clone())
+            }
         }
     }
 
@@ -198,6 +334,44 @@ impl AuditLogger {
     }
 }
 
+/// 逐个校验事件链接，返回最后一个事件的哈希；链接断开或哈希不符时返回 `None`
+fn verify_events(anchor: &str, events: &[AuditEvent]) -> Option<String> {
+    let mut prev = anchor.to_string();
+    for event in events {
+        if event.prev_hash != prev || event.compute_hash() != event.hash {
+            tracing::warn!(target: "audit", "审计日志哈希链在事件 {} 处断开", event.id);
+            return None;
+        }
+        prev = event.hash.clone();
+    }
+    Some(prev)
+}
+
+/// 解析持久化文件内容（每行一个事件）
+fn parse_events(content: &str) -> std::io::Result<Vec<AuditEvent>> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })
+        .collect()
+}
+
+/// 向持久化文件追加一个事件
+async fn append_event(path: &std::path::Path, event: &AuditEvent) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(event)?;
+    line.push(b'\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(&line).await?;
+    file.sync_data().await
+}
+
 impl Default for AuditLogger {
     fn default() -> Self {
         Self::new(1000)
@@ -374,4 +548,91 @@ mod tests {
         assert_eq!(stats.failed_events, 1);
         assert!(stats.action_counts.contains_key("FileUpload"));
     }
+
+    #[tokio::test]
+    async fn test_verify_chain_detects_tampering() {
+        let logger = AuditLogger::new(100);
+        for i in 0..5 {
+            logger
+                .log(AuditEvent::new(
+                    AuditAction::FileUpload,
+                    Some(format!("file-{}", i)),
+                ))
+                .await;
+        }
+        assert!(logger.verify_chain().await);
+
+        // 修改中间的事件
+        logger.events.write().await[2].resource_id = Some("forged".to_string());
+        assert!(!logger.verify_chain().await);
+    }
+
+    #[tokio::test]
+    async fn test_verify_chain_across_cache_eviction() {
+        let logger = AuditLogger::new(3);
+        for i in 0..10 {
+            logger
+                .log(AuditEvent::new(
+                    AuditAction::FileDownload,
+                    Some(format!("file-{}", i)),
+                ))
+                .await;
+        }
+        assert!(logger.verify_chain().await);
+
+        // 删除缓存中最早的事件
+        logger.events.write().await.remove(0);
+        assert!(!logger.verify_chain().await);
+    }
+
+    #[tokio::test]
+    async fn test_persisted_chain_survives_restart_and_detects_tampering() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("audit").join("audit.jsonl");
+
+        let logger = AuditLogger::with_persistence(2, path.clone())
+            .await
+            .unwrap();
+        for i in 0..3 {
+            logger
+                .log(AuditEvent::new(
+                    AuditAction::FileDelete,
+                    Some(format!("file-{}", i)),
+                ))
+                .await;
+        }
+        assert!(logger.verify_chain().await);
+        drop(logger);
+
+        // 重启后继续链接
+        let logger = AuditLogger::with_persistence(2, path.clone())
+            .await
+            .unwrap();
+        logger
+            .log(AuditEvent::new(
+                AuditAction::FileDelete,
+                Some("file-3".to_string()),
+            ))
+            .await;
+        assert!(logger.verify_chain().await);
+        assert_eq!(logger.get_recent_events(10).await.len(), 2);
+
+        // 篡改文件中间的一行
+        let content = tokio::fs::read_to_string(&path).await.unwrap();
+        let tampered: Vec<String> = content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                if i == 1 {
+                    line.replace("file-1", "file-x")
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect();
+        tokio::fs::write(&path, tampered.join("\n") + "\n")
+            .await
+            .unwrap();
+        assert!(!logger.verify_chain().await);
+    }
 }
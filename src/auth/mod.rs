@@ -18,27 +18,50 @@ pub use models::{
 };
 
 use crate::error::{NasError, Result};
+use crate::metrics;
 use chrono::{Local, TimeZone};
 use password::PasswordHandler;
+pub use password::PasswordPolicy;
 use rate_limit::{RateLimitConfig, RateLimiter};
+use silent_nas_core::{SharedClock, SystemClock};
 use std::path::Path;
 use std::sync::{Arc, RwLock};
 use storage::UserStorage;
 use token_blacklist::TokenBlacklist;
 use validator::Validate;
 
+/// 删除请求的发起者：请求中没有认证中间件写入的用户时视为未启用认证
+pub fn delete_actor(req: &silent::prelude::Request) -> crate::storage::DeleteActor {
+    match req.configs().get::<User>() {
+        Some(user) => crate::storage::DeleteActor::Authenticated {
+            id: user.id.clone(),
+            admin: user.role == UserRole::Admin,
+        },
+        None => crate::storage::DeleteActor::Anonymous,
+    }
+}
+
 /// 认证管理器
 #[derive(Clone)]
 pub struct AuthManager {
     pub(crate) storage: Arc<UserStorage>,
     jwt_config: Arc<RwLock<JwtConfig>>,
+    password_policy: Arc<RwLock<PasswordPolicy>>,
     rate_limiter: Option<Arc<RateLimiter>>,
     token_blacklist: Option<Arc<TokenBlacklist>>,
+    clock: SharedClock,
 }
 
 impl AuthManager {
     /// 创建认证管理器
     pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        Self::with_clock(db_path, SystemClock::shared())
+    }
+
+    /// 使用指定时钟创建认证管理器
+    ///
+    /// 登录锁定、Token 黑名单过期以及用户时间戳均以该时钟为准。
+    pub fn with_clock<P: AsRef<Path>>(db_path: P, clock: SharedClock) -> Result<Self> {
         let storage = UserStorage::new(&db_path)?;
         let jwt_config = JwtConfig::from_env();
 
@@ -51,7 +74,7 @@ impl AuthManager {
         let rate_limiter = {
             let rate_limit_path = db_dir.join("rate_limit.db");
             match RateLimiter::new(rate_limit_path, RateLimitConfig::default()) {
-                Ok(limiter) => Some(Arc::new(limiter)),
+                Ok(limiter) => Some(Arc::new(limiter.with_clock(clock.clone()))),
                 Err(e) => {
                     tracing::warn!("创建限流器失败: {}, 限流功能将被禁用", e);
                     None
@@ -63,7 +86,7 @@ impl AuthManager {
         let token_blacklist = {
             let blacklist_path = db_dir.join("token_blacklist.db");
             match TokenBlacklist::new(blacklist_path) {
-                Ok(blacklist) => Some(Arc::new(blacklist)),
+                Ok(blacklist) => Some(Arc::new(blacklist.with_clock(clock.clone()))),
                 Err(e) => {
                     tracing::warn!("创建Token黑名单失败: {}, 注销功能将被禁用", e);
                     None
@@ -74,8 +97,10 @@ impl AuthManager {
         Ok(Self {
             storage: Arc::new(storage),
             jwt_config: Arc::new(RwLock::new(jwt_config)),
+            password_policy: Arc::new(RwLock::new(PasswordPolicy::default())),
             rate_limiter,
             token_blacklist,
+            clock,
         })
     }
 
@@ -84,11 +109,24 @@ impl AuthManager {
         *self.jwt_config.write().unwrap() = config;
     }
 
+    /// 设置密码策略，策略无效时返回错误并保留原策略
+    pub fn set_password_policy(&self, policy: PasswordPolicy) -> Result<()> {
+        policy.validate()?;
+        *self.password_policy.write().unwrap() = policy;
+        Ok(())
+    }
+
+    /// 按当前密码策略检查密码
+    fn check_password_policy(&self, password: &str) -> Result<()> {
+        self.password_policy.read().unwrap().check(password)
+    }
+
     /// 注册用户
     pub fn register(&self, req: RegisterRequest) -> Result<UserInfo> {
         // 验证请求
         req.validate()
             .map_err(|e| NasError::Auth(format!("验证失败: {}", e)))?;
+        self.check_password_policy(&req.password)?;
 
         // 检查用户名是否存在
         if self.storage.username_exists(&req.username)? {
@@ -111,8 +149,8 @@ impl AuthManager {
             password_hash,
             role: UserRole::User, // 默认角色
             status: UserStatus::Active,
-            created_at: Local::now(),
-            updated_at: Local::now(),
+            created_at: self.clock.now(),
+            updated_at: self.clock.now(),
         };
 
         let created_user = self.storage.create_user(user)?;
@@ -128,6 +166,7 @@ impl AuthManager {
         {
             let remaining = limiter.get_lock_remaining(&req.username)?;
             if let Some(seconds) = remaining {
+                metrics::record_auth_login_failure("locked");
                 return Err(NasError::Auth(format!(
                     "账户已被锁定，请在 {} 秒后重试",
                     seconds
@@ -142,10 +181,7 @@ impl AuthManager {
             .or_else(|| self.storage.get_user_by_email(&req.username).ok().flatten());
 
         if user.is_none() {
-            // 记录失败
-            if let Some(ref limiter) = self.rate_limiter {
-                let _ = limiter.record_failure(&req.username);
-            }
+            self.record_login_failure(&req.username, "unknown_user");
             return Err(NasError::Auth("用户名或密码错误".to_string()));
         }
 
@@ -154,9 +190,11 @@ impl AuthManager {
         // 检查用户状态
         match user.status {
             UserStatus::Suspended => {
+                metrics::record_auth_login_failure("suspended");
                 return Err(NasError::Auth("账户已被暂停".to_string()));
             }
             UserStatus::Deleted => {
+                metrics::record_auth_login_failure("deleted");
                 return Err(NasError::Auth("账户已被删除".to_string()));
             }
             UserStatus::Active => {}
@@ -164,10 +202,7 @@ impl AuthManager {
 
         // 验证密码
         if !PasswordHandler::verify_password(&req.password, &user.password_hash)? {
-            // 记录失败
-            if let Some(ref limiter) = self.rate_limiter {
-                let _ = limiter.record_failure(&req.username);
-            }
+            self.record_login_failure(&req.username, "bad_password");
             return Err(NasError::Auth("用户名或密码错误".to_string()));
         }
 
@@ -180,6 +215,7 @@ impl AuthManager {
         let jwt_config = self.jwt_config.read().unwrap();
         let access_token = jwt_config.generate_access_token(&user)?;
         let refresh_token = jwt_config.generate_refresh_token(&user)?;
+        metrics::record_auth_login_success();
 
         Ok(LoginResponse {
             access_token,
@@ -190,6 +226,16 @@ impl AuthManager {
         })
     }
 
+    /// 记录登录失败（限流计数 + 指标）
+    fn record_login_failure(&self, identifier: &str, reason: &str) {
+        metrics::record_auth_login_failure(reason);
+        if let Some(ref limiter) = self.rate_limiter
+            && let Ok(true) = limiter.record_failure(identifier)
+        {
+            metrics::record_auth_lockout();
+        }
+    }
+
     /// 刷新 Token
     pub fn refresh_token(&self, refresh_token: &str) -> Result<LoginResponse> {
         // 验证刷新令牌
@@ -214,6 +260,7 @@ impl AuthManager {
         let jwt_config = self.jwt_config.read().unwrap();
         let access_token = jwt_config.generate_access_token(&user)?;
         let new_refresh_token = jwt_config.generate_refresh_token(&user)?;
+        metrics::record_auth_token_event("refresh", 1);
 
         Ok(LoginResponse {
             access_token,
@@ -263,6 +310,7 @@ impl AuthManager {
             .unwrap();
 
         blacklist.add(&claims.jti, &claims.sub, expires_at, "user_logout")?;
+        metrics::record_auth_token_event("revoke", 1);
 
         Ok(())
     }
@@ -274,7 +322,9 @@ impl AuthManager {
             .as_ref()
             .ok_or_else(|| NasError::Auth("注销功能未启用".to_string()))?;
 
-        blacklist.revoke_user_tokens(user_id)
+        let revoked = blacklist.revoke_user_tokens(user_id)?;
+        metrics::record_auth_token_event("revoke", revoked as u64);
+        Ok(revoked)
     }
 
     /// 修改密码
@@ -282,6 +332,7 @@ impl AuthManager {
         // 验证请求
         req.validate()
             .map_err(|e| NasError::Auth(format!("验证失败: {}", e)))?;
+        self.check_password_policy(&req.new_password)?;
 
         // 获取用户
         let mut user = self
@@ -296,7 +347,7 @@ impl AuthManager {
 
         // 哈希新密码
         user.password_hash = PasswordHandler::hash_password(&req.new_password)?;
-        user.updated_at = Local::now();
+        user.updated_at = self.clock.now();
 
         // 更新用户
         self.storage.update_user(user)?;
@@ -322,7 +373,7 @@ impl AuthManager {
     /// 更新用户信息（仅管理员）
     pub async fn update_user(&self, user: &User) -> Result<()> {
         let mut updated_user = user.clone();
-        updated_user.updated_at = Local::now();
+        updated_user.updated_at = self.clock.now();
         self.storage.update_user(updated_user)?;
         Ok(())
     }
@@ -335,7 +386,7 @@ impl AuthManager {
             .ok_or_else(|| NasError::Auth("用户不存在".to_string()))?;
 
         user.role = role;
-        user.updated_at = Local::now();
+        user.updated_at = self.clock.now();
 
         let updated = self.storage.update_user(user)?;
         Ok(updated.into())
@@ -349,7 +400,7 @@ impl AuthManager {
             .ok_or_else(|| NasError::Auth("用户不存在".to_string()))?;
 
         user.status = status;
-        user.updated_at = Local::now();
+        user.updated_at = self.clock.now();
 
         let updated = self.storage.update_user(user)?;
         Ok(updated.into())
@@ -357,6 +408,8 @@ impl AuthManager {
 
     /// 重置用户密码（仅管理员）
     pub async fn reset_password(&self, user_id: &str, new_password: &str) -> Result<()> {
+        self.check_password_policy(new_password)?;
+
         let mut user = self
             .storage
             .get_user_by_id(user_id)?
@@ -364,7 +417,7 @@ impl AuthManager {
 
         // 哈希新密码
         user.password_hash = PasswordHandler::hash_password(new_password)?;
-        user.updated_at = Local::now();
+        user.updated_at = self.clock.now();
 
         // 更新用户
         self.storage.update_user(user)?;
@@ -393,8 +446,8 @@ impl AuthManager {
             password_hash,
             role: UserRole::Admin,
             status: UserStatus::Active,
-            created_at: Local::now(),
-            updated_at: Local::now(),
+            created_at: self.clock.now(),
+            updated_at: self.clock.now(),
         };
 
         self.storage.create_user(admin)?;
@@ -474,6 +527,86 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_login_failure_metrics() {
+        let (auth, _temp) = create_test_auth_manager();
+        auth.register(RegisterRequest {
+            username: "metricsuser".to_string(),
+            email: "metrics@example.com".to_string(),
+            password: "SecureP@ss123".to_string(),
+        })
+        .unwrap();
+
+        let failures = || {
+            metrics::AUTH_LOGIN_FAILURES_TOTAL
+                .with_label_values(&["bad_password"])
+                .get()
+        };
+        let lockouts = || metrics::AUTH_LOCKOUTS_TOTAL.get();
+        let failures_before = failures();
+        let lockouts_before = lockouts();
+
+        // 默认配置下连续 5 次失败触发锁定
+        for _ in 0..5 {
+            let result = auth.login(LoginRequest {
+                username: "metricsuser".to_string(),
+                password: "WrongP@ss123".to_string(),
+            });
+            assert!(result.is_err());
+        }
+        assert!(failures() >= failures_before + 5);
+        assert!(lockouts() > lockouts_before);
+
+        // 锁定期间的登录按 locked 原因计数
+        let locked_before = metrics::AUTH_LOGIN_FAILURES_TOTAL
+            .with_label_values(&["locked"])
+            .get();
+        let result = auth.login(LoginRequest {
+            username: "metricsuser".to_string(),
+            password: "SecureP@ss123".to_string(),
+        });
+        assert!(result.unwrap_err().to_string().contains("锁定"));
+        assert!(
+            metrics::AUTH_LOGIN_FAILURES_TOTAL
+                .with_label_values(&["locked"])
+                .get()
+                > locked_before
+        );
+    }
+
+    #[test]
+    fn test_lockout_expires_with_mock_clock() {
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Arc::new(silent_nas_core::MockClock::default());
+        let auth = AuthManager::with_clock(temp_dir.path().join("auth.db"), clock.clone()).unwrap();
+        auth.register(RegisterRequest {
+            username: "clockuser".to_string(),
+            email: "clock@example.com".to_string(),
+            password: "SecureP@ss123".to_string(),
+        })
+        .unwrap();
+
+        let login = |password: &str| {
+            auth.login(LoginRequest {
+                username: "clockuser".to_string(),
+                password: password.to_string(),
+            })
+        };
+        for _ in 0..5 {
+            assert!(login("WrongP@ss123").is_err());
+        }
+        assert!(
+            login("SecureP@ss123")
+                .unwrap_err()
+                .to_string()
+                .contains("锁定")
+        );
+
+        // 默认锁定 30 分钟，推进时钟后无需等待即可登录
+        clock.advance(chrono::Duration::minutes(31));
+        assert!(login("SecureP@ss123").is_ok());
+    }
+
     #[test]
     fn test_verify_token() {
         let (auth, _temp) = create_test_auth_manager();
@@ -497,6 +630,59 @@ mod tests {
         assert_eq!(user.username, "testuser");
     }
 
+    #[tokio::test]
+    async fn test_password_policy_enforced() {
+        let (auth, _temp) = create_test_auth_manager();
+        auth.set_password_policy(PasswordPolicy {
+            min_length: 12,
+            require_upper: true,
+            require_digit: true,
+            require_symbol: true,
+            reject_common: true,
+            extra_blocklist: Vec::new(),
+        })
+        .unwrap();
+
+        let register = |password: &str| {
+            auth.register(RegisterRequest {
+                username: "policyuser".to_string(),
+                email: "policy@example.com".to_string(),
+                password: password.to_string(),
+            })
+        };
+        let err = register("SecureP@ss1").unwrap_err();
+        assert!(err.to_string().contains("长度至少 12 个字符"));
+        let user = register("Secure-P@ss-2024").unwrap();
+
+        // 修改与重置密码同样受策略约束
+        let err = auth
+            .change_password(
+                &user.id,
+                ChangePasswordRequest {
+                    old_password: "Secure-P@ss-2024".to_string(),
+                    new_password: "nouppercase-123!".to_string(),
+                },
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("必须包含大写字母"));
+        let err = auth.reset_password(&user.id, "P@ssw0rd").await.unwrap_err();
+        assert!(err.to_string().contains("不能使用常见弱密码"));
+        auth.reset_password(&user.id, "Another-Strong-42")
+            .await
+            .unwrap();
+
+        // 无效策略被拒绝，保留原策略
+        assert!(
+            auth.set_password_policy(PasswordPolicy {
+                min_length: 0,
+                ..Default::default()
+            })
+            .is_err()
+        );
+        let err = auth.reset_password(&user.id, "short").await.unwrap_err();
+        assert!(err.to_string().contains("长度至少 12 个字符"));
+    }
+
     #[test]
     fn test_change_password() {
         let (auth, _temp) = create_test_auth_manager();
@@ -116,8 +116,8 @@ pub struct RegisterRequest {
     #[validate(email(message = "无效的电子邮件格式"))]
     pub email: String,
 
-    /// 密码（8-72个字符）
-    #[validate(length(min = 8, max = 72, message = "密码长度必须在8-72个字符之间"))]
+    /// 密码（最多72个字符，复杂度由密码策略检查）
+    #[validate(length(min = 1, max = 72, message = "密码长度必须在1-72个字符之间"))]
     pub password: String,
 }
 
@@ -136,8 +136,8 @@ pub struct ChangePasswordRequest {
     /// 旧密码
     pub old_password: String,
 
-    /// 新密码（8-72个字符）
-    #[validate(length(min = 8, max = 72, message = "密码长度必须在8-72个字符之间"))]
+    /// 新密码（最多72个字符，复杂度由密码策略检查）
+    #[validate(length(min = 1, max = 72, message = "密码长度必须在1-72个字符之间"))]
     pub new_password: String,
 }
 
@@ -250,13 +250,13 @@ mod tests {
         };
         assert!(invalid_email.validate().is_err());
 
-        // 密码太短
-        let short_password = RegisterRequest {
+        // 密码为空（长度下限由密码策略检查）
+        let empty_password = RegisterRequest {
             username: "john_doe".to_string(),
             email: "john@example.com".to_string(),
-            password: "short".to_string(),
+            password: String::new(),
         };
-        assert!(short_password.validate().is_err());
+        assert!(empty_password.validate().is_err());
     }
 
     #[test]
@@ -1,11 +1,109 @@
 //! 密码哈希处理
 
+pub use crate::config::PasswordPolicy;
 use crate::error::{NasError, Result};
 use argon2::{
     Argon2,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 
+/// 密码长度上限（字节），与请求校验一致
+pub const MAX_PASSWORD_LENGTH: usize = 72;
+
+/// 内置的常见弱密码（比较时忽略大小写）
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456",
+    "12345678",
+    "123456789",
+    "1234567890",
+    "111111",
+    "000000",
+    "password",
+    "password1",
+    "password123",
+    "passw0rd",
+    "p@ssw0rd",
+    "qwerty",
+    "qwerty123",
+    "qwertyuiop",
+    "abc123",
+    "abcd1234",
+    "iloveyou",
+    "letmein",
+    "welcome",
+    "welcome1",
+    "admin",
+    "admin123",
+    "administrator",
+    "root",
+    "changeme",
+    "monkey",
+    "dragon",
+    "football",
+    "baseball",
+    "sunshine",
+    "princess",
+    "trustno1",
+    "1q2w3e4r",
+    "zaq12wsx",
+    "silent-nas",
+];
+
+/// 密码策略的校验，注册、修改密码和管理员重置密码时统一执行
+impl PasswordPolicy {
+    /// 校验策略本身
+    pub fn validate(&self) -> Result<()> {
+        if self.min_length == 0 || self.min_length > MAX_PASSWORD_LENGTH {
+            return Err(NasError::Config(format!(
+                "password_policy.min_length 必须在 1-{} 之间",
+                MAX_PASSWORD_LENGTH
+            )));
+        }
+        Ok(())
+    }
+
+    /// 检查密码是否满足策略
+    pub fn check(&self, password: &str) -> Result<()> {
+        let mut violations = Vec::new();
+        if password.chars().count() < self.min_length {
+            violations.push(format!("长度至少 {} 个字符", self.min_length));
+        }
+        if password.len() > MAX_PASSWORD_LENGTH {
+            violations.push(format!("长度不能超过 {} 字节", MAX_PASSWORD_LENGTH));
+        }
+        if self.require_upper && !password.chars().any(|c| c.is_uppercase()) {
+            violations.push("必须包含大写字母".to_string());
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push("必须包含数字".to_string());
+        }
+        if self.require_symbol && !password.chars().any(|c| !c.is_alphanumeric()) {
+            violations.push("必须包含特殊字符".to_string());
+        }
+        if self.reject_common && self.is_common(password) {
+            violations.push("不能使用常见弱密码".to_string());
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(NasError::Auth(format!(
+                "密码不符合策略: {}",
+                violations.join("；")
+            )))
+        }
+    }
+
+    fn is_common(&self, password: &str) -> bool {
+        let lowered = password.to_lowercase();
+        COMMON_PASSWORDS.contains(&lowered.as_str())
+            || self
+                .extra_blocklist
+                .iter()
+                .any(|blocked| blocked.to_lowercase() == lowered)
+    }
+}
+
 /// 密码处理器
 pub struct PasswordHandler;
 
@@ -106,6 +204,50 @@ mod tests {
         assert!(!PasswordHandler::verify_password("WrongPassword", &hash).unwrap());
     }
 
+    #[test]
+    fn test_password_policy_rules() {
+        let strict = PasswordPolicy {
+            min_length: 12,
+            require_upper: true,
+            require_digit: true,
+            require_symbol: true,
+            reject_common: true,
+            extra_blocklist: vec!["Company#Name2024".to_string()],
+        };
+
+        let rejected = |password: &str| match strict.check(password) {
+            Err(NasError::Auth(msg)) => msg,
+            other => panic!("密码 {} 应被拒绝: {:?}", password, other),
+        };
+        assert!(rejected("Sh0rt!pass").contains("长度至少 12 个字符"));
+        assert!(rejected("lowercase-only-123").contains("必须包含大写字母"));
+        assert!(rejected("No-Digits-Here!!").contains("必须包含数字"));
+        assert!(rejected("NoSymbolsHere123").contains("必须包含特殊字符"));
+        assert!(rejected("company#name2024").contains("不能使用常见弱密码"));
+        // 内置列表在关闭其他规则时同样生效
+        let common_only = PasswordPolicy {
+            min_length: 1,
+            reject_common: true,
+            ..Default::default()
+        };
+        assert!(common_only.check("Password123").is_err());
+        // 多条规则不满足时全部列出
+        let msg = rejected("abc");
+        assert!(msg.contains("长度至少") && msg.contains("必须包含数字"));
+
+        assert!(strict.check("Correct-Horse-42-Battery").is_ok());
+        assert!(PasswordPolicy::default().check("simplepassword").is_ok());
+
+        assert!(
+            PasswordPolicy {
+                min_length: 0,
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
     #[test]
     fn test_different_hashes() {
         let password = "SamePassword123!";
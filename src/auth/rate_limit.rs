@@ -4,6 +4,7 @@
 
 use chrono::{DateTime, Duration, Local};
 use serde::{Deserialize, Serialize};
+use silent_nas_core::{SharedClock, SystemClock};
 use sled::Db;
 use std::path::Path;
 use std::sync::Arc;
@@ -25,40 +26,40 @@ pub struct LoginAttempt {
 
 impl LoginAttempt {
     /// 创建新的尝试记录
-    pub fn new(identifier: String) -> Self {
+    pub fn new(identifier: String, now: DateTime<Local>) -> Self {
         Self {
             identifier,
             failed_count: 1,
-            first_failed_at: Local::now(),
-            last_failed_at: Local::now(),
+            first_failed_at: now,
+            last_failed_at: now,
             locked_until: None,
         }
     }
 
     /// 增加失败次数
-    pub fn increment(&mut self) {
+    pub fn increment(&mut self, now: DateTime<Local>) {
         self.failed_count += 1;
-        self.last_failed_at = Local::now();
+        self.last_failed_at = now;
     }
 
-    /// 检查是否被锁定
-    pub fn is_locked(&self) -> bool {
+    /// 检查在 `now` 时是否被锁定
+    pub fn is_locked(&self, now: DateTime<Local>) -> bool {
         if let Some(locked_until) = self.locked_until {
-            locked_until > Local::now()
+            locked_until > now
         } else {
             false
         }
     }
 
-    /// 设置锁定
-    pub fn lock_for(&mut self, duration_minutes: i64) {
-        self.locked_until = Some(Local::now() + Duration::minutes(duration_minutes));
+    /// 从 `now` 起锁定指定分钟数
+    pub fn lock_for(&mut self, duration_minutes: i64, now: DateTime<Local>) {
+        self.locked_until = Some(now + Duration::minutes(duration_minutes));
     }
 
-    /// 检查是否应该重置（超过时间窗口）
-    pub fn should_reset(&self, window_minutes: i64) -> bool {
+    /// 检查在 `now` 时是否应该重置（超过时间窗口）
+    pub fn should_reset(&self, window_minutes: i64, now: DateTime<Local>) -> bool {
         let window = Duration::minutes(window_minutes);
-        Local::now() - self.first_failed_at > window
+        now - self.first_failed_at > window
     }
 }
 
@@ -87,6 +88,7 @@ impl Default for RateLimitConfig {
 pub struct RateLimiter {
     db: Arc<Db>,
     config: RateLimitConfig,
+    clock: SharedClock,
 }
 
 impl RateLimiter {
@@ -96,28 +98,40 @@ impl RateLimiter {
         Ok(Self {
             db: Arc::new(db),
             config,
+            clock: SystemClock::shared(),
         })
     }
 
+    /// 使用指定时钟（锁定与时间窗口均以该时钟为准）
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// 记录登录失败
-    pub fn record_failure(&self, identifier: &str) -> crate::error::Result<()> {
+    ///
+    /// 返回本次失败是否触发了锁定
+    pub fn record_failure(&self, identifier: &str) -> crate::error::Result<bool> {
         let key = format!("attempt:{}", identifier);
+        let now = self.clock.now();
+        let mut locked = false;
 
         let attempt = if let Some(data) = self.db.get(key.as_bytes())? {
             let mut attempt: LoginAttempt = serde_json::from_slice(&data)
                 .map_err(|e| crate::error::NasError::Storage(format!("解析失败记录错误: {}", e)))?;
 
             // 如果超过时间窗口，重置
-            if attempt.should_reset(self.config.window_minutes) {
-                LoginAttempt::new(identifier.to_string())
+            if attempt.should_reset(self.config.window_minutes, now) {
+                LoginAttempt::new(identifier.to_string(), now)
             } else {
-                attempt.increment();
+                attempt.increment(now);
 
                 // 如果达到最大尝试次数，锁定账户
                 if attempt.failed_count >= self.config.max_attempts
                     && attempt.locked_until.is_none()
                 {
-                    attempt.lock_for(self.config.lock_duration_minutes);
+                    attempt.lock_for(self.config.lock_duration_minutes, now);
+                    locked = true;
                     tracing::warn!(
                         "用户/IP {} 因失败次数过多被锁定 {} 分钟",
                         identifier,
@@ -128,7 +142,7 @@ impl RateLimiter {
                 attempt
             }
         } else {
-            LoginAttempt::new(identifier.to_string())
+            LoginAttempt::new(identifier.to_string(), now)
         };
 
         // 保存到数据库
@@ -136,7 +150,7 @@ impl RateLimiter {
             .map_err(|e| crate::error::NasError::Storage(format!("序列化失败记录错误: {}", e)))?;
         self.db.insert(key.as_bytes(), data)?;
 
-        Ok(())
+        Ok(locked)
     }
 
     /// 检查是否被锁定
@@ -148,7 +162,7 @@ impl RateLimiter {
                 .map_err(|e| crate::error::NasError::Storage(format!("解析失败记录错误: {}", e)))?;
 
             // 如果锁定已过期，清除记录
-            if attempt.is_locked() {
+            if attempt.is_locked(self.clock.now()) {
                 Ok(true)
             } else if attempt.locked_until.is_some() {
                 // 锁定已过期，清除记录
@@ -171,7 +185,7 @@ impl RateLimiter {
                 .map_err(|e| crate::error::NasError::Storage(format!("解析失败记录错误: {}", e)))?;
 
             if let Some(locked_until) = attempt.locked_until {
-                let remaining = locked_until - Local::now();
+                let remaining = locked_until - self.clock.now();
                 if remaining.num_seconds() > 0 {
                     return Ok(Some(remaining.num_seconds()));
                 }
@@ -196,7 +210,7 @@ impl RateLimiter {
             let attempt: LoginAttempt = serde_json::from_slice(&data)
                 .map_err(|e| crate::error::NasError::Storage(format!("解析失败记录错误: {}", e)))?;
 
-            if attempt.should_reset(self.config.window_minutes) {
+            if attempt.should_reset(self.config.window_minutes, self.clock.now()) {
                 // 过期了，返回0
                 Ok(0)
             } else {
@@ -210,15 +224,16 @@ impl RateLimiter {
     /// 手动锁定用户/IP
     pub fn manual_lock(&self, identifier: &str, duration_minutes: i64) -> crate::error::Result<()> {
         let key = format!("attempt:{}", identifier);
+        let now = self.clock.now();
 
         let mut attempt = if let Some(data) = self.db.get(key.as_bytes())? {
             serde_json::from_slice(&data)
                 .map_err(|e| crate::error::NasError::Storage(format!("解析失败记录错误: {}", e)))?
         } else {
-            LoginAttempt::new(identifier.to_string())
+            LoginAttempt::new(identifier.to_string(), now)
         };
 
-        attempt.lock_for(duration_minutes);
+        attempt.lock_for(duration_minutes, now);
 
         let data = serde_json::to_vec(&attempt)
             .map_err(|e| crate::error::NasError::Storage(format!("序列化失败记录错误: {}", e)))?;
@@ -240,6 +255,7 @@ impl RateLimiter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use silent_nas_core::MockClock;
     use tempfile::TempDir;
 
     fn create_test_limiter() -> (RateLimiter, TempDir) {
@@ -263,12 +279,12 @@ mod tests {
         assert_eq!(limiter.get_failed_count("test@example.com").unwrap(), 1);
 
         // 第二次失败
-        limiter.record_failure("test@example.com").unwrap();
+        assert!(!limiter.record_failure("test@example.com").unwrap());
         assert!(!limiter.is_locked("test@example.com").unwrap());
         assert_eq!(limiter.get_failed_count("test@example.com").unwrap(), 2);
 
         // 第三次失败 - 应该被锁定
-        limiter.record_failure("test@example.com").unwrap();
+        assert!(limiter.record_failure("test@example.com").unwrap());
         assert!(limiter.is_locked("test@example.com").unwrap());
         assert_eq!(limiter.get_failed_count("test@example.com").unwrap(), 3);
     }
@@ -311,4 +327,32 @@ mod tests {
         assert!(remaining.unwrap() > 0);
         assert!(remaining.unwrap() <= 600); // 10 minutes = 600 seconds
     }
+
+    #[test]
+    fn test_lock_expires_with_mock_clock() {
+        let (limiter, _temp) = create_test_limiter();
+        let clock = Arc::new(MockClock::default());
+        let limiter = limiter.with_clock(clock.clone());
+
+        for _ in 0..3 {
+            limiter.record_failure("test@example.com").unwrap();
+        }
+        assert!(limiter.is_locked("test@example.com").unwrap());
+
+        clock.advance(Duration::minutes(29));
+        assert!(limiter.is_locked("test@example.com").unwrap());
+        assert_eq!(
+            limiter.get_lock_remaining("test@example.com").unwrap(),
+            Some(60)
+        );
+
+        // 锁定到期后自动解除并清除记录
+        clock.advance(Duration::minutes(2));
+        assert!(!limiter.is_locked("test@example.com").unwrap());
+        assert_eq!(
+            limiter.get_lock_remaining("test@example.com").unwrap(),
+            None
+        );
+        assert_eq!(limiter.get_failed_count("test@example.com").unwrap(), 0);
+    }
 }
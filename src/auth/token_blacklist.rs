@@ -4,6 +4,7 @@
 
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
+use silent_nas_core::{SharedClock, SystemClock};
 use sled::Db;
 use std::path::Path;
 use std::sync::Arc;
@@ -26,13 +27,23 @@ pub struct BlacklistedToken {
 /// Token黑名单管理器
 pub struct TokenBlacklist {
     db: Arc<Db>,
+    clock: SharedClock,
 }
 
 impl TokenBlacklist {
     /// 创建Token黑名单管理器
     pub fn new<P: AsRef<Path>>(db_path: P) -> crate::error::Result<Self> {
         let db = sled::open(db_path)?;
-        Ok(Self { db: Arc::new(db) })
+        Ok(Self {
+            db: Arc::new(db),
+            clock: SystemClock::shared(),
+        })
+    }
+
+    /// 使用指定时钟（黑名单项是否过期以该时钟为准）
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
 
     /// 添加Token到黑名单
@@ -46,7 +57,7 @@ impl TokenBlacklist {
         let item = BlacklistedToken {
             jti: jti.to_string(),
             user_id: user_id.to_string(),
-            blacklisted_at: Local::now(),
+            blacklisted_at: self.clock.now(),
             expires_at,
             reason: reason.to_string(),
         };
@@ -76,7 +87,7 @@ impl TokenBlacklist {
                 .map_err(|e| crate::error::NasError::Storage(format!("解析黑名单项错误: {}", e)))?;
 
             // 检查是否过期
-            if item.expires_at <= Local::now() {
+            if item.expires_at <= self.clock.now() {
                 // 已过期，从黑名单中移除
                 self.db.remove(key.as_bytes())?;
                 Ok(false)
@@ -118,7 +129,7 @@ impl TokenBlacklist {
     /// 清理过期的黑名单项
     pub fn cleanup_expired(&self) -> crate::error::Result<usize> {
         let mut removed = 0;
-        let now = Local::now();
+        let now = self.clock.now();
 
         for item in self.db.scan_prefix(b"token:") {
             let (key, value) = item?;
@@ -142,7 +153,7 @@ impl TokenBlacklist {
     pub fn get_stats(&self) -> crate::error::Result<BlacklistStats> {
         let mut total = 0;
         let mut expired = 0;
-        let now = Local::now();
+        let now = self.clock.now();
 
         for item in self.db.scan_prefix(b"token:") {
             let (_key, value) = item?;
@@ -256,6 +256,7 @@ mod tests {
             hash: "test-hash".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         // 测试设置和获取
@@ -317,6 +318,7 @@ mod tests {
             hash: "test-hash".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
         manager
             .metadata
@@ -357,6 +359,7 @@ mod tests {
             hash: "test-hash".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         // 设置缓存
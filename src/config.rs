@@ -1,5 +1,6 @@
 use crate::error::{NasError, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +16,15 @@ pub struct Config {
     /// 跨节点同步行为配置
     #[serde(default)]
     pub sync: SyncBehaviorConfig,
+    /// 全文搜索配置
+    #[serde(default)]
+    pub search: crate::search::SearchConfig,
+    /// HTTP API 请求限制
+    #[serde(default)]
+    pub http: HttpConfig,
+    /// HTTP API 与 S3 的跨域（CORS）配置
+    #[serde(default)]
+    pub cors: CorsConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +35,119 @@ pub struct ServerConfig {
     pub webdav_port: u16,
     pub s3_port: u16,
     pub host: String,
+    /// 周期后台任务（索引提交、上传会话清理、巡检补拉、墓碑回收）间隔的随机抖动比例（0~1）
+    ///
+    /// 每次等待 `间隔 × (1 ± background_jitter)`，避免多个节点同时执行。
+    /// 存储 GC 的抖动由 `storage.incremental.gc_jitter` 配置
+    #[serde(default = "ServerConfig::default_background_jitter")]
+    pub background_jitter: f64,
+}
+
+impl ServerConfig {
+    fn default_background_jitter() -> f64 {
+        silent_nas_core::DEFAULT_JITTER
+    }
+}
+
+/// HTTP API 请求限制配置
+///
+/// 仅作用于需要完整读取请求体的非流式接口（认证、管理、同步差异、版本恢复等），
+/// 文件上传走流式路径，受存储层 `max_upload_size` 约束。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// 非流式接口默认的请求体上限（字节）
+    #[serde(default = "HttpConfig::default_max_body_bytes")]
+    pub max_body_bytes: u64,
+    /// 按路由覆盖的请求体上限，键为 `/api` 下的路由路径（如 `"sync/delta/<id>"`）
+    #[serde(default)]
+    pub route_body_limits: HashMap<String, u64>,
+}
+
+impl HttpConfig {
+    fn default_max_body_bytes() -> u64 {
+        8 * 1024 * 1024
+    }
+
+    /// 指定路由的请求体上限
+    pub fn body_limit(&self, route: &str) -> u64 {
+        self.route_body_limits
+            .get(route)
+            .copied()
+            .unwrap_or(self.max_body_bytes)
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: Self::default_max_body_bytes(),
+            route_body_limits: HashMap::new(),
+        }
+    }
+}
+
+/// 跨域资源共享（CORS）配置
+///
+/// 同时作用于 HTTP API 与 S3 端点。`allowed_origins` 为空时不处理跨域请求，
+/// 浏览器按同源策略拦截；`"*"` 表示允许任意来源。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// 允许的来源（如 `"https://app.example.com"`），`"*"` 表示任意来源
+    pub allowed_origins: Vec<String>,
+    /// 允许的请求方法
+    pub allowed_methods: Vec<String>,
+    /// 允许的请求头，`"*"` 表示回显预检请求声明的全部请求头
+    pub allowed_headers: Vec<String>,
+    /// 允许浏览器读取的响应头
+    pub expose_headers: Vec<String>,
+    /// 是否允许携带凭据（Cookie、Authorization）
+    ///
+    /// 开启后 `allowed_origins` 必须列出具体来源，不能包含 `"*"`，
+    /// 否则任意站点都能带着用户凭据调用接口
+    pub allow_credentials: bool,
+    /// 预检结果的缓存时长（秒）
+    pub max_age_secs: u64,
+}
+
+impl CorsConfig {
+    /// 是否启用跨域处理
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_origins.is_empty()
+    }
+
+    /// 校验配置
+    pub fn validate(&self) -> Result<()> {
+        if self.allow_credentials && self.allowed_origins.iter().any(|o| o == "*") {
+            return Err(NasError::Config(
+                "cors.allow_credentials 开启时 allowed_origins 不能包含 \"*\"，请列出具体来源"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: ["GET", "HEAD", "PUT", "POST", "DELETE", "PATCH"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            allowed_headers: ["Authorization", "Content-Type", "Range"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            expose_headers: ["ETag", "Content-Length", "Content-Range"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            allow_credentials: false,
+            max_age_secs: 600,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,7 +157,7 @@ pub struct StorageConfig {
     /// 启用压缩
     #[serde(default = "StorageConfig::default_enable_compression")]
     pub enable_compression: bool,
-    /// 压缩算法 (lz4, zstd)
+    /// 压缩算法 (lz4, zstd, auto)
     #[serde(default = "StorageConfig::default_compression_algorithm")]
     pub compression_algorithm: String,
     /// 启用自动GC
@@ -43,6 +166,32 @@ pub struct StorageConfig {
     /// GC触发间隔（秒）
     #[serde(default = "StorageConfig::default_gc_interval_secs")]
     pub gc_interval_secs: u64,
+    /// 完整的增量存储配置（`[storage.incremental]`）
+    ///
+    /// 存在时取代上面的压缩/GC 简化字段，未填写的项使用默认值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incremental: Option<silent_storage::IncrementalConfig>,
+    /// Sled 元数据数据库配置（`[storage.metadata]`），存在时覆盖 `incremental.metadata`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<silent_storage::MetadataDbConfig>,
+    /// 块数据目录，默认 `<root_path>/incremental/chunks`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_root: Option<PathBuf>,
+    /// Sled 元数据数据库目录，默认 `<root_path>/incremental/metadata`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_dir: Option<PathBuf>,
+    /// 搜索索引目录，默认 `<root_path>/index`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_dir: Option<PathBuf>,
+    /// 热存储目录，默认 `<root_path>/hot`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hot_dir: Option<PathBuf>,
+    /// HTTP、S3、WebDAV 删除请求的默认删除语义（`soft` 移入回收站，`hard` 永久删除）
+    ///
+    /// 不设置时各协议使用自身的默认值：HTTP 与 WebDAV 软删除，S3 硬删除；
+    /// 开启版本控制的 S3 桶始终软删除（删除标记）。单个请求可用 `x-delete-mode` 请求头覆盖
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_delete: Option<crate::storage::DeleteMode>,
 }
 
 impl StorageConfig {
@@ -61,6 +210,41 @@ impl StorageConfig {
     fn default_gc_interval_secs() -> u64 {
         3600 // 默认每小时执行一次GC
     }
+
+    /// 生成传给存储管理器的增量配置
+    pub fn incremental_config(&self) -> silent_storage::IncrementalConfig {
+        let mut config = match &self.incremental {
+            Some(incremental) => incremental.clone(),
+            None => silent_storage::IncrementalConfig {
+                enable_compression: self.enable_compression,
+                compression_algorithm: self.compression_algorithm.clone(),
+                enable_auto_gc: self.enable_auto_gc,
+                gc_interval_secs: self.gc_interval_secs,
+                ..Default::default()
+            },
+        };
+        if let Some(metadata) = &self.metadata {
+            config.metadata = metadata.clone();
+        }
+        // [storage] 中的目录配置优先于 [storage.incremental]
+        if self.chunk_root.is_some() {
+            config.chunk_root = self.chunk_root.clone();
+        }
+        if self.metadata_dir.is_some() {
+            config.metadata_dir = self.metadata_dir.clone();
+        }
+        if self.hot_dir.is_some() {
+            config.hot_dir = self.hot_dir.clone();
+        }
+        config
+    }
+
+    /// 搜索索引目录
+    pub fn index_dir(&self) -> PathBuf {
+        self.index_dir
+            .clone()
+            .unwrap_or_else(|| self.root_path.join("index"))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,6 +258,15 @@ pub struct S3Config {
     pub access_key: String,
     pub secret_key: String,
     pub enable_auth: bool,
+    /// PUT 幂等令牌（`Idempotency-Key` 头）的有效期（秒），0 表示不识别令牌
+    #[serde(default = "S3Config::default_idempotency_ttl_secs")]
+    pub idempotency_ttl_secs: u64,
+}
+
+impl S3Config {
+    pub(crate) fn default_idempotency_ttl_secs() -> u64 {
+        15 * 60
+    }
 }
 
 /// 节点发现配置（对应 NodeDiscoveryConfig）
@@ -112,6 +305,9 @@ pub struct SyncBehaviorConfig {
     /// 同步并发文件数
     #[serde(default = "SyncBehaviorConfig::default_max_concurrency")]
     pub max_concurrency: usize,
+    /// 巡检补拉并发文件数
+    #[serde(default = "SyncBehaviorConfig::default_reconcile_concurrency")]
+    pub reconcile_concurrency: usize,
     /// 失败重试次数
     pub max_retries: u32,
     /// 拉取连接超时（秒）
@@ -129,6 +325,12 @@ pub struct SyncBehaviorConfig {
     /// 拉取退避上限（秒）
     #[serde(default = "SyncBehaviorConfig::default_fetch_max_backoff")]
     pub fetch_max_backoff: u64,
+    /// 远程事件处理并发数（同时进行的内容拉取上限）
+    #[serde(default = "SyncBehaviorConfig::default_event_workers")]
+    pub event_workers: usize,
+    /// 远程事件待处理队列容量，队列满时丢弃新事件，遗漏的文件由巡检补拉修复
+    #[serde(default = "SyncBehaviorConfig::default_event_queue_capacity")]
+    pub event_queue_capacity: usize,
     /// 失败补偿队列容量上限
     #[serde(default = "SyncBehaviorConfig::default_fail_queue_max")]
     pub fail_queue_max: usize,
@@ -150,6 +352,9 @@ pub struct SyncBehaviorConfig {
     /// 故障注入：额外延迟（毫秒）
     #[serde(default = "SyncBehaviorConfig::default_fault_delay_ms")]
     pub fault_delay_ms: u64,
+    /// 删除墓碑保留时长（秒），0 表示永不回收；应远大于节点间可能的最长分区时间
+    #[serde(default = "SyncBehaviorConfig::default_tombstone_retention_secs")]
+    pub tombstone_retention_secs: u64,
 }
 
 impl Default for SyncBehaviorConfig {
@@ -159,12 +364,15 @@ impl Default for SyncBehaviorConfig {
             sync_interval: 60,
             max_files_per_sync: 100,
             max_concurrency: Self::default_max_concurrency(),
+            reconcile_concurrency: Self::default_reconcile_concurrency(),
             max_retries: 3,
             http_connect_timeout: Self::default_http_connect_timeout(),
             http_request_timeout: Self::default_http_request_timeout(),
             fetch_max_retries: Self::default_fetch_max_retries(),
             fetch_base_backoff: Self::default_fetch_base_backoff(),
             fetch_max_backoff: Self::default_fetch_max_backoff(),
+            event_workers: Self::default_event_workers(),
+            event_queue_capacity: Self::default_event_queue_capacity(),
             fail_queue_max: Self::default_fail_queue_max(),
             fail_task_ttl_secs: Self::default_fail_task_ttl_secs(),
             grpc_connect_timeout: Self::default_grpc_connect_timeout(),
@@ -172,6 +380,7 @@ impl Default for SyncBehaviorConfig {
             fault_transfer_error_rate: Self::default_fault_transfer_rate(),
             fault_verify_error_rate: Self::default_fault_verify_rate(),
             fault_delay_ms: Self::default_fault_delay_ms(),
+            tombstone_retention_secs: Self::default_tombstone_retention_secs(),
         }
     }
 }
@@ -180,6 +389,9 @@ impl SyncBehaviorConfig {
     fn default_max_concurrency() -> usize {
         8
     }
+    fn default_reconcile_concurrency() -> usize {
+        4
+    }
     fn default_http_connect_timeout() -> u64 {
         5
     }
@@ -195,6 +407,12 @@ impl SyncBehaviorConfig {
     fn default_fetch_max_backoff() -> u64 {
         8
     }
+    fn default_event_workers() -> usize {
+        4
+    }
+    fn default_event_queue_capacity() -> usize {
+        1024
+    }
     fn default_fail_queue_max() -> usize {
         1000
     }
@@ -216,6 +434,42 @@ impl SyncBehaviorConfig {
     fn default_fault_delay_ms() -> u64 {
         0
     }
+    fn default_tombstone_retention_secs() -> u64 {
+        7 * 24 * 3600
+    }
+}
+
+/// 密码策略
+///
+/// 由 `auth` 模块在注册、修改密码和管理员重置密码时执行。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    /// 最小长度（字符数）
+    pub min_length: usize,
+    /// 必须包含大写字母
+    pub require_upper: bool,
+    /// 必须包含数字
+    pub require_digit: bool,
+    /// 必须包含特殊字符（非字母数字）
+    pub require_symbol: bool,
+    /// 拒绝常见弱密码（内置列表加 `extra_blocklist`）
+    pub reject_common: bool,
+    /// 额外禁止的密码（比较时忽略大小写）
+    pub extra_blocklist: Vec<String>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            require_upper: false,
+            require_digit: false,
+            require_symbol: false,
+            reject_common: false,
+            extra_blocklist: Vec::new(),
+        }
+    }
 }
 
 /// 认证配置
@@ -231,6 +485,9 @@ pub struct AuthConfig {
     pub access_token_exp: u64,
     /// 刷新令牌过期时间（秒）
     pub refresh_token_exp: u64,
+    /// 密码策略
+    #[serde(default)]
+    pub password_policy: PasswordPolicy,
 }
 
 impl Default for Config {
@@ -243,6 +500,7 @@ impl Default for Config {
                 webdav_port: 8081,
                 s3_port: 9000,
                 host: "127.0.0.1".to_string(),
+                background_jitter: ServerConfig::default_background_jitter(),
             },
             storage: StorageConfig {
                 root_path: PathBuf::from("./storage"),
@@ -251,6 +509,13 @@ impl Default for Config {
                 compression_algorithm: "lz4".to_string(),
                 enable_auto_gc: true,
                 gc_interval_secs: 3600,
+                incremental: None,
+                metadata: None,
+                chunk_root: None,
+                metadata_dir: None,
+                index_dir: None,
+                hot_dir: None,
+                default_delete: None,
             },
             nats: NatsConfig {
                 url: "nats://127.0.0.1:4222".to_string(),
@@ -260,6 +525,7 @@ impl Default for Config {
                 access_key: "minioadmin".to_string(),
                 secret_key: "minioadmin".to_string(),
                 enable_auth: false,
+                idempotency_ttl_secs: S3Config::default_idempotency_ttl_secs(),
             },
             node: NodeConfig {
                 enable: true,
@@ -272,12 +538,15 @@ impl Default for Config {
                 sync_interval: 60,
                 max_files_per_sync: 100,
                 max_concurrency: SyncBehaviorConfig::default_max_concurrency(),
+                reconcile_concurrency: SyncBehaviorConfig::default_reconcile_concurrency(),
                 max_retries: 3,
                 http_connect_timeout: SyncBehaviorConfig::default_http_connect_timeout(),
                 http_request_timeout: SyncBehaviorConfig::default_http_request_timeout(),
                 fetch_max_retries: SyncBehaviorConfig::default_fetch_max_retries(),
                 fetch_base_backoff: SyncBehaviorConfig::default_fetch_base_backoff(),
                 fetch_max_backoff: SyncBehaviorConfig::default_fetch_max_backoff(),
+                event_workers: SyncBehaviorConfig::default_event_workers(),
+                event_queue_capacity: SyncBehaviorConfig::default_event_queue_capacity(),
                 fail_queue_max: SyncBehaviorConfig::default_fail_queue_max(),
                 fail_task_ttl_secs: SyncBehaviorConfig::default_fail_task_ttl_secs(),
                 grpc_connect_timeout: SyncBehaviorConfig::default_grpc_connect_timeout(),
@@ -285,6 +554,7 @@ impl Default for Config {
                 fault_transfer_error_rate: SyncBehaviorConfig::default_fault_transfer_rate(),
                 fault_verify_error_rate: SyncBehaviorConfig::default_fault_verify_rate(),
                 fault_delay_ms: SyncBehaviorConfig::default_fault_delay_ms(),
+                tombstone_retention_secs: SyncBehaviorConfig::default_tombstone_retention_secs(),
             },
             auth: AuthConfig {
                 enable: false,
@@ -292,7 +562,11 @@ impl Default for Config {
                 jwt_secret: "silent-nas-secret-key-change-in-production".to_string(),
                 access_token_exp: 3600,    // 1小时
                 refresh_token_exp: 604800, // 7天
+                password_policy: PasswordPolicy::default(),
             },
+            search: crate::search::SearchConfig::default(),
+            http: HttpConfig::default(),
+            cors: CorsConfig::default(),
         }
     }
 }
@@ -496,6 +770,7 @@ mod tests {
             webdav_port: 8082,
             s3_port: 9001,
             host: "0.0.0.0".to_string(),
+            background_jitter: 0.2,
         };
 
         assert_eq!(server.http_port, 9090);
@@ -511,6 +786,13 @@ mod tests {
             compression_algorithm: "zstd".to_string(),
             enable_auto_gc: true,
             gc_interval_secs: 7200,
+            incremental: None,
+            metadata: None,
+            chunk_root: None,
+            metadata_dir: None,
+            index_dir: None,
+            hot_dir: None,
+            default_delete: None,
         };
 
         assert_eq!(storage.root_path, PathBuf::from("/tmp/storage"));
@@ -538,6 +820,7 @@ mod tests {
             access_key: "test_key".to_string(),
             secret_key: "test_secret".to_string(),
             enable_auth: true,
+            idempotency_ttl_secs: 0,
         };
 
         assert_eq!(s3.access_key, "test_key");
@@ -657,6 +940,7 @@ refresh_token_exp = 1209600
             jwt_secret: "test-secret".to_string(),
             access_token_exp: 7200,
             refresh_token_exp: 1209600,
+            password_policy: PasswordPolicy::default(),
         };
 
         assert!(auth.enable);
@@ -741,4 +1025,49 @@ refresh_token_exp = 1209600
         // 清理
         let _ = fs::remove_file(temp_file);
     }
+    #[test]
+    fn test_http_route_body_limits() {
+        let config: HttpConfig = toml::from_str(
+            r#"
+max_body_bytes = 1024
+[route_body_limits]
+"sync/delta/<id>" = 4096
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.body_limit("sync/delta/<id>"), 4096);
+        assert_eq!(config.body_limit("auth/login"), 1024);
+        assert_eq!(
+            Config::default().http.body_limit("auth/login"),
+            8 * 1024 * 1024
+        );
+    }
+
+    #[test]
+    fn test_storage_dirs() {
+        let storage: StorageConfig = toml::from_str(
+            r#"
+root_path = "/srv/nas"
+chunk_size = 4194304
+chunk_root = "/mnt/hdd/chunks"
+index_dir = "/mnt/ssd/index"
+
+[incremental]
+hot_dir = "/mnt/ssd/hot"
+"#,
+        )
+        .unwrap();
+        let incremental = storage.incremental_config();
+        assert_eq!(
+            incremental.chunk_root,
+            Some(PathBuf::from("/mnt/hdd/chunks"))
+        );
+        assert_eq!(incremental.hot_dir, Some(PathBuf::from("/mnt/ssd/hot")));
+        assert!(incremental.metadata_dir.is_none());
+        assert_eq!(storage.index_dir(), PathBuf::from("/mnt/ssd/index"));
+        assert_eq!(
+            Config::default().storage.index_dir(),
+            PathBuf::from("./storage/index")
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use http::StatusCode;
+use silent_storage::StorageError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -39,6 +41,9 @@ pub enum NasError {
     #[error("哈希校验失败")]
     HashMismatch,
 
+    #[error("查询语法错误: {0}")]
+    InvalidQuery(String),
+
     #[error("{0}")]
     Other(String),
 }
@@ -51,14 +56,34 @@ impl From<sled::Error> for NasError {
 }
 
 // 为 silent_storage::StorageError 实现 From trait
-impl From<silent_storage::StorageError> for NasError {
-    fn from(err: silent_storage::StorageError) -> Self {
+impl From<StorageError> for NasError {
+    fn from(err: StorageError) -> Self {
         NasError::Storage(format!("存储错误: {}", err))
     }
 }
 
 pub type Result<T> = std::result::Result<T, NasError>;
 
+/// 存储错误对应的 HTTP 状态码（HTTP API、WebDAV 与 S3 共用）
+pub fn status_for(err: &StorageError) -> StatusCode {
+    match err {
+        StorageError::FileNotFound(_)
+        | StorageError::VersionNotFound(_)
+        | StorageError::DanglingAlias(_) => StatusCode::NOT_FOUND,
+        StorageError::QuotaExceeded(_) | StorageError::OutOfSpace(_) => {
+            StatusCode::INSUFFICIENT_STORAGE
+        }
+        StorageError::FileTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+        StorageError::ReadOnly(_) | StorageError::NotLeader(_) => StatusCode::CONFLICT,
+        StorageError::Busy(_) | StorageError::Maintenance(_) => StatusCode::SERVICE_UNAVAILABLE,
+        StorageError::ChecksumMismatch(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        StorageError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+        StorageError::RetentionLocked(_) => StatusCode::FORBIDDEN,
+        StorageError::ReservedPath(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,6 +151,12 @@ mod tests {
         assert_eq!(err.to_string(), "哈希校验失败");
     }
 
+    #[test]
+    fn test_invalid_query_error() {
+        let err = NasError::InvalidQuery("缺少 FROM 子句".to_string());
+        assert_eq!(err.to_string(), "查询语法错误: 缺少 FROM 子句");
+    }
+
     #[test]
     fn test_other_error() {
         let err = NasError::Other("其他错误".to_string());
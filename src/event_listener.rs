@@ -2,16 +2,76 @@ use crate::error::Result;
 use crate::models::FileEvent;
 use crate::sync::crdt::{FileSync, SyncManager};
 use crate::sync::incremental::IncrementalSyncHandler;
+use bytes::Bytes;
 use futures_util::StreamExt;
 use sha2::{Digest, Sha256};
 use silent_nas_core::StorageManagerTrait;
+use std::future::Future;
 use std::sync::Arc;
+use tokio::sync::{Mutex, mpsc};
 use tokio::time::{Duration, sleep};
 use tracing::{debug, error, info, warn};
 
 fn jittered_secs(base: u64) -> u64 {
-    let jitter = rand::random::<f64>() * 0.4 + 0.8; // 0.8~1.2
-    ((base as f64) * jitter).round() as u64
+    // 0.8~1.2
+    silent_nas_core::jittered(Duration::from_secs(base), 0.2)
+        .as_secs_f64()
+        .round() as u64
+}
+
+/// 默认的事件处理并发数
+const DEFAULT_EVENT_WORKERS: usize = 4;
+/// 默认的事件队列容量
+const DEFAULT_EVENT_QUEUE_CAPACITY: usize = 1024;
+
+/// 远程事件处理队列
+///
+/// 收到的事件先放入有界队列，由固定数量的工作任务依次取出处理，
+/// 同时进行的内容拉取不超过工作任务数。突发大量事件时队列满后丢弃新事件并计数
+/// （`sync_events_dropped_total`），遗漏的文件由定期巡检补拉修复。
+struct EventQueue {
+    tx: mpsc::Sender<Bytes>,
+}
+
+impl EventQueue {
+    /// 启动 `workers` 个工作任务，队列最多缓存 `capacity` 个待处理事件
+    fn spawn<F, Fut>(workers: usize, capacity: usize, handler: F) -> Self
+    where
+        F: Fn(Bytes) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let rx = Arc::new(Mutex::new(rx));
+        let handler = Arc::new(handler);
+        for _ in 0..workers.max(1) {
+            let rx = rx.clone();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                loop {
+                    // 只在取事件时持有锁，处理期间其他工作任务可以继续取
+                    let payload = rx.lock().await.recv().await;
+                    let Some(payload) = payload else {
+                        break;
+                    };
+                    handler(payload).await;
+                }
+            });
+        }
+        Self { tx }
+    }
+
+    /// 放入事件，队列已满时丢弃并返回 `false`
+    fn offer(&self, payload: Bytes) -> bool {
+        match self.tx.try_send(payload) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                crate::metrics::record_sync_event_dropped();
+                warn!("事件队列已满，丢弃远程事件（由巡检补拉修复）");
+                false
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
 }
 
 /// NATS 事件监听器
@@ -27,6 +87,9 @@ pub struct EventListener {
     fetch_max_retries: u32,
     fetch_base_backoff: u64,
     fetch_max_backoff: u64,
+    // 事件处理并发与队列容量
+    event_workers: usize,
+    event_queue_capacity: usize,
 }
 
 impl EventListener {
@@ -55,9 +118,18 @@ impl EventListener {
             fetch_max_retries,
             fetch_base_backoff,
             fetch_max_backoff,
+            event_workers: DEFAULT_EVENT_WORKERS,
+            event_queue_capacity: DEFAULT_EVENT_QUEUE_CAPACITY,
         }
     }
 
+    /// 设置事件处理并发数与待处理队列容量
+    pub fn with_event_queue(mut self, workers: usize, capacity: usize) -> Self {
+        self.event_workers = workers;
+        self.event_queue_capacity = capacity;
+        self
+    }
+
     fn backoff_delay(&self, attempt: u32) -> Duration {
         let factor = 1u64 << attempt.min(6);
         let mut secs = self.fetch_base_backoff.saturating_mul(factor);
@@ -72,21 +144,32 @@ impl EventListener {
         let node_id = self.sync_manager.node_id().to_string();
         info!("启动事件监听器: node_id={}", node_id);
 
-        // 订阅所有文件事件（使用通配符）
-        let topic_pattern = format!("{}.*", self.topic_prefix);
+        // 订阅所有文件事件（全局主题，不含路径子主题）
+        let topic_pattern = crate::notify::SubscriptionFilter::all().subject(&self.topic_prefix);
         let mut subscriber = self
             .nats_client
             .subscribe(topic_pattern.clone())
             .await
             .map_err(|e| crate::error::NasError::Nats(format!("订阅主题失败: {}", e)))?;
 
-        info!("开始监听主题: {}", topic_pattern);
+        info!(
+            "开始监听主题: {} (并发={}, 队列容量={})",
+            topic_pattern, self.event_workers, self.event_queue_capacity
+        );
 
-        // 持续监听消息
-        while let Some(message) = subscriber.next().await {
-            if let Err(e) = self.handle_event(&message.payload).await {
-                error!("处理事件失败: {}", e);
+        // 持续监听消息，交给有界队列中的工作任务处理
+        let (workers, capacity) = (self.event_workers, self.event_queue_capacity);
+        let listener = Arc::new(self);
+        let queue = EventQueue::spawn(workers, capacity, move |payload| {
+            let listener = listener.clone();
+            async move {
+                if let Err(e) = listener.handle_event(&payload).await {
+                    error!("处理事件失败: {}", e);
+                }
             }
+        });
+        while let Some(message) = subscriber.next().await {
+            queue.offer(message.payload);
         }
 
         warn!("事件监听器已停止");
@@ -366,6 +449,7 @@ mod tests {
             hash: "testhash".to_string(),
             created_at: chrono::Local::now().naive_local(),
             modified_at: chrono::Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let event = FileEvent::new(
@@ -412,6 +496,49 @@ mod tests {
         assert!(!meta.hash.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_event_queue_bounds_concurrent_handling() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let handled = Arc::new(AtomicUsize::new(0));
+        let queue = {
+            let (in_flight, max_in_flight, handled) =
+                (in_flight.clone(), max_in_flight.clone(), handled.clone());
+            EventQueue::spawn(3, 16, move |_payload| {
+                let (in_flight, max_in_flight, handled) =
+                    (in_flight.clone(), max_in_flight.clone(), handled.clone());
+                async move {
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    // 模拟耗时的内容拉取
+                    sleep(Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    handled.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+
+        // 突发 200 个事件：超出队列容量的被丢弃，其余全部处理
+        let accepted = (0..200)
+            .filter(|i| queue.offer(Bytes::from(format!("event-{}", i))))
+            .count();
+        assert!(accepted >= 16);
+        assert!(accepted < 200);
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while handled.load(Ordering::SeqCst) < accepted {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(handled.load(Ordering::SeqCst), accepted);
+        assert!(max_in_flight.load(Ordering::SeqCst) <= 3);
+        assert_eq!(in_flight.load(Ordering::SeqCst), 0);
+    }
+
     #[test]
     fn test_topic_pattern_format() {
         // 测试主题模式格式
@@ -1,6 +1,7 @@
 //! 管理员API处理器
 
 use super::state::AppState;
+use super::storage_error::storage_error;
 use crate::auth::{UserInfo, UserRole, UserStatus};
 use crate::error::NasError;
 use http::StatusCode;
@@ -217,8 +218,8 @@ pub struct UpdateUserRequest {
 /// 重置密码请求
 #[derive(Debug, Deserialize, Validate)]
 pub struct ResetPasswordRequest {
-    /// 新密码（8-72个字符）
-    #[validate(length(min = 8, max = 72, message = "密码长度必须在8-72个字符之间"))]
+    /// 新密码（最多72个字符，复杂度由密码策略检查）
+    #[validate(length(min = 1, max = 72, message = "密码长度必须在1-72个字符之间"))]
     pub new_password: String,
 }
 
@@ -539,14 +540,101 @@ pub async fn trigger_gc(
     })?;
 
     info!("垃圾回收完成，清理了 {} 个未引用的块", deleted_count);
+    let usage = storage.recompute_usage_after_gc().await;
 
     Ok(serde_json::json!({
         "success": true,
         "deleted_blocks": deleted_count,
+        "usage": usage,
         "message": format!("垃圾回收完成，清理了 {} 个未引用的块", deleted_count)
     }))
 }
 
+/// 重建去重索引
+///
+/// POST /api/admin/dedup/rebuild
+/// 需要管理员权限
+/// 从所有版本的差异数据重新统计块引用计数，中断后再次调用从断点继续。
+/// 需要先进入维护模式，否则返回 412
+pub async fn rebuild_dedup_index(
+    _req: Request,
+    _state: CfgExtractor<AppState>,
+) -> silent::Result<serde_json::Value> {
+    info!("管理员触发去重索引重建");
+
+    let result = crate::storage::storage()
+        .rebuild_dedup_index()
+        .await
+        .map_err(|e| storage_error("重建去重索引失败", e))?;
+
+    Ok(serde_json::to_value(&result).unwrap())
+}
+
+/// 进入维护模式
+///
+/// POST /api/admin/maintenance/enter
+/// 需要管理员权限
+/// 停止 GC、后台优化与同步巡检任务并刷新数据，期间拒绝写操作（503），读取不受影响
+pub async fn enter_maintenance(
+    _req: Request,
+    _state: CfgExtractor<AppState>,
+) -> silent::Result<serde_json::Value> {
+    info!("管理员请求进入维护模式");
+
+    crate::storage::storage()
+        .enter_maintenance()
+        .await
+        .map_err(|e| {
+            SilentError::business_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("进入维护模式失败: {}", e),
+            )
+        })?;
+
+    Ok(serde_json::json!({ "success": true, "maintenance": true }))
+}
+
+/// 退出维护模式
+///
+/// POST /api/admin/maintenance/exit
+/// 需要管理员权限
+/// 恢复写操作并重新启动进入维护模式前在运行的后台任务
+pub async fn exit_maintenance(
+    _req: Request,
+    _state: CfgExtractor<AppState>,
+) -> silent::Result<serde_json::Value> {
+    info!("管理员请求退出维护模式");
+
+    crate::storage::storage()
+        .exit_maintenance()
+        .await
+        .map_err(|e| {
+            SilentError::business_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("退出维护模式失败: {}", e),
+            )
+        })?;
+
+    Ok(serde_json::json!({ "success": true, "maintenance": false }))
+}
+
+/// 获取维护模式状态
+///
+/// GET /api/admin/maintenance/status
+/// 需要管理员权限
+pub async fn get_maintenance_status(
+    _req: Request,
+    _state: CfgExtractor<AppState>,
+) -> silent::Result<serde_json::Value> {
+    let storage = crate::storage::storage();
+
+    Ok(serde_json::json!({
+        "maintenance": storage.is_in_maintenance(),
+        "gc_task_running": storage.is_gc_task_running().await,
+        "optimization_paused": storage.is_optimization_paused(),
+    }))
+}
+
 /// GC状态响应
 #[derive(Debug, Serialize)]
 pub struct GcStatusResponse {
@@ -581,6 +669,136 @@ pub async fn get_gc_status(
     Ok(serde_json::to_value(&response).unwrap())
 }
 
+/// 优化积压状态响应
+#[derive(Debug, Serialize)]
+pub struct OptimizationStatusResponse {
+    /// 热存储中等待优化的字节数
+    pub hot_storage_bytes: u64,
+    /// 优化队列长度
+    pub queue_length: usize,
+    /// 优化调度器是否暂停
+    pub paused: bool,
+}
+
+/// 获取优化积压状态
+///
+/// GET /api/admin/optimization/status
+/// 需要管理员权限
+/// 返回热存储中尚未优化的数据量与优化队列长度，两者持续增长说明优化器滞后
+pub async fn get_optimization_status(
+    _req: Request,
+    _state: CfgExtractor<AppState>,
+) -> silent::Result<serde_json::Value> {
+    let storage = crate::storage::storage();
+
+    let (hot_storage_bytes, queue_length) =
+        super::metrics_api::refresh_optimization_backlog(storage)
+            .await
+            .map_err(|e| {
+                SilentError::business_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("统计热存储失败: {}", e),
+                )
+            })?;
+
+    let response = OptimizationStatusResponse {
+        hot_storage_bytes,
+        queue_length,
+        paused: storage.is_optimization_paused(),
+    };
+
+    Ok(serde_json::to_value(&response).unwrap())
+}
+
+/// 块引用者
+#[derive(Debug, Serialize)]
+pub struct ChunkReferrer {
+    /// 文件ID
+    pub file_id: String,
+    /// 版本ID
+    pub version_id: String,
+}
+
+/// 获取引用指定块的所有版本
+///
+/// GET /api/admin/chunks/:id/referrers
+/// 需要管理员权限
+/// 基于块反向索引列出引用该块的 (文件, 版本)，用于排查去重问题
+pub async fn get_chunk_referrers(
+    mut req: Request,
+    _state: CfgExtractor<AppState>,
+) -> silent::Result<serde_json::Value> {
+    let chunk_id = req
+        .params()
+        .get("id")
+        .ok_or_else(|| SilentError::business_error(StatusCode::BAD_REQUEST, "缺少块ID参数"))?
+        .to_string();
+
+    let referrers = crate::storage::storage()
+        .chunk_referrers(&chunk_id)
+        .await
+        .map_err(|e| {
+            SilentError::business_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("查询块引用者失败: {}", e),
+            )
+        })?
+        .into_iter()
+        .map(|(file_id, version_id)| ChunkReferrer {
+            file_id,
+            version_id,
+        })
+        .collect::<Vec<_>>();
+
+    Ok(serde_json::json!({
+        "chunk_id": chunk_id,
+        "count": referrers.len(),
+        "referrers": referrers,
+    }))
+}
+
+/// 获取分块大小建议
+///
+/// GET /api/admin/chunking/recommendation
+/// 需要管理员权限
+/// 根据已有块的大小分布与去重率给出平均分块大小建议，仅供参考，不修改配置
+pub async fn get_chunking_recommendation(
+    _req: Request,
+    _state: CfgExtractor<AppState>,
+) -> silent::Result<serde_json::Value> {
+    let recommendation = crate::storage::storage()
+        .analyze_chunking()
+        .await
+        .map_err(|e| {
+            SilentError::business_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("分析分块情况失败: {}", e),
+            )
+        })?;
+
+    Ok(serde_json::to_value(&recommendation).unwrap())
+}
+
+/// 获取各节点同步状态
+///
+/// GET /api/admin/sync/status
+/// 需要管理员权限
+/// 返回每个节点的最后同步时间、待同步文件数、滞后秒数与健康状态
+pub async fn get_node_sync_status(
+    _req: Request,
+    _state: CfgExtractor<AppState>,
+) -> silent::Result<serde_json::Value> {
+    let Some(coordinator) = crate::sync::node::try_node_sync() else {
+        return Err(SilentError::business_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "节点同步未启用",
+        ));
+    };
+
+    let nodes = coordinator.node_sync_status().await;
+    Ok(serde_json::json!({ "nodes": nodes }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,7 +60,9 @@ pub async fn get_audit_stats(
 ) -> silent::Result<serde_json::Value> {
     if let Some(ref audit_logger) = state.audit_logger {
         let stats = audit_logger.get_stats().await;
-        Ok(serde_json::to_value(stats).unwrap())
+        let mut value = serde_json::to_value(stats).unwrap();
+        value["chain_valid"] = serde_json::Value::Bool(audit_logger.verify_chain().await);
+        Ok(value)
     } else {
         Err(SilentError::business_error(
             StatusCode::NOT_IMPLEMENTED,
@@ -75,6 +77,7 @@ fn parse_audit_action(s: &str) -> silent::Result<AuditAction> {
         "fileupload" | "file_upload" => Ok(AuditAction::FileUpload),
         "filedownload" | "file_download" => Ok(AuditAction::FileDownload),
         "filedelete" | "file_delete" => Ok(AuditAction::FileDelete),
+        "filepurge" | "file_purge" => Ok(AuditAction::FilePurge),
         "versioncreate" | "version_create" => Ok(AuditAction::VersionCreate),
         "versionrestore" | "version_restore" => Ok(AuditAction::VersionRestore),
         "versiondelete" | "version_delete" => Ok(AuditAction::VersionDelete),
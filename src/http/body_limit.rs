@@ -0,0 +1,121 @@
+//! 请求体大小限制中间件
+//!
+//! 用于需要完整读取请求体的非流式接口：先按 `Content-Length` 拒绝，
+//! 未声明长度（分块传输）时边读边计数，超过上限立即返回 413，不再继续读取。
+//! 读取完成的请求体以 `ReqBody::Once` 交给后续处理器。
+
+use bytes::BytesMut;
+use http::StatusCode;
+use http_body_util::BodyExt;
+use silent::SilentError;
+use silent::middleware::MiddleWareHandler;
+use silent::prelude::*;
+
+/// 请求体大小限制 Hook
+#[derive(Clone)]
+pub struct BodyLimitHook {
+    max_bytes: u64,
+}
+
+impl BodyLimitHook {
+    pub fn new(max_bytes: u64) -> Self {
+        Self { max_bytes }
+    }
+}
+
+#[async_trait::async_trait]
+impl MiddleWareHandler for BodyLimitHook {
+    async fn handle(&self, mut req: Request, next: &Next) -> silent::Result<Response> {
+        limit_body(&mut req, self.max_bytes).await?;
+        next.call(req).await
+    }
+}
+
+fn payload_too_large(max_bytes: u64) -> SilentError {
+    SilentError::business_error(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("请求体超过上限 {} 字节", max_bytes),
+    )
+}
+
+/// 检查并缓冲请求体，超过上限返回 413
+pub(crate) async fn limit_body(req: &mut Request, max_bytes: u64) -> silent::Result<()> {
+    let declared = req
+        .headers()
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|len| len > max_bytes) {
+        return Err(payload_too_large(max_bytes));
+    }
+
+    let body = match req.take_body() {
+        ReqBody::Incoming(mut incoming) => {
+            let mut buf = BytesMut::new();
+            while let Some(frame) = incoming.frame().await {
+                let frame = frame.map_err(|e| {
+                    SilentError::business_error(
+                        StatusCode::BAD_REQUEST,
+                        format!("读取请求体失败: {}", e),
+                    )
+                })?;
+                if let Ok(data) = frame.into_data() {
+                    if (buf.len() + data.len()) as u64 > max_bytes {
+                        return Err(payload_too_large(max_bytes));
+                    }
+                    buf.extend_from_slice(&data);
+                }
+            }
+            ReqBody::Once(buf.freeze())
+        }
+        ReqBody::Once(bytes) => {
+            if bytes.len() as u64 > max_bytes {
+                return Err(payload_too_large(max_bytes));
+            }
+            ReqBody::Once(bytes)
+        }
+        ReqBody::Empty => ReqBody::Empty,
+    };
+    req.replace_body(body);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post_request(body: &'static [u8], content_length: Option<usize>) -> Request {
+        let mut builder = http::Request::builder()
+            .method("POST")
+            .uri("/api/sync/delta/file-1");
+        if let Some(len) = content_length {
+            builder = builder.header(http::header::CONTENT_LENGTH, len);
+        }
+        let (parts, _) = builder.body(()).unwrap().into_parts();
+        Request::from_parts(parts, ReqBody::Once(bytes::Bytes::from_static(body)))
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_rejected_with_413() {
+        // 声明的长度超限：不读取请求体直接拒绝
+        let mut req = post_request(b"0123456789abcdef-overflow", Some(25));
+        let err = limit_body(&mut req, 16).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // 未声明长度：按实际读取的字节数拒绝
+        let mut req = post_request(b"0123456789abcdef-overflow", None);
+        let err = limit_body(&mut req, 16).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_passes_through() {
+        let mut req = post_request(b"small", Some(5));
+        limit_body(&mut req, 16).await.unwrap();
+
+        match req.take_body() {
+            ReqBody::Once(body) => assert_eq!(&body[..], b"small"),
+            _ => panic!("请求体应被缓冲为 Once"),
+        }
+    }
+}
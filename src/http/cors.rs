@@ -0,0 +1,306 @@
+//! 跨域资源共享（CORS）中间件
+//!
+//! 挂在 HTTP API 与 S3 的根路由上，在路由匹配前处理：
+//! - 预检请求（带 `Access-Control-Request-Method` 的 `OPTIONS`）直接返回 204，
+//!   来源、方法或请求头不被允许时返回 403；
+//! - 其他跨域请求交给后续处理器，并在响应上附加 `Access-Control-*` 头；
+//! - 不带 `Origin` 或与 `Host` 同源的请求原样放行。
+//!
+//! 配置了 `"*"` 时返回通配符；否则回显请求的具体来源并附加 `Vary: Origin`。
+//! 通配符不能与携带凭据同时开启，由 [`CorsConfig::validate`] 在启动时拒绝。
+
+use crate::config::CorsConfig;
+use http::header::{self, HeaderMap, HeaderValue};
+use http::{Method, StatusCode};
+use silent::SilentError;
+use silent::middleware::MiddleWareHandler;
+use silent::prelude::*;
+
+/// CORS Hook
+#[derive(Clone)]
+pub struct CorsHook {
+    config: CorsConfig,
+}
+
+impl CorsHook {
+    pub fn new(config: CorsConfig) -> Self {
+        Self { config }
+    }
+
+    fn allows_any_origin(&self) -> bool {
+        self.config.allowed_origins.iter().any(|o| o == "*")
+    }
+
+    fn origin_allowed(&self, origin: &str) -> bool {
+        self.allows_any_origin()
+            || self
+                .config
+                .allowed_origins
+                .iter()
+                .any(|o| o.trim_end_matches('/').eq_ignore_ascii_case(origin))
+    }
+
+    fn method_allowed(&self, method: &str) -> bool {
+        self.config
+            .allowed_methods
+            .iter()
+            .any(|m| m == "*" || m.eq_ignore_ascii_case(method))
+    }
+
+    fn headers_allowed(&self, requested: &[String]) -> bool {
+        if self.config.allowed_headers.iter().any(|h| h == "*") {
+            return true;
+        }
+        requested.iter().all(|name| {
+            self.config
+                .allowed_headers
+                .iter()
+                .any(|h| h.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// 写入 `Access-Control-Allow-Origin` / `-Allow-Credentials` / `Vary`
+    fn apply_origin(&self, headers: &mut HeaderMap, origin: &HeaderValue) {
+        if self.allows_any_origin() {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            );
+        } else {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        if self.config.allow_credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+
+    /// 处理预检请求
+    fn preflight(&self, req: &Request, origin: &HeaderValue) -> silent::Result<Response> {
+        let requested_method = req
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        if !self.method_allowed(requested_method) {
+            return Err(forbidden(format!(
+                "跨域请求方法不被允许: {}",
+                requested_method
+            )));
+        }
+
+        let requested_headers: Vec<String> = req
+            .headers()
+            .get_all(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|h| h.trim().to_ascii_lowercase())
+            .filter(|h| !h.is_empty())
+            .collect();
+        if !self.headers_allowed(&requested_headers) {
+            return Err(forbidden("跨域请求头不被允许"));
+        }
+
+        let mut resp = Response::empty();
+        resp.set_status(StatusCode::NO_CONTENT);
+        let headers = resp.headers_mut();
+        self.apply_origin(headers, origin);
+        if let Ok(value) = HeaderValue::from_str(&self.config.allowed_methods.join(", ")) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, value);
+        }
+        // 通配请求头回显预检声明的请求头：携带凭据时规范不把 "*" 视为通配
+        let allow_headers = if self.config.allowed_headers.iter().any(|h| h == "*") {
+            requested_headers.join(", ")
+        } else {
+            self.config.allowed_headers.join(", ")
+        };
+        if !allow_headers.is_empty()
+            && let Ok(value) = HeaderValue::from_str(&allow_headers)
+        {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, value);
+        }
+        headers.insert(
+            header::ACCESS_CONTROL_MAX_AGE,
+            HeaderValue::from(self.config.max_age_secs),
+        );
+        headers.append(
+            header::VARY,
+            HeaderValue::from_static(
+                "Access-Control-Request-Method, Access-Control-Request-Headers",
+            ),
+        );
+        Ok(resp)
+    }
+
+    /// 在实际请求的响应上附加跨域头
+    fn apply_actual(&self, resp: &mut Response, origin: &HeaderValue) {
+        let headers = resp.headers_mut();
+        self.apply_origin(headers, origin);
+        if !self.config.expose_headers.is_empty()
+            && let Ok(value) = HeaderValue::from_str(&self.config.expose_headers.join(", "))
+        {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, value);
+        }
+    }
+}
+
+fn forbidden(msg: impl Into<String>) -> SilentError {
+    SilentError::business_error(StatusCode::FORBIDDEN, msg.into())
+}
+
+/// `Origin` 与 `Host` 一致的同源请求（浏览器对同源 POST 也会带 `Origin`）
+fn is_same_origin(req: &Request, origin: &str) -> bool {
+    let authority = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    req.headers()
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|host| host.eq_ignore_ascii_case(authority))
+}
+
+#[async_trait::async_trait]
+impl MiddleWareHandler for CorsHook {
+    async fn handle(&self, req: Request, next: &Next) -> silent::Result<Response> {
+        let Some(origin) = req.headers().get(header::ORIGIN).cloned() else {
+            return next.call(req).await;
+        };
+        let origin_str = origin.to_str().unwrap_or_default();
+        if is_same_origin(&req, origin_str) {
+            return next.call(req).await;
+        }
+        if !self.origin_allowed(origin_str) {
+            return Err(forbidden(format!("跨域来源不被允许: {}", origin_str)));
+        }
+
+        if req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            return self.preflight(&req, &origin);
+        }
+
+        let mut resp = next.call(req).await?;
+        self.apply_actual(&mut resp, &origin);
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cors_config(origins: &[&str], allow_credentials: bool) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            allowed_headers: vec!["Authorization".into(), "Content-Type".into()],
+            allow_credentials,
+            max_age_secs: 120,
+            ..Default::default()
+        }
+    }
+
+    fn preflight_request(origin: &str, method: &str, headers: &str) -> Request {
+        let (parts, _) = http::Request::builder()
+            .method("OPTIONS")
+            .uri("/api/files")
+            .header(header::HOST, "nas.local:8080")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, method)
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, headers)
+            .body(())
+            .unwrap()
+            .into_parts();
+        Request::from_parts(parts, ReqBody::Empty)
+    }
+
+    #[test]
+    fn test_preflight_returns_configured_allow_headers() {
+        let hook = CorsHook::new(cors_config(&["https://app.example.com"], true));
+        let req = preflight_request(
+            "https://app.example.com",
+            "PUT",
+            "authorization, content-type",
+        );
+        let origin = req.headers()[header::ORIGIN].clone();
+        assert!(hook.origin_allowed(origin.to_str().unwrap()));
+
+        let resp = hook.preflight(&req, &origin).unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let headers = resp.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "Authorization, Content-Type"
+        );
+        assert!(
+            headers[header::ACCESS_CONTROL_ALLOW_METHODS]
+                .to_str()
+                .unwrap()
+                .contains("PUT")
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "120");
+
+        // 未配置的请求头被拒绝
+        let req = preflight_request("https://app.example.com", "PUT", "x-custom");
+        let err = hook.preflight(&req, &origin).unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_disallowed_origin_rejected() {
+        let hook = CorsHook::new(cors_config(&["https://app.example.com"], false));
+        assert!(!hook.origin_allowed("https://evil.example.com"));
+        assert!(!hook.origin_allowed("https://app.example.com.evil.com"));
+
+        // 同源请求不受来源列表限制
+        let req = preflight_request("http://nas.local:8080", "GET", "");
+        assert!(is_same_origin(&req, "http://nas.local:8080"));
+        assert!(!is_same_origin(&req, "https://evil.example.com"));
+    }
+
+    #[test]
+    fn test_credentials_echo_explicit_origin() {
+        let origin = HeaderValue::from_static("https://app.example.com");
+
+        // 通配符：不回显来源，也不允许凭据
+        let hook = CorsHook::new(cors_config(&["*"], false));
+        let mut headers = HeaderMap::new();
+        hook.apply_origin(&mut headers, &origin);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .is_none()
+        );
+
+        // 带凭据：只对明确列出的来源回显
+        let hook = CorsHook::new(cors_config(&["https://app.example.com"], true));
+        let mut headers = HeaderMap::new();
+        hook.apply_origin(&mut headers, &origin);
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[header::VARY], "Origin");
+    }
+
+    #[test]
+    fn test_wildcard_origin_with_credentials_rejected() {
+        assert!(cors_config(&["*"], true).validate().is_err());
+        assert!(cors_config(&["*"], false).validate().is_ok());
+        assert!(
+            cors_config(&["https://app.example.com"], true)
+                .validate()
+                .is_ok()
+        );
+    }
+}
@@ -1,20 +1,39 @@
 //! 文件操作 API 端点
 
 use super::state::AppState;
+use super::storage_error::{busy_response, storage_error};
 use crate::models::{EventType, FileEvent};
+use crate::range::RangeRequest;
 use http::StatusCode;
 use http_body_util::BodyExt;
+use serde::Deserialize;
 use silent::SilentError;
-use silent::extractor::{Configs as CfgExtractor, Path};
+use silent::extractor::{Configs as CfgExtractor, Path, Query};
 use silent::prelude::*;
 use silent_nas_core::StorageManagerTrait;
+use silent_storage::{RetentionLock, RetentionMode, StorageError};
+
+/// 上传文件时携带版本说明的请求头
+const VERSION_COMMENT_HEADER: &str = "x-version-comment";
 
 /// 上传文件
+///
+/// 版本创建者记为当前登录用户，版本说明取自 `X-Version-Comment` 头。
+/// 优化队列积压达到上限时返回 `503` 并带 `Retry-After` 头。
 pub async fn upload_file(
     mut req: Request,
     CfgExtractor(state): CfgExtractor<AppState>,
-) -> silent::Result<serde_json::Value> {
+) -> silent::Result<Response> {
     let file_id = scru128::new_string();
+    let author = req
+        .configs()
+        .get::<crate::auth::User>()
+        .map(|user| user.username.clone());
+    let comment = req
+        .headers()
+        .get(VERSION_COMMENT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
 
     let body = req.take_body();
     let bytes = match body {
@@ -38,15 +57,18 @@ pub async fn upload_file(
         }
     };
 
-    let metadata = crate::storage::storage()
-        .save_file(&file_id, &bytes)
+    let metadata = match state
+        .storage
+        .save_file_with_meta(&file_id, &bytes, author.as_deref(), comment.as_deref())
         .await
-        .map_err(|e| {
-            SilentError::business_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("保存文件失败: {}", e),
-            )
-        })?;
+    {
+        Ok(metadata) => metadata,
+        Err(e @ StorageError::Busy(_)) => {
+            let retry_after = state.storage.config().backpressure_retry_after_secs;
+            return Ok(busy_response("保存文件失败", &e, retry_after));
+        }
+        Err(e) => return Err(storage_error("保存文件失败", e)),
+    };
 
     // 索引文件到搜索引擎
     if let Err(e) = state.search_engine.index_file(&metadata).await {
@@ -59,46 +81,374 @@ pub async fn upload_file(
         let _ = n.notify_created(event).await;
     }
 
-    Ok(serde_json::json!({
+    let body = serde_json::json!({
         "file_id": file_id,
         "size": metadata.size,
         "hash": metadata.hash,
-    }))
+    });
+    let mut resp = Response::empty();
+    resp.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/json"),
+    );
+    resp.set_body(full(body.to_string().into_bytes()));
+    Ok(resp)
 }
 
 /// 下载文件
+///
+/// 按 `Accept-Encoding` 协商 gzip / zstd 压缩；已压缩的内容（归档、图片、音视频）
+/// 和小文件按原样返回。响应体始终按块流式返回，无需先把整个文件读入内存：
+/// 不压缩时按版本记录的原始大小设置 `Content-Length`，压缩时边读取边压缩（分块传输）。
+///
+/// ETag 取自内容哈希，与 S3、WebDAV 下载一致，支持 `If-None-Match` 与
+/// `If-Modified-Since`（按秒比较修改时间）条件请求，命中时返回 304；
+/// 支持单个字节范围的 `Range` 请求（`If-Range` 与当前 ETag 不符或多个范围时返回完整内容）
 pub async fn download_file(
-    (Path(id), CfgExtractor(_state)): (Path<String>, CfgExtractor<AppState>),
+    req: Request,
+    (Path(id), CfgExtractor(state)): (Path<String>, CfgExtractor<AppState>),
 ) -> silent::Result<Response> {
-    let data = crate::storage::storage()
-        .read_file(&id)
+    let stat = state
+        .storage
+        .stat(&id)
         .await
-        .map_err(|e| {
-            SilentError::business_error(StatusCode::NOT_FOUND, format!("文件不存在: {}", e))
-        })?;
+        .map_err(|e| storage_error("读取文件失败", e))?;
+    let version_id = stat.version_id.clone();
+
+    let etag = state
+        .storage
+        .entity_tag(&id)
+        .await
+        .map_err(|e| storage_error("读取文件失败", e))?;
 
     let mut resp = Response::empty();
     resp.headers_mut().insert(
         http::header::CONTENT_TYPE,
         http::HeaderValue::from_static("application/octet-stream"),
     );
-    resp.set_body(full(data));
+    resp.headers_mut().insert(
+        http::header::VARY,
+        http::HeaderValue::from_static("accept-encoding"),
+    );
+    resp.headers_mut().insert(
+        http::header::ACCEPT_RANGES,
+        http::HeaderValue::from_static("bytes"),
+    );
+    if let Ok(value) = http::HeaderValue::from_str(&etag) {
+        resp.headers_mut().insert(http::header::ETAG, value);
+    }
+    if let Ok(value) = http::HeaderValue::from_str(&stat.last_modified_http()) {
+        resp.headers_mut()
+            .insert(http::header::LAST_MODIFIED, value);
+    }
+
+    let header = |name: http::HeaderName| req.headers().get(name).and_then(|h| h.to_str().ok());
+    if stat.not_modified(
+        &etag,
+        header(http::header::IF_NONE_MATCH),
+        header(http::header::IF_MODIFIED_SINCE),
+    ) {
+        resp.set_status(StatusCode::NOT_MODIFIED);
+        return Ok(resp);
+    }
+
+    // 范围请求针对原始内容，不做压缩
+    let if_range_matches = req
+        .headers()
+        .get(http::header::IF_RANGE)
+        .and_then(|h| h.to_str().ok())
+        .is_none_or(|v| v.trim() == etag);
+    let range_request = req
+        .headers()
+        .get(http::header::RANGE)
+        .and_then(|h| h.to_str().ok())
+        .filter(|_| if_range_matches)
+        .map(|range_str| crate::range::parse_range(range_str, stat.size))
+        .unwrap_or(RangeRequest::Ignored);
+    match range_request {
+        RangeRequest::Unsatisfiable => {
+            resp.headers_mut().insert(
+                http::header::CONTENT_RANGE,
+                http::HeaderValue::from_str(&format!("bytes */{}", stat.size)).unwrap(),
+            );
+            resp.headers_mut()
+                .insert(http::header::CONTENT_LENGTH, http::HeaderValue::from(0));
+            resp.set_status(StatusCode::RANGE_NOT_SATISFIABLE);
+            return Ok(resp);
+        }
+        RangeRequest::Ranges(ranges) if ranges.len() == 1 => {
+            let (start, end) = ranges[0];
+            let data = state
+                .storage
+                .read_version_range(&version_id, start, end - start + 1)
+                .await
+                .map_err(|e| storage_error("读取文件失败", e))?;
+            resp.headers_mut().insert(
+                http::header::CONTENT_RANGE,
+                http::HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, stat.size))
+                    .unwrap(),
+            );
+            resp.headers_mut().insert(
+                http::header::CONTENT_LENGTH,
+                http::HeaderValue::from(data.len()),
+            );
+            resp.set_body(full(data));
+            resp.set_status(StatusCode::PARTIAL_CONTENT);
+            return Ok(resp);
+        }
+        _ => {}
+    }
+
+    let mut reader = state
+        .storage
+        .open_read(&version_id)
+        .await
+        .map_err(|e| storage_error("读取文件失败", e))?;
+
+    let encoding =
+        negotiate_encoding(req.headers()).filter(|_| stat.size >= MIN_COMPRESS_SIZE as u64);
+    if let Some(encoding) = encoding {
+        // 按开头的内容判断是否已是压缩格式，读出的部分再拼回读取句柄
+        let head = read_head(&mut reader, COMPRESS_SNIFF_SIZE)
+            .await
+            .map_err(|e| {
+                SilentError::business_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("读取文件失败: {}", e),
+                )
+            })?;
+        let compressible = !silent_storage::core::FileType::detect(&head).is_compressed();
+        reader = Box::new(tokio::io::AsyncReadExt::chain(
+            std::io::Cursor::new(head),
+            reader,
+        ));
+        if compressible {
+            match encoding.encoder() {
+                Ok(encoder) => {
+                    resp.headers_mut().insert(
+                        http::header::CONTENT_ENCODING,
+                        http::HeaderValue::from_static(encoding.as_str()),
+                    );
+                    // 压缩后的表示与原始内容字节不同，只能给出弱 ETag
+                    if let Ok(value) = http::HeaderValue::from_str(&format!("W/{}", etag)) {
+                        resp.headers_mut().insert(http::header::ETAG, value);
+                    }
+                    resp.set_body(stream_body(compressed_stream(reader, encoder)));
+                    return Ok(resp);
+                }
+                Err(e) => {
+                    tracing::warn!("创建压缩器失败，按原样返回: {} - {}", id, e);
+                }
+            }
+        }
+    }
+
+    resp.headers_mut().insert(
+        http::header::CONTENT_LENGTH,
+        http::HeaderValue::from(stat.size),
+    );
+    resp.set_body(stream_body(crate::storage::read_stream(reader)));
     Ok(resp)
 }
 
+/// 小于该大小的文件不压缩
+const MIN_COMPRESS_SIZE: usize = 1024;
+
+/// 判断内容是否已是压缩格式时读取的开头字节数
+const COMPRESS_SNIFF_SIZE: usize = 8 * 1024;
+
+/// 流式压缩每次读取的大小
+const COMPRESS_STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 读取开头至多 `limit` 字节（文件更短时读到末尾为止）
+async fn read_head(
+    reader: &mut Box<dyn tokio::io::AsyncRead + Send + Unpin>,
+    limit: usize,
+) -> std::io::Result<Vec<u8>> {
+    use tokio::io::AsyncReadExt;
+    let mut head = vec![0u8; limit];
+    let mut filled = 0;
+    while filled < limit {
+        let n = reader.read(&mut head[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    head.truncate(filled);
+    Ok(head)
+}
+
+/// 边读取边压缩的响应体
+///
+/// 每次读取一段交给压缩器，压缩在阻塞线程池中执行，不占用异步工作线程，
+/// 内存占用与文件大小无关。读取或压缩出错时产生一个错误后结束。
+fn compressed_stream(
+    reader: Box<dyn tokio::io::AsyncRead + Send + Unpin>,
+    encoder: StreamEncoder,
+) -> impl futures_util::Stream<Item = std::io::Result<bytes::Bytes>> + Send {
+    futures_util::stream::unfold(Some((reader, encoder)), |state| async move {
+        use tokio::io::AsyncReadExt;
+        let (mut reader, mut encoder) = state?;
+        loop {
+            let mut buf = vec![0u8; COMPRESS_STREAM_BUFFER_SIZE];
+            let n = match reader.read(&mut buf).await {
+                Ok(n) => n,
+                Err(e) => return Some((Err(e), None)),
+            };
+            buf.truncate(n);
+
+            let step = tokio::task::spawn_blocking(move || -> std::io::Result<_> {
+                if buf.is_empty() {
+                    Ok((None, encoder.finish()?))
+                } else {
+                    let out = encoder.write(&buf)?;
+                    Ok((Some(encoder), out))
+                }
+            })
+            .await
+            .map_err(std::io::Error::other)
+            .and_then(|result| result);
+            match step {
+                Err(e) => return Some((Err(e), None)),
+                Ok((None, out)) => return Some((Ok(bytes::Bytes::from(out)), None)),
+                // 压缩器尚未产生输出，继续读取
+                Ok((Some(next), out)) if out.is_empty() => encoder = next,
+                Ok((Some(next), out)) => {
+                    return Some((Ok(bytes::Bytes::from(out)), Some((reader, next))));
+                }
+            }
+        }
+    })
+}
+
+/// 流式压缩器，每次写入后取出已产生的压缩数据
+enum StreamEncoder {
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl StreamEncoder {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        use std::io::Write;
+        match self {
+            StreamEncoder::Gzip(encoder) => {
+                encoder.write_all(data)?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+            StreamEncoder::Zstd(encoder) => {
+                encoder.write_all(data)?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+        }
+    }
+
+    fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            StreamEncoder::Gzip(encoder) => encoder.finish(),
+            StreamEncoder::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+/// 下载响应的压缩编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentEncoding {
+    Gzip,
+    Zstd,
+}
+
+impl ContentEncoding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Zstd => "zstd",
+        }
+    }
+
+    /// 创建流式压缩器
+    fn encoder(&self) -> std::io::Result<StreamEncoder> {
+        match self {
+            ContentEncoding::Gzip => Ok(StreamEncoder::Gzip(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::fast(),
+            ))),
+            ContentEncoding::Zstd => Ok(StreamEncoder::Zstd(zstd::stream::write::Encoder::new(
+                Vec::new(),
+                1,
+            )?)),
+        }
+    }
+}
+
+/// 根据 `Accept-Encoding` 选择压缩编码
+///
+/// 取 q 值最高的受支持编码，q 值相同时优先 zstd；`q=0` 表示拒绝该编码
+fn negotiate_encoding(headers: &http::HeaderMap) -> Option<ContentEncoding> {
+    let mut best: Option<(ContentEncoding, f32)> = None;
+
+    for value in headers.get_all(http::header::ACCEPT_ENCODING) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        for item in value.split(',') {
+            let mut parts = item.split(';');
+            let encoding = match parts
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "gzip" | "x-gzip" => ContentEncoding::Gzip,
+                "zstd" => ContentEncoding::Zstd,
+                _ => continue,
+            };
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if q <= 0.0 {
+                continue;
+            }
+
+            let better = best.is_none_or(|(_, best_q)| {
+                q > best_q || (q == best_q && encoding == ContentEncoding::Zstd)
+            });
+            if better {
+                best = Some((encoding, q));
+            }
+        }
+    }
+
+    best.map(|(encoding, _)| encoding)
+}
+
 /// 删除文件
+///
+/// DELETE /api/files/<id>
+/// 默认软删除（移入回收站），可由 `storage.default_delete` 配置或 `X-Delete-Mode` 请求头
+/// 改为硬删除，与 S3、WebDAV 的删除语义、权限与审计一致（见 [`crate::storage::DeletePolicy`]）。
 pub async fn delete_file(
+    req: Request,
     (Path(id), CfgExtractor(state)): (Path<String>, CfgExtractor<AppState>),
 ) -> silent::Result<serde_json::Value> {
-    crate::storage::storage()
-        .delete_file(&id)
-        .await
-        .map_err(|e| {
-            SilentError::business_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("删除文件失败: {}", e),
-            )
+    use crate::storage::DeleteMode;
+
+    let actor = crate::auth::delete_actor(&req);
+    let mode = state
+        .delete_policy
+        .resolve(req.headers(), DeleteMode::Soft, &actor)
+        .map_err(|rejection| {
+            SilentError::business_error(rejection.status(), rejection.to_string())
         })?;
+    if mode == DeleteMode::Hard {
+        return hard_delete(&state, id, &actor).await;
+    }
+
+    crate::storage::delete_with_mode(&state.storage, &state.delete_policy, &id, mode, &actor)
+        .await
+        .map_err(|e| storage_error("删除文件失败", e))?;
 
     // 从搜索引擎删除索引
     if let Err(e) = state.search_engine.delete_file(&id).await {
@@ -110,22 +460,247 @@ pub async fn delete_file(
         let _ = n.notify_deleted(event).await;
     }
 
-    Ok(serde_json::json!({"success": true}))
+    Ok(serde_json::json!({"success": true, "mode": mode.as_str()}))
+}
+
+/// 硬删除文件（GDPR 擦除）
+///
+/// DELETE /api/files/<id>/purge
+/// 跳过回收站立即永久删除，搜索索引由存储层的硬删除钩子在同一调用中清理，并记录审计事件
+pub async fn purge_file(
+    req: Request,
+    (Path(id), CfgExtractor(state)): (Path<String>, CfgExtractor<AppState>),
+) -> silent::Result<serde_json::Value> {
+    hard_delete(&state, id, &crate::auth::delete_actor(&req)).await
+}
+
+/// 硬删除文件（审计事件由 [`crate::storage::delete_with_mode`] 记录）
+async fn hard_delete(
+    state: &AppState,
+    id: String,
+    actor: &crate::storage::DeleteActor,
+) -> silent::Result<serde_json::Value> {
+    let erased_chunks = crate::storage::delete_with_mode(
+        &state.storage,
+        &state.delete_policy,
+        &id,
+        crate::storage::DeleteMode::Hard,
+        actor,
+    )
+    .await
+    .map_err(|e| storage_error("硬删除文件失败", e))?;
+
+    let event = FileEvent::new(EventType::Deleted, id, None);
+    if let Some(ref n) = state.notifier {
+        let _ = n.notify_deleted(event).await;
+    }
+
+    Ok(serde_json::json!({
+        "success": true,
+        "mode": "hard",
+        "erased_chunks": erased_chunks,
+    }))
 }
 
 /// 列出文件
 pub async fn list_files(
-    CfgExtractor(_state): CfgExtractor<AppState>,
+    CfgExtractor(state): CfgExtractor<AppState>,
 ) -> silent::Result<Vec<crate::models::FileMetadata>> {
-    use silent_nas_core::StorageManagerTrait;
+    state
+        .storage
+        .list_files_with_metadata()
+        .await
+        .map_err(|e| storage_error("列出文件失败", e))
+}
+
+/// 变更查询参数
+#[derive(Debug, Deserialize)]
+pub struct ChangesQuery {
+    /// 起始时间（RFC 3339，或不带时区的本地时间 `YYYY-MM-DDTHH:MM:SS`）
+    pub since: String,
+}
+
+/// 列出指定时间之后发生变更的文件
+///
+/// GET /api/changes?since=<时间>
+/// 按变更时间升序返回，每个文件只返回最近一次变更，供增量备份与变更订阅使用。
+pub async fn list_changes(
+    (Query(query), CfgExtractor(state)): (Query<ChangesQuery>, CfgExtractor<AppState>),
+) -> silent::Result<serde_json::Value> {
+    let since = parse_since(&query.since).ok_or_else(|| {
+        SilentError::business_error(
+            StatusCode::BAD_REQUEST,
+            format!("无效的时间: {}", query.since),
+        )
+    })?;
+
+    let changes = state
+        .storage
+        .changes_since(since)
+        .await
+        .map_err(|e| storage_error("查询变更失败", e))?;
+
+    Ok(serde_json::json!({
+        "changes": changes,
+        "count": changes.len(),
+    }))
+}
+
+/// 按内容哈希查询是否已有相同内容
+///
+/// GET /api/content/<hash>
+/// `hash` 为整文件 SHA-256（64 位十六进制），命中时返回持有该内容的文件与版本，
+/// 备份客户端据此跳过上传（客户端秒传）；未命中返回 `exists: false`。
+/// 与块级存在性检查不同，这里只匹配完整文件内容。
+/// 只凭哈希即可探测内容，与秒传同样受 `instant_upload` 约束，禁用时总是未命中。
+pub async fn content_exists(
+    (Path(hash), CfgExtractor(state)): (Path<String>, CfgExtractor<AppState>),
+) -> silent::Result<serde_json::Value> {
+    if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(SilentError::business_error(
+            StatusCode::BAD_REQUEST,
+            format!("无效的 SHA-256: {}", hash),
+        ));
+    }
+
+    let found = state
+        .storage
+        .content_exists(&hash)
+        .await
+        .map_err(|e| storage_error("查询内容失败", e))?;
+
+    Ok(match found {
+        Some(content) => serde_json::json!({
+            "exists": true,
+            "file_id": content.file_id,
+            "version_id": content.version_id,
+            "size": content.size,
+        }),
+        None => serde_json::json!({"exists": false}),
+    })
+}
+
+/// 设置保留锁请求
+#[derive(Debug, Deserialize)]
+pub struct RetentionRequest {
+    /// 保留截止时间（RFC 3339，或不带时区的本地时间 `YYYY-MM-DDTHH:MM:SS`）
+    pub until: String,
+    /// 保留模式
+    pub mode: RetentionMode,
+    /// 缩短治理模式保留期（需要管理员权限）
+    #[serde(default)]
+    pub bypass_governance: bool,
+}
+
+/// 解除保留锁查询参数
+#[derive(Debug, Deserialize)]
+pub struct RetentionRemoveQuery {
+    /// 解除有效的治理模式保留锁（需要管理员权限）
+    #[serde(default)]
+    pub bypass_governance: bool,
+}
 
-    // 显式调用 trait 方法
-    StorageManagerTrait::list_files(crate::storage::storage())
+/// 查询文件的保留锁
+///
+/// GET /api/files/<id>/retention
+pub async fn get_retention(
+    (Path(id), CfgExtractor(state)): (Path<String>, CfgExtractor<AppState>),
+) -> silent::Result<serde_json::Value> {
+    let retention = state
+        .storage
+        .get_retention(&id)
         .await
-        .map_err(|e| {
-            SilentError::business_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("列出文件失败: {}", e),
-            )
-        })
+        .map_err(|e| storage_error("查询保留锁失败", e))?;
+    let now = chrono::Local::now().naive_local();
+
+    Ok(serde_json::json!({
+        "file_id": id,
+        "retention": retention,
+        "active": retention.is_some_and(|lock| lock.is_active(now)),
+    }))
+}
+
+/// 设置文件的保留锁（WORM）
+///
+/// PUT /api/files/<id>/retention
+/// 保留期内文件不能删除或移动，旧版本不能删除，仍可上传新版本。
+/// 合规模式的保留期只能延长；缩短治理模式保留期需要管理员并指定 `bypass_governance`。
+pub async fn set_retention(
+    mut req: Request,
+    (Path(id), CfgExtractor(state)): (Path<String>, CfgExtractor<AppState>),
+) -> silent::Result<serde_json::Value> {
+    let bytes = match req.take_body() {
+        ReqBody::Incoming(body) => body.collect().await?.to_bytes().to_vec(),
+        ReqBody::Once(bytes) => bytes.to_vec(),
+        ReqBody::Empty => {
+            return Err(SilentError::business_error(
+                StatusCode::BAD_REQUEST,
+                "请求体为空",
+            ));
+        }
+    };
+    let payload: RetentionRequest = serde_json::from_slice(&bytes)
+        .map_err(|e| SilentError::business_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+    let until = parse_since(&payload.until).ok_or_else(|| {
+        SilentError::business_error(
+            StatusCode::BAD_REQUEST,
+            format!("无效的时间: {}", payload.until),
+        )
+    })?;
+    let bypass = governance_bypass(&req, payload.bypass_governance)?;
+
+    let lock = RetentionLock {
+        until,
+        mode: payload.mode,
+    };
+    state
+        .storage
+        .set_retention(&id, lock, bypass)
+        .await
+        .map_err(|e| storage_error("设置保留锁失败", e))?;
+
+    Ok(serde_json::json!({"success": true, "file_id": id, "retention": lock}))
+}
+
+/// 解除文件的保留锁
+///
+/// DELETE /api/files/<id>/retention?bypass_governance=true
+/// 有效的合规模式保留锁不能解除；有效的治理模式保留锁需要管理员并指定 `bypass_governance`
+pub async fn remove_retention(
+    req: Request,
+    (Path(id), Query(query), CfgExtractor(state)): (
+        Path<String>,
+        Query<RetentionRemoveQuery>,
+        CfgExtractor<AppState>,
+    ),
+) -> silent::Result<serde_json::Value> {
+    let bypass = governance_bypass(&req, query.bypass_governance)?;
+    state
+        .storage
+        .remove_retention(&id, bypass)
+        .await
+        .map_err(|e| storage_error("解除保留锁失败", e))?;
+
+    Ok(serde_json::json!({"success": true, "file_id": id}))
+}
+
+/// 检查治理模式绕过权限：只有管理员（或未启用认证时）可以绕过
+fn governance_bypass(req: &Request, requested: bool) -> silent::Result<bool> {
+    if !requested {
+        return Ok(false);
+    }
+    match req.configs().get::<crate::auth::User>() {
+        Some(user) if user.role != crate::auth::UserRole::Admin => Err(
+            SilentError::business_error(StatusCode::FORBIDDEN, "绕过治理模式保留锁需要管理员权限"),
+        ),
+        _ => Ok(true),
+    }
+}
+
+/// 解析变更查询的起始时间，统一转换为本地时间（与文件索引中的时间一致）
+fn parse_since(value: &str) -> Option<chrono::NaiveDateTime> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&chrono::Local).naive_local());
+    }
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").ok()
 }
@@ -10,6 +10,46 @@ pub async fn health(_req: Request) -> silent::Result<&'static str> {
     Ok("OK")
 }
 
+/// 构建信息 - 服务版本、构建来源、存储格式版本与启用的功能
+///
+/// 供运维与客户端做兼容性检查；存储格式版本变化时，旧版本导出的数据可能需要迁移。
+pub async fn build_info(
+    _req: Request,
+    CfgExtractor(state): CfgExtractor<AppState>,
+) -> silent::Result<serde_json::Value> {
+    let storage_config = state.storage.config();
+    let build_time = env!("SILENT_NAS_BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+        .map(|t| t.to_rfc3339());
+
+    Ok(serde_json::json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_commit": env!("SILENT_NAS_GIT_COMMIT"),
+        "build_time": build_time,
+        "formats": {
+            "storage": silent_storage::STORAGE_FORMAT_VERSION,
+            "snapshot": silent_storage::SNAPSHOT_FORMAT_VERSION,
+            "bundle": silent_storage::BUNDLE_FORMAT_VERSION,
+        },
+        "features": {
+            "auth": state.auth_manager.is_some(),
+            "nats": state.notifier.is_some(),
+            "audit": state.audit_logger.is_some(),
+            "upload_sessions": state.upload_sessions.is_some(),
+            "read_only": storage_config.read_only,
+            "small_file_packing": storage_config.packing.enable,
+            "compression": {
+                "enabled": storage_config.enable_compression,
+                "default_algorithm": storage_config.compression_algorithm,
+                "algorithms": ["lz4", "zstd", "auto"],
+            },
+        }
+    }))
+}
+
 /// 就绪检查 - 检查所有依赖服务
 pub async fn readiness(
     _req: Request,
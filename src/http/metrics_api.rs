@@ -5,8 +5,26 @@ use http::StatusCode;
 use silent::SilentError;
 use silent::prelude::*;
 
+/// 刷新优化积压指标，返回热存储字节数与优化队列长度
+pub(crate) async fn refresh_optimization_backlog(
+    storage: &crate::storage::StorageManager,
+) -> silent_storage::Result<(u64, usize)> {
+    let hot_bytes = storage.hot_storage_bytes().await?;
+    let queue_length = storage.get_optimization_queue_length().await;
+    metrics::update_optimization_backlog(hot_bytes as i64, queue_length as i64);
+    Ok((hot_bytes, queue_length))
+}
+
 /// Prometheus metrics 端点
 pub async fn get_metrics(_req: Request) -> silent::Result<Response> {
+    // 按抓取刷新需要查询存储的指标
+    if let Some(storage) = crate::storage::try_storage() {
+        if let Err(e) = refresh_optimization_backlog(storage).await {
+            tracing::warn!("刷新优化积压指标失败: {}", e);
+        }
+        metrics::update_corrupt_reads(storage.corrupt_read_count());
+    }
+
     match metrics::export_metrics() {
         Ok(metrics_text) => {
             let mut resp = Response::empty();
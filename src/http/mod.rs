@@ -6,18 +6,23 @@ mod admin_handlers;
 mod audit_api;
 mod auth_handlers;
 mod auth_middleware;
+mod body_limit;
+mod cors;
 mod files;
 mod health;
 mod incremental_sync;
 mod metrics_api;
 mod search;
 mod state;
+mod storage_error;
 mod storage_v2_metrics;
 mod sync;
 mod upload_sessions;
 mod versions;
 
 pub use auth_middleware::{AuthHook, OptionalAuthHook};
+pub use body_limit::BodyLimitHook;
+pub use cors::CorsHook;
 pub use state::AppState;
 pub use storage_v2_metrics::StorageV2MetricsState;
 
@@ -50,17 +55,11 @@ pub async fn start_http_server(
     storage: Arc<StorageManager>,
     search_engine: Arc<SearchEngine>,
     config: crate::config::Config,
+    audit_logger: Option<Arc<crate::audit::AuditLogger>>,
 ) -> Result<()> {
     // 创建增量同步处理器
     let inc_sync_handler = Arc::new(IncrementalSyncHandler::new(64 * 1024));
 
-    // 创建审计日志管理器（可选，通过环境变量启用）
-    let audit_logger = if std::env::var("ENABLE_AUDIT").is_ok() {
-        Some(Arc::new(crate::audit::AuditLogger::new(1000)))
-    } else {
-        None
-    };
-
     // 创建认证管理器（使用配置）
     let auth_manager = if config.auth.enable {
         match crate::auth::AuthManager::new(&config.auth.db_path) {
@@ -71,6 +70,9 @@ pub async fn start_http_server(
                     access_token_exp: config.auth.access_token_exp,
                     refresh_token_exp: config.auth.refresh_token_exp,
                 });
+                if let Err(e) = manager.set_password_policy(config.auth.password_policy.clone()) {
+                    tracing::error!("密码策略无效，使用默认策略: {}", e);
+                }
 
                 // 初始化默认管理员
                 if let Err(e) = manager.init_default_admin() {
@@ -106,8 +108,8 @@ pub async fn start_http_server(
     let upload_sessions = {
         use crate::webdav::upload_session::UploadSessionManager;
 
-        // 使用临时目录存储上传会话
-        let temp_dir = std::env::temp_dir().join("silent-nas-uploads");
+        // 上传会话写在存储的临时目录中，启动时由存储清理过期残留
+        let temp_dir = storage.scratch_dir().join("uploads");
         #[allow(clippy::collapsible_if)]
         if !temp_dir.exists() {
             if let Err(e) = std::fs::create_dir_all(&temp_dir) {
@@ -129,18 +131,20 @@ pub async fn start_http_server(
         search_engine: search_engine.clone(),
         inc_sync_handler,
         source_http_addr,
-        audit_logger,
+        audit_logger: audit_logger.clone(),
         auth_manager,
         storage_v2_metrics: storage_v2_metrics.clone(),
         upload_sessions,
+        delete_policy: crate::storage::DeletePolicy::new(config.storage.default_delete)
+            .with_audit_logger(audit_logger),
     };
 
-    // 定期提交索引
+    // 定期提交索引（间隔叠加随机抖动，避免多个节点同时提交）
+    let jitter = config.server.background_jitter;
     tokio::spawn(async move {
-        use tokio::time::{Duration, interval};
-        let mut timer = interval(Duration::from_secs(30));
+        use tokio::time::{Duration, sleep};
         loop {
-            timer.tick().await;
+            sleep(silent_nas_core::jittered(Duration::from_secs(30), jitter)).await;
             if let Err(e) = search_engine.commit().await {
                 tracing::warn!("定期提交索引失败: {}", e);
             }
@@ -150,10 +154,10 @@ pub async fn start_http_server(
     // 定期清理过期上传会话
     if let Some(sessions_mgr) = app_state.upload_sessions.clone() {
         tokio::spawn(async move {
-            use tokio::time::{Duration, interval};
-            let mut timer = interval(Duration::from_secs(3600)); // 每小时清理一次
+            use tokio::time::{Duration, sleep};
             loop {
-                timer.tick().await;
+                // 约每小时清理一次
+                sleep(silent_nas_core::jittered(Duration::from_secs(3600), jitter)).await;
                 let cleaned = sessions_mgr.cleanup_expired_sessions().await;
                 if cleaned > 0 {
                     tracing::info!("清理了 {} 个过期上传会话", cleaned);
@@ -162,20 +166,44 @@ pub async fn start_http_server(
         });
     }
 
+    // 非流式接口的请求体上限（按 /api 下的路由路径查找覆盖值）
+    let body_limit = |route: &str| BodyLimitHook::new(config.http.body_limit(route));
+
     // 构建路由
     let mut api_route = Route::new("api")
         .append(
             Route::new("auth")
-                .append(Route::new("register").post(auth_handlers::register_handler))
-                .append(Route::new("login").post(auth_handlers::login_handler))
-                .append(Route::new("refresh").post(auth_handlers::refresh_handler))
-                .append(Route::new("logout").post(auth_handlers::logout_handler))
+                .append(
+                    Route::new("register")
+                        .hook(body_limit("auth/register"))
+                        .post(auth_handlers::register_handler),
+                )
+                .append(
+                    Route::new("login")
+                        .hook(body_limit("auth/login"))
+                        .post(auth_handlers::login_handler),
+                )
+                .append(
+                    Route::new("refresh")
+                        .hook(body_limit("auth/refresh"))
+                        .post(auth_handlers::refresh_handler),
+                )
+                .append(
+                    Route::new("logout")
+                        .hook(body_limit("auth/logout"))
+                        .post(auth_handlers::logout_handler),
+                )
                 .append(Route::new("me").get(auth_handlers::me_handler))
-                .append(Route::new("password").put(auth_handlers::change_password_handler)),
+                .append(
+                    Route::new("password")
+                        .hook(body_limit("auth/password"))
+                        .put(auth_handlers::change_password_handler),
+                ),
         )
         .append(Route::new("health").get(health::health))
         .append(Route::new("health/readiness").get(health::readiness))
-        .append(Route::new("health/status").get(health::health_status));
+        .append(Route::new("health/status").get(health::health_status))
+        .append(Route::new("version").get(health::build_info));
 
     // 如果启用认证，为需要保护的API添加认证Hook
     if let Some(ref auth_mgr) = app_state.auth_manager {
@@ -193,6 +221,7 @@ pub async fn start_http_server(
             .append(
                 Route::new("admin/users/<id>")
                     .hook(admin_hook.clone())
+                    .hook(body_limit("admin/users/<id>"))
                     .get(admin_handlers::get_user)
                     .put(admin_handlers::update_user)
                     .delete(admin_handlers::delete_user),
@@ -200,7 +229,14 @@ pub async fn start_http_server(
             .append(
                 Route::new("admin/users/<id>/reset-password")
                     .hook(admin_hook.clone())
+                    .hook(body_limit("admin/users/<id>/reset-password"))
                     .post(admin_handlers::reset_password),
+            )
+            // 硬删除（合规擦除）- 需要管理员权限
+            .append(
+                Route::new("files/<id>/purge")
+                    .hook(admin_hook.clone())
+                    .delete(files::purge_file),
             );
 
         // 文件操作 - 需要认证
@@ -217,27 +253,58 @@ pub async fn start_http_server(
                     .get(files::download_file)
                     .delete(files::delete_file),
             )
+            .append(
+                Route::new("changes")
+                    .hook(auth_hook.clone())
+                    .get(files::list_changes),
+            )
+            .append(
+                Route::new("content/<hash>")
+                    .hook(auth_hook.clone())
+                    .get(files::content_exists),
+            )
             // 版本管理 - 需要认证
             .append(
                 Route::new("files/<id>/versions")
                     .hook(auth_hook.clone())
                     .get(versions::list_versions),
             )
+            .append(
+                Route::new("files/<id>/dedup")
+                    .hook(auth_hook.clone())
+                    .get(versions::get_file_dedup_report),
+            )
+            .append(
+                Route::new("files/<id>/retention")
+                    .hook(auth_hook.clone())
+                    .hook(body_limit("files/<id>/retention"))
+                    .get(files::get_retention)
+                    .put(files::set_retention)
+                    .delete(files::remove_retention),
+            )
             // 同步管理 - 需要管理员权限
             .append(
                 Route::new("admin/sync/push")
                     .hook(admin_hook.clone())
+                    .hook(body_limit("admin/sync/push"))
                     .post(admin_handlers::trigger_push_sync),
             )
             .append(
                 Route::new("admin/sync/request")
                     .hook(admin_hook.clone())
+                    .hook(body_limit("admin/sync/request"))
                     .post(admin_handlers::trigger_request_sync),
             )
+            .append(
+                Route::new("admin/sync/status")
+                    .hook(admin_hook.clone())
+                    .get(admin_handlers::get_node_sync_status),
+            )
             // GC管理 - 需要管理员权限
             .append(
                 Route::new("admin/gc/trigger")
                     .hook(admin_hook.clone())
+                    .hook(body_limit("admin/gc/trigger"))
                     .post(admin_handlers::trigger_gc),
             )
             .append(
@@ -245,6 +312,49 @@ pub async fn start_http_server(
                     .hook(admin_hook.clone())
                     .get(admin_handlers::get_gc_status),
             )
+            // 维护模式 - 需要管理员权限
+            .append(
+                Route::new("admin/maintenance/enter")
+                    .hook(admin_hook.clone())
+                    .hook(body_limit("admin/maintenance/enter"))
+                    .post(admin_handlers::enter_maintenance),
+            )
+            .append(
+                Route::new("admin/maintenance/exit")
+                    .hook(admin_hook.clone())
+                    .hook(body_limit("admin/maintenance/exit"))
+                    .post(admin_handlers::exit_maintenance),
+            )
+            .append(
+                Route::new("admin/maintenance/status")
+                    .hook(admin_hook.clone())
+                    .get(admin_handlers::get_maintenance_status),
+            )
+            // 去重索引重建 - 需要管理员权限
+            .append(
+                Route::new("admin/dedup/rebuild")
+                    .hook(admin_hook.clone())
+                    .hook(body_limit("admin/dedup/rebuild"))
+                    .post(admin_handlers::rebuild_dedup_index),
+            )
+            // 优化积压 - 需要管理员权限
+            .append(
+                Route::new("admin/optimization/status")
+                    .hook(admin_hook.clone())
+                    .get(admin_handlers::get_optimization_status),
+            )
+            // 块反向索引 - 需要管理员权限
+            .append(
+                Route::new("admin/chunks/<id>/referrers")
+                    .hook(admin_hook.clone())
+                    .get(admin_handlers::get_chunk_referrers),
+            )
+            // 分块大小建议 - 需要管理员权限
+            .append(
+                Route::new("admin/chunking/recommendation")
+                    .hook(admin_hook.clone())
+                    .get(admin_handlers::get_chunking_recommendation),
+            )
             .append(
                 Route::new("files/<id>/versions/<version_id>")
                     .hook(auth_hook.clone())
@@ -254,6 +364,7 @@ pub async fn start_http_server(
             .append(
                 Route::new("files/<id>/versions/<version_id>/restore")
                     .hook(auth_hook.clone())
+                    .hook(body_limit("files/<id>/versions/<version_id>/restore"))
                     .post(versions::restore_version),
             )
             .append(
@@ -277,6 +388,11 @@ pub async fn start_http_server(
                     .hook(optional_auth_hook.clone())
                     .get(sync::get_conflicts),
             )
+            .append(
+                Route::new("sync/conflicts/<id>/diff")
+                    .hook(optional_auth_hook.clone())
+                    .get(sync::get_conflict_diff),
+            )
             .append(
                 Route::new("sync/signature/<id>")
                     .hook(optional_auth_hook.clone())
@@ -285,6 +401,7 @@ pub async fn start_http_server(
             .append(
                 Route::new("sync/delta/<id>")
                     .hook(optional_auth_hook.clone())
+                    .hook(body_limit("sync/delta/<id>"))
                     .post(incremental_sync::get_file_delta),
             )
             // 搜索 - 需要认证
@@ -346,6 +463,7 @@ pub async fn start_http_server(
             .append(
                 Route::new("upload/sessions/<session_id>/pause")
                     .hook(auth_hook.clone())
+                    .hook(body_limit("upload/sessions/<session_id>/pause"))
                     .post(upload_sessions::pause_session),
             );
 
@@ -363,7 +481,18 @@ pub async fn start_http_server(
                     .get(files::download_file)
                     .delete(files::delete_file),
             )
+            .append(Route::new("changes").get(files::list_changes))
+            .append(Route::new("content/<hash>").get(files::content_exists))
+            .append(Route::new("files/<id>/purge").delete(files::purge_file))
             .append(Route::new("files/<id>/versions").get(versions::list_versions))
+            .append(Route::new("files/<id>/dedup").get(versions::get_file_dedup_report))
+            .append(
+                Route::new("files/<id>/retention")
+                    .hook(body_limit("files/<id>/retention"))
+                    .get(files::get_retention)
+                    .put(files::set_retention)
+                    .delete(files::remove_retention),
+            )
             .append(
                 Route::new("files/<id>/versions/<version_id>")
                     .get(versions::get_version)
@@ -371,18 +500,66 @@ pub async fn start_http_server(
             )
             .append(
                 Route::new("files/<id>/versions/<version_id>/restore")
+                    .hook(body_limit("files/<id>/versions/<version_id>/restore"))
                     .post(versions::restore_version),
             )
             .append(Route::new("versions/stats").get(versions::get_version_stats))
-            .append(Route::new("admin/sync/push").post(admin_handlers::trigger_push_sync))
-            .append(Route::new("admin/sync/request").post(admin_handlers::trigger_request_sync))
-            .append(Route::new("admin/gc/trigger").post(admin_handlers::trigger_gc))
+            .append(
+                Route::new("admin/sync/push")
+                    .hook(body_limit("admin/sync/push"))
+                    .post(admin_handlers::trigger_push_sync),
+            )
+            .append(
+                Route::new("admin/sync/request")
+                    .hook(body_limit("admin/sync/request"))
+                    .post(admin_handlers::trigger_request_sync),
+            )
+            .append(Route::new("admin/sync/status").get(admin_handlers::get_node_sync_status))
+            .append(
+                Route::new("admin/gc/trigger")
+                    .hook(body_limit("admin/gc/trigger"))
+                    .post(admin_handlers::trigger_gc),
+            )
             .append(Route::new("admin/gc/status").get(admin_handlers::get_gc_status))
+            .append(
+                Route::new("admin/maintenance/enter")
+                    .hook(body_limit("admin/maintenance/enter"))
+                    .post(admin_handlers::enter_maintenance),
+            )
+            .append(
+                Route::new("admin/maintenance/exit")
+                    .hook(body_limit("admin/maintenance/exit"))
+                    .post(admin_handlers::exit_maintenance),
+            )
+            .append(
+                Route::new("admin/maintenance/status").get(admin_handlers::get_maintenance_status),
+            )
+            .append(
+                Route::new("admin/dedup/rebuild")
+                    .hook(body_limit("admin/dedup/rebuild"))
+                    .post(admin_handlers::rebuild_dedup_index),
+            )
+            .append(
+                Route::new("admin/optimization/status")
+                    .get(admin_handlers::get_optimization_status),
+            )
+            .append(
+                Route::new("admin/chunks/<id>/referrers").get(admin_handlers::get_chunk_referrers),
+            )
+            .append(
+                Route::new("admin/chunking/recommendation")
+                    .get(admin_handlers::get_chunking_recommendation),
+            )
             .append(Route::new("sync/states").get(sync::list_sync_states))
             .append(Route::new("sync/states/<id>").get(sync::get_sync_state))
             .append(Route::new("sync/conflicts").get(sync::get_conflicts))
+            .append(Route::new("sync/conflicts/<id>/diff").get(sync::get_conflict_diff))
             .append(Route::new("sync/signature/<id>").get(incremental_sync::get_file_signature))
-            .append(Route::new("sync/delta/<id>").post(incremental_sync::get_file_delta))
+            .append(
+                Route::new("sync/delta/<id>")
+                    .hook(body_limit("sync/delta/<id>"))
+                    .post(incremental_sync::get_file_delta),
+            )
             .append(Route::new("search").get(search::search_files))
             .append(Route::new("search/stats").get(search::get_search_stats))
             .append(Route::new("metrics").get(metrics_api::get_metrics))
@@ -407,13 +584,19 @@ pub async fn start_http_server(
             )
             .append(
                 Route::new("upload/sessions/<session_id>/pause")
+                    .hook(body_limit("upload/sessions/<session_id>/pause"))
                     .post(upload_sessions::pause_session),
             );
 
         info!("⚠️  认证功能未启用 - API端点无保护");
     }
 
-    let route = Route::new_root()
+    let mut route = Route::new_root();
+    if config.cors.is_enabled() {
+        // 先于认证处理：预检请求不带凭据
+        route = route.hook(CorsHook::new(config.cors.clone()));
+    }
+    let route = route
         .hook(state_injector(app_state))
         .append(api_route)
         // 暴露根路径 /metrics（便于 Prometheus 默认抓取路径），与 /api/metrics 并存
@@ -491,6 +674,7 @@ mod tests {
             auth_manager: None,
             storage_v2_metrics,
             upload_sessions: None,
+            delete_policy: Default::default(),
         };
 
         (app_state, temp_dir)
@@ -559,6 +743,38 @@ mod tests {
         assert_eq!(result.unwrap(), "OK");
     }
 
+    #[tokio::test]
+    async fn test_build_info_reports_format_version_and_features() {
+        let (app_state, _temp_dir) = create_test_app_state().await;
+        let info = health::build_info(Request::empty(), CfgExtractor(app_state))
+            .await
+            .unwrap();
+
+        assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+        assert!(info["git_commit"].as_str().is_some_and(|c| !c.is_empty()));
+        assert!(info["build_time"].is_string());
+        assert_eq!(
+            info["formats"]["storage"],
+            silent_storage::STORAGE_FORMAT_VERSION
+        );
+        assert_eq!(
+            info["formats"]["snapshot"],
+            silent_storage::SNAPSHOT_FORMAT_VERSION
+        );
+
+        let features = &info["features"];
+        assert_eq!(features["auth"], false);
+        assert_eq!(features["nats"], false);
+        assert_eq!(features["small_file_packing"], false);
+        assert!(
+            features["compression"]["algorithms"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|a| a == "zstd")
+        );
+    }
+
     #[tokio::test]
     async fn test_state_injector_creation() {
         let (app_state, _temp_dir) = create_test_app_state().await;
@@ -609,6 +825,596 @@ mod tests {
         // 只验证 list_files 能正常工作
     }
 
+    #[tokio::test]
+    async fn test_purge_file_removes_all_traces() {
+        use silent::extractor::Path;
+        use silent_nas_core::StorageManagerTrait;
+
+        let (mut app_state, temp_dir) = create_test_app_state().await;
+        let audit_logger = Arc::new(crate::audit::AuditLogger::new(100));
+        app_state.audit_logger = Some(audit_logger.clone());
+        app_state.delete_policy =
+            crate::storage::DeletePolicy::default().with_audit_logger(Some(audit_logger.clone()));
+
+        // 独立的存储实例，钩子不会注册到其他测试共享的全局存储上
+        let storage = Arc::new(StorageManager::new(
+            temp_dir.path().join("purge"),
+            64 * 1024,
+            Default::default(),
+        ));
+        storage.init().await.unwrap();
+        storage.add_erase_hook(app_state.search_engine.clone());
+        app_state.storage = storage.clone();
+        let file_id = format!("purge{}", scru128::new_string());
+        let metadata = storage
+            .save_file(&file_id, b"right to be forgotten")
+            .await
+            .unwrap();
+        app_state.search_engine.index_file(&metadata).await.unwrap();
+        app_state.search_engine.commit().await.unwrap();
+        assert!(
+            !app_state
+                .search_engine
+                .search(&file_id, 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let result = files::purge_file(
+            Request::empty(),
+            (Path(file_id.clone()), CfgExtractor(app_state.clone())),
+        )
+        .await;
+        assert!(result.is_ok());
+
+        // 列表、搜索结果、版本列表中都不再有该文件
+        assert!(!storage.list_files().await.unwrap().contains(&file_id));
+        assert!(
+            app_state
+                .search_engine
+                .search(&file_id, 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            storage
+                .list_file_versions(&file_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // 记录了审计事件
+        let events = audit_logger
+            .filter_by_action(crate::audit::AuditAction::FilePurge, 10)
+            .await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].resource_id.as_deref(), Some(file_id.as_str()));
+        assert!(events[0].success);
+    }
+
+    #[tokio::test]
+    async fn test_download_missing_file_returns_404() {
+        use silent::extractor::Path;
+
+        let (app_state, _temp_dir) = create_test_app_state().await;
+        let file_id = format!("missing{}", scru128::new_string());
+
+        let err = files::download_file(Request::empty(), (Path(file_id), CfgExtractor(app_state)))
+            .await
+            .err()
+            .expect("读取不存在的文件应失败");
+        assert_eq!(err.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_download_negotiates_gzip() {
+        use http_body_util::BodyExt;
+        use silent::extractor::Path;
+        use std::io::Read;
+
+        let (app_state, _temp_dir) = create_test_app_state().await;
+        let file_id = format!("log{}", scru128::new_string());
+        let data = "2025-01-01 INFO request handled in 3ms\n"
+            .repeat(512)
+            .into_bytes();
+        app_state.storage.save_file(&file_id, &data).await.unwrap();
+
+        let mut req = Request::empty();
+        req.headers_mut().insert(
+            http::header::ACCEPT_ENCODING,
+            http::HeaderValue::from_static("gzip, deflate"),
+        );
+        let mut resp = files::download_file(req, (Path(file_id.clone()), CfgExtractor(app_state)))
+            .await
+            .unwrap();
+        assert_eq!(
+            resp.headers().get(http::header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        assert!(body.len() < data.len());
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[tokio::test]
+    async fn test_download_streams_zstd_for_large_file() {
+        use http_body_util::BodyExt;
+        use silent::extractor::Path;
+
+        let (app_state, _temp_dir) = create_test_app_state().await;
+        let file_id = format!("log{}", scru128::new_string());
+        // 跨越多个流式压缩缓冲区
+        let data: Vec<u8> = (0..20_000)
+            .flat_map(|i| format!("line {} handled\n", i).into_bytes())
+            .collect();
+        app_state.storage.save_file(&file_id, &data).await.unwrap();
+
+        let mut req = Request::empty();
+        req.headers_mut().insert(
+            http::header::ACCEPT_ENCODING,
+            http::HeaderValue::from_static("zstd"),
+        );
+        let mut resp = files::download_file(req, (Path(file_id), CfgExtractor(app_state)))
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()[http::header::CONTENT_ENCODING], "zstd");
+        // 边读取边压缩，长度事先未知
+        assert!(resp.headers().get(http::header::CONTENT_LENGTH).is_none());
+
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        assert!(body.len() < data.len());
+        assert_eq!(zstd::stream::decode_all(&body[..]).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_download_without_accept_encoding_is_identity() {
+        use silent::extractor::Path;
+
+        let (app_state, _temp_dir) = create_test_app_state().await;
+        let file_id = format!("log{}", scru128::new_string());
+        let data = "plain text line\n".repeat(512).into_bytes();
+        app_state.storage.save_file(&file_id, &data).await.unwrap();
+
+        let resp = files::download_file(Request::empty(), (Path(file_id), CfgExtractor(app_state)))
+            .await
+            .unwrap();
+        assert!(resp.headers().get(http::header::CONTENT_ENCODING).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_download_etag_matches_s3() {
+        use http_body_util::BodyExt;
+        use silent::extractor::Path;
+
+        let (app_state, _temp_dir) = create_test_app_state().await;
+        let file_id = format!("etag-bucket/obj{}", scru128::new_string());
+        let data = b"same bytes, same entity tag".repeat(64);
+        app_state.storage.save_file(&file_id, &data).await.unwrap();
+
+        let resp = files::download_file(
+            Request::empty(),
+            (Path(file_id.clone()), CfgExtractor(app_state.clone())),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.headers()[http::header::ACCEPT_RANGES], "bytes");
+        let http_etag = resp.headers()[http::header::ETAG].clone();
+
+        let s3 = crate::s3::S3Service::new(
+            app_state.storage.clone(),
+            None,
+            None,
+            String::new(),
+            Arc::new(crate::s3::VersioningManager::new()),
+        );
+        let s3_resp = s3.head_object_response(&file_id).await.unwrap();
+        assert_eq!(s3_resp.headers()["ETag"], http_etag);
+
+        // If-None-Match 命中返回 304
+        let mut req = Request::empty();
+        req.headers_mut()
+            .insert(http::header::IF_NONE_MATCH, http_etag.clone());
+        let resp = files::download_file(
+            req,
+            (Path(file_id.clone()), CfgExtractor(app_state.clone())),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), http::StatusCode::NOT_MODIFIED);
+
+        // 单个字节范围返回 206
+        let mut req = Request::empty();
+        req.headers_mut().insert(
+            http::header::RANGE,
+            http::HeaderValue::from_static("bytes=5-9"),
+        );
+        let mut resp = files::download_file(req, (Path(file_id), CfgExtractor(app_state)))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers()[http::header::CONTENT_RANGE],
+            format!("bytes 5-9/{}", data.len()).as_str()
+        );
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], &data[5..10]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_policy_consistent_across_http_and_s3() {
+        use crate::s3::versioning::VersioningStatus;
+        use crate::storage::{DELETE_MODE_HEADER, DeleteMode, DeletePolicy};
+        use silent::extractor::Path;
+
+        let (mut app_state, _temp_dir) = create_test_app_state().await;
+        let storage = app_state.storage.clone();
+        let bucket = "delete-policy";
+
+        for mode in [DeleteMode::Soft, DeleteMode::Hard] {
+            app_state.delete_policy = DeletePolicy::new(Some(mode));
+            let s3 = crate::s3::S3Service::new(
+                storage.clone(),
+                None,
+                None,
+                String::new(),
+                Arc::new(crate::s3::VersioningManager::new()),
+            )
+            .with_delete_policy(app_state.delete_policy.clone());
+
+            let http_id = format!("{}/http{}", bucket, scru128::new_string());
+            let key = format!("s3{}", scru128::new_string());
+            let s3_id = format!("{}/{}", bucket, key);
+            storage.save_file(&http_id, b"same data").await.unwrap();
+            storage.save_file(&s3_id, b"same data").await.unwrap();
+
+            let result = files::delete_file(
+                Request::empty(),
+                (Path(http_id.clone()), CfgExtractor(app_state.clone())),
+            )
+            .await
+            .unwrap();
+            assert_eq!(result["mode"], mode.as_str());
+            let resp = s3
+                .delete_object_at(bucket, &key, &Request::empty())
+                .await
+                .unwrap();
+            assert_eq!(resp.headers()[DELETE_MODE_HEADER], mode.as_str());
+
+            // 两种协议删除后的结果一致：软删除留在回收站，硬删除不留记录
+            for file_id in [&http_id, &s3_id] {
+                let entry = storage.get_file_info(file_id).await;
+                match mode {
+                    DeleteMode::Soft => assert!(entry.unwrap().is_deleted),
+                    DeleteMode::Hard => assert!(entry.is_err()),
+                }
+            }
+        }
+
+        // 未配置时 S3 硬删除，开启版本控制的桶只写入删除标记；请求头覆盖两者
+        let versioning = Arc::new(crate::s3::VersioningManager::new());
+        let s3 = crate::s3::S3Service::new(
+            storage.clone(),
+            None,
+            None,
+            String::new(),
+            versioning.clone(),
+        );
+        let key = format!("s3{}", scru128::new_string());
+        let s3_id = format!("{}/{}", bucket, key);
+        storage.save_file(&s3_id, b"data").await.unwrap();
+        versioning
+            .set_versioning(bucket, VersioningStatus::Enabled)
+            .await;
+        let resp = s3
+            .delete_object_at(bucket, &key, &Request::empty())
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["x-amz-delete-marker"], "true");
+        assert!(storage.get_file_info(&s3_id).await.unwrap().is_deleted);
+
+        let mut req = Request::empty();
+        req.headers_mut()
+            .insert(DELETE_MODE_HEADER, http::HeaderValue::from_static("hard"));
+        s3.delete_object_at(bucket, &key, &req).await.unwrap();
+        assert!(storage.get_file_info(&s3_id).await.is_err());
+
+        let mut req = Request::empty();
+        req.headers_mut()
+            .insert(DELETE_MODE_HEADER, http::HeaderValue::from_static("bogus"));
+        let resp = s3.delete_object_at(bucket, &key, &req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_delete_authorization_and_audit_shared_across_protocols() {
+        use crate::audit::AuditAction;
+        use crate::storage::{DELETE_MODE_HEADER, DeletePolicy};
+        use silent::extractor::Path;
+
+        let (mut app_state, _temp_dir) = create_test_app_state().await;
+        let audit_logger = Arc::new(crate::audit::AuditLogger::new(100));
+        app_state.delete_policy =
+            DeletePolicy::default().with_audit_logger(Some(audit_logger.clone()));
+        let storage = app_state.storage.clone();
+
+        // 普通用户不能通过请求头要求硬删除
+        let file_id = format!("audited{}", scru128::new_string());
+        storage.save_file(&file_id, b"keep me").await.unwrap();
+        let mut req = Request::empty();
+        req.headers_mut()
+            .insert(DELETE_MODE_HEADER, http::HeaderValue::from_static("hard"));
+        let now = chrono::Local::now();
+        req.configs_mut().insert(crate::auth::User {
+            id: "u1".to_string(),
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            password_hash: String::new(),
+            role: crate::auth::UserRole::User,
+            status: crate::auth::UserStatus::Active,
+            created_at: now,
+            updated_at: now,
+        });
+        let err = files::delete_file(
+            req,
+            (Path(file_id.clone()), CfgExtractor(app_state.clone())),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(err.status(), http::StatusCode::FORBIDDEN);
+        assert!(!storage.get_file_info(&file_id).await.unwrap().is_deleted);
+
+        // HTTP 软删除与 S3 硬删除都记录到同一审计日志
+        files::delete_file(
+            Request::empty(),
+            (Path(file_id.clone()), CfgExtractor(app_state.clone())),
+        )
+        .await
+        .unwrap();
+        let s3 = crate::s3::S3Service::new(
+            storage.clone(),
+            None,
+            None,
+            String::new(),
+            Arc::new(crate::s3::VersioningManager::new()),
+        )
+        .with_delete_policy(app_state.delete_policy.clone());
+        let key = format!("s3{}", scru128::new_string());
+        storage
+            .save_file(&format!("audited/{}", key), b"purge me")
+            .await
+            .unwrap();
+        s3.delete_object_at("audited", &key, &Request::empty())
+            .await
+            .unwrap();
+
+        let deletes = audit_logger
+            .filter_by_action(AuditAction::FileDelete, 10)
+            .await;
+        assert_eq!(deletes.len(), 1);
+        assert_eq!(deletes[0].resource_id.as_deref(), Some(file_id.as_str()));
+        let purges = audit_logger
+            .filter_by_action(AuditAction::FilePurge, 10)
+            .await;
+        assert_eq!(purges.len(), 1);
+        assert_eq!(purges[0].resource_id, Some(format!("audited/{}", key)));
+    }
+
+    #[tokio::test]
+    async fn test_download_conditional_not_modified() {
+        use silent::extractor::Path;
+
+        async fn download(
+            app_state: &AppState,
+            file_id: &str,
+            name: http::HeaderName,
+            value: &str,
+        ) -> Response {
+            let mut req = Request::empty();
+            req.headers_mut()
+                .insert(name, http::HeaderValue::from_str(value).unwrap());
+            files::download_file(
+                req,
+                (Path(file_id.to_string()), CfgExtractor(app_state.clone())),
+            )
+            .await
+            .unwrap()
+        }
+
+        let (app_state, _temp_dir) = create_test_app_state().await;
+        let file_id = format!("cond{}", scru128::new_string());
+        app_state
+            .storage
+            .save_file(&file_id, b"first content")
+            .await
+            .unwrap();
+
+        let resp = files::download_file(
+            Request::empty(),
+            (Path(file_id.clone()), CfgExtractor(app_state.clone())),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let etag = resp.headers()[http::header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let last_modified = resp.headers()[http::header::LAST_MODIFIED]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        // ETag 命中
+        let resp = download(&app_state, &file_id, http::header::IF_NONE_MATCH, &etag).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers()[http::header::ETAG], etag.as_str());
+        // 修改时间不晚于 If-Modified-Since（按秒比较）
+        let resp = download(
+            &app_state,
+            &file_id,
+            http::header::IF_MODIFIED_SINCE,
+            &last_modified,
+        )
+        .await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_MODIFIED);
+
+        // 文件变化后返回完整内容
+        app_state
+            .storage
+            .save_file(&file_id, b"second content")
+            .await
+            .unwrap();
+        let later = chrono::Local::now().naive_local() + chrono::Duration::minutes(1);
+        app_state.storage.touch(&file_id, later).await.unwrap();
+        let resp = download(&app_state, &file_id, http::header::IF_NONE_MATCH, &etag).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let resp = download(
+            &app_state,
+            &file_id,
+            http::header::IF_MODIFIED_SINCE,
+            &last_modified,
+        )
+        .await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_download_sets_content_length_for_cold_file() {
+        use http_body_util::BodyExt;
+        use silent::extractor::Path;
+
+        let (app_state, _temp_dir) = create_test_app_state().await;
+        let file_id = format!("cold{}", scru128::new_string());
+        let data = b"cold storage payload ".repeat(32 * 1024);
+        let saved = app_state.storage.save_file(&file_id, &data).await.unwrap();
+
+        // 转入整文件压缩的冷存储，存储大小与原始大小不同
+        app_state
+            .storage
+            .save_version_with_mode(
+                &file_id,
+                &data,
+                Some(&saved.hash),
+                silent_storage::StorageMode::Compressed,
+            )
+            .await
+            .unwrap();
+        let info = app_state.storage.get_file_info(&file_id).await.unwrap();
+        assert_eq!(info.storage_mode, silent_storage::StorageMode::Compressed);
+        assert!(info.stored_size < data.len() as u64);
+
+        let mut resp =
+            files::download_file(Request::empty(), (Path(file_id), CfgExtractor(app_state)))
+                .await
+                .unwrap();
+        assert_eq!(
+            resp.headers().get(http::header::CONTENT_LENGTH).unwrap(),
+            data.len().to_string().as_str()
+        );
+
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.len(), data.len());
+        assert_eq!(&body[..], &data[..]);
+    }
+
+    #[tokio::test]
+    async fn test_upload_over_quota_returns_507() {
+        let (mut app_state, temp_dir) = create_test_app_state().await;
+        let config = crate::storage::IncrementalConfig {
+            quota_bytes: Some(16),
+            ..Default::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().join("quota"), 64 * 1024, config);
+        storage.init().await.unwrap();
+        app_state.storage = Arc::new(storage);
+
+        let (parts, _) = http::Request::builder()
+            .method("POST")
+            .uri("/api/files")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let req = Request::from_parts(
+            parts,
+            ReqBody::Once(bytes::Bytes::from_static(b"more than sixteen bytes")),
+        );
+
+        let err = files::upload_file(req, CfgExtractor(app_state))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), http::StatusCode::INSUFFICIENT_STORAGE);
+    }
+
+    #[tokio::test]
+    async fn test_upload_over_max_size_returns_413() {
+        let (mut app_state, temp_dir) = create_test_app_state().await;
+        let config = crate::storage::IncrementalConfig {
+            max_upload_size: Some(16),
+            ..Default::default()
+        };
+        let storage = StorageManager::new(temp_dir.path().join("limit"), 64 * 1024, config);
+        storage.init().await.unwrap();
+        app_state.storage = Arc::new(storage);
+
+        let (parts, _) = http::Request::builder()
+            .method("POST")
+            .uri("/api/files")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let req = Request::from_parts(
+            parts,
+            ReqBody::Once(bytes::Bytes::from_static(b"more than sixteen bytes")),
+        );
+
+        let err = files::upload_file(req, CfgExtractor(app_state))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_list_changes() {
+        use silent::extractor::Query;
+
+        let (app_state, _temp_dir) = create_test_app_state().await;
+        let since = chrono::Local::now().to_rfc3339();
+        let file_id = format!("changes-{}", scru128::new_string());
+        app_state
+            .storage
+            .save_file(&file_id, b"changed")
+            .await
+            .unwrap();
+
+        let query = files::ChangesQuery { since };
+        let result = files::list_changes((Query(query), CfgExtractor(app_state.clone())))
+            .await
+            .unwrap();
+        let changes = result["changes"].as_array().unwrap();
+        assert!(
+            changes
+                .iter()
+                .any(|c| c["file_id"] == file_id.as_str() && c["op"] == "created")
+        );
+
+        let query = files::ChangesQuery {
+            since: "not-a-time".to_string(),
+        };
+        let err = files::list_changes((Query(query), CfgExtractor(app_state)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), http::StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn test_get_version_stats() {
         let (app_state, _temp_dir) = create_test_app_state().await;
@@ -1,6 +1,7 @@
 //! 搜索 API 端点
 
 use super::state::{AppState, SearchQuery, SearchSuggestQuery};
+use crate::error::NasError;
 use http::StatusCode;
 use serde_json::{Value, json};
 use silent::SilentError;
@@ -17,16 +18,31 @@ pub async fn search_files(
         ));
     }
 
+    let filters = parse_field_filters(query.filter.as_deref().unwrap_or(""))?;
+    let known_fields = state.search_engine.extra_field_names();
+    if let Some((name, _)) = filters
+        .iter()
+        .find(|(name, _)| !known_fields.contains(&name.as_str()))
+    {
+        return Err(SilentError::business_error(
+            StatusCode::BAD_REQUEST,
+            format!("未知的过滤字段: {}", name),
+        ));
+    }
+
     // 执行搜索
     let results = state
         .search_engine
-        .search(&query.q, query.limit, query.offset)
+        .search_fuzzy(&query.q, &filters, query.fuzzy, query.limit, query.offset)
         .await
-        .map_err(|e| {
-            SilentError::business_error(
+        .map_err(|e| match e {
+            NasError::InvalidQuery(_) => {
+                SilentError::business_error(StatusCode::BAD_REQUEST, e.to_string())
+            }
+            e => SilentError::business_error(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("搜索失败: {}", e),
-            )
+            ),
         })?;
 
     // 应用过滤
@@ -50,7 +66,8 @@ pub async fn search_files(
             "min_size": query.min_size,
             "max_size": query.max_size,
             "modified_after": query.modified_after,
-            "modified_before": query.modified_before
+            "modified_before": query.modified_before,
+            "filter": query.filter
         }
     });
 
@@ -120,6 +137,23 @@ pub async fn rebuild_search_index(
     }))
 }
 
+/// 解析附加字段过滤条件（`字段:值`，逗号分隔）
+fn parse_field_filters(raw: &str) -> silent::Result<Vec<(String, String)>> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| match item.split_once(':') {
+            Some((name, value)) if !name.trim().is_empty() && !value.trim().is_empty() => {
+                Ok((name.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(SilentError::business_error(
+                StatusCode::BAD_REQUEST,
+                format!("无效的过滤条件: {}（格式为 字段:值）", item),
+            )),
+        })
+        .collect()
+}
+
 /// 应用过滤条件
 fn apply_filters(
     results: Vec<crate::search::SearchResult>,
@@ -5,7 +5,7 @@ use crate::auth::AuthManager;
 use crate::http::StorageV2MetricsState;
 use crate::notify::EventNotifier;
 use crate::search::SearchEngine;
-use crate::storage::StorageManager;
+use crate::storage::{DeletePolicy, StorageManager};
 #[cfg(not(test))]
 use crate::sync::crdt::SyncManager;
 #[cfg(not(test))]
@@ -33,6 +33,8 @@ pub struct AppState {
     pub auth_manager: Option<Arc<AuthManager>>,
     pub storage_v2_metrics: Arc<StorageV2MetricsState>,
     pub upload_sessions: Option<Arc<UploadSessionManager>>,
+    /// 删除请求的默认删除语义（见 `storage.default_delete`）
+    pub delete_policy: DeletePolicy,
 }
 
 /// 搜索查询参数
@@ -59,6 +61,12 @@ pub struct SearchQuery {
     /// 修改时间范围 - 结束时间戳
     #[serde(default)]
     pub modified_before: Option<i64>,
+    /// 附加字段过滤，格式 `owner:alice,tags:work`，所有条件同时满足
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// 模糊匹配的编辑距离（0 表示精确匹配），上限由 `search.max_fuzzy_distance` 配置
+    #[serde(default)]
+    pub fuzzy: u8,
     /// 排序字段（name, size, modified_at, score）
     #[serde(default = "default_sort_by")]
     pub sort_by: String,
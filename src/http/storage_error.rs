@@ -0,0 +1,35 @@
+//! 存储错误到 HTTP 响应的转换（状态码映射见 [`crate::error::status_for`]）
+
+use crate::error::status_for;
+use http::StatusCode;
+use silent::SilentError;
+use silent::prelude::*;
+use silent_storage::StorageError;
+
+/// 将存储错误转换为 HTTP 业务错误，消息中带上稳定错误码
+pub(crate) fn storage_error(context: &str, err: StorageError) -> SilentError {
+    SilentError::business_error(
+        status_for(&err),
+        format!("{} [{}]: {}", context, err.code(), err),
+    )
+}
+
+/// 存储繁忙（上传背压）时的 503 响应，带 `Retry-After` 头提示客户端稍后重试
+pub(crate) fn busy_response(context: &str, err: &StorageError, retry_after_secs: u64) -> Response {
+    let body = serde_json::json!({
+        "error": format!("{} [{}]: {}", context, err.code(), err),
+        "retry_after": retry_after_secs,
+    });
+    let mut resp = Response::empty();
+    resp.set_status(StatusCode::SERVICE_UNAVAILABLE);
+    resp.headers_mut().insert(
+        http::header::RETRY_AFTER,
+        http::HeaderValue::from(retry_after_secs),
+    );
+    resp.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/json"),
+    );
+    resp.set_body(full(body.to_string().into_bytes()));
+    resp
+}
@@ -63,7 +63,12 @@ pub async fn get_storage_v2_metrics(req: Request) -> silent::Result<Response> {
     let app_state = req.extensions().get::<AppState>().cloned();
 
     if let Some(state) = app_state {
-        let metrics_text = state.storage_v2_metrics.get_prometheus_format().await;
+        let mut metrics_text = state.storage_v2_metrics.get_prometheus_format().await;
+        // 重组文件缓存的容量与淘汰指标
+        if let Some(storage) = crate::storage::try_storage() {
+            let cache_stats = storage.get_cache_manager().get_stats().await;
+            metrics_text.push_str(&cache_stats.to_prometheus());
+        }
 
         let mut resp = Response::empty();
         resp.headers_mut().insert(
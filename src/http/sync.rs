@@ -1,6 +1,8 @@
 //! 同步相关 API 端点
 
 use super::state::AppState;
+use super::storage_error::storage_error;
+use crate::sync::incremental::IncrementalSyncManager;
 use http::StatusCode;
 use silent::SilentError;
 use silent::extractor::{Configs as CfgExtractor, Path};
@@ -33,3 +35,46 @@ pub async fn get_conflicts(
     let conflicts = state.sync_manager.check_conflicts().await;
     Ok(serde_json::to_value(conflicts).unwrap())
 }
+
+/// 获取冲突双方版本的差异
+///
+/// 差异以本地一方为源、远程一方为目标计算；任一方的内容尚未同步到本节点时返回 409。
+pub async fn get_conflict_diff(
+    (Path(id), CfgExtractor(state)): (Path<String>, CfgExtractor<AppState>),
+) -> silent::Result<serde_json::Value> {
+    let conflict = state
+        .sync_manager
+        .get_conflict(&id)
+        .await
+        .ok_or_else(|| SilentError::business_error(StatusCode::NOT_FOUND, "冲突记录不存在"))?;
+    let (Some(from), Some(to)) = (
+        conflict.local.version_id.as_deref(),
+        conflict.remote.version_id.as_deref(),
+    ) else {
+        return Err(SilentError::business_error(
+            StatusCode::CONFLICT,
+            "冲突双方的版本尚未全部同步到本节点",
+        ));
+    };
+
+    let from_data = state
+        .storage
+        .read_version_data(from)
+        .await
+        .map_err(|e| storage_error("读取版本失败", e))?;
+    let to_data = state
+        .storage
+        .read_version_data(to)
+        .await
+        .map_err(|e| storage_error("读取版本失败", e))?;
+    let diff = IncrementalSyncManager::default()
+        .version_diff(from, &from_data, to, &to_data)
+        .map_err(|e| {
+            SilentError::business_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("计算差异失败: {}", e),
+            )
+        })?;
+
+    Ok(serde_json::json!({ "conflict": conflict, "diff": diff }))
+}
@@ -1,9 +1,8 @@
 //! 版本管理 API 端点
 
 use super::state::AppState;
+use super::storage_error::storage_error;
 use crate::models::{EventType, FileEvent};
-use http::StatusCode;
-use silent::SilentError;
 use silent::extractor::{Configs as CfgExtractor, Path};
 use silent::prelude::*;
 use silent_nas_core::StorageManagerTrait;
@@ -14,12 +13,10 @@ pub async fn list_versions(
 ) -> silent::Result<serde_json::Value> {
     let storage = &state.storage;
 
-    let versions = storage.list_file_versions(&id).await.map_err(|e| {
-        SilentError::business_error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("获取版本列表失败: {}", e),
-        )
-    })?;
+    let versions = storage
+        .list_file_versions(&id)
+        .await
+        .map_err(|e| storage_error("获取版本列表失败", e))?;
 
     Ok(serde_json::to_value(versions).unwrap())
 }
@@ -30,15 +27,32 @@ pub async fn get_version(
 ) -> silent::Result<Response> {
     let storage = &state.storage;
 
-    let data = storage.read_version_data(&version_id).await.map_err(|e| {
-        SilentError::business_error(StatusCode::NOT_FOUND, format!("版本不存在: {}", e))
-    })?;
+    let info = storage
+        .get_version_info(&version_id)
+        .await
+        .map_err(|e| storage_error("读取版本失败", e))?;
+    let data = storage
+        .read_version_data(&version_id)
+        .await
+        .map_err(|e| storage_error("读取版本失败", e))?;
 
     let mut resp = Response::empty();
     resp.headers_mut().insert(
         http::header::CONTENT_TYPE,
         http::HeaderValue::from_static("application/octet-stream"),
     );
+    // 创建者与版本说明（无法作为头部值时省略）
+    for (name, value) in [
+        ("x-version-author", &info.author),
+        ("x-version-comment", &info.comment),
+    ] {
+        if let Some(value) = value
+            .as_deref()
+            .and_then(|v| http::HeaderValue::from_str(v).ok())
+        {
+            resp.headers_mut().insert(name, value);
+        }
+    }
     resp.set_body(full(data));
     Ok(resp)
 }
@@ -56,12 +70,7 @@ pub async fn restore_version(
     storage
         .restore_file_version(&file_id, &version_id)
         .await
-        .map_err(|e| {
-            SilentError::business_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("恢复版本失败: {}", e),
-            )
-        })?;
+        .map_err(|e| storage_error("恢复版本失败", e))?;
 
     // 发送修改事件
     if let Ok(metadata) = storage.get_metadata(&file_id).await {
@@ -83,12 +92,7 @@ pub async fn delete_version(
     storage
         .delete_file_version(&version_id)
         .await
-        .map_err(|e| {
-            SilentError::business_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("删除版本失败: {}", e),
-            )
-        })?;
+        .map_err(|e| storage_error("删除版本失败", e))?;
 
     Ok(serde_json::json!({"success": true}))
 }
@@ -99,12 +103,24 @@ pub async fn get_version_stats(
 ) -> silent::Result<serde_json::Value> {
     let storage = &state.storage;
 
-    let stats = storage.get_storage_stats().await.map_err(|e| {
-        SilentError::business_error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("获取统计信息失败: {}", e),
-        )
-    })?;
+    let stats = storage
+        .get_storage_stats()
+        .await
+        .map_err(|e| storage_error("获取统计信息失败", e))?;
 
     Ok(serde_json::to_value(stats).unwrap())
 }
+
+/// 获取文件的跨版本去重报告
+pub async fn get_file_dedup_report(
+    (Path(id), CfgExtractor(state)): (Path<String>, CfgExtractor<AppState>),
+) -> silent::Result<serde_json::Value> {
+    let storage = &state.storage;
+
+    let report = storage
+        .file_dedup_report(&id)
+        .await
+        .map_err(|e| storage_error("获取去重报告失败", e))?;
+
+    Ok(serde_json::to_value(report).unwrap())
+}
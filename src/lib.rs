@@ -7,12 +7,17 @@ pub mod config;
 pub mod error;
 pub mod metrics;
 pub mod notify;
+pub mod range;
+pub mod rpc;
 pub mod s3;
 pub mod s3_search;
 pub mod search;
 pub mod storage; // 导出 storage 模块以支持 V2 测试
 pub mod unified_search;
 
+#[cfg(test)]
+mod test_util;
+
 // Re-export core types and storage
 pub use silent_nas_core as models;
 pub use silent_storage;
@@ -8,11 +8,14 @@ mod http;
 mod metrics;
 mod models;
 mod notify;
+mod range;
 mod rpc;
 mod s3;
 mod search;
 mod storage;
 mod sync;
+#[cfg(test)]
+mod test_util;
 mod transfer;
 mod webdav;
 
@@ -21,7 +24,6 @@ use error::Result;
 use event_listener::EventListener;
 use notify::EventNotifier;
 use rpc::FileServiceImpl;
-use sha2::Digest;
 use silent::prelude::*;
 use silent_nas_core::StorageManagerTrait;
 use std::net::SocketAddr;
@@ -41,13 +43,15 @@ async fn main() -> Result<()> {
 
     // 加载配置
     let config = Config::load();
+    config.cors.validate()?;
     info!("配置加载完成: {:?}", config);
 
     // 初始化全局存储管理器
     let storage = storage::create_storage(&config.storage).await?;
     info!(
-        "存储引擎初始化完成: compression={}",
-        config.storage.enable_compression
+        "存储引擎初始化完成: chunker={:?}, compression={}",
+        storage.config().chunker_type,
+        storage.config().enable_compression
     );
 
     // 将存储设置为全局实例
@@ -66,15 +70,33 @@ async fn main() -> Result<()> {
     // 初始化同步管理器
     let node_id = scru128::new_string();
     let sync_manager = SyncManager::new(node_id.clone(), notifier.clone().map(Arc::new));
+    sync_manager.set_tombstone_retention(std::time::Duration::from_secs(
+        config.sync.tombstone_retention_secs,
+    ));
     info!("同步管理器已初始化: node_id={}", node_id);
 
     // 初始化搜索引擎
-    let index_path = std::path::PathBuf::from(&config.storage.root_path).join("index");
-    let search_engine = Arc::new(crate::search::SearchEngine::new(
-        index_path,
+    let search_engine = Arc::new(crate::search::SearchEngine::with_config(
+        config.storage.index_dir(),
         config.storage.root_path.clone(),
+        config.search.clone(),
     )?);
     info!("搜索引擎已初始化");
+    search_engine.follow_mutations(storage.subscribe_mutations());
+    storage.add_erase_hook(search_engine.clone());
+    if search_engine.needs_reindex() {
+        let engine = search_engine.clone();
+        tokio::spawn(async move {
+            match StorageManagerTrait::list_files(storage::storage()).await {
+                Ok(files) => {
+                    if let Err(e) = engine.rebuild_index(&files).await {
+                        error!("重建搜索索引失败: {}", e);
+                    }
+                }
+                Err(e) => error!("重建搜索索引时读取文件列表失败: {}", e),
+            }
+        });
+    }
 
     // 计算对外 HTTP 基址（优先 ADVERTISE_HOST，否则容器 HOSTNAME），用于事件携带源地址
     let advertise_host = std::env::var("ADVERTISE_HOST")
@@ -101,7 +123,8 @@ async fn main() -> Result<()> {
             config.sync.fetch_max_retries,
             config.sync.fetch_base_backoff,
             config.sync.fetch_max_backoff,
-        );
+        )
+        .with_event_queue(config.sync.event_workers, config.sync.event_queue_capacity);
         let mut shutdown_rx_clone = shutdown_rx.clone();
         tokio::spawn(async move {
             tokio::select! {
@@ -120,6 +143,11 @@ async fn main() -> Result<()> {
         info!("跳过事件监听器（单节点模式）");
     }
 
+    // 审计日志（可选，通过环境变量启用），HTTP、WebDAV、S3 共用
+    let audit_logger = audit::AuditLogger::from_env(&config.storage.root_path).await;
+    let delete_policy = storage::DeletePolicy::new(config.storage.default_delete)
+        .with_audit_logger(audit_logger.clone());
+
     // 启动 HTTP 服务器（使用 Silent 框架）
     let http_addr = format!("{}:{}", config.server.host, config.server.http_port);
     let http_addr_clone = http_addr.clone();
@@ -138,6 +166,7 @@ async fn main() -> Result<()> {
             storage_http,
             search_clone,
             config_clone,
+            audit_logger,
         )
         .await
         {
@@ -151,63 +180,20 @@ async fn main() -> Result<()> {
         let storage_reconcile = storage.clone();
         let sync_reconcile = sync_manager.clone();
         let sync_cfg_reconcile = config.sync.clone();
+        let jitter = config.server.background_jitter;
         let mut shutdown_rx_reconcile = shutdown_rx.clone();
         tokio::spawn(async move {
-            use tokio::time::{Duration, sleep};
+            let client = sync::reconcile::build_client(&sync_cfg_reconcile);
             loop {
                 tokio::select! {
-                    _ = sleep(Duration::from_secs(30)) => {
-                        let states = sync_reconcile.get_all_sync_states().await;
-                        for st in states {
-                            if st.is_deleted() { continue; }
-                            if let Some(meta) = st.get_metadata().cloned() {
-                                let need_fetch = match storage_reconcile.get_metadata(&st.file_id).await {
-                                    Ok(local) => local.hash != meta.hash || local.size != meta.size,
-                                    Err(_) => true,
-                                };
-                                if need_fetch && let Some(src) = sync_reconcile.get_last_source(&st.file_id).await {
-                                    let client = reqwest::Client::builder()
-                                        .connect_timeout(Duration::from_secs(sync_cfg_reconcile.http_connect_timeout))
-                                        .timeout(Duration::from_secs(sync_cfg_reconcile.http_request_timeout))
-                                        .build()
-                                        .unwrap_or_else(|_| reqwest::Client::new());
-                                    let url = format!("{}/api/files/{}", src.trim_end_matches('/'), st.file_id);
-                                    let mut last_err: Option<String> = None;
-                                    let mut ok = false;
-                                    for attempt in 0..=sync_cfg_reconcile.fetch_max_retries {
-                                        match client.get(&url).send().await {
-                                            Ok(resp) if resp.status().is_success() => {
-                                                if let Ok(bytes) = resp.bytes().await {
-                                                    let actual = format!("{:x}", sha2::Sha256::digest(&bytes));
-                                                    if actual != meta.hash {
-                                                        last_err = Some(format!("哈希不一致 expected={} actual={}", meta.hash, actual));
-                                                    } else if let Err(e) = storage_reconcile.save_file(&st.file_id, &bytes).await {
-                                                        last_err = Some(format!("保存失败: {}", e));
-                                                    } else {
-                                                        info!("📥 补拉已完成: {}", st.file_id);
-                                                        ok = true;
-                                                        break;
-                                                    }
-                                                }
-                                            }
-                                            Ok(resp) => { last_err = Some(format!("HTTP {}", resp.status())); }
-                                            Err(e) => { last_err = Some(format!("请求失败: {}", e)); }
-                                        }
-                                        if attempt < sync_cfg_reconcile.fetch_max_retries {
-                                            let factor = 1u64 << (attempt.min(6));
-                                            let mut secs = sync_cfg_reconcile.fetch_base_backoff.saturating_mul(factor);
-                                            if secs > sync_cfg_reconcile.fetch_max_backoff { secs = sync_cfg_reconcile.fetch_max_backoff; }
-                                            let jitter = rand::random::<f64>() * 0.4 + 0.8;
-                                            let dur = Duration::from_secs(((secs as f64) * jitter).round() as u64);
-                                            sleep(dur).await;
-                                        }
-                                    }
-                                    if !ok {
-                                        warn!("补拉失败: {} - {}", st.file_id, last_err.unwrap_or_else(||"unknown".into()));
-                                    }
-                                }
-                            }
-                        }
+                    _ = tokio::time::sleep(silent_nas_core::jittered(sync::reconcile::RECONCILE_INTERVAL, jitter)) => {
+                        sync::reconcile::reconcile_once(
+                            &storage_reconcile,
+                            &sync_reconcile,
+                            &client,
+                            &sync_cfg_reconcile,
+                        )
+                        .await;
                     }
                     _ = shutdown_rx_reconcile.changed() => {
                         info!("巡检补拉任务收到退出信号");
@@ -220,6 +206,30 @@ async fn main() -> Result<()> {
         debug!("跳过巡检补拉任务（单节点或 NATS 未启用）");
     }
 
+    // 启动删除墓碑回收任务（需等待所有已知节点在删除之后完成同步）
+    if config.sync.tombstone_retention_secs > 0 {
+        let sync_tombstone = sync_manager.clone();
+        let jitter = config.server.background_jitter;
+        let mut shutdown_rx_tombstone = shutdown_rx.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(silent_nas_core::jittered(sync::crdt::TOMBSTONE_GC_INTERVAL, jitter)) => {
+                        let acked_until = match sync::node::try_node_sync() {
+                            Some(coordinator) => coordinator.tombstone_ack_horizon().await,
+                            None => i64::MAX,
+                        };
+                        sync_tombstone.collect_tombstones(acked_until).await;
+                    }
+                    _ = shutdown_rx_tombstone.changed() => {
+                        info!("墓碑回收任务收到退出信号");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
     // 启动 gRPC 服务器
     let grpc_addr: SocketAddr = format!("{}:{}", config.server.host, config.server.grpc_port)
         .parse()
@@ -255,6 +265,7 @@ async fn main() -> Result<()> {
     let notifier_webdav = notifier.clone();
     let sync_webdav = sync_manager.clone();
     let source_http_for_webdav = source_http_addr.clone();
+    let delete_policy_webdav = delete_policy.clone();
 
     let webdav_handle = tokio::spawn(async move {
         if let Err(e) = start_webdav_server(
@@ -263,6 +274,7 @@ async fn main() -> Result<()> {
             sync_webdav,
             source_http_for_webdav,
             search_engine.clone(),
+            delete_policy_webdav,
         )
         .await
         {
@@ -281,6 +293,7 @@ async fn main() -> Result<()> {
     let storage_s3 = Arc::new(storage.clone());
     let notifier_s3 = notifier.clone();
     let s3_config = config.s3.clone();
+    let cors_config = config.cors.clone();
     let source_http_addr_for_s3 = source_http_addr.clone();
     let s3_versioning_clone = s3_versioning_manager.clone();
 
@@ -290,6 +303,8 @@ async fn main() -> Result<()> {
             storage_s3,
             notifier_s3,
             s3_config,
+            cors_config,
+            delete_policy,
             source_http_addr_for_s3,
             s3_versioning_clone,
         )
@@ -421,6 +436,7 @@ async fn start_grpc_server(
         sync_manager.clone(),
         storage.clone(),
     );
+    sync::node::init_global_node_sync(node_sync.clone());
 
     // 启动节点心跳与自动同步任务
     if node_cfg.enable {
@@ -494,6 +510,7 @@ async fn start_webdav_server(
     sync_manager: Arc<SyncManager>,
     source_http_addr: String,
     search_engine: Arc<search::SearchEngine>,
+    delete_policy: storage::DeletePolicy,
 ) -> Result<()> {
     let notifier = notifier.map(Arc::new);
 
@@ -502,6 +519,7 @@ async fn start_webdav_server(
         sync_manager,
         source_http_addr,
         search_engine.clone(),
+        delete_policy,
     );
 
     info!("WebDAV 服务器启动: {}", addr);
@@ -517,11 +535,14 @@ async fn start_webdav_server(
 }
 
 /// 启动 S3 服务器
+#[allow(clippy::too_many_arguments)]
 async fn start_s3_server(
     addr: &str,
     storage: Arc<StorageManager>,
     notifier: Option<EventNotifier>,
     s3_config: config::S3Config,
+    cors_config: config::CorsConfig,
+    delete_policy: storage::DeletePolicy,
     source_http_addr: String,
     versioning_manager: Arc<s3::VersioningManager>,
 ) -> Result<()> {
@@ -534,13 +555,18 @@ async fn start_s3_server(
         None
     };
 
-    let route = s3::create_s3_routes(
+    let mut route = s3::create_s3_routes(
         storage,
         notifier,
         auth,
         source_http_addr.clone(),
         versioning_manager,
+        std::time::Duration::from_secs(s3_config.idempotency_ttl_secs),
+        delete_policy,
     );
+    if cors_config.is_enabled() {
+        route = route.hook(http::CorsHook::new(cors_config));
+    }
 
     info!("S3 服务器启动: {}", addr);
     info!("  - S3 API: http://{}/", addr);
@@ -6,9 +6,9 @@
 
 use lazy_static::lazy_static;
 use prometheus::{
-    CounterVec, Encoder, Gauge, HistogramVec, IntCounterVec, IntGauge, TextEncoder,
-    register_counter_vec, register_gauge, register_histogram_vec, register_int_counter_vec,
-    register_int_gauge,
+    CounterVec, Encoder, Gauge, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+    TextEncoder, register_counter_vec, register_gauge, register_histogram_vec,
+    register_int_counter, register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
 };
 
 lazy_static! {
@@ -70,6 +70,27 @@ lazy_static! {
     )
     .unwrap();
 
+    /// 热存储中等待优化的字节数
+    pub static ref STORAGE_HOT_BYTES: IntGauge = register_int_gauge!(
+        "storage_hot_bytes",
+        "Bytes held in hot storage awaiting optimization"
+    )
+    .unwrap();
+
+    /// 优化队列长度
+    pub static ref STORAGE_OPTIMIZATION_QUEUE_LENGTH: IntGauge = register_int_gauge!(
+        "storage_optimization_queue_length",
+        "Current number of pending storage optimization tasks"
+    )
+    .unwrap();
+
+    /// 下载校验发现内容损坏的次数
+    pub static ref STORAGE_CORRUPT_READS_TOTAL: IntCounter = register_int_counter!(
+        "storage_corrupt_reads_total",
+        "Total number of downloads whose content did not match the stored file hash"
+    )
+    .unwrap();
+
     // ============ 搜索指标 ============
     /// 搜索查询总数
     pub static ref SEARCH_QUERIES_TOTAL: IntCounterVec = register_int_counter_vec!(
@@ -96,6 +117,14 @@ lazy_static! {
     )
     .unwrap();
 
+    /// 超出成本上限的搜索查询数
+    pub static ref SEARCH_QUERIES_LIMITED_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "search_queries_limited_total",
+        "Total number of search queries clamped or rejected by cost limits",
+        &["reason"] // limit_clamped, fuzzy_rejected, timeout
+    )
+    .unwrap();
+
     // ============ 同步指标 ============
     /// 同步操作总数
     pub static ref SYNC_OPERATIONS_TOTAL: IntCounterVec = register_int_counter_vec!(
@@ -139,12 +168,63 @@ lazy_static! {
         &["stage"] // transfer, verify, other
     ).unwrap();
 
+    /// 远程事件队列已满而丢弃的事件数
+    pub static ref SYNC_EVENTS_DROPPED_TOTAL: IntCounter = register_int_counter!(
+        "sync_events_dropped_total",
+        "Total number of remote sync events dropped because the event queue was full"
+    ).unwrap();
+
     /// 失败补偿队列长度
     pub static ref SYNC_FAIL_QUEUE_LENGTH: IntGauge = register_int_gauge!(
         "sync_fail_queue_length",
         "Current length of sync failure compensation queue"
     ).unwrap();
 
+    /// 各节点同步滞后（秒）
+    pub static ref SYNC_PEER_LAG_SECONDS: IntGaugeVec = register_int_gauge_vec!(
+        "sync_peer_lag_seconds",
+        "Sync lag of each peer node in seconds",
+        &["node_id"]
+    ).unwrap();
+
+    /// 各节点待同步文件数
+    pub static ref SYNC_PEER_PENDING_FILES: IntGaugeVec = register_int_gauge_vec!(
+        "sync_peer_pending_files",
+        "Number of files pending sync to each peer node",
+        &["node_id"]
+    ).unwrap();
+
+    // ============ 认证指标 ============
+    /// 登录成功总数
+    pub static ref AUTH_LOGIN_SUCCESS_TOTAL: IntCounter = register_int_counter!(
+        "auth_login_success_total",
+        "Total number of successful logins"
+    )
+    .unwrap();
+
+    /// 登录失败总数
+    pub static ref AUTH_LOGIN_FAILURES_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "auth_login_failures_total",
+        "Total number of failed logins",
+        &["reason"] // bad_password, unknown_user, suspended, deleted, locked
+    )
+    .unwrap();
+
+    /// 因失败次数过多触发的锁定总数
+    pub static ref AUTH_LOCKOUTS_TOTAL: IntCounter = register_int_counter!(
+        "auth_lockouts_total",
+        "Total number of lockouts triggered by repeated login failures"
+    )
+    .unwrap();
+
+    /// Token 事件总数
+    pub static ref AUTH_TOKEN_EVENTS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "auth_token_events_total",
+        "Total number of token refreshes and revocations",
+        &["event"] // refresh, revoke
+    )
+    .unwrap();
+
     // ============ 缓存指标 ============
     /// 缓存命中率
     pub static ref CACHE_HIT_RATE: Gauge = register_gauge!(
@@ -285,6 +365,20 @@ pub fn update_storage_stats(file_count: i64, bytes_used: i64) {
     STORAGE_BYTES_USED.set(bytes_used);
 }
 
+/// 更新优化积压统计（热存储字节数与优化队列长度）
+pub fn update_optimization_backlog(hot_bytes: i64, queue_length: i64) {
+    STORAGE_HOT_BYTES.set(hot_bytes);
+    STORAGE_OPTIMIZATION_QUEUE_LENGTH.set(queue_length);
+}
+
+/// 同步存储层累计的下载损坏次数
+pub fn update_corrupt_reads(total: u64) {
+    let recorded = STORAGE_CORRUPT_READS_TOTAL.get();
+    if total > recorded {
+        STORAGE_CORRUPT_READS_TOTAL.inc_by(total - recorded);
+    }
+}
+
 /// 记录搜索查询
 pub fn record_search_query(status: &str, duration: f64, result_count: usize) {
     SEARCH_QUERIES_TOTAL.with_label_values(&[status]).inc();
@@ -296,6 +390,13 @@ pub fn record_search_query(status: &str, duration: f64, result_count: usize) {
         .inc_by(result_count as f64);
 }
 
+/// 记录超出成本上限的搜索查询
+pub fn record_search_limited(reason: &str) {
+    SEARCH_QUERIES_LIMITED_TOTAL
+        .with_label_values(&[reason])
+        .inc();
+}
+
 /// 记录同步操作
 pub fn record_sync_operation(sync_type: &str, status: &str, bytes: u64) {
     SYNC_OPERATIONS_TOTAL
@@ -319,6 +420,28 @@ pub fn record_sync_stage(stage: &str, result: &str, seconds: f64) {
     // 分位数通过 Prometheus 端计算
 }
 
+/// 记录登录成功
+pub fn record_auth_login_success() {
+    AUTH_LOGIN_SUCCESS_TOTAL.inc();
+}
+
+/// 记录登录失败
+pub fn record_auth_login_failure(reason: &str) {
+    AUTH_LOGIN_FAILURES_TOTAL.with_label_values(&[reason]).inc();
+}
+
+/// 记录一次账户锁定
+pub fn record_auth_lockout() {
+    AUTH_LOCKOUTS_TOTAL.inc();
+}
+
+/// 记录 Token 事件（refresh / revoke）
+pub fn record_auth_token_event(event: &str, count: u64) {
+    AUTH_TOKEN_EVENTS_TOTAL
+        .with_label_values(&[event])
+        .inc_by(count);
+}
+
 /// 更新缓存统计
 pub fn update_cache_stats(hit_rate: f64, size_bytes: i64, entries: i64) {
     CACHE_HIT_RATE.set(hit_rate);
@@ -331,11 +454,26 @@ pub fn record_sync_retry(stage: &str) {
     SYNC_RETRIES_TOTAL.with_label_values(&[stage]).inc();
 }
 
+/// 记录因事件队列已满而丢弃的远程事件
+pub fn record_sync_event_dropped() {
+    SYNC_EVENTS_DROPPED_TOTAL.inc();
+}
+
 /// 更新失败补偿队列长度
 pub fn set_sync_fail_queue_length(len: i64) {
     SYNC_FAIL_QUEUE_LENGTH.set(len);
 }
 
+/// 更新节点同步滞后与待同步文件数
+pub fn set_sync_peer_status(node_id: &str, lag_seconds: i64, pending_files: i64) {
+    SYNC_PEER_LAG_SECONDS
+        .with_label_values(&[node_id])
+        .set(lag_seconds);
+    SYNC_PEER_PENDING_FILES
+        .with_label_values(&[node_id])
+        .set(pending_files);
+}
+
 /// 记录上传会话创建
 pub fn record_upload_session_created() {
     UPLOAD_SESSIONS_TOTAL.with_label_values(&["created"]).inc();
@@ -441,6 +579,13 @@ mod tests {
         assert_eq!(STORAGE_BYTES_USED.get(), 1024 * 1024);
     }
 
+    #[test]
+    fn test_update_optimization_backlog() {
+        update_optimization_backlog(4096, 3);
+        assert_eq!(STORAGE_HOT_BYTES.get(), 4096);
+        assert_eq!(STORAGE_OPTIMIZATION_QUEUE_LENGTH.get(), 3);
+    }
+
     #[test]
     fn test_cache_stats() {
         update_cache_stats(0.85, 10 * 1024 * 1024, 1000);
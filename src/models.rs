@@ -14,6 +14,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: chrono::Local::now().naive_local(),
             modified_at: chrono::Local::now().naive_local(),
+            user_metadata: Default::default(),
         }
     }
 
@@ -1,7 +1,136 @@
 use crate::error::{NasError, Result};
 use crate::models::{EventType, FileEvent};
 use async_nats::Client;
-use tracing::{debug, error, info};
+use futures_util::StreamExt;
+use tracing::{debug, error, info, warn};
+
+/// 按路径划分的子主题段：`<prefix>.path.<首段>.<事件类型>`
+const PATH_TOPIC_SEGMENT: &str = "path";
+
+/// 没有目录的文件（位于根目录）使用的子主题名
+const ROOT_SUBTOPIC: &str = "_root";
+
+/// 文件 ID 的首个路径段对应的子主题名
+///
+/// NATS 主题中 `.`、`*`、`>` 和空白有特殊含义，替换为 `_`。
+pub fn path_subtopic(file_id: &str) -> String {
+    let trimmed = file_id.trim_start_matches('/');
+    let segment = match trimmed.split_once('/') {
+        Some((first, _)) if !first.is_empty() => first,
+        _ => return ROOT_SUBTOPIC.to_string(),
+    };
+    segment
+        .chars()
+        .map(|c| match c {
+            '.' | '*' | '>' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// 事件类型对应的主题后缀
+fn event_suffix(event_type: &EventType) -> &'static str {
+    match event_type {
+        EventType::Created => "created",
+        EventType::Modified => "modified",
+        EventType::Deleted => "deleted",
+    }
+}
+
+/// 事件发布到的所有主题
+///
+/// - 全局主题 `<prefix>.<事件类型>`，供全局监听者使用
+/// - 路径子主题 `<prefix>.path.<首段>.<事件类型>`，供只关心某个目录的订阅者使用
+///
+/// 全局订阅 `<prefix>.*` 只匹配一级，不会重复收到子主题上的事件。
+pub fn event_topics(topic_prefix: &str, event: &FileEvent) -> [String; 2] {
+    let suffix = event_suffix(&event.event_type);
+    [
+        format!("{}.{}", topic_prefix, suffix),
+        format!(
+            "{}.{}.{}.{}",
+            topic_prefix,
+            PATH_TOPIC_SEGMENT,
+            path_subtopic(&event.file_id),
+            suffix
+        ),
+    ]
+}
+
+/// 订阅过滤条件
+///
+/// 不指定路径前缀时订阅全局主题，接收所有事件；
+/// 指定时只订阅前缀首段对应的子主题，更深的前缀在客户端按文件 ID 再过滤一次。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriptionFilter {
+    path_prefix: Option<String>,
+}
+
+impl SubscriptionFilter {
+    /// 接收所有事件
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// 只接收指定路径前缀下的事件（如 `photos` 或 `photos/2024`）
+    #[allow(dead_code)]
+    pub fn path_prefix(prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        Self {
+            path_prefix: (!prefix.is_empty()).then(|| prefix.to_string()),
+        }
+    }
+
+    /// 订阅的 NATS 主题
+    pub fn subject(&self, topic_prefix: &str) -> String {
+        match &self.path_prefix {
+            None => format!("{}.*", topic_prefix),
+            Some(prefix) => format!(
+                "{}.{}.{}.*",
+                topic_prefix,
+                PATH_TOPIC_SEGMENT,
+                path_subtopic(&format!("{}/", prefix))
+            ),
+        }
+    }
+
+    /// 事件是否满足过滤条件
+    #[allow(dead_code)]
+    pub fn matches(&self, event: &FileEvent) -> bool {
+        let Some(prefix) = &self.path_prefix else {
+            return true;
+        };
+        let file_id = event.file_id.trim_start_matches('/');
+        file_id
+            .strip_prefix(prefix.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// 按过滤条件订阅的事件流
+#[allow(dead_code)]
+pub struct EventSubscription {
+    subscriber: async_nats::Subscriber,
+    filter: SubscriptionFilter,
+}
+
+impl EventSubscription {
+    /// 下一个满足过滤条件的事件，订阅关闭时返回 `None`
+    ///
+    /// 无法解析的消息被跳过。
+    #[allow(dead_code)]
+    pub async fn next(&mut self) -> Option<FileEvent> {
+        while let Some(message) = self.subscriber.next().await {
+            match serde_json::from_slice::<FileEvent>(&message.payload) {
+                Ok(event) if self.filter.matches(&event) => return Some(event),
+                Ok(_) => {}
+                Err(e) => warn!("解析事件失败: {} - {}", message.subject, e),
+            }
+        }
+        None
+    }
+}
 
 /// NATS 事件通知器
 #[derive(Clone)]
@@ -53,33 +182,40 @@ impl EventNotifier {
         &self.topic_prefix
     }
 
-    /// 获取主题名称
-    fn get_topic(&self, event_type: &EventType) -> String {
-        match event_type {
-            EventType::Created => format!("{}.created", self.topic_prefix),
-            EventType::Modified => format!("{}.modified", self.topic_prefix),
-            EventType::Deleted => format!("{}.deleted", self.topic_prefix),
-        }
-    }
-
     /// 发布文件事件
+    ///
+    /// 同时发布到全局主题与路径子主题（见 [`event_topics`]）。
     pub async fn publish_event(&self, event: &FileEvent) -> Result<()> {
-        let topic = self.get_topic(&event.event_type);
-        let payload = serde_json::to_vec(event)?;
-
-        self.client
-            .publish(topic.clone(), payload.into())
-            .await
-            .map_err(|e| NasError::Nats(format!("发布事件失败: {}", e)))?;
-
-        debug!(
-            "事件已发布: {} - 文件ID: {} - 事件ID: {}",
-            topic, event.file_id, event.event_id
-        );
+        let payload: bytes::Bytes = serde_json::to_vec(event)?.into();
+
+        for topic in event_topics(&self.topic_prefix, event) {
+            self.client
+                .publish(topic.clone(), payload.clone())
+                .await
+                .map_err(|e| NasError::Nats(format!("发布事件失败: {}", e)))?;
+
+            debug!(
+                "事件已发布: {} - 文件ID: {} - 事件ID: {}",
+                topic, event.file_id, event.event_id
+            );
+        }
 
         Ok(())
     }
 
+    /// 按过滤条件订阅文件事件
+    #[allow(dead_code)]
+    pub async fn subscribe(&self, filter: SubscriptionFilter) -> Result<EventSubscription> {
+        let subject = filter.subject(&self.topic_prefix);
+        let subscriber = self
+            .client
+            .subscribe(subject.clone())
+            .await
+            .map_err(|e| NasError::Nats(format!("订阅主题失败: {}", e)))?;
+        debug!("已订阅主题: {}", subject);
+        Ok(EventSubscription { subscriber, filter })
+    }
+
     /// 发布文件创建事件
     pub async fn notify_created(&self, event: FileEvent) -> Result<()> {
         self.publish_event(&event).await
@@ -237,6 +373,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let event = FileEvent::new(EventType::Created, "file-123".to_string(), Some(metadata));
@@ -297,6 +434,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: chrono::Local::now().naive_local(),
             modified_at: chrono::Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let event = FileEvent {
@@ -401,4 +539,82 @@ mod tests {
             assert!(topic.starts_with(prefix));
         }
     }
+
+    /// NATS 主题通配符匹配（`*` 匹配一级，`>` 匹配剩余所有级）
+    fn subject_matches(pattern: &str, subject: &str) -> bool {
+        let mut pattern = pattern.split('.');
+        let mut subject = subject.split('.');
+        loop {
+            match (pattern.next(), subject.next()) {
+                (Some(">"), Some(_)) => return true,
+                (Some("*"), Some(_)) => {}
+                (Some(p), Some(s)) if p == s => {}
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+
+    #[test]
+    fn test_path_subtopic() {
+        assert_eq!(path_subtopic("docs/a.txt"), "docs");
+        assert_eq!(path_subtopic("/photos/2024/b.jpg"), "photos");
+        assert_eq!(path_subtopic("a.txt"), ROOT_SUBTOPIC);
+        assert_eq!(path_subtopic("my.dir/x"), "my_dir");
+        assert_eq!(path_subtopic("with space/x"), "with_space");
+    }
+
+    #[test]
+    fn test_event_published_on_path_subtopic_and_filtered() {
+        let prefix = "silent.nas.files";
+        let event = FileEvent::new(EventType::Created, "docs/a.txt".to_string(), None);
+
+        let topics = event_topics(prefix, &event);
+        assert_eq!(
+            topics,
+            [
+                "silent.nas.files.created".to_string(),
+                "silent.nas.files.path.docs.created".to_string(),
+            ]
+        );
+
+        // docs 订阅者通过子主题收到一次
+        let docs = SubscriptionFilter::path_prefix("docs");
+        let docs_subject = docs.subject(prefix);
+        assert_eq!(docs_subject, "silent.nas.files.path.docs.*");
+        assert_eq!(
+            topics
+                .iter()
+                .filter(|t| subject_matches(&docs_subject, t))
+                .count(),
+            1
+        );
+        assert!(docs.matches(&event));
+
+        // photos 订阅者收不到
+        let photos = SubscriptionFilter::path_prefix("/photos/");
+        let photos_subject = photos.subject(prefix);
+        assert!(!topics.iter().any(|t| subject_matches(&photos_subject, t)));
+        assert!(!photos.matches(&event));
+
+        // 全局订阅者只通过全局主题收到一次
+        let all_subject = SubscriptionFilter::all().subject(prefix);
+        assert_eq!(
+            topics
+                .iter()
+                .filter(|t| subject_matches(&all_subject, t))
+                .count(),
+            1
+        );
+        assert!(SubscriptionFilter::all().matches(&event));
+
+        // 更深的前缀在客户端按文件 ID 过滤
+        let nested = SubscriptionFilter::path_prefix("docs/reports");
+        assert_eq!(nested.subject(prefix), docs_subject);
+        assert!(!nested.matches(&event));
+        let report = FileEvent::new(EventType::Modified, "docs/reports/q1.pdf".to_string(), None);
+        assert!(nested.matches(&report));
+        let sibling = FileEvent::new(EventType::Modified, "docs/reports2/x".to_string(), None);
+        assert!(!nested.matches(&sibling));
+    }
 }
@@ -0,0 +1,73 @@
+//! HTTP 字节范围请求
+//!
+//! 解析 `Range` 请求头（RFC 9110），S3 GET Object 与 WebDAV GET 共用。
+
+/// 单个请求允许的最大范围数，超出时忽略 Range 头
+const MAX_RANGES: usize = 16;
+
+/// Range 头解析结果
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum RangeRequest {
+    /// 不是合法的字节范围，忽略 Range 头并返回完整内容
+    Ignored,
+    /// 所有范围都超出文件大小（416）
+    Unsatisfiable,
+    /// 可满足的范围列表（闭区间）
+    Ranges(Vec<(u64, u64)>),
+}
+
+/// 解析Range头，范围均为闭区间 (start, end)
+///
+/// 支持 `bytes=start-end`、`bytes=start-`、`bytes=-count` 及逗号分隔的多个范围。
+/// 无法满足的范围被丢弃，全部无法满足时返回 [`RangeRequest::Unsatisfiable`]。
+pub(crate) fn parse_range(range_str: &str, file_size: u64) -> RangeRequest {
+    let Some(specs) = range_str.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Ignored;
+    };
+
+    let specs: Vec<&str> = specs.split(',').map(str::trim).collect();
+    if specs.len() > MAX_RANGES {
+        return RangeRequest::Ignored;
+    }
+
+    let mut ranges = Vec::with_capacity(specs.len());
+    for spec in specs {
+        let Some((start_str, end_str)) = spec.split_once('-') else {
+            return RangeRequest::Ignored;
+        };
+        match (start_str.trim(), end_str.trim()) {
+            ("", count_str) => {
+                // bytes=-count: 最后count字节
+                let Ok(count) = count_str.parse::<u64>() else {
+                    return RangeRequest::Ignored;
+                };
+                if count > 0 && file_size > 0 {
+                    ranges.push((file_size.saturating_sub(count), file_size - 1));
+                }
+            }
+            (start_str, end_str) => {
+                // bytes=start- 或 bytes=start-end，end 超出文件大小时截断
+                let Ok(start) = start_str.parse::<u64>() else {
+                    return RangeRequest::Ignored;
+                };
+                let end = if end_str.is_empty() {
+                    u64::MAX
+                } else {
+                    match end_str.parse::<u64>() {
+                        Ok(end) if end >= start => end,
+                        _ => return RangeRequest::Ignored,
+                    }
+                };
+                if start < file_size {
+                    ranges.push((start, end.min(file_size - 1)));
+                }
+            }
+        }
+    }
+
+    if ranges.is_empty() {
+        RangeRequest::Unsatisfiable
+    } else {
+        RangeRequest::Ranges(ranges)
+    }
+}
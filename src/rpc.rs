@@ -8,6 +8,10 @@ pub mod file_service {
     tonic::include_proto!("silent.nas");
 }
 
+pub mod client;
+
+pub use client::{ClientTlsConfig, FileServiceClient, FileServiceClientBuilder, RetryPolicy};
+
 use file_service::file_service_server::{FileService, FileServiceServer};
 use file_service::*;
 
@@ -180,6 +184,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let proto_metadata = convert_metadata(&metadata);
@@ -203,6 +208,7 @@ mod tests {
             hash: "".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let proto_metadata = convert_metadata(&metadata);
@@ -224,6 +230,7 @@ mod tests {
             hash: "hash_of_large_file".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let proto_metadata = convert_metadata(&metadata);
@@ -241,6 +248,7 @@ mod tests {
             hash: "hash123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let proto_metadata = convert_metadata(&metadata);
@@ -261,6 +269,7 @@ mod tests {
             hash: "hash".to_string(),
             created_at: now,
             modified_at: now,
+            user_metadata: Default::default(),
         };
 
         let proto_metadata = convert_metadata(&metadata);
@@ -284,6 +293,7 @@ mod tests {
                 hash: "hash1".to_string(),
                 created_at: Local::now().naive_local(),
                 modified_at: Local::now().naive_local(),
+                user_metadata: Default::default(),
             },
             crate::models::FileMetadata {
                 id: "2".to_string(),
@@ -293,6 +303,7 @@ mod tests {
                 hash: "hash2".to_string(),
                 created_at: Local::now().naive_local(),
                 modified_at: Local::now().naive_local(),
+                user_metadata: Default::default(),
             },
         ];
 
@@ -0,0 +1,344 @@
+//! FileService gRPC 客户端
+//!
+//! 封装生成的 `FileServiceClient`，提供连接/请求超时、可选 TLS，
+//! 以及只读（幂等）调用的自动重试：
+//!
+//! ```ignore
+//! let client = FileServiceClientBuilder::new("127.0.0.1:50051")
+//!     .connect_timeout(Duration::from_secs(3))
+//!     .request_timeout(Duration::from_secs(10))
+//!     .connect()
+//!     .await?;
+//! let meta = client.get_metadata("file-id").await?;
+//! ```
+
+// 供嵌入本 crate 的调用方使用，二进制内未使用
+#![allow(dead_code)]
+
+use super::file_service::file_service_client::FileServiceClient as GeneratedClient;
+use super::file_service::*;
+use crate::error::{NasError, Result};
+use std::time::Duration;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Status};
+use tracing::debug;
+
+pub use tonic::transport::ClientTlsConfig;
+
+/// 幂等调用的重试策略
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 最大重试次数（不含首次调用），0 表示不重试
+    pub max_retries: u32,
+    /// 首次重试前的等待时长，之后按 2 倍递增
+    pub initial_backoff: Duration,
+    /// 单次等待上限
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 不重试
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// 第 attempt 次重试前的等待时长
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(1u32 << attempt.min(16))
+            .min(self.max_backoff)
+    }
+
+    /// 可重试的状态码（瞬时故障）
+    fn should_retry(status: &Status) -> bool {
+        matches!(
+            status.code(),
+            Code::Unavailable | Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted
+        )
+    }
+}
+
+/// FileService 客户端构建器
+#[derive(Debug, Clone)]
+pub struct FileServiceClientBuilder {
+    address: String,
+    connect_timeout: Duration,
+    request_timeout: Duration,
+    tls: Option<ClientTlsConfig>,
+    retry: RetryPolicy,
+}
+
+impl FileServiceClientBuilder {
+    /// 创建构建器，`address` 可以是 `host:port` 或完整的 `http(s)://host:port`
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(30),
+            tls: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// 连接超时
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// 单次请求超时
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 启用 TLS
+    pub fn tls(mut self, config: ClientTlsConfig) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    /// 幂等调用的重试策略
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    fn endpoint(&self) -> Result<Endpoint> {
+        let uri = if self.address.contains("://") {
+            self.address.clone()
+        } else if self.tls.is_some() {
+            format!("https://{}", self.address)
+        } else {
+            format!("http://{}", self.address)
+        };
+
+        let mut endpoint = Endpoint::from_shared(uri)
+            .map_err(|e| NasError::Other(format!("无效的地址: {}", e)))?
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.request_timeout)
+            .tcp_nodelay(true);
+        if let Some(tls) = self.tls.clone() {
+            endpoint = endpoint
+                .tls_config(tls)
+                .map_err(|e| NasError::Other(format!("TLS 配置无效: {}", e)))?;
+        }
+        Ok(endpoint)
+    }
+
+    /// 建立连接并返回客户端
+    pub async fn connect(self) -> Result<FileServiceClient> {
+        let channel = self
+            .endpoint()?
+            .connect()
+            .await
+            .map_err(|e| NasError::Other(format!("连接失败: {}", e)))?;
+        Ok(FileServiceClient {
+            inner: GeneratedClient::new(channel),
+            retry: self.retry,
+        })
+    }
+
+    /// 返回延迟连接的客户端，首次调用时才建立连接
+    pub fn connect_lazy(self) -> Result<FileServiceClient> {
+        let channel = self.endpoint()?.connect_lazy();
+        Ok(FileServiceClient {
+            inner: GeneratedClient::new(channel),
+            retry: self.retry,
+        })
+    }
+}
+
+/// FileService 客户端
+///
+/// 可廉价克隆，克隆之间共享底层连接。只读调用按重试策略重试，
+/// 上传与删除不自动重试，由调用方决定。
+#[derive(Debug, Clone)]
+pub struct FileServiceClient {
+    inner: GeneratedClient<Channel>,
+    retry: RetryPolicy,
+}
+
+impl FileServiceClient {
+    /// 生成的原始客户端，用于未封装的调用
+    pub fn inner(&self) -> GeneratedClient<Channel> {
+        self.inner.clone()
+    }
+
+    /// 上传文件
+    pub async fn upload_file(
+        &self,
+        file_id: &str,
+        data: Vec<u8>,
+    ) -> std::result::Result<Option<FileMetadata>, Status> {
+        let req = UploadFileRequest {
+            file_id: file_id.to_string(),
+            data,
+        };
+        let resp = self.inner.clone().upload_file(req).await?;
+        Ok(resp.into_inner().metadata)
+    }
+
+    /// 下载文件
+    pub async fn download_file(
+        &self,
+        file_id: &str,
+    ) -> std::result::Result<DownloadFileResponse, Status> {
+        self.with_retry("download_file", |mut client| {
+            let req = DownloadFileRequest {
+                file_id: file_id.to_string(),
+            };
+            async move { client.download_file(req).await }
+        })
+        .await
+    }
+
+    /// 删除文件
+    pub async fn delete_file(&self, file_id: &str) -> std::result::Result<bool, Status> {
+        let req = DeleteFileRequest {
+            file_id: file_id.to_string(),
+        };
+        let resp = self.inner.clone().delete_file(req).await?;
+        Ok(resp.into_inner().success)
+    }
+
+    /// 获取文件元数据
+    pub async fn get_metadata(
+        &self,
+        file_id: &str,
+    ) -> std::result::Result<Option<FileMetadata>, Status> {
+        let resp = self
+            .with_retry("get_metadata", |mut client| {
+                let req = GetMetadataRequest {
+                    file_id: file_id.to_string(),
+                };
+                async move { client.get_metadata(req).await }
+            })
+            .await?;
+        Ok(resp.metadata)
+    }
+
+    /// 列出所有文件
+    pub async fn list_files(&self) -> std::result::Result<Vec<FileMetadata>, Status> {
+        let resp = self
+            .with_retry("list_files", |mut client| async move {
+                client.list_files(ListFilesRequest {}).await
+            })
+            .await?;
+        Ok(resp.files)
+    }
+
+    /// 按重试策略执行幂等调用
+    async fn with_retry<T, F, Fut>(&self, op: &str, mut call: F) -> std::result::Result<T, Status>
+    where
+        F: FnMut(GeneratedClient<Channel>) -> Fut,
+        Fut: Future<Output = std::result::Result<tonic::Response<T>, Status>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match call(self.inner.clone()).await {
+                Ok(resp) => return Ok(resp.into_inner()),
+                Err(status)
+                    if attempt < self.retry.max_retries && RetryPolicy::should_retry(&status) =>
+                {
+                    let delay = self.retry.backoff(attempt);
+                    debug!(
+                        "gRPC 调用 {} 失败，{:?} 后重试（第 {} 次）: {}",
+                        op,
+                        delay,
+                        attempt + 1,
+                        status
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(status) => return Err(status),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::FileServiceImpl;
+    use crate::storage::{IncrementalConfig, StorageManager};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_builder_client_calls_server() {
+        let dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(
+            dir.path().to_path_buf(),
+            64 * 1024,
+            IncrementalConfig::default(),
+        );
+        storage.init().await.unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = FileServiceImpl::new(storage, None, None).into_server();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener))
+                .await
+                .unwrap();
+        });
+
+        let client = FileServiceClientBuilder::new(addr.to_string())
+            .connect_timeout(Duration::from_secs(2))
+            .request_timeout(Duration::from_secs(5))
+            .retry_policy(RetryPolicy {
+                max_retries: 2,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(50),
+            })
+            .connect()
+            .await
+            .unwrap();
+
+        let uploaded = client
+            .upload_file("grpc-file", b"hello grpc".to_vec())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(uploaded.size, 10);
+
+        let meta = client.get_metadata("grpc-file").await.unwrap().unwrap();
+        assert_eq!(meta.hash, uploaded.hash);
+        let downloaded = client.download_file("grpc-file").await.unwrap();
+        assert_eq!(downloaded.data, b"hello grpc");
+        assert_eq!(client.list_files().await.unwrap().len(), 1);
+
+        // 不可重试的错误直接返回
+        let err = client.get_metadata("missing").await.unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[test]
+    fn test_retry_backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(350),
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(350));
+        assert_eq!(policy.backoff(10), Duration::from_millis(350));
+    }
+}
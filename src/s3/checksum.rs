@@ -0,0 +1,233 @@
+//! S3 附加校验和（`x-amz-checksum-*`）
+//!
+//! 新版 AWS SDK 上传时声明校验算法并携带请求体的校验值，服务端需校验后在响应中回显。
+//! 目前支持 CRC32、CRC32C 与 SHA-256，校验值均为大端字节序的 Base64 编码。
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use crc::{CRC_32_ISCSI, CRC_32_ISO_HDLC, Crc};
+use http::HeaderMap;
+use sha2::{Digest, Sha256};
+
+const CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+const CRC32C: Crc<u32> = Crc::<u32>::new(&CRC_32_ISCSI);
+
+/// 声明校验算法的请求头（SDK 使用前者，直接调用 API 时为后者）
+const ALGORITHM_HEADERS: [&str; 2] = ["x-amz-sdk-checksum-algorithm", "x-amz-checksum-algorithm"];
+
+/// 校验算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChecksumAlgorithm {
+    Crc32,
+    Crc32c,
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    const ALL: [ChecksumAlgorithm; 3] = [Self::Crc32, Self::Crc32c, Self::Sha256];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|alg| alg.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// 算法名（`x-amz-checksum-algorithm` 的取值）
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Crc32 => "CRC32",
+            Self::Crc32c => "CRC32C",
+            Self::Sha256 => "SHA256",
+        }
+    }
+
+    /// 携带校验值的请求/响应头
+    pub fn header_name(self) -> &'static str {
+        match self {
+            Self::Crc32 => "x-amz-checksum-crc32",
+            Self::Crc32c => "x-amz-checksum-crc32c",
+            Self::Sha256 => "x-amz-checksum-sha256",
+        }
+    }
+
+    fn digest_len(self) -> usize {
+        match self {
+            Self::Crc32 | Self::Crc32c => 4,
+            Self::Sha256 => 32,
+        }
+    }
+
+    /// 计算数据的校验值（Base64）
+    pub fn compute(self, data: &[u8]) -> String {
+        match self {
+            Self::Crc32 => BASE64.encode(CRC32.checksum(data).to_be_bytes()),
+            Self::Crc32c => BASE64.encode(CRC32C.checksum(data).to_be_bytes()),
+            Self::Sha256 => BASE64.encode(Sha256::digest(data)),
+        }
+    }
+}
+
+/// 请求声明的校验和
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RequestedChecksum {
+    pub algorithm: ChecksumAlgorithm,
+    /// 客户端提供的校验值；只声明算法时为空，服务端计算后回显
+    pub expected: Option<String>,
+}
+
+/// 校验失败
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ChecksumError {
+    /// 请求头无效（400 InvalidRequest）
+    Invalid(String),
+    /// 校验值与请求体不一致（400 BadDigest）
+    Mismatch(ChecksumAlgorithm),
+}
+
+impl ChecksumError {
+    /// S3 错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Invalid(_) => "InvalidRequest",
+            Self::Mismatch(_) => "BadDigest",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::Invalid(msg) => msg.clone(),
+            Self::Mismatch(alg) => format!(
+                "The {} you specified did not match the calculated checksum.",
+                alg.name()
+            ),
+        }
+    }
+}
+
+/// 解析请求声明的校验和，未声明时返回 `None`
+pub(crate) fn requested_checksum(
+    headers: &HeaderMap,
+) -> Result<Option<RequestedChecksum>, ChecksumError> {
+    let mut provided = ChecksumAlgorithm::ALL.into_iter().filter_map(|alg| {
+        headers
+            .get(alg.header_name())
+            .map(|v| (alg, v.to_str().unwrap_or_default().trim().to_string()))
+    });
+    let first = provided.next();
+    if provided.next().is_some() {
+        return Err(ChecksumError::Invalid(
+            "Expecting a single x-amz-checksum- header. Multiple checksum Types are not allowed."
+                .to_string(),
+        ));
+    }
+
+    let declared = ALGORITHM_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .map(|v| {
+            let name = v.to_str().unwrap_or_default();
+            ChecksumAlgorithm::from_name(name).ok_or_else(|| {
+                ChecksumError::Invalid(format!("Unsupported checksum algorithm: {}", name))
+            })
+        })
+        .transpose()?;
+
+    match (first, declared) {
+        (Some((alg, _)), Some(declared)) if alg != declared => {
+            Err(ChecksumError::Invalid(format!(
+                "Value for x-amz-checksum-algorithm header is invalid: expected {}",
+                alg.name()
+            )))
+        }
+        (Some((algorithm, value)), _) => {
+            let valid = BASE64
+                .decode(&value)
+                .is_ok_and(|raw| raw.len() == algorithm.digest_len());
+            if !valid {
+                return Err(ChecksumError::Invalid(format!(
+                    "Value for {} header is invalid.",
+                    algorithm.header_name()
+                )));
+            }
+            Ok(Some(RequestedChecksum {
+                algorithm,
+                expected: Some(value),
+            }))
+        }
+        (None, Some(algorithm)) => Ok(Some(RequestedChecksum {
+            algorithm,
+            expected: None,
+        })),
+        (None, None) => Ok(None),
+    }
+}
+
+impl RequestedChecksum {
+    /// 校验请求体，返回用于回显的校验值
+    pub fn verify(&self, body: &[u8]) -> Result<String, ChecksumError> {
+        let actual = self.algorithm.compute(body);
+        match &self.expected {
+            Some(expected) if *expected != actual => Err(ChecksumError::Mismatch(self.algorithm)),
+            _ => Ok(actual),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_values_match_aws_encoding() {
+        assert_eq!(ChecksumAlgorithm::Crc32c.compute(b"123456789"), "4waSgw==");
+        assert_eq!(ChecksumAlgorithm::Crc32.compute(b"123456789"), "y/Q5Jg==");
+        assert_eq!(
+            ChecksumAlgorithm::Sha256.compute(b""),
+            "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+        );
+    }
+
+    #[test]
+    fn test_requested_checksum_parsing() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(requested_checksum(&headers), Ok(None));
+
+        // 只声明算法
+        headers.insert(
+            "x-amz-sdk-checksum-algorithm",
+            http::HeaderValue::from_static("crc32c"),
+        );
+        let requested = requested_checksum(&headers).unwrap().unwrap();
+        assert_eq!(requested.algorithm, ChecksumAlgorithm::Crc32c);
+        assert_eq!(requested.expected, None);
+
+        // 声明的算法与校验值头不一致
+        headers.insert(
+            "x-amz-checksum-sha256",
+            http::HeaderValue::from_static("47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="),
+        );
+        assert!(matches!(
+            requested_checksum(&headers),
+            Err(ChecksumError::Invalid(_))
+        ));
+
+        // 校验值长度不符
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-amz-checksum-crc32c",
+            http::HeaderValue::from_static("AAAA"),
+        );
+        assert!(matches!(
+            requested_checksum(&headers),
+            Err(ChecksumError::Invalid(_))
+        ));
+
+        // 不支持的算法
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-amz-checksum-algorithm",
+            http::HeaderValue::from_static("CRC64NVME"),
+        );
+        assert!(requested_checksum(&headers).is_err());
+    }
+}
@@ -15,6 +15,13 @@ impl S3Service {
 
         debug!("DeleteObjects: bucket={}", bucket);
 
+        // 批量删除中的所有对象使用同一删除语义
+        let mode = match self.delete_mode(&req, &bucket).await {
+            Ok(mode) => mode,
+            Err(rejection) => return self.delete_rejection_response(&rejection),
+        };
+        let actor = self.delete_actor(&req);
+
         // 读取请求体XML
         let body_bytes = Self::read_body(req).await?;
         let body_str = String::from_utf8_lossy(&body_bytes);
@@ -28,7 +35,15 @@ impl S3Service {
         // 批量删除对象
         for key in keys {
             let file_id = format!("{}/{}", bucket, key);
-            match self.storage.delete_file(&file_id).await {
+            match crate::storage::delete_with_mode(
+                &self.storage,
+                &self.delete_policy,
+                &file_id,
+                mode,
+                &actor,
+            )
+            .await
+            {
                 Ok(_) => {
                     // 发送删除事件
                     let mut event = FileEvent::new(EventType::Deleted, file_id.clone(), None);
@@ -38,9 +53,11 @@ impl S3Service {
                     }
                     deleted.push(key);
                 }
+                // 对象不存在时与单个删除一样视为成功
+                Err(silent_storage::StorageError::FileNotFound(_)) => deleted.push(key),
                 Err(e) => {
                     debug!("删除失败: {} - {}", key, e);
-                    errors.push((key, "InternalError", e.to_string()));
+                    errors.push((key, Self::storage_error_code(&e), e.to_string()));
                 }
             }
         }
@@ -51,11 +51,14 @@ impl S3Service {
         let mut contents = Vec::new();
         for key in object_keys.iter().take(max_keys) {
             let file_id = format!("{}/{}", bucket, key);
-            if let Ok(metadata) = self.storage.get_metadata(&file_id).await {
+            if let (Ok(metadata), Ok(etag)) = (
+                self.storage.get_metadata(&file_id).await,
+                self.storage.content_hash(&file_id).await,
+            ) {
                 contents.push(S3Object {
                     key: key.clone(),
                     last_modified: metadata.modified_at.and_utc(),
-                    etag: metadata.hash,
+                    etag,
                     size: metadata.size,
                 });
             }
@@ -126,11 +129,14 @@ impl S3Service {
         let mut contents = Vec::new();
         for key in object_keys.iter().take(max_keys) {
             let file_id = format!("{}/{}", bucket, key);
-            if let Ok(metadata) = self.storage.get_metadata(&file_id).await {
+            if let (Ok(metadata), Ok(etag)) = (
+                self.storage.get_metadata(&file_id).await,
+                self.storage.content_hash(&file_id).await,
+            ) {
                 contents.push(S3Object {
                     key: key.clone(),
                     last_modified: metadata.modified_at.and_utc(),
-                    etag: metadata.hash,
+                    etag,
                     size: metadata.size,
                 });
             }
@@ -1,3 +1,4 @@
+use crate::error::status_for;
 use crate::s3::models::{MultipartUpload, PartInfo};
 use crate::s3::service::S3Service;
 use chrono::Utc;
@@ -185,14 +186,11 @@ impl S3Service {
         // 保存合并后的对象
         let file_id = format!("{}/{}", bucket, key);
         let metadata = self.storage.save_file(&file_id, &all).await.map_err(|e| {
-            SilentError::business_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("合并分片失败: {}", e),
-            )
+            SilentError::business_error(status_for(&e), format!("合并分片失败: {}", e))
         })?;
 
         // 返回XML响应（与 S3 兼容）
-        let etag = format!("\"{}\"", metadata.hash);
+        let etag = self.entity_tag(&file_id).await?;
         let last_modified = metadata.modified_at.and_utc().to_rfc3339();
         let xml = format!(
             "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
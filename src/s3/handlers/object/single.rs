@@ -1,9 +1,26 @@
-use crate::models::{EventType, FileEvent};
-use crate::s3::service::S3Service;
+use crate::error::status_for;
+use crate::models::{EventType, FileEvent, FileMetadata};
+use crate::s3::checksum::{ChecksumError, RequestedChecksum, requested_checksum};
+use crate::s3::idempotency::{IDEMPOTENCY_HEADER, body_hash};
+use crate::s3::service::{RangeRequest, S3Service};
 use http::StatusCode;
 use silent::prelude::*;
 use silent_nas_core::StorageManagerTrait;
-use tracing::debug;
+use silent_storage::FileStat;
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+/// 保存对象失败时的响应状态
+fn save_error(e: silent_storage::StorageError) -> SilentError {
+    let status = match e {
+        silent_storage::StorageError::Busy(_) | silent_storage::StorageError::Maintenance(_) => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        silent_storage::StorageError::FileTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    SilentError::business_error(status, format!("保存文件失败: {}", e))
+}
 
 #[allow(clippy::collapsible_if)]
 impl S3Service {
@@ -26,12 +43,62 @@ impl S3Service {
 
         // 使用bucket/key组合作file_id
         let file_id = format!("{}/{}", bucket, key);
+        self.put_object_at(&file_id, req).await
+    }
+
+    /// 写入指定对象（PutObject 路径参数解析之后的部分）
+    ///
+    /// 带 `Idempotency-Key` 头时，令牌有效期内的重复请求不再写入，
+    /// 直接返回首次成功的 ETag；重复请求的请求体与首次不同时返回 400。
+    /// 带 `x-amz-checksum-*` 头时按声明的算法校验请求体，不一致返回 400 BadDigest。
+    pub(crate) async fn put_object_at(
+        &self,
+        file_id: &str,
+        req: Request,
+    ) -> silent::Result<Response> {
+        let file_id = file_id.to_string();
+
+        let checksum = match requested_checksum(req.headers()) {
+            Ok(checksum) => checksum,
+            Err(e) => return self.checksum_error_response(e),
+        };
+
+        // 幂等令牌：先于条件请求检查，避免首次已成功的重试被 If-None-Match 拒绝
+        let token = req
+            .headers()
+            .get(IDEMPOTENCY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty() && self.idempotency.is_enabled())
+            .map(str::to_string);
+        let idempotency_guard = match &token {
+            Some(token) => Some(self.idempotency.acquire(&file_id, token).await),
+            None => None,
+        };
+        if let Some(done) = idempotency_guard.as_ref().and_then(|g| g.completed()) {
+            let body_bytes = Self::read_body(req).await?;
+            if body_hash(&body_bytes) != done.body_hash {
+                return self.error_response(
+                    StatusCode::BAD_REQUEST,
+                    "IdempotentParameterMismatch",
+                    "The request body does not match the original request for this idempotency key",
+                );
+            }
+            debug!("PutObject 幂等重放: file_id={}", file_id);
+            let mut resp = Self::put_response(&done.etag);
+            if let Some(checksum) = &checksum {
+                match checksum.verify(&body_bytes) {
+                    Ok(value) => Self::insert_checksum(&mut resp, checksum, &value),
+                    Err(e) => return self.checksum_error_response(e),
+                }
+            }
+            return Ok(resp);
+        }
 
         // 检查条件请求头 - If-Match
         if let Some(if_match) = req.headers().get("If-Match") {
             if let Ok(header_value) = if_match.to_str() {
-                if let Ok(existing_meta) = self.storage.get_metadata(&file_id).await {
-                    let etag = format!("\"{}\"", existing_meta.hash);
+                if let Ok(etag) = self.storage.entity_tag(&file_id).await {
                     if header_value != "*" && !header_value.split(',').any(|tag| tag.trim() == etag)
                     {
                         return self.error_response(
@@ -53,8 +120,7 @@ impl S3Service {
         // 检查条件请求头 - If-None-Match
         if let Some(if_none_match) = req.headers().get("If-None-Match") {
             if let Ok(header_value) = if_none_match.to_str() {
-                if let Ok(existing_meta) = self.storage.get_metadata(&file_id).await {
-                    let etag = format!("\"{}\"", existing_meta.hash);
+                if let Ok(etag) = self.storage.entity_tag(&file_id).await {
                     if header_value == "*" || header_value.split(',').any(|tag| tag.trim() == etag)
                     {
                         return self.error_response(
@@ -67,41 +133,193 @@ impl S3Service {
             }
         }
 
+        // 用户自定义元数据（x-amz-meta-*）
+        let user_metadata = Self::parse_user_metadata(req.headers());
+        if Self::user_metadata_too_large(&user_metadata) {
+            return self.error_response(
+                StatusCode::BAD_REQUEST,
+                "MetadataTooLarge",
+                "Your metadata headers exceed the maximum allowed metadata size",
+            );
+        }
+
+        // 秒传：已有相同内容时引用已有块，不读取请求体；
+        // 结果与内容一一对应，重试自然幂等，无需记录幂等令牌。
+        // 声明了附加校验和时需要读取请求体校验，不走秒传
+        if let Some((hash, size)) =
+            crate::storage::instant_upload_request(req.headers()).filter(|_| checksum.is_none())
+        {
+            if let Some(resp) = self
+                .put_object_by_hash(&file_id, &hash, size, &req, user_metadata.clone())
+                .await?
+            {
+                return Ok(resp);
+            }
+        }
+
         // 读取请求体
         let body_bytes = Self::read_body(req).await?;
 
+        // 校验附加校验和，不一致时不写入
+        let checksum_value = match checksum.as_ref().map(|c| c.verify(&body_bytes)) {
+            Some(Err(e)) => return self.checksum_error_response(e),
+            Some(Ok(value)) => Some(value),
+            None => None,
+        };
+
         // 保存文件
+        let metadata = self
+            .store_object(&file_id, &body_bytes, user_metadata)
+            .await?;
+
+        // 发送事件
+        let mut event = FileEvent::new(EventType::Created, file_id.clone(), Some(metadata.clone()));
+        event.source_http_addr = Some(self.source_http_addr.clone());
+        if let Some(ref n) = self.notifier {
+            let _ = n.notify_created(event).await;
+        }
+
+        // 返回响应
+        let etag = self.entity_tag(&file_id).await?;
+        if let Some(guard) = idempotency_guard {
+            guard.complete(&body_bytes, etag.clone());
+        }
+        let mut resp = Self::put_response(&etag);
+        if let (Some(checksum), Some(value)) = (&checksum, &checksum_value) {
+            Self::insert_checksum(&mut resp, checksum, value);
+        }
+        Ok(resp)
+    }
+
+    /// 按整文件哈希秒传对象
+    ///
+    /// 未命中且请求体为空时返回 412（`x-instant-upload: false`），客户端改为普通上传；
+    /// 未命中但带有请求体时返回 `Ok(None)`，由调用方继续普通上传。
+    async fn put_object_by_hash(
+        &self,
+        file_id: &str,
+        hash: &str,
+        size: u64,
+        req: &Request,
+        user_metadata: HashMap<String, String>,
+    ) -> silent::Result<Option<Response>> {
         let metadata = self
             .storage
-            .save_file(&file_id, &body_bytes)
+            .save_file_by_hash(file_id, hash, size)
+            .await
+            .map_err(|e| {
+                SilentError::business_error(status_for(&e), format!("保存文件失败: {}", e))
+            })?;
+
+        let Some(mut metadata) = metadata else {
+            let body_empty = req
+                .headers()
+                .get(http::header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .is_some_and(|len| len == 0);
+            if !body_empty || size == 0 {
+                return Ok(None);
+            }
+            let mut resp = self.error_response(
+                StatusCode::PRECONDITION_FAILED,
+                "PreconditionFailed",
+                "No object with the given content hash, upload the content instead",
+            )?;
+            resp.headers_mut().insert(
+                crate::storage::INSTANT_UPLOAD_RESULT_HEADER,
+                http::HeaderValue::from_static("false"),
+            );
+            return Ok(Some(resp));
+        };
+        debug!("PutObject 秒传: file_id={} size={}", file_id, size);
+
+        self.storage
+            .set_user_metadata(file_id, user_metadata.clone())
             .await
             .map_err(|e| {
                 SilentError::business_error(
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("保存文件失败: {}", e),
+                    format!("保存用户元数据失败: {}", e),
                 )
             })?;
+        metadata.user_metadata = user_metadata;
 
-        // 发送事件
-        let mut event = FileEvent::new(EventType::Created, file_id.clone(), Some(metadata.clone()));
+        let mut event = FileEvent::new(EventType::Created, file_id.to_string(), Some(metadata));
         event.source_http_addr = Some(self.source_http_addr.clone());
         if let Some(ref n) = self.notifier {
             let _ = n.notify_created(event).await;
         }
 
-        // 返回响应
-        let mut resp = Response::empty();
+        let etag = self.entity_tag(file_id).await?;
+        let mut resp = Self::put_response(&etag);
         resp.headers_mut().insert(
-            "ETag",
-            http::HeaderValue::from_str(&format!("\"{}\"", metadata.hash)).unwrap(),
+            crate::storage::INSTANT_UPLOAD_RESULT_HEADER,
+            http::HeaderValue::from_static("true"),
         );
+        Ok(Some(resp))
+    }
+
+    /// PutObject 成功响应
+    fn put_response(etag: &str) -> Response {
+        let mut resp = Response::empty();
+        resp.headers_mut()
+            .insert("ETag", http::HeaderValue::from_str(etag).unwrap());
         resp.headers_mut().insert(
             "x-amz-request-id",
             http::HeaderValue::from_static("silent-nas-001"),
         );
         resp.set_status(StatusCode::OK);
+        resp
+    }
 
-        Ok(resp)
+    /// 在响应中回显校验值
+    fn insert_checksum(resp: &mut Response, checksum: &RequestedChecksum, value: &str) {
+        if let Ok(value) = http::HeaderValue::from_str(value) {
+            resp.headers_mut()
+                .insert(checksum.algorithm.header_name(), value);
+        }
+    }
+
+    /// 校验和错误响应
+    fn checksum_error_response(&self, e: ChecksumError) -> silent::Result<Response> {
+        self.error_response(StatusCode::BAD_REQUEST, e.code(), &e.message())
+    }
+
+    /// 保存对象内容，并整体替换对象的用户自定义元数据
+    pub(crate) async fn store_object(
+        &self,
+        file_id: &str,
+        data: &[u8],
+        user_metadata: HashMap<String, String>,
+    ) -> silent::Result<FileMetadata> {
+        let metadata = self
+            .storage
+            .save_file(file_id, data)
+            .await
+            .map_err(save_error)?;
+        self.attach_user_metadata(file_id, metadata, user_metadata)
+            .await
+    }
+
+    /// 保存对象的用户自定义元数据
+    async fn attach_user_metadata(
+        &self,
+        file_id: &str,
+        mut metadata: FileMetadata,
+        user_metadata: HashMap<String, String>,
+    ) -> silent::Result<FileMetadata> {
+        self.storage
+            .set_user_metadata(file_id, user_metadata.clone())
+            .await
+            .map_err(|e| {
+                SilentError::business_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("保存用户元数据失败: {}", e),
+                )
+            })?;
+        metadata.user_metadata = user_metadata;
+        Ok(metadata)
     }
 
     /// GetObject - 获取对象
@@ -117,31 +335,17 @@ impl S3Service {
 
         let file_id = format!("{}/{}", bucket, key);
 
-        // 先获取元数据以支持条件请求
+        // 先获取元数据以支持条件请求（只读取文件索引）
         let metadata = self
             .storage
-            .get_metadata(&file_id)
+            .stat(&file_id)
             .await
             .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "NoSuchKey"))?;
-
-        // 检查If-None-Match
-        if let Some(if_none_match) = req.headers().get("If-None-Match") {
-            if let Ok(header_value) = if_none_match.to_str() {
-                let etag = format!("\"{}\"", metadata.hash);
-                if header_value == "*" || header_value.split(',').any(|tag| tag.trim() == etag) {
-                    let mut resp = Response::empty();
-                    resp.headers_mut()
-                        .insert("ETag", http::HeaderValue::from_str(&etag).unwrap());
-                    resp.set_status(StatusCode::NOT_MODIFIED);
-                    return Ok(resp);
-                }
-            }
-        }
+        let etag = self.entity_tag(&file_id).await?;
 
         // 检查If-Match
         if let Some(if_match) = req.headers().get("If-Match") {
             if let Ok(header_value) = if_match.to_str() {
-                let etag = format!("\"{}\"", metadata.hash);
                 if header_value != "*" && !header_value.split(',').any(|tag| tag.trim() == etag) {
                     return self.error_response(
                         StatusCode::PRECONDITION_FAILED,
@@ -152,34 +356,36 @@ impl S3Service {
             }
         }
 
-        // 检查If-Modified-Since
-        if let Some(if_modified_since) = req.headers().get("If-Modified-Since") {
-            if let Ok(header_value) = if_modified_since.to_str() {
-                if let Ok(since_time) = chrono::DateTime::parse_from_rfc2822(header_value) {
-                    let file_modified = metadata.modified_at.and_utc();
-                    if file_modified <= since_time {
-                        let mut resp = Response::empty();
-                        resp.headers_mut().insert(
-                            "Last-Modified",
-                            http::HeaderValue::from_str(&file_modified.to_rfc2822()).unwrap(),
-                        );
-                        resp.set_status(StatusCode::NOT_MODIFIED);
-                        return Ok(resp);
-                    }
-                }
-            }
+        // 检查If-None-Match / If-Modified-Since（前者存在时忽略后者，修改时间按秒比较）
+        let if_none_match = req
+            .headers()
+            .get("If-None-Match")
+            .and_then(|h| h.to_str().ok());
+        let if_modified_since = req
+            .headers()
+            .get("If-Modified-Since")
+            .and_then(|h| h.to_str().ok());
+        if metadata.not_modified(&etag, if_none_match, if_modified_since) {
+            let mut resp = Response::empty();
+            resp.headers_mut()
+                .insert("ETag", http::HeaderValue::from_str(&etag).unwrap());
+            resp.headers_mut().insert(
+                "Last-Modified",
+                http::HeaderValue::from_str(&metadata.last_modified_http()).unwrap(),
+            );
+            resp.set_status(StatusCode::NOT_MODIFIED);
+            return Ok(resp);
         }
 
-        // 读取完整文件
-        let data = self
-            .storage
-            .read_file(&file_id)
-            .await
-            .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "NoSuchKey"))?;
-        let file_size = data.len() as u64;
+        let file_size = metadata.size;
 
         // 检查Range请求
-        let range_header = req.headers().get("range").and_then(|v| v.to_str().ok());
+        let range_request = req
+            .headers()
+            .get("range")
+            .and_then(|v| v.to_str().ok())
+            .map(|range_str| Self::parse_range(range_str, file_size))
+            .unwrap_or(RangeRequest::Ignored);
 
         let mut resp = Response::empty();
         resp.headers_mut().insert(
@@ -188,13 +394,11 @@ impl S3Service {
         );
 
         // 添加ETag和Last-Modified
-        resp.headers_mut().insert(
-            "ETag",
-            http::HeaderValue::from_str(&format!("\"{}\"", metadata.hash)).unwrap(),
-        );
+        resp.headers_mut()
+            .insert("ETag", http::HeaderValue::from_str(&etag).unwrap());
         resp.headers_mut().insert(
             "Last-Modified",
-            http::HeaderValue::from_str(&metadata.modified_at.and_utc().to_rfc2822()).unwrap(),
+            http::HeaderValue::from_str(&FileStat::http_date(&metadata.modified_at)).unwrap(),
         );
 
         resp.headers_mut().insert(
@@ -204,50 +408,127 @@ impl S3Service {
         resp.headers_mut()
             .insert("Accept-Ranges", http::HeaderValue::from_static("bytes"));
 
-        // 添加用户元数据支持（示例）
-        Self::add_user_metadata(&mut resp);
+        Self::add_user_metadata(&mut resp, &metadata.user_metadata);
 
-        // 处理Range请求
-        if let Some(range_str) = range_header {
-            if let Some((start, end)) = Self::parse_range(range_str, file_size) {
-                let range_data = data[start..=end].to_vec();
-                let range_len = range_data.len();
+        self.write_object_body(&file_id, file_size, range_request, resp)
+            .await
+    }
 
+    /// 写入对象内容
+    ///
+    /// 单个范围返回 206 与 `Content-Range`；多个范围返回 `multipart/byteranges`；
+    /// 范围均无法满足时返回 416；没有有效 Range 头时返回完整对象。
+    /// 范围请求只读取与范围重叠的块，不加载整个对象。
+    pub(crate) async fn write_object_body(
+        &self,
+        file_id: &str,
+        file_size: u64,
+        range_request: RangeRequest,
+        mut resp: Response,
+    ) -> silent::Result<Response> {
+        let ranges = match range_request {
+            RangeRequest::Ignored => {
+                // 正常完整响应，流式读取
+                let version_id = self
+                    .storage
+                    .current_version_id(file_id)
+                    .await
+                    .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "NoSuchKey"))?;
+                let reader = self.storage.open_read(&version_id).await.map_err(|e| {
+                    SilentError::business_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("读取对象失败: {}", e),
+                    )
+                })?;
                 resp.headers_mut().insert(
                     http::header::CONTENT_LENGTH,
-                    http::HeaderValue::from_str(&range_len.to_string()).unwrap(),
-                );
-                resp.headers_mut().insert(
-                    "Content-Range",
-                    http::HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, file_size))
-                        .unwrap(),
+                    http::HeaderValue::from(file_size),
                 );
-                resp.set_body(full(range_data));
-                resp.set_status(StatusCode::PARTIAL_CONTENT);
-
-                debug!("Range request: {}-{}/{}", start, end, file_size);
-            } else {
-                // Range格式无效，返回416
+                resp.set_body(stream_body(crate::storage::read_stream(reader)));
+                resp.set_status(StatusCode::OK);
+                return Ok(resp);
+            }
+            RangeRequest::Unsatisfiable => {
                 resp.headers_mut().insert(
                     "Content-Range",
                     http::HeaderValue::from_str(&format!("bytes */{}", file_size)).unwrap(),
                 );
+                resp.headers_mut()
+                    .insert(http::header::CONTENT_LENGTH, http::HeaderValue::from(0));
                 resp.set_status(StatusCode::RANGE_NOT_SATISFIABLE);
                 return Ok(resp);
             }
+            RangeRequest::Ranges(ranges) => ranges,
+        };
+
+        let version_id = self
+            .storage
+            .get_file_info(file_id)
+            .await
+            .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "NoSuchKey"))?
+            .latest_version_id;
+
+        let body = if let [(start, end)] = ranges[..] {
+            resp.headers_mut().insert(
+                "Content-Range",
+                http::HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, file_size))
+                    .unwrap(),
+            );
+            debug!("Range request: {}-{}/{}", start, end, file_size);
+            self.read_object_range(&version_id, start, end).await?
         } else {
-            // 正常完整响应
+            let boundary = scru128::new_string();
+            let mut body = Vec::new();
+            for (start, end) in ranges {
+                body.extend_from_slice(
+                    format!(
+                        "--{}\r\nContent-Type: binary/octet-stream\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                        boundary, start, end, file_size
+                    )
+                    .as_bytes(),
+                );
+                body.extend(self.read_object_range(&version_id, start, end).await?);
+                body.extend_from_slice(b"\r\n");
+            }
+            body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
             resp.headers_mut().insert(
-                http::header::CONTENT_LENGTH,
-                http::HeaderValue::from_str(&data.len().to_string()).unwrap(),
+                http::header::CONTENT_TYPE,
+                http::HeaderValue::from_str(&format!(
+                    "multipart/byteranges; boundary={}",
+                    boundary
+                ))
+                .unwrap(),
             );
-            resp.set_body(full(data));
-            resp.set_status(StatusCode::OK);
-        }
+            body
+        };
 
+        resp.headers_mut().insert(
+            http::header::CONTENT_LENGTH,
+            http::HeaderValue::from_str(&body.len().to_string()).unwrap(),
+        );
+        resp.set_body(full(body));
+        resp.set_status(StatusCode::PARTIAL_CONTENT);
         Ok(resp)
     }
 
+    /// 读取对象的闭区间范围 `[start, end]`
+    async fn read_object_range(
+        &self,
+        version_id: &str,
+        start: u64,
+        end: u64,
+    ) -> silent::Result<Vec<u8>> {
+        self.storage
+            .read_version_range(version_id, start, end - start + 1)
+            .await
+            .map_err(|e| {
+                SilentError::business_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("读取对象范围失败: {}", e),
+                )
+            })
+    }
+
     /// CopyObject - 复制对象
     pub async fn copy_object(&self, req: Request) -> silent::Result<Response> {
         if !self.verify_request(&req) {
@@ -256,7 +537,16 @@ impl S3Service {
 
         let dest_bucket: String = req.get_path_params("bucket")?;
         let dest_key: String = req.get_path_params("key")?;
+        let dest_file_id = format!("{}/{}", dest_bucket, dest_key);
+        self.copy_object_at(&dest_file_id, &req).await
+    }
 
+    /// 服务端复制对象：目标直接引用源对象的块，不读写对象数据（跨 bucket 同样适用）
+    pub(crate) async fn copy_object_at(
+        &self,
+        dest_file_id: &str,
+        req: &Request,
+    ) -> silent::Result<Response> {
         // 获取源对象路径 from x-amz-copy-source header
         let copy_source = req
             .headers()
@@ -266,7 +556,10 @@ impl S3Service {
                 SilentError::business_error(StatusCode::BAD_REQUEST, "缺少x-amz-copy-source头")
             })?;
 
-        // 解析源路径 (格式: /source-bucket/source-key)
+        // 解析源路径 (格式: /source-bucket/source-key，可能经过 URL 编码)
+        let copy_source = urlencoding::decode(copy_source)
+            .map(|s| s.into_owned())
+            .unwrap_or_else(|_| copy_source.to_string());
         let source_path = copy_source.trim_start_matches('/');
         let source_parts: Vec<&str> = source_path.splitn(2, '/').collect();
 
@@ -279,31 +572,57 @@ impl S3Service {
         }
 
         let source_file_id = format!("{}/{}", source_parts[0], source_parts[1]);
-        let dest_file_id = format!("{}/{}", dest_bucket, dest_key);
 
         debug!("CopyObject: from {} to {}", source_file_id, dest_file_id);
 
-        // 读取源文件
-        let data = self
+        // 元数据指令：COPY（默认）沿用源对象元数据，REPLACE 使用请求头中的元数据
+        let replace_metadata = req
+            .headers()
+            .get("x-amz-metadata-directive")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.eq_ignore_ascii_case("REPLACE"));
+        let user_metadata = if replace_metadata {
+            let user_metadata = Self::parse_user_metadata(req.headers());
+            if Self::user_metadata_too_large(&user_metadata) {
+                return self.error_response(
+                    StatusCode::BAD_REQUEST,
+                    "MetadataTooLarge",
+                    "Your metadata headers exceed the maximum allowed metadata size",
+                );
+            }
+            Some(user_metadata)
+        } else {
+            None
+        };
+
+        let source_metadata = self
             .storage
-            .read_file(&source_file_id)
+            .get_metadata(&source_file_id)
             .await
             .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "源对象不存在"))?;
 
-        // 保存到目标位置
         let metadata = self
             .storage
-            .save_file(&dest_file_id, &data)
+            .copy_file(&source_file_id, dest_file_id)
             .await
-            .map_err(|e| {
-                SilentError::business_error(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("复制失败: {}", e),
-                )
+            .map_err(|e| match e {
+                silent_storage::StorageError::FileNotFound(_) => {
+                    SilentError::business_error(StatusCode::NOT_FOUND, "源对象不存在")
+                }
+                e => save_error(e),
             })?;
+        let user_metadata = user_metadata.unwrap_or(source_metadata.user_metadata);
+        let metadata = self
+            .attach_user_metadata(dest_file_id, metadata, user_metadata)
+            .await?;
+        let etag = self.entity_tag(dest_file_id).await?;
 
         // 发送事件
-        let mut event = FileEvent::new(EventType::Created, dest_file_id, Some(metadata.clone()));
+        let mut event = FileEvent::new(
+            EventType::Created,
+            dest_file_id.to_string(),
+            Some(metadata.clone()),
+        );
         event.source_http_addr = Some(self.source_http_addr.clone());
         if let Some(ref n) = self.notifier {
             let _ = n.notify_created(event).await;
@@ -311,7 +630,6 @@ impl S3Service {
 
         // 生成CopyObjectResult XML响应
         let last_modified = metadata.modified_at.and_utc().to_rfc3339();
-        let etag = format!("\"{}\"", metadata.hash);
 
         let xml = format!(
             "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
@@ -347,17 +665,47 @@ impl S3Service {
         let key: String = req.get_path_params("key")?;
 
         debug!("DeleteObject: bucket={}, key={}", bucket, key);
+        self.delete_object_at(&bucket, &key, &req).await
+    }
 
+    /// 删除对象（按删除语义移入回收站或永久删除，见 [`S3Service::delete_mode`]）
+    pub(crate) async fn delete_object_at(
+        &self,
+        bucket: &str,
+        key: &str,
+        req: &Request,
+    ) -> silent::Result<Response> {
         let file_id = format!("{}/{}", bucket, key);
+        let mode = match self.delete_mode(req, bucket).await {
+            Ok(mode) => mode,
+            Err(rejection) => return self.delete_rejection_response(&rejection),
+        };
+        let delete_marker = mode == crate::storage::DeleteMode::Soft
+            && self.versioning_manager.is_versioning_enabled(bucket).await;
 
-        // 删除文件
-        let _ = self.storage.delete_file(&file_id).await;
-
-        // 发送事件
-        let mut event = FileEvent::new(EventType::Deleted, file_id, None);
-        event.source_http_addr = Some(self.source_http_addr.clone());
-        if let Some(ref n) = self.notifier {
-            let _ = n.notify_deleted(event).await;
+        // 删除文件（对象不存在时同样返回成功；其他失败如保留期、只读副本、维护模式按错误类型返回）
+        match crate::storage::delete_with_mode(
+            &self.storage,
+            &self.delete_policy,
+            &file_id,
+            mode,
+            &self.delete_actor(req),
+        )
+        .await
+        {
+            Ok(_) => {
+                // 只为实际发生的删除发送事件
+                let mut event = FileEvent::new(EventType::Deleted, file_id, None);
+                event.source_http_addr = Some(self.source_http_addr.clone());
+                if let Some(ref n) = self.notifier {
+                    let _ = n.notify_deleted(event).await;
+                }
+            }
+            Err(silent_storage::StorageError::FileNotFound(_)) => {}
+            Err(e) => {
+                warn!("删除对象失败: {} - {}", file_id, e);
+                return self.storage_error_response(&e);
+            }
         }
 
         let mut resp = Response::empty();
@@ -365,6 +713,16 @@ impl S3Service {
             "x-amz-request-id",
             http::HeaderValue::from_static("silent-nas-003"),
         );
+        resp.headers_mut().insert(
+            crate::storage::DELETE_MODE_HEADER,
+            http::HeaderValue::from_static(mode.as_str()),
+        );
+        if delete_marker {
+            resp.headers_mut().insert(
+                "x-amz-delete-marker",
+                http::HeaderValue::from_static("true"),
+            );
+        }
         resp.set_status(StatusCode::NO_CONTENT);
 
         Ok(resp)
@@ -380,44 +738,438 @@ impl S3Service {
         debug!("HeadObject: bucket={}, key={}", bucket, key);
 
         let file_id = format!("{}/{}", bucket, key);
+        self.head_object_response(&file_id).await
+    }
 
-        // 获取元数据
-        let metadata = self
+    /// 生成 HeadObject 响应（对象元数据与用户自定义元数据头）
+    pub(crate) async fn head_object_response(&self, file_id: &str) -> silent::Result<Response> {
+        // 只读取文件索引，不查询版本记录
+        let stat = self
             .storage
-            .get_metadata(&file_id)
+            .stat(file_id)
             .await
             .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "NoSuchKey"))?;
 
         let mut resp = Response::empty();
         resp.headers_mut().insert(
             http::header::CONTENT_LENGTH,
-            http::HeaderValue::from_str(&metadata.size.to_string()).unwrap(),
-        );
-        resp.headers_mut().insert(
-            "ETag",
-            http::HeaderValue::from_str(&format!("\"{}\"", metadata.hash)).unwrap(),
+            http::HeaderValue::from_str(&stat.size.to_string()).unwrap(),
         );
+        let etag = self.entity_tag(file_id).await?;
+        resp.headers_mut()
+            .insert("ETag", http::HeaderValue::from_str(&etag).unwrap());
         resp.headers_mut().insert(
             "Last-Modified",
-            http::HeaderValue::from_str(
-                &metadata
-                    .modified_at
-                    .and_utc()
-                    .format("%a, %d %b %Y %H:%M:%S GMT")
-                    .to_string(),
-            )
-            .unwrap(),
+            http::HeaderValue::from_str(&stat.last_modified_http()).unwrap(),
         );
         resp.headers_mut().insert(
             "x-amz-request-id",
             http::HeaderValue::from_static("silent-nas-004"),
         );
 
-        // 添加用户元数据支持（示例）
-        Self::add_user_metadata(&mut resp);
+        Self::add_user_metadata(&mut resp, &stat.user_metadata);
 
         resp.set_status(StatusCode::OK);
 
         Ok(resp)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s3::versioning::VersioningManager;
+    use crate::storage::{IncrementalConfig, StorageManager};
+    use crate::test_util::test_data;
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    async fn create_service() -> (S3Service, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(
+            temp_dir.path().to_path_buf(),
+            4096,
+            IncrementalConfig::default(),
+        );
+        storage.init().await.unwrap();
+        let service = S3Service::new(
+            Arc::new(storage),
+            None,
+            None,
+            String::new(),
+            Arc::new(VersioningManager::new()),
+        );
+        (service, temp_dir)
+    }
+
+    fn put_request(body: &'static [u8], token: Option<&str>) -> Request {
+        let mut builder = http::Request::builder().method("PUT").uri("/bucket/key");
+        if let Some(token) = token {
+            builder = builder.header(IDEMPOTENCY_HEADER, token);
+        }
+        let (parts, _) = builder.body(()).unwrap().into_parts();
+        Request::from_parts(parts, ReqBody::Once(bytes::Bytes::from_static(body)))
+    }
+
+    fn put_request_with_checksum(body: &'static [u8], crc32c: &'static str) -> Request {
+        let (parts, _) = http::Request::builder()
+            .method("PUT")
+            .uri("/bucket/key")
+            .header("x-amz-sdk-checksum-algorithm", "CRC32C")
+            .header("x-amz-checksum-crc32c", crc32c)
+            .body(())
+            .unwrap()
+            .into_parts();
+        Request::from_parts(parts, ReqBody::Once(bytes::Bytes::from_static(body)))
+    }
+
+    async fn get_range(
+        service: &S3Service,
+        file_id: &str,
+        size: u64,
+        range: &str,
+    ) -> (StatusCode, http::HeaderMap, Vec<u8>) {
+        let request = S3Service::parse_range(range, size);
+        let mut resp = service
+            .write_object_body(file_id, size, request, Response::empty())
+            .await
+            .unwrap();
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        (resp.status(), resp.headers().clone(), body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_put_with_same_idempotency_token_creates_one_version() {
+        let (service, _temp) = create_service().await;
+        let file_id = "bucket/report.csv";
+
+        let first = service
+            .put_object_at(file_id, put_request(b"v1", Some("token-1")))
+            .await
+            .unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        // 重试到达前已有其他客户端写入新内容
+        service
+            .put_object_at(file_id, put_request(b"v2", None))
+            .await
+            .unwrap();
+
+        // 重试不再写入，返回首次的 ETag，也不覆盖新内容
+        let retry = service
+            .put_object_at(file_id, put_request(b"v1", Some("token-1")))
+            .await
+            .unwrap();
+        assert_eq!(retry.status(), StatusCode::OK);
+        assert_eq!(retry.headers()["ETag"], first.headers()["ETag"]);
+        assert_eq!(service.storage.read_file(file_id).await.unwrap(), b"v2");
+        assert_eq!(
+            service
+                .storage
+                .list_file_versions(file_id)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // 同一令牌携带不同内容被拒绝
+        let mismatch = service
+            .put_object_at(file_id, put_request(b"v3", Some("token-1")))
+            .await
+            .unwrap();
+        assert_eq!(mismatch.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_delete_object_maps_storage_errors() {
+        let (service, _temp) = create_service().await;
+        let file_id = "bucket/kept.txt";
+        service
+            .put_object_at(file_id, put_request(b"kept", None))
+            .await
+            .unwrap();
+        let delete_request = || {
+            let (parts, _) = http::Request::builder()
+                .method("DELETE")
+                .uri("/bucket/kept.txt")
+                .body(())
+                .unwrap()
+                .into_parts();
+            Request::from_parts(parts, ReqBody::Empty)
+        };
+
+        // 不存在的对象同样删除成功
+        let resp = service
+            .delete_object_at("bucket", "missing.txt", &delete_request())
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+
+        // 维护模式下删除失败，返回对应的错误而不是成功
+        service.storage.enter_maintenance().await.unwrap();
+        let mut resp = service
+            .delete_object_at("bucket", "kept.txt", &delete_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>ServiceUnavailable</Code>"));
+        assert_eq!(service.storage.read_file(file_id).await.unwrap(), b"kept");
+    }
+
+    #[tokio::test]
+    async fn test_put_object_with_valid_crc32c_echoes_checksum() {
+        let (service, _temp) = create_service().await;
+        let file_id = "bucket/checked.txt";
+
+        let resp = service
+            .put_object_at(
+                file_id,
+                put_request_with_checksum(b"hello checksum", "CRPpGg=="),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["x-amz-checksum-crc32c"], "CRPpGg==");
+        assert_eq!(
+            service.storage.read_file(file_id).await.unwrap(),
+            b"hello checksum"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_put_object_with_mismatched_crc32c_rejected() {
+        let (service, _temp) = create_service().await;
+        let file_id = "bucket/corrupted.txt";
+
+        // 校验值为 "123456789" 的 CRC32C，与请求体不符
+        let mut resp = service
+            .put_object_at(
+                file_id,
+                put_request_with_checksum(b"hello checksum", "4waSgw=="),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>BadDigest</Code>"));
+        assert!(resp.headers().get("x-amz-checksum-crc32c").is_none());
+
+        // 未写入任何内容
+        assert!(service.storage.read_file(file_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_object_single_range() {
+        let (service, _temp) = create_service().await;
+        let data = test_data(64 * 1024, 0);
+        service
+            .storage
+            .save_file("media/a.bin", &data)
+            .await
+            .unwrap();
+
+        let (status, headers, body) = get_range(
+            &service,
+            "media/a.bin",
+            data.len() as u64,
+            "bytes=1000-20999",
+        )
+        .await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers["Content-Range"], "bytes 1000-20999/65536");
+        assert_eq!(headers[http::header::CONTENT_LENGTH], "20000");
+        assert_eq!(body, &data[1000..21_000]);
+    }
+
+    #[tokio::test]
+    async fn test_get_object_open_ended_range() {
+        let (service, _temp) = create_service().await;
+        let data = test_data(64 * 1024, 0);
+        service
+            .storage
+            .save_file("media/b.bin", &data)
+            .await
+            .unwrap();
+
+        let (status, headers, body) =
+            get_range(&service, "media/b.bin", data.len() as u64, "bytes=60000-").await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers["Content-Range"], "bytes 60000-65535/65536");
+        assert_eq!(body, &data[60_000..]);
+    }
+
+    #[tokio::test]
+    async fn test_get_object_out_of_bounds_range() {
+        let (service, _temp) = create_service().await;
+        let data = test_data(1024, 0);
+        service
+            .storage
+            .save_file("media/c.bin", &data)
+            .await
+            .unwrap();
+
+        let (status, headers, body) = get_range(
+            &service,
+            "media/c.bin",
+            data.len() as u64,
+            "bytes=5000-6000",
+        )
+        .await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(headers["Content-Range"], "bytes */1024");
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_object_multi_range() {
+        let (service, _temp) = create_service().await;
+        let data = test_data(16 * 1024, 0);
+        service
+            .storage
+            .save_file("media/d.bin", &data)
+            .await
+            .unwrap();
+
+        let (status, headers, body) = get_range(
+            &service,
+            "media/d.bin",
+            data.len() as u64,
+            "bytes=0-9,8000-8009",
+        )
+        .await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        let content_type = headers[http::header::CONTENT_TYPE].to_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+
+        let mut expected = Vec::new();
+        for (start, end) in [(0usize, 9usize), (8000, 8009)] {
+            expected.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Type: binary/octet-stream\r\nContent-Range: bytes {}-{}/16384\r\n\r\n",
+                    boundary, start, end
+                )
+                .as_bytes(),
+            );
+            expected.extend_from_slice(&data[start..=end]);
+            expected.extend_from_slice(b"\r\n");
+        }
+        expected.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        assert_eq!(body, expected);
+    }
+
+    #[tokio::test]
+    async fn test_put_object_user_metadata_returned_on_head() {
+        let (service, _temp) = create_service().await;
+        let mut headers = http::HeaderMap::new();
+        headers.insert("X-Amz-Meta-Author", http::HeaderValue::from_static("alice"));
+        headers.insert("x-amz-meta-project", http::HeaderValue::from_static("nas"));
+        headers.insert("content-type", http::HeaderValue::from_static("text/plain"));
+
+        let user_metadata = S3Service::parse_user_metadata(&headers);
+        assert_eq!(user_metadata.len(), 2);
+        service
+            .store_object("docs/a.txt", b"hello", user_metadata)
+            .await
+            .unwrap();
+
+        let resp = service.head_object_response("docs/a.txt").await.unwrap();
+        assert_eq!(resp.headers()["x-amz-meta-author"], "alice");
+        assert_eq!(resp.headers()["x-amz-meta-project"], "nas");
+
+        // 新版本保留元数据
+        service
+            .storage
+            .save_file("docs/a.txt", b"hello again")
+            .await
+            .unwrap();
+        let resp = service.head_object_response("docs/a.txt").await.unwrap();
+        assert_eq!(resp.headers()["x-amz-meta-author"], "alice");
+
+        // 超过大小上限
+        let oversized = HashMap::from([("k".to_string(), "v".repeat(3000))]);
+        assert!(S3Service::user_metadata_too_large(&oversized));
+    }
+
+    fn copy_request(source: &str, headers: &[(&str, &str)]) -> Request {
+        let mut builder = http::Request::builder()
+            .method("PUT")
+            .uri("/dst/key")
+            .header("x-amz-copy-source", source);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        let (parts, _) = builder.body(()).unwrap().into_parts();
+        Request::from_parts(parts, ReqBody::Empty)
+    }
+
+    #[tokio::test]
+    async fn test_copy_object_shares_chunks_across_buckets() {
+        let (service, _temp) = create_service().await;
+        let data = test_data(512 * 1024, 0);
+        let user_metadata = HashMap::from([("author".to_string(), "alice".to_string())]);
+        service
+            .store_object("src/big file.bin", &data, user_metadata)
+            .await
+            .unwrap();
+        let before = service.storage.get_deduplication_stats().await.unwrap();
+
+        // 默认 COPY：沿用源对象元数据，源路径经过 URL 编码
+        let resp = service
+            .copy_object_at("dst/copy.bin", &copy_request("/src/big%20file.bin", &[]))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // 没有写入任何块，只增加了块引用
+        let after = service.storage.get_deduplication_stats().await.unwrap();
+        assert_eq!(after.new_chunks, before.new_chunks);
+        assert_eq!(after.stored_size, before.stored_size);
+        assert_eq!(after.total_chunks, before.total_chunks * 2);
+
+        assert_eq!(
+            service.storage.read_file("dst/copy.bin").await.unwrap(),
+            data
+        );
+        assert_eq!(
+            service.storage.read_file("src/big file.bin").await.unwrap(),
+            data
+        );
+        let resp = service.head_object_response("dst/copy.bin").await.unwrap();
+        assert_eq!(resp.headers()["x-amz-meta-author"], "alice");
+
+        // REPLACE：使用请求头中的元数据
+        service
+            .copy_object_at(
+                "dst/replaced.bin",
+                &copy_request(
+                    "src/big file.bin",
+                    &[
+                        ("x-amz-metadata-directive", "REPLACE"),
+                        ("x-amz-meta-author", "bob"),
+                    ],
+                ),
+            )
+            .await
+            .unwrap();
+        let resp = service
+            .head_object_response("dst/replaced.bin")
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["x-amz-meta-author"], "bob");
+        assert_eq!(
+            service.storage.read_file("dst/replaced.bin").await.unwrap(),
+            data
+        );
+
+        // 源对象不存在
+        let err = service
+            .copy_object_at("dst/missing.bin", &copy_request("/src/missing.bin", &[]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -91,8 +91,8 @@ impl S3Service {
                                 hash: metadata.hash.clone(), // 使用当前文件的 hash
                                 created_at: version.created_at,
                                 is_current: version.is_current,
-                                author: None,
-                                comment: None,
+                                author: version.author,
+                                comment: version.comment,
                             },
                         ));
                     }
@@ -2,11 +2,12 @@ use crate::notify::EventNotifier;
 use crate::s3::auth::S3Auth;
 use crate::s3::service::S3Service;
 use crate::s3::versioning::VersioningManager;
-use crate::storage::StorageManager;
+use crate::storage::{DeletePolicy, StorageManager};
 use http::Method;
 use http::StatusCode;
 use silent::prelude::*;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::debug;
 
 /// 创建S3路由
@@ -16,14 +17,20 @@ pub fn create_s3_routes(
     auth: Option<S3Auth>,
     source_http_addr: String,
     versioning_manager: Arc<VersioningManager>,
+    idempotency_ttl: Duration,
+    delete_policy: DeletePolicy,
 ) -> Route {
-    let service = Arc::new(S3Service::new(
-        storage,
-        notifier,
-        auth,
-        source_http_addr,
-        versioning_manager,
-    ));
+    let service = Arc::new(
+        S3Service::new(
+            storage,
+            notifier,
+            auth,
+            source_http_addr,
+            versioning_manager,
+        )
+        .with_idempotency_ttl(idempotency_ttl)
+        .with_delete_policy(delete_policy),
+    );
 
     // Bucket操作 - 合并GET和HEAD
     let service_bucket = service.clone();
@@ -0,0 +1,144 @@
+//! S3 PUT 幂等令牌
+//!
+//! 客户端在超时后重发 PUT 时，首次请求可能已在服务端成功，重发会再创建一个版本，
+//! 还可能覆盖期间其他客户端写入的新内容。带 `Idempotency-Key` 头的 PUT 以
+//! `(对象, 令牌)` 为键记录首次成功的结果，令牌有效期内的重复请求直接返回该结果。
+//!
+//! 仅凭内容哈希（如 `x-amz-content-sha256`）无法区分重试与有意的重复上传，
+//! 因此必须由客户端提供令牌；内容哈希只用于校验重试请求体与首次一致。
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OwnedMutexGuard;
+
+/// 携带幂等令牌的请求头
+pub(crate) const IDEMPOTENCY_HEADER: &str = "idempotency-key";
+
+/// 首次成功的 PUT 结果
+#[derive(Debug, Clone)]
+pub(crate) struct CompletedPut {
+    /// 请求体的 SHA-256（十六进制）
+    pub body_hash: String,
+    /// 返回给客户端的 ETag
+    pub etag: String,
+    completed_at: Instant,
+}
+
+type Slot = Arc<tokio::sync::Mutex<Option<CompletedPut>>>;
+
+/// 幂等令牌缓存
+///
+/// 同一 `(对象, 令牌)` 的请求持有同一个槽位锁串行执行，
+/// 并发重试中只有第一个真正写入，其余等待后直接返回其结果。
+pub(crate) struct IdempotencyCache {
+    ttl: Duration,
+    slots: Mutex<HashMap<(String, String), Slot>>,
+}
+
+impl IdempotencyCache {
+    /// 创建缓存，`ttl` 为零时禁用
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 是否启用
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero()
+    }
+
+    /// 获取令牌对应的槽位锁，持有期间同一令牌的其他请求等待
+    pub async fn acquire(&self, file_id: &str, token: &str) -> IdempotencyGuard {
+        let slot = {
+            let mut slots = self.slots.lock().unwrap();
+            self.prune(&mut slots);
+            slots
+                .entry((file_id.to_string(), token.to_string()))
+                .or_default()
+                .clone()
+        };
+        IdempotencyGuard {
+            guard: slot.lock_owned().await,
+            ttl: self.ttl,
+        }
+    }
+
+    /// 清理已过期且无人持有的槽位
+    fn prune(&self, slots: &mut HashMap<(String, String), Slot>) {
+        let ttl = self.ttl;
+        slots.retain(|_, slot| {
+            // 仍有请求持有或等待该槽位
+            if Arc::strong_count(slot) > 1 {
+                return true;
+            }
+            slot.try_lock().is_ok_and(|entry| {
+                entry
+                    .as_ref()
+                    .is_some_and(|done| done.completed_at.elapsed() < ttl)
+            })
+        });
+    }
+}
+
+/// 持有中的幂等槽位
+pub(crate) struct IdempotencyGuard {
+    guard: OwnedMutexGuard<Option<CompletedPut>>,
+    ttl: Duration,
+}
+
+impl IdempotencyGuard {
+    /// 有效期内首次成功的结果
+    pub fn completed(&self) -> Option<&CompletedPut> {
+        self.guard
+            .as_ref()
+            .filter(|done| done.completed_at.elapsed() < self.ttl)
+    }
+
+    /// 记录首次成功的结果
+    pub fn complete(mut self, body: &[u8], etag: String) {
+        *self.guard = Some(CompletedPut {
+            body_hash: body_hash(body),
+            etag,
+            completed_at: Instant::now(),
+        });
+    }
+}
+
+/// 请求体的 SHA-256（十六进制）
+pub(crate) fn body_hash(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_completed_result_expires() {
+        let cache = IdempotencyCache::new(Duration::from_millis(50));
+        let guard = cache.acquire("b/k", "token-1").await;
+        assert!(guard.completed().is_none());
+        guard.complete(b"body", "\"etag\"".to_string());
+
+        let guard = cache.acquire("b/k", "token-1").await;
+        let done = guard.completed().unwrap();
+        assert_eq!(done.etag, "\"etag\"");
+        assert_eq!(done.body_hash, body_hash(b"body"));
+        // 令牌按对象区分
+        drop(guard);
+        assert!(
+            cache
+                .acquire("b/other", "token-1")
+                .await
+                .completed()
+                .is_none()
+        );
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert!(cache.acquire("b/k", "token-1").await.completed().is_none());
+    }
+}
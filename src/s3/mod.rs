@@ -1,9 +1,12 @@
 mod auth;
+mod checksum;
 mod handlers;
+mod idempotency;
 mod models;
 mod service;
 pub mod versioning;
 
 pub use auth::S3Auth;
 pub use handlers::create_s3_routes;
+pub use service::S3Service;
 pub use versioning::VersioningManager;
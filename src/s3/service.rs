@@ -1,11 +1,18 @@
 use crate::notify::EventNotifier;
+pub(crate) use crate::range::RangeRequest;
 use crate::s3::auth::S3Auth;
+use crate::s3::idempotency::IdempotencyCache;
 use crate::s3::models::MultipartUpload;
 use crate::s3::versioning::VersioningManager;
-use crate::storage::StorageManager;
+use crate::storage::{DeleteActor, DeleteMode, DeletePolicy, DeleteRejection, StorageManager};
 use silent::prelude::*;
+use silent_storage::MAX_USER_METADATA_SIZE;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// 用户自定义元数据请求头前缀
+const USER_METADATA_PREFIX: &str = "x-amz-meta-";
 
 /// S3服务
 pub struct S3Service {
@@ -15,6 +22,8 @@ pub struct S3Service {
     pub(crate) multipart_uploads: Arc<RwLock<HashMap<String, MultipartUpload>>>,
     pub(crate) source_http_addr: String,
     pub(crate) versioning_manager: Arc<VersioningManager>,
+    pub(crate) idempotency: Arc<IdempotencyCache>,
+    pub(crate) delete_policy: DeletePolicy,
 }
 
 impl S3Service {
@@ -32,9 +41,68 @@ impl S3Service {
             multipart_uploads: Arc::new(RwLock::new(HashMap::new())),
             source_http_addr,
             versioning_manager,
+            idempotency: Arc::new(IdempotencyCache::new(Duration::from_secs(
+                crate::config::S3Config::default_idempotency_ttl_secs(),
+            ))),
+            delete_policy: DeletePolicy::default(),
+        }
+    }
+
+    /// 设置 PUT 幂等令牌的有效期，为零时禁用（见 [`crate::config::S3Config::idempotency_ttl_secs`]）
+    pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency = Arc::new(IdempotencyCache::new(ttl));
+        self
+    }
+
+    /// 设置删除请求的默认删除语义（见 [`crate::config::StorageConfig::default_delete`]）
+    pub fn with_delete_policy(mut self, policy: DeletePolicy) -> Self {
+        self.delete_policy = policy;
+        self
+    }
+
+    /// 删除对象使用的删除语义
+    ///
+    /// 请求头 `x-delete-mode` 优先；开启版本控制的桶保留历史版本，只写入删除标记（软删除）；
+    /// 其余按配置的默认删除语义，未配置时与 S3 一致直接删除（硬删除）。
+    pub(crate) async fn delete_mode(
+        &self,
+        req: &Request,
+        bucket: &str,
+    ) -> Result<DeleteMode, DeleteRejection> {
+        if let Some(mode) = DeletePolicy::requested(req.headers(), &self.delete_actor(req))? {
+            return Ok(mode);
+        }
+        if self.versioning_manager.is_versioning_enabled(bucket).await {
+            return Ok(DeleteMode::Soft);
+        }
+        Ok(self.delete_policy.default_or(DeleteMode::Hard))
+    }
+
+    /// 删除请求的发起者
+    ///
+    /// S3 只有配置的一组访问密钥，通过校验的请求视为管理员；未启用认证时不区分权限。
+    pub(crate) fn delete_actor(&self, req: &Request) -> DeleteActor {
+        match &self.auth {
+            Some(auth) => DeleteActor::Authenticated {
+                id: auth.access_key.clone(),
+                admin: auth.verify_request(req),
+            },
+            None => DeleteActor::Anonymous,
         }
     }
 
+    /// 删除请求被拒绝时的错误响应
+    pub(crate) fn delete_rejection_response(
+        &self,
+        rejection: &DeleteRejection,
+    ) -> silent::Result<Response> {
+        let code = match rejection {
+            DeleteRejection::InvalidMode(_) => "InvalidArgument",
+            DeleteRejection::Forbidden => "AccessDenied",
+        };
+        self.error_response(rejection.status(), code, &rejection.to_string())
+    }
+
     /// 验证请求
     pub(crate) fn verify_request(&self, req: &Request) -> bool {
         match &self.auth {
@@ -83,71 +151,61 @@ impl S3Service {
             .collect()
     }
 
-    /// 解析Range头，返回(start, end)，都是包含的
-    pub(crate) fn parse_range(range_str: &str, file_size: u64) -> Option<(usize, usize)> {
-        // 格式: "bytes=start-end" 或 "bytes=start-" 或 "bytes=-count"
-        let range_str = range_str.trim();
-        if !range_str.starts_with("bytes=") {
-            return None;
-        }
-
-        let range = range_str.strip_prefix("bytes=")?;
-        let parts: Vec<&str> = range.split('-').collect();
-
-        if parts.len() != 2 {
-            return None;
-        }
-
-        match (parts[0].trim(), parts[1].trim()) {
-            ("", end_str) => {
-                // bytes=-count: 最后count字节
-                let count: u64 = end_str.parse().ok()?;
-                let start = file_size.saturating_sub(count);
-                Some((start as usize, (file_size - 1) as usize))
-            }
-            (start_str, "") => {
-                // bytes=start-: 从start到结束
-                let start: u64 = start_str.parse().ok()?;
-                if start >= file_size {
-                    return None;
-                }
-                Some((start as usize, (file_size - 1) as usize))
-            }
-            (start_str, end_str) => {
-                // bytes=start-end: 指定范围
-                let start: u64 = start_str.parse().ok()?;
-                let mut end: u64 = end_str.parse().ok()?;
+    /// 解析Range头，范围均为闭区间 (start, end)
+    ///
+    /// 见 [`crate::range::parse_range`]。
+    pub(crate) fn parse_range(range_str: &str, file_size: u64) -> RangeRequest {
+        crate::range::parse_range(range_str, file_size)
+    }
 
-                if start >= file_size {
-                    return None;
-                }
+    /// 对象的 ETag（含双引号）
+    ///
+    /// 见 [`StorageManager::entity_tag`]，与 HTTP、WebDAV 下载返回的 ETag 相同。
+    pub(crate) async fn entity_tag(&self, file_id: &str) -> silent::Result<String> {
+        self.storage.entity_tag(file_id).await.map_err(|e| {
+            let status = match e {
+                silent_storage::StorageError::FileNotFound(_) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            SilentError::business_error(status, format!("读取对象哈希失败: {}", e))
+        })
+    }
 
-                // end不能超过文件大小
-                if end >= file_size {
-                    end = file_size - 1;
-                }
+    /// 从请求头提取用户自定义元数据（`x-amz-meta-*`，键去掉前缀）
+    ///
+    /// HTTP 头名不区分大小写，键统一为小写；值不是合法 UTF-8 的头被忽略。
+    pub(crate) fn parse_user_metadata(headers: &http::HeaderMap) -> HashMap<String, String> {
+        headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let key = name.as_str().strip_prefix(USER_METADATA_PREFIX)?;
+                let value = value.to_str().ok()?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect()
+    }
 
-                if start > end {
-                    return None;
-                }
+    /// 用户自定义元数据是否超过大小上限
+    pub(crate) fn user_metadata_too_large(user_metadata: &HashMap<String, String>) -> bool {
+        user_metadata
+            .iter()
+            .map(|(k, v)| k.len() + v.len())
+            .sum::<usize>()
+            > MAX_USER_METADATA_SIZE
+    }
 
-                Some((start as usize, end as usize))
+    /// 将用户自定义元数据写为 `x-amz-meta-*` 响应头
+    pub(crate) fn add_user_metadata(resp: &mut Response, user_metadata: &HashMap<String, String>) {
+        for (key, value) in user_metadata {
+            if let (Ok(name), Ok(value)) = (
+                http::HeaderName::from_bytes(format!("{}{}", USER_METADATA_PREFIX, key).as_bytes()),
+                http::HeaderValue::from_str(value),
+            ) {
+                resp.headers_mut().insert(name, value);
             }
         }
     }
 
-    /// 添加用户自定义元数据（示例实现）
-    pub(crate) fn add_user_metadata(resp: &mut Response) {
-        // 注：实际应用中应该从持久化存储读取
-        // 这里仅为演示S3协议兼容性
-        resp.headers_mut().insert(
-            "x-amz-meta-author",
-            http::HeaderValue::from_static("silent-nas"),
-        );
-        resp.headers_mut()
-            .insert("x-amz-meta-version", http::HeaderValue::from_static("1.0"));
-    }
-
     /// XML转义
     pub(crate) fn xml_escape(s: &str) -> String {
         s.replace('&', "&amp;")
@@ -185,4 +243,90 @@ impl S3Service {
 
         Ok(resp)
     }
+
+    /// 存储错误对应的 S3 错误码（状态码见 [`crate::error::status_for`]）
+    pub(crate) fn storage_error_code(err: &silent_storage::StorageError) -> &'static str {
+        match crate::error::status_for(err) {
+            StatusCode::NOT_FOUND => "NoSuchKey",
+            StatusCode::FORBIDDEN => "AccessDenied",
+            StatusCode::CONFLICT => "OperationAborted",
+            StatusCode::SERVICE_UNAVAILABLE => "ServiceUnavailable",
+            StatusCode::PRECONDITION_FAILED => "PreconditionFailed",
+            StatusCode::PAYLOAD_TOO_LARGE => "EntityTooLarge",
+            StatusCode::BAD_REQUEST => "InvalidRequest",
+            _ => "InternalError",
+        }
+    }
+
+    /// 存储错误响应（状态码与 S3 错误码由错误类型决定）
+    pub(crate) fn storage_error_response(
+        &self,
+        err: &silent_storage::StorageError,
+    ) -> silent::Result<Response> {
+        self.error_response(
+            crate::error::status_for(err),
+            Self::storage_error_code(err),
+            &err.to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            S3Service::parse_range("bytes=0-99", 1000),
+            RangeRequest::Ranges(vec![(0, 99)])
+        );
+        // 开放结尾与后缀范围
+        assert_eq!(
+            S3Service::parse_range("bytes=900-", 1000),
+            RangeRequest::Ranges(vec![(900, 999)])
+        );
+        assert_eq!(
+            S3Service::parse_range("bytes=-100", 1000),
+            RangeRequest::Ranges(vec![(900, 999)])
+        );
+        // end 超出文件大小时截断
+        assert_eq!(
+            S3Service::parse_range("bytes=990-2000", 1000),
+            RangeRequest::Ranges(vec![(990, 999)])
+        );
+        // 多个范围，丢弃无法满足的部分
+        assert_eq!(
+            S3Service::parse_range("bytes=0-1, 5-6, 2000-", 1000),
+            RangeRequest::Ranges(vec![(0, 1), (5, 6)])
+        );
+    }
+
+    #[test]
+    fn test_parse_range_unsatisfiable_and_invalid() {
+        assert_eq!(
+            S3Service::parse_range("bytes=1000-", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            S3Service::parse_range("bytes=-0", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            S3Service::parse_range("bytes=0-", 0),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            S3Service::parse_range("items=0-1", 1000),
+            RangeRequest::Ignored
+        );
+        assert_eq!(
+            S3Service::parse_range("bytes=5-1", 1000),
+            RangeRequest::Ignored
+        );
+        assert_eq!(
+            S3Service::parse_range("bytes=abc", 1000),
+            RangeRequest::Ignored
+        );
+    }
 }
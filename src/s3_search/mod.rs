@@ -74,6 +74,40 @@ pub struct QueryStats {
     pub processing_time_ms: u64,
 }
 
+/// 查询计划
+///
+/// SQL 的解析结果，用于在执行前校验语句并展示投影、过滤条件和数据源。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedQueryPlan {
+    /// 投影（SELECT 字段）
+    pub projection: parser::SelectClause,
+    /// 数据源（FROM）
+    pub source: String,
+    /// 过滤条件树（WHERE），多个条件合并为 `And`
+    pub predicate: Option<parser::Condition>,
+    /// 返回记录数上限（LIMIT）
+    pub limit: Option<u64>,
+}
+
+impl From<parser::ParsedQuery> for ParsedQueryPlan {
+    fn from(query: parser::ParsedQuery) -> Self {
+        let predicate = query.where_clause.and_then(|clause| {
+            let mut conditions = clause.conditions;
+            match conditions.len() {
+                0 => None,
+                1 => conditions.pop(),
+                _ => Some(parser::Condition::And(conditions)),
+            }
+        });
+        Self {
+            projection: query.select,
+            source: query.from.source_type,
+            predicate,
+            limit: query.limit,
+        }
+    }
+}
+
 /// S3 搜索引擎
 pub struct S3SearchEngine {
     /// 内部搜索引擎
@@ -97,6 +131,14 @@ impl S3SearchEngine {
         Ok(result)
     }
 
+    /// 解析 SQL 并返回查询计划，不扫描任何数据
+    ///
+    /// 语句无效时返回 [`NasError::InvalidQuery`](crate::error::NasError::InvalidQuery)，
+    /// 可在执行代价较高的 [`select`](Self::select) 之前校验语句。
+    pub fn explain(expression: &str) -> Result<ParsedQueryPlan> {
+        Ok(parser::parse_sql(expression)?.into())
+    }
+
     /// 查询对象标签
     pub async fn query_tags(&self, _object_key: &str, _tags: &[(&str, &str)]) -> Result<bool> {
         // 这里应该查询对象的标签
@@ -146,6 +188,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_explain_valid_query() {
+        let plan = S3SearchEngine::explain(
+            "SELECT name AS file_name, size FROM s3object WHERE size > 100 AND name LIKE '%.txt' LIMIT 5",
+        )
+        .unwrap();
+
+        match &plan.projection {
+            parser::SelectClause::Fields(fields) => {
+                assert_eq!(fields.len(), 2);
+                assert_eq!(fields[0].name, "name");
+                assert_eq!(fields[0].alias.as_deref(), Some("file_name"));
+                assert_eq!(fields[1].name, "size");
+            }
+            parser::SelectClause::All => panic!("应该选择指定字段"),
+        }
+        assert_eq!(plan.source, "s3object");
+        assert_eq!(plan.limit, Some(5));
+        match &plan.predicate {
+            Some(parser::Condition::And(conditions)) => assert_eq!(conditions.len(), 2),
+            other => panic!("应该是 AND 条件树: {:?}", other),
+        }
+
+        // 计划可序列化后返回给客户端
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["source"], "s3object");
+    }
+
+    #[test]
+    fn test_explain_invalid_query() {
+        for sql in [
+            "",
+            "DELETE FROM s3object",
+            "SELECT * s3object",
+            "SELECT FROM s3object",
+            "SELECT * FROM s3object WHERE size >",
+            "SELECT * FROM s3object LIMIT ten",
+        ] {
+            match S3SearchEngine::explain(sql) {
+                Err(crate::error::NasError::InvalidQuery(msg)) => {
+                    assert!(!msg.is_empty(), "错误信息不应为空: {}", sql)
+                }
+                other => panic!("{} 应返回语法错误: {:?}", sql, other),
+            }
+        }
+
+        let err = S3SearchEngine::explain("SELECT * s3object").unwrap_err();
+        assert!(err.to_string().contains("FROM"));
+    }
+
     #[test]
     fn test_record_format_serialization() {
         let csv_format = RecordFormat::CSV;
@@ -1,11 +1,14 @@
 //! SQL 查询解析器
 //!
 //! 解析 S3 Select 兼容的 SQL 查询语句，提取查询条件和字段
+//!
+//! 关键字按 ASCII 大小写不敏感匹配，无效语句返回 [`NasError::InvalidQuery`]。
 
 use crate::error::{NasError, Result};
+use serde::{Deserialize, Serialize};
 
 /// SQL 查询语句的抽象表示
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedQuery {
     /// SELECT 子句
     pub select: SelectClause,
@@ -18,7 +21,7 @@ pub struct ParsedQuery {
 }
 
 /// SELECT 子句
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SelectClause {
     /// 选择所有字段
     All,
@@ -27,7 +30,7 @@ pub enum SelectClause {
 }
 
 /// 字段
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Field {
     /// 字段名
     pub name: String,
@@ -36,21 +39,21 @@ pub struct Field {
 }
 
 /// FROM 子句
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FromClause {
     /// 数据源类型
     pub source_type: String,
 }
 
 /// WHERE 子句
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WhereClause {
     /// 条件表达式
     pub conditions: Vec<Condition>,
 }
 
 /// 条件表达式
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Condition {
     /// 比较条件
     Comparison(Comparison),
@@ -63,7 +66,7 @@ pub enum Condition {
 }
 
 /// 比较操作符
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Operator {
     Equal,
     NotEqual,
@@ -77,7 +80,7 @@ pub enum Operator {
 }
 
 /// 比较条件
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Comparison {
     /// 左操作数
     pub left: Operand,
@@ -88,7 +91,7 @@ pub struct Comparison {
 }
 
 /// 操作数
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Operand {
     /// 字段
     Field(String),
@@ -97,7 +100,7 @@ pub enum Operand {
 }
 
 /// 字面量值
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Literal {
     /// 字符串
     String(String),
@@ -113,9 +116,13 @@ pub enum Literal {
 pub fn parse_sql(sql: &str) -> Result<ParsedQuery> {
     let sql = sql.trim();
 
+    if sql.is_empty() {
+        return Err(NasError::InvalidQuery("SQL 语句为空".to_string()));
+    }
+
     // 基本的 SQL 解析（简化实现）
-    if !sql.to_uppercase().starts_with("SELECT") {
-        return Err(NasError::Other(format!("无效的 SQL 语句: {}", sql)));
+    if !sql.to_ascii_uppercase().starts_with("SELECT ") {
+        return Err(NasError::InvalidQuery(format!("无效的 SQL 语句: {}", sql)));
     }
 
     // 解析 SELECT 子句
@@ -143,10 +150,13 @@ fn parse_select_clause(sql: &str) -> Result<SelectClause> {
     let sql = sql.trim();
 
     // 查找 SELECT 和 FROM 之间的内容
-    if let Some(from_pos) = sql.to_uppercase().find(" FROM ") {
-        let select_part = &sql[7..from_pos].trim();
+    if let Some(from_pos) = sql.to_ascii_uppercase().find(" FROM ") {
+        let select_part = sql.get(7..from_pos).unwrap_or_default().trim();
 
-        if *select_part == "*" {
+        if select_part.is_empty() {
+            return Err(NasError::InvalidQuery("SELECT 子句缺少字段".to_string()));
+        }
+        if select_part == "*" {
             return Ok(SelectClause::All);
         }
 
@@ -155,7 +165,13 @@ fn parse_select_clause(sql: &str) -> Result<SelectClause> {
             .split(',')
             .map(|field| {
                 let field = field.trim();
-                if let Some(alias_pos) = field.to_uppercase().find(" AS ") {
+                if field.is_empty() {
+                    return Err(NasError::InvalidQuery(format!(
+                        "SELECT 字段列表中有空字段: {}",
+                        select_part
+                    )));
+                }
+                if let Some(alias_pos) = field.to_ascii_uppercase().find(" AS ") {
                     // 处理别名
                     let (name, alias) = field.split_at(alias_pos);
                     Ok(Field {
@@ -173,7 +189,7 @@ fn parse_select_clause(sql: &str) -> Result<SelectClause> {
 
         Ok(SelectClause::Fields(fields))
     } else {
-        Err(NasError::Other("缺少 FROM 子句".to_string()))
+        Err(NasError::InvalidQuery("缺少 FROM 子句".to_string()))
     }
 }
 
@@ -182,27 +198,29 @@ fn parse_from_clause(sql: &str) -> Result<FromClause> {
     let sql = sql.trim();
 
     // 查找 FROM 关键字
-    if let Some(from_pos) = sql.to_uppercase().find(" FROM ") {
+    if let Some(from_pos) = sql.to_ascii_uppercase().find(" FROM ") {
         let from_part_start = from_pos + 6;
-        let from_part =
-            if let Some(where_pos) = sql[from_part_start..].to_uppercase().find(" WHERE ") {
-                &sql[from_part_start..from_part_start + where_pos]
-            } else if let Some(limit_pos) = sql[from_part_start..].to_uppercase().find(" LIMIT ") {
-                &sql[from_part_start..from_part_start + limit_pos]
-            } else {
-                &sql[from_part_start..]
-            }
-            .trim();
+        let from_part = if let Some(where_pos) =
+            sql[from_part_start..].to_ascii_uppercase().find(" WHERE ")
+        {
+            &sql[from_part_start..from_part_start + where_pos]
+        } else if let Some(limit_pos) = sql[from_part_start..].to_ascii_uppercase().find(" LIMIT ")
+        {
+            &sql[from_part_start..from_part_start + limit_pos]
+        } else {
+            &sql[from_part_start..]
+        }
+        .trim();
 
         if from_part.is_empty() {
-            return Err(NasError::Other("FROM 子句不能为空".to_string()));
+            return Err(NasError::InvalidQuery("FROM 子句不能为空".to_string()));
         }
 
         Ok(FromClause {
             source_type: from_part.to_string(),
         })
     } else {
-        Err(NasError::Other("缺少 FROM 子句".to_string()))
+        Err(NasError::InvalidQuery("缺少 FROM 子句".to_string()))
     }
 }
 
@@ -211,10 +229,10 @@ fn parse_where_clause(sql: &str) -> Result<Option<WhereClause>> {
     let sql = sql.trim();
 
     // 查找 WHERE 关键字
-    if let Some(where_pos) = sql.to_uppercase().find(" WHERE ") {
+    if let Some(where_pos) = sql.to_ascii_uppercase().find(" WHERE ") {
         let where_part_start = where_pos + 7;
         let where_part =
-            if let Some(limit_pos) = sql[where_part_start..].to_uppercase().find(" LIMIT ") {
+            if let Some(limit_pos) = sql[where_part_start..].to_ascii_uppercase().find(" LIMIT ") {
                 &sql[where_part_start..where_part_start + limit_pos]
             } else {
                 &sql[where_part_start..]
@@ -222,7 +240,7 @@ fn parse_where_clause(sql: &str) -> Result<Option<WhereClause>> {
             .trim();
 
         if where_part.is_empty() {
-            return Err(NasError::Other("WHERE 子句不能为空".to_string()));
+            return Err(NasError::InvalidQuery("WHERE 子句不能为空".to_string()));
         }
 
         // 解析简单条件
@@ -246,7 +264,7 @@ fn parse_comparison_conditions(where_part: &str) -> Result<Vec<Condition>> {
     let remaining = where_part.trim();
 
     // 简单的条件解析（处理 "AND" 连接的条件）
-    if remaining.to_uppercase().contains(" AND ") {
+    if remaining.to_ascii_uppercase().contains(" AND ") {
         for part in remaining.split(" AND ") {
             let condition = parse_single_condition(part.trim())?;
             conditions.push(condition);
@@ -276,15 +294,15 @@ fn parse_single_condition(condition: &str) -> Result<Condition> {
     ];
 
     for (op_str, operator) in &operators {
-        if condition.to_uppercase().contains(op_str) {
+        if condition.to_ascii_uppercase().contains(op_str) {
             let parts: Vec<&str> = if op_str.trim() == "" {
                 // 特殊处理 LIKE 空格
-                let like_pos = condition.to_uppercase().find("LIKE").unwrap();
+                let like_pos = condition.to_ascii_uppercase().find("LIKE").unwrap();
                 let left = &condition[..like_pos];
                 let right = &condition[like_pos + 4..];
                 vec![left.trim(), right.trim()]
             } else {
-                let op_pos = condition.to_uppercase().find(op_str).unwrap();
+                let op_pos = condition.to_ascii_uppercase().find(op_str).unwrap();
                 let left = &condition[..op_pos];
                 let right = &condition[op_pos + op_str.len()..];
                 vec![left.trim(), right.trim()]
@@ -303,15 +321,22 @@ fn parse_single_condition(condition: &str) -> Result<Condition> {
         }
     }
 
-    Err(NasError::Other(format!("无法解析条件: {}", condition)))
+    Err(NasError::InvalidQuery(format!(
+        "无法解析条件: {}",
+        condition
+    )))
 }
 
 /// 解析操作数
 fn parse_operand(operand: &str) -> Result<Operand> {
     let operand = operand.trim();
 
+    if operand.is_empty() {
+        return Err(NasError::InvalidQuery("条件缺少操作数".to_string()));
+    }
+
     // 字符串字面量（单引号）
-    if operand.starts_with('\'') && operand.ends_with('\'') {
+    if operand.len() >= 2 && operand.starts_with('\'') && operand.ends_with('\'') {
         let value = &operand[1..operand.len() - 1];
         return Ok(Operand::Literal(Literal::String(value.to_string())));
     }
@@ -322,15 +347,15 @@ fn parse_operand(operand: &str) -> Result<Operand> {
     }
 
     // 布尔值
-    if operand.to_uppercase() == "TRUE" {
+    if operand.to_ascii_uppercase() == "TRUE" {
         return Ok(Operand::Literal(Literal::Boolean(true)));
     }
-    if operand.to_uppercase() == "FALSE" {
+    if operand.to_ascii_uppercase() == "FALSE" {
         return Ok(Operand::Literal(Literal::Boolean(false)));
     }
 
     // NULL
-    if operand.to_uppercase() == "NULL" {
+    if operand.to_ascii_uppercase() == "NULL" {
         return Ok(Operand::Literal(Literal::Null));
     }
 
@@ -343,13 +368,16 @@ fn parse_limit_clause(sql: &str) -> Result<Option<u64>> {
     let sql = sql.trim();
 
     // 查找 LIMIT 关键字
-    if let Some(limit_pos) = sql.to_uppercase().find(" LIMIT ") {
+    if let Some(limit_pos) = sql.to_ascii_uppercase().find(" LIMIT ") {
         let limit_part = &sql[limit_pos + 7..].trim();
 
         if let Ok(limit) = limit_part.parse::<u64>() {
             Ok(Some(limit))
         } else {
-            Err(NasError::Other("无效的 LIMIT 值".to_string()))
+            Err(NasError::InvalidQuery(format!(
+                "无效的 LIMIT 值: {}",
+                limit_part
+            )))
         }
     } else {
         Ok(None)
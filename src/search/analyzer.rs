@@ -0,0 +1,164 @@
+//! 多语言分词器
+//!
+//! Tantivy 默认分词器按非字母数字字符切分，连续的中日韩文字会被当作一个整词，
+//! 无法按词内子串检索。CJK 二元分词器将连续的中日韩文字切分为重叠的二元组，
+//! 其余文字仍按字母数字串切分，短语查询即可匹配任意长度不小于 2 的子串。
+
+use serde::{Deserialize, Serialize};
+use tantivy::tokenizer::{
+    LowerCaser, RemoveLongFilter, TextAnalyzer, Token, TokenStream, Tokenizer,
+};
+
+/// CJK 二元分词器在索引中注册的名称
+pub const CJK_BIGRAM_TOKENIZER: &str = "cjk_bigram";
+
+/// `content`/`name` 字段使用的分析器
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyzerKind {
+    /// Tantivy 默认分词器（适合以空格分词的语言）
+    #[default]
+    Default,
+    /// CJK 二元分词（中日韩文字按重叠二元组切分）
+    CjkBigram,
+}
+
+impl AnalyzerKind {
+    /// 在索引 Schema 中使用的分词器名称
+    pub fn tokenizer_name(self) -> &'static str {
+        match self {
+            AnalyzerKind::Default => "default",
+            AnalyzerKind::CjkBigram => CJK_BIGRAM_TOKENIZER,
+        }
+    }
+}
+
+/// 构建 CJK 二元分析器（小写化并丢弃过长的词）
+pub fn cjk_bigram_analyzer() -> TextAnalyzer {
+    TextAnalyzer::builder(CjkBigramTokenizer)
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .build()
+}
+
+/// 是否为中日韩文字（汉字、假名、谚文）
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FA1F}')
+}
+
+/// CJK 二元分词器
+///
+/// 单独出现的中日韩文字作为单字词输出。
+#[derive(Debug, Clone, Default)]
+pub struct CjkBigramTokenizer;
+
+impl Tokenizer for CjkBigramTokenizer {
+    type TokenStream<'a> = CjkBigramTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        CjkBigramTokenStream {
+            tokens: tokenize(text),
+            index: None,
+        }
+    }
+}
+
+/// 预先切分好的词流
+pub struct CjkBigramTokenStream {
+    tokens: Vec<Token>,
+    index: Option<usize>,
+}
+
+impl TokenStream for CjkBigramTokenStream {
+    fn advance(&mut self) -> bool {
+        let next = self.index.map_or(0, |i| i + 1);
+        self.index = Some(next);
+        next < self.tokens.len()
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.index.unwrap_or(0)]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.index.unwrap_or(0)]
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut push = |text: &str, from: usize, to: usize| {
+        let position = tokens.len();
+        tokens.push(Token {
+            offset_from: from,
+            offset_to: to,
+            position,
+            text: text[from..to].to_string(),
+            position_length: 1,
+        });
+    };
+
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let end_of = |i: usize| chars.get(i).map_or(text.len(), |(offset, _)| *offset);
+
+    let mut i = 0;
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        if is_cjk(c) {
+            let mut j = i + 1;
+            while j < chars.len() && is_cjk(chars[j].1) {
+                j += 1;
+            }
+            if j - i == 1 {
+                push(text, start, end_of(i + 1));
+            } else {
+                for (k, &(from, _)) in chars.iter().enumerate().take(j - 1).skip(i) {
+                    push(text, from, end_of(k + 2));
+                }
+            }
+            i = j;
+        } else if c.is_alphanumeric() {
+            let mut j = i + 1;
+            while j < chars.len() && chars[j].1.is_alphanumeric() && !is_cjk(chars[j].1) {
+                j += 1;
+            }
+            push(text, start, end_of(j));
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_texts(text: &str) -> Vec<String> {
+        let mut analyzer = cjk_bigram_analyzer();
+        let mut stream = analyzer.token_stream(text);
+        let mut texts = Vec::new();
+        while stream.advance() {
+            texts.push(stream.token().text.clone());
+        }
+        texts
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenize() {
+        assert_eq!(token_texts("全文搜索"), vec!["全文", "文搜", "搜索"]);
+        assert_eq!(
+            token_texts("Rust语言 v2.0 版"),
+            vec!["rust", "语言", "v2", "0", "版"]
+        );
+        assert_eq!(token_texts("日本語のテキスト")[..2], ["日本", "本語"]);
+        assert!(token_texts("  ,. ").is_empty());
+    }
+}
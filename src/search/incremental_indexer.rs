@@ -232,6 +232,7 @@ impl IncrementalIndexer {
                                 )
                                 .unwrap_or_default()
                                 .naive_local(),
+                                user_metadata: Default::default(),
                             };
                             files.insert(path, file_meta);
                         }
@@ -368,6 +369,7 @@ mod tests {
             hash: "test_hash".to_string(),
             created_at: Utc::now().naive_local(),
             modified_at: Utc::now().naive_local(),
+            user_metadata: Default::default(),
         }
     }
 
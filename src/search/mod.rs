@@ -7,20 +7,25 @@
 //! - 高级搜索过滤
 //! - 搜索结果排序与分页
 
+pub mod analyzer;
 pub mod content_extractor;
 pub mod incremental_indexer;
 
 use crate::error::{NasError, Result};
 use crate::models::FileMetadata;
+use crate::storage::{MutationEvent, MutationOp};
+use analyzer::{AnalyzerKind, CJK_BIGRAM_TOKENIZER};
 use content_extractor::{ContentExtractor, FileType};
+use futures_util::{StreamExt, stream};
 use incremental_indexer::{IncrementalIndexer, IncrementalIndexerConfig};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tantivy::schema::*;
 use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, doc};
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, broadcast};
 use tracing::{debug, info, warn};
 
 /// 搜索结果
@@ -40,6 +45,155 @@ pub struct SearchResult {
     pub score: f32,
 }
 
+/// 索引 Schema 版本，内置字段变化时递增
+const INDEX_SCHEMA_VERSION: u32 = 2;
+
+/// 索引目录中记录 Schema 签名的文件名
+const SCHEMA_VERSION_FILE: &str = "schema_version";
+
+/// 索引目录中记录重建断点的文件名
+const REINDEX_CHECKPOINT_FILE: &str = "reindex_checkpoint.json";
+
+/// 重建索引时每批提交的文件数
+const REINDEX_BATCH_SIZE: usize = 500;
+
+/// 批量索引时每次持有写锁写入的文档数
+const INDEX_WRITE_BATCH: usize = 64;
+
+/// 重建索引断点
+///
+/// 重建按文件 ID 升序分批进行，每批提交后记录最后一个已提交的文件 ID；
+/// 中断后再次重建时跳过这些文件，全部完成后删除断点文件。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ReindexCheckpoint {
+    /// 最后一个已提交的文件 ID
+    last_file_id: String,
+    /// 已提交的文件数
+    indexed: usize,
+}
+
+/// Tantivy 模糊查询支持的最大编辑距离
+const MAX_FUZZY_DISTANCE: u8 = 2;
+
+/// 内置字段名，附加字段不能与之重名
+const BUILTIN_FIELDS: [&str; 7] = [
+    "file_id",
+    "path",
+    "name",
+    "size",
+    "modified_at",
+    "file_type",
+    "content",
+];
+
+/// 搜索引擎配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchConfig {
+    /// `content`/`name` 字段的分析器（`file_id`/`path` 不受影响）
+    ///
+    /// 修改后需删除已有索引目录重建，已有索引沿用创建时的分析器
+    #[serde(default)]
+    pub analyzer: AnalyzerKind,
+    /// 附加索引字段，取值来自文件的用户元数据，搜索时可按字段精确过滤
+    ///
+    /// 字段列表变化后已有索引失效，启动时自动清空并重建
+    #[serde(default = "SearchConfig::default_extra_fields")]
+    pub extra_fields: Vec<ExtraField>,
+    /// 单次查询返回结果数上限，超过时截断为该值
+    #[serde(default = "SearchConfig::default_max_limit")]
+    pub max_limit: usize,
+    /// 模糊查询允许的最大编辑距离，超过时拒绝查询（Tantivy 最多支持 2）
+    #[serde(default = "SearchConfig::default_max_fuzzy_distance")]
+    pub max_fuzzy_distance: u8,
+    /// 单次查询超时（毫秒），0 表示不限制
+    #[serde(default = "SearchConfig::default_query_timeout_ms")]
+    pub query_timeout_ms: u64,
+    /// 批量索引与重建索引时同时提取内容的文件数
+    #[serde(default = "SearchConfig::default_index_concurrency")]
+    pub index_concurrency: usize,
+}
+
+impl SearchConfig {
+    fn default_extra_fields() -> Vec<ExtraField> {
+        vec![ExtraField::single("owner"), ExtraField::multi("tags")]
+    }
+
+    fn default_max_limit() -> usize {
+        1000
+    }
+
+    fn default_max_fuzzy_distance() -> u8 {
+        2
+    }
+
+    fn default_query_timeout_ms() -> u64 {
+        5000
+    }
+
+    fn default_index_concurrency() -> usize {
+        4
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            analyzer: AnalyzerKind::default(),
+            extra_fields: Self::default_extra_fields(),
+            max_limit: Self::default_max_limit(),
+            max_fuzzy_distance: Self::default_max_fuzzy_distance(),
+            query_timeout_ms: Self::default_query_timeout_ms(),
+            index_concurrency: Self::default_index_concurrency(),
+        }
+    }
+}
+
+/// 附加索引字段（不分词，按完整值精确匹配）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtraField {
+    /// 字段名，同时是用户元数据中的键
+    pub name: String,
+    /// 多值字段：元数据值按逗号拆分为多个值（如标签）
+    #[serde(default)]
+    pub multi_valued: bool,
+}
+
+impl ExtraField {
+    /// 单值字段
+    pub fn single(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            multi_valued: false,
+        }
+    }
+
+    /// 多值字段
+    pub fn multi(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            multi_valued: true,
+        }
+    }
+
+    /// 从文件元数据中取出要索引的值
+    fn values(&self, file_meta: &FileMetadata) -> Vec<String> {
+        let Some(raw) = file_meta.user_metadata.get(&self.name) else {
+            return Vec::new();
+        };
+        if self.multi_valued {
+            raw.split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .collect()
+        } else if raw.trim().is_empty() {
+            Vec::new()
+        } else {
+            vec![raw.trim().to_string()]
+        }
+    }
+}
+
 /// 搜索引擎
 pub struct SearchEngine {
     /// 索引
@@ -51,11 +205,23 @@ pub struct SearchEngine {
     /// Schema 字段
     schema_fields: SchemaFields,
     /// 内容提取器
-    content_extractor: ContentExtractor,
+    content_extractor: Arc<ContentExtractor>,
     /// 存储根路径
     storage_root: PathBuf,
     /// 增量索引管理器
     incremental_indexer: Arc<IncrementalIndexer>,
+    /// 索引目录
+    index_path: PathBuf,
+    /// 索引因 Schema 变化被清空或上次重建未完成，需要调用方重建
+    needs_reindex: AtomicBool,
+    /// 单次查询返回结果数上限
+    max_limit: usize,
+    /// 模糊查询允许的最大编辑距离
+    max_fuzzy_distance: u8,
+    /// 单次查询超时
+    query_timeout: Option<Duration>,
+    /// 批量索引时同时提取内容的文件数
+    index_concurrency: usize,
 }
 
 /// Schema 字段定义
@@ -68,40 +234,157 @@ struct SchemaFields {
     modified_at: Field,
     file_type: Field,
     content: Field,
+    /// 附加字段
+    extra: Vec<(ExtraField, Field)>,
+}
+
+impl SchemaFields {
+    /// 写入附加字段的值
+    fn add_extra_values(&self, doc: &mut TantivyDocument, file_meta: &FileMetadata) {
+        for (extra, field) in &self.extra {
+            for value in extra.values(file_meta) {
+                doc.add_text(*field, value);
+            }
+        }
+    }
+
+    /// 按名称查找附加字段
+    fn extra_field(&self, name: &str) -> Option<Field> {
+        self.extra
+            .iter()
+            .find(|(extra, _)| extra.name == name)
+            .map(|(_, field)| *field)
+    }
+
+    /// 将检索到的文档转换为搜索结果
+    fn to_result(&self, doc: &TantivyDocument, score: f32) -> SearchResult {
+        let text = |field: Field| {
+            doc.get_first(field)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        SearchResult {
+            file_id: text(self.file_id),
+            path: text(self.path),
+            name: text(self.name),
+            size: doc
+                .get_first(self.size)
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0),
+            modified_at: doc
+                .get_first(self.modified_at)
+                .and_then(|v| v.as_i64())
+                .unwrap_or(0),
+            score,
+        }
+    }
 }
 
 impl SearchEngine {
-    /// 创建新的搜索引擎
+    /// 创建新的搜索引擎（使用默认配置）
     pub fn new(index_path: PathBuf, storage_root: PathBuf) -> Result<Self> {
+        Self::with_config(index_path, storage_root, SearchConfig::default())
+    }
+
+    /// 使用指定配置创建搜索引擎
+    pub fn with_config(
+        index_path: PathBuf,
+        storage_root: PathBuf,
+        config: SearchConfig,
+    ) -> Result<Self> {
+        validate_extra_fields(&config.extra_fields)?;
+        if config.max_limit == 0 {
+            return Err(NasError::Config("search.max_limit 必须大于 0".to_string()));
+        }
+        if config.index_concurrency == 0 {
+            return Err(NasError::Config(
+                "search.index_concurrency 必须大于 0".to_string(),
+            ));
+        }
+        if config.max_fuzzy_distance > MAX_FUZZY_DISTANCE {
+            return Err(NasError::Config(format!(
+                "search.max_fuzzy_distance 不能超过 {}",
+                MAX_FUZZY_DISTANCE
+            )));
+        }
+
         // 创建索引目录
         std::fs::create_dir_all(&index_path)
             .map_err(|e| NasError::Storage(format!("创建索引目录失败: {}", e)))?;
 
         // 创建内容提取器
-        let content_extractor = ContentExtractor::new();
+        let content_extractor = Arc::new(ContentExtractor::new());
 
         // 定义 Schema
         let mut schema_builder = Schema::builder();
 
         let file_id = schema_builder.add_text_field("file_id", STRING | STORED);
         let path = schema_builder.add_text_field("path", TEXT | STORED);
-        let name = schema_builder.add_text_field("name", TEXT | STORED);
+        let name =
+            schema_builder.add_text_field("name", analyzed_text(config.analyzer).set_stored());
         let size = schema_builder.add_u64_field("size", INDEXED | STORED);
         let modified_at = schema_builder.add_i64_field("modified_at", INDEXED | STORED);
         let file_type = schema_builder.add_text_field("file_type", STRING | STORED);
-        let content = schema_builder.add_text_field("content", TEXT);
+        let content = schema_builder.add_text_field("content", analyzed_text(config.analyzer));
+        let extra = config
+            .extra_fields
+            .iter()
+            .map(|f| {
+                (
+                    f.clone(),
+                    schema_builder.add_text_field(&f.name, STRING | STORED),
+                )
+            })
+            .collect();
 
         let schema = schema_builder.build();
 
+        // Schema 签名不一致（含升级前未记录签名的索引）时清空索引，由调用方重建
+        let signature = schema_signature(&config.extra_fields);
+        let signature_path = index_path.join(SCHEMA_VERSION_FILE);
+        let mut needs_reindex = false;
+        if index_path.join("meta.json").exists()
+            && std::fs::read_to_string(&signature_path).ok().as_deref() != Some(signature.as_str())
+        {
+            warn!("索引 Schema 已变化，清空旧索引等待重建: {:?}", index_path);
+            std::fs::remove_dir_all(&index_path)
+                .and_then(|_| std::fs::create_dir_all(&index_path))
+                .map_err(|e| NasError::Storage(format!("清空旧索引失败: {}", e)))?;
+            needs_reindex = true;
+        }
+        // 上次重建中断，需从断点继续
+        if index_path.join(REINDEX_CHECKPOINT_FILE).exists() {
+            info!("检测到未完成的索引重建断点: {:?}", index_path);
+            needs_reindex = true;
+        }
+
         // 打开或创建索引
         let index = if index_path.join("meta.json").exists() {
             Index::open_in_dir(&index_path)
                 .map_err(|e| NasError::Storage(format!("打开索引失败: {}", e)))?
         } else {
-            Index::create_in_dir(&index_path, schema.clone())
-                .map_err(|e| NasError::Storage(format!("创建索引失败: {}", e)))?
+            let index = Index::create_in_dir(&index_path, schema.clone())
+                .map_err(|e| NasError::Storage(format!("创建索引失败: {}", e)))?;
+            std::fs::write(&signature_path, &signature)
+                .map_err(|e| NasError::Storage(format!("写入索引 Schema 版本失败: {}", e)))?;
+            index
         };
 
+        // 注册自定义分词器（已有索引的 Schema 可能引用它）
+        index
+            .tokenizers()
+            .register(CJK_BIGRAM_TOKENIZER, analyzer::cjk_bigram_analyzer());
+        if let Some(existing) = field_tokenizer(&index, "content")
+            && existing != config.analyzer.tokenizer_name()
+        {
+            warn!(
+                "已有索引使用分词器 {}，与配置的 {} 不一致；删除索引目录后重建才会生效",
+                existing,
+                config.analyzer.tokenizer_name()
+            );
+        }
+
         // 创建索引写入器（处理意外遗留的锁文件）
         let writer = match index.writer(50_000_000) {
             Ok(w) => w,
@@ -151,63 +434,24 @@ impl SearchEngine {
                 modified_at,
                 file_type,
                 content,
+                extra,
             },
             content_extractor,
             storage_root,
             incremental_indexer,
+            index_path,
+            needs_reindex: AtomicBool::new(needs_reindex),
+            max_limit: config.max_limit,
+            max_fuzzy_distance: config.max_fuzzy_distance,
+            query_timeout: (config.query_timeout_ms > 0)
+                .then(|| Duration::from_millis(config.query_timeout_ms)),
+            index_concurrency: config.index_concurrency,
         })
     }
 
     /// 索引单个文件
     pub async fn index_file(&self, file_meta: &FileMetadata) -> Result<()> {
-        let fields = &self.schema_fields;
-
-        // 提取文件内容
-        let file_path = self.storage_root.join(&file_meta.path);
-        let mut content = String::new();
-        #[allow(unused_assignments)]
-        let mut file_type_str = String::new();
-
-        if file_path.exists() && file_path.is_file() {
-            // 尝试提取文件内容
-            match self.content_extractor.extract_content(&file_path) {
-                Ok(extraction_result) => {
-                    content = extraction_result.content;
-                    file_type_str = match extraction_result.file_type {
-                        FileType::Text => "text".to_string(),
-                        FileType::Html => "html".to_string(),
-                        FileType::Markdown => "markdown".to_string(),
-                        FileType::Pdf => "pdf".to_string(),
-                        FileType::Code => "code".to_string(),
-                        FileType::Log => "log".to_string(),
-                        FileType::Binary => "binary".to_string(),
-                        FileType::Unknown => "unknown".to_string(),
-                    };
-                }
-                Err(e) => {
-                    warn!("提取文件内容失败 {}: {}", file_path.display(), e);
-                    // 即使内容提取失败，也继续索引元数据
-                    file_type_str = "unknown".to_string();
-                }
-            }
-        } else {
-            debug!(
-                "文件不存在或不是文件，跳过内容提取: {}",
-                file_path.display()
-            );
-            file_type_str = "unknown".to_string();
-        }
-
-        let doc = doc!(
-            fields.file_id => file_meta.id.clone(),
-            fields.path => file_meta.path.clone(),
-            fields.name => file_meta.name.clone(),
-            fields.size => file_meta.size,
-            fields.modified_at => file_meta.modified_at.and_utc().timestamp(),
-            fields.file_type => file_type_str,
-            fields.content => content.clone(),
-        );
-
+        let doc = self.extract_document(file_meta.clone()).await?;
         {
             let writer = self.writer.write().await;
             writer
@@ -215,73 +459,47 @@ impl SearchEngine {
                 .map_err(|e| NasError::Storage(format!("添加文档到索引失败: {}", e)))?;
         } // 释放锁
 
-        debug!(
-            "文件已索引: {} ({}) - 内容长度: {} 字节",
-            file_meta.name,
-            file_meta.id,
-            content.len()
-        );
+        debug!("文件已索引: {} ({})", file_meta.name, file_meta.id);
         Ok(())
     }
 
     /// 批量索引文件
+    ///
+    /// 内容提取在阻塞线程池中并发进行（最多 `index_concurrency` 个文件），不持有写锁；
+    /// 提取完成的文档每 `INDEX_WRITE_BATCH` 个获取一次写锁批量写入。
+    /// 单个文件内容提取失败时只索引其元数据，不中断整批。
     #[allow(dead_code)]
     pub async fn index_files(&self, files: &[FileMetadata]) -> Result<()> {
-        let fields = &self.schema_fields;
-        {
-            let writer = self.writer.write().await;
-
-            for file_meta in files {
-                // 提取文件内容
-                let file_path = self.storage_root.join(&file_meta.path);
-                let mut content = String::new();
-                #[allow(unused_assignments)]
-                let mut file_type_str = String::new();
-
-                if file_path.exists() && file_path.is_file() {
-                    match self.content_extractor.extract_content(&file_path) {
-                        Ok(extraction_result) => {
-                            content = extraction_result.content;
-                            file_type_str = match extraction_result.file_type {
-                                FileType::Text => "text".to_string(),
-                                FileType::Html => "html".to_string(),
-                                FileType::Markdown => "markdown".to_string(),
-                                FileType::Pdf => "pdf".to_string(),
-                                FileType::Code => "code".to_string(),
-                                FileType::Log => "log".to_string(),
-                                FileType::Binary => "binary".to_string(),
-                                FileType::Unknown => "unknown".to_string(),
-                            };
-                        }
-                        Err(e) => {
-                            warn!("提取文件内容失败 {}: {}", file_path.display(), e);
-                            file_type_str = "unknown".to_string();
-                        }
-                    }
-                } else {
-                    file_type_str = "unknown".to_string();
-                }
-
-                let doc = doc!(
-                    fields.file_id => file_meta.id.clone(),
-                    fields.path => file_meta.path.clone(),
-                    fields.name => file_meta.name.clone(),
-                    fields.size => file_meta.size,
-                    fields.modified_at => file_meta.modified_at.and_utc().timestamp(),
-                    fields.file_type => file_type_str,
-                    fields.content => content.clone(),
-                );
+        let mut batches = stream::iter(files.iter().cloned())
+            .map(|file_meta| self.extract_document(file_meta))
+            .buffer_unordered(self.index_concurrency)
+            .chunks(INDEX_WRITE_BATCH);
 
+        while let Some(batch) = batches.next().await {
+            let writer = self.writer.write().await;
+            for doc in batch {
                 writer
-                    .add_document(doc)
+                    .add_document(doc?)
                     .map_err(|e| NasError::Storage(format!("添加文档到索引失败: {}", e)))?;
             }
-        } // 释放锁
+        }
 
         info!("批量索引完成: {} 个文件", files.len());
         Ok(())
     }
 
+    /// 在阻塞线程池中提取文件内容并构建索引文档
+    async fn extract_document(&self, file_meta: FileMetadata) -> Result<TantivyDocument> {
+        let fields = self.schema_fields.clone();
+        let extractor = self.content_extractor.clone();
+        let file_path = self.storage_root.join(&file_meta.path);
+        tokio::task::spawn_blocking(move || {
+            build_document(&fields, &extractor, &file_path, &file_meta)
+        })
+        .await
+        .map_err(|e| NasError::Storage(format!("内容提取任务失败: {}", e)))
+    }
+
     /// 提交索引更改
     pub async fn commit(&self) -> Result<()> {
         let mut writer = self.writer.write().await;
@@ -318,79 +536,164 @@ impl SearchEngine {
         limit: usize,
         offset: usize,
     ) -> Result<Vec<SearchResult>> {
-        use tantivy::collector::TopDocs;
-        use tantivy::query::QueryParser;
+        self.search_with_filters(query_str, &[], limit, offset)
+            .await
+    }
 
-        // 空查询直接返回空结果
-        if query_str.trim().is_empty() {
+    /// 搜索文件，并要求附加字段精确匹配给定值
+    ///
+    /// `filters` 为 `(字段名, 值)` 列表，所有条件同时满足；查询为空时只按过滤条件匹配。
+    pub async fn search_with_filters(
+        &self,
+        query_str: &str,
+        filters: &[(String, String)],
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_fuzzy(query_str, filters, 0, limit, offset)
+            .await
+    }
+
+    /// 模糊搜索文件，`fuzzy_distance` 为查询词允许的编辑距离（0 表示精确匹配）
+    ///
+    /// 查询受配置的成本上限约束：`limit` 超过 `max_limit` 时截断，
+    /// 编辑距离超过 `max_fuzzy_distance` 时返回 [`NasError::InvalidQuery`]，
+    /// 执行超过 `query_timeout_ms` 时返回错误。每次查询记录延迟与结果指标。
+    pub async fn search_fuzzy(
+        &self,
+        query_str: &str,
+        filters: &[(String, String)],
+        fuzzy_distance: u8,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<SearchResult>> {
+        // 空查询且无过滤条件直接返回空结果
+        if query_str.trim().is_empty() && filters.is_empty() {
             return Ok(Vec::new());
         }
 
-        let searcher = self.reader.searcher();
-        let fields = &self.schema_fields;
-
-        // 创建查询解析器，搜索 path、name 和 content 字段
-        let query_parser =
-            QueryParser::for_index(&self.index, vec![fields.path, fields.name, fields.content]);
-
-        let query = query_parser
-            .parse_query(query_str)
-            .map_err(|e| NasError::Storage(format!("解析搜索查询失败: {}", e)))?;
-
-        // 执行搜索
-        let top_docs = searcher
-            .search(&query, &TopDocs::with_limit(limit + offset))
-            .map_err(|e| NasError::Storage(format!("搜索失败: {}", e)))?;
-
-        // 转换结果
-        let mut results = Vec::new();
-        for (_score, doc_address) in top_docs.into_iter().skip(offset) {
-            let retrieved_doc: TantivyDocument = searcher
-                .doc(doc_address)
-                .map_err(|e| NasError::Storage(format!("获取文档失败: {}", e)))?;
-
-            let file_id = retrieved_doc
-                .get_first(fields.file_id)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-
-            let path = retrieved_doc
-                .get_first(fields.path)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
+        let started = std::time::Instant::now();
+        let result = self
+            .run_query(query_str, filters, fuzzy_distance, limit, offset)
+            .await;
+        let elapsed = started.elapsed().as_secs_f64();
+        match &result {
+            Ok(results) => crate::metrics::record_search_query("success", elapsed, results.len()),
+            Err(_) => crate::metrics::record_search_query("error", elapsed, 0),
+        }
+        result
+    }
 
-            let name = retrieved_doc
-                .get_first(fields.name)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
+    /// 按成本上限校验并执行查询
+    async fn run_query(
+        &self,
+        query_str: &str,
+        filters: &[(String, String)],
+        fuzzy_distance: u8,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<SearchResult>> {
+        use tantivy::collector::TopDocs;
+        use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, TermQuery};
+
+        if fuzzy_distance > self.max_fuzzy_distance {
+            crate::metrics::record_search_limited("fuzzy_rejected");
+            return Err(NasError::InvalidQuery(format!(
+                "模糊距离 {} 超过上限 {}",
+                fuzzy_distance, self.max_fuzzy_distance
+            )));
+        }
+        let limit = if limit > self.max_limit {
+            crate::metrics::record_search_limited("limit_clamped");
+            debug!("搜索结果数 {} 超过上限，截断为 {}", limit, self.max_limit);
+            self.max_limit
+        } else {
+            limit
+        };
 
-            let size = retrieved_doc
-                .get_first(fields.size)
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0);
+        let searcher = self.reader.searcher();
+        let fields = self.schema_fields.clone();
 
-            let modified_at = retrieved_doc
-                .get_first(fields.modified_at)
-                .and_then(|v| v.as_i64())
-                .unwrap_or(0);
+        // 创建查询解析器，搜索 path、name 和 content 字段
+        let text_query: Box<dyn Query> = if query_str.trim().is_empty() {
+            Box::new(AllQuery)
+        } else {
+            let mut query_parser =
+                QueryParser::for_index(&self.index, vec![fields.path, fields.name, fields.content]);
+            if fuzzy_distance > 0 {
+                for field in [fields.path, fields.name, fields.content] {
+                    query_parser.set_field_fuzzy(field, false, fuzzy_distance, true);
+                }
+            }
+            query_parser
+                .parse_query(query_str)
+                .map_err(|e| NasError::Storage(format!("解析搜索查询失败: {}", e)))?
+        };
 
-            results.push(SearchResult {
-                file_id,
-                path,
-                name,
-                size,
-                modified_at,
-                score: _score,
-            });
+        let mut clauses = vec![(Occur::Must, text_query)];
+        for (name, value) in filters {
+            let field = fields
+                .extra_field(name)
+                .ok_or_else(|| NasError::Other(format!("未知的过滤字段: {}", name)))?;
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(field, value),
+                    IndexRecordOption::Basic,
+                )),
+            ));
         }
+        let query = BooleanQuery::new(clauses);
+
+        // 在阻塞线程池中执行搜索，超时后不再等待结果
+        let task = tokio::task::spawn_blocking(move || -> Result<Vec<SearchResult>> {
+            let top_docs = searcher
+                .search(&query, &TopDocs::with_limit(limit + offset))
+                .map_err(|e| NasError::Storage(format!("搜索失败: {}", e)))?;
+
+            // 转换结果
+            let mut results = Vec::new();
+            for (score, doc_address) in top_docs.into_iter().skip(offset) {
+                let retrieved_doc: TantivyDocument = searcher
+                    .doc(doc_address)
+                    .map_err(|e| NasError::Storage(format!("获取文档失败: {}", e)))?;
+                results.push(fields.to_result(&retrieved_doc, score));
+            }
+            Ok(results)
+        });
+        let joined = match self.query_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, task).await {
+                Ok(joined) => joined,
+                Err(_) => {
+                    crate::metrics::record_search_limited("timeout");
+                    return Err(NasError::Other(format!(
+                        "搜索超时（超过 {} 毫秒）",
+                        timeout.as_millis()
+                    )));
+                }
+            },
+            None => task.await,
+        };
+        let results = joined.map_err(|e| NasError::Other(format!("搜索任务异常退出: {}", e)))??;
 
         debug!("搜索完成: 找到 {} 个结果", results.len());
         Ok(results)
     }
 
+    /// 可用于过滤的附加字段名
+    pub fn extra_field_names(&self) -> Vec<&str> {
+        self.schema_fields
+            .extra
+            .iter()
+            .map(|(extra, _)| extra.name.as_str())
+            .collect()
+    }
+
+    /// 索引是否因 Schema 变化被清空或上次重建未完成、需要重建
+    pub fn needs_reindex(&self) -> bool {
+        self.needs_reindex.load(Ordering::Relaxed)
+    }
+
     /// 按文件名搜索
     #[allow(dead_code)]
     pub async fn search_by_name(&self, name: &str, limit: usize) -> Result<Vec<SearchResult>> {
@@ -398,25 +701,125 @@ impl SearchEngine {
     }
 
     /// 重建索引（从存储管理器获取所有文件）
+    ///
+    /// 按文件 ID 升序分批索引并提交，每批提交后记录断点，已提交的部分立即可查询。
+    /// 存在上次中断留下的断点时不清空索引，从断点之后继续。
     #[allow(dead_code)]
     pub async fn rebuild_index(&self, files: &[FileMetadata]) -> Result<()> {
-        info!("开始重建索引...");
+        self.rebuild_index_batched(files, REINDEX_BATCH_SIZE, None)
+            .await
+    }
 
-        // 清空现有索引
-        let mut writer = self.writer.write().await;
-        writer
-            .delete_all_documents()
-            .map_err(|e| NasError::Storage(format!("清空索引失败: {}", e)))?;
-        writer
-            .commit()
-            .map_err(|e| NasError::Storage(format!("提交清空失败: {}", e)))?;
-        drop(writer);
+    /// 分批重建索引，`max_batches` 用于在提交指定批数后停止（模拟中断）
+    async fn rebuild_index_batched(
+        &self,
+        files: &[FileMetadata],
+        batch_size: usize,
+        max_batches: Option<usize>,
+    ) -> Result<()> {
+        let mut checkpoint = match self.load_reindex_checkpoint().await? {
+            Some(checkpoint) => {
+                info!(
+                    "从断点继续重建索引: 已完成 {} 个文件，最后文件 {}",
+                    checkpoint.indexed, checkpoint.last_file_id
+                );
+                checkpoint
+            }
+            None => {
+                info!("开始重建索引...");
+
+                // 清空现有索引
+                let mut writer = self.writer.write().await;
+                writer
+                    .delete_all_documents()
+                    .map_err(|e| NasError::Storage(format!("清空索引失败: {}", e)))?;
+                writer
+                    .commit()
+                    .map_err(|e| NasError::Storage(format!("提交清空失败: {}", e)))?;
+                drop(writer);
 
-        // 重新索引所有文件
-        self.index_files(files).await?;
+                let checkpoint = ReindexCheckpoint::default();
+                self.save_reindex_checkpoint(&checkpoint).await?;
+                checkpoint
+            }
+        };
+
+        let mut pending: Vec<FileMetadata> = files
+            .iter()
+            .filter(|f| f.id > checkpoint.last_file_id)
+            .cloned()
+            .collect();
+        pending.sort_by(|a, b| a.id.cmp(&b.id));
+
+        for (batch_no, batch) in pending.chunks(batch_size.max(1)).enumerate() {
+            if max_batches.is_some_and(|max| batch_no >= max) {
+                return Ok(());
+            }
+
+            // 先按 file_id 删除：断点写入前中断时同一批会被重新提交，避免重复文档
+            {
+                let writer = self.writer.write().await;
+                for file_meta in batch {
+                    writer.delete_term(Term::from_field_text(
+                        self.schema_fields.file_id,
+                        &file_meta.id,
+                    ));
+                }
+            }
+            self.index_files(batch).await?;
+            self.commit().await?;
+
+            checkpoint.indexed += batch.len();
+            if let Some(last) = batch.last() {
+                checkpoint.last_file_id = last.id.clone();
+            }
+            self.save_reindex_checkpoint(&checkpoint).await?;
+            debug!("索引重建进度: {} 个文件", checkpoint.indexed);
+        }
+
+        // 没有待索引文件时也需提交一次，使读取器反映清空后的索引
         self.commit().await?;
+        match tokio::fs::remove_file(self.index_path.join(REINDEX_CHECKPOINT_FILE)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(NasError::Storage(format!("删除索引重建断点失败: {}", e))),
+        }
+
+        self.needs_reindex.store(false, Ordering::Relaxed);
+        info!("索引重建完成: {} 个文件", checkpoint.indexed);
+        Ok(())
+    }
+
+    /// 读取重建断点
+    async fn load_reindex_checkpoint(&self) -> Result<Option<ReindexCheckpoint>> {
+        let path = self.index_path.join(REINDEX_CHECKPOINT_FILE);
+        let data = match tokio::fs::read(&path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(NasError::Storage(format!("读取索引重建断点失败: {}", e))),
+        };
+        match serde_json::from_slice(&data) {
+            Ok(checkpoint) => Ok(Some(checkpoint)),
+            Err(e) => {
+                // 断点损坏时从头重建
+                warn!("索引重建断点损坏，将从头重建: {}", e);
+                Ok(None)
+            }
+        }
+    }
 
-        info!("索引重建完成: {} 个文件", files.len());
+    /// 原子写入重建断点
+    async fn save_reindex_checkpoint(&self, checkpoint: &ReindexCheckpoint) -> Result<()> {
+        let path = self.index_path.join(REINDEX_CHECKPOINT_FILE);
+        let tmp = path.with_extension("json.tmp");
+        let data = serde_json::to_vec(checkpoint)
+            .map_err(|e| NasError::Storage(format!("序列化索引重建断点失败: {}", e)))?;
+        tokio::fs::write(&tmp, data)
+            .await
+            .map_err(|e| NasError::Storage(format!("写入索引重建断点失败: {}", e)))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .map_err(|e| NasError::Storage(format!("写入索引重建断点失败: {}", e)))?;
         Ok(())
     }
 
@@ -502,12 +905,167 @@ impl SearchEngine {
         info!("自动增量更新已启动，间隔: {}ms", interval.as_millis());
     }
 
+    /// 跟随存储变更通知清理失效的索引条目（后台任务）
+    ///
+    /// 删除的文件移出索引，移动的文件按新ID重新索引；新建与更新由上传路径直接索引。
+    pub fn follow_mutations(self: &Arc<Self>, mut events: broadcast::Receiver<MutationEvent>) {
+        let search_engine = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => search_engine.apply_mutation(&event).await,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("搜索索引跳过了 {} 个存储变更通知", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn apply_mutation(&self, event: &MutationEvent) {
+        let moved_to = match &event.op {
+            MutationOp::Deleted => None,
+            MutationOp::Moved { to } => Some(to),
+            MutationOp::Created | MutationOp::Updated => return,
+        };
+
+        if let Err(e) = self.delete_file(&event.file_id).await {
+            warn!("删除索引失败: {} - {}", event.file_id, e);
+        }
+        if let Some(to) = moved_to
+            && let Some(storage) = crate::storage::try_storage()
+        {
+            match crate::storage::StorageManagerTrait::get_metadata(storage, to).await {
+                Ok(metadata) => {
+                    if let Err(e) = self.index_file(&metadata).await {
+                        warn!("索引移动后的文件失败: {} - {}", to, e);
+                    }
+                }
+                Err(e) => warn!("读取移动后的文件元数据失败: {} - {}", to, e),
+            }
+        }
+        if let Err(e) = self.commit().await {
+            warn!("提交索引失败: {}", e);
+        }
+    }
+
     /// 获取增量索引统计
     pub async fn get_incremental_stats(&self) -> incremental_indexer::UpdateStats {
         self.incremental_indexer.get_stats().await
     }
 }
 
+/// 硬删除时立即移出索引并提交，不留检索痕迹
+#[async_trait::async_trait]
+impl silent_storage::EraseHook for SearchEngine {
+    async fn erase(&self, file_id: &str) -> silent_storage::Result<()> {
+        let to_storage_error = |e: NasError| silent_storage::StorageError::Storage(e.to_string());
+        self.delete_file(file_id).await.map_err(to_storage_error)?;
+        self.commit().await.map_err(to_storage_error)
+    }
+}
+
+/// 使用指定分析器分词的文本字段（含词频与位置，支持短语查询）
+fn analyzed_text(analyzer: AnalyzerKind) -> TextOptions {
+    TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(analyzer.tokenizer_name())
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    )
+}
+
+/// 提取文件内容并构建索引文档
+///
+/// 文件不存在或内容提取失败时只索引元数据，文件类型记为 `unknown`
+fn build_document(
+    fields: &SchemaFields,
+    extractor: &ContentExtractor,
+    file_path: &Path,
+    file_meta: &FileMetadata,
+) -> TantivyDocument {
+    let (content, file_type) = if file_path.is_file() {
+        match extractor.extract_content(file_path) {
+            Ok(extraction_result) => {
+                let file_type = match extraction_result.file_type {
+                    FileType::Text => "text",
+                    FileType::Html => "html",
+                    FileType::Markdown => "markdown",
+                    FileType::Pdf => "pdf",
+                    FileType::Code => "code",
+                    FileType::Log => "log",
+                    FileType::Binary => "binary",
+                    FileType::Unknown => "unknown",
+                };
+                (extraction_result.content, file_type)
+            }
+            Err(e) => {
+                warn!("提取文件内容失败 {}: {}", file_path.display(), e);
+                (String::new(), "unknown")
+            }
+        }
+    } else {
+        debug!(
+            "文件不存在或不是文件，跳过内容提取: {}",
+            file_path.display()
+        );
+        (String::new(), "unknown")
+    };
+
+    let mut doc = doc!(
+        fields.file_id => file_meta.id.clone(),
+        fields.path => file_meta.path.clone(),
+        fields.name => file_meta.name.clone(),
+        fields.size => file_meta.size,
+        fields.modified_at => file_meta.modified_at.and_utc().timestamp(),
+        fields.file_type => file_type.to_string(),
+        fields.content => content,
+    );
+    fields.add_extra_values(&mut doc, file_meta);
+    doc
+}
+
+/// 校验附加字段：名称非空、不与内置字段或彼此重名
+fn validate_extra_fields(extra_fields: &[ExtraField]) -> Result<()> {
+    let mut seen = std::collections::HashSet::new();
+    for field in extra_fields {
+        if field.name.trim().is_empty() {
+            return Err(NasError::Config("附加索引字段名不能为空".to_string()));
+        }
+        if BUILTIN_FIELDS.contains(&field.name.as_str()) {
+            return Err(NasError::Config(format!(
+                "附加索引字段 {} 与内置字段重名",
+                field.name
+            )));
+        }
+        if !seen.insert(field.name.as_str()) {
+            return Err(NasError::Config(format!(
+                "附加索引字段 {} 重复",
+                field.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 索引 Schema 签名：Schema 版本加附加字段列表
+fn schema_signature(extra_fields: &[ExtraField]) -> String {
+    let names: Vec<&str> = extra_fields.iter().map(|f| f.name.as_str()).collect();
+    format!("{}:{}", INDEX_SCHEMA_VERSION, names.join(","))
+}
+
+/// 读取索引中文本字段实际使用的分词器
+fn field_tokenizer(index: &Index, field_name: &str) -> Option<String> {
+    let schema = index.schema();
+    let field = schema.get_field(field_name).ok()?;
+    match schema.get_field_entry(field).field_type() {
+        FieldType::Str(options) => options
+            .get_indexing_options()
+            .map(|indexing| indexing.tokenizer().to_string()),
+        _ => None,
+    }
+}
+
 /// 索引统计信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexStats {
@@ -530,6 +1088,7 @@ mod tests {
             hash: "test_hash".to_string(),
             created_at: Utc::now().naive_local(),
             modified_at: Utc::now().naive_local(),
+            user_metadata: Default::default(),
         }
     }
 
@@ -565,6 +1124,73 @@ mod tests {
         assert_eq!(results[0].name, "test.txt");
     }
 
+    #[tokio::test]
+    async fn test_search_limits_clamp_and_record_latency() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = SearchConfig {
+            max_limit: 2,
+            max_fuzzy_distance: 1,
+            ..Default::default()
+        };
+        let engine = SearchEngine::with_config(
+            temp_dir.path().join("index"),
+            temp_dir.path().to_path_buf(),
+            config,
+        )
+        .unwrap();
+        for i in 0..5 {
+            let file = create_test_metadata(
+                &i.to_string(),
+                &format!("report{}.txt", i),
+                &format!("/docs/report{}.txt", i),
+            );
+            engine.index_file(&file).await.unwrap();
+        }
+        engine.commit().await.unwrap();
+
+        let histogram = crate::metrics::SEARCH_QUERY_DURATION_SECONDS.with_label_values(&[]);
+        let clamped =
+            crate::metrics::SEARCH_QUERIES_LIMITED_TOTAL.with_label_values(&["limit_clamped"]);
+        let samples_before = histogram.get_sample_count();
+        let clamped_before = clamped.get();
+
+        // 超过上限的 limit 被截断
+        let results = engine.search("docs", 100, 0).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(histogram.get_sample_count() > samples_before);
+        assert!(clamped.get() > clamped_before);
+
+        // 模糊距离在上限内可匹配拼写错误，超过上限被拒绝
+        let results = engine.search_fuzzy("reprt0", &[], 1, 10, 0).await.unwrap();
+        assert_eq!(results.len(), 1);
+        let err = engine
+            .search_fuzzy("reprt0", &[], 2, 10, 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, NasError::InvalidQuery(_)));
+    }
+
+    #[tokio::test]
+    async fn test_empty_file_searchable_by_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_path = temp_dir.path().join("index");
+        let storage_root = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(storage_root.join("notes")).unwrap();
+        std::fs::write(storage_root.join("notes/placeholder.txt"), b"").unwrap();
+
+        let engine = SearchEngine::new(index_path, storage_root).unwrap();
+
+        let mut file = create_test_metadata("empty", "placeholder.txt", "notes/placeholder.txt");
+        file.size = 0;
+        engine.index_file(&file).await.unwrap();
+        engine.commit().await.unwrap();
+
+        let results = engine.search("placeholder", 10, 0).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, "empty");
+        assert_eq!(results[0].size, 0);
+    }
+
     #[tokio::test]
     async fn test_delete_file() {
         let temp_dir = TempDir::new().unwrap();
@@ -624,6 +1250,136 @@ mod tests {
         assert!(!results.is_empty(), "应该找到 image.png");
     }
 
+    /// 在 `dir` 下创建 `count` 个文本文件，第 i 个文件内容包含唯一词 `uniqueword{i}`
+    fn create_text_files(dir: &Path, count: usize) -> Vec<FileMetadata> {
+        (0..count)
+            .map(|i| {
+                let path = dir.join(format!("doc{}.txt", i));
+                std::fs::write(&path, format!("bulk indexing uniqueword{} ", i).repeat(200))
+                    .unwrap();
+                create_test_metadata(
+                    &format!("{:05}", i),
+                    &format!("doc{}.txt", i),
+                    path.to_str().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_parallel_batch_indexing_indexes_all_documents() {
+        let temp_dir = TempDir::new().unwrap();
+        let files_dir = temp_dir.path().join("files");
+        std::fs::create_dir_all(&files_dir).unwrap();
+
+        let mut files = create_text_files(&files_dir, 150);
+        // 内容提取失败（非 UTF-8）与文件不存在都只索引元数据，不中断整批
+        let broken = files_dir.join("broken.txt");
+        std::fs::write(&broken, [0xff, 0xfe, 0xfd]).unwrap();
+        files.push(create_test_metadata(
+            "broken",
+            "broken.txt",
+            broken.to_str().unwrap(),
+        ));
+        files.push(create_test_metadata(
+            "missing",
+            "missing.txt",
+            files_dir.join("missing.txt").to_str().unwrap(),
+        ));
+
+        let config = SearchConfig {
+            index_concurrency: 8,
+            ..Default::default()
+        };
+        let engine = SearchEngine::with_config(
+            temp_dir.path().join("index"),
+            temp_dir.path().to_path_buf(),
+            config,
+        )
+        .unwrap();
+        engine.index_files(&files).await.unwrap();
+        engine.commit().await.unwrap();
+
+        assert_eq!(engine.get_stats().total_documents, files.len());
+        for i in [0, 77, 149] {
+            let results = engine
+                .search(&format!("uniqueword{}", i), 10, 0)
+                .await
+                .unwrap();
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].file_id, format!("{:05}", i));
+        }
+        assert!(!engine.search("broken.txt", 10, 0).await.unwrap().is_empty());
+        assert!(
+            !engine
+                .search("missing.txt", 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // 并发数为 0 的配置被拒绝
+        let config = SearchConfig {
+            index_concurrency: 0,
+            ..Default::default()
+        };
+        assert!(
+            SearchEngine::with_config(
+                temp_dir.path().join("index_zero"),
+                temp_dir.path().to_path_buf(),
+                config,
+            )
+            .is_err()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_parallel_batch_indexing_matches_serial() {
+        let temp_dir = TempDir::new().unwrap();
+        let files_dir = temp_dir.path().join("files");
+        std::fs::create_dir_all(&files_dir).unwrap();
+        let files = create_text_files(&files_dir, 300);
+
+        // 串行与并发提取的索引结果一致
+        let mut indexed = Vec::new();
+        for concurrency in [1, 8] {
+            let config = SearchConfig {
+                index_concurrency: concurrency,
+                ..Default::default()
+            };
+            let engine = SearchEngine::with_config(
+                temp_dir.path().join(format!("index_{}", concurrency)),
+                temp_dir.path().to_path_buf(),
+                config,
+            )
+            .unwrap();
+            engine.index_files(&files).await.unwrap();
+            engine.commit().await.unwrap();
+            assert_eq!(engine.get_stats().total_documents, files.len());
+
+            for i in (0..files.len()).step_by(37) {
+                let results = engine
+                    .search(&format!("uniqueword{}", i), 10, 0)
+                    .await
+                    .unwrap();
+                assert_eq!(results.len(), 1);
+                assert_eq!(results[0].file_id, format!("{:05}", i));
+            }
+
+            let mut ids: Vec<String> = engine
+                .search("indexing", files.len() * 2, 0)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|r| r.file_id)
+                .collect();
+            ids.sort();
+            assert_eq!(ids.len(), files.len());
+            indexed.push(ids);
+        }
+        assert_eq!(indexed[0], indexed[1]);
+    }
+
     #[tokio::test]
     async fn test_search_pagination() {
         let temp_dir = TempDir::new().unwrap();
@@ -731,6 +1487,49 @@ mod tests {
         assert_eq!(results[0].name, "文档.txt");
     }
 
+    #[tokio::test]
+    async fn test_cjk_bigram_analyzer_matches_interior_substring() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage_root = temp_dir.path().to_path_buf();
+        std::fs::write(
+            storage_root.join("notes.txt"),
+            "我们正在测试全文搜索功能，确保中文内容可以被检索。",
+        )
+        .unwrap();
+        let file = create_test_metadata("1", "项目计划书.txt", "notes.txt");
+
+        // 默认分词器把整句当作一个词，词内子串无法命中
+        let default_engine =
+            SearchEngine::new(temp_dir.path().join("index_default"), storage_root.clone()).unwrap();
+        default_engine.index_file(&file).await.unwrap();
+        default_engine.commit().await.unwrap();
+        assert!(
+            default_engine
+                .search("全文搜索", 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let engine = SearchEngine::with_config(
+            temp_dir.path().join("index_cjk"),
+            storage_root,
+            SearchConfig {
+                analyzer: AnalyzerKind::CjkBigram,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        engine.index_file(&file).await.unwrap();
+        engine.commit().await.unwrap();
+
+        let results = engine.search("全文搜索", 10, 0).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, "1");
+        assert_eq!(engine.search("计划", 10, 0).await.unwrap().len(), 1);
+        assert!(engine.search("搜索全文", 10, 0).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_empty_search_query() {
         let temp_dir = TempDir::new().unwrap();
@@ -768,4 +1567,156 @@ mod tests {
         let stats = engine.get_stats();
         assert_eq!(stats.total_documents, 1);
     }
+
+    #[tokio::test]
+    async fn test_filter_search_by_owner() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine =
+            SearchEngine::new(temp_dir.path().join("index"), temp_dir.path().to_path_buf())
+                .unwrap();
+
+        let mut alice = create_test_metadata("1", "report_q1.txt", "/files/report_q1.txt");
+        alice.user_metadata.insert("owner".into(), "alice".into());
+        alice
+            .user_metadata
+            .insert("tags".into(), "work, finance".into());
+        let mut bob = create_test_metadata("2", "report_q2.txt", "/files/report_q2.txt");
+        bob.user_metadata.insert("owner".into(), "bob".into());
+        engine.index_file(&alice).await.unwrap();
+        engine.index_file(&bob).await.unwrap();
+        engine.commit().await.unwrap();
+
+        let owner = |v: &str| vec![("owner".to_string(), v.to_string())];
+        let results = engine
+            .search_with_filters("report", &owner("alice"), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, "1");
+
+        // 空查询只按过滤条件匹配；多值字段按拆分后的值匹配
+        let results = engine
+            .search_with_filters("", &owner("bob"), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, "2");
+        let tags = vec![("tags".to_string(), "finance".to_string())];
+        let results = engine.search_with_filters("", &tags, 10, 0).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, "1");
+
+        assert!(
+            engine
+                .search_with_filters("", &[("unknown".into(), "x".into())], 10, 0)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schema_change_triggers_reindex() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_path = temp_dir.path().join("index");
+        let storage_root = temp_dir.path().to_path_buf();
+
+        {
+            let engine = SearchEngine::new(index_path.clone(), storage_root.clone()).unwrap();
+            assert!(!engine.needs_reindex());
+            let file = create_test_metadata("1", "test.txt", "/files/test.txt");
+            engine.index_file(&file).await.unwrap();
+            engine.commit().await.unwrap();
+        }
+
+        // 字段不变时沿用已有索引
+        let engine = SearchEngine::new(index_path.clone(), storage_root.clone()).unwrap();
+        assert!(!engine.needs_reindex());
+        assert_eq!(engine.get_stats().total_documents, 1);
+        drop(engine);
+
+        let config = SearchConfig {
+            extra_fields: vec![ExtraField::single("owner"), ExtraField::single("project")],
+            ..Default::default()
+        };
+        let engine = SearchEngine::with_config(index_path, storage_root, config).unwrap();
+        assert!(engine.needs_reindex());
+        assert_eq!(engine.get_stats().total_documents, 0);
+        assert_eq!(engine.extra_field_names(), vec!["owner", "project"]);
+
+        let file = create_test_metadata("1", "test.txt", "/files/test.txt");
+        engine.rebuild_index(&[file]).await.unwrap();
+        assert!(!engine.needs_reindex());
+        assert_eq!(engine.get_stats().total_documents, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_index_resumes_from_checkpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_path = temp_dir.path().join("index");
+        let storage_root = temp_dir.path().to_path_buf();
+        let files: Vec<FileMetadata> = (0..10)
+            .map(|i| {
+                create_test_metadata(
+                    &format!("file-{:02}", i),
+                    &format!("doc{}.txt", i),
+                    &format!("/files/doc{}.txt", i),
+                )
+            })
+            .collect();
+
+        {
+            let engine = SearchEngine::new(index_path.clone(), storage_root.clone()).unwrap();
+            // 提交 2 批（6 个文件）后中断
+            engine
+                .rebuild_index_batched(&files, 3, Some(2))
+                .await
+                .unwrap();
+            // 已提交的部分可查询
+            assert_eq!(engine.get_stats().total_documents, 6);
+        }
+
+        // 重启后识别到未完成的重建，从断点继续
+        let engine = SearchEngine::new(index_path.clone(), storage_root).unwrap();
+        assert!(engine.needs_reindex());
+        assert_eq!(engine.get_stats().total_documents, 6);
+        engine.rebuild_index(&files).await.unwrap();
+
+        assert!(!engine.needs_reindex());
+        assert!(!index_path.join(REINDEX_CHECKPOINT_FILE).exists());
+        assert_eq!(engine.get_stats().total_documents, files.len());
+        for i in 0..10 {
+            let results = engine
+                .search(&format!("doc{}.txt", i), 20, 0)
+                .await
+                .unwrap();
+            let hits = results
+                .iter()
+                .filter(|r| r.file_id == format!("file-{:02}", i))
+                .count();
+            assert_eq!(hits, 1);
+        }
+    }
+
+    #[test]
+    fn test_invalid_extra_fields_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        for extra_fields in [
+            vec![ExtraField::single("name")],
+            vec![ExtraField::single("owner"), ExtraField::multi("owner")],
+            vec![ExtraField::single(" ")],
+        ] {
+            let config = SearchConfig {
+                extra_fields,
+                ..Default::default()
+            };
+            assert!(
+                SearchEngine::with_config(
+                    temp_dir.path().join("index"),
+                    temp_dir.path().to_path_buf(),
+                    config
+                )
+                .is_err()
+            );
+        }
+    }
 }
@@ -15,6 +15,11 @@
 //! [storage]
 //! root_path = "./storage"
 //! chunk_size = 4194304  # 4MB
+//!
+//! # 可选：完整的增量存储配置，存在时取代 [storage] 中的压缩/GC 字段
+//! [storage.incremental]
+//! chunker_type = "FastCdc"        # Fixed / RabinKarp / FastCdc
+//! compression_algorithm = "zstd"  # lz4 / zstd / auto / none
 //! ```
 //!
 //! ## 存储引擎特性
@@ -31,14 +36,20 @@
 //!   - WAL 日志保障数据可靠性
 //!   - 三级缓存提升性能
 
+mod delete;
 mod global;
 
+pub use delete::{
+    DELETE_MODE_HEADER, DeleteActor, DeleteMode, DeletePolicy, DeleteRejection, delete_with_mode,
+};
 #[cfg(test)]
 pub use global::init_test_storage_async;
-pub use global::{init_global_storage, storage};
+pub use global::{init_global_storage, storage, try_storage};
 
 use crate::config::StorageConfig;
 use crate::error::{NasError, Result};
+use futures_util::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 // 重新导出 StorageManager trait
 pub use silent_nas_core::StorageManagerTrait;
@@ -46,6 +57,7 @@ pub use silent_nas_core::StorageManagerTrait;
 // 导出存储实现
 pub use silent_storage::IncrementalConfig;
 pub use silent_storage::StorageManager;
+pub use silent_storage::{MutationEvent, MutationOp};
 
 /// 从配置创建存储管理器
 ///
@@ -63,6 +75,13 @@ pub use silent_storage::StorageManager;
 ///     compression_algorithm: "lz4".to_string(),
 ///     enable_auto_gc: true,
 ///     gc_interval_secs: 3600,
+///     incremental: None,
+///     metadata: None,
+///     chunk_root: None,
+///     metadata_dir: None,
+///     index_dir: None,
+///     hot_dir: None,
+///     default_delete: None,
 /// };
 ///
 /// let storage = create_storage(&config).await?;
@@ -71,13 +90,10 @@ pub use silent_storage::StorageManager;
 /// ```
 pub async fn create_storage(config: &StorageConfig) -> Result<StorageManager> {
     // 创建增量配置（去重功能已内置于存储策略，无需配置）
-    let incremental_config = IncrementalConfig {
-        enable_compression: config.enable_compression,
-        compression_algorithm: config.compression_algorithm.clone(),
-        enable_auto_gc: config.enable_auto_gc,
-        gc_interval_secs: config.gc_interval_secs,
-        ..IncrementalConfig::default()
-    };
+    let incremental_config = config.incremental_config();
+    incremental_config
+        .validate()
+        .map_err(|e| NasError::Config(format!("存储配置无效: {}", e)))?;
 
     // 创建存储管理器
     let storage = StorageManager::new(
@@ -92,18 +108,74 @@ pub async fn create_storage(config: &StorageConfig) -> Result<StorageManager> {
         .await
         .map_err(|e| NasError::Storage(e.to_string()))?;
 
+    let incremental_config = storage.config();
     tracing::info!(
-        "存储管理器初始化成功: root={:?}, chunk_size={}, compression={}, auto_gc={}, gc_interval={}s",
+        "存储管理器初始化成功: root={:?}, chunk_size={}, chunker={:?}, compression={} ({}), auto_gc={}, gc_interval={}s",
         config.root_path,
         config.chunk_size,
-        config.enable_compression,
-        config.enable_auto_gc,
-        config.gc_interval_secs
+        incremental_config.chunker_type,
+        incremental_config.enable_compression,
+        incremental_config.compression_algorithm,
+        incremental_config.enable_auto_gc,
+        incremental_config.gc_interval_secs
     );
 
     Ok(storage)
 }
 
+/// 流式响应体每次读取的缓冲大小
+const READ_STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 将 [`StorageManager::open_read`] 返回的读取句柄转换为字节流，供各协议的流式响应体使用
+///
+/// 读取出错时产生一个错误后结束，由连接中断告知客户端。
+pub fn read_stream(
+    reader: Box<dyn AsyncRead + Send + Unpin>,
+) -> impl Stream<Item = std::io::Result<bytes::Bytes>> + Send {
+    futures_util::stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut buf = vec![0u8; READ_STREAM_BUFFER_SIZE];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(bytes::Bytes::from(buf)), Some(reader)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
+
+/// 秒传请求头：整文件 SHA-256（十六进制）
+pub const INSTANT_UPLOAD_HASH_HEADER: &str = "x-file-hash";
+/// 秒传请求头：文件大小（字节），缺省时取 `Content-Length`
+pub const INSTANT_UPLOAD_SIZE_HEADER: &str = "x-file-size";
+/// 秒传响应头：`true` 表示已按哈希保存、未读取请求体，`false` 表示需要上传内容
+pub const INSTANT_UPLOAD_RESULT_HEADER: &str = "x-instant-upload";
+
+/// 从请求头解析秒传参数 `(整文件 SHA-256, 文件大小)`
+///
+/// 未携带哈希、哈希不是 64 位十六进制或无法确定大小时返回 `None`，按普通上传处理。
+pub fn instant_upload_request(headers: &http::HeaderMap) -> Option<(String, u64)> {
+    let hash = headers
+        .get(INSTANT_UPLOAD_HASH_HEADER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let size = headers
+        .get(INSTANT_UPLOAD_SIZE_HEADER)
+        .or_else(|| headers.get(http::header::CONTENT_LENGTH))?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some((hash.to_ascii_lowercase(), size))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,6 +191,13 @@ mod tests {
             compression_algorithm: "lz4".to_string(),
             enable_auto_gc: false, // 禁用自动GC以加快测试速度
             gc_interval_secs: 3600,
+            incremental: None,
+            metadata: None,
+            chunk_root: None,
+            metadata_dir: None,
+            index_dir: None,
+            hot_dir: None,
+            default_delete: None,
         };
 
         let storage = create_storage(&config).await.unwrap();
@@ -133,6 +212,67 @@ mod tests {
         assert_eq!(read_data, test_data);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_create_storage_with_incremental_section() {
+        let temp_dir = TempDir::new().unwrap();
+        let config: StorageConfig = toml::from_str(&format!(
+            r#"
+            root_path = "{}"
+            chunk_size = 65536
+
+            [incremental]
+            chunker_type = "FastCdc"
+            compression_algorithm = "zstd"
+            enable_auto_gc = false
+            "#,
+            temp_dir.path().display()
+        ))
+        .unwrap();
+
+        let storage = create_storage(&config).await.unwrap();
+        assert_eq!(
+            storage.config().chunker_type,
+            silent_storage::ChunkerType::FastCdc
+        );
+        assert_eq!(storage.config().compression_algorithm, "zstd");
+        assert!(storage.config().enable_compression);
+
+        // 可压缩数据的块应使用 zstd 存储
+        let data = b"silent-nas incremental config ".repeat(8 * 1024);
+        let (delta, _) = storage
+            .save_version("zstd_file", &data, None)
+            .await
+            .unwrap();
+        assert!(!delta.chunks.is_empty());
+        assert!(
+            delta
+                .chunks
+                .iter()
+                .all(|c| c.compression == silent_storage::CompressionAlgorithm::Zstd)
+        );
+        assert_eq!(storage.read_file("zstd_file").await.unwrap(), data);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_create_storage_rejects_invalid_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config: StorageConfig = toml::from_str(&format!(
+            r#"
+            root_path = "{}"
+            chunk_size = 65536
+
+            [incremental]
+            compression_algorithm = "brotli"
+            "#,
+            temp_dir.path().display()
+        ))
+        .unwrap();
+
+        let err = create_storage(&config).await.unwrap_err();
+        assert!(matches!(err, NasError::Config(_)));
+        assert!(err.to_string().contains("brotli"));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_global_storage() {
         use silent_nas_core::StorageManagerTrait;
@@ -0,0 +1,279 @@
+//! 跨协议统一的删除语义
+//!
+//! HTTP、S3、WebDAV 的删除请求都按同一套规则决定软删除（移入回收站）还是硬删除
+//! （跳过回收站永久删除并立即擦除不再被引用的块）：
+//! 1. 请求头 `x-delete-mode: soft|hard` 显式指定；
+//! 2. S3 开启版本控制的桶：软删除，相当于写入删除标记；
+//! 3. 配置的 `storage.default_delete`；
+//! 4. 协议默认值：HTTP 与 WebDAV 为软删除，S3 为硬删除。
+//!
+//! 三种协议共用同一套权限与审计：请求头要求硬删除时调用方必须是管理员
+//! （见 [`DeleteActor`]），每次删除都由 [`delete_with_mode`] 记录审计事件。
+
+use super::StorageManager;
+use crate::audit::{AuditAction, AuditEvent, AuditLogger};
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// 按请求覆盖删除语义的请求头，响应中回显实际使用的删除语义
+pub const DELETE_MODE_HEADER: &str = "x-delete-mode";
+
+/// 删除语义
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteMode {
+    /// 软删除：移入回收站，可恢复
+    Soft,
+    /// 硬删除：永久删除所有版本，不可恢复
+    Hard,
+}
+
+impl DeleteMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Soft => "soft",
+            Self::Hard => "hard",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "soft" => Some(Self::Soft),
+            "hard" => Some(Self::Hard),
+            _ => None,
+        }
+    }
+}
+
+/// 删除请求的发起者
+///
+/// 由各协议的认证结果确定：HTTP 与 WebDAV 取认证中间件写入请求的用户
+/// （`crate::auth::delete_actor`），S3 通过配置的访问密钥校验的请求视为管理员。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DeleteActor {
+    /// 协议未启用认证，不区分权限
+    #[default]
+    Anonymous,
+    /// 已认证的调用方
+    Authenticated { id: String, admin: bool },
+}
+
+impl DeleteActor {
+    /// 是否允许通过请求头要求硬删除（与 purge 接口一样只允许管理员）
+    fn may_request_hard_delete(&self) -> bool {
+        !matches!(self, Self::Authenticated { admin: false, .. })
+    }
+
+    fn user_id(&self) -> Option<&str> {
+        match self {
+            Self::Anonymous => None,
+            Self::Authenticated { id, .. } => Some(id),
+        }
+    }
+}
+
+/// 删除请求被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeleteRejection {
+    /// 请求头取值无效
+    InvalidMode(String),
+    /// 非管理员通过请求头要求硬删除
+    Forbidden,
+}
+
+impl DeleteRejection {
+    /// 对应的 HTTP 状态码
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidMode(_) => StatusCode::BAD_REQUEST,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+impl std::fmt::Display for DeleteRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidMode(msg) => f.write_str(msg),
+            Self::Forbidden => f.write_str("硬删除需要管理员权限"),
+        }
+    }
+}
+
+/// 删除策略（配置的默认删除语义与审计日志）
+#[derive(Clone, Default)]
+pub struct DeletePolicy {
+    default: Option<DeleteMode>,
+    audit_logger: Option<Arc<AuditLogger>>,
+}
+
+impl DeletePolicy {
+    /// `default` 为 `None` 时各协议使用自身的默认删除语义
+    pub fn new(default: Option<DeleteMode>) -> Self {
+        Self {
+            default,
+            audit_logger: None,
+        }
+    }
+
+    /// 设置记录删除事件的审计日志
+    pub fn with_audit_logger(mut self, audit_logger: Option<Arc<AuditLogger>>) -> Self {
+        self.audit_logger = audit_logger;
+        self
+    }
+
+    /// 请求头指定的删除语义
+    ///
+    /// 取值无效时返回 `InvalidMode`；非管理员要求硬删除时返回 `Forbidden`。
+    pub fn requested(
+        headers: &HeaderMap,
+        actor: &DeleteActor,
+    ) -> Result<Option<DeleteMode>, DeleteRejection> {
+        let Some(value) = headers.get(DELETE_MODE_HEADER) else {
+            return Ok(None);
+        };
+        let value = value.to_str().unwrap_or_default();
+        let mode = DeleteMode::parse(value).ok_or_else(|| {
+            DeleteRejection::InvalidMode(format!(
+                "{} 取值无效: {}（可选 soft, hard）",
+                DELETE_MODE_HEADER, value
+            ))
+        })?;
+        if mode == DeleteMode::Hard && !actor.may_request_hard_delete() {
+            return Err(DeleteRejection::Forbidden);
+        }
+        Ok(Some(mode))
+    }
+
+    /// 未由请求头指定时使用的删除语义
+    pub fn default_or(&self, protocol_default: DeleteMode) -> DeleteMode {
+        self.default.unwrap_or(protocol_default)
+    }
+
+    /// 本次请求的删除语义：请求头优先，其次是配置，最后是协议默认值
+    pub fn resolve(
+        &self,
+        headers: &HeaderMap,
+        protocol_default: DeleteMode,
+        actor: &DeleteActor,
+    ) -> Result<DeleteMode, DeleteRejection> {
+        Ok(Self::requested(headers, actor)?.unwrap_or_else(|| self.default_or(protocol_default)))
+    }
+}
+
+/// 按删除语义删除文件并记录审计事件
+///
+/// 软删除记录 `FileDelete`，硬删除记录 `FilePurge` 及擦除的块数量，失败时记录错误。
+/// 返回硬删除擦除的块数量（软删除为 0）。搜索索引、事件通知等上层状态由调用方处理。
+pub async fn delete_with_mode(
+    storage: &StorageManager,
+    policy: &DeletePolicy,
+    file_id: &str,
+    mode: DeleteMode,
+    actor: &DeleteActor,
+) -> silent_storage::Result<usize> {
+    let result = match mode {
+        DeleteMode::Soft => storage.delete_file(file_id).await.map(|_| 0),
+        DeleteMode::Hard => storage.hard_delete_file(file_id).await,
+    };
+
+    if let Some(ref audit_logger) = policy.audit_logger {
+        let action = match mode {
+            DeleteMode::Soft => AuditAction::FileDelete,
+            DeleteMode::Hard => AuditAction::FilePurge,
+        };
+        let mut event = AuditEvent::new(action, Some(file_id.to_string()));
+        if let Some(user_id) = actor.user_id() {
+            event = event.with_user(user_id.to_string());
+        }
+        event = match &result {
+            Ok(erased_chunks) => event.with_metadata(serde_json::json!({
+                "mode": mode.as_str(),
+                "erased_chunks": erased_chunks,
+            })),
+            Err(e) => event
+                .with_metadata(serde_json::json!({"mode": mode.as_str()}))
+                .with_error(e.to_string()),
+        };
+        audit_logger.log(event).await;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_delete_mode_resolution_order() {
+        let mut headers = HeaderMap::new();
+        let actor = DeleteActor::Anonymous;
+
+        // 未配置：协议默认值
+        let policy = DeletePolicy::default();
+        assert_eq!(
+            policy.resolve(&headers, DeleteMode::Soft, &actor),
+            Ok(DeleteMode::Soft)
+        );
+        assert_eq!(
+            policy.resolve(&headers, DeleteMode::Hard, &actor),
+            Ok(DeleteMode::Hard)
+        );
+
+        // 配置覆盖协议默认值
+        let policy = DeletePolicy::new(Some(DeleteMode::Hard));
+        assert_eq!(
+            policy.resolve(&headers, DeleteMode::Soft, &actor),
+            Ok(DeleteMode::Hard)
+        );
+
+        // 请求头覆盖配置
+        headers.insert(DELETE_MODE_HEADER, HeaderValue::from_static("Soft"));
+        assert_eq!(
+            policy.resolve(&headers, DeleteMode::Hard, &actor),
+            Ok(DeleteMode::Soft)
+        );
+
+        headers.insert(DELETE_MODE_HEADER, HeaderValue::from_static("trash"));
+        assert!(policy.resolve(&headers, DeleteMode::Soft, &actor).is_err());
+    }
+
+    #[test]
+    fn test_requested_hard_delete_requires_admin() {
+        let mut headers = HeaderMap::new();
+        headers.insert(DELETE_MODE_HEADER, HeaderValue::from_static("hard"));
+        let policy = DeletePolicy::default();
+
+        let user = DeleteActor::Authenticated {
+            id: "u1".to_string(),
+            admin: false,
+        };
+        assert_eq!(
+            policy.resolve(&headers, DeleteMode::Soft, &user),
+            Err(DeleteRejection::Forbidden)
+        );
+        let admin = DeleteActor::Authenticated {
+            id: "root".to_string(),
+            admin: true,
+        };
+        assert_eq!(
+            policy.resolve(&headers, DeleteMode::Soft, &admin),
+            Ok(DeleteMode::Hard)
+        );
+
+        // 配置的默认硬删除不受限制，软删除请求头对所有人开放
+        let policy = DeletePolicy::new(Some(DeleteMode::Hard));
+        assert_eq!(
+            policy.resolve(&HeaderMap::new(), DeleteMode::Soft, &user),
+            Ok(DeleteMode::Hard)
+        );
+        headers.insert(DELETE_MODE_HEADER, HeaderValue::from_static("soft"));
+        assert_eq!(
+            policy.resolve(&headers, DeleteMode::Hard, &user),
+            Ok(DeleteMode::Soft)
+        );
+    }
+}
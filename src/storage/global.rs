@@ -34,7 +34,6 @@ pub fn storage() -> &'static StorageManager {
 /// 尝试获取全局存储管理器的引用
 ///
 /// 如果存储未初始化则返回 None
-#[allow(dead_code)]
 pub fn try_storage() -> Option<&'static StorageManager> {
     STORAGE.get()
 }
@@ -61,10 +60,14 @@ pub async fn init_test_storage_async() -> &'static StorageManager {
             let temp_dir = TEST_DIR.get_or_init(|| Box::leak(Box::new(TempDir::new().unwrap())));
 
             // 创建并初始化存储
+            // 开启秒传，供各协议的秒传握手测试使用（默认关闭）
             let mgr = StorageManager::new(
                 temp_dir.path().to_path_buf(),
                 64 * 1024,
-                crate::storage::IncrementalConfig::default(),
+                crate::storage::IncrementalConfig {
+                    instant_upload: silent_storage::InstantUploadScope::Namespace,
+                    ..Default::default()
+                },
             );
 
             // 初始化存储（这是唯一会初始化 Sled 数据库的地方）
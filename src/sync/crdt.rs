@@ -10,9 +10,14 @@ use serde::{Deserialize, Serialize};
 use silent_crdt::crdt::{LWWRegister, VectorClock};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
 use tokio::sync::{RwLock, broadcast};
 use tracing::{debug, info, warn};
 
+/// 墓碑回收间隔
+pub const TOMBSTONE_GC_INTERVAL: Duration = Duration::from_secs(3600);
+
 /// 文件同步状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileSync {
@@ -81,6 +86,11 @@ impl FileSync {
         self.deleted.value.unwrap_or(false)
     }
 
+    /// 删除时间（毫秒），未删除时返回 None
+    pub fn deleted_at(&self) -> Option<i64> {
+        self.is_deleted().then_some(self.deleted.timestamp)
+    }
+
     /// 检测是否有冲突
     pub fn has_conflict(&self, other: &FileSync) -> bool {
         // 如果两个状态的向量时钟并发，则存在冲突
@@ -98,8 +108,12 @@ pub struct SyncManager {
     sync_states: Arc<RwLock<HashMap<String, FileSync>>>,
     /// 每个文件最近一次已知的源HTTP地址（用于补拉）
     last_sources: Arc<RwLock<HashMap<String, String>>>,
+    /// 每个文件最近一次检测到的冲突
+    conflicts: Arc<RwLock<HashMap<String, ConflictInfo>>>,
     /// 本地变更事件通道（广播 file_id）
     local_change_tx: broadcast::Sender<String>,
+    /// 墓碑保留时长（毫秒），0 表示永不回收
+    tombstone_retention_ms: AtomicI64,
 }
 
 impl SyncManager {
@@ -110,7 +124,9 @@ impl SyncManager {
             notifier,
             sync_states: Arc::new(RwLock::new(HashMap::new())),
             last_sources: Arc::new(RwLock::new(HashMap::new())),
+            conflicts: Arc::new(RwLock::new(HashMap::new())),
             local_change_tx: tx,
+            tombstone_retention_ms: AtomicI64::new(0),
         })
     }
 
@@ -119,6 +135,57 @@ impl SyncManager {
         &self.node_id
     }
 
+    /// 设置墓碑保留时长，`Duration::ZERO` 表示永不回收
+    ///
+    /// 保留时长应远大于节点间可能的最长分区时间：分区期间未收到删除的节点
+    /// 在墓碑回收后重新上线，会把文件当作新文件重新同步回来。
+    pub fn set_tombstone_retention(&self, retention: Duration) {
+        let ms = i64::try_from(retention.as_millis()).unwrap_or(i64::MAX);
+        self.tombstone_retention_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// 回收过期的删除墓碑，返回回收数量
+    ///
+    /// 只回收删除时间同时早于保留期界限与 `acked_until_ms` 的墓碑，
+    /// `acked_until_ms` 为所有已知节点最后一次成功同步的最早时间（无其他节点时传 `i64::MAX`），
+    /// 保证回收时每个节点都已在删除之后完成过同步，不再需要该墓碑。
+    /// 回收后同 ID 的文件再次出现时按新文件处理。
+    pub async fn collect_tombstones(&self, acked_until_ms: i64) -> usize {
+        let retention_ms = self.tombstone_retention_ms.load(Ordering::Relaxed);
+        if retention_ms <= 0 {
+            return 0;
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        let horizon = now.saturating_sub(retention_ms).min(acked_until_ms);
+
+        let mut states = self.sync_states.write().await;
+        let expired: Vec<String> = states
+            .values()
+            .filter(|s| s.deleted_at().is_some_and(|at| at < horizon))
+            .map(|s| s.file_id.clone())
+            .collect();
+        if expired.is_empty() {
+            return 0;
+        }
+        for file_id in &expired {
+            states.remove(file_id);
+        }
+        drop(states);
+
+        let mut sources = self.last_sources.write().await;
+        for file_id in &expired {
+            sources.remove(file_id);
+        }
+        drop(sources);
+
+        let mut conflicts = self.conflicts.write().await;
+        for file_id in &expired {
+            conflicts.remove(file_id);
+        }
+        info!("回收删除墓碑: {} 个", expired.len());
+        expired.len()
+    }
+
     /// 处理本地文件变更事件
     pub async fn handle_local_change(
         &self,
@@ -170,16 +237,29 @@ impl SyncManager {
 
         match states.get_mut(&file_id) {
             Some(local_state) => {
-                // 检测冲突
-                if local_state.has_conflict(&remote_state) {
+                // 检测冲突（合并前记录双方的状态）
+                let conflict = if local_state.has_conflict(&remote_state) {
                     warn!("检测到文件冲突: {}, 使用 LWW 策略自动合并", file_id);
-                    self.handle_conflict(local_state, &remote_state).await?;
-                }
+                    Some(self.handle_conflict(local_state, &remote_state).await)
+                } else {
+                    None
+                };
 
                 // 合并状态
                 local_state.merge(&remote_state);
                 info!("合并远程文件状态: {}", file_id);
 
+                if let Some(mut conflict) = conflict {
+                    conflict.winner = if local_state.metadata.timestamp == conflict.remote.timestamp
+                        && local_state.metadata.node_id == conflict.remote.node_id
+                    {
+                        ConflictSide::Remote
+                    } else {
+                        ConflictSide::Local
+                    };
+                    self.store_conflict(conflict).await;
+                }
+
                 // 应用合并后的状态到存储
                 self.apply_merged_state(local_state).await?;
 
@@ -198,26 +278,52 @@ impl SyncManager {
         }
     }
 
-    /// 处理冲突
-    async fn handle_conflict(&self, local_state: &FileSync, remote_state: &FileSync) -> Result<()> {
+    /// 处理冲突：记录双方合并前的版本信息
+    ///
+    /// LWW 策略会自动选择时间戳更大的版本，落选一方的内容仍保留在其节点的版本历史中，
+    /// 冲突记录用于事后比对与人工恢复。
+    async fn handle_conflict(
+        &self,
+        local_state: &FileSync,
+        remote_state: &FileSync,
+    ) -> ConflictInfo {
         debug!(
             "冲突详情 - 本地时间: {:?}, 远程时间: {:?}",
             local_state.metadata.timestamp, remote_state.metadata.timestamp
         );
 
-        // LWW 策略会自动选择时间戳更大的版本
-        // 这里可以记录冲突事件或创建冲突副本
-        let conflict_info = ConflictInfo {
+        let mut conflict = ConflictInfo {
             file_id: local_state.file_id.clone(),
             local_timestamp: local_state.metadata.timestamp,
             remote_timestamp: remote_state.metadata.timestamp,
+            local: ConflictVersion::from_state(local_state),
+            remote: ConflictVersion::from_state(remote_state),
+            winner: ConflictSide::Local,
             resolved_by: "LWW".to_string(),
             timestamp: chrono::Utc::now().naive_utc(),
         };
+        resolve_conflict_versions(&mut conflict).await;
+        conflict
+    }
 
-        debug!("冲突已解决: {:?}", conflict_info);
+    /// 记录未经合并即已决定保留一方的冲突（如按修改时间判定本地较新）
+    pub async fn record_conflict(
+        &self,
+        local_state: &FileSync,
+        remote_state: &FileSync,
+        winner: ConflictSide,
+    ) {
+        let mut conflict = self.handle_conflict(local_state, remote_state).await;
+        conflict.winner = winner;
+        self.store_conflict(conflict).await;
+    }
 
-        Ok(())
+    async fn store_conflict(&self, conflict: ConflictInfo) {
+        debug!("冲突已解决: {:?}", conflict);
+        self.conflicts
+            .write()
+            .await
+            .insert(conflict.file_id.clone(), conflict);
     }
 
     /// 应用合并后的状态到存储
@@ -252,11 +358,34 @@ impl SyncManager {
         states.values().cloned().collect()
     }
 
-    /// 检查文件是否有冲突
+    /// 列出已检测到的冲突（每个文件保留最近一次），按检测时间从新到旧排列
     pub async fn check_conflicts(&self) -> Vec<ConflictInfo> {
-        // 这里可以实现冲突检测逻辑
-        // 比如比较本地状态和远程状态
-        vec![]
+        let file_ids: Vec<String> = self.conflicts.read().await.keys().cloned().collect();
+        let mut conflicts = Vec::with_capacity(file_ids.len());
+        for file_id in file_ids {
+            if let Some(conflict) = self.get_conflict(&file_id).await {
+                conflicts.push(conflict);
+            }
+        }
+        conflicts.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        conflicts
+    }
+
+    /// 获取文件最近一次冲突的记录
+    ///
+    /// 冲突时尚未同步到本节点的一方，在其内容成为本地当前版本后补记版本 ID。
+    pub async fn get_conflict(&self, file_id: &str) -> Option<ConflictInfo> {
+        let mut conflict = self.conflicts.read().await.get(file_id).cloned()?;
+        if conflict.local.version_id.is_none() || conflict.remote.version_id.is_none() {
+            resolve_conflict_versions(&mut conflict).await;
+            if let Some(entry) = self.conflicts.write().await.get_mut(file_id)
+                && entry.timestamp == conflict.timestamp
+            {
+                entry.local.version_id = conflict.local.version_id.clone();
+                entry.remote.version_id = conflict.remote.version_id.clone();
+            }
+        }
+        Some(conflict)
     }
 
     /// 广播文件变更到其他节点
@@ -295,10 +424,90 @@ pub struct ConflictInfo {
     pub file_id: String,
     pub local_timestamp: i64,
     pub remote_timestamp: i64,
+    /// 本地一方合并前的版本
+    pub local: ConflictVersion,
+    /// 远程一方合并前的版本
+    pub remote: ConflictVersion,
+    /// 合并后保留的一方
+    pub winner: ConflictSide,
     pub resolved_by: String,
     pub timestamp: NaiveDateTime,
 }
 
+/// 冲突中的一方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictSide {
+    Local,
+    Remote,
+}
+
+/// 冲突一方的版本信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictVersion {
+    /// 本节点存储中对应的版本 ID，该内容尚未同步到本节点时为 None
+    pub version_id: Option<String>,
+    /// 内容哈希（SHA-256），没有元数据时为 None
+    pub hash: Option<String>,
+    /// 文件大小
+    pub size: u64,
+    /// 文件修改时间
+    pub modified_at: Option<NaiveDateTime>,
+    /// LWW 时间戳（毫秒）
+    pub timestamp: i64,
+    /// 写入该版本的节点
+    pub node_id: String,
+    /// 是否为删除
+    pub deleted: bool,
+}
+
+impl ConflictVersion {
+    fn from_state(state: &FileSync) -> Self {
+        let metadata = state.metadata.value.as_ref();
+        Self {
+            version_id: None,
+            hash: metadata.map(|m| m.hash.clone()),
+            size: metadata.map_or(0, |m| m.size),
+            modified_at: metadata.map(|m| m.modified_at),
+            timestamp: state.metadata.timestamp,
+            node_id: state.metadata.node_id.clone(),
+            deleted: state.is_deleted(),
+        }
+    }
+}
+
+/// 补记冲突双方在本节点存储中的版本 ID
+///
+/// 存储层的文件元数据以版本 ID 作为哈希，哈希即为本节点已有的版本时直接使用；
+/// 否则与本地当前版本的内容哈希比较（远程内容同步到本节点后成为当前版本）。
+async fn resolve_conflict_versions(conflict: &mut ConflictInfo) {
+    let Some(storage) = storage::try_storage() else {
+        return;
+    };
+    let current = storage
+        .get_file_info(&conflict.file_id)
+        .await
+        .ok()
+        .filter(|entry| !entry.is_deleted);
+    for side in [&mut conflict.local, &mut conflict.remote] {
+        let Some(hash) = side.hash.as_deref() else {
+            continue;
+        };
+        if side.version_id.is_some() {
+            continue;
+        }
+        if storage
+            .get_version_info(hash)
+            .await
+            .is_ok_and(|version| version.file_id == conflict.file_id)
+        {
+            side.version_id = Some(hash.to_string());
+        } else if let Some(current) = current.as_ref().filter(|c| c.file_hash == hash) {
+            side.version_id = Some(current.latest_version_id.clone());
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -314,6 +523,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let file_sync = FileSync::new("test-file-1".to_string(), metadata.clone(), "node1");
@@ -333,6 +543,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let metadata2 = FileMetadata {
@@ -343,6 +554,7 @@ mod tests {
             hash: "def456".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local() + chrono::Duration::seconds(10),
+            user_metadata: Default::default(),
         };
 
         let mut sync1 = FileSync::new("test-file-1".to_string(), metadata1, "node1");
@@ -366,6 +578,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let metadata2 = FileMetadata {
@@ -376,6 +589,7 @@ mod tests {
             hash: "def456".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let sync1 = FileSync::new("test-file-1".to_string(), metadata1, "node1");
@@ -395,6 +609,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let mut file_sync = FileSync::new("test-file-1".to_string(), metadata.clone(), "node1");
@@ -407,6 +622,7 @@ mod tests {
             hash: "def456".to_string(),
             created_at: metadata.created_at,
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         file_sync.update_metadata(new_metadata.clone(), "node1");
@@ -425,6 +641,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let mut file_sync = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -447,6 +664,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let file_sync = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -465,6 +683,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let file_sync = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -484,6 +703,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let file_sync = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -507,6 +727,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let metadata2 = FileMetadata {
@@ -517,6 +738,7 @@ mod tests {
             hash: "def456".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local() + chrono::Duration::seconds(5),
+            user_metadata: Default::default(),
         };
 
         let mut sync1 = FileSync::new("test-file-1".to_string(), metadata1, "node1");
@@ -538,6 +760,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let mut sync1 = FileSync::new("test-file-1".to_string(), metadata.clone(), "node1");
@@ -596,6 +819,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let mut sync1 = FileSync::new("test-file-1".to_string(), metadata.clone(), "node1");
@@ -610,6 +834,7 @@ mod tests {
                 hash: format!("hash{}", i),
                 created_at: metadata.created_at,
                 modified_at: Local::now().naive_local(),
+                user_metadata: Default::default(),
             };
 
             sync1.update_metadata(updated_metadata, "node1");
@@ -629,6 +854,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let mut sync = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -648,6 +874,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let mut sync = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -667,6 +894,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let sync = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -685,6 +913,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let sync1 = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -704,6 +933,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let sync = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -725,6 +955,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let mut sync1 = FileSync::new("test-file-1".to_string(), metadata.clone(), "node1");
@@ -750,6 +981,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let late_metadata = FileMetadata {
@@ -760,6 +992,7 @@ mod tests {
             hash: "def456".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local() + chrono::Duration::seconds(10),
+            user_metadata: Default::default(),
         };
 
         let mut sync1 = FileSync::new("test-file-1".to_string(), early_metadata, "node1");
@@ -782,6 +1015,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let sync1 = FileSync::new("test-file-1".to_string(), metadata.clone(), "node1");
@@ -801,6 +1035,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let mut sync = FileSync::new("test-file-1".to_string(), metadata, "node1");
@@ -850,6 +1085,7 @@ mod tests {
             hash: "hash_中文".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let sync = FileSync::new("文件-123".to_string(), metadata, "节点1");
@@ -867,6 +1103,7 @@ mod tests {
             hash: "abc123".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let metadata2 = FileMetadata {
@@ -877,6 +1114,7 @@ mod tests {
             hash: "def456".to_string(),
             created_at: Local::now().naive_local(),
             modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
 
         let mut sync = FileSync::new("test-file-1".to_string(), metadata1, "node1");
@@ -887,4 +1125,135 @@ mod tests {
         // 向量时钟应该递增
         assert!(sync.vector_clock.get("node1") > initial_clock.get("node1"));
     }
+    #[tokio::test]
+    async fn test_collect_tombstones_respects_retention() {
+        let _storage = crate::storage::init_test_storage_async().await;
+        let manager = SyncManager::new("node1".to_string(), None);
+        manager.set_tombstone_retention(Duration::from_secs(3600));
+
+        let metadata = |id: &str| FileMetadata {
+            id: id.to_string(),
+            name: format!("{}.txt", id),
+            path: format!("/{}.txt", id),
+            size: 16,
+            hash: "abc123".to_string(),
+            created_at: Local::now().naive_local(),
+            modified_at: Local::now().naive_local(),
+            user_metadata: Default::default(),
+        };
+
+        // 旧墓碑 2 小时前删除，新墓碑刚刚删除
+        let now = chrono::Utc::now().timestamp_millis();
+        for (id, deleted_at) in [("old-file", now - 2 * 3600 * 1000), ("recent-file", now)] {
+            let mut state = FileSync::new(id.to_string(), metadata(id), "node1");
+            state.mark_deleted(deleted_at, "node1");
+            manager
+                .sync_states
+                .write()
+                .await
+                .insert(id.to_string(), state);
+        }
+
+        // 其他节点尚未在删除之后完成同步：不回收
+        assert_eq!(manager.collect_tombstones(now - 3 * 3600 * 1000).await, 0);
+
+        assert_eq!(manager.collect_tombstones(i64::MAX).await, 1);
+        assert!(manager.get_sync_state("old-file").await.is_none());
+        assert!(
+            manager
+                .get_sync_state("recent-file")
+                .await
+                .unwrap()
+                .is_deleted()
+        );
+
+        // 保留的墓碑仍压制其他节点的过期更新
+        let stale = FileSync::new("recent-file".to_string(), metadata("recent-file"), "node2");
+        let merged = manager.handle_remote_sync(stale).await.unwrap().unwrap();
+        assert!(merged.is_deleted());
+        assert!(merged.get_metadata().is_none());
+
+        // 已回收的 ID 再次出现时按新文件处理
+        let recreated = FileSync::new("old-file".to_string(), metadata("old-file"), "node2");
+        let merged = manager
+            .handle_remote_sync(recreated)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!merged.is_deleted());
+        assert!(merged.get_metadata().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_conflict_exposes_both_versions_and_diff() {
+        use sha2::{Digest, Sha256};
+
+        let storage = crate::storage::init_test_storage_async().await;
+        let manager = SyncManager::new("node1".to_string(), None);
+        let file_id = "conflict-diff-file";
+
+        // 本节点的编辑
+        let base = b"shared line of the document\n".repeat(256);
+        let mut local_data = base.clone();
+        local_data.extend_from_slice(b"local edit");
+        let local_meta = storage.save_file(file_id, &local_data).await.unwrap();
+        manager
+            .handle_local_change(
+                EventType::Created,
+                file_id.to_string(),
+                Some(local_meta.clone()),
+            )
+            .await
+            .unwrap();
+
+        // 另一节点的并发编辑，内容尚未同步到本节点
+        let mut remote_data = base.clone();
+        remote_data.extend_from_slice(b"remote edit from node2");
+        let remote_meta = FileMetadata {
+            size: remote_data.len() as u64,
+            hash: hex::encode(Sha256::digest(&remote_data)),
+            modified_at: local_meta.modified_at + chrono::Duration::seconds(10),
+            ..local_meta.clone()
+        };
+        let remote = FileSync::new(file_id.to_string(), remote_meta.clone(), "node2");
+        manager.handle_remote_sync(remote).await.unwrap();
+
+        let conflict = manager.get_conflict(file_id).await.unwrap();
+        assert_eq!(conflict.winner, ConflictSide::Remote);
+        assert_eq!(conflict.local.node_id, "node1");
+        assert_eq!(
+            conflict.local.version_id.as_deref(),
+            Some(local_meta.hash.as_str())
+        );
+        assert_eq!(conflict.local.size, local_data.len() as u64);
+        assert_eq!(conflict.remote.node_id, "node2");
+        assert_eq!(
+            conflict.remote.hash.as_deref(),
+            Some(remote_meta.hash.as_str())
+        );
+        assert_eq!(conflict.remote.size, remote_data.len() as u64);
+        assert_eq!(conflict.remote.modified_at, Some(remote_meta.modified_at));
+        assert!(conflict.remote.version_id.is_none());
+
+        // 远程内容同步到本节点后补记其版本 ID
+        let synced = storage.save_file(file_id, &remote_data).await.unwrap();
+        let conflicts = manager.check_conflicts().await;
+        let conflict = conflicts.iter().find(|c| c.file_id == file_id).unwrap();
+        let from = conflict.local.version_id.as_deref().unwrap();
+        let to = conflict.remote.version_id.as_deref().unwrap();
+        assert_eq!(to, synced.hash);
+
+        let diff = crate::sync::incremental::IncrementalSyncManager::new(1024)
+            .version_diff(
+                from,
+                &storage.read_version_data(from).await.unwrap(),
+                to,
+                &storage.read_version_data(to).await.unwrap(),
+            )
+            .unwrap();
+        assert_eq!(diff.from_size, local_data.len() as u64);
+        assert_eq!(diff.to_size, remote_data.len() as u64);
+        assert!(diff.unchanged_bytes >= (base.len() / 1024 * 1024) as u64);
+        assert!(diff.changed_bytes > 0);
+    }
 }
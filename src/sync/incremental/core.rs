@@ -1,7 +1,7 @@
 // 增量同步模块
 // 实现基于块的文件差异检测和同步
 
-use crate::error::Result;
+use crate::error::{NasError, Result};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
@@ -41,15 +41,49 @@ pub struct FileSignature {
     pub chunks: Vec<ChunkInfo>,
 }
 
+/// 差异块类型
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeltaKind {
+    /// 字面数据：`data` 即为该段内容
+    #[default]
+    Literal,
+    /// 复制：从目标（旧）文件 `target_offset` 处复制 `size` 字节
+    Copy { target_offset: u64, size: usize },
+}
+
 /// 差异块（需要传输的数据）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeltaChunk {
     /// 块索引
     pub index: usize,
-    /// 块偏移
+    /// 块在源（新）文件中的偏移
     pub offset: u64,
-    /// 块数据
+    /// 块数据（复制块为空）
     pub data: Vec<u8>,
+    /// 块类型（缺省为字面数据，兼容旧版本节点）
+    #[serde(default)]
+    pub kind: DeltaKind,
+}
+
+impl DeltaChunk {
+    /// 该块在源文件中覆盖的长度
+    pub fn len(&self) -> usize {
+        match self.kind {
+            DeltaKind::Literal => self.data.len(),
+            DeltaKind::Copy { size, .. } => size,
+        }
+    }
+
+    /// 是否为空块
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 是否为复制块
+    pub fn is_copy(&self) -> bool {
+        matches!(self.kind, DeltaKind::Copy { .. })
+    }
 }
 
 /// 同步差异信息
@@ -67,6 +101,42 @@ pub struct SyncDelta {
     pub total_chunks: usize,
     /// 需要更新的块数
     pub changed_chunks: usize,
+    /// 源文件大小（应用差异后的文件大小）
+    #[serde(default)]
+    pub file_size: u64,
+}
+
+/// 两个版本之间的差异
+///
+/// 按目标（新）版本的字节顺序列出区间：能在源（旧）版本中找到的区间给出源偏移，
+/// 新增或修改的区间源偏移为空。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionDiff {
+    /// 源版本 ID
+    pub from_version_id: String,
+    /// 目标版本 ID
+    pub to_version_id: String,
+    /// 源版本大小
+    pub from_size: u64,
+    /// 目标版本大小
+    pub to_size: u64,
+    /// 目标版本中与源版本相同的字节数
+    pub unchanged_bytes: u64,
+    /// 目标版本中新增或修改的字节数
+    pub changed_bytes: u64,
+    /// 目标版本的区间列表
+    pub ranges: Vec<DiffRange>,
+}
+
+/// 差异区间
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffRange {
+    /// 在目标版本中的偏移
+    pub offset: u64,
+    /// 区间长度
+    pub len: u64,
+    /// 对应源版本中的偏移，新增或修改的内容为 None
+    pub from_offset: Option<u64>,
 }
 
 /// 增量同步管理器
@@ -156,7 +226,7 @@ impl IncrementalSyncManager {
             target_chunks.insert(chunk.hash.clone(), chunk);
         }
 
-        // 找出需要更新的块
+        // 找出需要更新的块（超出目标文件长度的块必然不在映射中，已被计入）
         let mut changed_indices = Vec::new();
         for source_chunk in &source_sig.chunks {
             // 检查目标是否有相同哈希的块
@@ -165,13 +235,6 @@ impl IncrementalSyncManager {
             }
         }
 
-        // 如果源文件比目标文件大，需要添加新块
-        if source_sig.chunks.len() > target_sig.chunks.len() {
-            for i in target_sig.chunks.len()..source_sig.chunks.len() {
-                changed_indices.push(i);
-            }
-        }
-
         info!(
             "文件差异检测完成: file_id={}, 总块数={}, 变更块数={}",
             source_sig.file_id,
@@ -186,69 +249,203 @@ impl IncrementalSyncManager {
             chunks: Vec::new(), // 实际数据需要后续填充
             total_chunks: source_sig.chunks.len(),
             changed_chunks: changed_indices.len(),
+            file_size: source_sig.file_size,
         }))
     }
 
-    /// 从完整文件数据中提取差异块
-    pub fn extract_delta_chunks(
+    /// 根据目标签名生成源文件的差异（rsync 算法）
+    ///
+    /// 使用目标签名的块大小在源数据上滑动滚动弱哈希，弱哈希命中后再校验强哈希，
+    /// 命中的区间生成复制块，其余数据生成字面块。目标签名的块大小可以与本地不同。
+    pub fn generate_delta(
         &self,
+        file_id: &str,
         data: &[u8],
-        delta: &SyncDelta,
-        source_sig: &FileSignature,
-    ) -> Result<Vec<DeltaChunk>> {
-        let mut chunks = Vec::new();
+        target_sig: &FileSignature,
+    ) -> Result<SyncDelta> {
+        validate_signature(target_sig)?;
 
-        // 构建目标块的哈希集合
-        let target_hashes: std::collections::HashSet<String> = std::collections::HashSet::new();
-
-        // 提取需要传输的块
-        for chunk_info in &source_sig.chunks {
-            if !target_hashes.contains(&chunk_info.hash) {
-                let start = chunk_info.offset as usize;
-                let end = (start + chunk_info.size).min(data.len());
-
-                if start < data.len() {
-                    chunks.push(DeltaChunk {
-                        index: chunk_info.index,
-                        offset: chunk_info.offset,
-                        data: data[start..end].to_vec(),
-                    });
-                }
-            }
-        }
+        let source_hash = format!("{:x}", Sha256::digest(data));
+        // 内容相同时差异为空，应用后保持目标数据不变
+        let chunks = if source_hash == target_sig.file_hash {
+            Vec::new()
+        } else {
+            self.match_blocks(data, target_sig)
+        };
+        let changed_chunks = chunks.iter().filter(|c| !c.is_copy()).count();
 
         info!(
-            "提取差异块完成: file_id={}, 提取块数={}",
-            delta.file_id,
-            chunks.len()
+            "生成差异完成: file_id={}, 总块数={}, 字面块数={}, 块大小: 本地={} 目标={}",
+            file_id,
+            chunks.len(),
+            changed_chunks,
+            self.chunk_size,
+            target_sig.chunk_size
         );
 
-        Ok(chunks)
+        Ok(SyncDelta {
+            file_id: file_id.to_string(),
+            source_hash,
+            target_hash: target_sig.file_hash.clone(),
+            total_chunks: chunks.len(),
+            changed_chunks,
+            chunks,
+            file_size: data.len() as u64,
+        })
     }
 
-    /// 应用差异块到目标文件
-    pub fn apply_delta(&self, target_data: &[u8], delta_chunks: &[DeltaChunk]) -> Result<Vec<u8>> {
-        // 计算最终文件大小
-        let mut max_offset = target_data.len();
-        for chunk in delta_chunks {
-            let end = (chunk.offset as usize + chunk.data.len()).max(max_offset);
-            max_offset = end;
+    /// 在源数据上滑动窗口匹配目标块，生成复制块与字面块
+    fn match_blocks(&self, data: &[u8], target_sig: &FileSignature) -> Vec<DeltaChunk> {
+        let block_size = target_sig.chunk_size;
+        let mut blocks: HashMap<u32, Vec<&ChunkInfo>> = HashMap::new();
+        for chunk in &target_sig.chunks {
+            blocks.entry(chunk.weak_hash).or_default().push(chunk);
         }
 
-        // 创建新文件缓冲区
+        let mut builder = DeltaBuilder::new(data, self.chunk_size);
+
+        let mut pos = 0;
+        let mut window = block_size.min(data.len());
+        let mut rolling = RollingChecksum::new(&data[..window]);
+
+        while !blocks.is_empty() && pos < data.len() {
+            let matched = blocks.get(&rolling.digest()).and_then(|candidates| {
+                let window_data = &data[pos..pos + window];
+                let mut strong = None;
+                candidates.iter().find(|c| {
+                    c.size == window
+                        && *strong
+                            .get_or_insert_with(|| format!("{:x}", Sha256::digest(window_data)))
+                            == c.hash
+                })
+            });
+
+            if let Some(chunk) = matched {
+                builder.copy(pos, chunk.offset, window);
+                pos += window;
+                window = block_size.min(data.len() - pos);
+                rolling = RollingChecksum::new(&data[pos..pos + window]);
+                continue;
+            }
+
+            // 滑动一个字节：窗口末尾仍有数据时滚入，否则窗口缩短（匹配末尾短块）
+            rolling.roll_out(data[pos]);
+            if pos + window < data.len() {
+                rolling.roll_in(data[pos + window]);
+            } else {
+                window -= 1;
+            }
+            pos += 1;
+        }
+
+        builder.finish()
+    }
+
+    /// 计算两个版本之间的差异
+    ///
+    /// 以源版本按块大小生成签名，再在目标版本上匹配，相邻的同类区间合并为一段。
+    pub fn version_diff(
+        &self,
+        from_version_id: &str,
+        from_data: &[u8],
+        to_version_id: &str,
+        to_data: &[u8],
+    ) -> Result<VersionDiff> {
+        let signature = self.calculate_signature(from_version_id, from_data)?;
+        let delta = self.generate_delta(to_version_id, to_data, &signature)?;
+
+        let mut ranges: Vec<DiffRange> = Vec::new();
+        let mut push = |offset: u64, len: u64, from_offset: Option<u64>| {
+            if let Some(last) = ranges.last_mut()
+                && last.offset + last.len == offset
+                && match (last.from_offset, from_offset) {
+                    (Some(prev), Some(next)) => prev + last.len == next,
+                    (None, None) => true,
+                    _ => false,
+                }
+            {
+                last.len += len;
+                return;
+            }
+            ranges.push(DiffRange {
+                offset,
+                len,
+                from_offset,
+            });
+        };
+        if delta.chunks.is_empty() {
+            // 内容相同
+            if !to_data.is_empty() {
+                push(0, to_data.len() as u64, Some(0));
+            }
+        } else {
+            for chunk in &delta.chunks {
+                let from_offset = match chunk.kind {
+                    DeltaKind::Copy { target_offset, .. } => Some(target_offset),
+                    DeltaKind::Literal => None,
+                };
+                push(chunk.offset, chunk.len() as u64, from_offset);
+            }
+        }
+
+        let changed_bytes = ranges
+            .iter()
+            .filter(|r| r.from_offset.is_none())
+            .map(|r| r.len)
+            .sum();
+        Ok(VersionDiff {
+            from_version_id: from_version_id.to_string(),
+            to_version_id: to_version_id.to_string(),
+            from_size: from_data.len() as u64,
+            to_size: to_data.len() as u64,
+            unchanged_bytes: to_data.len() as u64 - changed_bytes,
+            changed_bytes,
+            ranges,
+        })
+    }
+
+    /// 应用差异块到目标文件
+    ///
+    /// `file_size` 为源文件大小，结果会按此截断或扩展；
+    /// 复制块始终从未修改的目标数据中读取。
+    pub fn apply_delta(
+        &self,
+        target_data: &[u8],
+        delta_chunks: &[DeltaChunk],
+        file_size: u64,
+    ) -> Result<Vec<u8>> {
+        // 创建新文件缓冲区（文件缩小时截断）
         let mut result = target_data.to_vec();
-        result.resize(max_offset, 0);
+        result.resize(file_size as usize, 0);
 
         // 应用差异块
         for chunk in delta_chunks {
             let start = chunk.offset as usize;
-            let end = start + chunk.data.len();
+            let end = start + chunk.len();
 
             if end > result.len() {
-                result.resize(end, 0);
+                return Err(NasError::Other(format!(
+                    "差异块越界: index={}, end={}, file_size={}",
+                    chunk.index, end, file_size
+                )));
             }
 
-            result[start..end].copy_from_slice(&chunk.data);
+            match chunk.kind {
+                DeltaKind::Literal => result[start..end].copy_from_slice(&chunk.data),
+                DeltaKind::Copy {
+                    target_offset,
+                    size,
+                } => {
+                    let from = target_offset as usize;
+                    let source = target_data.get(from..from + size).ok_or_else(|| {
+                        NasError::Other(format!(
+                            "复制块超出目标文件范围: index={}, offset={}, size={}",
+                            chunk.index, target_offset, size
+                        ))
+                    })?;
+                    result[start..end].copy_from_slice(source);
+                }
+            }
         }
 
         info!("差异块应用完成: 应用了 {} 个块", delta_chunks.len());
@@ -276,6 +473,122 @@ impl IncrementalSyncManager {
     }
 }
 
+/// 校验签名的块信息与块大小、文件大小一致
+fn validate_signature(sig: &FileSignature) -> Result<()> {
+    if !sig.chunks.is_empty() && sig.chunk_size == 0 {
+        return Err(NasError::Other(format!(
+            "无效的目标签名: file_id={}, 块大小为 0",
+            sig.file_id
+        )));
+    }
+
+    for chunk in &sig.chunks {
+        if chunk.size == 0
+            || chunk.size > sig.chunk_size
+            || chunk.offset + chunk.size as u64 > sig.file_size
+        {
+            return Err(NasError::Other(format!(
+                "无效的目标签名: file_id={}, 块 {} (offset={}, size={}) 超出范围",
+                sig.file_id, chunk.index, chunk.offset, chunk.size
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// 滚动弱哈希（与 `calculate_weak_hash` 结果一致）
+struct RollingChecksum {
+    a: u32,
+    b: u32,
+    len: u32,
+}
+
+impl RollingChecksum {
+    const MOD_ADLER: u32 = 65521;
+
+    fn new(data: &[u8]) -> Self {
+        let mut checksum = Self { a: 1, b: 0, len: 0 };
+        for &byte in data {
+            checksum.roll_in(byte);
+        }
+        checksum
+    }
+
+    /// 窗口末尾追加一个字节
+    fn roll_in(&mut self, byte: u8) {
+        self.a = (self.a + byte as u32) % Self::MOD_ADLER;
+        self.b = (self.b + self.a) % Self::MOD_ADLER;
+        self.len += 1;
+    }
+
+    /// 移除窗口首字节
+    fn roll_out(&mut self, byte: u8) {
+        let m = Self::MOD_ADLER as u64;
+        let byte = byte as u64;
+        let len = self.len as u64;
+        self.a = ((self.a as u64 + m - byte) % m) as u32;
+        // 首字节在 b 中贡献了 len * byte，另有初始值 1 贡献了 1
+        self.b = ((self.b as u64 + (len % m) * (m - byte) + m - 1) % m) as u32;
+        self.len -= 1;
+    }
+
+    fn digest(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+/// 差异块构建器：合并连续的字面数据并按块大小切分
+struct DeltaBuilder<'a> {
+    data: &'a [u8],
+    max_literal: usize,
+    literal_start: usize,
+    chunks: Vec<DeltaChunk>,
+}
+
+impl<'a> DeltaBuilder<'a> {
+    fn new(data: &'a [u8], max_literal: usize) -> Self {
+        Self {
+            data,
+            max_literal: max_literal.max(1),
+            literal_start: 0,
+            chunks: Vec::new(),
+        }
+    }
+
+    fn copy(&mut self, offset: usize, target_offset: u64, size: usize) {
+        self.flush_literal(offset);
+        self.chunks.push(DeltaChunk {
+            index: self.chunks.len(),
+            offset: offset as u64,
+            data: Vec::new(),
+            kind: DeltaKind::Copy {
+                target_offset,
+                size,
+            },
+        });
+        self.literal_start = offset + size;
+    }
+
+    fn flush_literal(&mut self, end: usize) {
+        while self.literal_start < end {
+            let chunk_end = (self.literal_start + self.max_literal).min(end);
+            self.chunks.push(DeltaChunk {
+                index: self.chunks.len(),
+                offset: self.literal_start as u64,
+                data: self.data[self.literal_start..chunk_end].to_vec(),
+                kind: DeltaKind::Literal,
+            });
+            self.literal_start = chunk_end;
+        }
+    }
+
+    fn finish(mut self) -> Vec<DeltaChunk> {
+        self.flush_literal(self.data.len());
+        self.chunks
+    }
+}
+
 /// 快速差异检测（仅比较文件哈希）
 #[allow(dead_code)]
 pub fn quick_diff_check(local_hash: &str, remote_hash: &str) -> bool {
@@ -285,6 +598,7 @@ pub fn quick_diff_check(local_hash: &str, remote_hash: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::test_data;
 
     #[test]
     fn test_calculate_signature() {
@@ -352,9 +666,12 @@ mod tests {
             index: 1,
             offset: 10,
             data: b"XYZ".to_vec(),
+            kind: DeltaKind::Literal,
         }];
 
-        let result = manager.apply_delta(original, &chunks).unwrap();
+        let result = manager
+            .apply_delta(original, &chunks, original.len() as u64)
+            .unwrap();
         assert_eq!(&result[10..13], b"XYZ");
     }
 
@@ -378,6 +695,7 @@ mod tests {
             chunks: vec![],
             total_chunks: 10,
             changed_chunks: 2,
+            file_size: 10240,
         };
 
         let (transferred, saved, percent) = manager.calculate_savings(10240, &delta);
@@ -386,6 +704,181 @@ mod tests {
         assert!((percent - 80.0).abs() < 0.01);
     }
 
+    /// 以 `client` 为目标签名生成差异，应用后校验结果与源数据一致
+    fn sync_roundtrip(
+        server: &IncrementalSyncManager,
+        client: &IncrementalSyncManager,
+        source: &[u8],
+        target: &[u8],
+    ) -> SyncDelta {
+        let target_sig = client.calculate_signature("file", target).unwrap();
+        let delta = server.generate_delta("file", source, &target_sig).unwrap();
+        let result = client
+            .apply_delta(target, &delta.chunks, delta.file_size)
+            .unwrap();
+        assert_eq!(result, source);
+        assert!(client.verify_hash(&result, &delta.source_hash));
+        delta
+    }
+
+    fn copied_bytes(delta: &SyncDelta) -> usize {
+        delta
+            .chunks
+            .iter()
+            .filter(|c| c.is_copy())
+            .map(|c| c.len())
+            .sum()
+    }
+
+    fn literal_bytes(delta: &SyncDelta) -> usize {
+        delta.chunks.iter().map(|c| c.data.len()).sum()
+    }
+
+    #[test]
+    fn test_rolling_checksum_matches_weak_hash() {
+        let manager = IncrementalSyncManager::new(16);
+        let data = test_data(256, 0);
+        let window = 16;
+
+        let mut rolling = RollingChecksum::new(&data[..window]);
+        for pos in 0..data.len() - window {
+            assert_eq!(
+                rolling.digest(),
+                manager.calculate_weak_hash(&data[pos..pos + window])
+            );
+            rolling.roll_out(data[pos]);
+            rolling.roll_in(data[pos + window]);
+        }
+
+        // 窗口缩短时仍与直接计算一致
+        let tail = data.len() - window;
+        let mut rolling = RollingChecksum::new(&data[tail..]);
+        for pos in tail..data.len() - 1 {
+            rolling.roll_out(data[pos]);
+            assert_eq!(
+                rolling.digest(),
+                manager.calculate_weak_hash(&data[pos + 1..])
+            );
+        }
+    }
+
+    #[test]
+    fn test_delta_small_edit_mostly_copies() {
+        let manager = IncrementalSyncManager::new(1024);
+        let target = test_data(64 * 1024, 0);
+        let mut source = target.clone();
+        // 中间插入少量字节，后续块整体偏移
+        source.splice(30_000..30_000, b"inserted bytes".iter().copied());
+
+        let delta = sync_roundtrip(&manager, &manager, &source, &target);
+        assert!(literal_bytes(&delta) <= 2 * 1024);
+        assert!(copied_bytes(&delta) >= source.len() - 2 * 1024);
+        assert!(delta.changed_chunks < delta.total_chunks / 10);
+    }
+
+    #[test]
+    fn test_delta_full_rewrite_mostly_literals() {
+        let manager = IncrementalSyncManager::new(1024);
+        let target = test_data(32 * 1024, 0);
+        // 取序列中与目标不重叠的片段
+        let source = test_data(40 * 1024, 1 << 20);
+
+        let delta = sync_roundtrip(&manager, &manager, &source, &target);
+        assert_eq!(copied_bytes(&delta), 0);
+        assert_eq!(literal_bytes(&delta), source.len());
+    }
+
+    #[test]
+    fn test_delta_truncation() {
+        let manager = IncrementalSyncManager::new(1024);
+        let target = test_data(10 * 1024 + 300, 0);
+
+        // 截断到块边界与非块边界
+        for len in [4 * 1024, 4 * 1024 + 100] {
+            let source = target[..len].to_vec();
+            let delta = sync_roundtrip(&manager, &manager, &source, &target);
+            assert_eq!(delta.file_size, len as u64);
+            assert!(literal_bytes(&delta) < 1024);
+        }
+
+        // 截断为空文件
+        let delta = sync_roundtrip(&manager, &manager, b"", &target);
+        assert!(delta.chunks.is_empty());
+        assert_eq!(delta.file_size, 0);
+    }
+
+    #[test]
+    fn test_delta_empty_files() {
+        let manager = IncrementalSyncManager::new(1024);
+        let source = test_data(3000, 0);
+
+        // 目标为空：全部为字面块
+        let delta = sync_roundtrip(&manager, &manager, &source, b"");
+        assert_eq!(literal_bytes(&delta), source.len());
+
+        // 两端都为空：无差异
+        let delta = sync_roundtrip(&manager, &manager, b"", b"");
+        assert!(delta.chunks.is_empty());
+    }
+
+    #[test]
+    fn test_delta_mismatched_block_sizes() {
+        let server = IncrementalSyncManager::new(4096);
+        let client = IncrementalSyncManager::new(1000);
+        let target = test_data(50_500, 0);
+        let mut source = target.clone();
+        source[20_000..20_010].copy_from_slice(b"0123456789");
+
+        // 按客户端块大小匹配，末尾短块同样可以复制
+        let delta = sync_roundtrip(&server, &client, &source, &target);
+        assert!(literal_bytes(&delta) <= 1000);
+        assert!(delta.chunks.iter().all(|c| c.len() <= 4096));
+    }
+
+    #[test]
+    fn test_delta_rejects_invalid_signature() {
+        let manager = IncrementalSyncManager::new(1024);
+        let mut sig = manager
+            .calculate_signature("file", &test_data(4096, 0))
+            .unwrap();
+        sig.chunks[1].offset = 8192;
+
+        assert!(manager.generate_delta("file", b"data", &sig).is_err());
+    }
+
+    #[test]
+    fn test_version_diff_ranges() {
+        let manager = IncrementalSyncManager::new(1024);
+        let from = test_data(8192, 0);
+        let mut to = from.clone();
+        to[3000..3010].copy_from_slice(b"0123456789");
+        to.extend_from_slice(b"appended tail");
+
+        let diff = manager.version_diff("v1", &from, "v2", &to).unwrap();
+        assert_eq!(diff.from_size, 8192);
+        assert_eq!(diff.to_size, to.len() as u64);
+        assert_eq!(diff.unchanged_bytes + diff.changed_bytes, diff.to_size);
+        // 修改落在第 3 块内，其余块原样复制
+        assert_eq!(diff.unchanged_bytes, 8192 - 1024);
+        assert_eq!(
+            diff.ranges[0],
+            DiffRange {
+                offset: 0,
+                len: 2048,
+                from_offset: Some(0),
+            }
+        );
+        assert_eq!(diff.ranges[1].from_offset, None);
+        assert!(diff.ranges[1].offset <= 3000);
+        let covered: u64 = diff.ranges.iter().map(|r| r.len).sum();
+        assert_eq!(covered, diff.to_size);
+
+        // 内容相同：整段复制
+        let diff = manager.version_diff("v1", &from, "v1", &from).unwrap();
+        assert_eq!(diff.changed_bytes, 0);
+        assert_eq!(diff.ranges.len(), 1);
+    }
+
     #[test]
     fn test_quick_diff_check() {
         assert!(quick_diff_check("hash1", "hash2"));
@@ -59,8 +59,8 @@ impl IncrementalSyncHandler {
 
         let storage = storage::storage();
 
-        // 1. 尝试读取本地文件和计算签名
-        let local_signature = match storage.read_file(file_id).await {
+        // 1. 尝试读取本地文件和计算签名（强一致读取，避免与缓存中的过期版本比对）
+        let local_signature = match storage.read_file_consistent(file_id).await {
             Ok(local_data) => {
                 debug!("本地文件存在，计算签名: file_id={}", file_id);
                 Some(
@@ -173,8 +173,12 @@ impl IncrementalSyncHandler {
         };
 
         // 8. 应用差异块
-        let local_data = storage::storage().read_file(file_id).await?;
-        let updated_data = self.sync_manager.apply_delta(&local_data, &delta_chunks)?;
+        let local_data = storage::storage().read_file_consistent(file_id).await?;
+        let updated_data = self.sync_manager.apply_delta(
+            &local_data,
+            &delta_chunks,
+            remote_signature.file_size,
+        )?;
 
         // 9. 验证哈希
         if !self
@@ -241,24 +245,14 @@ impl IncrementalSyncHandler {
         file_id: &str,
         target_signature: &FileSignature,
     ) -> Result<Vec<DeltaChunk>> {
-        // 读取源文件
+        // 读取源文件（当前版本）
         let data = storage::storage().read_file(file_id).await?;
 
-        // 计算源签名
-        let source_sig = self.sync_manager.calculate_signature(file_id, &data)?;
-
-        // 计算差异
-        let delta = match self
+        // 按目标签名的块大小生成复制块与字面块
+        let delta = self
             .sync_manager
-            .calculate_delta(&source_sig, target_signature)?
-        {
-            Some(d) => d,
-            None => return Ok(Vec::new()),
-        };
-
-        // 提取差异块
-        self.sync_manager
-            .extract_delta_chunks(&data, &delta, &source_sig)
+            .generate_delta(file_id, &data, target_signature)?;
+        Ok(delta.chunks)
     }
 }
 
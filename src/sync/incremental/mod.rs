@@ -6,5 +6,5 @@ pub mod core;
 pub mod handler;
 
 // 重新导出核心类型
-pub use core::{DeltaChunk, FileSignature, IncrementalSyncManager, SyncDelta};
+pub use core::{DeltaChunk, DeltaKind, FileSignature, IncrementalSyncManager, SyncDelta};
 pub use handler::IncrementalSyncHandler;
@@ -4,6 +4,7 @@
 pub mod crdt;
 pub mod incremental;
 pub mod node;
+pub mod reconcile;
 
 // 重新导出常用类型，保持向后兼容性
 // 这些在main.rs、webdav.rs等地方会被使用
@@ -348,6 +348,21 @@ pub struct SyncStats {
     pub error_count: u32,
 }
 
+/// 节点同步状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeSyncStatus {
+    /// 节点 ID
+    pub node_id: String,
+    /// 最后一次成功同步时间
+    pub last_sync_at: Option<NaiveDateTime>,
+    /// 待同步（补偿队列中）的文件数
+    pub pending_files: usize,
+    /// 同步滞后（秒）：最早一个待同步任务入队至今的时间，无待同步文件时为 0
+    pub lag_seconds: i64,
+    /// 节点在线且滞后未超过 3 个同步间隔
+    pub healthy: bool,
+}
+
 /// 失败补偿任务
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct CompTask {
@@ -385,6 +400,8 @@ pub struct NodeSyncCoordinator {
     fail_queue: Arc<RwLock<VecDeque<CompTask>>>,
     /// 失败补偿队列持久化路径
     fail_queue_path: std::path::PathBuf,
+    /// 各节点最后一次成功同步时间
+    peer_last_sync: Arc<RwLock<HashMap<String, NaiveDateTime>>>,
 }
 
 impl NodeSyncCoordinator {
@@ -423,6 +440,7 @@ impl NodeSyncCoordinator {
             stats: Arc::new(RwLock::new(SyncStats::default())),
             fail_queue: Arc::new(RwLock::new(VecDeque::new())),
             fail_queue_path: persist_path,
+            peer_last_sync: Arc::new(RwLock::new(HashMap::new())),
         });
 
         // 尝试加载持久化队列
@@ -930,9 +948,16 @@ impl NodeSyncCoordinator {
         }
 
         // 更新统计
+        let now = Local::now().naive_local();
+        if synced > 0 {
+            self.peer_last_sync
+                .write()
+                .await
+                .insert(node_id.to_string(), now);
+        }
         let mut stats = self.stats.write().await;
         stats.synced_files += synced;
-        stats.last_sync_time = Some(Local::now().naive_local());
+        stats.last_sync_time = Some(now);
 
         // 断开连接
         client.disconnect().await;
@@ -1001,6 +1026,11 @@ impl NodeSyncCoordinator {
             loop {
                 interval.tick().await;
 
+                if self.storage.is_in_maintenance() {
+                    debug!("维护模式，跳过自动同步");
+                    continue;
+                }
+
                 info!("开始自动同步...");
 
                 // 获取所有在线节点
@@ -1044,6 +1074,74 @@ impl NodeSyncCoordinator {
     pub async fn get_stats(&self) -> SyncStats {
         self.stats.read().await.clone()
     }
+
+    /// 获取各节点的同步状态，并更新对应的 Prometheus 指标
+    pub async fn node_sync_status(&self) -> Vec<NodeSyncStatus> {
+        let now = Local::now().naive_local();
+        let node_timeout = self.node_manager.config.node_timeout;
+        let max_lag = (self.config.read().await.sync_interval as i64).saturating_mul(3);
+
+        // 按目标节点汇总补偿队列：待同步文件数与最早入队时间
+        let mut pending: HashMap<String, (usize, NaiveDateTime)> = HashMap::new();
+        for task in self.fail_queue.read().await.iter() {
+            let entry = pending
+                .entry(task.target_node_id.clone())
+                .or_insert((0, task.created_at));
+            entry.0 += 1;
+            entry.1 = entry.1.min(task.created_at);
+        }
+
+        let last_sync = self.peer_last_sync.read().await;
+        let mut statuses: Vec<NodeSyncStatus> = self
+            .node_manager
+            .list_nodes()
+            .await
+            .into_iter()
+            .map(|node| {
+                let (pending_files, lag_seconds) = match pending.get(&node.node_id) {
+                    Some((count, oldest)) => (*count, (now - *oldest).num_seconds().max(0)),
+                    None => (0, 0),
+                };
+                NodeSyncStatus {
+                    last_sync_at: last_sync.get(&node.node_id).copied(),
+                    pending_files,
+                    lag_seconds,
+                    healthy: node.is_alive(node_timeout) && lag_seconds < max_lag,
+                    node_id: node.node_id,
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+
+        for status in &statuses {
+            crate::metrics::set_sync_peer_status(
+                &status.node_id,
+                status.lag_seconds,
+                status.pending_files as i64,
+            );
+        }
+        statuses
+    }
+
+    /// 墓碑回收的确认界限（毫秒）
+    ///
+    /// 取所有已知节点最后一次成功同步时间的最小值：存在从未同步成功的节点时返回
+    /// `i64::MIN`（暂不回收），没有其他节点时返回 `i64::MAX`。
+    pub async fn tombstone_ack_horizon(&self) -> i64 {
+        let last_sync = self.peer_last_sync.read().await;
+        self.node_manager
+            .list_nodes()
+            .await
+            .iter()
+            .map(|node| {
+                last_sync
+                    .get(&node.node_id)
+                    .and_then(|at| at.and_local_timezone(Local).single())
+                    .map_or(i64::MIN, |at| at.timestamp_millis())
+            })
+            .min()
+            .unwrap_or(i64::MAX)
+    }
 }
 
 impl CompTask {
@@ -1227,4 +1325,106 @@ mod tests {
         assert_eq!(t.file_id, "file-1");
         assert_eq!(t.last_error.as_deref(), Some("unit_test"));
     }
+
+    #[tokio::test]
+    async fn test_node_sync_status_reports_lag_for_pending_peer() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(crate::storage::StorageManager::new(
+            dir.path().to_path_buf(),
+            4 * 1024 * 1024,
+            crate::storage::IncrementalConfig::default(),
+        ));
+        storage.init().await.unwrap();
+        let syncm = SyncManager::new("node-test".to_string(), None);
+        let nm = NodeManager::new(NodeDiscoveryConfig::default(), syncm.clone());
+        for id in ["node-a", "node-b"] {
+            nm.register_node(NodeInfo::new(
+                id.to_string(),
+                "127.0.0.1:0".to_string(),
+                "1.0".to_string(),
+            ))
+            .await
+            .unwrap();
+        }
+        let coord = NodeSyncCoordinator::new(SyncConfig::default(), nm, syncm, storage);
+
+        coord
+            .enqueue_compensation("node-b", "file-1", 0, Some("unit_test".into()))
+            .await;
+        coord
+            .enqueue_compensation("node-b", "file-2", 0, Some("unit_test".into()))
+            .await;
+        {
+            // 模拟任务已积压 2 分钟，并推迟补偿执行以免被后台 worker 取走
+            let mut q = coord.fail_queue.write().await;
+            let now = Local::now().naive_local();
+            for t in q.iter_mut() {
+                t.created_at = now - chrono::TimeDelta::seconds(120);
+                t.next_at = now + chrono::TimeDelta::seconds(3600);
+            }
+        }
+
+        let statuses = coord.node_sync_status().await;
+        assert_eq!(statuses.len(), 2);
+        let a = statuses.iter().find(|s| s.node_id == "node-a").unwrap();
+        let b = statuses.iter().find(|s| s.node_id == "node-b").unwrap();
+
+        assert_eq!(a.pending_files, 0);
+        assert_eq!(a.lag_seconds, 0);
+        assert!(a.healthy);
+        assert!(a.last_sync_at.is_none());
+
+        assert_eq!(b.pending_files, 2);
+        assert!(b.lag_seconds >= 120);
+        assert!(b.lag_seconds > a.lag_seconds);
+        assert!(b.healthy);
+    }
+
+    #[tokio::test]
+    async fn test_tombstone_ack_horizon_waits_for_all_peers() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(crate::storage::StorageManager::new(
+            dir.path().to_path_buf(),
+            4 * 1024 * 1024,
+            crate::storage::IncrementalConfig::default(),
+        ));
+        storage.init().await.unwrap();
+        let syncm = SyncManager::new("node-test".to_string(), None);
+        let nm = NodeManager::new(NodeDiscoveryConfig::default(), syncm.clone());
+        let coord = NodeSyncCoordinator::new(SyncConfig::default(), nm.clone(), syncm, storage);
+
+        // 没有其他节点：不受约束
+        assert_eq!(coord.tombstone_ack_horizon().await, i64::MAX);
+
+        for id in ["node-a", "node-b"] {
+            nm.register_node(NodeInfo::new(
+                id.to_string(),
+                "127.0.0.1:0".to_string(),
+                "1.0".to_string(),
+            ))
+            .await
+            .unwrap();
+        }
+        let synced_at = Local::now().naive_local() - chrono::TimeDelta::seconds(60);
+        coord
+            .peer_last_sync
+            .write()
+            .await
+            .insert("node-a".to_string(), synced_at);
+
+        // node-b 从未同步成功：不能回收任何墓碑
+        assert_eq!(coord.tombstone_ack_horizon().await, i64::MIN);
+
+        coord
+            .peer_last_sync
+            .write()
+            .await
+            .insert("node-b".to_string(), Local::now().naive_local());
+        let expected = synced_at
+            .and_local_timezone(Local)
+            .single()
+            .unwrap()
+            .timestamp_millis();
+        assert_eq!(coord.tombstone_ack_horizon().await, expected);
+    }
 }
@@ -6,4 +6,19 @@ pub mod manager;
 pub mod service;
 
 // 重新导出核心类型
-pub use manager::{NodeInfo, NodeManager, NodeSyncCoordinator};
+pub use manager::{NodeInfo, NodeManager, NodeSyncCoordinator, NodeSyncStatus};
+
+use std::sync::{Arc, OnceLock};
+
+/// 全局跨节点同步协调器（gRPC 服务启动时注册，供 HTTP 管理接口查询）
+static NODE_SYNC: OnceLock<Arc<NodeSyncCoordinator>> = OnceLock::new();
+
+/// 注册全局同步协调器，重复注册时忽略
+pub fn init_global_node_sync(coordinator: Arc<NodeSyncCoordinator>) {
+    let _ = NODE_SYNC.set(coordinator);
+}
+
+/// 获取全局同步协调器，未注册时返回 None
+pub fn try_node_sync() -> Option<&'static Arc<NodeSyncCoordinator>> {
+    NODE_SYNC.get()
+}
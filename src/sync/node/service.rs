@@ -2,7 +2,7 @@
 #![allow(dead_code)]
 
 use crate::storage::{StorageManager, StorageManagerTrait};
-use crate::sync::crdt::SyncManager;
+use crate::sync::crdt::{ConflictSide, SyncManager};
 use crate::sync::node::{NodeManager, NodeSyncCoordinator};
 use chrono::{DateTime, Utc};
 use std::sync::Arc;
@@ -40,13 +40,13 @@ impl NodeSyncServiceImpl {
         NodeSyncServiceServer::new(self)
     }
 
-    /// 应用远程状态到本地（辅助方法）
-    async fn apply_remote_state(
-        &self,
+    /// 由远程节点发来的文件状态构造 FileSync（辅助方法）
+    fn remote_file_sync(
         file_id: &str,
         state: &FileSyncState,
         vector_clock: &silent_crdt::crdt::VectorClock,
-    ) -> Result<(), Status> {
+        source_node_id: &str,
+    ) -> crate::sync::crdt::FileSync {
         use chrono::NaiveDateTime;
         use silent_crdt::crdt::LWWRegister;
 
@@ -64,22 +64,34 @@ impl NodeSyncServiceImpl {
                     .unwrap_or_else(|_| chrono::Local::now().naive_local()),
                 modified_at: NaiveDateTime::parse_from_str(&m.modified_at, "%Y-%m-%d %H:%M:%S%.f")
                     .unwrap_or_else(|_| chrono::Local::now().naive_local()),
+                user_metadata: Default::default(),
             });
 
-        // 构造远程 FileSync 对象
+        // 构造远程 FileSync 对象（记录来源节点，冲突记录中据此标明版本来源）
         let mut deleted_reg = LWWRegister::new();
-        deleted_reg.set(state.deleted, state.timestamp, "remote");
+        deleted_reg.set(state.deleted, state.timestamp, source_node_id);
 
-        let remote_sync = crate::sync::crdt::FileSync {
+        crate::sync::crdt::FileSync {
             file_id: file_id.to_string(),
             metadata: LWWRegister {
-                value: metadata.clone(),
+                value: metadata,
                 timestamp: state.timestamp,
-                node_id: "remote".to_string(),
+                node_id: source_node_id.to_string(),
             },
             deleted: deleted_reg,
             vector_clock: vector_clock.clone(),
-        };
+        }
+    }
+
+    /// 应用远程状态到本地（辅助方法）
+    async fn apply_remote_state(
+        &self,
+        file_id: &str,
+        state: &FileSyncState,
+        vector_clock: &silent_crdt::crdt::VectorClock,
+        source_node_id: &str,
+    ) -> Result<(), Status> {
+        let remote_sync = Self::remote_file_sync(file_id, state, vector_clock, source_node_id);
 
         // 使用 handle_remote_sync 处理远程状态
         if let Err(e) = self.sync_manager.handle_remote_sync(remote_sync).await {
@@ -221,16 +233,34 @@ impl NodeSyncService for NodeSyncServiceImpl {
                             if remote_timestamp > local_timestamp {
                                 // 远程更新，应用远程状态
                                 info!("应用远程状态 (较新): {}", file_id);
-                                self.apply_remote_state(&file_id, &state, &remote_vc)
-                                    .await?;
+                                self.apply_remote_state(
+                                    &file_id,
+                                    &state,
+                                    &remote_vc,
+                                    &req.source_node_id,
+                                )
+                                .await?;
                             } else {
                                 info!("保留本地状态 (较新): {}", file_id);
+                                let remote_sync = Self::remote_file_sync(
+                                    &file_id,
+                                    &state,
+                                    &remote_vc,
+                                    &req.source_node_id,
+                                );
+                                self.sync_manager
+                                    .record_conflict(
+                                        &local_state,
+                                        &remote_sync,
+                                        ConflictSide::Local,
+                                    )
+                                    .await;
                             }
                         }
                     } else if local_vc.happens_before(&remote_vc) {
                         // 本地状态在远程之前，远程状态更新，直接应用
                         info!("应用远程状态 (happens-before): {}", file_id);
-                        self.apply_remote_state(&file_id, &state, &remote_vc)
+                        self.apply_remote_state(&file_id, &state, &remote_vc, &req.source_node_id)
                             .await?;
                     } else {
                         // 本地状态已是最新或在远程之后，无需操作
@@ -240,7 +270,7 @@ impl NodeSyncService for NodeSyncServiceImpl {
                 None => {
                     // 本地没有该文件，直接应用远程状态
                     info!("创建新文件状态: {}", file_id);
-                    self.apply_remote_state(&file_id, &state, &remote_vc)
+                    self.apply_remote_state(&file_id, &state, &remote_vc, &req.source_node_id)
                         .await?;
                 }
             }
@@ -296,6 +326,31 @@ impl NodeSyncService for NodeSyncServiceImpl {
         }))
     }
 
+    /// 获取各节点同步状态
+    async fn get_node_sync_status(
+        &self,
+        _request: Request<GetNodeSyncStatusRequest>,
+    ) -> Result<Response<GetNodeSyncStatusResponse>, Status> {
+        let nodes = self
+            .sync_coordinator
+            .node_sync_status()
+            .await
+            .into_iter()
+            .map(|s| NodeSyncStatus {
+                node_id: s.node_id,
+                last_sync_at: s
+                    .last_sync_at
+                    .map(|t| t.and_utc().timestamp_millis())
+                    .unwrap_or(0),
+                pending_files: s.pending_files as i32,
+                lag_seconds: s.lag_seconds,
+                healthy: s.healthy,
+            })
+            .collect();
+
+        Ok(Response::new(GetNodeSyncStatusResponse { nodes }))
+    }
+
     /// 传输文件（用于小文件）
     async fn transfer_file(
         &self,
@@ -547,6 +602,7 @@ mod tests {
             hash: "h1".into(),
             created_at: chrono::Local::now().naive_local(),
             modified_at: chrono::Local::now().naive_local(),
+            user_metadata: Default::default(),
         };
         service
             .sync_manager
@@ -0,0 +1,240 @@
+//! 巡检补拉
+//!
+//! 定期比对 CRDT 同步状态与本地存储，对缺失或内容不一致的文件从最后来源节点拉取。
+//! 文件之间按 `sync.reconcile_concurrency` 并发处理；单个文件的拉取、校验与保存见
+//! [`fetch_and_store`]，保存前必须通过 SHA-256 校验。
+
+use crate::config::SyncBehaviorConfig;
+use crate::error::{NasError, Result};
+use crate::storage::StorageManager;
+use crate::sync::crdt::{FileSync, SyncManager};
+use futures_util::stream::{self, StreamExt};
+use sha2::{Digest, Sha256};
+use silent_nas_core::StorageManagerTrait;
+use tokio::time::{Duration, sleep};
+use tracing::{debug, info, warn};
+
+/// 巡检间隔
+pub const RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 构建补拉使用的 HTTP 客户端
+pub fn build_client(cfg: &SyncBehaviorConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(cfg.http_connect_timeout))
+        .timeout(Duration::from_secs(cfg.http_request_timeout))
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+/// 第 attempt 次失败后的退避时长（指数退避，±20% 抖动）
+fn backoff_delay(cfg: &SyncBehaviorConfig, attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.min(6);
+    let secs = cfg
+        .fetch_base_backoff
+        .saturating_mul(factor)
+        .min(cfg.fetch_max_backoff);
+    silent_nas_core::jittered(Duration::from_secs(secs), 0.2)
+}
+
+/// 单次拉取并校验 SHA-256
+async fn fetch_once(client: &reqwest::Client, url: &str, expected_hash: &str) -> Result<Vec<u8>> {
+    let resp = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| NasError::Transfer(format!("请求失败: {}", e)))?;
+    if !resp.status().is_success() {
+        return Err(NasError::Transfer(format!("HTTP {}", resp.status())));
+    }
+    let bytes = resp
+        .bytes()
+        .await
+        .map_err(|e| NasError::Transfer(format!("读取响应失败: {}", e)))?;
+
+    let actual = format!("{:x}", Sha256::digest(&bytes));
+    if actual != expected_hash {
+        warn!("哈希不一致 expected={} actual={}", expected_hash, actual);
+        return Err(NasError::HashMismatch);
+    }
+    Ok(bytes.to_vec())
+}
+
+/// 从源节点拉取文件，校验 SHA-256 后保存到本地
+///
+/// 拉取、校验或保存失败时按 `fetch_max_retries` 重试并退避，全部失败返回最后一次的错误。
+/// 校验不通过的内容不会写入存储。
+pub async fn fetch_and_store(
+    storage: &StorageManager,
+    client: &reqwest::Client,
+    source: &str,
+    file_id: &str,
+    expected_hash: &str,
+    cfg: &SyncBehaviorConfig,
+) -> Result<()> {
+    let url = format!("{}/api/files/{}", source.trim_end_matches('/'), file_id);
+    let mut last_err = NasError::Transfer("unknown".into());
+
+    for attempt in 0..=cfg.fetch_max_retries {
+        match fetch_once(client, &url, expected_hash).await {
+            Ok(bytes) => match storage.save_file(file_id, &bytes).await {
+                Ok(_) => return Ok(()),
+                Err(e) => last_err = NasError::Storage(format!("保存失败: {}", e)),
+            },
+            Err(e) => last_err = e,
+        }
+        if attempt < cfg.fetch_max_retries {
+            sleep(backoff_delay(cfg, attempt)).await;
+        }
+    }
+    Err(last_err)
+}
+
+/// 巡检单个文件，必要时补拉
+///
+/// 返回是否完成了补拉。
+async fn reconcile_file(
+    storage: &StorageManager,
+    sync_manager: &SyncManager,
+    client: &reqwest::Client,
+    cfg: &SyncBehaviorConfig,
+    state: FileSync,
+) -> bool {
+    let Some(meta) = state.get_metadata() else {
+        return false;
+    };
+    let up_to_date = storage
+        .content_hash(&state.file_id)
+        .await
+        .is_ok_and(|hash| hash == meta.hash);
+    if up_to_date {
+        return false;
+    }
+    let Some(source) = sync_manager.get_last_source(&state.file_id).await else {
+        return false;
+    };
+
+    match fetch_and_store(storage, client, &source, &state.file_id, &meta.hash, cfg).await {
+        Ok(()) => {
+            info!("📥 补拉已完成: {}", state.file_id);
+            true
+        }
+        Err(e) => {
+            warn!("补拉失败: {} - {}", state.file_id, e);
+            false
+        }
+    }
+}
+
+/// 执行一轮巡检补拉
+///
+/// 同时处理的文件数不超过 `reconcile_concurrency`，返回本轮补拉成功的文件数。
+/// 存储处于维护模式时跳过本轮。
+pub async fn reconcile_once(
+    storage: &StorageManager,
+    sync_manager: &SyncManager,
+    client: &reqwest::Client,
+    cfg: &SyncBehaviorConfig,
+) -> usize {
+    // 维护模式下不补拉（写入会被拒绝）
+    if storage.is_in_maintenance() {
+        debug!("维护模式，跳过巡检补拉");
+        return 0;
+    }
+    let states = sync_manager.get_all_sync_states().await;
+    stream::iter(states)
+        .map(|state| reconcile_file(storage, sync_manager, client, cfg, state))
+        .buffer_unordered(cfg.reconcile_concurrency.max(1))
+        .filter(|fetched| std::future::ready(*fetched))
+        .count()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::IncrementalConfig;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 对任意请求都返回固定内容的 HTTP 服务器，返回基址与请求计数
+    async fn spawn_mock_server(body: &'static [u8]) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(body).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+        (format!("http://{}", addr), hits)
+    }
+
+    async fn create_storage(dir: &TempDir) -> StorageManager {
+        let storage = StorageManager::new(
+            dir.path().to_path_buf(),
+            64 * 1024,
+            IncrementalConfig::default(),
+        );
+        storage.init().await.unwrap();
+        storage
+    }
+
+    fn test_config(fetch_max_retries: u32) -> SyncBehaviorConfig {
+        SyncBehaviorConfig {
+            fetch_max_retries,
+            fetch_base_backoff: 0,
+            ..SyncBehaviorConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_store_verifies_hash() {
+        let body: &'static [u8] = b"reconcile payload";
+        let (base, hits) = spawn_mock_server(body).await;
+        let dir = TempDir::new().unwrap();
+        let storage = create_storage(&dir).await;
+        let cfg = test_config(0);
+        let client = build_client(&cfg);
+
+        let expected = format!("{:x}", Sha256::digest(body));
+        fetch_and_store(&storage, &client, &base, "remote-file", &expected, &cfg)
+            .await
+            .unwrap();
+
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert_eq!(storage.read_file("remote-file").await.unwrap(), body);
+        assert_eq!(storage.content_hash("remote-file").await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_store_rejects_hash_mismatch() {
+        let (base, hits) = spawn_mock_server(b"tampered payload").await;
+        let dir = TempDir::new().unwrap();
+        let storage = create_storage(&dir).await;
+        let cfg = test_config(1);
+        let client = build_client(&cfg);
+
+        let expected = format!("{:x}", Sha256::digest(b"original payload"));
+        let err = fetch_and_store(&storage, &client, &base, "remote-file", &expected, &cfg)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, NasError::HashMismatch));
+        // 首次请求 + 1 次重试
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert!(!storage.file_exists("remote-file").await);
+    }
+}
@@ -0,0 +1,16 @@
+//! 测试辅助函数
+
+/// 生成伪随机测试数据（splitmix64 计数器序列，每个位置取结果的最高字节）
+///
+/// 内容可复现，高熵，分块后各块互不相同，不会被去重或压缩；
+/// `seed` 不同时生成的是同一序列的不同片段。
+pub(crate) fn test_data(size: usize, seed: usize) -> Vec<u8> {
+    (0..size)
+        .map(|i| {
+            let mut z = ((i + seed) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            ((z ^ (z >> 31)) >> 56) as u8
+        })
+        .collect()
+}
@@ -17,6 +17,9 @@ pub const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>";
 pub const XML_NS_DAV: &str = "<D:multistatus xmlns:D=\"DAV:\">";
 pub const XML_MULTISTATUS_END: &str = "</D:multistatus>";
 
+// HEAD 响应中的文件版本数量
+pub const VERSION_COUNT_HEADER: &str = "x-version-count";
+
 // 按需返回 DAV 能力集合
 // 需求：OPTIONS DAV: 返回 1,2,ordered-collections
 pub const HEADER_DAV_VALUE: &str = "1, 2, ordered-collections";
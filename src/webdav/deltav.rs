@@ -10,9 +10,9 @@ impl WebDavHandler {
     /// VERSION-CONTROL - 启用版本控制（简化为标记属性）
     pub(super) async fn handle_version_control(&self, path: &str) -> silent::Result<Response> {
         let path = Self::decode_path(path)?;
-        let mut props = self.props.write().await;
-        let entry = props.entry(path).or_default();
-        entry.insert("dav:version-controlled".to_string(), "true".to_string());
+        let mut props = self.load_props(&path).await;
+        props.insert("dav:version-controlled".to_string(), "true".to_string());
+        self.save_props(&path, &props).await?;
         Ok(Response::empty())
     }
 
@@ -319,33 +319,11 @@ impl WebDavHandler {
                 let is_dir = m.is_dir();
                 // 标签过滤（结构化键）
                 if !tags.is_empty() {
-                    let mut pass = true;
-                    let key_for_props = if is_dir {
-                        href.trim_end_matches('/').to_string()
-                    } else {
-                        href.clone()
-                    };
-                    let props_map = self.props.read().await;
-                    let entry_props = props_map.get(&key_for_props);
-                    for (tk, tv) in &tags {
-                        if let Some(em) = entry_props {
-                            if let Some(val) = em.get(tk) {
-                                if let Some(expect) = tv
-                                    && val != expect
-                                {
-                                    pass = false;
-                                    break;
-                                }
-                            } else {
-                                pass = false;
-                                break;
-                            }
-                        } else {
-                            pass = false;
-                            break;
-                        }
-                    }
-                    drop(props_map);
+                    let entry_props = self.load_props(&relative_path).await;
+                    let pass = tags.iter().all(|(tk, tv)| match entry_props.get(tk) {
+                        Some(val) => tv.as_ref().is_none_or(|expect| val == expect),
+                        None => false,
+                    });
                     if !pass {
                         continue;
                     }
@@ -420,9 +398,23 @@ impl WebDavHandler {
         xml.push_str(XML_HEADER);
         xml.push_str("<D:multistatus xmlns:D=\"DAV:\">");
         for v in versions {
+            // 创建者与版本说明按 DeltaV 的 creator-displayname / comment 属性输出
+            let mut extra = String::new();
+            if let Some(author) = &v.author {
+                extra.push_str(&format!(
+                    "<D:creator-displayname>{}</D:creator-displayname>",
+                    Self::xml_escape(author)
+                ));
+            }
+            if let Some(comment) = &v.comment {
+                extra.push_str(&format!(
+                    "<D:comment>{}</D:comment>",
+                    Self::xml_escape(comment)
+                ));
+            }
             xml.push_str(&format!(
-                "<D:response><D:href>{}</D:href><D:propstat><D:prop><D:version-name>{}</D:version-name><D:version-created>{}</D:version-created></D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
-                self.build_full_href(path), v.version_id, v.created_at
+                "<D:response><D:href>{}</D:href><D:propstat><D:prop><D:version-name>{}</D:version-name><D:version-created>{}</D:version-created>{}</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+                self.build_full_href(path), v.version_id, v.created_at, extra
             ));
         }
         xml.push_str(XML_MULTISTATUS_END);
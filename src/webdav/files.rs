@@ -1,5 +1,7 @@
 use super::{WebDavHandler, constants::*};
+use crate::error::status_for;
 use crate::models::{EventType, FileEvent};
+use crate::range::RangeRequest;
 use http_body_util::BodyExt;
 use silent::prelude::*;
 use silent_nas_core::StorageManagerTrait;
@@ -197,7 +199,7 @@ impl WebDavHandler {
                     chrono::DateTime::from_timestamp(result.modified_at, 0).unwrap_or_default();
                 xml.push_str(&format!(
                     "        <D:getlastmodified>{}</D:getlastmodified>\n",
-                    dt.to_rfc2822()
+                    silent_storage::FileStat::http_date(&dt.naive_utc())
                 ));
             }
 
@@ -273,7 +275,7 @@ impl WebDavHandler {
         let is_directory = storage_path.is_dir();
 
         // 获取元数据
-        let (file_size, _modified_time) = if is_directory {
+        let (file_size, _modified_time, file_stat) = if is_directory {
             // 目录：从文件系统获取元数据
             let metadata = fs::metadata(&storage_path).await.map_err(|e| {
                 // macOS 系统文件和元数据文件不存在是正常的，只记录 debug 日志
@@ -300,16 +302,16 @@ impl WebDavHandler {
                 }
                 SilentError::business_error(StatusCode::NOT_FOUND, "路径不存在")
             })?;
-            (metadata.len(), metadata.modified().ok())
+            (metadata.len(), metadata.modified().ok(), None)
         } else {
-            // 文件：从存储引擎获取元数据（不创建副本）
-            let file_meta = storage.get_metadata(&path).await.map_err(|e| {
+            // 文件：从存储引擎的文件索引获取元数据（不读取版本记录）
+            let file_stat = storage.stat(&path).await.map_err(|e| {
                 tracing::warn!("PROPFIND 文件不存在: {} error: {}", path, e);
                 SilentError::business_error(StatusCode::NOT_FOUND, "文件不存在")
             })?;
 
             // 将 NaiveDateTime 转换为 SystemTime
-            let modified_time = file_meta
+            let modified_time = file_stat
                 .modified_at
                 .and_utc()
                 .timestamp()
@@ -319,7 +321,7 @@ impl WebDavHandler {
                     std::time::UNIX_EPOCH.checked_add(std::time::Duration::from_secs(secs))
                 });
 
-            (file_meta.size, modified_time)
+            (file_stat.size, modified_time, Some(file_stat))
         };
 
         tracing::debug!(
@@ -383,12 +385,12 @@ impl WebDavHandler {
                     for file_id in files {
                         let full_href = self.build_full_href(&file_id);
 
-                        // 从存储引擎获取文件元数据（不创建副本）
-                        if let Ok(file_meta) = storage.get_metadata(&file_id).await {
-                            self.add_prop_response_from_metadata(
+                        // 从文件索引获取文件元数据（不读取版本记录）
+                        if let Ok(stat) = storage.stat(&file_id).await {
+                            self.add_prop_response_from_stat(
                                 &mut xml,
                                 &full_href,
-                                &file_meta,
+                                &stat,
                                 props_filter.as_ref(),
                                 Some(&ns_echo_map),
                             )
@@ -397,13 +399,12 @@ impl WebDavHandler {
                     }
                 }
             }
-        } else {
+        } else if let Some(file_stat) = file_stat {
             let full_href = self.build_full_href(&path);
-            self.add_prop_response_with_filter(
+            self.add_prop_response_from_stat(
                 &mut xml,
                 &full_href,
-                &storage_path,
-                false,
+                &file_stat,
                 props_filter.as_ref(),
                 Some(&ns_echo_map),
             )
@@ -553,48 +554,22 @@ impl WebDavHandler {
                 ));
             }
         }
-        // 扩展属性（自定义属性）：仅输出结构化键 ns:{URI}#{local}
-        let key_for_props = if is_dir {
-            href.trim_end_matches('/').to_string()
-        } else {
-            href.to_string()
-        };
-        if let Some(map) = self.props.read().await.get(&key_for_props) {
-            for (k, v) in map {
-                if let Some(rest) = k.strip_prefix("ns:")
-                    && let Some((uri, local)) = rest.split_once('#')
-                {
-                    let val = v.as_str();
-                    let esc = WebDavHandler::xml_escape(val);
-                    // 选择回显前缀：客户端声明的优先；避免使用 D/d
-                    let mut pfx = ns_echo
-                        .and_then(|m| m.get(uri).cloned())
-                        .unwrap_or_else(|| "x".to_string());
-                    if pfx.eq_ignore_ascii_case("d") || pfx.is_empty() {
-                        pfx = "x".to_string();
-                    }
-                    xml.push_str(&format!(
-                        "<{p}:{local} xmlns:{p}=\"{uri}\">{esc}</{p}:{local}>",
-                        p = pfx,
-                        local = local,
-                        uri = uri,
-                        esc = esc
-                    ));
-                }
-            }
-        }
+        self.push_dead_props(xml, href, ns_echo).await;
         xml.push_str("</D:prop>");
         xml.push_str("<D:status>HTTP/1.1 200 OK</D:status>");
         xml.push_str("</D:propstat>");
         xml.push_str("</D:response>");
     }
 
-    /// 从存储引擎元数据添加属性响应（不需要文件系统副本）
-    pub(super) async fn add_prop_response_from_metadata(
+    /// 从存储引擎的文件状态添加属性响应（不需要文件系统副本）
+    ///
+    /// 除标准属性外输出 `silent:version-count`（命名空间 `urn:silent-webdav`），
+    /// 别名另外输出 `silent:alias-target`。
+    pub(super) async fn add_prop_response_from_stat(
         &self,
         xml: &mut String,
         href: &str,
-        file_meta: &silent_nas_core::FileMetadata,
+        file_stat: &silent_storage::FileStat,
         props_filter: Option<&std::collections::HashSet<String>>,
         ns_echo: Option<&std::collections::HashMap<String, String>>, // uri -> preferred prefix
     ) {
@@ -626,66 +601,72 @@ impl WebDavHandler {
         if props_filter.is_none() || props_filter.unwrap().contains("getcontentlength") {
             xml.push_str(&format!(
                 "<D:getcontentlength>{}</D:getcontentlength>",
-                file_meta.size
+                file_stat.size
             ));
         }
 
-        // getetag - 使用文件哈希和大小
-        if props_filter.is_none() || props_filter.unwrap().contains("getetag") {
-            let etag = format!(
-                "\"{}-{}\"",
-                file_meta.size,
-                file_meta.hash.chars().take(8).collect::<String>()
-            );
+        // getetag - 内容哈希（与 GET/HEAD 返回的 ETag 一致）
+        if (props_filter.is_none() || props_filter.unwrap().contains("getetag"))
+            && let Ok(etag) = crate::storage::storage()
+                .entity_tag(&file_stat.file_id)
+                .await
+        {
             xml.push_str(&format!("<D:getetag>{}</D:getetag>", etag));
         }
 
         // getlastmodified
         if props_filter.is_none() || props_filter.unwrap().contains("getlastmodified") {
-            let timestamp = file_meta.modified_at.and_utc().timestamp();
-            if let Some(dt) = chrono::DateTime::from_timestamp(timestamp, 0) {
-                let rfc_time = dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
-                xml.push_str(&format!(
-                    "<D:getlastmodified>{}</D:getlastmodified>",
-                    rfc_time
-                ));
-            }
+            xml.push_str(&format!(
+                "<D:getlastmodified>{}</D:getlastmodified>",
+                file_stat.last_modified_http()
+            ));
         }
 
         // creationdate
         if props_filter.is_none() || props_filter.unwrap().contains("creationdate") {
-            let timestamp = file_meta.created_at.and_utc().timestamp();
-            if let Some(dt) = chrono::DateTime::from_timestamp(timestamp, 0) {
-                let iso_time = dt.format("%Y-%m-%dT%H:%M:%SZ").to_string();
-                xml.push_str(&format!("<D:creationdate>{}</D:creationdate>", iso_time));
-            }
+            let iso_time = file_stat
+                .created_at
+                .and_utc()
+                .format("%Y-%m-%dT%H:%M:%SZ")
+                .to_string();
+            xml.push_str(&format!("<D:creationdate>{}</D:creationdate>", iso_time));
+        }
+
+        // version-count - 版本数量
+        if props_filter.is_none() || props_filter.unwrap().contains("version-count") {
+            xml.push_str(&format!(
+                "<silent:version-count xmlns:silent=\"urn:silent-webdav\">{}</silent:version-count>",
+                file_stat.version_count
+            ));
+        }
+
+        // alias-target - 别名指向的文件
+        if let Some(target) = &file_stat.alias_target
+            && (props_filter.is_none() || props_filter.unwrap().contains("alias-target"))
+        {
+            xml.push_str(&format!(
+                "<silent:alias-target xmlns:silent=\"urn:silent-webdav\">{}</silent:alias-target>",
+                Self::xml_escape(target)
+            ));
         }
 
         // getcontenttype - 根据文件名推测
         if props_filter.is_none() || props_filter.unwrap().contains("getcontenttype") {
-            let content_type = if let Some(ext) = std::path::Path::new(&file_meta.name).extension()
-            {
-                mime_guess::from_ext(&ext.to_string_lossy())
-                    .first_or_octet_stream()
-                    .to_string()
-            } else {
-                "application/octet-stream".to_string()
-            };
+            let content_type =
+                if let Some(ext) = std::path::Path::new(&file_stat.file_id).extension() {
+                    mime_guess::from_ext(&ext.to_string_lossy())
+                        .first_or_octet_stream()
+                        .to_string()
+                } else {
+                    "application/octet-stream".to_string()
+                };
             xml.push_str(&format!(
                 "<D:getcontenttype>{}</D:getcontenttype>",
                 content_type
             ));
         }
 
-        // 自定义属性（如果客户端请求）
-        if let Some(ns_map) = ns_echo {
-            for uri in ns_map.keys() {
-                // 处理可能的自定义命名空间属性
-                if uri.contains("apple") || uri.contains("ical") {
-                    // 可以在这里添加对特定命名空间的支持
-                }
-            }
-        }
+        self.push_dead_props(xml, href, ns_echo).await;
 
         xml.push_str("</D:prop>");
         xml.push_str("<D:status>HTTP/1.1 200 OK</D:status>");
@@ -693,6 +674,37 @@ impl WebDavHandler {
         xml.push_str("</D:response>");
     }
 
+    /// 输出资源的死属性：仅输出结构化键 ns:{URI}#{local}
+    async fn push_dead_props(
+        &self,
+        xml: &mut String,
+        href: &str,
+        ns_echo: Option<&std::collections::HashMap<String, String>>, // uri -> preferred prefix
+    ) {
+        let path = href.strip_prefix(&self.base_path).unwrap_or(href);
+        for (k, v) in self.load_props(path).await {
+            if let Some(rest) = k.strip_prefix("ns:")
+                && let Some((uri, local)) = rest.split_once('#')
+            {
+                let esc = WebDavHandler::xml_escape(&v);
+                // 选择回显前缀：客户端声明的优先；避免使用 D/d
+                let mut pfx = ns_echo
+                    .and_then(|m| m.get(uri).cloned())
+                    .unwrap_or_else(|| "x".to_string());
+                if pfx.eq_ignore_ascii_case("d") || pfx.is_empty() {
+                    pfx = "x".to_string();
+                }
+                xml.push_str(&format!(
+                    "<{p}:{local} xmlns:{p}=\"{uri}\">{esc}</{p}:{local}>",
+                    p = pfx,
+                    local = local,
+                    uri = uri,
+                    esc = esc
+                ));
+            }
+        }
+    }
+
     pub(super) fn xml_escape(s: &str) -> String {
         let mut out = String::with_capacity(s.len());
         for ch in s.chars() {
@@ -793,9 +805,9 @@ impl WebDavHandler {
             for file_id in files {
                 let full_href = self.build_full_href(&file_id);
 
-                // 从存储引擎获取文件元数据（不创建副本）
-                if let Ok(file_meta) = storage.get_metadata(&file_id).await {
-                    self.add_prop_response_from_metadata(xml, &full_href, &file_meta, None, None)
+                // 从文件索引获取文件元数据（不读取版本记录）
+                if let Ok(stat) = storage.stat(&file_id).await {
+                    self.add_prop_response_from_stat(xml, &full_href, &stat, None, None)
                         .await;
                 }
             }
@@ -818,9 +830,9 @@ impl WebDavHandler {
                 http::HeaderValue::from_static(CONTENT_TYPE_HTML),
             );
         } else {
-            // 文件：从存储引擎获取元数据（不创建副本）
-            let file_meta = storage
-                .get_metadata(&path)
+            // 文件：从文件索引获取元数据（不读取版本记录）
+            let file_stat = storage
+                .stat(&path)
                 .await
                 .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "文件不存在"))?;
 
@@ -831,16 +843,21 @@ impl WebDavHandler {
             // 设置 Content-Length
             resp.headers_mut().insert(
                 http::header::CONTENT_LENGTH,
-                http::HeaderValue::from_str(&file_meta.size.to_string()).unwrap(),
+                http::HeaderValue::from_str(&file_stat.size.to_string()).unwrap(),
             );
             // 声明支持范围请求
             resp.headers_mut().insert(
                 http::header::ACCEPT_RANGES,
                 http::HeaderValue::from_static("bytes"),
             );
+            // 版本数量
+            resp.headers_mut().insert(
+                VERSION_COUNT_HEADER,
+                http::HeaderValue::from(file_stat.version_count),
+            );
 
             // 根据文件名推测 MIME 类型
-            if let Some(ext) = std::path::Path::new(&file_meta.name).extension() {
+            if let Some(ext) = std::path::Path::new(&file_stat.file_id).extension() {
                 let mime = mime_guess::from_ext(&ext.to_string_lossy()).first_or_octet_stream();
                 resp.headers_mut().insert(
                     http::header::CONTENT_TYPE,
@@ -851,41 +868,26 @@ impl WebDavHandler {
             }
 
             // 生成并设置 ETag
-            let etag = format!(
-                "\"{}-{}\"",
-                file_meta.size,
-                file_meta.hash.chars().take(8).collect::<String>()
-            );
+            let etag = storage
+                .entity_tag(&path)
+                .await
+                .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "文件不存在"))?;
             if let Ok(val) = http::HeaderValue::from_str(&etag) {
                 resp.headers_mut().insert(http::header::ETAG, val);
             }
 
-            // 检查 If-None-Match
-            if let Some(if_none_match) = req
-                .headers()
-                .get("If-None-Match")
-                .and_then(|h| h.to_str().ok())
-            {
-                let matches = if_none_match == "*"
-                    || if_none_match
-                        .split(',')
-                        .map(|s| s.trim())
-                        .any(|t| t == etag);
-                if matches {
-                    resp.set_status(StatusCode::NOT_MODIFIED);
-                    return Ok(resp);
-                }
-            }
-
             // 设置 Last-Modified
-            let timestamp = file_meta.modified_at.and_utc().timestamp();
-            if let Some(dt) = chrono::DateTime::from_timestamp(timestamp, 0)
-                && let Ok(last_modified) =
-                    http::HeaderValue::from_str(&dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+            if let Ok(last_modified) = http::HeaderValue::from_str(&file_stat.last_modified_http())
             {
                 resp.headers_mut()
                     .insert(http::header::LAST_MODIFIED, last_modified);
             }
+
+            // 条件请求（If-None-Match / If-Modified-Since）
+            if Self::not_modified(req, &file_stat, &etag) {
+                resp.set_status(StatusCode::NOT_MODIFIED);
+                return Ok(resp);
+            }
         }
         Ok(resp)
     }
@@ -906,79 +908,62 @@ impl WebDavHandler {
             return Ok(resp);
         }
 
-        // 文件：从存储引擎获取元数据（不创建副本）
-        let file_meta = storage
-            .get_metadata(&path)
+        // 文件：从文件索引获取元数据（不读取版本记录）
+        let file_stat = storage
+            .stat(&path)
             .await
             .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "文件不存在"))?;
 
         // 生成 ETag
-        let etag = format!(
-            "\"{}-{}\"",
-            file_meta.size,
-            file_meta.hash.chars().take(8).collect::<String>()
-        );
+        let etag = storage
+            .entity_tag(&path)
+            .await
+            .map_err(|_| SilentError::business_error(StatusCode::NOT_FOUND, "文件不存在"))?;
 
-        // 检查 If-None-Match（304 Not Modified）
-        if let Some(if_none_match) = req
-            .headers()
-            .get("If-None-Match")
-            .and_then(|h| h.to_str().ok())
-        {
-            let matches = if_none_match == "*"
-                || if_none_match
-                    .split(',')
-                    .map(|s| s.trim())
-                    .any(|t| t == etag);
-            if matches {
-                let mut resp = Response::empty();
-                if let Ok(val) = http::HeaderValue::from_str(&etag) {
-                    resp.headers_mut().insert(http::header::ETAG, val);
-                }
-                // 设置 Last-Modified
-                let timestamp = file_meta.modified_at.and_utc().timestamp();
-                if let Some(dt) = chrono::DateTime::from_timestamp(timestamp, 0)
-                    && let Ok(last_modified) = http::HeaderValue::from_str(
-                        &dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
-                    )
-                {
-                    resp.headers_mut()
-                        .insert(http::header::LAST_MODIFIED, last_modified);
-                }
-                resp.set_status(StatusCode::NOT_MODIFIED);
-                return Ok(resp);
+        // 条件请求命中（304 Not Modified）
+        if Self::not_modified(req, &file_stat, &etag) {
+            let mut resp = Response::empty();
+            if let Ok(val) = http::HeaderValue::from_str(&etag) {
+                resp.headers_mut().insert(http::header::ETAG, val);
+            }
+            if let Ok(last_modified) = http::HeaderValue::from_str(&file_stat.last_modified_http())
+            {
+                resp.headers_mut()
+                    .insert(http::header::LAST_MODIFIED, last_modified);
             }
+            resp.set_status(StatusCode::NOT_MODIFIED);
+            return Ok(resp);
         }
 
-        // 从存储引擎读取文件内容（不创建副本）
-        let data = storage.read_file(&path).await.map_err(|e| {
-            SilentError::business_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("读取文件失败: {}", e),
-            )
-        })?;
+        // 解析 Range（媒体播放器拖动进度时发送）；If-Range 与当前 ETag 不符时返回完整文件
+        let if_range_matches = req
+            .headers()
+            .get(http::header::IF_RANGE)
+            .and_then(|h| h.to_str().ok())
+            .is_none_or(|v| v.trim() == etag);
+        let range_request = req
+            .headers()
+            .get(http::header::RANGE)
+            .and_then(|h| h.to_str().ok())
+            .filter(|_| if_range_matches)
+            .map(|range_str| crate::range::parse_range(range_str, file_stat.size))
+            .unwrap_or(RangeRequest::Ignored);
 
         let mut resp = Response::empty();
 
         // 设置 Content-Type
-        if let Some(ext) = std::path::Path::new(&file_meta.name).extension() {
-            let mime = mime_guess::from_ext(&ext.to_string_lossy()).first_or_octet_stream();
-            resp.headers_mut().insert(
-                http::header::CONTENT_TYPE,
-                http::HeaderValue::from_str(mime.as_ref())
-                    .unwrap_or_else(|_| http::HeaderValue::from_static("application/octet-stream")),
-            );
-        } else {
-            resp.headers_mut().insert(
-                http::header::CONTENT_TYPE,
-                http::HeaderValue::from_static("application/octet-stream"),
-            );
-        }
-
-        // 设置 Content-Length
+        let content_type = std::path::Path::new(&file_stat.file_id)
+            .extension()
+            .map(|ext| {
+                mime_guess::from_ext(&ext.to_string_lossy())
+                    .first_or_octet_stream()
+                    .to_string()
+            })
+            .unwrap_or_else(|| "application/octet-stream".to_string());
         resp.headers_mut().insert(
-            http::header::CONTENT_LENGTH,
-            http::HeaderValue::from_str(&data.len().to_string()).unwrap(),
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_str(&content_type)
+                .unwrap_or_else(|_| http::HeaderValue::from_static("application/octet-stream")),
         );
 
         // 声明支持范围请求
@@ -993,19 +978,195 @@ impl WebDavHandler {
         }
 
         // 设置 Last-Modified
-        let timestamp = file_meta.modified_at.and_utc().timestamp();
-        if let Some(dt) = chrono::DateTime::from_timestamp(timestamp, 0)
-            && let Ok(last_modified) =
-                http::HeaderValue::from_str(&dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
-        {
+        if let Ok(last_modified) = http::HeaderValue::from_str(&file_stat.last_modified_http()) {
             resp.headers_mut()
                 .insert(http::header::LAST_MODIFIED, last_modified);
         }
 
-        resp.set_body(full(data));
+        let ranges = match range_request {
+            RangeRequest::Ignored => {
+                // 从存储引擎流式读取文件内容（不创建副本）
+                let reader = storage
+                    .open_read(&file_stat.version_id)
+                    .await
+                    .map_err(|e| {
+                        SilentError::business_error(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("读取文件失败: {}", e),
+                        )
+                    })?;
+                resp.headers_mut().insert(
+                    http::header::CONTENT_LENGTH,
+                    http::HeaderValue::from(file_stat.size),
+                );
+                resp.set_body(stream_body(crate::storage::read_stream(reader)));
+                return Ok(resp);
+            }
+            RangeRequest::Unsatisfiable => {
+                resp.headers_mut().insert(
+                    http::header::CONTENT_RANGE,
+                    http::HeaderValue::from_str(&format!("bytes */{}", file_stat.size)).unwrap(),
+                );
+                resp.headers_mut()
+                    .insert(http::header::CONTENT_LENGTH, http::HeaderValue::from(0));
+                resp.set_status(StatusCode::RANGE_NOT_SATISFIABLE);
+                return Ok(resp);
+            }
+            RangeRequest::Ranges(ranges) => ranges,
+        };
+
+        // 范围读取只加载与范围重叠的块
+        let version_id = &file_stat.version_id;
+        let body = if let [(start, end)] = ranges[..] {
+            resp.headers_mut().insert(
+                http::header::CONTENT_RANGE,
+                http::HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, file_stat.size))
+                    .unwrap(),
+            );
+            Self::read_range(storage, version_id, start, end).await?
+        } else {
+            let boundary = scru128::new_string();
+            let mut body = Vec::new();
+            for (start, end) in ranges {
+                body.extend_from_slice(
+                    format!(
+                        "--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                        boundary, content_type, start, end, file_stat.size
+                    )
+                    .as_bytes(),
+                );
+                body.extend(Self::read_range(storage, version_id, start, end).await?);
+                body.extend_from_slice(b"\r\n");
+            }
+            body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+            resp.headers_mut().insert(
+                http::header::CONTENT_TYPE,
+                http::HeaderValue::from_str(&format!(
+                    "multipart/byteranges; boundary={}",
+                    boundary
+                ))
+                .unwrap(),
+            );
+            body
+        };
+
+        resp.headers_mut().insert(
+            http::header::CONTENT_LENGTH,
+            http::HeaderValue::from(body.len()),
+        );
+        resp.set_body(full(body));
+        resp.set_status(StatusCode::PARTIAL_CONTENT);
         Ok(resp)
     }
 
+    /// 条件 GET/HEAD 是否命中（If-None-Match 优先，其次 If-Modified-Since）
+    fn not_modified(req: &Request, stat: &silent_storage::FileStat, etag: &str) -> bool {
+        let header = |name: http::HeaderName| req.headers().get(name).and_then(|h| h.to_str().ok());
+        stat.not_modified(
+            etag,
+            header(http::header::IF_NONE_MATCH),
+            header(http::header::IF_MODIFIED_SINCE),
+        )
+    }
+
+    /// 读取版本的闭区间范围 `[start, end]`
+    async fn read_range(
+        storage: &crate::storage::StorageManager,
+        version_id: &str,
+        start: u64,
+        end: u64,
+    ) -> silent::Result<Vec<u8>> {
+        storage
+            .read_version_range(version_id, start, end - start + 1)
+            .await
+            .map_err(|e| {
+                SilentError::business_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("读取文件失败: {}", e),
+                )
+            })
+    }
+
+    /// 按请求头尝试秒传
+    ///
+    /// 返回 `Ok(None)` 表示未携带秒传参数或未命中且带有请求体，调用方继续普通上传。
+    pub(super) async fn try_instant_upload(
+        &self,
+        path: &str,
+        req: &Request,
+        file_exists: bool,
+    ) -> silent::Result<Option<Response>> {
+        let Some((hash, size)) = crate::storage::instant_upload_request(req.headers()) else {
+            return Ok(None);
+        };
+
+        let metadata = crate::storage::storage()
+            .save_file_by_hash(path, &hash, size)
+            .await
+            .map_err(|e| SilentError::business_error(status_for(&e), format!("秒传失败: {}", e)))?;
+
+        let Some(metadata) = metadata else {
+            let body_empty = req
+                .headers()
+                .get(http::header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|s| s.trim().parse::<u64>().ok())
+                .is_some_and(|len| len == 0);
+            if !body_empty || size == 0 {
+                return Ok(None);
+            }
+            tracing::debug!("秒传未命中: path='{}' hash={}", path, &hash[..8]);
+            let mut resp = Response::empty();
+            resp.set_status(StatusCode::PRECONDITION_FAILED);
+            resp.headers_mut().insert(
+                crate::storage::INSTANT_UPLOAD_RESULT_HEADER,
+                http::HeaderValue::from_static("false"),
+            );
+            return Ok(Some(resp));
+        };
+
+        tracing::info!(
+            "秒传成功: path='{}' size={} hash={}",
+            path,
+            size,
+            &hash[..8]
+        );
+
+        let event_type = if file_exists {
+            EventType::Modified
+        } else {
+            EventType::Created
+        };
+        let mut event = FileEvent::new(event_type, metadata.id.clone(), Some(metadata));
+        event.source_http_addr = Some(self.source_http_addr.clone());
+
+        if let Some(ref n) = self.notifier {
+            if file_exists {
+                let _ = n.notify_modified(event).await;
+            } else {
+                let _ = n.notify_created(event).await;
+            }
+        }
+
+        if file_exists {
+            self.append_change("modified", path);
+        } else {
+            self.append_change("created", path);
+        }
+
+        let mut resp = Response::empty();
+        resp.set_status(if file_exists {
+            StatusCode::NO_CONTENT
+        } else {
+            StatusCode::CREATED
+        });
+        resp.headers_mut().insert(
+            crate::storage::INSTANT_UPLOAD_RESULT_HEADER,
+            http::HeaderValue::from_static("true"),
+        );
+        Ok(Some(resp))
+    }
+
     pub(super) async fn handle_put(
         &self,
         path: &str,
@@ -1034,6 +1195,11 @@ impl WebDavHandler {
             req.headers().get("User-Agent")
         );
 
+        // 秒传：已有相同内容时不读取请求体
+        if let Some(resp) = self.try_instant_upload(&path, req, file_exists).await? {
+            return Ok(resp);
+        }
+
         let body = req.take_body();
 
         let receive_start = std::time::Instant::now();
@@ -1116,10 +1282,7 @@ impl WebDavHandler {
                             save_start.elapsed().as_secs_f64(),
                             e
                         );
-                        SilentError::business_error(
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("写入文件失败: {}", e),
-                        )
+                        SilentError::business_error(status_for(&e), format!("写入文件失败: {}", e))
                     })?;
 
                 tracing::info!(
@@ -1199,10 +1362,7 @@ impl WebDavHandler {
                             save_start.elapsed().as_secs_f64(),
                             e
                         );
-                        SilentError::business_error(
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("写入文件失败: {}", e),
-                        )
+                        SilentError::business_error(status_for(&e), format!("写入文件失败: {}", e))
                     })?;
 
                 tracing::info!(
@@ -1260,8 +1420,19 @@ impl WebDavHandler {
         }
     }
 
-    pub(super) async fn handle_delete(&self, path: &str) -> silent::Result<Response> {
+    pub(super) async fn handle_delete(
+        &self,
+        path: &str,
+        req: &Request,
+    ) -> silent::Result<Response> {
         let path = Self::decode_path(path)?;
+        let actor = crate::auth::delete_actor(req);
+        let mode = self
+            .delete_policy
+            .resolve(req.headers(), crate::storage::DeleteMode::Soft, &actor)
+            .map_err(|rejection| {
+                SilentError::business_error(rejection.status(), rejection.to_string())
+            })?;
 
         tracing::debug!(
             "DELETE path='{}' user-agent={:?}",
@@ -1305,16 +1476,20 @@ impl WebDavHandler {
                 )
             })?;
         } else {
-            // 删除文件（从存储引擎）
-            storage.delete_file(&path).await.map_err(|e| {
-                SilentError::business_error(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("删除文件失败: {}", e),
-                )
-            })?;
+            // 删除文件（从存储引擎，按删除语义移入回收站或永久删除）
+            crate::storage::delete_with_mode(storage, &self.delete_policy, &path, mode, &actor)
+                .await
+                .map_err(|e| {
+                    let status = match e {
+                        silent_storage::StorageError::RetentionLocked(_) => StatusCode::FORBIDDEN,
+                        _ => StatusCode::INTERNAL_SERVER_ERROR,
+                    };
+                    SilentError::business_error(status, format!("删除文件失败: {}", e))
+                })?;
         }
 
-        tracing::debug!("DELETE completed: path='{}'", path);
+        self.remove_props(&path).await;
+        tracing::debug!("DELETE completed: path='{}', mode={}", path, mode.as_str());
 
         let file_id = scru128::new_string();
         let mut event = FileEvent::new(EventType::Deleted, file_id, None);
@@ -1334,6 +1509,10 @@ impl WebDavHandler {
         // 记录删除
         self.append_change("deleted", &path);
         let mut resp = Response::empty();
+        resp.headers_mut().insert(
+            crate::storage::DELETE_MODE_HEADER,
+            http::HeaderValue::from_static(mode.as_str()),
+        );
         resp.set_status(StatusCode::NO_CONTENT);
         Ok(resp)
     }
@@ -1379,7 +1558,25 @@ impl WebDavHandler {
         let is_directory = storage_path.is_dir();
 
         if is_directory {
-            // 目录：使用文件系统移动
+            // 目录：先在存储引擎中整体移动目录下的文件（失败时自动回滚），
+            // 再移动文件系统中的目录
+            match storage.move_prefix(&path, &dest_path).await {
+                Ok(moved) => {
+                    tracing::info!(
+                        "目录移动: {} -> {}（{} 个文件）",
+                        path,
+                        dest_path,
+                        moved.len()
+                    );
+                }
+                Err(silent_storage::StorageError::FileNotFound(_)) => {}
+                Err(e) => {
+                    return Err(SilentError::business_error(
+                        StatusCode::CONFLICT,
+                        format!("移动目录失败: {}", e),
+                    ));
+                }
+            }
             if let Some(parent) = dest_storage_path.parent() {
                 fs::create_dir_all(parent).await.map_err(|e| {
                     SilentError::business_error(
@@ -1402,14 +1599,16 @@ impl WebDavHandler {
 
             storage.move_file(&path, &dest_path).await.map_err(|e| {
                 tracing::error!("移动文件失败: {} -> {}, error: {}", path, dest_path, e);
-                SilentError::business_error(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("移动文件失败: {}", e),
-                )
+                let status = match e {
+                    silent_storage::StorageError::RetentionLocked(_) => StatusCode::FORBIDDEN,
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+                };
+                SilentError::business_error(status, format!("移动文件失败: {}", e))
             })?;
 
             tracing::info!("文件移动成功: {} -> {}", path, dest_path);
         }
+        self.relocate_props(&path, &dest_path, false).await;
         // 记录为移动 from->to，供 REPORT 增量同步输出
         self.append_move(&path, &dest_path);
         // 发布事件
@@ -1484,6 +1683,7 @@ impl WebDavHandler {
                 )
             })?;
         }
+        self.relocate_props(&path, &dest_path, true).await;
         // 记录创建
         self.append_change("created", &dest_path);
         let mut resp = Response::empty();
@@ -1604,6 +1804,142 @@ mod tests {
         assert!(detag.starts_with('\"') && detag.ends_with('\"'));
     }
 
+    #[tokio::test]
+    async fn test_head_reflects_new_version() {
+        use silent::prelude::ReqBody;
+
+        let (handler, _temp_dir) = build_handler_with_独立storage().await;
+        let put = |body: &'static str| {
+            let (parts, _) = http::Request::builder()
+                .method("PUT")
+                .uri("/head-versions.txt")
+                .body(())
+                .unwrap()
+                .into_parts();
+            Request::from_parts(parts, ReqBody::Once(bytes::Bytes::from(body)))
+        };
+        let header = |resp: &Response, name: &str| {
+            resp.headers()
+                .get(name)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        handler
+            .handle_put("/head-versions.txt", &mut put("v1"))
+            .await
+            .unwrap();
+        // 将修改时间调早，保证新版本的 Last-Modified 不同
+        let old_time = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        crate::storage::storage()
+            .touch("/head-versions.txt", old_time)
+            .await
+            .unwrap();
+        let before = handler
+            .handle_head("/head-versions.txt", &Request::empty())
+            .await
+            .unwrap();
+        assert_eq!(header(&before, "content-length"), "2");
+        assert_eq!(
+            header(&before, "last-modified"),
+            "Wed, 01 May 2024 12:00:00 GMT"
+        );
+        assert_eq!(header(&before, VERSION_COUNT_HEADER), "1");
+
+        handler
+            .handle_put("/head-versions.txt", &mut put("version 2"))
+            .await
+            .unwrap();
+        let after = handler
+            .handle_head("/head-versions.txt", &Request::empty())
+            .await
+            .unwrap();
+        assert_eq!(header(&after, "content-length"), "9");
+        assert_ne!(
+            header(&after, "last-modified"),
+            header(&before, "last-modified")
+        );
+        assert!(header(&after, "last-modified").ends_with(" GMT"));
+        assert_eq!(header(&after, VERSION_COUNT_HEADER), "2");
+        assert_ne!(header(&after, "etag"), header(&before, "etag"));
+    }
+
+    #[tokio::test]
+    async fn test_put_instant_upload_handshake() {
+        use sha2::{Digest, Sha256};
+        use silent::prelude::ReqBody;
+
+        let (handler, _temp_dir) = build_handler_with_独立storage().await;
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i * 31 % 253) as u8).collect();
+        let hash = hex::encode(Sha256::digest(&data));
+        let handshake = |uri: &str, hash: &str| {
+            let (parts, _) = http::Request::builder()
+                .method("PUT")
+                .uri(uri)
+                .header(crate::storage::INSTANT_UPLOAD_HASH_HEADER, hash)
+                .header(crate::storage::INSTANT_UPLOAD_SIZE_HEADER, data.len())
+                .header(http::header::CONTENT_LENGTH, 0)
+                .body(())
+                .unwrap()
+                .into_parts();
+            Request::from_parts(parts, ReqBody::Empty)
+        };
+
+        // 内容未知：412，客户端改为普通上传
+        let resp = handler
+            .handle_put(
+                "/instant/copy.bin",
+                &mut handshake("/instant/copy.bin", &hash),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(
+            resp.headers()
+                .get(crate::storage::INSTANT_UPLOAD_RESULT_HEADER)
+                .unwrap(),
+            "false"
+        );
+
+        let (parts, _) = http::Request::builder()
+            .method("PUT")
+            .uri("/instant/orig.bin")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let mut put_req = Request::from_parts(parts, ReqBody::Once(data.clone().into()));
+        handler
+            .handle_put("/instant/orig.bin", &mut put_req)
+            .await
+            .unwrap();
+
+        // 内容已存在：不传输任何字节即完成上传
+        let resp = handler
+            .handle_put(
+                "/instant/copy.bin",
+                &mut handshake("/instant/copy.bin", &hash),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(
+            resp.headers()
+                .get(crate::storage::INSTANT_UPLOAD_RESULT_HEADER)
+                .unwrap(),
+            "true"
+        );
+        let copied = crate::storage::storage()
+            .read_file("/instant/copy.bin")
+            .await
+            .unwrap();
+        assert_eq!(copied, data);
+    }
+
     #[tokio::test]
     async fn test_propfind_depth_infinity_and_head_get() {
         use silent::prelude::ReqBody;
@@ -1680,6 +2016,143 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_get_conditional_not_modified() {
+        let (handler, _temp_dir) = build_handler_with_独立storage().await;
+        let path = format!("/cond/{}.txt", scru128::new_string());
+
+        let http_req = http::Request::builder()
+            .method("PUT")
+            .uri(&path)
+            .body(())
+            .unwrap();
+        let (parts, _) = http_req.into_parts();
+        let mut put_req =
+            Request::from_parts(parts, ReqBody::Once(bytes::Bytes::from_static(b"v1")));
+        handler.handle_put(&path, &mut put_req).await.unwrap();
+
+        let resp = handler.handle_get(&path, &Request::empty()).await.unwrap();
+        let etag = resp.headers()[http::header::ETAG].clone();
+        let last_modified = resp.headers()[http::header::LAST_MODIFIED].clone();
+        let conditional = |name: http::HeaderName, value: &http::HeaderValue| {
+            let mut req = Request::empty();
+            req.headers_mut().insert(name, value.clone());
+            req
+        };
+
+        let if_none_match = conditional(http::header::IF_NONE_MATCH, &etag);
+        let if_modified_since = conditional(http::header::IF_MODIFIED_SINCE, &last_modified);
+        for req in [&if_none_match, &if_modified_since] {
+            let resp = handler.handle_get(&path, req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+            let resp = handler.handle_head(&path, req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        }
+
+        // 内容与修改时间变化后返回完整内容
+        let storage = crate::storage::storage();
+        storage.save_file(&path, b"v2").await.unwrap();
+        let later = chrono::Local::now().naive_local() + chrono::Duration::minutes(1);
+        storage.touch(&path, later).await.unwrap();
+        for req in [&if_none_match, &if_modified_since] {
+            let resp = handler.handle_get(&path, req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_range_requests() {
+        let (handler, _temp_dir) = build_handler_with_独立storage().await;
+
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let http_req = http::Request::builder()
+            .method("PUT")
+            .uri("/media/clip.mp4")
+            .body(())
+            .unwrap();
+        let (parts, _) = http_req.into_parts();
+        let mut put_req =
+            Request::from_parts(parts, ReqBody::Once(bytes::Bytes::from(data.clone())));
+        handler
+            .handle_put("/media/clip.mp4", &mut put_req)
+            .await
+            .unwrap();
+
+        let get_range = |range: &'static str| {
+            let mut req = Request::empty();
+            req.headers_mut()
+                .insert(http::header::RANGE, http::HeaderValue::from_static(range));
+            req
+        };
+
+        // 中间范围
+        let mut resp = handler
+            .handle_get("/media/clip.mp4", &get_range("bytes=1000-1999"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers()[http::header::CONTENT_RANGE],
+            "bytes 1000-1999/10000"
+        );
+        assert_eq!(resp.headers()[http::header::ACCEPT_RANGES], "bytes");
+        assert_eq!(resp.headers()[http::header::CONTENT_LENGTH], "1000");
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], &data[1000..2000]);
+
+        // 后缀范围
+        let mut resp = handler
+            .handle_get("/media/clip.mp4", &get_range("bytes=-500"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers()[http::header::CONTENT_RANGE],
+            "bytes 9500-9999/10000"
+        );
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], &data[9500..]);
+
+        // 多个范围
+        let mut resp = handler
+            .handle_get("/media/clip.mp4", &get_range("bytes=0-9, 5000-5009"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = resp.headers()[http::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        let expected_part = |start: usize, end: usize| {
+            let mut part = format!(
+                "--{}\r\nContent-Type: video/mp4\r\nContent-Range: bytes {}-{}/10000\r\n\r\n",
+                boundary,
+                start,
+                end - 1
+            )
+            .into_bytes();
+            part.extend_from_slice(&data[start..end]);
+            part.extend_from_slice(b"\r\n");
+            part
+        };
+        let mut expected = expected_part(0, 10);
+        expected.extend(expected_part(5000, 5010));
+        expected.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        assert_eq!(&body[..], &expected[..]);
+
+        // 超出文件大小
+        let resp = handler
+            .handle_get("/media/clip.mp4", &get_range("bytes=20000-"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(resp.headers()[http::header::CONTENT_RANGE], "bytes */10000");
+    }
+
     #[tokio::test]
     async fn test_mkcol_move_copy() {
         let (handler, _temp_dir) = build_handler_with_独立storage().await;
@@ -1796,7 +2269,11 @@ mod tests {
         assert_eq!(err.status(), StatusCode::BAD_REQUEST);
 
         // DELETE 不存在 -> 404
-        let err2 = handler.handle_delete("/not-exist").await.err().unwrap();
+        let err2 = handler
+            .handle_delete("/not-exist", &Request::empty())
+            .await
+            .err()
+            .unwrap();
         assert_eq!(err2.status(), StatusCode::NOT_FOUND);
 
         // MOVE/COPY 缺少 Destination -> 400
@@ -1825,18 +2302,83 @@ mod tests {
         assert_eq!(e.status(), StatusCode::BAD_REQUEST);
 
         // HEAD If-None-Match 304
-        // 从存储引擎获取文件元数据并计算 ETag（与 handle_head 中的计算方式一致）
-        let storage = crate::storage::storage();
-        let file_meta = storage.get_metadata("/p0/a.txt").await.unwrap();
-        let etag = format!(
-            "\"{}-{}\"",
-            file_meta.size,
-            file_meta.hash.chars().take(8).collect::<String>()
-        );
+        let etag = crate::storage::storage()
+            .entity_tag("/p0/a.txt")
+            .await
+            .unwrap();
         let mut hreq = Request::empty();
         hreq.headers_mut()
             .insert("If-None-Match", http::HeaderValue::from_str(&etag).unwrap());
         let head_resp = handler.handle_head("/p0/a.txt", &hreq).await.unwrap();
         assert_eq!(head_resp.status(), StatusCode::NOT_MODIFIED);
     }
+
+    fn dav_request(method: &str, path: &str, destination: Option<&str>, body: &str) -> Request {
+        let mut builder = http::Request::builder().method(method).uri(path);
+        if let Some(dest) = destination {
+            builder = builder.header("Destination", dest);
+        }
+        let (parts, _) = builder.body(()).unwrap().into_parts();
+        Request::from_parts(parts, ReqBody::Once(body.as_bytes().to_vec().into()))
+    }
+
+    async fn propfind_body(handler: &WebDavHandler, path: &str) -> String {
+        let mut resp = handler
+            .call(dav_request("PROPFIND", path, None, ""))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+        let body = resp.take_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dead_properties_follow_move_copy_and_delete() {
+        let (handler, _temp_dir) = build_handler_with_独立storage().await;
+        let dir = format!("/dead-{}", scru128::new_string());
+        let src = format!("{}/a.txt", dir);
+        let moved = format!("{}/b.txt", dir);
+        let copied = format!("{}/c.txt", dir);
+        crate::storage::storage()
+            .save_at_path(&src, b"dead props")
+            .await
+            .unwrap();
+
+        let set_xml = r#"<D:propertyupdate xmlns:D="DAV:"><D:set><D:prop><Z:category xmlns:Z="urn:x-example">interop</Z:category></D:prop></D:set></D:propertyupdate>"#;
+        let resp = handler
+            .call(dav_request("PROPPATCH", &src, None, set_xml))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+
+        let expected = r#"<x:category xmlns:x="urn:x-example">interop</x:category>"#;
+        assert!(propfind_body(&handler, &src).await.contains(expected));
+
+        // MOVE：属性随资源移动
+        let resp = handler
+            .call(dav_request("MOVE", &src, Some(&moved), ""))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert!(propfind_body(&handler, &moved).await.contains(expected));
+        assert!(handler.load_props(&src).await.is_empty());
+
+        // COPY：目标获得属性副本，源保留
+        let resp = handler
+            .call(dav_request("COPY", &moved, Some(&copied), ""))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert!(propfind_body(&handler, &copied).await.contains(expected));
+        assert!(propfind_body(&handler, &moved).await.contains(expected));
+
+        // DELETE：属性随资源删除
+        let resp = handler
+            .call(dav_request("DELETE", &moved, None, ""))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(handler.load_props(&moved).await.is_empty());
+        assert!(!handler.load_props(&copied).await.is_empty());
+    }
 }
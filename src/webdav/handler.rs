@@ -39,11 +39,6 @@ pub struct WebDavHandler {
     pub source_http_addr: String,
     pub search_engine: Arc<SearchEngine>,
     pub(super) locks: Arc<tokio::sync::RwLock<std::collections::HashMap<String, Vec<DavLock>>>>,
-    pub(super) props: Arc<
-        tokio::sync::RwLock<
-            std::collections::HashMap<String, std::collections::HashMap<String, String>>,
-        >,
-    >,
     /// 上传会话管理器 (支持断点续传)
     #[allow(dead_code)]
     pub(super) upload_sessions: Arc<super::upload_session::UploadSessionManager>,
@@ -53,6 +48,8 @@ pub struct WebDavHandler {
     /// 秒传管理器 (基于哈希快速上传)
     #[allow(dead_code)]
     pub(super) instant_upload: Arc<super::instant_upload::InstantUploadManager>,
+    /// 删除请求的默认删除语义（见 `storage.default_delete`）
+    pub(super) delete_policy: crate::storage::DeletePolicy,
 }
 
 impl WebDavHandler {
@@ -77,7 +74,6 @@ impl WebDavHandler {
             source_http_addr,
             search_engine,
             locks: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
-            props: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
             upload_sessions: Arc::new(super::upload_session::UploadSessionManager::new(
                 temp_dir, 24, // 24小时过期
                 10, // 最多10个并发上传
@@ -87,11 +83,18 @@ impl WebDavHandler {
                 80,  // 80% 警告阈值
             )),
             instant_upload: Arc::new(super::instant_upload::InstantUploadManager::new()),
+            delete_policy: Default::default(),
         };
         handler.load_persistent_state();
         handler
     }
 
+    /// 设置删除请求的默认删除语义
+    pub fn with_delete_policy(mut self, policy: crate::storage::DeletePolicy) -> Self {
+        self.delete_policy = policy;
+        self
+    }
+
     pub(super) fn lock_token() -> String {
         format!("opaquelocktoken:{}", scru128::new_string())
     }
@@ -122,15 +125,24 @@ impl WebDavHandler {
                 *locks.write().await = map;
             });
         }
+        // 死属性已迁移到元数据库，导入旧版 props.json 后改名保留
         if let Ok(bytes) = std::fs::read(self.props_file())
             && let Ok(map) = serde_json::from_slice::<
                 std::collections::HashMap<String, std::collections::HashMap<String, String>>,
             >(&bytes)
         {
             let rt = tokio::runtime::Handle::current();
-            let props = self.props.clone();
+            let props_file = self.props_file();
             rt.spawn(async move {
-                *props.write().await = map;
+                let storage = crate::storage::storage();
+                for (path, props) in map {
+                    let key = Self::props_key(&path);
+                    if let Err(e) = storage.set_dead_properties(&key, &props).await {
+                        tracing::warn!("迁移死属性失败: {} - {}", key, e);
+                        return;
+                    }
+                }
+                let _ = std::fs::rename(&props_file, props_file.with_extension("json.migrated"));
             });
         }
     }
@@ -143,11 +155,67 @@ impl WebDavHandler {
         }
     }
 
-    pub(super) async fn persist_props(&self) {
-        let map = self.props.read().await.clone();
-        let _ = std::fs::create_dir_all(self.meta_dir());
-        if let Ok(bytes) = serde_json::to_vec_pretty(&map) {
-            let _ = std::fs::write(self.props_file(), bytes);
+    /// 死属性的存储键：解码后的资源路径，以 `/` 开头且不带尾斜杠
+    pub(super) fn props_key(path: &str) -> String {
+        let trimmed = path.trim_end_matches('/');
+        if trimmed.starts_with('/') {
+            trimmed.to_string()
+        } else {
+            format!("/{}", trimmed)
+        }
+    }
+
+    /// 读取资源的死属性（读取失败时视为无属性）
+    pub(super) async fn load_props(&self, path: &str) -> std::collections::HashMap<String, String> {
+        let key = Self::props_key(path);
+        crate::storage::storage()
+            .get_dead_properties(&key)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("读取死属性失败: {} - {}", key, e);
+                Default::default()
+            })
+    }
+
+    /// 保存资源的死属性
+    pub(super) async fn save_props(
+        &self,
+        path: &str,
+        props: &std::collections::HashMap<String, String>,
+    ) -> silent::Result<()> {
+        crate::storage::storage()
+            .set_dead_properties(&Self::props_key(path), props)
+            .await
+            .map_err(|e| {
+                SilentError::business_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("保存属性失败: {}", e),
+                )
+            })
+    }
+
+    /// 资源移动或复制后同步死属性（包括子资源，目标原有属性被覆盖）
+    pub(super) async fn relocate_props(&self, from: &str, to: &str, keep_source: bool) {
+        let (from, to) = (Self::props_key(from), Self::props_key(to));
+        if from == to {
+            return;
+        }
+        let storage = crate::storage::storage();
+        let result = match storage.remove_dead_properties(&to).await {
+            Ok(_) if keep_source => storage.copy_dead_properties(&from, &to).await,
+            Ok(_) => storage.move_dead_properties(&from, &to).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!("同步死属性失败: {} -> {} - {}", from, to, e);
+        }
+    }
+
+    /// 删除资源后清理其死属性（包括子资源）
+    pub(super) async fn remove_props(&self, path: &str) {
+        let key = Self::props_key(path);
+        if let Err(e) = crate::storage::storage().remove_dead_properties(&key).await {
+            tracing::warn!("删除死属性失败: {} - {}", key, e);
         }
     }
 
@@ -587,20 +655,9 @@ impl WebDavHandler {
 
     // 辅助类型定义移动到模块级（impl 内不支持定义）
 
-    fn current_etag(&self, path: &str) -> Option<String> {
-        let full = crate::storage::storage().get_full_path(path);
-        if let Ok(meta) = std::fs::metadata(full) {
-            let len = meta.len();
-            let ts = meta
-                .modified()
-                .ok()?
-                .duration_since(std::time::UNIX_EPOCH)
-                .ok()?
-                .as_secs();
-            Some(format!("\"{}-{}\"", len, ts))
-        } else {
-            None
-        }
+    /// 资源当前的 ETag（与 GET/HEAD 返回的一致），资源不存在时为 `None`
+    async fn current_etag(&self, path: &str) -> Option<String> {
+        crate::storage::storage().entity_tag(path).await.ok()
     }
 
     fn parse_if_header_full(
@@ -775,7 +832,7 @@ impl WebDavHandler {
             }
         }
         drop(locks);
-        let etag_now = self.current_etag(path);
+        let etag_now = self.current_etag(path).await;
 
         // 评估：OR(AND(terms))
         'outer: for terms in lists {
@@ -866,7 +923,7 @@ impl Handler for WebDavHandler {
             "HEAD" => self.handle_head(&relative_path, &req).await,
             "GET" => self.handle_get(&relative_path, &req).await,
             "PUT" => self.handle_put(&relative_path, &mut req).await,
-            "DELETE" => self.handle_delete(&relative_path).await,
+            "DELETE" => self.handle_delete(&relative_path, &req).await,
             "MKCOL" => self.handle_mkcol(&relative_path).await,
             "MOVE" => self.handle_move(&relative_path, &req).await,
             "COPY" => self.handle_copy(&relative_path, &req).await,
@@ -1,6 +1,19 @@
 //! WebDAV 秒传功能
 //!
-//! 通过文件哈希快速判断文件是否已存在，实现秒传
+//! 通过文件哈希快速判断文件是否已存在，实现秒传。
+//!
+//! ## 握手流程
+//!
+//! 1. 客户端计算整文件 SHA-256，发送 `PUT`，携带 `X-File-Hash` 与 `X-File-Size`
+//!    （缺省取 `Content-Length`），可附带 `Expect: 100-continue` 或 `Content-Length: 0`
+//! 2. 存储中已有相同内容（按 `[storage.incremental] instant_upload` 配置的全局或
+//!    同命名空间范围）时，直接引用已有块创建新版本，不读取请求体，
+//!    返回 201/204 并带 `X-Instant-Upload: true`
+//! 3. 未命中且请求体为空时返回 412 并带 `X-Instant-Upload: false`，客户端改为普通上传；
+//!    请求体非空时按普通上传继续处理
+//!
+//! 握手由 `WebDavHandler::try_instant_upload` 处理；下方的 [`InstantUploadManager`]
+//! 是仅存于内存的旧索引，秒传判定以存储层的内容哈希索引为准。
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -5,7 +5,7 @@ use quick_xml::events::Event;
 use silent::prelude::*;
 
 impl WebDavHandler {
-    /// PROPPATCH - 设置/移除自定义属性（简化实现）
+    /// PROPPATCH - 设置/移除自定义属性（死属性按资源路径保存在元数据库中）
     pub(super) async fn handle_proppatch(
         &self,
         path: &str,
@@ -29,6 +29,7 @@ impl WebDavHandler {
             ReqBody::Once(bytes) => bytes.to_vec(),
             ReqBody::Empty => Vec::new(),
         };
+        let mut entry = self.load_props(&path).await;
         // 解析 set/remove（简化 XML 解析）
         if !xml_bytes.is_empty() {
             let mut reader = Reader::from_reader(xml_bytes.as_slice());
@@ -133,9 +134,11 @@ impl WebDavHandler {
                 }
                 buf.clear();
             }
+            // DAV:getlastmodified 是唯一可写的 DAV: 属性，仅更新文件修改时间
+            if let Some(value) = Self::take_lastmodified(&mut updates, &mut fq_updates) {
+                self.touch_resource(&path, &value).await?;
+            }
             if !updates.is_empty() || !fq_updates.is_empty() {
-                let mut props = self.props.write().await;
-                let entry = props.entry(path.clone()).or_default();
                 // 只允许非 DAV: 命名空间的可写属性
                 let mut reject_dav = false;
                 let mut conflict = false;
@@ -221,15 +224,11 @@ impl WebDavHandler {
             }
         }
         // 记录 PROPPATCH 时间戳
-        {
-            let mut props = self.props.write().await;
-            let entry = props.entry(path.clone()).or_default();
-            entry.insert(
-                "prop:last-proppatch".to_string(),
-                chrono::Local::now().naive_local().to_string(),
-            );
-        }
-        self.persist_props().await;
+        entry.insert(
+            "prop:last-proppatch".to_string(),
+            chrono::Local::now().naive_local().to_string(),
+        );
+        self.save_props(&path, &entry).await?;
 
         // 审计：记录属性变更
         self.append_change("prop:patch", &path);
@@ -243,6 +242,59 @@ impl WebDavHandler {
         Ok(resp)
     }
 
+    /// 从待更新属性中取出 DAV:getlastmodified 的设置值
+    fn take_lastmodified(
+        updates: &mut Vec<(String, Option<String>)>,
+        fq_updates: &mut Vec<(String, Option<String>)>,
+    ) -> Option<String> {
+        let mut value = None;
+        updates.retain(|(k, v)| {
+            let is_lastmodified = matches!(k.trim(), "D:getlastmodified" | "d:getlastmodified");
+            if is_lastmodified && v.is_some() {
+                value = v.clone();
+            }
+            !(is_lastmodified && v.is_some())
+        });
+        fq_updates.retain(|(k, v)| {
+            let is_lastmodified = k.trim() == "ns:DAV:#getlastmodified";
+            if is_lastmodified && v.is_some() {
+                value = v.clone();
+            }
+            !(is_lastmodified && v.is_some())
+        });
+        value
+    }
+
+    /// 更新资源修改时间（不创建新版本）并刷新搜索索引
+    async fn touch_resource(&self, path: &str, value: &str) -> silent::Result<()> {
+        use silent_nas_core::StorageManagerTrait;
+
+        let modified_at = chrono::DateTime::parse_from_rfc2822(value.trim())
+            .map_err(|_| {
+                SilentError::business_error(
+                    StatusCode::BAD_REQUEST,
+                    format!("无效的 getlastmodified: {}", value),
+                )
+            })?
+            .naive_utc();
+
+        let storage = crate::storage::storage();
+        storage.touch(path, modified_at).await.map_err(|e| {
+            let status = match e {
+                silent_storage::StorageError::FileNotFound(_) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            SilentError::business_error(status, format!("更新修改时间失败: {}", e))
+        })?;
+
+        if let Ok(metadata) = storage.get_metadata(path).await
+            && let Err(e) = self.search_engine.index_file(&metadata).await
+        {
+            tracing::warn!("更新索引失败: {} - {}", path, e);
+        }
+        Ok(())
+    }
+
     fn validate_prop_value(key: &str, val: &str) -> silent::Result<()> {
         // 简单类型约定：local 名以 ".bool" 结尾时必须为 true/false；以 ".int" 结尾时必须为整数
         let local = if let Some(rest) = key.strip_prefix("ns:") {
@@ -317,8 +369,7 @@ mod tests {
         let mut req = make_request_with_body("PROPPATCH", path, set_xml);
         handler.handle_proppatch(path, &mut req).await.unwrap();
         {
-            let props = handler.props.read().await;
-            let entry = props.get(path).unwrap();
+            let entry = handler.load_props(path).await;
             // 记录的键为元素名（包含前缀）
             assert_eq!(entry.get("Z:category").unwrap(), "interop");
             assert!(entry.contains_key("prop:last-proppatch"));
@@ -335,11 +386,40 @@ mod tests {
         let mut req2 = make_request_with_body("PROPPATCH", path, remove_xml);
         handler.handle_proppatch(path, &mut req2).await.unwrap();
         {
-            let props = handler.props.read().await;
-            let entry = props.get(path).unwrap();
+            let entry = handler.load_props(path).await;
             assert!(!entry.contains_key("Z:category"));
             assert!(!entry.contains_key("ns:urn:x-example#category"));
             assert!(entry.contains_key("prop:last-proppatch"));
         }
     }
+
+    #[tokio::test]
+    async fn test_proppatch_getlastmodified_touches_file() {
+        let handler = build_handler().await;
+        let path = format!("/touch-{}.txt", scru128::new_string());
+        let storage = crate::storage::storage();
+        storage.save_at_path(&path, b"unchanged").await.unwrap();
+        let before = storage.get_file_info(&path).await.unwrap();
+
+        let set_xml = r#"
+<D:propertyupdate xmlns:D="DAV:">
+  <D:set><D:prop><D:getlastmodified>Wed, 01 May 2024 12:00:00 GMT</D:getlastmodified></D:prop></D:set>
+</D:propertyupdate>
+"#;
+        let mut req = make_request_with_body("PROPPATCH", &path, set_xml);
+        handler.handle_proppatch(&path, &mut req).await.unwrap();
+
+        let after = storage.get_file_info(&path).await.unwrap();
+        assert_eq!(
+            after.modified_at,
+            chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+        );
+        assert_eq!(after.version_count, before.version_count);
+        // 不作为死属性保存
+        let props = handler.load_props(&path).await;
+        assert!(!props.contains_key("D:getlastmodified"));
+    }
 }
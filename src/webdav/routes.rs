@@ -36,14 +36,18 @@ pub fn create_webdav_routes(
     sync_manager: Arc<crate::sync::crdt::SyncManager>,
     source_http_addr: String,
     search_engine: Arc<crate::search::SearchEngine>,
+    delete_policy: crate::storage::DeletePolicy,
 ) -> Route {
-    let handler = Arc::new(WebDavHandler::new(
-        notifier,
-        sync_manager,
-        "".to_string(),
-        source_http_addr,
-        search_engine,
-    ));
+    let handler = Arc::new(
+        WebDavHandler::new(
+            notifier,
+            sync_manager,
+            "".to_string(),
+            source_http_addr,
+            search_engine,
+        )
+        .with_delete_policy(delete_policy),
+    );
     let root_route = register_webdav_methods(Route::new(""), handler.clone());
     let path_route = register_webdav_methods(Route::new("<path:**>"), handler);
     root_route.append(path_route)
@@ -111,62 +111,9 @@ impl WebDavHandler {
             session_id
         );
 
-        // 1. 检查秒传
-        #[allow(clippy::collapsible_if)]
-        if let Some(ref hash) = file_hash {
-            if let Some(existing_path) = self
-                .instant_upload
-                .check_instant_upload(hash, content_length)
-                .await
-            {
-                tracing::info!(
-                    "秒传成功: path='{}' existing='{}' hash={}",
-                    path,
-                    existing_path,
-                    &hash[..8]
-                );
-
-                // 复制元数据
-                let storage = crate::storage::storage();
-                if let Ok(existing_metadata) = storage.get_metadata(&existing_path).await {
-                    // 创建链接或复制元数据
-                    let _ = storage.save_at_path(&path, &[]).await;
-
-                    // 添加到秒传索引
-                    self.instant_upload
-                        .add_entry(hash.clone(), content_length, path.clone())
-                        .await;
-
-                    // 发布事件
-                    let event_type = if file_exists {
-                        EventType::Modified
-                    } else {
-                        EventType::Created
-                    };
-                    let mut event =
-                        FileEvent::new(event_type, path.clone(), Some(existing_metadata));
-                    event.source_http_addr = Some(self.source_http_addr.clone());
-
-                    if let Some(ref n) = self.notifier {
-                        if file_exists {
-                            let _ = n.notify_modified(event).await;
-                        } else {
-                            let _ = n.notify_created(event).await;
-                        }
-                    }
-
-                    let mut resp = Response::empty();
-                    resp.set_status(if file_exists {
-                        StatusCode::NO_CONTENT
-                    } else {
-                        StatusCode::CREATED
-                    });
-                    resp.headers_mut()
-                        .insert("X-Instant-Upload", "true".parse().unwrap());
-
-                    return Ok(resp);
-                }
-            }
+        // 1. 检查秒传：存储中已有相同内容时引用已有块，不读取请求体
+        if let Some(resp) = self.try_instant_upload(&path, req, file_exists).await? {
+            return Ok(resp);
         }
 
         // 2. 内存监控